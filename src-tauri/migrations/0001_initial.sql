@@ -0,0 +1,120 @@
+-- The schema as it was before migrations. Everything is IF NOT EXISTS so databases
+-- created by earlier versions pick it up unchanged.
+
+CREATE TABLE IF NOT EXISTS conversations (
+    id TEXT PRIMARY KEY,
+    title TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS messages (
+    id TEXT PRIMARY KEY,
+    conversation_id TEXT NOT NULL REFERENCES conversations(id) ON DELETE CASCADE,
+    role TEXT NOT NULL,
+    content TEXT NOT NULL,
+    created_at INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS messages_by_conversation
+    ON messages(conversation_id, created_at);
+CREATE INDEX IF NOT EXISTS conversations_by_updated ON conversations(updated_at DESC);
+
+-- Full-text search over message content, kept in sync by triggers.
+CREATE VIRTUAL TABLE IF NOT EXISTS messages_fts USING fts5(
+    content,
+    content = 'messages',
+    content_rowid = 'rowid',
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+CREATE TRIGGER IF NOT EXISTS messages_fts_insert AFTER INSERT ON messages BEGIN
+    INSERT INTO messages_fts (rowid, content) VALUES (new.rowid, new.content);
+END;
+CREATE TRIGGER IF NOT EXISTS messages_fts_delete AFTER DELETE ON messages BEGIN
+    INSERT INTO messages_fts (messages_fts, rowid, content)
+        VALUES ('delete', old.rowid, old.content);
+END;
+CREATE TRIGGER IF NOT EXISTS messages_fts_update AFTER UPDATE OF content ON messages BEGIN
+    INSERT INTO messages_fts (messages_fts, rowid, content)
+        VALUES ('delete', old.rowid, old.content);
+    INSERT INTO messages_fts (rowid, content) VALUES (new.rowid, new.content);
+END;
+INSERT INTO messages_fts (messages_fts) VALUES ('rebuild');
+
+CREATE TABLE IF NOT EXISTS clipboard_entries (
+    id TEXT PRIMARY KEY,
+    kind TEXT NOT NULL,
+    content TEXT NOT NULL,
+    width INTEGER,
+    height INTEGER,
+    pinned INTEGER NOT NULL DEFAULT 0,
+    created_at INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS clipboard_entries_by_created
+    ON clipboard_entries(pinned, created_at DESC);
+
+CREATE TABLE IF NOT EXISTS index_sources (
+    path TEXT PRIMARY KEY,
+    created_at INTEGER NOT NULL,
+    watched INTEGER NOT NULL DEFAULT 1
+);
+CREATE TABLE IF NOT EXISTS index_files (
+    id INTEGER PRIMARY KEY,
+    source TEXT NOT NULL REFERENCES index_sources(path) ON DELETE CASCADE,
+    path TEXT NOT NULL UNIQUE,
+    modified_at INTEGER NOT NULL,
+    size INTEGER NOT NULL,
+    model TEXT NOT NULL,
+    indexed_at INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS index_chunks (
+    id INTEGER PRIMARY KEY,
+    file_id INTEGER NOT NULL REFERENCES index_files(id) ON DELETE CASCADE,
+    chunk_index INTEGER NOT NULL,
+    offset INTEGER NOT NULL,
+    text TEXT NOT NULL,
+    embedding BLOB NOT NULL
+);
+CREATE INDEX IF NOT EXISTS index_files_by_source ON index_files(source);
+CREATE INDEX IF NOT EXISTS index_chunks_by_file ON index_chunks(file_id);
+
+CREATE TABLE IF NOT EXISTS usage_events (
+    id INTEGER PRIMARY KEY,
+    request_id TEXT,
+    kind TEXT NOT NULL,
+    provider TEXT NOT NULL,
+    model TEXT NOT NULL,
+    prompt_tokens INTEGER NOT NULL,
+    completion_tokens INTEGER NOT NULL,
+    cost REAL,
+    estimated INTEGER NOT NULL,
+    created_at INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS usage_events_by_created ON usage_events(created_at);
+
+CREATE TABLE IF NOT EXISTS command_runs (
+    id TEXT PRIMARY KEY,
+    request_id TEXT,
+    command TEXT NOT NULL,
+    cwd TEXT NOT NULL,
+    status TEXT NOT NULL,
+    exit_code INTEGER,
+    stdout TEXT NOT NULL DEFAULT '',
+    stderr TEXT NOT NULL DEFAULT '',
+    started_at INTEGER NOT NULL,
+    finished_at INTEGER
+);
+CREATE INDEX IF NOT EXISTS command_runs_by_started ON command_runs(started_at);
+
+CREATE TABLE IF NOT EXISTS scheduled_jobs (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    schedule TEXT NOT NULL,
+    prompt TEXT,
+    message TEXT,
+    enabled INTEGER NOT NULL DEFAULT 1,
+    next_run_at INTEGER,
+    last_run_at INTEGER,
+    last_error TEXT,
+    created_at INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS scheduled_jobs_by_next_run
+    ON scheduled_jobs(enabled, next_run_at);
@@ -0,0 +1,12 @@
+CREATE TABLE generated_images (
+    id TEXT PRIMARY KEY,
+    prompt TEXT NOT NULL,
+    provider TEXT NOT NULL,
+    model TEXT,
+    width INTEGER NOT NULL,
+    height INTEGER NOT NULL,
+    path TEXT NOT NULL,
+    thumbnail_path TEXT NOT NULL,
+    created_at INTEGER NOT NULL
+);
+CREATE INDEX generated_images_by_created ON generated_images(created_at DESC);
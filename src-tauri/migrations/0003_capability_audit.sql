@@ -0,0 +1,7 @@
+CREATE TABLE capability_audit (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    capability TEXT NOT NULL,
+    detail TEXT NOT NULL,
+    created_at INTEGER NOT NULL
+);
+CREATE INDEX capability_audit_by_capability ON capability_audit(capability, created_at DESC);
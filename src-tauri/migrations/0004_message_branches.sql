@@ -0,0 +1,21 @@
+-- Messages form a tree: regenerating an answer or editing a message adds a sibling
+-- instead of replacing it, and the conversation remembers which leaf is shown.
+ALTER TABLE messages ADD COLUMN parent_id TEXT REFERENCES messages(id) ON DELETE CASCADE;
+ALTER TABLE conversations ADD COLUMN active_leaf_id TEXT;
+
+-- Until now every conversation was a single line of messages.
+UPDATE messages SET parent_id = (
+    SELECT p.id FROM messages p
+    WHERE p.conversation_id = messages.conversation_id
+      AND (p.created_at < messages.created_at
+           OR (p.created_at = messages.created_at AND p.rowid < messages.rowid))
+    ORDER BY p.created_at DESC, p.rowid DESC
+    LIMIT 1
+);
+UPDATE conversations SET active_leaf_id = (
+    SELECT m.id FROM messages m
+    WHERE m.conversation_id = conversations.id
+    ORDER BY m.created_at DESC, m.rowid DESC
+    LIMIT 1
+);
+CREATE INDEX messages_by_parent ON messages(parent_id);
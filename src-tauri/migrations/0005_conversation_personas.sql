@@ -0,0 +1,2 @@
+-- The persona a conversation uses, ahead of app profiles and the default one.
+ALTER TABLE conversations ADD COLUMN persona_id TEXT;
@@ -0,0 +1,13 @@
+-- Rolling summaries of the older part of long conversations, sent in place of the
+-- messages they cover. Each covers the path from the first message down to
+-- `through_message_id`, so it only applies to branches that go through it.
+CREATE TABLE conversation_summaries (
+    id TEXT PRIMARY KEY,
+    conversation_id TEXT NOT NULL REFERENCES conversations(id) ON DELETE CASCADE,
+    through_message_id TEXT NOT NULL REFERENCES messages(id) ON DELETE CASCADE,
+    content TEXT NOT NULL,
+    message_count INTEGER NOT NULL,
+    created_at INTEGER NOT NULL
+);
+CREATE INDEX conversation_summaries_by_conversation
+    ON conversation_summaries(conversation_id, created_at);
@@ -0,0 +1,11 @@
+-- How long each stage of answering took, from the hotkey to the last token, for
+-- `get_performance_report`. Kept for 30 days.
+CREATE TABLE latency_spans (
+    id INTEGER PRIMARY KEY,
+    request_id TEXT,
+    stage TEXT NOT NULL,
+    provider TEXT,
+    duration_ms INTEGER NOT NULL,
+    created_at INTEGER NOT NULL
+);
+CREATE INDEX latency_spans_by_created ON latency_spans(created_at);
@@ -0,0 +1,204 @@
+use serde::Serialize;
+use serde_json::json;
+use tauri::{Emitter, WebviewWindow};
+
+use crate::error::AppError;
+use crate::window_manager::{self, AppWindow};
+use crate::{focus, settings};
+
+#[cfg(windows)]
+mod platform {
+    use std::ffi::c_void;
+
+    use tauri::WebviewWindow;
+    use windows::core::BSTR;
+    use windows::Win32::Graphics::Dwm::{DwmSetWindowAttribute, DWMWA_TRANSITIONS_FORCEDISABLED};
+    use windows::Win32::UI::Accessibility::{
+        NotificationKind_Other, NotificationProcessing_ImportantMostRecent,
+        UiaHostProviderFromHwnd, UiaRaiseNotificationEvent,
+    };
+
+    /// Raise a UI Automation notification from the window, which Narrator and NVDA read
+    /// out whether or not it has focus.
+    pub fn announce(app: &tauri::AppHandle, message: &str) -> Result<bool, String> {
+        let Some(window) = super::window(app) else {
+            return Ok(false);
+        };
+        let hwnd = window.hwnd().map_err(|e| e.to_string())?;
+        // SAFETY: `hwnd` is our own live window, and both strings outlive the call.
+        unsafe {
+            let provider = UiaHostProviderFromHwnd(hwnd).map_err(|e| e.to_string())?;
+            UiaRaiseNotificationEvent(
+                &provider,
+                NotificationKind_Other,
+                NotificationProcessing_ImportantMostRecent,
+                &BSTR::from(message),
+                &BSTR::from("aikeya-announcement"),
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        Ok(true)
+    }
+
+    /// Turn off the fade DWM plays as the window is shown and hidden.
+    pub fn set_reduced_motion(window: &WebviewWindow, enabled: bool) -> Result<(), String> {
+        let hwnd = window.hwnd().map_err(|e| e.to_string())?;
+        let disabled = i32::from(enabled);
+        // SAFETY: the attribute takes a BOOL, which `disabled` is the size of and outlives
+        // the call.
+        unsafe {
+            DwmSetWindowAttribute(
+                hwnd,
+                DWMWA_TRANSITIONS_FORCEDISABLED,
+                std::ptr::addr_of!(disabled).cast::<c_void>(),
+                std::mem::size_of::<i32>() as u32,
+            )
+        }
+        .map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use objc2::runtime::AnyObject;
+    use objc2::MainThreadMarker;
+    use objc2_app_kit::{
+        NSAccessibilityAnnouncementKey, NSAccessibilityAnnouncementRequestedNotification,
+        NSAccessibilityPostNotificationWithUserInfo, NSAccessibilityPriorityKey,
+        NSAccessibilityPriorityLevel, NSApplication, NSWindow, NSWindowAnimationBehavior,
+    };
+    use objc2_foundation::{NSDictionary, NSNumber, NSString};
+    use tauri::WebviewWindow;
+
+    /// Ask VoiceOver to read `message` out at high priority, interrupting what it was
+    /// saying.
+    pub fn announce(app: &tauri::AppHandle, message: &str) -> Result<bool, String> {
+        let message = message.to_string();
+        app.run_on_main_thread(move || {
+            let Some(mtm) = MainThreadMarker::new() else {
+                return;
+            };
+            let application = NSApplication::sharedApplication(mtm);
+            let text = NSString::from_str(&message);
+            let priority = NSNumber::numberWithInteger(NSAccessibilityPriorityLevel::High.0);
+            // SAFETY: the keys are AppKit's own, given the value types it documents for
+            // them, and everything outlives the call.
+            unsafe {
+                let info = NSDictionary::<NSString, AnyObject>::from_slices(
+                    &[NSAccessibilityAnnouncementKey, NSAccessibilityPriorityKey],
+                    &[&text, &priority],
+                );
+                NSAccessibilityPostNotificationWithUserInfo(
+                    &application,
+                    NSAccessibilityAnnouncementRequestedNotification,
+                    Some(&info),
+                );
+            }
+        })
+        .map_err(|e| e.to_string())?;
+        Ok(true)
+    }
+
+    /// Drop the zoom AppKit animates the window in and out with.
+    pub fn set_reduced_motion(window: &WebviewWindow, enabled: bool) -> Result<(), String> {
+        let target = window.clone();
+        window
+            .run_on_main_thread(move || match target.ns_window() {
+                // SAFETY: Tauri hands out the NSWindow behind the webview window, which
+                // lives as long as `target` does, and this closure runs on the main thread.
+                Ok(ns_window) => {
+                    let ns_window = unsafe { &*ns_window.cast::<NSWindow>() };
+                    ns_window.setAnimationBehavior(if enabled {
+                        NSWindowAnimationBehavior::None
+                    } else {
+                        NSWindowAnimationBehavior::Default
+                    });
+                }
+                Err(error) => tracing::warn!("No NSWindow for {}: {error}", target.label()),
+            })
+            .map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+mod platform {
+    use tauri::WebviewWindow;
+
+    /// AT-SPI has no announcements of its own; Orca reads the webview's live region.
+    pub fn announce(_app: &tauri::AppHandle, _message: &str) -> Result<bool, String> {
+        Ok(false)
+    }
+
+    /// Window managers animate windows as they see fit, with no way to opt out.
+    pub fn set_reduced_motion(_window: &WebviewWindow, _enabled: bool) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Announcement<'a> {
+    message: &'a str,
+    /// Whether the platform's screen reader was asked to read it already, so the
+    /// overlay's live region should stay quiet rather than have it read twice.
+    native: bool,
+}
+
+fn window(app: &tauri::AppHandle) -> Option<WebviewWindow> {
+    window_manager::get(app, AppWindow::Overlay)
+}
+
+/// Have screen readers read `message` out, per `accessibility.announcements`. Also
+/// emitted as `screen-reader-announcement` for the overlay's live region.
+pub fn announce(app: &tauri::AppHandle, message: &str) {
+    if !settings::current(app).accessibility.announcements {
+        return;
+    }
+    let native = platform::announce(app, message).unwrap_or_else(|error| {
+        tracing::warn!("Could not announce to screen readers: {error}");
+        false
+    });
+    let _ = app.emit(
+        "screen-reader-announcement",
+        Announcement { message, native },
+    );
+}
+
+pub fn reduced_motion(app: &tauri::AppHandle) -> bool {
+    settings::current(app).accessibility.reduced_motion
+}
+
+/// Match a window's show and hide animations to `accessibility.reducedMotion`.
+pub fn apply_reduced_motion(app: &tauri::AppHandle, window: &WebviewWindow) {
+    if let Err(error) = platform::set_reduced_motion(window, reduced_motion(app)) {
+        tracing::warn!(
+            "Could not set reduced motion on {}: {error}",
+            window.label()
+        );
+    }
+}
+
+#[tauri::command]
+pub fn announce_to_screen_reader(app: tauri::AppHandle, message: String) {
+    announce(&app, message.trim());
+}
+
+/// Turn window animations, the overlay's slide-in included, off or back on.
+#[tauri::command]
+pub fn set_reduced_motion(app: tauri::AppHandle, enabled: bool) -> Result<(), AppError> {
+    settings::update(
+        &app,
+        &json!({ "accessibility": { "reducedMotion": enabled } }),
+    )?;
+    if let Some(window) = window(&app) {
+        apply_reduced_motion(&app, &window);
+    }
+    Ok(())
+}
+
+/// Give the keyboard back to the app the overlay was summoned over, leaving the overlay
+/// up, so focus never gets stuck in it.
+#[tauri::command]
+pub fn return_focus(app: tauri::AppHandle) -> Result<(), AppError> {
+    focus::restore(&app).map_err(AppError::from)
+}
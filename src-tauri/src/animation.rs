@@ -0,0 +1,138 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use tauri::{Manager, PhysicalPosition};
+
+use crate::error::AppError;
+use crate::window_manager::{self, AppWindow};
+use crate::{accessibility, layer_shell, overlay};
+
+const DEFAULT_DURATION_MS: u64 = 200;
+const MAX_DURATION_MS: u64 = 2_000;
+/// About 60 frames a second.
+const FRAME: Duration = Duration::from_millis(16);
+
+/// The screen edge the overlay slides in from.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    #[default]
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+#[derive(Default)]
+pub struct AnimationState {
+    /// Bumped by every new animation, so an older one still running stops.
+    generation: AtomicU64,
+    /// Where the running animation is taking the overlay.
+    target: Mutex<Option<PhysicalPosition<i32>>>,
+}
+
+fn ease_out_cubic(t: f64) -> f64 {
+    1.0 - (1.0 - t).powi(3)
+}
+
+fn lerp(from: i32, to: i32, t: f64) -> i32 {
+    from + ((to - from) as f64 * t).round() as i32
+}
+
+/// Slide the overlay in from `direction`, ending where the positioning setting puts it.
+/// Returns once it has arrived, or early when it is hidden or another slide takes over;
+/// that one carries on from wherever the overlay got to. An overlay that is already up
+/// just comes to the front.
+pub async fn slide_in(
+    app: &tauri::AppHandle,
+    direction: Direction,
+    duration: Duration,
+) -> Result<(), AppError> {
+    let window = window_manager::get_or_create(app, AppWindow::Overlay)?;
+    let state = app.state::<AnimationState>();
+    let generation = state.generation.fetch_add(1, Ordering::SeqCst) + 1;
+    let running = *state.target.lock().unwrap();
+    let visible = window.is_visible()?;
+
+    let (start, target) = match running {
+        Some(target) if visible => (window.outer_position()?, target),
+        _ if visible => return window_manager::show_overlay_in_place(app),
+        _ => {
+            overlay::place(&window)?;
+            let target = window.outer_position()?;
+            let size = window.outer_size()?;
+            let monitor = overlay::monitor_containing(app, target.x, target.y)
+                .ok_or_else(|| AppError::from("No monitor found"))?;
+            let area = monitor.work_area();
+            let start = match direction {
+                Direction::Top => {
+                    PhysicalPosition::new(target.x, area.position.y - size.height as i32)
+                }
+                Direction::Bottom => {
+                    PhysicalPosition::new(target.x, area.position.y + area.size.height as i32)
+                }
+                Direction::Left => {
+                    PhysicalPosition::new(area.position.x - size.width as i32, target.y)
+                }
+                Direction::Right => {
+                    PhysicalPosition::new(area.position.x + area.size.width as i32, target.y)
+                }
+            };
+            (start, target)
+        }
+    };
+    let scale_factor = window.scale_factor()?;
+    *state.target.lock().unwrap() = Some(target);
+    layer_shell::set_position(&window, start, scale_factor)?;
+    window_manager::show_overlay_in_place(app)?;
+
+    let started = Instant::now();
+    loop {
+        tokio::time::sleep(FRAME).await;
+        if state.generation.load(Ordering::SeqCst) != generation {
+            return Ok(());
+        }
+        let progress = (started.elapsed().as_secs_f64() / duration.as_secs_f64()).min(1.0);
+        // Hidden halfway: leave it where it belongs, so a plain show isn't off screen.
+        let hidden = !window.is_visible().unwrap_or(false);
+        let position = if hidden || progress >= 1.0 {
+            target
+        } else {
+            let eased = ease_out_cubic(progress);
+            PhysicalPosition::new(
+                lerp(start.x, target.x, eased),
+                lerp(start.y, target.y, eased),
+            )
+        };
+        layer_shell::set_position(&window, position, scale_factor)?;
+        if position == target {
+            *state.target.lock().unwrap() = None;
+            return Ok(());
+        }
+    }
+}
+
+/// Show the overlay by sliding it in from a screen edge, like a drop-down terminal.
+/// `direction` defaults to the top edge and `duration_ms` to 200; 0 shows it at once, as
+/// does `accessibility.reducedMotion`.
+#[tauri::command]
+pub async fn show_overlay_animated(
+    app: tauri::AppHandle,
+    direction: Option<Direction>,
+    duration_ms: Option<u64>,
+) -> Result<(), AppError> {
+    let duration_ms = duration_ms
+        .unwrap_or(DEFAULT_DURATION_MS)
+        .min(MAX_DURATION_MS);
+    if duration_ms == 0 || accessibility::reduced_motion(&app) {
+        return window_manager::show_overlay_nonactivating(&app, None);
+    }
+    slide_in(
+        &app,
+        direction.unwrap_or_default(),
+        Duration::from_millis(duration_ms),
+    )
+    .await
+}
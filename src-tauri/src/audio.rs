@@ -0,0 +1,265 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, SampleFormat, SizedSample};
+use serde::Serialize;
+use tauri::{Emitter, Manager};
+
+use crate::capabilities::{self, Capability};
+use crate::error::AppError;
+
+/// How often `audio-level` is emitted while recording.
+const LEVEL_INTERVAL: Duration = Duration::from_millis(50);
+/// Recordings are cut off here so a stuck push-to-talk key can't fill the disk.
+const MAX_RECORDING: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Clone, Serialize)]
+struct LevelPayload {
+    rms: f32,
+    peak: f32,
+}
+
+/// A finished recording: 16-bit mono WAV in the app cache.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Recording {
+    pub id: String,
+    pub path: PathBuf,
+    pub sample_rate: u32,
+    pub duration_ms: u64,
+}
+
+struct ActiveRecording {
+    stop: mpsc::Sender<()>,
+    worker: JoinHandle<Result<(Vec<f32>, u32), String>>,
+}
+
+/// The microphone capture in progress, if any. cpal streams aren't `Send` on every
+/// platform, so each one lives on its own thread and is stopped over a channel.
+#[derive(Default)]
+pub struct AudioState(Mutex<Option<ActiveRecording>>);
+
+fn recordings_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_cache_dir()
+        .map_err(|e| e.to_string())?
+        .join("recordings");
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir)
+}
+
+/// Where a recording returned by `stop_recording` lives on disk.
+pub fn recording_path(app: &tauri::AppHandle, id: &str) -> Result<PathBuf, String> {
+    if uuid::Uuid::parse_str(id).is_err() {
+        return Err(format!("Invalid recording id: {id}"));
+    }
+    Ok(recordings_dir(app)?.join(format!("{id}.wav")))
+}
+
+/// Collects mono samples and reports input levels as they arrive.
+struct Sink {
+    app: tauri::AppHandle,
+    samples: Arc<Mutex<Vec<f32>>>,
+    channels: usize,
+    last_level: Instant,
+    sum_squares: f32,
+    peak: f32,
+    count: usize,
+}
+
+impl Sink {
+    fn push<T>(&mut self, data: &[T])
+    where
+        T: SizedSample,
+        f32: FromSample<T>,
+    {
+        let mut samples = self.samples.lock().unwrap();
+        for frame in data.chunks(self.channels) {
+            let sample =
+                frame.iter().map(|s| s.to_sample::<f32>()).sum::<f32>() / frame.len() as f32;
+            samples.push(sample);
+            self.sum_squares += sample * sample;
+            self.peak = self.peak.max(sample.abs());
+            self.count += 1;
+        }
+        drop(samples);
+
+        if self.last_level.elapsed() >= LEVEL_INTERVAL && self.count > 0 {
+            let rms = (self.sum_squares / self.count as f32).sqrt();
+            let _ = self.app.emit(
+                "audio-level",
+                LevelPayload {
+                    rms,
+                    peak: self.peak,
+                },
+            );
+            self.last_level = Instant::now();
+            self.sum_squares = 0.0;
+            self.peak = 0.0;
+            self.count = 0;
+        }
+    }
+}
+
+fn build_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    mut sink: Sink,
+) -> Result<cpal::Stream, String>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    device
+        .build_input_stream(
+            config,
+            move |data: &[T], _| sink.push(data),
+            |error| tracing::error!("Audio input error: {error}"),
+            None,
+        )
+        .map_err(|e| e.to_string())
+}
+
+fn open_stream(
+    app: tauri::AppHandle,
+    samples: Arc<Mutex<Vec<f32>>>,
+) -> Result<(cpal::Stream, u32), String> {
+    let device = cpal::default_host()
+        .default_input_device()
+        .ok_or_else(|| "No microphone found".to_string())?;
+    let supported = device.default_input_config().map_err(|e| e.to_string())?;
+    let format = supported.sample_format();
+    let config: cpal::StreamConfig = supported.into();
+    let sink = Sink {
+        app,
+        samples,
+        channels: config.channels.max(1) as usize,
+        last_level: Instant::now(),
+        sum_squares: 0.0,
+        peak: 0.0,
+        count: 0,
+    };
+    let stream = match format {
+        SampleFormat::F32 => build_stream::<f32>(&device, &config, sink),
+        SampleFormat::I16 => build_stream::<i16>(&device, &config, sink),
+        SampleFormat::U16 => build_stream::<u16>(&device, &config, sink),
+        SampleFormat::I32 => build_stream::<i32>(&device, &config, sink),
+        other => Err(format!("Unsupported microphone sample format {other}")),
+    }?;
+    stream.play().map_err(|e| e.to_string())?;
+    Ok((stream, config.sample_rate.0))
+}
+
+/// Record from the default input device until told to stop, returning mono samples and
+/// their sample rate. `ready` reports whether the stream could be opened at all.
+fn record(
+    app: tauri::AppHandle,
+    stop: mpsc::Receiver<()>,
+    ready: mpsc::Sender<Result<(), String>>,
+) -> Result<(Vec<f32>, u32), String> {
+    let samples = Arc::new(Mutex::new(Vec::new()));
+    let opened = open_stream(app, samples.clone());
+
+    let (stream, sample_rate) = match opened {
+        Ok(opened) => {
+            let _ = ready.send(Ok(()));
+            opened
+        }
+        Err(error) => {
+            let _ = ready.send(Err(error.clone()));
+            return Err(error);
+        }
+    };
+    // Either an explicit stop or the sender being dropped ends the recording.
+    let _ = stop.recv_timeout(MAX_RECORDING);
+    drop(stream);
+    let samples = std::mem::take(&mut *samples.lock().unwrap());
+    Ok((samples, sample_rate))
+}
+
+/// Start capturing the default microphone, emitting `audio-level` as it goes.
+pub fn start(app: &tauri::AppHandle) -> Result<(), String> {
+    let state = app.state::<AudioState>();
+    let mut active = state.0.lock().unwrap();
+    if active.is_some() {
+        return Err("Already recording".to_string());
+    }
+    let (stop, stop_rx) = mpsc::channel();
+    let (ready_tx, ready) = mpsc::channel();
+    let recorder_app = app.clone();
+    let worker = thread::Builder::new()
+        .name("audio-recorder".to_string())
+        .spawn(move || record(recorder_app, stop_rx, ready_tx))
+        .map_err(|e| e.to_string())?;
+    ready
+        .recv()
+        .map_err(|_| "Audio recorder stopped unexpectedly".to_string())??;
+    *active = Some(ActiveRecording { stop, worker });
+    let _ = app.emit("recording-started", ());
+    Ok(())
+}
+
+/// Stop the current recording and save it as a WAV file.
+pub fn stop(app: &tauri::AppHandle) -> Result<Recording, String> {
+    let active = app
+        .state::<AudioState>()
+        .0
+        .lock()
+        .unwrap()
+        .take()
+        .ok_or_else(|| "Not recording".to_string())?;
+    let _ = active.stop.send(());
+    let (samples, sample_rate) = active
+        .worker
+        .join()
+        .map_err(|_| "Audio recorder panicked".to_string())??;
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let path = recording_path(app, &id)?;
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(&path, spec).map_err(|e| e.to_string())?;
+    for sample in &samples {
+        let value = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+        writer.write_sample(value).map_err(|e| e.to_string())?;
+    }
+    writer.finalize().map_err(|e| e.to_string())?;
+
+    let recording = Recording {
+        id,
+        path,
+        sample_rate,
+        duration_ms: samples.len() as u64 * 1000 / sample_rate.max(1) as u64,
+    };
+    let _ = app.emit("recording-stopped", &recording);
+    Ok(recording)
+}
+
+pub fn is_recording(app: &tauri::AppHandle) -> bool {
+    app.state::<AudioState>().0.lock().unwrap().is_some()
+}
+
+#[tauri::command]
+pub async fn start_recording(app: tauri::AppHandle) -> Result<(), AppError> {
+    capabilities::require(&app, Capability::Microphone, "Voice recording").await?;
+    tauri::async_runtime::spawn_blocking(move || start(&app))
+        .await?
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+pub async fn stop_recording(app: tauri::AppHandle) -> Result<Recording, AppError> {
+    tauri::async_runtime::spawn_blocking(move || stop(&app))
+        .await?
+        .map_err(AppError::from)
+}
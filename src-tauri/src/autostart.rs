@@ -0,0 +1,32 @@
+use tauri_plugin_autostart::ManagerExt;
+
+use crate::error::AppError;
+
+/// Passed by the login item so the app starts in the tray instead of opening the main window.
+pub const HIDDEN_FLAG: &str = "--hidden";
+
+pub fn launched_hidden() -> bool {
+    std::env::args().skip(1).any(|arg| arg == HIDDEN_FLAG)
+}
+
+#[tauri::command]
+pub fn get_autostart(app: tauri::AppHandle) -> Result<bool, AppError> {
+    app.autolaunch()
+        .is_enabled()
+        .map_err(|e| AppError::from(e.to_string()))
+}
+
+/// Register or remove the login item, returning the state the OS now reports.
+#[tauri::command]
+pub fn set_autostart(app: tauri::AppHandle, enabled: bool) -> Result<bool, AppError> {
+    let autolaunch = app.autolaunch();
+    if enabled {
+        autolaunch.enable()
+    } else {
+        autolaunch.disable()
+    }
+    .map_err(|e| e.to_string())?;
+    autolaunch
+        .is_enabled()
+        .map_err(|e| AppError::from(e.to_string()))
+}
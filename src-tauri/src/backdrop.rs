@@ -0,0 +1,107 @@
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tauri::utils::config::WindowEffectsConfig;
+use tauri::window::{Color, Effect, EffectState};
+use tauri::{Listener, WebviewWindow};
+
+use crate::error::AppError;
+use crate::settings::{self, BackdropEffect};
+use crate::theme;
+use crate::window_manager::{self, AppWindow};
+
+/// What the theme resolves to once `system` has been looked up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Appearance {
+    Light,
+    Dark,
+}
+
+/// The effects to ask for, best first. Windows and macOS each apply the first one they
+/// support and ignore the rest.
+fn effects(effect: BackdropEffect, appearance: Appearance) -> Option<WindowEffectsConfig> {
+    let dark = appearance == Appearance::Dark;
+    let vibrancy = if dark {
+        Effect::HudWindow
+    } else {
+        Effect::Popover
+    };
+    let effects = match effect {
+        BackdropEffect::None => return None,
+        BackdropEffect::Blur => vec![Effect::Blur, vibrancy],
+        BackdropEffect::Acrylic => vec![Effect::Acrylic, vibrancy],
+        BackdropEffect::Mica if dark => vec![Effect::MicaDark, vibrancy],
+        BackdropEffect::Mica => vec![Effect::MicaLight, vibrancy],
+        BackdropEffect::Vibrancy => vec![vibrancy, Effect::Acrylic],
+    };
+    Some(WindowEffectsConfig {
+        effects,
+        // The overlay is shown without activating the app, and vibrancy that follows the
+        // window's active state would look washed out there.
+        state: Some(EffectState::Active),
+        radius: None,
+        // Only Windows 10 uses the tint; without one blur and acrylic are barely visible.
+        color: Some(if dark {
+            Color(32, 32, 32, 160)
+        } else {
+            Color(243, 243, 243, 160)
+        }),
+    })
+}
+
+/// Draw the backdrop the settings pick for the current appearance behind the overlay.
+pub fn apply(app: &tauri::AppHandle, window: &WebviewWindow) -> Result<(), String> {
+    let backdrop = settings::current(app).overlay.backdrop;
+    let appearance = theme::appearance(app);
+    let effect = match appearance {
+        Appearance::Light => backdrop.light,
+        Appearance::Dark => backdrop.dark,
+    };
+    window
+        .set_effects(effects(effect, appearance))
+        .map_err(|e| e.to_string())
+}
+
+/// Apply the backdrop again if the overlay exists, after the theme or its setting changed.
+pub fn refresh(app: &tauri::AppHandle) {
+    let Some(window) = window_manager::get(app, AppWindow::Overlay) else {
+        return;
+    };
+    if let Err(error) = apply(app, &window) {
+        tracing::warn!("Could not set the overlay backdrop: {error}");
+    }
+}
+
+/// Apply the backdrop again whenever the theme or the backdrop settings change.
+pub fn init(app: &tauri::AppHandle) {
+    let settings = settings::current(app);
+    let applied = Mutex::new((settings.general.theme, settings.overlay.backdrop));
+    let handle = app.clone();
+    app.listen("settings-changed", move |_| {
+        let settings = settings::current(&handle);
+        let wanted = (settings.general.theme, settings.overlay.backdrop);
+        let mut applied = applied.lock().unwrap();
+        if *applied != wanted {
+            *applied = wanted;
+            refresh(&handle);
+        }
+    });
+}
+
+/// Set the overlay backdrop for one appearance, or for both when `appearance` is omitted.
+#[tauri::command]
+pub fn set_overlay_backdrop(
+    app: tauri::AppHandle,
+    effect: BackdropEffect,
+    appearance: Option<Appearance>,
+) -> Result<(), AppError> {
+    let backdrop = match appearance {
+        Some(Appearance::Light) => json!({ "light": effect }),
+        Some(Appearance::Dark) => json!({ "dark": effect }),
+        None => json!({ "light": effect, "dark": effect }),
+    };
+    settings::update(&app, &json!({ "overlay": { "backdrop": backdrop } }))?;
+    Ok(())
+}
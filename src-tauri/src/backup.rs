@@ -0,0 +1,362 @@
+use std::fs;
+use std::io::{Cursor, Read, Write as _};
+use std::path::{Path, PathBuf};
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use argon2::Argon2;
+use rusqlite::{Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use tauri::Manager;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::capabilities;
+use crate::db::Database;
+use crate::error::AppError;
+use crate::migrations;
+use crate::personas::{self, Persona};
+use crate::profiles::{self, AppProfile};
+use crate::settings::{self, Settings, SETTINGS_VERSION};
+use crate::state;
+use crate::templates::{self, PromptTemplate};
+
+const MAGIC: &[u8; 8] = b"AIKEYABK";
+/// Bump when the archive layout changes; older versions are still read.
+const FORMAT_VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const HEADER_LEN: usize = MAGIC.len() + 1 + SALT_LEN + NONCE_LEN;
+
+const MANIFEST_ENTRY: &str = "manifest.json";
+const SETTINGS_ENTRY: &str = "settings.json";
+const TEMPLATES_ENTRY: &str = "templates.json";
+const PROFILES_ENTRY: &str = "profiles.json";
+/// Not in backups made before personas existed.
+const PERSONAS_ENTRY: &str = "personas.json";
+const DATABASE_ENTRY: &str = "history.db";
+
+/// What a backup holds, stored inside it and returned by both commands.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupManifest {
+    pub format_version: u8,
+    pub app_version: String,
+    pub settings_version: u32,
+    pub created_at: i64,
+    pub conversations: usize,
+    pub messages: usize,
+    pub templates: usize,
+    pub profiles: usize,
+    #[serde(default)]
+    pub personas: usize,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreReport {
+    #[serde(flatten)]
+    pub manifest: BackupManifest,
+    /// False for a dry run, which only checks the backup.
+    pub restored: bool,
+}
+
+/// A backup's contents once decrypted and checked.
+struct Contents {
+    manifest: BackupManifest,
+    settings: Settings,
+    templates: Vec<PromptTemplate>,
+    profiles: Vec<AppProfile>,
+    /// `None` when the backup predates personas, which then stay as they are.
+    personas: Option<Vec<Persona>>,
+    database: Vec<u8>,
+}
+
+/// A file in the app's cache dir, readable by the user alone so the decrypted database
+/// never sits in a shared temp dir. Deleted when dropped.
+struct TempFile(PathBuf);
+
+impl TempFile {
+    fn new(app: &tauri::AppHandle) -> Result<Self, String> {
+        let dir = app.path().app_cache_dir().map_err(|e| e.to_string())?;
+        fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+        let path = dir.join(format!("backup-{}.db", uuid::Uuid::new_v4()));
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        options.open(&path).map_err(|e| e.to_string())?;
+        Ok(Self(path))
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; 32], String> {
+    let mut key = [0; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| e.to_string())?;
+    Ok(key)
+}
+
+/// `MAGIC`, the format version, salt and nonce, then the archive sealed with AES-256-GCM
+/// under a key derived from the passphrase with Argon2id. The header is authenticated too.
+fn encrypt(archive: &[u8], passphrase: &str) -> Result<Vec<u8>, String> {
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    header.extend_from_slice(MAGIC);
+    header.push(FORMAT_VERSION);
+    let mut salt = [0; SALT_LEN];
+    let mut nonce = [0; NONCE_LEN];
+    OsRng.fill_bytes(&mut salt);
+    OsRng.fill_bytes(&mut nonce);
+    header.extend_from_slice(&salt);
+    header.extend_from_slice(&nonce);
+
+    let key = derive_key(passphrase, &salt)?;
+    let cipher = Aes256Gcm::new_from_slice(&key).map_err(|e| e.to_string())?;
+    let sealed = cipher
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: archive,
+                aad: &header,
+            },
+        )
+        .map_err(|e| e.to_string())?;
+    header.extend_from_slice(&sealed);
+    Ok(header)
+}
+
+fn decrypt(data: &[u8], passphrase: &str) -> Result<Vec<u8>, AppError> {
+    if data.len() < HEADER_LEN || !data.starts_with(MAGIC) {
+        return Err(AppError::InvalidInput("Not an Aikeya backup".into()));
+    }
+    let version = data[MAGIC.len()];
+    if version > FORMAT_VERSION {
+        return Err(AppError::InvalidInput(format!(
+            "The backup is format version {version}, newer than this version of Aikeya reads"
+        )));
+    }
+    let (header, sealed) = data.split_at(HEADER_LEN);
+    let salt = &header[MAGIC.len() + 1..MAGIC.len() + 1 + SALT_LEN];
+    let nonce = &header[MAGIC.len() + 1 + SALT_LEN..];
+    let key = derive_key(passphrase, salt)?;
+    let cipher = Aes256Gcm::new_from_slice(&key).map_err(|e| AppError::from(e.to_string()))?;
+    cipher
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: sealed,
+                aad: header,
+            },
+        )
+        .map_err(|_| AppError::InvalidInput("Wrong passphrase, or the backup is damaged".into()))
+}
+
+/// Conversations and messages in a database file. Also proves it is one of ours.
+fn count_history(path: &Path) -> Result<(usize, usize), String> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| e.to_string())?;
+    let integrity: String = conn
+        .query_row("PRAGMA integrity_check", [], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    if integrity != "ok" {
+        return Err(format!("The history database is damaged: {integrity}"));
+    }
+    let schema = migrations::version(&conn).map_err(|e| e.to_string())?;
+    if schema > migrations::latest_version() {
+        return Err(format!(
+            "The history database is at schema version {schema}, newer than this version reads"
+        ));
+    }
+    let count = |table: &str| {
+        conn.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| {
+            row.get::<_, i64>(0)
+        })
+        .map(|count| count as usize)
+        .map_err(|e| format!("The history database has no {table}: {e}"))
+    };
+    Ok((count("conversations")?, count("messages")?))
+}
+
+fn build_archive(
+    manifest: &BackupManifest,
+    settings: &Settings,
+    templates: &[PromptTemplate],
+    profiles: &[AppProfile],
+    personas: &[Persona],
+    database: &[u8],
+) -> Result<Vec<u8>, String> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let entries = [
+        (MANIFEST_ENTRY, serde_json::to_vec_pretty(manifest)),
+        (SETTINGS_ENTRY, serde_json::to_vec_pretty(settings)),
+        (TEMPLATES_ENTRY, serde_json::to_vec_pretty(templates)),
+        (PROFILES_ENTRY, serde_json::to_vec_pretty(profiles)),
+        (PERSONAS_ENTRY, serde_json::to_vec_pretty(personas)),
+    ];
+    for (name, contents) in entries {
+        let contents = contents.map_err(|e| e.to_string())?;
+        zip.start_file(name, options).map_err(|e| e.to_string())?;
+        zip.write_all(&contents).map_err(|e| e.to_string())?;
+    }
+    zip.start_file(DATABASE_ENTRY, options)
+        .map_err(|e| e.to_string())?;
+    zip.write_all(database).map_err(|e| e.to_string())?;
+    Ok(zip.finish().map_err(|e| e.to_string())?.into_inner())
+}
+
+fn read_entry(archive: &mut ZipArchive<Cursor<Vec<u8>>>, name: &str) -> Result<Vec<u8>, String> {
+    let mut entry = archive
+        .by_name(name)
+        .map_err(|e| format!("The backup has no {name}: {e}"))?;
+    let mut contents = Vec::new();
+    entry
+        .read_to_end(&mut contents)
+        .map_err(|e| e.to_string())?;
+    Ok(contents)
+}
+
+/// Decrypt a backup and check every part of it, without touching anything.
+fn open(app: &tauri::AppHandle, path: &Path, passphrase: &str) -> Result<Contents, AppError> {
+    let data = fs::read(path).map_err(|e| AppError::from(e.to_string()))?;
+    let archive = decrypt(&data, passphrase)?;
+    let mut archive = ZipArchive::new(Cursor::new(archive)).map_err(|e| e.to_string())?;
+
+    let manifest: BackupManifest =
+        serde_json::from_slice(&read_entry(&mut archive, MANIFEST_ENTRY)?)
+            .map_err(|e| format!("Invalid backup manifest: {e}"))?;
+    if manifest.format_version > FORMAT_VERSION || manifest.settings_version > SETTINGS_VERSION {
+        return Err(AppError::InvalidInput(format!(
+            "The backup was made by Aikeya {}, which is newer than this version",
+            manifest.app_version
+        )));
+    }
+    let settings = String::from_utf8(read_entry(&mut archive, SETTINGS_ENTRY)?)
+        .map_err(|e| e.to_string())
+        .and_then(|contents| settings::parse(&contents))
+        .map_err(|e| AppError::InvalidInput(format!("Invalid settings in the backup: {e}")))?;
+    let templates = serde_json::from_slice(&read_entry(&mut archive, TEMPLATES_ENTRY)?)
+        .map_err(|e| AppError::InvalidInput(format!("Invalid templates in the backup: {e}")))?;
+    let profiles = serde_json::from_slice(&read_entry(&mut archive, PROFILES_ENTRY)?)
+        .map_err(|e| AppError::InvalidInput(format!("Invalid profiles in the backup: {e}")))?;
+    let personas = match archive.index_for_name(PERSONAS_ENTRY) {
+        Some(_) => Some(
+            serde_json::from_slice(&read_entry(&mut archive, PERSONAS_ENTRY)?).map_err(|e| {
+                AppError::InvalidInput(format!("Invalid personas in the backup: {e}"))
+            })?,
+        ),
+        None => None,
+    };
+    let database = read_entry(&mut archive, DATABASE_ENTRY)?;
+
+    let temp = TempFile::new(app)?;
+    fs::write(&temp.0, &database).map_err(|e| AppError::from(e.to_string()))?;
+    count_history(&temp.0).map_err(AppError::InvalidInput)?;
+    Ok(Contents {
+        manifest,
+        settings,
+        templates,
+        profiles,
+        personas,
+        database,
+    })
+}
+
+fn check_passphrase(passphrase: &str) -> Result<(), AppError> {
+    if passphrase.is_empty() {
+        return Err(AppError::InvalidInput("A passphrase is required".into()));
+    }
+    Ok(())
+}
+
+/// Write settings, templates, app profiles, personas and the history database to one
+/// encrypted file at `path`. API keys stay in the system keychain and are not included.
+#[tauri::command]
+pub async fn create_backup(
+    app: tauri::AppHandle,
+    path: PathBuf,
+    passphrase: String,
+) -> Result<BackupManifest, AppError> {
+    check_passphrase(&passphrase)?;
+    capabilities::require_path(&app, &path, "Create a backup").await?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let settings = settings::current(&app);
+        let templates = templates::list_templates(app.clone());
+        let profiles = profiles::list_app_profiles(app.clone());
+        let personas = personas::list_personas(app.clone());
+        let temp = TempFile::new(&app)?;
+        app.state::<Database>().snapshot(&temp.0)?;
+        let (conversations, messages) = count_history(&temp.0)?;
+        let database = fs::read(&temp.0).map_err(|e| e.to_string())?;
+        let manifest = BackupManifest {
+            format_version: FORMAT_VERSION,
+            app_version: app.package_info().version.to_string(),
+            settings_version: SETTINGS_VERSION,
+            created_at: crate::db::now_ms(),
+            conversations,
+            messages,
+            templates: templates.len(),
+            profiles: profiles.len(),
+            personas: personas.len(),
+        };
+        let archive = build_archive(
+            &manifest, &settings, &templates, &profiles, &personas, &database,
+        )?;
+        let partial = path.with_extension("part");
+        fs::write(&partial, encrypt(&archive, &passphrase)?).map_err(|e| e.to_string())?;
+        fs::rename(&partial, &path).map_err(|e| e.to_string())?;
+        tracing::info!("Wrote an encrypted backup to {}", path.display());
+        Ok::<_, String>(manifest)
+    })
+    .await?
+    .map_err(AppError::from)
+}
+
+/// Replace settings, templates, app profiles, personas and history with those in a backup
+/// made by `create_backup`. With `dry_run` the backup is only decrypted and checked.
+#[tauri::command]
+pub async fn restore_backup(
+    app: tauri::AppHandle,
+    path: PathBuf,
+    passphrase: String,
+    dry_run: Option<bool>,
+) -> Result<RestoreReport, AppError> {
+    check_passphrase(&passphrase)?;
+    capabilities::require_path(&app, &path, "Restore a backup").await?;
+    let dry_run = dry_run.unwrap_or(false);
+    let handle = app.clone();
+    let manifest = tauri::async_runtime::spawn_blocking(move || {
+        let contents = open(&handle, &path, &passphrase)?;
+        if dry_run {
+            return Ok::<_, AppError>(contents.manifest);
+        }
+        let temp = TempFile::new(&handle)?;
+        fs::write(&temp.0, &contents.database).map_err(|e| AppError::from(e.to_string()))?;
+        handle.state::<Database>().restore_from(&temp.0)?;
+        settings::replace(&handle, contents.settings)?;
+        templates::replace(&handle, contents.templates)?;
+        profiles::replace(&handle, contents.profiles)?;
+        if let Some(personas) = contents.personas {
+            personas::replace(&handle, personas)?;
+        }
+        tracing::info!("Restored the backup at {}", path.display());
+        Ok(contents.manifest)
+    })
+    .await??;
+    if !dry_run {
+        // The conversation that was open may not exist any more.
+        state::update(&app, |session| session.active_conversation = None).await;
+    }
+    Ok(RestoreReport {
+        manifest,
+        restored: !dry_run,
+    })
+}
@@ -0,0 +1,243 @@
+use std::collections::{HashMap, HashSet};
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use tauri::{Emitter, Manager, State};
+
+use crate::db::Database;
+use crate::error::AppError;
+use crate::history::{self, Message};
+use crate::llm::{self, ChatMessage, ProviderConfig};
+use crate::providers::params::GenerationParams;
+use crate::summaries;
+
+/// Alternatives that follow the same message, oldest first.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BranchPoint {
+    /// `None` for alternatives to the first message.
+    pub parent_id: Option<String>,
+    pub message_ids: Vec<String>,
+    /// The one on the branch that is shown, if any.
+    pub active: Option<usize>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BranchChangedPayload<'a> {
+    conversation_id: &'a str,
+    leaf_id: &'a str,
+}
+
+fn emit_changed(app: &tauri::AppHandle, message: &Message) {
+    let _ = app.emit(
+        "branch-changed",
+        BranchChangedPayload {
+            conversation_id: &message.conversation_id,
+            leaf_id: &message.id,
+        },
+    );
+}
+
+fn message(db: &Database, id: &str) -> Result<Message, AppError> {
+    db.with(|conn| history::find_message(conn, id))?
+        .ok_or_else(|| AppError::NotFound(format!("Message {id} not found")))
+}
+
+fn siblings(conn: &Connection, message: &Message) -> rusqlite::Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT id FROM messages WHERE conversation_id = ?1 AND parent_id IS ?2
+         ORDER BY created_at, rowid",
+    )?;
+    let ids = stmt
+        .query_map(params![message.conversation_id, message.parent_id], |row| {
+            row.get(0)
+        })?
+        .collect();
+    ids
+}
+
+/// Follow the newest reply from `id` on down to the end of its branch.
+fn leaf_below(conn: &Connection, id: &str) -> rusqlite::Result<String> {
+    let mut leaf = id.to_string();
+    while let Some(child) = conn
+        .query_row(
+            "SELECT id FROM messages WHERE parent_id = ?1 ORDER BY created_at DESC, rowid DESC
+             LIMIT 1",
+            [&leaf],
+            |row| row.get(0),
+        )
+        .optional()?
+    {
+        leaf = child;
+    }
+    Ok(leaf)
+}
+
+/// Fork the conversation at message `id`: a copy with `content` in its place is added
+/// next to it and becomes the branch shown. The original and what followed it are kept.
+#[tauri::command]
+pub async fn edit_message(
+    app: tauri::AppHandle,
+    db: State<'_, Database>,
+    id: String,
+    content: String,
+) -> Result<Message, AppError> {
+    let original = message(&db, &id)?;
+    let edited = db.with(|conn| {
+        let tx = conn.transaction()?;
+        let edited = history::insert_reply(
+            &tx,
+            &original.conversation_id,
+            original.parent_id.as_deref(),
+            &original.role,
+            &content,
+        )?;
+        tx.commit()?;
+        Ok(edited)
+    })?;
+    emit_changed(&app, &edited);
+    Ok(edited)
+}
+
+/// Ask the model again and return the request's id, as `chat_completion` does. For an
+/// answer, the new one is added next to it; for a user message, a new answer is added
+/// after it. Either way the earlier answers are kept, and `branch-changed` is emitted
+/// once the new one is saved.
+#[tauri::command]
+pub async fn regenerate_message(
+    app: tauri::AppHandle,
+    id: String,
+    config: Option<ProviderConfig>,
+    params: Option<GenerationParams>,
+) -> Result<String, AppError> {
+    let db = app.state::<Database>();
+    let target = message(&db, &id)?;
+    let parent = match target.role.as_str() {
+        "assistant" => target.parent_id,
+        "user" => Some(target.id),
+        role => {
+            return Err(AppError::InvalidInput(format!(
+                "Only answers and user messages can be regenerated, not {role} messages"
+            )))
+        }
+    };
+    let parent = parent.ok_or_else(|| {
+        AppError::InvalidInput("There is nothing before this answer to reply to".to_string())
+    })?;
+    let conversation_id = target.conversation_id;
+    let messages = db
+        .with(|conn| history::path_to(conn, &parent))?
+        .into_iter()
+        .map(|message| ChatMessage {
+            role: message.role,
+            content: message.content,
+            images: Vec::new(),
+        })
+        .collect();
+    let messages = summaries::condense(&app, &conversation_id, messages);
+    let params = params.unwrap_or_default();
+    llm::start_chat(
+        &app,
+        config,
+        Some(conversation_id.clone()),
+        messages,
+        params,
+        move |app, content| {
+            let answer = app.state::<Database>().with(|conn| {
+                history::insert_reply(conn, &conversation_id, Some(&parent), "assistant", content)
+            })?;
+            emit_changed(app, &answer);
+            Ok(())
+        },
+    )
+    .await
+}
+
+/// Show alternative `branch` of those next to `message_id`, numbered from 0 as in
+/// `list_branches`, down to the newest message below it. Returns the messages now shown.
+#[tauri::command]
+pub async fn switch_branch(
+    app: tauri::AppHandle,
+    db: State<'_, Database>,
+    message_id: String,
+    branch: usize,
+) -> Result<Vec<Message>, AppError> {
+    let message = message(&db, &message_id)?;
+    let siblings = db.with(|conn| siblings(conn, &message))?;
+    let chosen = siblings.get(branch).ok_or_else(|| {
+        AppError::InvalidInput(format!(
+            "Message {message_id} has {} branches, not {}",
+            siblings.len(),
+            branch + 1
+        ))
+    })?;
+    let path = db.with(|conn| {
+        let tx = conn.transaction()?;
+        let leaf = leaf_below(&tx, chosen)?;
+        tx.execute(
+            "UPDATE conversations SET active_leaf_id = ?2 WHERE id = ?1",
+            params![message.conversation_id, leaf],
+        )?;
+        let path = history::path_to(&tx, &leaf)?;
+        tx.commit()?;
+        Ok(path)
+    })?;
+    if let Some(leaf) = path.last() {
+        emit_changed(&app, leaf);
+    }
+    Ok(path)
+}
+
+/// Every place in a conversation with more than one alternative, in the order they
+/// were first written.
+#[tauri::command]
+pub async fn list_branches(
+    db: State<'_, Database>,
+    conversation_id: String,
+) -> Result<Vec<BranchPoint>, AppError> {
+    if db
+        .with(|conn| history::find_conversation(conn, &conversation_id))?
+        .is_none()
+    {
+        return Err(AppError::NotFound(format!(
+            "Conversation {conversation_id} not found"
+        )));
+    }
+    let points = db.with(|conn| {
+        let shown: HashSet<String> = history::conversation_messages(conn, &conversation_id)?
+            .into_iter()
+            .map(|message| message.id)
+            .collect();
+        let mut stmt = conn.prepare(
+            "SELECT id, parent_id FROM messages WHERE conversation_id = ?1
+             ORDER BY created_at, rowid",
+        )?;
+        let rows = stmt
+            .query_map([&conversation_id], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let mut points: Vec<BranchPoint> = Vec::new();
+        let mut by_parent: HashMap<Option<String>, usize> = HashMap::new();
+        for (id, parent_id) in rows {
+            let index = *by_parent.entry(parent_id.clone()).or_insert_with(|| {
+                points.push(BranchPoint {
+                    parent_id,
+                    message_ids: Vec::new(),
+                    active: None,
+                });
+                points.len() - 1
+            });
+            let point = &mut points[index];
+            if shown.contains(&id) {
+                point.active = Some(point.message_ids.len());
+            }
+            point.message_ids.push(id);
+        }
+        points.retain(|point| point.message_ids.len() > 1);
+        Ok(points)
+    })?;
+    Ok(points)
+}
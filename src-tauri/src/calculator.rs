@@ -0,0 +1,701 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::Manager;
+
+use crate::error::AppError;
+use crate::providers::service_error_for_status;
+use crate::{db, http, persist};
+
+const RATES_FILE: &str = "exchange-rates.json";
+/// Rates in US dollars, from a free endpoint that needs no key.
+const RATES_URL: &str = "https://open.er-api.com/v6/latest/USD";
+/// Rates older than this are still used, but refreshed in the background.
+const RATES_MAX_AGE_MS: i64 = 12 * 60 * 60 * 1000;
+const RATES_TIMEOUT: Duration = Duration::from_secs(5);
+/// Significant digits in an answer.
+const PRECISION: i32 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dimension {
+    Length,
+    Area,
+    Volume,
+    Mass,
+    Time,
+    Speed,
+    Temperature,
+    Data,
+    Currency,
+}
+
+/// Units: symbol shown, names it goes by, what it measures, and its size and offset in
+/// the base unit of that dimension.
+#[rustfmt::skip]
+const UNITS: &[(&str, &[&str], Dimension, f64, f64)] = &[
+    ("mm", &["mm", "millimeter", "millimeters", "millimetre", "millimetres"], Dimension::Length, 0.001, 0.0),
+    ("cm", &["cm", "centimeter", "centimeters", "centimetre", "centimetres"], Dimension::Length, 0.01, 0.0),
+    ("m", &["m", "meter", "meters", "metre", "metres"], Dimension::Length, 1.0, 0.0),
+    ("km", &["km", "kilometer", "kilometers", "kilometre", "kilometres"], Dimension::Length, 1000.0, 0.0),
+    ("in", &["in", "inch", "inches"], Dimension::Length, 0.0254, 0.0),
+    ("ft", &["ft", "foot", "feet"], Dimension::Length, 0.3048, 0.0),
+    ("yd", &["yd", "yard", "yards"], Dimension::Length, 0.9144, 0.0),
+    ("mi", &["mi", "mile", "miles"], Dimension::Length, 1609.344, 0.0),
+    ("nmi", &["nmi", "nauticalmile", "nauticalmiles"], Dimension::Length, 1852.0, 0.0),
+    ("cm²", &["cm2", "cm²"], Dimension::Area, 1e-4, 0.0),
+    ("m²", &["m2", "m²", "sqm"], Dimension::Area, 1.0, 0.0),
+    ("km²", &["km2", "km²"], Dimension::Area, 1e6, 0.0),
+    ("ft²", &["ft2", "ft²", "sqft"], Dimension::Area, 0.092_903_04, 0.0),
+    ("mi²", &["mi2", "mi²"], Dimension::Area, 2_589_988.110_336, 0.0),
+    ("ha", &["ha", "hectare", "hectares"], Dimension::Area, 1e4, 0.0),
+    ("acre", &["acre", "acres"], Dimension::Area, 4_046.856_422_4, 0.0),
+    ("ml", &["ml", "milliliter", "milliliters", "millilitre", "millilitres"], Dimension::Volume, 0.001, 0.0),
+    ("l", &["l", "liter", "liters", "litre", "litres"], Dimension::Volume, 1.0, 0.0),
+    ("m³", &["m3", "m³"], Dimension::Volume, 1000.0, 0.0),
+    ("tsp", &["tsp", "teaspoon", "teaspoons"], Dimension::Volume, 0.004_928_921_593_75, 0.0),
+    ("tbsp", &["tbsp", "tablespoon", "tablespoons"], Dimension::Volume, 0.014_786_764_781_25, 0.0),
+    ("fl oz", &["floz", "fluidounce", "fluidounces"], Dimension::Volume, 0.029_573_529_562_5, 0.0),
+    ("cup", &["cup", "cups"], Dimension::Volume, 0.236_588_236_5, 0.0),
+    ("pt", &["pt", "pint", "pints"], Dimension::Volume, 0.473_176_473, 0.0),
+    ("qt", &["qt", "quart", "quarts"], Dimension::Volume, 0.946_352_946, 0.0),
+    ("gal", &["gal", "gallon", "gallons"], Dimension::Volume, 3.785_411_784, 0.0),
+    ("mg", &["mg", "milligram", "milligrams"], Dimension::Mass, 1e-6, 0.0),
+    ("g", &["g", "gram", "grams"], Dimension::Mass, 0.001, 0.0),
+    ("kg", &["kg", "kilogram", "kilograms", "kilo", "kilos"], Dimension::Mass, 1.0, 0.0),
+    ("t", &["t", "tonne", "tonnes"], Dimension::Mass, 1000.0, 0.0),
+    ("oz", &["oz", "ounce", "ounces"], Dimension::Mass, 0.028_349_523_125, 0.0),
+    ("lb", &["lb", "lbs", "pound", "pounds"], Dimension::Mass, 0.453_592_37, 0.0),
+    ("st", &["st", "stone", "stones"], Dimension::Mass, 6.350_293_18, 0.0),
+    ("ms", &["ms", "millisecond", "milliseconds"], Dimension::Time, 0.001, 0.0),
+    ("s", &["s", "sec", "secs", "second", "seconds"], Dimension::Time, 1.0, 0.0),
+    ("min", &["min", "mins", "minute", "minutes"], Dimension::Time, 60.0, 0.0),
+    ("h", &["h", "hr", "hrs", "hour", "hours"], Dimension::Time, 3600.0, 0.0),
+    ("d", &["d", "day", "days"], Dimension::Time, 86_400.0, 0.0),
+    ("wk", &["wk", "week", "weeks"], Dimension::Time, 604_800.0, 0.0),
+    ("yr", &["yr", "year", "years"], Dimension::Time, 31_557_600.0, 0.0),
+    ("m/s", &["mps"], Dimension::Speed, 1.0, 0.0),
+    ("km/h", &["kph", "kmh"], Dimension::Speed, 1.0 / 3.6, 0.0),
+    ("mph", &["mph"], Dimension::Speed, 0.447_04, 0.0),
+    ("kn", &["kn", "knot", "knots"], Dimension::Speed, 0.514_444_444_444_444_5, 0.0),
+    ("°C", &["c", "°c", "celsius"], Dimension::Temperature, 1.0, 273.15),
+    ("°F", &["f", "°f", "fahrenheit"], Dimension::Temperature, 5.0 / 9.0, 459.67 * 5.0 / 9.0),
+    ("K", &["k", "kelvin"], Dimension::Temperature, 1.0, 0.0),
+    ("bit", &["bit", "bits"], Dimension::Data, 0.125, 0.0),
+    ("B", &["b", "byte", "bytes"], Dimension::Data, 1.0, 0.0),
+    ("KB", &["kb", "kilobyte", "kilobytes"], Dimension::Data, 1e3, 0.0),
+    ("MB", &["mb", "megabyte", "megabytes"], Dimension::Data, 1e6, 0.0),
+    ("GB", &["gb", "gigabyte", "gigabytes"], Dimension::Data, 1e9, 0.0),
+    ("TB", &["tb", "terabyte", "terabytes"], Dimension::Data, 1e12, 0.0),
+    ("KiB", &["kib"], Dimension::Data, 1024.0, 0.0),
+    ("MiB", &["mib"], Dimension::Data, 1_048_576.0, 0.0),
+    ("GiB", &["gib"], Dimension::Data, 1_073_741_824.0, 0.0),
+    ("TiB", &["tib"], Dimension::Data, 1_099_511_627_776.0, 0.0),
+];
+
+/// Currency codes recognized, in any case.
+const CURRENCIES: &[&str] = &[
+    "USD", "EUR", "GBP", "JPY", "CNY", "INR", "CAD", "AUD", "NZD", "CHF", "SEK", "NOK", "DKK",
+    "PLN", "CZK", "HUF", "RON", "BGN", "ISK", "TRY", "RUB", "UAH", "BRL", "MXN", "ARS", "CLP",
+    "COP", "KRW", "SGD", "HKD", "TWD", "THB", "IDR", "MYR", "PHP", "VND", "ZAR", "EGP", "NGN",
+    "AED", "SAR", "ILS", "PKR",
+];
+const CURRENCY_SYMBOLS: &[(char, &str)] = &[
+    ('$', "USD"),
+    ('€', "EUR"),
+    ('£', "GBP"),
+    ('¥', "JPY"),
+    ('₹', "INR"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EvaluationKind {
+    Math,
+    Unit,
+    Currency,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Evaluation {
+    pub value: f64,
+    pub unit: Option<String>,
+    /// The answer as shown, e.g. `3.107 mi`.
+    pub text: String,
+    pub kind: EvaluationKind,
+    /// When the exchange rates used were fetched, for currency answers.
+    pub rates_fetched_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Rates {
+    /// Units of each currency to one US dollar, by code.
+    rates: HashMap<String, f64>,
+    fetched_at: i64,
+}
+
+/// The exchange rates last fetched, kept across restarts so currency answers work
+/// offline too.
+pub struct ExchangeRates {
+    path: PathBuf,
+    rates: Mutex<Option<Rates>>,
+    refreshing: AtomicBool,
+}
+
+impl ExchangeRates {
+    pub fn load(app: &tauri::AppHandle) -> Result<Self, String> {
+        let path = persist::data_file(app, RATES_FILE)?;
+        let rates = persist::load_json(&path);
+        Ok(Self {
+            path,
+            rates: Mutex::new(rates),
+            refreshing: AtomicBool::new(false),
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct RatesResponse {
+    rates: HashMap<String, f64>,
+}
+
+async fn fetch_rates(app: &tauri::AppHandle) -> Result<Rates, AppError> {
+    let request = http::client(app)?.get(RATES_URL).timeout(RATES_TIMEOUT);
+    let response = http::send(app, "exchange-rates", None, request)
+        .await
+        .map_err(|e| AppError::Network(format!("Could not fetch exchange rates: {e}")))?;
+    let response: RatesResponse =
+        service_error_for_status("exchange-rates", "Exchange rates", response)
+            .await?
+            .json()
+            .await?;
+    let rates = Rates {
+        rates: response.rates,
+        fetched_at: db::now_ms(),
+    };
+    let state = app.state::<ExchangeRates>();
+    if let Err(error) = persist::save_json(&state.path, &rates) {
+        tracing::warn!("Could not save exchange rates: {error}");
+    }
+    *state.rates.lock().unwrap() = Some(rates.clone());
+    Ok(rates)
+}
+
+/// The cached rates, fetched first if there are none. Stale ones are used as they are
+/// and refreshed in the background, so an answer never waits on the network twice.
+async fn rates(app: &tauri::AppHandle) -> Result<Rates, AppError> {
+    let state = app.state::<ExchangeRates>();
+    let cached = state.rates.lock().unwrap().clone();
+    let Some(rates) = cached else {
+        return fetch_rates(app).await;
+    };
+    if db::now_ms() - rates.fetched_at > RATES_MAX_AGE_MS
+        && !state.refreshing.swap(true, Ordering::SeqCst)
+    {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(error) = fetch_rates(&app).await {
+                tracing::debug!("Could not refresh exchange rates: {error}");
+            }
+            app.state::<ExchangeRates>()
+                .refreshing
+                .store(false, Ordering::SeqCst);
+        });
+    }
+    Ok(rates)
+}
+
+#[derive(Debug, Clone)]
+struct Unit {
+    symbol: String,
+    dimension: Dimension,
+    factor: f64,
+    offset: f64,
+}
+
+impl Unit {
+    fn base_value(&self, value: f64) -> f64 {
+        value * self.factor + self.offset
+    }
+
+    fn value_from_base(&self, value: f64) -> f64 {
+        (value - self.offset) / self.factor
+    }
+}
+
+fn currency_code(word: &str) -> Option<&'static str> {
+    CURRENCIES
+        .iter()
+        .find(|code| code.eq_ignore_ascii_case(word))
+        .copied()
+}
+
+/// The unit `word` names, with currencies priced from `rates` when there are any.
+fn unit(word: &str, rates: Option<&Rates>) -> Option<Unit> {
+    let lower = word.to_lowercase();
+    if let Some(&(symbol, _, dimension, factor, offset)) = UNITS
+        .iter()
+        .find(|(_, names, ..)| names.contains(&lower.as_str()))
+    {
+        return Some(Unit {
+            symbol: symbol.to_string(),
+            dimension,
+            factor,
+            offset,
+        });
+    }
+    let code = currency_code(word)?;
+    let rate = *rates?.rates.get(code)?;
+    (rate > 0.0).then(|| Unit {
+        symbol: code.to_string(),
+        dimension: Dimension::Currency,
+        factor: 1.0 / rate,
+        offset: 0.0,
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Word(String),
+    Op(char),
+}
+
+impl Token {
+    fn is_currency(&self) -> bool {
+        matches!(self, Token::Word(word) if currency_code(word).is_some())
+    }
+}
+
+fn lex(input: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let digit_at = |at: usize| chars.get(at).is_some_and(char::is_ascii_digit);
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || (c == '.' && digit_at(i + 1)) {
+            let mut number = String::new();
+            while i < chars.len() {
+                let c = chars[i];
+                // A comma between groups of three digits separates thousands.
+                let thousands = c == ',' && (1..=3).all(|k| digit_at(i + k)) && !digit_at(i + 4);
+                let exponent = matches!(c, 'e' | 'E')
+                    && (digit_at(i + 1)
+                        || (matches!(chars.get(i + 1), Some('+' | '-')) && digit_at(i + 2)));
+                if c.is_ascii_digit() || c == '.' {
+                    number.push(c);
+                } else if exponent {
+                    number.push(c);
+                    number.push(chars[i + 1]);
+                    i += 1;
+                } else if !thousands {
+                    break;
+                }
+                i += 1;
+            }
+            let value = number
+                .parse()
+                .map_err(|_| format!("{number} is not a number"))?;
+            tokens.push(Token::Number(value));
+        } else if c.is_alphabetic() || c == '°' {
+            let start = i;
+            while i < chars.len()
+                && (chars[i].is_alphanumeric() || matches!(chars[i], '°' | '²' | '³'))
+            {
+                i += 1;
+            }
+            tokens.push(Token::Word(chars[start..i].iter().collect()));
+        } else if let Some((_, code)) = CURRENCY_SYMBOLS.iter().find(|(symbol, _)| *symbol == c) {
+            tokens.push(Token::Word(code.to_string()));
+            i += 1;
+        } else if c == '*' && chars.get(i + 1) == Some(&'*') {
+            tokens.push(Token::Op('^'));
+            i += 2;
+        } else {
+            let op = match c {
+                '×' => '*',
+                '÷' => '/',
+                '+' | '-' | '*' | '/' | '^' | '%' | '(' | ')' => c,
+                _ => return Err(format!("Unexpected {c}")),
+            };
+            tokens.push(Token::Op(op));
+            i += 1;
+        }
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone)]
+struct Quantity {
+    value: f64,
+    unit: Option<Unit>,
+}
+
+impl Quantity {
+    fn number(value: f64) -> Self {
+        Self { value, unit: None }
+    }
+
+    fn plain(&self, what: &str) -> Result<f64, String> {
+        match &self.unit {
+            None => Ok(self.value),
+            Some(unit) => Err(format!("Can't {what} a quantity in {}", unit.symbol)),
+        }
+    }
+
+    /// This quantity in `unit`, which must measure the same thing.
+    fn convert(&self, unit: &Unit) -> Result<f64, String> {
+        match &self.unit {
+            Some(from) if from.dimension == unit.dimension => {
+                Ok(unit.value_from_base(from.base_value(self.value)))
+            }
+            Some(from) => Err(format!("Can't convert {} to {}", from.symbol, unit.symbol)),
+            None => Err(format!("Nothing to convert to {}", unit.symbol)),
+        }
+    }
+}
+
+const KEYWORDS: &[&str] = &["in", "to", "as", "into", "of", "mod"];
+
+/// A recursive descent parser that evaluates as it goes.
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    rates: Option<&'a Rates>,
+    /// Whether anything was computed, as opposed to a bare number or quantity.
+    computed: bool,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_word(&self, offset: usize) -> Option<&str> {
+        match self.tokens.get(self.pos + offset) {
+            Some(Token::Word(word)) => Some(word),
+            _ => None,
+        }
+    }
+
+    fn eat_op(&mut self, op: char) -> bool {
+        if self.peek() == Some(&Token::Op(op)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_word(&mut self, words: &[&str]) -> bool {
+        if self
+            .peek_word(0)
+            .is_some_and(|word| words.contains(&word.to_lowercase().as_str()))
+        {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// The unit at `offset`, unless it is a keyword used as one.
+    fn unit_at(&self, offset: usize) -> Option<Unit> {
+        let word = self.peek_word(offset)?;
+        // "in" is inches only when no unit follows to convert into.
+        if word.eq_ignore_ascii_case("in") && self.unit_word(offset + 1).is_some() {
+            return None;
+        }
+        unit(word, self.rates)
+    }
+
+    fn unit_word(&self, offset: usize) -> Option<Unit> {
+        unit(self.peek_word(offset)?, self.rates)
+    }
+
+    /// The whole input: an expression, converted if it ends in `in <unit>`.
+    fn input(&mut self) -> Result<Quantity, String> {
+        let mut quantity = self.sum()?;
+        if self.eat_word(&["in", "to", "as", "into"]) {
+            let target = self
+                .unit_word(0)
+                .ok_or_else(|| "Expected a unit to convert to".to_string())?;
+            self.pos += 1;
+            quantity = Quantity {
+                value: quantity.convert(&target)?,
+                unit: Some(target),
+            };
+            self.computed = true;
+        }
+        match self.peek() {
+            None => Ok(quantity),
+            Some(token) => Err(format!("Unexpected {token:?}")),
+        }
+    }
+
+    fn sum(&mut self) -> Result<Quantity, String> {
+        let mut left = self.product()?;
+        loop {
+            let sign = if self.eat_op('+') {
+                1.0
+            } else if self.eat_op('-') {
+                -1.0
+            } else {
+                return Ok(left);
+            };
+            let right = self.product()?;
+            self.computed = true;
+            left = match (&left.unit, &right.unit) {
+                (Some(unit), Some(_)) => Quantity {
+                    value: left.value + sign * right.convert(unit)?,
+                    unit: left.unit,
+                },
+                (None, _) | (_, None) => Quantity {
+                    value: left.value + sign * right.value,
+                    unit: left.unit.or(right.unit),
+                },
+            };
+        }
+    }
+
+    fn product(&mut self) -> Result<Quantity, String> {
+        let mut left = self.unary()?;
+        loop {
+            let op = if self.eat_op('*') || self.eat_word(&["of"]) {
+                '*'
+            } else if self.eat_op('/') {
+                '/'
+            } else if self.eat_word(&["mod"]) {
+                '%'
+            } else {
+                return Ok(left);
+            };
+            let right = self.unary()?;
+            self.computed = true;
+            left = match op {
+                '*' => match (&left.unit, &right.unit) {
+                    (Some(_), Some(_)) => return Err("Can't multiply two quantities".to_string()),
+                    _ => Quantity {
+                        value: left.value * right.value,
+                        unit: left.unit.or(right.unit),
+                    },
+                },
+                '/' => match &right.unit {
+                    // The same kind of quantity divided by another is a plain ratio.
+                    Some(unit) => Quantity::number(left.convert(unit)? / right.value),
+                    None => Quantity {
+                        value: left.value / right.value,
+                        unit: left.unit,
+                    },
+                },
+                _ => Quantity::number(
+                    left.plain("take the remainder of")? % right.plain("divide by")?,
+                ),
+            };
+        }
+    }
+
+    /// A sign binds looser than `^`, so `-2^2` is -4.
+    fn unary(&mut self) -> Result<Quantity, String> {
+        if self.eat_op('-') {
+            let mut quantity = self.unary()?;
+            quantity.value = -quantity.value;
+            return Ok(quantity);
+        }
+        if self.eat_op('+') {
+            return self.unary();
+        }
+        self.power()
+    }
+
+    fn power(&mut self) -> Result<Quantity, String> {
+        let base = self.postfix()?;
+        if !self.eat_op('^') {
+            return Ok(base);
+        }
+        let exponent = self.unary()?;
+        self.computed = true;
+        Ok(Quantity::number(
+            base.plain("raise")?.powf(exponent.plain("raise to")?),
+        ))
+    }
+
+    /// A value with any `%` and unit after it.
+    fn postfix(&mut self) -> Result<Quantity, String> {
+        let mut quantity = self.primary()?;
+        if self.eat_op('%') {
+            quantity.value /= 100.0;
+            self.computed = true;
+        }
+        if quantity.unit.is_none() {
+            if let Some(unit) = self.unit_at(0) {
+                self.pos += 1;
+                quantity.unit = Some(unit);
+            }
+        }
+        Ok(quantity)
+    }
+
+    fn primary(&mut self) -> Result<Quantity, String> {
+        let token = self
+            .peek()
+            .cloned()
+            .ok_or_else(|| "Unexpected end of input".to_string())?;
+        self.pos += 1;
+        match token {
+            Token::Number(value) => Ok(Quantity::number(value)),
+            Token::Op('(') => {
+                let inner = self.sum()?;
+                if !self.eat_op(')') {
+                    return Err("Missing )".to_string());
+                }
+                Ok(inner)
+            }
+            Token::Word(word) => {
+                let lower = word.to_lowercase();
+                if self.eat_op('(') {
+                    let argument = self.sum()?;
+                    if !self.eat_op(')') {
+                        return Err("Missing )".to_string());
+                    }
+                    self.computed = true;
+                    let x = argument.plain("take a function of")?;
+                    return function(&lower, x).map(Quantity::number);
+                }
+                if let Some(value) = constant(&lower) {
+                    self.computed = true;
+                    return Ok(Quantity::number(value));
+                }
+                // A unit written first, as in "$20".
+                let unit = unit(&word, self.rates).filter(|_| !KEYWORDS.contains(&lower.as_str()));
+                if let (Some(unit), Some(Token::Number(value))) = (unit, self.peek().cloned()) {
+                    self.pos += 1;
+                    return Ok(Quantity {
+                        value,
+                        unit: Some(unit),
+                    });
+                }
+                Err(format!("Unknown word {word}"))
+            }
+            Token::Op(op) => Err(format!("Unexpected {op}")),
+        }
+    }
+}
+
+fn function(name: &str, x: f64) -> Result<f64, String> {
+    Ok(match name {
+        "sqrt" => x.sqrt(),
+        "cbrt" => x.cbrt(),
+        "abs" => x.abs(),
+        "ln" => x.ln(),
+        "log" => x.log10(),
+        "log2" => x.log2(),
+        "exp" => x.exp(),
+        "sin" => x.sin(),
+        "cos" => x.cos(),
+        "tan" => x.tan(),
+        "asin" => x.asin(),
+        "acos" => x.acos(),
+        "atan" => x.atan(),
+        "round" => x.round(),
+        "floor" => x.floor(),
+        "ceil" => x.ceil(),
+        _ => return Err(format!("Unknown function {name}")),
+    })
+}
+
+fn constant(name: &str) -> Option<f64> {
+    match name {
+        "pi" | "π" => Some(std::f64::consts::PI),
+        "tau" | "τ" => Some(std::f64::consts::TAU),
+        "e" => Some(std::f64::consts::E),
+        _ => None,
+    }
+}
+
+/// `value` to `PRECISION` significant digits, or to exactly `decimals` places.
+fn format_number(value: f64, decimals: Option<usize>) -> String {
+    if let Some(decimals) = decimals {
+        return format!("{value:.decimals$}");
+    }
+    if value == 0.0 {
+        return "0".to_string();
+    }
+    let magnitude = value.abs().log10().floor() as i32;
+    if !(-6..15).contains(&magnitude) {
+        return format!("{value:e}");
+    }
+    let decimals = (PRECISION - 1 - magnitude).max(0) as usize;
+    let text = format!("{value:.decimals$}");
+    if text.contains('.') {
+        text.trim_end_matches('0').trim_end_matches('.').to_string()
+    } else {
+        text
+    }
+}
+
+/// Evaluate `tokens`, or `None` if they aren't a calculation: something that doesn't
+/// parse, or a bare number with nothing done to it.
+fn evaluate(tokens: Vec<Token>, rates: Option<&Rates>) -> Option<Evaluation> {
+    if tokens.is_empty() {
+        return None;
+    }
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        rates,
+        computed: false,
+    };
+    let quantity = parser.input().ok()?;
+    if !parser.computed || !quantity.value.is_finite() {
+        return None;
+    }
+    let currency = quantity
+        .unit
+        .as_ref()
+        .is_some_and(|unit| unit.dimension == Dimension::Currency);
+    let number = format_number(quantity.value, currency.then_some(2));
+    let (kind, text) = match &quantity.unit {
+        Some(unit) if currency => (
+            EvaluationKind::Currency,
+            format!("{number} {}", unit.symbol),
+        ),
+        Some(unit) => (EvaluationKind::Unit, format!("{number} {}", unit.symbol)),
+        None => (EvaluationKind::Math, number),
+    };
+    Some(Evaluation {
+        value: quantity.value,
+        unit: quantity.unit.map(|unit| unit.symbol),
+        text,
+        kind,
+        rates_fetched_at: rates.filter(|_| currency).map(|rates| rates.fetched_at),
+    })
+}
+
+/// Answer arithmetic, unit conversions and currency conversions such as `2^10`,
+/// `5 km in miles`, `20% of 150` or `$20 to EUR` locally, for the quick-ask bar to show
+/// before anything goes to a model. Returns `None` when the input isn't a calculation.
+/// Exchange rates are fetched once and cached.
+#[tauri::command]
+pub async fn evaluate_expression(
+    app: tauri::AppHandle,
+    input: String,
+) -> Result<Option<Evaluation>, AppError> {
+    let Ok(tokens) = lex(&input) else {
+        return Ok(None);
+    };
+    let rates = if tokens.iter().any(Token::is_currency) {
+        Some(rates(&app).await?)
+    } else {
+        None
+    };
+    Ok(evaluate(tokens, rates.as_ref()))
+}
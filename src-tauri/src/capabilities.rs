@@ -0,0 +1,411 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, Manager};
+use tokio::sync::oneshot;
+
+use crate::db::{self, Database};
+use crate::error::AppError;
+use crate::persist;
+use crate::window_manager::{self, AppWindow};
+use crate::workspaces;
+
+const GRANTS_FILE: &str = "capabilities.json";
+const DEFAULT_PAGE_SIZE: u32 = 50;
+/// How long a feature waits for the user to answer before giving up. An unanswered
+/// request stays open, so it can still be answered later.
+const CONSENT_TIMEOUT: Duration = Duration::from_secs(120);
+/// The audit log keeps this many of the most recent uses.
+const MAX_AUDIT_ENTRIES: i64 = 50_000;
+
+/// Something the app can do that reaches beyond its own windows and files, and so needs
+/// the user's say-so the first time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Capability {
+    ScreenCapture,
+    /// Reading the selection from, and typing into, other apps.
+    Accessibility,
+    Microphone,
+    /// Files outside the app's own data, config, cache and log folders.
+    FileAccess,
+}
+
+impl Capability {
+    const ALL: [Capability; 4] = [
+        Capability::ScreenCapture,
+        Capability::Accessibility,
+        Capability::Microphone,
+        Capability::FileAccess,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Capability::ScreenCapture => "screenCapture",
+            Capability::Accessibility => "accessibility",
+            Capability::Microphone => "microphone",
+            Capability::FileAccess => "fileAccess",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|capability| capability.name() == name)
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Capability::ScreenCapture => "screen capture",
+            Capability::Accessibility => "other apps' text",
+            Capability::Microphone => "the microphone",
+            Capability::FileAccess => "files outside the app folder",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CapabilityGrant {
+    pub allowed: bool,
+    pub decided_at: i64,
+}
+
+/// A consent request the user hasn't answered yet.
+struct Pending {
+    id: String,
+    waiters: Vec<oneshot::Sender<bool>>,
+}
+
+/// The user's answer for each capability, and the questions still open.
+pub struct CapabilityStore {
+    path: PathBuf,
+    grants: Mutex<HashMap<Capability, CapabilityGrant>>,
+    pending: Mutex<HashMap<Capability, Pending>>,
+}
+
+impl CapabilityStore {
+    pub fn load(app: &tauri::AppHandle) -> Result<Self, String> {
+        let path = persist::data_file(app, GRANTS_FILE)?;
+        let grants = persist::load_json(&path);
+        Ok(Self {
+            path,
+            grants: Mutex::new(grants),
+            pending: Mutex::new(HashMap::new()),
+        })
+    }
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ConsentRequest<'a> {
+    id: &'a str,
+    capability: Capability,
+    label: &'a str,
+    /// What wants it, such as "Screenshot" or a file path.
+    detail: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    pub id: i64,
+    pub capability: Capability,
+    pub detail: String,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditPage {
+    pub entries: Vec<AuditEntry>,
+    pub total: u32,
+}
+
+fn decision(app: &tauri::AppHandle, capability: Capability) -> Option<bool> {
+    app.state::<CapabilityStore>()
+        .grants
+        .lock()
+        .unwrap()
+        .get(&capability)
+        .map(|grant| grant.allowed)
+}
+
+fn denied(capability: Capability) -> String {
+    format!(
+        "Access to {} is turned off in the privacy settings",
+        capability.label()
+    )
+}
+
+/// Append a use to the audit log. Failing to log doesn't stop the feature.
+fn audit(app: &tauri::AppHandle, capability: Capability, detail: &str) {
+    let Some(db) = app.try_state::<Database>() else {
+        return;
+    };
+    let logged = db.with(|conn| {
+        conn.execute(
+            "INSERT INTO capability_audit (capability, detail, created_at) VALUES (?1, ?2, ?3)",
+            params![capability.name(), detail, db::now_ms()],
+        )?;
+        conn.execute(
+            "DELETE FROM capability_audit WHERE id <= last_insert_rowid() - ?1",
+            [MAX_AUDIT_ENTRIES],
+        )
+    });
+    if let Err(error) = logged {
+        tracing::warn!("Could not log a use of {}: {error}", capability.name());
+    }
+}
+
+fn save_grant(
+    app: &tauri::AppHandle,
+    capability: Capability,
+    allowed: Option<bool>,
+) -> Result<(), String> {
+    let store = app.state::<CapabilityStore>();
+    let mut grants = store.grants.lock().unwrap();
+    match allowed {
+        Some(allowed) => {
+            grants.insert(
+                capability,
+                CapabilityGrant {
+                    allowed,
+                    decided_at: db::now_ms(),
+                },
+            );
+        }
+        None => {
+            grants.remove(&capability);
+        }
+    }
+    persist::save_json(&store.path, &*grants)?;
+    let _ = app.emit("capability-grants-changed", &*grants);
+    Ok(())
+}
+
+/// Emit `capability-consent-request`, unless one is already out for `capability`, and
+/// get a receiver for the answer.
+fn ask(app: &tauri::AppHandle, capability: Capability, detail: &str) -> oneshot::Receiver<bool> {
+    let (sender, receiver) = oneshot::channel();
+    let store = app.state::<CapabilityStore>();
+    let mut pending = store.pending.lock().unwrap();
+    if let Some(open) = pending.get_mut(&capability) {
+        open.waiters.push(sender);
+        return receiver;
+    }
+    let id = uuid::Uuid::new_v4().to_string();
+    let _ = app.emit(
+        "capability-consent-request",
+        ConsentRequest {
+            id: &id,
+            capability,
+            label: capability.label(),
+            detail,
+        },
+    );
+    // The question may come from a hotkey while every window is hidden.
+    let _ = window_manager::show(app, AppWindow::Main);
+    pending.insert(
+        capability,
+        Pending {
+            id,
+            waiters: vec![sender],
+        },
+    );
+    receiver
+}
+
+/// Make sure the user allows `capability`, asking and waiting for them the first time,
+/// and log the use. `detail` says what it is for.
+pub async fn require(
+    app: &tauri::AppHandle,
+    capability: Capability,
+    detail: &str,
+) -> Result<(), AppError> {
+    let allowed = match decision(app, capability) {
+        Some(allowed) => allowed,
+        None => tokio::time::timeout(CONSENT_TIMEOUT, ask(app, capability, detail))
+            .await
+            .ok()
+            .and_then(Result::ok)
+            .unwrap_or(false),
+    };
+    if !allowed {
+        return Err(AppError::PermissionDenied(denied(capability)));
+    }
+    audit(app, capability, detail);
+    Ok(())
+}
+
+/// Like `require`, for code that can't wait on the user, such as a hotkey grabbing the
+/// selection: the first time it raises the question and fails, and once the user has
+/// answered it goes by that.
+pub fn check(app: &tauri::AppHandle, capability: Capability, detail: &str) -> Result<(), String> {
+    match decision(app, capability) {
+        Some(true) => {
+            audit(app, capability, detail);
+            Ok(())
+        }
+        Some(false) => Err(denied(capability)),
+        None => {
+            drop(ask(app, capability, detail));
+            Err(format!(
+                "Waiting for permission to use {}",
+                capability.label()
+            ))
+        }
+    }
+}
+
+/// Folders the app owns, where no permission is needed.
+pub fn app_dirs(app: &tauri::AppHandle) -> Vec<PathBuf> {
+    let paths = app.path();
+    [
+        paths.app_data_dir(),
+        paths.app_config_dir(),
+        paths.app_cache_dir(),
+        paths.app_log_dir(),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|dir| fs::canonicalize(&dir).unwrap_or(dir))
+    .collect()
+}
+
+/// `require` file access for `path`, unless it lies inside the app's own folders.
+pub async fn require_path(
+    app: &tauri::AppHandle,
+    path: &Path,
+    detail: &str,
+) -> Result<(), AppError> {
+    // A file that doesn't exist yet, such as a backup about to be written, is judged by
+    // its folder.
+    let resolved = fs::canonicalize(path)
+        .or_else(|_| {
+            path.parent()
+                .map_or_else(|| Ok(path.to_path_buf()), fs::canonicalize)
+        })
+        .unwrap_or_else(|_| path.to_path_buf());
+    if workspaces::is_foreign(app, &resolved) {
+        return Err(AppError::InvalidInput(format!(
+            "{} belongs to another workspace",
+            path.display()
+        )));
+    }
+    if app_dirs(app).iter().any(|dir| resolved.starts_with(dir)) {
+        return Ok(());
+    }
+    require(
+        app,
+        Capability::FileAccess,
+        &format!("{detail}: {}", path.display()),
+    )
+    .await
+}
+
+/// Answer a `capability-consent-request`. The answer is kept for every later use until
+/// it is changed with `set_capability_grant`. Returns `false` if the request is no
+/// longer open.
+#[tauri::command]
+pub fn respond_capability_consent(
+    app: tauri::AppHandle,
+    id: String,
+    allow: bool,
+) -> Result<bool, AppError> {
+    let open = {
+        let store = app.state::<CapabilityStore>();
+        let mut pending = store.pending.lock().unwrap();
+        let capability = pending
+            .iter()
+            .find(|(_, open)| open.id == id)
+            .map(|(capability, _)| *capability);
+        capability.and_then(|capability| pending.remove_entry(&capability))
+    };
+    let Some((capability, open)) = open else {
+        return Ok(false);
+    };
+    save_grant(&app, capability, Some(allow))?;
+    tracing::info!(
+        "{} {}",
+        if allow { "Allowed" } else { "Denied" },
+        capability.name()
+    );
+    for waiter in open.waiters {
+        let _ = waiter.send(allow);
+    }
+    Ok(true)
+}
+
+#[tauri::command]
+pub fn list_capability_grants(app: tauri::AppHandle) -> HashMap<Capability, CapabilityGrant> {
+    app.state::<CapabilityStore>()
+        .grants
+        .lock()
+        .unwrap()
+        .clone()
+}
+
+/// Allow or deny a capability from the privacy settings, or with `None` forget the
+/// answer so the next use asks again.
+#[tauri::command]
+pub fn set_capability_grant(
+    app: tauri::AppHandle,
+    capability: Capability,
+    allowed: Option<bool>,
+) -> Result<(), AppError> {
+    save_grant(&app, capability, allowed).map_err(AppError::from)
+}
+
+/// Every logged use of a granted capability, newest first, optionally of one kind.
+#[tauri::command]
+pub async fn get_capability_audit(
+    app: tauri::AppHandle,
+    capability: Option<Capability>,
+    limit: Option<u32>,
+    offset: Option<u32>,
+) -> Result<AuditPage, AppError> {
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).min(500);
+    let offset = offset.unwrap_or(0);
+    let filter = capability.map(Capability::name);
+    app.state::<Database>()
+        .with(|conn| {
+            let total = conn.query_row(
+                "SELECT COUNT(*) FROM capability_audit WHERE ?1 IS NULL OR capability = ?1",
+                [filter],
+                |row| row.get(0),
+            )?;
+            let mut stmt = conn.prepare(
+                "SELECT id, capability, detail, created_at FROM capability_audit
+                 WHERE ?1 IS NULL OR capability = ?1
+                 ORDER BY id DESC LIMIT ?2 OFFSET ?3",
+            )?;
+            let rows = stmt.query_map(params![filter, limit, offset], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, i64>(3)?,
+                ))
+            })?;
+            let mut entries = Vec::new();
+            for row in rows {
+                let (id, name, detail, created_at) = row?;
+                if let Some(capability) = Capability::from_name(&name) {
+                    entries.push(AuditEntry {
+                        id,
+                        capability,
+                        detail,
+                        created_at,
+                    });
+                }
+            }
+            Ok(AuditPage { entries, total })
+        })
+        .map_err(AppError::from)
+}
@@ -0,0 +1,352 @@
+use std::fs;
+use std::io::{IsTerminal, Read};
+
+use chrono::{Local, TimeZone};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::local_api;
+use crate::settings::{self, Settings};
+
+/// Names the config directory, like `identifier` in tauri.conf.json, which this has to
+/// find without starting the app.
+const IDENTIFIER: &str = "com.aikeya.app";
+/// Exit status for a command line we couldn't make sense of.
+const USAGE_ERROR: i32 = 2;
+
+const USAGE: &str = "\
+Usage: aikeya <command> [options]
+
+Talks to the running app through its local API, which has to be turned on in settings
+unless the app was started with --headless.
+
+Commands:
+  ask [options] [text]       Ask the model and print the answer; reads the prompt
+                             from stdin when no text is given
+      --show                 Open the overlay with the prompt instead
+      --system <text>        Use a system prompt
+      --provider <id>        Use another provider, together with --model
+      --model <id>           Use another model, together with --provider
+  show | hide | toggle       Show, hide or toggle the overlay
+  history list [--limit <n>] List recent conversations
+  history export <id> [--format markdown|json|html]
+                             Print a conversation
+  help                       Show this message
+";
+
+enum Command {
+    Ask {
+        text: Option<String>,
+        system: Option<String>,
+        provider: Option<String>,
+        model: Option<String>,
+        show: bool,
+    },
+    Show,
+    Hide,
+    Toggle,
+    HistoryList {
+        limit: Option<u32>,
+    },
+    HistoryExport {
+        id: String,
+        format: String,
+    },
+    Help,
+}
+
+fn is_command(arg: &str) -> bool {
+    matches!(
+        arg,
+        "ask" | "show" | "hide" | "toggle" | "history" | "help" | "--help" | "-h"
+    )
+}
+
+/// The value after a flag like `--model`.
+fn value(flag: &str, args: &mut impl Iterator<Item = String>) -> Result<String, String> {
+    args.next().ok_or_else(|| format!("{flag} needs a value"))
+}
+
+fn parse(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let command = args.next().unwrap_or_default();
+    match command.as_str() {
+        "ask" => {
+            let (mut system, mut provider, mut model, mut show) = (None, None, None, false);
+            let mut words = Vec::new();
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "--show" => show = true,
+                    "--system" => system = Some(value(&arg, &mut args)?),
+                    "--provider" => provider = Some(value(&arg, &mut args)?),
+                    "--model" => model = Some(value(&arg, &mut args)?),
+                    "--" => words.extend(args.by_ref()),
+                    flag if flag.starts_with("--") => return Err(format!("Unknown option {flag}")),
+                    _ => words.push(arg),
+                }
+            }
+            if provider.is_some() != model.is_some() {
+                return Err("--provider and --model go together".to_string());
+            }
+            Ok(Command::Ask {
+                text: (!words.is_empty()).then(|| words.join(" ")),
+                system,
+                provider,
+                model,
+                show,
+            })
+        }
+        "show" | "hide" | "toggle" => {
+            if let Some(extra) = args.next() {
+                return Err(format!("{command} takes no arguments, got {extra}"));
+            }
+            Ok(match command.as_str() {
+                "show" => Command::Show,
+                "hide" => Command::Hide,
+                _ => Command::Toggle,
+            })
+        }
+        "history" => match args.next().as_deref() {
+            Some("list") => {
+                let mut limit = None;
+                while let Some(arg) = args.next() {
+                    match arg.as_str() {
+                        "--limit" => {
+                            let given = value(&arg, &mut args)?;
+                            limit =
+                                Some(given.parse().map_err(|_| {
+                                    format!("--limit must be a number, got {given}")
+                                })?);
+                        }
+                        other => return Err(format!("Unknown option {other}")),
+                    }
+                }
+                Ok(Command::HistoryList { limit })
+            }
+            Some("export") => {
+                let (mut id, mut format) = (None, "markdown".to_string());
+                while let Some(arg) = args.next() {
+                    match arg.as_str() {
+                        "--format" => {
+                            format = value(&arg, &mut args)?;
+                            if !matches!(format.as_str(), "markdown" | "json" | "html") {
+                                return Err(format!(
+                                    "--format must be markdown, json or html, got {format}"
+                                ));
+                            }
+                        }
+                        flag if flag.starts_with("--") => {
+                            return Err(format!("Unknown option {flag}"))
+                        }
+                        _ if id.is_none() => id = Some(arg),
+                        _ => return Err(format!("Unexpected argument {arg}")),
+                    }
+                }
+                let id = id.ok_or_else(|| "history export needs a conversation id".to_string())?;
+                Ok(Command::HistoryExport { id, format })
+            }
+            Some(other) => Err(format!("Unknown history command {other}")),
+            None => Err("history needs list or export".to_string()),
+        },
+        _ => Ok(Command::Help),
+    }
+}
+
+/// The running app's local API, as its settings and token describe it.
+struct Client {
+    base: String,
+    token: String,
+    http: reqwest::Client,
+}
+
+impl Client {
+    fn connect() -> Result<Self, String> {
+        let path = dirs::config_dir()
+            .ok_or_else(|| "Could not find the config directory".to_string())?
+            .join(IDENTIFIER)
+            .join(settings::SETTINGS_FILE);
+        let settings = match fs::read_to_string(&path) {
+            Ok(contents) => settings::parse(&contents)?,
+            Err(_) => Settings::default(),
+        };
+        let token = local_api::saved_token()?.ok_or_else(|| {
+            "No API token yet; start the app with the local API on first".to_string()
+        })?;
+        Ok(Self {
+            base: format!("http://127.0.0.1:{}", settings.api.port),
+            token,
+            // A system proxy has no business seeing requests to localhost.
+            http: reqwest::Client::builder()
+                .no_proxy()
+                .build()
+                .map_err(|e| e.to_string())?,
+        })
+    }
+
+    async fn send(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        body: Option<Value>,
+    ) -> Result<reqwest::Response, String> {
+        let mut request = self
+            .http
+            .request(method, format!("{}{path}", self.base))
+            .bearer_auth(&self.token);
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+        let response = request.send().await.map_err(|error| {
+            if error.is_connect() {
+                format!(
+                    "Nothing answered on {}; is Aikeya running, with the local API on or \
+                     --headless?",
+                    self.base
+                )
+            } else {
+                error.to_string()
+            }
+        })?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let message = response
+            .json::<Value>()
+            .await
+            .ok()
+            .and_then(|body| body["error"].as_str().map(str::to_string))
+            .unwrap_or_else(|| status.to_string());
+        Err(message)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Listed {
+    id: String,
+    title: String,
+    updated_at: i64,
+}
+
+#[derive(Deserialize)]
+struct Page {
+    conversations: Vec<Listed>,
+}
+
+fn read_stdin() -> Result<String, String> {
+    let mut stdin = std::io::stdin();
+    if stdin.is_terminal() {
+        return Err("ask needs a prompt, as arguments or on stdin".to_string());
+    }
+    let mut text = String::new();
+    stdin.read_to_string(&mut text).map_err(|e| e.to_string())?;
+    Ok(text)
+}
+
+async fn execute(command: Command) -> Result<(), String> {
+    use reqwest::Method;
+
+    if let Command::Help = command {
+        print!("{USAGE}");
+        return Ok(());
+    }
+    let client = Client::connect()?;
+    match command {
+        Command::Ask {
+            text,
+            system,
+            provider,
+            model,
+            show,
+        } => {
+            let text = match text {
+                Some(text) => text,
+                None => read_stdin()?,
+            };
+            let body = json!({
+                "text": text,
+                "system": system,
+                "provider": provider,
+                "model": model,
+                "show": show,
+            });
+            let response = client.send(Method::POST, "/ask", Some(body)).await?;
+            if !show {
+                let answer: Value = response.json().await.map_err(|e| e.to_string())?;
+                println!("{}", answer["content"].as_str().unwrap_or_default());
+            }
+        }
+        Command::Show => {
+            client.send(Method::POST, "/show", None).await?;
+        }
+        Command::Hide => {
+            client.send(Method::POST, "/hide", None).await?;
+        }
+        Command::Toggle => {
+            let response = client.send(Method::POST, "/toggle", None).await?;
+            let state: Value = response.json().await.map_err(|e| e.to_string())?;
+            let visible = state["visible"].as_bool().unwrap_or_default();
+            println!("{}", if visible { "shown" } else { "hidden" });
+        }
+        Command::HistoryList { limit } => {
+            let path = match limit {
+                Some(limit) => format!("/history?limit={limit}"),
+                None => "/history".to_string(),
+            };
+            let response = client.send(Method::GET, &path, None).await?;
+            let page: Page = response.json().await.map_err(|e| e.to_string())?;
+            for conversation in page.conversations {
+                let updated = Local
+                    .timestamp_millis_opt(conversation.updated_at)
+                    .single()
+                    .map(|time| time.format("%Y-%m-%d %H:%M").to_string())
+                    .unwrap_or_default();
+                println!("{}\t{updated}\t{}", conversation.id, conversation.title);
+            }
+        }
+        Command::HistoryExport { id, format } => {
+            let path = format!("/history/{id}/export?format={format}");
+            let response = client.send(Method::GET, &path, None).await?;
+            let text = response.text().await.map_err(|e| e.to_string())?;
+            print!("{text}");
+        }
+        Command::Help => {}
+    }
+    Ok(())
+}
+
+/// Release builds are GUI programs on Windows, with no console to print to; borrow the
+/// one of the shell that ran us.
+#[cfg(windows)]
+fn attach_console() {
+    use windows::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+
+    // Fails harmlessly when there is already a console, as in debug builds.
+    let _ = unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+}
+
+/// Run a command like `aikeya ask "…"` against the running app, if that is what we were
+/// launched with, returning the exit status. `None` for a normal launch, including one
+/// with a deep link or the autostart flag.
+pub fn run() -> Option<i32> {
+    let mut args = std::env::args().skip(1).peekable();
+    if !args.peek().is_some_and(|arg| is_command(arg)) {
+        return None;
+    }
+    #[cfg(windows)]
+    attach_console();
+    let command = match parse(args) {
+        Ok(command) => command,
+        Err(error) => {
+            eprintln!("aikeya: {error}\nRun `aikeya help` for usage.");
+            return Some(USAGE_ERROR);
+        }
+    };
+    match tauri::async_runtime::block_on(execute(command)) {
+        Ok(()) => Some(0),
+        Err(error) => {
+            eprintln!("aikeya: {error}");
+            Some(1)
+        }
+    }
+}
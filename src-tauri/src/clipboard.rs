@@ -0,0 +1,177 @@
+use std::borrow::Cow;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use arboard::{Clipboard, ImageData};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
+use tauri::Manager;
+
+use crate::error::AppError;
+use crate::imaging;
+
+/// Lazily opened system clipboard. It is kept alive for the whole session because on
+/// Linux the clipboard contents we set disappear once the owning handle is dropped.
+#[derive(Default)]
+pub struct ClipboardState {
+    clipboard: Mutex<Option<Clipboard>>,
+    /// Open `Borrow`s; while any is held the clipboard holds our own scratch content.
+    borrows: AtomicUsize,
+    /// Bumped whenever a borrow ends.
+    generation: AtomicU64,
+}
+
+impl ClipboardState {
+    fn with<T>(
+        &self,
+        f: impl FnOnce(&mut Clipboard) -> Result<T, arboard::Error>,
+    ) -> Result<T, String> {
+        let mut guard = self.clipboard.lock().unwrap();
+        if guard.is_none() {
+            *guard = Some(Clipboard::new().map_err(|e| e.to_string())?);
+        }
+        f(guard.as_mut().unwrap()).map_err(|e| e.to_string())
+    }
+}
+
+/// Clipboard payload exchanged with the webview. Images travel as base64-encoded PNG.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ClipboardContent {
+    Text {
+        text: String,
+    },
+    Image {
+        data: String,
+        width: u32,
+        height: u32,
+    },
+    Empty,
+}
+
+fn decode_png(data: &str) -> Result<RgbaImage, String> {
+    let bytes = BASE64.decode(data).map_err(|e| e.to_string())?;
+    let image = image::load_from_memory(&bytes).map_err(|e| e.to_string())?;
+    Ok(image.to_rgba8())
+}
+
+/// The image on the clipboard, if there is one.
+pub fn read_image(app: &tauri::AppHandle) -> Result<Option<RgbaImage>, String> {
+    let Ok(image) = app.state::<ClipboardState>().with(|c| c.get_image()) else {
+        return Ok(None);
+    };
+    RgbaImage::from_raw(
+        image.width as u32,
+        image.height as u32,
+        image.bytes.into_owned(),
+    )
+    .map(Some)
+    .ok_or_else(|| "Clipboard image does not match its dimensions".to_string())
+}
+
+/// Read the clipboard, preferring text and falling back to an image.
+pub fn read(app: &tauri::AppHandle) -> Result<ClipboardContent, String> {
+    let state = app.state::<ClipboardState>();
+    if let Ok(text) = state.with(|c| c.get_text()) {
+        if !text.is_empty() {
+            return Ok(ClipboardContent::Text { text });
+        }
+    }
+    let Some(image) = read_image(app)? else {
+        return Ok(ClipboardContent::Empty);
+    };
+    let png = imaging::encode_png(&image)?;
+    Ok(ClipboardContent::Image {
+        data: BASE64.encode(png),
+        width: image.width(),
+        height: image.height(),
+    })
+}
+
+/// The X11/Wayland PRIMARY selection: whatever text is currently highlighted.
+#[cfg(target_os = "linux")]
+pub fn read_primary_selection(app: &tauri::AppHandle) -> Option<String> {
+    use arboard::{GetExtLinux, LinuxClipboardKind};
+
+    app.state::<ClipboardState>()
+        .with(|c| c.get().clipboard(LinuxClipboardKind::Primary).text())
+        .ok()
+}
+
+pub fn write(app: &tauri::AppHandle, content: &ClipboardContent) -> Result<(), String> {
+    let state = app.state::<ClipboardState>();
+    match content {
+        ClipboardContent::Text { text } => state.with(|c| c.set_text(text.as_str())),
+        ClipboardContent::Image { data, .. } => {
+            let image = decode_png(data)?;
+            let (width, height) = image.dimensions();
+            state.with(|c| {
+                c.set_image(ImageData {
+                    width: width as usize,
+                    height: height as usize,
+                    bytes: Cow::Owned(image.into_raw()),
+                })
+            })
+        }
+        ClipboardContent::Empty => state.with(|c| c.clear()),
+    }
+}
+
+/// Marks the clipboard as temporarily used by us, e.g. to simulate a copy or a paste, so
+/// the history watcher doesn't record what passes through it.
+pub struct Borrow(tauri::AppHandle);
+
+impl Drop for Borrow {
+    fn drop(&mut self) {
+        let state = self.0.state::<ClipboardState>();
+        state.generation.fetch_add(1, Ordering::SeqCst);
+        state.borrows.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+pub fn borrow(app: &tauri::AppHandle) -> Borrow {
+    app.state::<ClipboardState>()
+        .borrows
+        .fetch_add(1, Ordering::SeqCst);
+    Borrow(app.clone())
+}
+
+/// `None` while a `Borrow` is held, otherwise the number of borrows that have ended so
+/// far. Callers compare it between polls to notice clipboard changes that were ours.
+pub fn borrow_generation(app: &tauri::AppHandle) -> Option<u64> {
+    let state = app.state::<ClipboardState>();
+    if state.borrows.load(Ordering::SeqCst) > 0 {
+        return None;
+    }
+    Some(state.generation.load(Ordering::SeqCst))
+}
+
+/// A cheap hash of the clipboard's text or raw image pixels, for change detection without
+/// encoding images.
+pub fn fingerprint(app: &tauri::AppHandle) -> Option<u64> {
+    let state = app.state::<ClipboardState>();
+    let mut hasher = DefaultHasher::new();
+    match state.with(|c| c.get_text()) {
+        Ok(text) if !text.is_empty() => text.hash(&mut hasher),
+        _ => {
+            let image = state.with(|c| c.get_image()).ok()?;
+            (image.width, image.height).hash(&mut hasher);
+            image.bytes.hash(&mut hasher);
+        }
+    }
+    Some(hasher.finish())
+}
+
+#[tauri::command]
+pub fn read_clipboard(app: tauri::AppHandle) -> Result<ClipboardContent, AppError> {
+    read(&app).map_err(AppError::from)
+}
+
+#[tauri::command]
+pub fn write_clipboard(app: tauri::AppHandle, content: ClipboardContent) -> Result<(), AppError> {
+    write(&app, &content).map_err(AppError::from)
+}
@@ -0,0 +1,214 @@
+use std::thread;
+use std::time::Duration;
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::Serialize;
+use tauri::{Emitter, Manager, State};
+
+use crate::clipboard::{self, ClipboardContent};
+use crate::db::{self, Database};
+use crate::error::AppError;
+use crate::settings;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+const DEFAULT_PAGE_SIZE: u32 = 50;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClipboardEntry {
+    pub id: String,
+    pub content: ClipboardContent,
+    pub pinned: bool,
+    /// When this content was last copied.
+    pub created_at: i64,
+}
+
+const ENTRY_COLUMNS: &str = "id, kind, content, width, height, pinned, created_at";
+
+fn entry_from_row(row: &Row) -> rusqlite::Result<ClipboardEntry> {
+    let kind: String = row.get(1)?;
+    let content = match kind.as_str() {
+        "image" => ClipboardContent::Image {
+            data: row.get(2)?,
+            width: row.get(3)?,
+            height: row.get(4)?,
+        },
+        _ => ClipboardContent::Text { text: row.get(2)? },
+    };
+    Ok(ClipboardEntry {
+        id: row.get(0)?,
+        content,
+        pinned: row.get(5)?,
+        created_at: row.get(6)?,
+    })
+}
+
+/// Store `content` as the newest entry. Copying something already in the history moves
+/// that entry to the top instead of adding a duplicate. Unpinned entries beyond `limit`
+/// are dropped, oldest first.
+fn record(
+    conn: &mut Connection,
+    content: &ClipboardContent,
+    limit: u32,
+) -> rusqlite::Result<Option<ClipboardEntry>> {
+    let (kind, data, width, height) = match content {
+        ClipboardContent::Text { text } if !text.trim().is_empty() => ("text", text, None, None),
+        ClipboardContent::Image {
+            data,
+            width,
+            height,
+        } => ("image", data, Some(*width), Some(*height)),
+        _ => return Ok(None),
+    };
+    let now = db::now_ms();
+    let tx = conn.transaction()?;
+    let existing: Option<String> = tx
+        .query_row(
+            "SELECT id FROM clipboard_entries WHERE kind = ?1 AND content = ?2",
+            params![kind, data],
+            |row| row.get(0),
+        )
+        .optional()?;
+    let id = match existing {
+        Some(id) => {
+            tx.execute(
+                "UPDATE clipboard_entries SET created_at = ?2 WHERE id = ?1",
+                params![id, now],
+            )?;
+            id
+        }
+        None => {
+            let id = uuid::Uuid::new_v4().to_string();
+            tx.execute(
+                "INSERT INTO clipboard_entries (id, kind, content, width, height, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![id, kind, data, width, height, now],
+            )?;
+            id
+        }
+    };
+    tx.execute(
+        "DELETE FROM clipboard_entries WHERE pinned = 0 AND id NOT IN (
+            SELECT id FROM clipboard_entries WHERE pinned = 0
+            ORDER BY created_at DESC LIMIT ?1
+        )",
+        [limit],
+    )?;
+    let entry = tx.query_row(
+        &format!("SELECT {ENTRY_COLUMNS} FROM clipboard_entries WHERE id = ?1"),
+        [id],
+        entry_from_row,
+    )?;
+    tx.commit()?;
+    Ok(Some(entry))
+}
+
+/// Poll the clipboard for as long as the app runs, recording each new copy while the
+/// history is enabled. Content we put there ourselves while simulating a copy or paste
+/// is skipped.
+pub fn start_watcher(app: &tauri::AppHandle) {
+    let app = app.clone();
+    let spawned = thread::Builder::new()
+        .name("clipboard-watcher".to_string())
+        .spawn(move || {
+            let mut last_fingerprint = None;
+            let mut last_generation = None;
+            loop {
+                thread::sleep(POLL_INTERVAL);
+                let settings = settings::current(&app).clipboard;
+                if !settings.history_enabled {
+                    last_fingerprint = None;
+                    continue;
+                }
+                let Some(generation) = clipboard::borrow_generation(&app) else {
+                    continue;
+                };
+                let fingerprint = clipboard::fingerprint(&app);
+                // A borrow that started while we were reading makes the read worthless.
+                if clipboard::borrow_generation(&app) != Some(generation) {
+                    continue;
+                }
+                let ours = last_generation.is_some_and(|last| last != generation);
+                let changed = fingerprint.is_some() && fingerprint != last_fingerprint;
+                last_generation = Some(generation);
+                last_fingerprint = fingerprint;
+                if ours || !changed {
+                    continue;
+                }
+                let Ok(content) = clipboard::read(&app) else {
+                    continue;
+                };
+                let db = app.state::<Database>();
+                match db.with(|conn| record(conn, &content, settings.history_limit)) {
+                    Ok(Some(entry)) => {
+                        let _ = app.emit("clipboard-history-changed", entry);
+                    }
+                    Ok(None) => {}
+                    Err(error) => tracing::error!("Failed to record clipboard entry: {error}"),
+                }
+            }
+        });
+    if let Err(error) = spawned {
+        tracing::error!("Failed to start the clipboard watcher: {error}");
+    }
+}
+
+/// Pinned entries first, then the most recently copied.
+#[tauri::command]
+pub async fn list_clipboard_history(
+    db: State<'_, Database>,
+    limit: Option<u32>,
+    offset: Option<u32>,
+) -> Result<Vec<ClipboardEntry>, AppError> {
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).min(500);
+    let offset = offset.unwrap_or(0);
+    db.with(|conn| {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {ENTRY_COLUMNS} FROM clipboard_entries
+             ORDER BY pinned DESC, created_at DESC LIMIT ?1 OFFSET ?2"
+        ))?;
+        let entries = stmt
+            .query_map(params![limit, offset], entry_from_row)?
+            .collect();
+        entries
+    })
+    .map_err(AppError::from)
+}
+
+/// Pinned entries survive trimming and `clear_clipboard_history`. Returns whether the
+/// entry exists.
+#[tauri::command]
+pub async fn pin_clipboard_entry(
+    db: State<'_, Database>,
+    id: String,
+    pinned: Option<bool>,
+) -> Result<bool, AppError> {
+    let pinned = pinned.unwrap_or(true);
+    db.with(|conn| {
+        conn.execute(
+            "UPDATE clipboard_entries SET pinned = ?2 WHERE id = ?1",
+            params![id, pinned],
+        )
+    })
+    .map(|changed| changed > 0)
+    .map_err(AppError::from)
+}
+
+pub fn clear(conn: &Connection, include_pinned: bool) -> rusqlite::Result<usize> {
+    conn.execute(
+        "DELETE FROM clipboard_entries WHERE pinned = 0 OR ?1",
+        [include_pinned],
+    )
+}
+
+/// Delete the history, keeping pinned entries unless `include_pinned` is set. Returns the
+/// number of entries deleted.
+#[tauri::command]
+pub async fn clear_clipboard_history(
+    db: State<'_, Database>,
+    include_pinned: Option<bool>,
+) -> Result<usize, AppError> {
+    let include_pinned = include_pinned.unwrap_or(false);
+    db.with(|conn| clear(conn, include_pinned))
+        .map_err(AppError::from)
+}
@@ -0,0 +1,229 @@
+use std::fs;
+use std::path::PathBuf;
+
+use rusqlite::OptionalExtension;
+use serde::Serialize;
+use tauri::{Manager, State};
+
+use crate::capabilities;
+use crate::db::Database;
+use crate::error::AppError;
+use crate::persist;
+
+/// A fence may be indented by up to this many spaces and still open a block.
+const MAX_FENCE_INDENT: usize = 3;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CodeBlock {
+    /// `message-id:index`, for `save_code_block`.
+    pub id: String,
+    pub message_id: String,
+    pub index: usize,
+    pub language: Option<String>,
+    /// A file name given with the language, as in ```` ```rust src/main.rs ````,
+    /// ```` ```rust:src/main.rs ```` or ```` ```rust title="src/main.rs" ````.
+    pub path: Option<String>,
+    pub code: String,
+    /// Whether the block looks like a unified diff, for `apply_unified_diff`.
+    pub is_diff: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedCodeBlock {
+    pub path: PathBuf,
+    pub bytes: usize,
+}
+
+struct Fence {
+    marker: char,
+    len: usize,
+    indent: usize,
+    info: String,
+}
+
+fn opening_fence(line: &str) -> Option<Fence> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    if indent > MAX_FENCE_INDENT {
+        return None;
+    }
+    let rest = &line[indent..];
+    let marker = rest.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let len = rest.len() - rest.trim_start_matches(marker).len();
+    let info = rest[len..].trim();
+    // A backtick in the info string would make it inline code instead.
+    if len < 3 || (marker == '`' && info.contains('`')) {
+        return None;
+    }
+    Some(Fence {
+        marker,
+        len,
+        indent,
+        info: info.to_string(),
+    })
+}
+
+fn closes(fence: &Fence, line: &str) -> bool {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > MAX_FENCE_INDENT {
+        return false;
+    }
+    let len = trimmed.len() - trimmed.trim_start_matches(fence.marker).len();
+    len >= fence.len && trimmed[len..].trim().is_empty()
+}
+
+fn looks_like_path(text: &str) -> bool {
+    !text.is_empty() && (text.contains('.') || text.contains('/')) && !text.contains('=')
+}
+
+/// The language and file name in a fence's info string.
+fn parse_info(info: &str) -> (Option<String>, Option<String>) {
+    let mut words = info.split_whitespace();
+    let Some(first) = words.next() else {
+        return (None, None);
+    };
+    let (language, mut path) = match first.split_once(':') {
+        Some((language, path)) if looks_like_path(path) => (language, Some(path.to_string())),
+        _ => (first, None),
+    };
+    for word in words {
+        if path.is_some() {
+            break;
+        }
+        let value = ["title=", "file=", "filename=", "path="]
+            .iter()
+            .find_map(|key| word.strip_prefix(key))
+            .map(|value| value.trim_matches(|c| c == '"' || c == '\''));
+        match value {
+            Some(value) if !value.is_empty() => path = Some(value.to_string()),
+            None if looks_like_path(word) => path = Some(word.to_string()),
+            _ => {}
+        }
+    }
+    let language = (!language.is_empty()).then(|| language.to_lowercase());
+    (language, path)
+}
+
+fn is_diff(language: Option<&str>, code: &str) -> bool {
+    if matches!(language, Some("diff" | "patch" | "udiff")) {
+        return true;
+    }
+    let start = code.trim_start();
+    (start.starts_with("--- ") || start.starts_with("diff --git "))
+        && code.contains("\n+++ ")
+        && code.contains("\n@@ ")
+}
+
+/// The fenced code blocks in Markdown `text`, in order, as language, path and code. A
+/// fence left open runs to the end, as it does while a response is still streaming.
+pub fn parse(text: &str) -> Vec<(Option<String>, Option<String>, String)> {
+    let mut blocks = Vec::new();
+    let mut open: Option<(Fence, Vec<&str>)> = None;
+    for line in text.lines() {
+        let Some((fence, lines)) = &mut open else {
+            open = opening_fence(line).map(|fence| (fence, Vec::new()));
+            continue;
+        };
+        if closes(fence, line) {
+            blocks.extend(open.take());
+            continue;
+        }
+        // Content is unindented by as much as its fence was.
+        let strip = line.len() - line.trim_start_matches(' ').len();
+        lines.push(&line[strip.min(fence.indent)..]);
+    }
+    blocks.extend(open);
+    blocks
+        .into_iter()
+        .map(|(fence, lines)| {
+            let (language, path) = parse_info(&fence.info);
+            let mut code = lines.join("\n");
+            if !code.is_empty() {
+                code.push('\n');
+            }
+            (language, path, code)
+        })
+        .collect()
+}
+
+fn message_blocks(db: &Database, message_id: &str) -> Result<Vec<CodeBlock>, AppError> {
+    let content: Option<String> = db.with(|conn| {
+        conn.query_row(
+            "SELECT content FROM messages WHERE id = ?1",
+            [message_id],
+            |row| row.get(0),
+        )
+        .optional()
+    })?;
+    let content =
+        content.ok_or_else(|| AppError::NotFound(format!("Message {message_id} not found")))?;
+    Ok(parse(&content)
+        .into_iter()
+        .enumerate()
+        .map(|(index, (language, path, code))| CodeBlock {
+            id: format!("{message_id}:{index}"),
+            message_id: message_id.to_string(),
+            index,
+            is_diff: is_diff(language.as_deref(), &code),
+            language,
+            path,
+            code,
+        })
+        .collect())
+}
+
+/// The fenced code blocks in a saved message, in order.
+#[tauri::command]
+pub async fn extract_code_blocks(
+    db: State<'_, Database>,
+    message_id: String,
+) -> Result<Vec<CodeBlock>, AppError> {
+    message_blocks(&db, &message_id)
+}
+
+/// Write a code block from `extract_code_blocks` to `path`, which must be absolute. An
+/// existing file is only replaced with `overwrite`.
+#[tauri::command]
+pub async fn save_code_block(
+    app: tauri::AppHandle,
+    id: String,
+    path: PathBuf,
+    overwrite: Option<bool>,
+) -> Result<SavedCodeBlock, AppError> {
+    let (message_id, index) = id
+        .rsplit_once(':')
+        .and_then(|(message_id, index)| Some((message_id, index.parse::<usize>().ok()?)))
+        .ok_or_else(|| AppError::InvalidInput(format!("Invalid code block id {id}")))?;
+    if !path.is_absolute() {
+        return Err(AppError::InvalidInput(
+            "The path to save to must be absolute".to_string(),
+        ));
+    }
+    let block = message_blocks(&app.state::<Database>(), message_id)?
+        .into_iter()
+        .nth(index)
+        .ok_or_else(|| AppError::NotFound(format!("Code block {id} not found")))?;
+    capabilities::require_path(&app, &path, "Save a code block").await?;
+    if path.is_dir() {
+        return Err(AppError::InvalidInput(format!(
+            "{} is a folder",
+            path.display()
+        )));
+    }
+    if path.exists() && !overwrite.unwrap_or(false) {
+        return Err(AppError::InvalidInput(format!(
+            "{} already exists",
+            path.display()
+        )));
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    persist::write_atomic(&path, block.code.as_bytes())?;
+    tracing::info!("Saved code block {id} to {}", path.display());
+    Ok(SavedCodeBlock {
+        path,
+        bytes: block.code.len(),
+    })
+}
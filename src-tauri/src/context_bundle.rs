@@ -0,0 +1,167 @@
+use serde::Serialize;
+
+use crate::capabilities::{self, Capability};
+use crate::clipboard::{self, ClipboardContent};
+use crate::error::AppError;
+use crate::focus::{self, AppContext};
+use crate::screenshot::{self, Screenshot};
+use crate::settings::{self, ContextBundleSettings};
+use crate::window_manager::{self, AppWindow};
+use crate::{db, selection};
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ContextItem {
+    App,
+    WindowTitle,
+    Selection,
+    Clipboard,
+    Screenshot,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextItemError {
+    pub item: ContextItem,
+    pub message: String,
+}
+
+/// Everything about what the user is looking at, for asking about it in one go.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextBundle {
+    /// `None` when nothing but our own windows is in front.
+    pub app: Option<AppContext>,
+    /// `None` when nothing is selected.
+    pub selection: Option<String>,
+    pub clipboard: Option<ClipboardContent>,
+    /// Of the window in front.
+    pub screenshot: Option<Screenshot>,
+    /// What `contextBundle` in settings left out.
+    pub skipped: Vec<ContextItem>,
+    /// What was wanted but couldn't be had.
+    pub errors: Vec<ContextItemError>,
+    pub captured_at: i64,
+}
+
+fn skipped(wanted: &ContextBundleSettings) -> Vec<ContextItem> {
+    [
+        (wanted.app, ContextItem::App),
+        (wanted.window_title, ContextItem::WindowTitle),
+        (wanted.selection, ContextItem::Selection),
+        (wanted.clipboard, ContextItem::Clipboard),
+        (wanted.screenshot, ContextItem::Screenshot),
+    ]
+    .into_iter()
+    .filter(|(on, _)| !on)
+    .map(|(_, item)| item)
+    .collect()
+}
+
+/// Blocks while the selection is copied, so it runs off the async runtime.
+fn gather(
+    app: &tauri::AppHandle,
+    wanted: &ContextBundleSettings,
+    screenshot_allowed: Option<Result<(), AppError>>,
+) -> ContextBundle {
+    let mut errors = Vec::new();
+    // Called from the overlay, what matters is the app it was summoned over.
+    let from_overlay = window_manager::is_focused(app, AppWindow::Overlay);
+
+    let app_context = (wanted.app || wanted.window_title)
+        .then(|| {
+            if from_overlay {
+                focus::previous_context(app)
+            } else {
+                focus::current_context()
+            }
+        })
+        .flatten()
+        .map(|context| match (wanted.app, wanted.window_title) {
+            (true, true) => context,
+            (true, false) => AppContext {
+                window_title: None,
+                ..context
+            },
+            _ => AppContext {
+                window_title: context.window_title,
+                ..AppContext::default()
+            },
+        });
+
+    let clipboard = if wanted.clipboard {
+        match clipboard::read(app) {
+            Ok(ClipboardContent::Empty) => None,
+            Ok(content) => Some(content),
+            Err(message) => {
+                errors.push(ContextItemError {
+                    item: ContextItem::Clipboard,
+                    message,
+                });
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    // Before the selection, whose clipboard fallback may briefly disturb the window.
+    let screenshot = match screenshot_allowed {
+        Some(Ok(())) => screenshot::capture_window(app)
+            .map_err(|message| {
+                errors.push(ContextItemError {
+                    item: ContextItem::Screenshot,
+                    message,
+                })
+            })
+            .ok(),
+        Some(Err(error)) => {
+            errors.push(ContextItemError {
+                item: ContextItem::Screenshot,
+                message: error.to_string(),
+            });
+            None
+        }
+        None => None,
+    };
+
+    let selection = if !wanted.selection {
+        None
+    } else if from_overlay {
+        selection::cached(app)
+    } else {
+        selection::capture(app)
+    };
+
+    ContextBundle {
+        app: app_context,
+        selection: selection.filter(|text| !text.trim().is_empty()),
+        clipboard,
+        screenshot,
+        skipped: skipped(wanted),
+        errors,
+        captured_at: db::now_ms(),
+    }
+}
+
+/// Gather the app and window in front, the selection, the clipboard and a screenshot of
+/// the window in one call, leaving out whatever `contextBundle` in settings turns off.
+/// An item that can't be had is listed in `errors` rather than failing the rest.
+#[tauri::command]
+pub async fn capture_context_bundle(app: tauri::AppHandle) -> Result<ContextBundle, AppError> {
+    let wanted = settings::current(&app).context_bundle;
+    // Asked here, since waiting on the user isn't possible once off the async runtime.
+    let screenshot_allowed = if wanted.screenshot {
+        Some(capabilities::require(&app, Capability::ScreenCapture, "Context bundle").await)
+    } else {
+        None
+    };
+    let bundle =
+        tauri::async_runtime::spawn_blocking(move || gather(&app, &wanted, screenshot_allowed))
+            .await?;
+    tracing::debug!(
+        "Captured a context bundle with {} errors",
+        bundle.errors.len()
+    );
+    Ok(bundle)
+}
@@ -0,0 +1,157 @@
+use std::any::Any;
+use std::backtrace::Backtrace;
+use std::collections::VecDeque;
+use std::fs;
+use std::panic::{self, Location};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+use crate::{db, persist};
+
+const CRASH_DIR: &str = "crashes";
+/// Reports kept on disk; older ones are deleted when a new one is written.
+const KEEP_REPORTS: usize = 10;
+const RECENT_COMMANDS: usize = 20;
+
+/// The last commands the frontend invoked, oldest first, for context in crash reports.
+/// A plain static because the panic hook can't reach managed state.
+static COMMANDS: Mutex<VecDeque<InvokedCommand>> = Mutex::new(VecDeque::new());
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InvokedCommand {
+    pub command: String,
+    pub at: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrashReport {
+    pub timestamp: i64,
+    pub message: String,
+    /// `file:line:column` of the panic.
+    pub location: Option<String>,
+    pub thread: Option<String>,
+    pub backtrace: String,
+    pub os: String,
+    pub arch: String,
+    pub app_version: String,
+    pub recent_commands: Vec<InvokedCommand>,
+}
+
+/// Note a command invocation for the next crash report.
+pub fn record_command(command: &str) {
+    let Ok(mut commands) = COMMANDS.lock() else {
+        return;
+    };
+    if commands.len() == RECENT_COMMANDS {
+        commands.pop_front();
+    }
+    commands.push_back(InvokedCommand {
+        command: command.to_string(),
+        at: db::now_ms(),
+    });
+}
+
+fn crash_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let dir = persist::data_file(app, CRASH_DIR)?;
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir)
+}
+
+/// Report files, newest first. Names are zero-padded timestamps, so they sort by age.
+fn reports(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort_unstable_by(|a, b| b.cmp(a));
+    files
+}
+
+fn report(
+    payload: &(dyn Any + Send),
+    location: Option<&Location>,
+    app_version: &str,
+) -> CrashReport {
+    let message = payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "Unknown panic".to_string());
+    // try_lock: the panic may have happened while the list was being updated.
+    let recent_commands = COMMANDS
+        .try_lock()
+        .map(|commands| commands.iter().cloned().collect())
+        .unwrap_or_default();
+    CrashReport {
+        timestamp: db::now_ms(),
+        message,
+        location: location.map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column())),
+        thread: std::thread::current().name().map(str::to_string),
+        backtrace: Backtrace::force_capture().to_string(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        app_version: app_version.to_string(),
+        recent_commands,
+    }
+}
+
+fn write_report(dir: &Path, report: &CrashReport) {
+    let path = dir.join(format!("crash-{:015}.json", report.timestamp));
+    if let Err(error) = persist::save_json(&path, report) {
+        tracing::error!("Failed to write crash report: {error}");
+        return;
+    }
+    for old in reports(dir).into_iter().skip(KEEP_REPORTS) {
+        let _ = fs::remove_file(old);
+    }
+}
+
+/// Write a crash report for any panic, then carry on with the default hook. Release
+/// builds abort on panic, so this is the only chance to record anything.
+pub fn install(app: &tauri::AppHandle) -> Result<(), String> {
+    let dir = crash_dir(app)?;
+    let app_version = app.package_info().version.to_string();
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let report = report(info.payload(), info.location(), &app_version);
+        tracing::error!(
+            "Panic at {}: {}",
+            report.location.as_deref().unwrap_or("unknown location"),
+            report.message
+        );
+        write_report(&dir, &report);
+        default_hook(info);
+    }));
+    Ok(())
+}
+
+/// The most recent crash report, if the app has crashed since they were last cleared.
+#[tauri::command]
+pub fn get_last_crash_report(app: tauri::AppHandle) -> Result<Option<CrashReport>, AppError> {
+    let dir = crash_dir(&app)?;
+    Ok(reports(&dir).into_iter().find_map(|path| {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+    }))
+}
+
+/// Delete every saved crash report, once the user has seen or sent them.
+#[tauri::command]
+pub fn clear_crash_reports(app: tauri::AppHandle) -> Result<usize, AppError> {
+    let dir = crash_dir(&app)?;
+    let files = reports(&dir);
+    for file in &files {
+        fs::remove_file(file).map_err(|e| e.to_string())?;
+    }
+    Ok(files.len())
+}
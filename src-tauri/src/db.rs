@@ -0,0 +1,60 @@
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rusqlite::{Connection, DatabaseName};
+
+use crate::{migrations, persist};
+
+const DATABASE_FILE: &str = "aikeya.db";
+
+/// The app's SQLite database. Queries are short, so a single connection behind a mutex
+/// is plenty; never hold the lock across an `.await`.
+pub struct Database(Mutex<Connection>);
+
+impl Database {
+    pub fn open(app: &tauri::AppHandle) -> Result<Self, String> {
+        let path = persist::data_file(app, DATABASE_FILE)?;
+        let mut conn = Connection::open(&path).map_err(|e| e.to_string())?;
+        conn.execute_batch("PRAGMA journal_mode = WAL; PRAGMA foreign_keys = ON;")
+            .map_err(|e| e.to_string())?;
+        migrations::run(&mut conn, Some(&path))?;
+        Ok(Self(Mutex::new(conn)))
+    }
+
+    /// Copy the whole database to a new file at `path`, consistent even while in use.
+    pub fn snapshot(&self, path: &Path) -> Result<(), String> {
+        let conn = self.0.lock().unwrap();
+        conn.backup(DatabaseName::Main, path, None)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Replace the contents of the database with the one at `path`, then bring its schema
+    /// up to date.
+    pub fn restore_from(&self, path: &Path) -> Result<(), String> {
+        let mut conn = self.0.lock().unwrap();
+        conn.restore(
+            DatabaseName::Main,
+            path,
+            None::<fn(rusqlite::backup::Progress)>,
+        )
+        .map_err(|e| e.to_string())?;
+        migrations::run(&mut conn, None)
+    }
+
+    pub fn with<T>(
+        &self,
+        f: impl FnOnce(&mut Connection) -> rusqlite::Result<T>,
+    ) -> Result<T, String> {
+        let mut conn = self.0.lock().unwrap();
+        f(&mut conn).map_err(|e| e.to_string())
+    }
+}
+
+/// Milliseconds since the Unix epoch, the timestamp format used in every table.
+pub fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+}
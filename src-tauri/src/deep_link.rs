@@ -0,0 +1,111 @@
+use std::sync::Mutex;
+
+use serde::Serialize;
+use tauri::{Emitter, Manager, State, Url};
+use tauri_plugin_deep_link::DeepLinkExt;
+
+use crate::window_manager::{self, AppWindow};
+
+pub const SCHEME: &str = "aikeya";
+
+/// A prompt from an `aikeya://ask` link that arrived before the overlay was listening,
+/// as happens when the link is what launched the app.
+#[derive(Default)]
+pub struct DeepLinkState(Mutex<Option<AskPayload>>);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AskPayload {
+    pub text: String,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OpenSettingsPayload<'a> {
+    /// The settings page to open, such as `display`; `None` for the first one.
+    section: Option<&'a str>,
+}
+
+/// Whether a command-line argument is one of our links rather than a regular flag.
+pub fn is_deep_link(arg: &str) -> bool {
+    arg.strip_prefix(SCHEME)
+        .is_some_and(|rest| rest.starts_with("://") || rest.starts_with(':'))
+}
+
+/// Show the overlay with `text` prefilled, via `deep-link-ask`. Also used by the local
+/// API, which hands prompts to the overlay the same way.
+pub fn ask(app: &tauri::AppHandle, text: String, at_launch: bool) -> Result<(), String> {
+    window_manager::show_overlay(app)?;
+    let payload = AskPayload { text };
+    if at_launch {
+        *app.state::<DeepLinkState>().0.lock().unwrap() = Some(payload.clone());
+    }
+    let _ = app.emit("deep-link-ask", payload);
+    Ok(())
+}
+
+fn handle(app: &tauri::AppHandle, url: &Url, at_launch: bool) {
+    // `aikeya://ask?…` puts the action in the host, `aikeya:ask?…` in the path.
+    let route = url.host_str().unwrap_or_default().to_string() + url.path();
+    let mut segments = route.split('/').filter(|s| !s.is_empty());
+    match segments.next() {
+        Some("ask") => {
+            let text = url
+                .query_pairs()
+                .find(|(key, _)| key == "text")
+                .map(|(_, value)| value.into_owned())
+                .unwrap_or_default();
+            window_manager::spawn(app, move |app| {
+                if let Err(error) = ask(app, text, at_launch) {
+                    tracing::error!("Failed to show overlay for a deep link: {error}");
+                }
+            });
+        }
+        Some("settings") => {
+            let section = segments.next().map(str::to_string);
+            window_manager::spawn(app, move |app| {
+                let opened = window_manager::emit_to(
+                    app,
+                    AppWindow::Settings,
+                    "open-settings",
+                    OpenSettingsPayload {
+                        section: section.as_deref(),
+                    },
+                )
+                .and_then(|()| window_manager::show(app, AppWindow::Settings));
+                if let Err(error) = opened {
+                    tracing::error!("Failed to open settings for a deep link: {error}");
+                }
+            });
+        }
+        _ => tracing::warn!("Ignoring unknown deep link {url}"),
+    }
+}
+
+/// Handle links opened while running, including ones forwarded by a second instance,
+/// and the link the app was launched with.
+pub fn init(app: &tauri::AppHandle) {
+    // Installers register the scheme; this covers dev builds and unintegrated AppImages.
+    #[cfg(any(windows, target_os = "linux"))]
+    if let Err(error) = app.deep_link().register_all() {
+        tracing::warn!("Could not register the {SCHEME}:// scheme: {error}");
+    }
+
+    let app_handle = app.clone();
+    app.deep_link().on_open_url(move |event| {
+        for url in event.urls() {
+            handle(&app_handle, &url, false);
+        }
+    });
+    if let Ok(Some(urls)) = app.deep_link().get_current() {
+        for url in urls {
+            handle(app, &url, true);
+        }
+    }
+}
+
+/// The prompt of the `aikeya://ask` link the app was launched with, once.
+#[tauri::command]
+pub fn take_pending_ask(state: State<'_, DeepLinkState>) -> Option<AskPayload> {
+    state.0.lock().unwrap().take()
+}
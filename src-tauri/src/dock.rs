@@ -0,0 +1,441 @@
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, Manager, Monitor, PhysicalPosition, PhysicalSize, WebviewWindow};
+
+use crate::error::AppError;
+use crate::layer_shell;
+use crate::screenshot::{self, Region};
+use crate::window_manager::{self, AppWindow};
+
+/// The narrowest a docked overlay is made, in physical pixels.
+const MIN_THICKNESS: u32 = 240;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DockEdge {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+impl DockEdge {
+    fn vertical(self) -> bool {
+        matches!(self, DockEdge::Left | DockEdge::Right)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DockedOverlay {
+    pub edge: DockEdge,
+    pub monitor_id: Option<String>,
+    pub bounds: Region,
+    /// Whether other windows keep clear of the panel, which only Windows and X11 allow.
+    pub reserved: bool,
+}
+
+struct Docked {
+    overlay: DockedOverlay,
+    reservation: Option<platform::Reservation>,
+    /// Where the overlay was before, to put it back when undocked.
+    before: (PhysicalPosition<i32>, PhysicalSize<u32>),
+}
+
+#[derive(Default)]
+pub struct DockState {
+    docked: Mutex<Option<Docked>>,
+}
+
+#[cfg(windows)]
+mod platform {
+    use tauri::WebviewWindow;
+    use windows::Win32::Foundation::{HWND, RECT};
+    use windows::Win32::UI::Shell::{
+        SHAppBarMessage, ABE_BOTTOM, ABE_LEFT, ABE_RIGHT, ABE_TOP, ABM_NEW, ABM_QUERYPOS,
+        ABM_REMOVE, ABM_SETPOS, APPBARDATA,
+    };
+
+    use super::DockEdge;
+    use crate::screenshot::Region;
+
+    /// The overlay's window handle, registered as an app bar.
+    pub struct Reservation(isize);
+
+    fn app_bar(hwnd: isize) -> APPBARDATA {
+        APPBARDATA {
+            cbSize: std::mem::size_of::<APPBARDATA>() as u32,
+            hWnd: HWND(hwnd as *mut _),
+            ..Default::default()
+        }
+    }
+
+    /// Register the overlay as an app bar along `edge` of `monitor`, which shrinks the
+    /// work area other windows maximize into. Returns where the shell lets it go, moved
+    /// clear of the taskbar and other app bars.
+    pub fn reserve(
+        window: &WebviewWindow,
+        edge: DockEdge,
+        monitor: Region,
+        thickness: u32,
+    ) -> Result<(Reservation, Region), String> {
+        let hwnd = window.hwnd().map_err(|e| e.to_string())?.0 as isize;
+        let mut data = app_bar(hwnd);
+        // No callback message: the panel doesn't follow the shell moving other app bars
+        // until it is docked again.
+        // SAFETY: `data` is a valid APPBARDATA that outlives the call.
+        if unsafe { SHAppBarMessage(ABM_NEW, &mut data) } == 0 {
+            return Err("The shell would not register the overlay as an app bar".to_string());
+        }
+        let (left, top) = (monitor.x, monitor.y);
+        let right = left + monitor.width as i32;
+        let bottom = top + monitor.height as i32;
+        let thickness = thickness as i32;
+        data.uEdge = match edge {
+            DockEdge::Left => ABE_LEFT,
+            DockEdge::Top => ABE_TOP,
+            DockEdge::Right => ABE_RIGHT,
+            DockEdge::Bottom => ABE_BOTTOM,
+        };
+        data.rc = RECT {
+            left,
+            top,
+            right,
+            bottom,
+        };
+        // SAFETY: as above.
+        unsafe { SHAppBarMessage(ABM_QUERYPOS, &mut data) };
+        // The shell moves the edge we asked for out of the way of others, so the far
+        // side has to be put back at our thickness.
+        let rc = &mut data.rc;
+        match edge {
+            DockEdge::Left => rc.right = rc.left + thickness,
+            DockEdge::Top => rc.bottom = rc.top + thickness,
+            DockEdge::Right => rc.left = rc.right - thickness,
+            DockEdge::Bottom => rc.top = rc.bottom - thickness,
+        }
+        // SAFETY: as above.
+        unsafe { SHAppBarMessage(ABM_SETPOS, &mut data) };
+        let rc = data.rc;
+        Ok((
+            Reservation(hwnd),
+            Region {
+                x: rc.left,
+                y: rc.top,
+                width: (rc.right - rc.left).max(0) as u32,
+                height: (rc.bottom - rc.top).max(0) as u32,
+            },
+        ))
+    }
+
+    pub fn release(reservation: Reservation) {
+        let mut data = app_bar(reservation.0);
+        // SAFETY: `data` is a valid APPBARDATA that outlives the call.
+        unsafe { SHAppBarMessage(ABM_REMOVE, &mut data) };
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use tauri::WebviewWindow;
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xproto::{AtomEnum, ConnectionExt, PropMode, Window};
+    use x11rb::rust_connection::RustConnection;
+    use x11rb::wrapper::ConnectionExt as _;
+
+    use super::DockEdge;
+    use crate::screenshot::Region;
+    use crate::x11;
+
+    /// The overlay's X11 window, which has struts set on it.
+    pub struct Reservation(Window);
+
+    /// Our windows all share a class, so the overlay is told apart by its title.
+    fn overlay_window(conn: &RustConnection, root: Window, title: &str) -> Option<Window> {
+        let own_pid = std::process::id();
+        let clients = x11::property_u32s(conn, root, x11::atom(conn, "_NET_CLIENT_LIST").ok()?);
+        clients.into_iter().find(|&window| {
+            x11::window_pid(conn, window) == Some(own_pid)
+                && x11::window_title(conn, window).as_deref() == Some(title)
+        })
+    }
+
+    /// Set `_NET_WM_STRUT_PARTIAL` on the overlay so the window manager keeps other
+    /// windows clear of `bounds`. Struts are measured from the edges of the whole
+    /// screen, and Wayland has nothing like them for ordinary windows.
+    pub fn reserve(
+        window: &WebviewWindow,
+        edge: DockEdge,
+        bounds: Region,
+        _thickness: u32,
+    ) -> Result<(Reservation, Region), String> {
+        let title = window.title().map_err(|e| e.to_string())?;
+        let (conn, root) = x11::connect_for_windows()?;
+        let screen = conn
+            .get_geometry(root)
+            .map_err(|e| e.to_string())?
+            .reply()
+            .map_err(|e| e.to_string())?;
+        let target = overlay_window(&conn, root, &title)
+            .ok_or_else(|| "Could not find the overlay's X11 window".to_string())?;
+
+        let (x, y) = (bounds.x.max(0) as u32, bounds.y.max(0) as u32);
+        let (right, bottom) = (x + bounds.width, y + bounds.height);
+        let (screen_width, screen_height) = (u32::from(screen.width), u32::from(screen.height));
+        // left, right, top, bottom, then the start and end of each along its edge.
+        let mut strut = [0u32; 12];
+        match edge {
+            DockEdge::Left => {
+                strut[0] = right;
+                strut[4] = y;
+                strut[5] = bottom.saturating_sub(1);
+            }
+            DockEdge::Right => {
+                strut[1] = screen_width.saturating_sub(x);
+                strut[6] = y;
+                strut[7] = bottom.saturating_sub(1);
+            }
+            DockEdge::Top => {
+                strut[2] = bottom;
+                strut[8] = x;
+                strut[9] = right.saturating_sub(1);
+            }
+            DockEdge::Bottom => {
+                strut[3] = screen_height.saturating_sub(y);
+                strut[10] = x;
+                strut[11] = right.saturating_sub(1);
+            }
+        }
+        let partial = x11::atom(&conn, "_NET_WM_STRUT_PARTIAL")?;
+        let full = x11::atom(&conn, "_NET_WM_STRUT")?;
+        conn.change_property32(
+            PropMode::REPLACE,
+            target,
+            partial,
+            AtomEnum::CARDINAL,
+            &strut,
+        )
+        .map_err(|e| e.to_string())?;
+        // For window managers that predate the partial kind.
+        conn.change_property32(
+            PropMode::REPLACE,
+            target,
+            full,
+            AtomEnum::CARDINAL,
+            &strut[..4],
+        )
+        .map_err(|e| e.to_string())?;
+        conn.flush().map_err(|e| e.to_string())?;
+        Ok((Reservation(target), bounds))
+    }
+
+    pub fn release(reservation: Reservation) {
+        let Ok((conn, _)) = x11::connect() else {
+            return;
+        };
+        for name in ["_NET_WM_STRUT_PARTIAL", "_NET_WM_STRUT"] {
+            if let Ok(atom) = x11::atom(&conn, name) {
+                let _ = conn.delete_property(reservation.0, atom);
+            }
+        }
+        let _ = conn.flush();
+    }
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+mod platform {
+    use tauri::WebviewWindow;
+
+    use super::DockEdge;
+    use crate::screenshot::Region;
+
+    pub struct Reservation;
+
+    /// macOS keeps the work area to the menu bar and the Dock.
+    pub fn reserve(
+        _window: &WebviewWindow,
+        _edge: DockEdge,
+        _bounds: Region,
+        _thickness: u32,
+    ) -> Result<(Reservation, Region), String> {
+        Err("Reserving screen space is not supported on this platform".to_string())
+    }
+
+    pub fn release(_reservation: Reservation) {}
+}
+
+pub fn is_docked(app: &tauri::AppHandle) -> bool {
+    app.state::<DockState>().docked.lock().unwrap().is_some()
+}
+
+fn find_monitor(
+    app: &tauri::AppHandle,
+    window: &WebviewWindow,
+    monitor_id: Option<&str>,
+) -> Result<Monitor, AppError> {
+    match monitor_id {
+        Some(id) => app
+            .available_monitors()?
+            .into_iter()
+            .find(|monitor| monitor.name().map(String::as_str) == Some(id))
+            .ok_or_else(|| AppError::NotFound(format!("Monitor {id} not found"))),
+        None => window
+            .current_monitor()?
+            .or_else(|| screenshot::cursor_monitor(app).ok())
+            .ok_or_else(|| AppError::Other("No monitor found".to_string())),
+    }
+}
+
+/// A strip `thickness` deep along `edge` of `area`.
+fn strip(area: Region, edge: DockEdge, thickness: u32) -> Region {
+    match edge {
+        DockEdge::Left => Region {
+            width: thickness,
+            ..area
+        },
+        DockEdge::Right => Region {
+            x: area.x + area.width as i32 - thickness as i32,
+            width: thickness,
+            ..area
+        },
+        DockEdge::Top => Region {
+            height: thickness,
+            ..area
+        },
+        DockEdge::Bottom => Region {
+            y: area.y + area.height as i32 - thickness as i32,
+            height: thickness,
+            ..area
+        },
+    }
+}
+
+fn move_to(window: &WebviewWindow, bounds: Region, scale_factor: f64) -> Result<(), String> {
+    window
+        .set_size(PhysicalSize::new(bounds.width, bounds.height))
+        .map_err(|e| e.to_string())?;
+    layer_shell::set_position(
+        window,
+        PhysicalPosition::new(bounds.x, bounds.y),
+        scale_factor,
+    )
+}
+
+/// Take the dock down, giving back the space it reserved. Returns where the overlay was
+/// before it was docked.
+fn release(app: &tauri::AppHandle) -> Option<(PhysicalPosition<i32>, PhysicalSize<u32>)> {
+    let docked = app.state::<DockState>().docked.lock().unwrap().take()?;
+    if let Some(reservation) = docked.reservation {
+        platform::release(reservation);
+    }
+    let _ = app.emit("overlay-dock-changed", None::<DockedOverlay>);
+    Some(docked.before)
+}
+
+/// Called as the overlay is destroyed or the app exits, so no space stays reserved for
+/// a panel that is gone.
+pub fn forget(app: &tauri::AppHandle) {
+    release(app);
+}
+
+/// Snap the overlay to `edge` of a monitor as a sidebar spanning it, as deep as the
+/// overlay is now wide (or tall, for the top and bottom). `monitor_id` is a monitor's
+/// name, the overlay's own monitor when left out. With `reserve_space`, maximized
+/// windows keep clear of it: an app bar on Windows, struts on X11. Elsewhere it is
+/// docked anyway and `reserved` says it isn't. A docked overlay stays where it is when
+/// shown and doesn't hide when it loses focus.
+#[tauri::command]
+pub async fn dock_overlay(
+    app: tauri::AppHandle,
+    edge: DockEdge,
+    monitor_id: Option<String>,
+    reserve_space: bool,
+) -> Result<DockedOverlay, AppError> {
+    let window = window_manager::get_or_create(&app, AppWindow::Overlay)?;
+    let monitor = find_monitor(&app, &window, monitor_id.as_deref())?;
+    // Docking again, say on another edge, starts from where it was before the first time.
+    let before = match release(&app) {
+        Some(before) => before,
+        None => (window.outer_position()?, window.outer_size()?),
+    };
+
+    let area = monitor.work_area();
+    let area = Region {
+        x: area.position.x,
+        y: area.position.y,
+        width: area.size.width,
+        height: area.size.height,
+    };
+    let (size, span) = if edge.vertical() {
+        (before.1.width, area.width)
+    } else {
+        (before.1.height, area.height)
+    };
+    let thickness = size.max(MIN_THICKNESS).min(span / 2);
+
+    let mut bounds = strip(area, edge, thickness);
+    let mut reservation = None;
+    if reserve_space {
+        // The app bar is placed against the whole monitor, and moved off the taskbar by
+        // the shell; a strut is set for where the overlay lands.
+        let target = if cfg!(windows) {
+            Region::of_monitor(&monitor)
+        } else {
+            bounds
+        };
+        match platform::reserve(&window, edge, target, thickness) {
+            Ok((reserved, placed)) => {
+                reservation = Some(reserved);
+                bounds = placed;
+            }
+            Err(error) => tracing::warn!("Docking the overlay without reserving space: {error}"),
+        }
+    }
+    move_to(&window, bounds, monitor.scale_factor())?;
+
+    let overlay = DockedOverlay {
+        edge,
+        monitor_id: monitor.name().cloned(),
+        bounds,
+        reserved: reservation.is_some(),
+    };
+    *app.state::<DockState>().docked.lock().unwrap() = Some(Docked {
+        overlay: overlay.clone(),
+        reservation,
+        before,
+    });
+    tracing::info!("Docked the overlay to the {edge:?} edge");
+    let _ = app.emit("overlay-dock-changed", Some(&overlay));
+    Ok(overlay)
+}
+
+/// Return a docked overlay to the size and place it had before. Returns whether it was
+/// docked.
+#[tauri::command]
+pub async fn undock_overlay(app: tauri::AppHandle) -> Result<bool, AppError> {
+    let Some((position, size)) = release(&app) else {
+        return Ok(false);
+    };
+    if let Some(window) = window_manager::get(&app, AppWindow::Overlay) {
+        window.set_size(size)?;
+        let scale_factor = window.scale_factor()?;
+        layer_shell::set_position(&window, position, scale_factor)?;
+    }
+    Ok(true)
+}
+
+/// The dock the overlay is in, if it is docked.
+pub fn current(app: &tauri::AppHandle) -> Option<DockedOverlay> {
+    app.state::<DockState>()
+        .docked
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|docked| docked.overlay.clone())
+}
+
+#[tauri::command]
+pub fn get_overlay_dock(app: tauri::AppHandle) -> Option<DockedOverlay> {
+    current(&app)
+}
@@ -0,0 +1,238 @@
+use std::fs::{self, File};
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use serde::Serialize;
+use tauri::Emitter;
+
+use crate::capabilities;
+use crate::error::AppError;
+
+/// Files bigger than this are refused rather than read into memory.
+const MAX_DOCUMENT_BYTES: u64 = 50 * 1024 * 1024;
+/// Target chunk size, small enough to fit several chunks into one prompt.
+const CHUNK_CHARS: usize = 4000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DocumentFormat {
+    Pdf,
+    Docx,
+    Markdown,
+    Text,
+}
+
+impl DocumentFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "pdf" => Some(Self::Pdf),
+            "docx" => Some(Self::Docx),
+            "md" | "markdown" => Some(Self::Markdown),
+            "txt" | "text" | "log" | "csv" => Some(Self::Text),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentChunk {
+    pub index: usize,
+    /// Byte offset of the chunk in the extracted text.
+    pub offset: usize,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParsedDocument {
+    pub path: PathBuf,
+    pub name: String,
+    pub format: DocumentFormat,
+    /// PDFs only.
+    pub page_count: Option<usize>,
+    pub char_count: usize,
+    pub chunks: Vec<DocumentChunk>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DocumentProgress<'a> {
+    path: &'a Path,
+    /// Pages for PDFs; other formats report a single step.
+    completed: usize,
+    total: usize,
+}
+
+/// Split `text` into chunks of at most `max_chars` characters, preferring to break between
+/// paragraphs, then lines, then words.
+pub fn chunk_text(text: &str, max_chars: usize) -> Vec<DocumentChunk> {
+    let mut chunks = Vec::new();
+    let mut offset = 0;
+    while offset < text.len() {
+        let rest = &text[offset..];
+        let limit = rest
+            .char_indices()
+            .nth(max_chars)
+            .map_or(rest.len(), |(i, _)| i);
+        let end = if limit == rest.len() {
+            limit
+        } else {
+            let window = &rest[..limit];
+            ["\n\n", "\n", " "]
+                .iter()
+                .find_map(|separator| {
+                    window
+                        .rfind(separator)
+                        .filter(|&i| i > limit / 2)
+                        .map(|i| i + separator.len())
+                })
+                .unwrap_or(limit)
+        };
+        let piece = rest[..end].trim();
+        if !piece.is_empty() {
+            chunks.push(DocumentChunk {
+                index: chunks.len(),
+                offset: offset + (rest[..end].len() - rest[..end].trim_start().len()),
+                text: piece.to_string(),
+            });
+        }
+        offset += end;
+    }
+    chunks
+}
+
+fn extract_pdf(path: &Path, progress: &dyn Fn(usize, usize)) -> Result<(String, usize), String> {
+    use pdf_extract::{output_doc_page, Document, PlainTextOutput};
+
+    let mut doc = Document::load(path).map_err(|e| e.to_string())?;
+    if doc.is_encrypted() {
+        // Many PDFs are "encrypted" with an empty user password just to set permissions.
+        doc.decrypt("")
+            .map_err(|_| "The PDF is password protected".to_string())?;
+    }
+    let pages: Vec<u32> = doc.get_pages().into_keys().collect();
+    let mut text = String::new();
+    for (i, page) in pages.iter().enumerate() {
+        let mut page_text = String::new();
+        output_doc_page(&doc, &mut PlainTextOutput::new(&mut page_text), *page)
+            .map_err(|e| format!("Could not read page {page}: {e}"))?;
+        text.push_str(page_text.trim_end());
+        text.push_str("\n\n");
+        progress(i + 1, pages.len());
+    }
+    Ok((text, pages.len()))
+}
+
+/// The text of `word/document.xml`: a line per paragraph, with tabs and line breaks kept.
+fn extract_docx(path: &Path) -> Result<String, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let mut archive = zip::ZipArchive::new(file).map_err(|e| e.to_string())?;
+    let entry = archive
+        .by_name("word/document.xml")
+        .map_err(|_| "Not a Word document".to_string())?;
+    let mut reader = Reader::from_reader(BufReader::new(entry));
+    let mut buffer = Vec::new();
+    let mut text = String::new();
+    let mut in_text = false;
+    loop {
+        match reader
+            .read_event_into(&mut buffer)
+            .map_err(|e| e.to_string())?
+        {
+            Event::Start(e) if e.local_name().as_ref() == b"t" => in_text = true,
+            Event::End(e) => match e.local_name().as_ref() {
+                b"t" => in_text = false,
+                b"p" => text.push('\n'),
+                _ => {}
+            },
+            Event::Empty(e) => match e.local_name().as_ref() {
+                b"tab" => text.push('\t'),
+                b"br" | b"cr" => text.push('\n'),
+                _ => {}
+            },
+            Event::Text(e) if in_text => text.push_str(&e.unescape().map_err(|e| e.to_string())?),
+            Event::Eof => break,
+            _ => {}
+        }
+        buffer.clear();
+    }
+    Ok(text)
+}
+
+fn read_text(path: &Path) -> Result<String, String> {
+    let mut bytes = Vec::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_end(&mut bytes))
+        .map_err(|e| e.to_string())?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Extract and chunk a document. `progress` is called with pages done and total for
+/// PDFs, and once at the end for other formats.
+pub fn extract(path: &Path, progress: &dyn Fn(usize, usize)) -> Result<ParsedDocument, String> {
+    let format = DocumentFormat::from_path(path)
+        .ok_or_else(|| "Unsupported document type; use PDF, DOCX, Markdown or text".to_string())?;
+    let size = fs::metadata(path).map_err(|e| e.to_string())?.len();
+    if size > MAX_DOCUMENT_BYTES {
+        return Err(format!(
+            "Documents larger than {} MB are not supported",
+            MAX_DOCUMENT_BYTES / 1024 / 1024
+        ));
+    }
+
+    let (text, page_count) = match format {
+        DocumentFormat::Pdf => {
+            let (text, pages) = extract_pdf(path, progress)?;
+            (text, Some(pages))
+        }
+        DocumentFormat::Docx => (extract_docx(path)?, None),
+        DocumentFormat::Markdown | DocumentFormat::Text => (read_text(path)?, None),
+    };
+    if page_count.is_none() {
+        progress(1, 1);
+    }
+    if text.trim().is_empty() {
+        return Err("The document contains no extractable text".to_string());
+    }
+
+    Ok(ParsedDocument {
+        path: path.to_path_buf(),
+        name: path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        format,
+        page_count,
+        char_count: text.chars().count(),
+        chunks: chunk_text(&text, CHUNK_CHARS),
+    })
+}
+
+/// `extract`, reporting progress as `document-progress` events.
+pub fn parse(app: &tauri::AppHandle, path: &Path) -> Result<ParsedDocument, String> {
+    extract(path, &|completed, total| {
+        let _ = app.emit(
+            "document-progress",
+            DocumentProgress {
+                path,
+                completed,
+                total,
+            },
+        );
+    })
+}
+
+#[tauri::command]
+pub async fn parse_document(
+    app: tauri::AppHandle,
+    path: PathBuf,
+) -> Result<ParsedDocument, AppError> {
+    capabilities::require_path(&app, &path, "Attach a document").await?;
+    tauri::async_runtime::spawn_blocking(move || parse(&app, &path))
+        .await?
+        .map_err(AppError::from)
+}
@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::Manager;
+
+use crate::db;
+use crate::persist;
+
+const DRAFTS_FILE: &str = "drafts.json";
+/// How often changed drafts are written out; at most this much typing is lost in a crash.
+const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(5);
+/// Where the draft of a conversation that hasn't been started yet is kept.
+const NEW_CONVERSATION: &str = "";
+
+/// A prompt typed but not sent yet.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Draft {
+    pub text: String,
+    pub updated_at: i64,
+}
+
+/// Drafts by conversation, kept here so they outlive the webview that was typing them:
+/// through the overlay being hidden or unloaded, a reload, or a crash. Changes are saved
+/// every few seconds rather than on each keystroke.
+pub struct DraftStore {
+    path: PathBuf,
+    drafts: Mutex<HashMap<String, Draft>>,
+    dirty: AtomicBool,
+}
+
+impl DraftStore {
+    pub fn load(app: &tauri::AppHandle) -> Result<Self, String> {
+        let path = persist::data_file(app, DRAFTS_FILE)?;
+        let drafts = persist::load_json(&path);
+        Ok(Self {
+            path,
+            drafts: Mutex::new(drafts),
+            dirty: AtomicBool::new(false),
+        })
+    }
+
+    /// Write the drafts out if they changed since last time.
+    pub fn flush(&self) -> Result<(), String> {
+        if !self.dirty.swap(false, Ordering::SeqCst) {
+            return Ok(());
+        }
+        let drafts = self.drafts.lock().unwrap();
+        persist::save_json(&self.path, &*drafts).map_err(|error| {
+            self.dirty.store(true, Ordering::SeqCst);
+            error
+        })
+    }
+}
+
+fn key(conversation_id: Option<String>) -> String {
+    conversation_id.unwrap_or_else(|| NEW_CONVERSATION.to_string())
+}
+
+/// Save changed drafts every `AUTOSAVE_INTERVAL` for as long as the app runs.
+pub fn start(app: &tauri::AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(AUTOSAVE_INTERVAL).await;
+            if let Err(error) = app.state::<DraftStore>().flush() {
+                tracing::warn!("Could not save drafts: {error}");
+            }
+        }
+    });
+}
+
+/// Drop the draft of a conversation that was deleted.
+pub fn discard(app: &tauri::AppHandle, conversation_id: &str) {
+    let store = app.state::<DraftStore>();
+    if store
+        .drafts
+        .lock()
+        .unwrap()
+        .remove(conversation_id)
+        .is_some()
+    {
+        store.dirty.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Keep `text` as the draft of `conversation_id`, or of a conversation not started yet
+/// without one; empty text clears it. Meant to be called as the user types.
+#[tauri::command]
+pub fn save_draft(app: tauri::AppHandle, conversation_id: Option<String>, text: String) {
+    let store = app.state::<DraftStore>();
+    let mut drafts = store.drafts.lock().unwrap();
+    let key = key(conversation_id);
+    let changed = if text.is_empty() {
+        drafts.remove(&key).is_some()
+    } else if drafts.get(&key).is_some_and(|draft| draft.text == text) {
+        false
+    } else {
+        drafts.insert(
+            key,
+            Draft {
+                text,
+                updated_at: db::now_ms(),
+            },
+        );
+        true
+    };
+    if changed {
+        store.dirty.store(true, Ordering::SeqCst);
+    }
+}
+
+#[tauri::command]
+pub fn get_draft(app: tauri::AppHandle, conversation_id: Option<String>) -> Option<Draft> {
+    app.state::<DraftStore>()
+        .drafts
+        .lock()
+        .unwrap()
+        .get(&key(conversation_id))
+        .cloned()
+}
@@ -0,0 +1,298 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use rusqlite::params;
+use serde::Serialize;
+use tauri::{Emitter, Listener, Manager};
+use tokio::sync::mpsc;
+
+use crate::db::{self, Database};
+use crate::documents::{self, DocumentFormat};
+use crate::imaging::ImageSource;
+use crate::llm::{self, ChatMessage};
+use crate::notifications::{self, Completion};
+use crate::{history, ocr, settings, whisper};
+
+/// How long a file's size has to stay put before it counts as fully written.
+const SETTLE: Duration = Duration::from_secs(1);
+/// Give up on a file that is still growing after this many checks.
+const SETTLE_CHECKS: u32 = 120;
+/// Characters of a document sent to be summarized; the rest is left out.
+const SUMMARY_CHARS: usize = 60_000;
+/// Whisper reads WAV only.
+const AUDIO_EXTENSIONS: &[&str] = &["wav"];
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "bmp", "webp", "tif", "tiff"];
+
+/// What happens to a file dropped in the folder.
+#[derive(Debug, Clone, Copy)]
+enum Job {
+    Transcribe,
+    Summarize,
+    ReadText,
+}
+
+impl Job {
+    fn for_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        if AUDIO_EXTENSIONS.contains(&extension.as_str()) {
+            Some(Self::Transcribe)
+        } else if IMAGE_EXTENSIONS.contains(&extension.as_str()) {
+            Some(Self::ReadText)
+        } else {
+            DocumentFormat::from_path(path).map(|_| Self::Summarize)
+        }
+    }
+
+    fn title(self, name: &str) -> String {
+        match self {
+            Self::Transcribe => format!("Transcript of {name}"),
+            Self::Summarize => format!("Summary of {name}"),
+            Self::ReadText => format!("Text in {name}"),
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ProcessedPayload<'a> {
+    path: &'a Path,
+    conversation_id: Option<&'a str>,
+    error: Option<&'a str>,
+}
+
+struct Watching {
+    watcher: Option<RecommendedWatcher>,
+    path: Option<PathBuf>,
+}
+
+/// The watch on `dropFolder.path`, and the queue its new files wait in.
+pub struct DropFolder {
+    watching: Mutex<Watching>,
+    queue: mpsc::UnboundedSender<PathBuf>,
+}
+
+impl DropFolder {
+    pub fn start(app: &tauri::AppHandle) -> Self {
+        let (queue, rx) = mpsc::unbounded_channel();
+        tauri::async_runtime::spawn(work(app.clone(), rx));
+        Self {
+            watching: Mutex::new(Watching {
+                watcher: None,
+                path: None,
+            }),
+            queue,
+        }
+    }
+}
+
+/// Files that appeared in the folder, directly or by being moved there. Hidden files
+/// are skipped, since apps write those as temporary copies before renaming them.
+fn arrived(event: notify::Event) -> impl Iterator<Item = PathBuf> {
+    let new = matches!(
+        event.kind,
+        EventKind::Create(_) | EventKind::Modify(notify::event::ModifyKind::Name(_))
+    );
+    event.paths.into_iter().filter(move |path| {
+        new && path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| !name.starts_with('.'))
+            && Job::for_path(path).is_some()
+    })
+}
+
+/// Wait for a file to stop growing, returning when it was last modified; `None` if it
+/// went away or never settled.
+async fn settle(path: &Path) -> Option<SystemTime> {
+    let mut size = None;
+    for _ in 0..SETTLE_CHECKS {
+        let metadata = tokio::fs::metadata(path).await.ok()?;
+        if !metadata.is_file() {
+            return None;
+        }
+        if size == Some(metadata.len()) {
+            return metadata.modified().ok();
+        }
+        size = Some(metadata.len());
+        tokio::time::sleep(SETTLE).await;
+    }
+    None
+}
+
+/// Take queued files one at a time. A file shows up as several events, so each version
+/// of it is processed once.
+async fn work(app: tauri::AppHandle, mut rx: mpsc::UnboundedReceiver<PathBuf>) {
+    let mut done = HashSet::new();
+    while let Some(path) = rx.recv().await {
+        let Some(modified) = settle(&path).await else {
+            continue;
+        };
+        if done.insert((path.clone(), modified)) {
+            process(&app, &path).await;
+        }
+    }
+}
+
+/// The prompt and answer to save for a file.
+async fn run(app: &tauri::AppHandle, job: Job, path: &Path) -> Result<(String, String), String> {
+    let name = file_name(path);
+    match job {
+        Job::Transcribe => {
+            let transcript = whisper::transcribe_audio(app.clone(), path.to_path_buf(), None)
+                .await?
+                .text;
+            if transcript.trim().is_empty() {
+                return Err("No speech was recognized".to_string());
+            }
+            Ok((format!("Transcribe {name}"), transcript))
+        }
+        Job::ReadText => {
+            let source = ImageSource::Path {
+                path: path.to_path_buf(),
+            };
+            let text = ocr::ocr_image(app.clone(), source).await?.text;
+            if text.trim().is_empty() {
+                return Err("No text was found in the image".to_string());
+            }
+            Ok((format!("Read the text in {name}"), text))
+        }
+        Job::Summarize => {
+            let file = path.to_path_buf();
+            let document =
+                tauri::async_runtime::spawn_blocking(move || documents::extract(&file, &|_, _| {}))
+                    .await
+                    .map_err(|e| e.to_string())??;
+            let text: String = document
+                .chunks
+                .iter()
+                .map(|chunk| chunk.text.as_str())
+                .collect::<Vec<_>>()
+                .join("\n")
+                .chars()
+                .take(SUMMARY_CHARS)
+                .collect();
+            // The whole prompt is saved, so the conversation can go on about the document.
+            let prompt = format!("Summarize the document {name}.\n\n{text}");
+            let summary = llm::complete(
+                app,
+                None,
+                vec![ChatMessage {
+                    role: "user".to_string(),
+                    content: prompt.clone(),
+                    images: Vec::new(),
+                }],
+            )
+            .await?;
+            Ok((prompt, summary))
+        }
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Save the result as a conversation of its own.
+fn save(app: &tauri::AppHandle, title: &str, prompt: &str, answer: &str) -> Result<String, String> {
+    let conversation_id = uuid::Uuid::new_v4().to_string();
+    app.state::<Database>().with(|conn| {
+        let tx = conn.transaction()?;
+        let now = db::now_ms();
+        tx.execute(
+            "INSERT INTO conversations (id, title, created_at, updated_at) VALUES (?1, ?2, ?3, ?4)",
+            params![conversation_id, title, now, now],
+        )?;
+        history::insert_message(&tx, &conversation_id, "user", prompt)?;
+        history::insert_message(&tx, &conversation_id, "assistant", answer)?;
+        tx.commit()
+    })?;
+    Ok(conversation_id)
+}
+
+/// Process one file, then notify and emit `drop-folder-processed` with the outcome.
+async fn process(app: &tauri::AppHandle, path: &Path) {
+    let Some(job) = Job::for_path(path) else {
+        return;
+    };
+    let title = job.title(&file_name(path));
+    tracing::info!("Processing {} from the drop folder", path.display());
+    let outcome = match run(app, job, path).await {
+        Ok((prompt, answer)) => save(app, &title, &prompt, &answer).map(|id| (id, answer)),
+        Err(error) => Err(error),
+    };
+    let (conversation_id, error) = match outcome {
+        Ok((conversation_id, answer)) => {
+            let body = notifications::preview(&answer);
+            let completion = Completion {
+                request_id: None,
+                conversation_id: Some(conversation_id.clone()),
+                text: answer,
+            };
+            notifications::show(app, &title, &body, Some(completion));
+            (Some(conversation_id), None)
+        }
+        Err(error) => {
+            tracing::warn!("Could not process {}: {error}", path.display());
+            notifications::show(app, &title, &format!("Failed: {error}"), None);
+            (None, Some(error))
+        }
+    };
+    let _ = app.emit(
+        "drop-folder-processed",
+        ProcessedPayload {
+            path,
+            conversation_id: conversation_id.as_deref(),
+            error: error.as_deref(),
+        },
+    );
+}
+
+/// Point the watch at `dropFolder.path`, or drop it when that is unset. Files already
+/// in the folder are left alone; only new ones are processed.
+fn sync(app: &tauri::AppHandle) {
+    let wanted = settings::current(app).drop_folder.path;
+    let state = app.state::<DropFolder>();
+    let mut watching = state.watching.lock().unwrap();
+    if watching.path == wanted {
+        return;
+    }
+    watching.watcher = None;
+    watching.path = None;
+    let Some(path) = wanted else {
+        return;
+    };
+    let queue = state.queue.clone();
+    let watcher =
+        notify::recommended_watcher(move |result: notify::Result<notify::Event>| match result {
+            Ok(event) => {
+                for path in arrived(event) {
+                    let _ = queue.send(path);
+                }
+            }
+            Err(error) => tracing::warn!("Drop folder watcher error: {error}"),
+        });
+    let watched = watcher.and_then(|mut watcher| {
+        watcher.watch(&path, RecursiveMode::NonRecursive)?;
+        Ok(watcher)
+    });
+    match watched {
+        Ok(watcher) => {
+            tracing::info!("Watching {} as the drop folder", path.display());
+            watching.watcher = Some(watcher);
+            watching.path = Some(path);
+        }
+        Err(error) => tracing::warn!("Could not watch {}: {error}", path.display()),
+    }
+}
+
+/// Follow `dropFolder.path` for as long as the app runs.
+pub fn init(app: &tauri::AppHandle) {
+    sync(app);
+    let handle = app.clone();
+    app.listen("settings-changed", move |_| sync(&handle));
+}
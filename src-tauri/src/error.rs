@@ -0,0 +1,112 @@
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use serde_json::{json, Value};
+
+/// The error every command returns. It reaches the frontend as
+/// `{ code, message, context }`: `code` to branch on, `message` to show as is, and
+/// `context` with the details that kind of error carries, if any.
+///
+/// Internal helpers mostly still fail with a `String`; those convert to `Other`, and
+/// the kinds the UI can offer a way out of are raised as their own variants.
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum AppError {
+    /// A window that should exist couldn't be found or built.
+    #[error("{message}")]
+    WindowMissing { window: String, message: String },
+    /// The user declined, or the path or action is outside what they allowed.
+    #[error("{0}")]
+    PermissionDenied(String),
+    /// The request never got an answer: DNS, connection, TLS or proxy trouble.
+    #[error("{0}")]
+    Network(String),
+    /// A provider has no key saved or rejected the one it was sent.
+    #[error("{message}")]
+    ProviderAuth { provider: String, message: String },
+    /// A provider answered with any other error status.
+    #[error("{message}")]
+    Provider {
+        provider: String,
+        status: u16,
+        message: String,
+    },
+    #[error("{0}")]
+    NotFound(String),
+    #[error("{0}")]
+    InvalidInput(String),
+    #[error("Timed out after {0}s")]
+    Timeout(u64),
+    #[error("Request cancelled")]
+    Cancelled,
+    #[error("{0}")]
+    Other(String),
+}
+
+impl AppError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::WindowMissing { .. } => "windowMissing",
+            AppError::PermissionDenied(_) => "permissionDenied",
+            AppError::Network(_) => "network",
+            AppError::ProviderAuth { .. } => "providerAuth",
+            AppError::Provider { .. } => "provider",
+            AppError::NotFound(_) => "notFound",
+            AppError::InvalidInput(_) => "invalidInput",
+            AppError::Timeout(_) => "timeout",
+            AppError::Cancelled => "cancelled",
+            AppError::Other(_) => "other",
+        }
+    }
+
+    fn context(&self) -> Value {
+        match self {
+            AppError::WindowMissing { window, .. } => json!({ "window": window }),
+            AppError::ProviderAuth { provider, .. } => json!({ "provider": provider }),
+            AppError::Provider {
+                provider, status, ..
+            } => json!({ "provider": provider, "status": status }),
+            AppError::Timeout(seconds) => json!({ "seconds": seconds }),
+            _ => Value::Null,
+        }
+    }
+}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut error = serializer.serialize_struct("AppError", 3)?;
+        error.serialize_field("code", self.code())?;
+        error.serialize_field("message", &self.to_string())?;
+        error.serialize_field("context", &self.context())?;
+        error.end()
+    }
+}
+
+impl From<String> for AppError {
+    fn from(message: String) -> Self {
+        AppError::Other(message)
+    }
+}
+
+impl From<&str> for AppError {
+    fn from(message: &str) -> Self {
+        AppError::Other(message.to_string())
+    }
+}
+
+/// So `?` still works in helpers that report plain messages.
+impl From<AppError> for String {
+    fn from(error: AppError) -> Self {
+        error.to_string()
+    }
+}
+
+impl From<reqwest::Error> for AppError {
+    fn from(error: reqwest::Error) -> Self {
+        AppError::Network(error.to_string())
+    }
+}
+
+impl From<tauri::Error> for AppError {
+    fn from(error: tauri::Error) -> Self {
+        AppError::Other(error.to_string())
+    }
+}
@@ -0,0 +1,434 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use rusqlite::{params, Row};
+use serde::Serialize;
+use tauri::{Emitter, Manager, State};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::Command;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::db::{self, Database};
+use crate::error::AppError;
+use crate::{requests, settings};
+
+/// Unanswered confirmations count as a no after this long.
+const APPROVAL_TIMEOUT: Duration = Duration::from_secs(300);
+/// Output kept per stream; anything after it is read and dropped.
+const MAX_CAPTURE_BYTES: usize = 256 * 1024;
+const DEFAULT_PAGE_SIZE: u32 = 50;
+
+/// Commands waiting for `approve_command` or `deny_command`, by run id.
+#[derive(Default)]
+pub struct PendingCommands(Mutex<HashMap<String, oneshot::Sender<bool>>>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RunStatus {
+    Pending,
+    Running,
+    Completed,
+    Denied,
+    Timeout,
+    Failed,
+    Cancelled,
+}
+
+impl RunStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Running => "running",
+            Self::Completed => "completed",
+            Self::Denied => "denied",
+            Self::Timeout => "timeout",
+            Self::Failed => "failed",
+            Self::Cancelled => "cancelled",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandRun {
+    pub id: String,
+    pub request_id: Option<String>,
+    pub command: String,
+    pub cwd: String,
+    pub status: String,
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    pub started_at: i64,
+    pub finished_at: Option<i64>,
+}
+
+const RUN_COLUMNS: &str =
+    "id, request_id, command, cwd, status, exit_code, stdout, stderr, started_at, finished_at";
+
+fn run_from_row(row: &Row) -> rusqlite::Result<CommandRun> {
+    Ok(CommandRun {
+        id: row.get(0)?,
+        request_id: row.get(1)?,
+        command: row.get(2)?,
+        cwd: row.get(3)?,
+        status: row.get(4)?,
+        exit_code: row.get(5)?,
+        stdout: row.get(6)?,
+        stderr: row.get(7)?,
+        started_at: row.get(8)?,
+        finished_at: row.get(9)?,
+    })
+}
+
+/// What a finished command printed.
+#[derive(Debug, Clone, Default)]
+pub struct CommandOutput {
+    pub stdout: String,
+    pub stderr: String,
+    pub exit_code: Option<i32>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ConfirmationRequest<'a> {
+    id: &'a str,
+    request_id: Option<&'a str>,
+    command: &'a str,
+    cwd: &'a str,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Stream {
+    Stdout,
+    Stderr,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OutputChunk<'a> {
+    id: &'a str,
+    stream: Stream,
+    text: &'a str,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RunFinished<'a> {
+    id: &'a str,
+    status: RunStatus,
+    exit_code: Option<i32>,
+}
+
+/// The history row of one run. If the run is dropped before `finish`, because its request
+/// was cancelled, the row is marked cancelled.
+struct Record {
+    app: tauri::AppHandle,
+    id: String,
+    finished: bool,
+}
+
+impl Record {
+    fn set_status(&self, status: RunStatus) {
+        let updated = self.app.state::<Database>().with(|conn| {
+            conn.execute(
+                "UPDATE command_runs SET status = ?2 WHERE id = ?1",
+                params![self.id, status.as_str()],
+            )
+        });
+        if let Err(error) = updated {
+            tracing::error!("Failed to update command run {}: {error}", self.id);
+        }
+    }
+
+    fn finish(&mut self, status: RunStatus, output: &CommandOutput) {
+        self.finished = true;
+        let updated = self.app.state::<Database>().with(|conn| {
+            conn.execute(
+                "UPDATE command_runs
+                 SET status = ?2, exit_code = ?3, stdout = ?4, stderr = ?5, finished_at = ?6
+                 WHERE id = ?1",
+                params![
+                    self.id,
+                    status.as_str(),
+                    output.exit_code,
+                    output.stdout,
+                    output.stderr,
+                    db::now_ms()
+                ],
+            )
+        });
+        if let Err(error) = updated {
+            tracing::error!("Failed to record command run {}: {error}", self.id);
+        }
+        let _ = self.app.emit(
+            "command-finished",
+            RunFinished {
+                id: &self.id,
+                status,
+                exit_code: output.exit_code,
+            },
+        );
+    }
+}
+
+impl Drop for Record {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.finish(RunStatus::Cancelled, &CommandOutput::default());
+        }
+    }
+}
+
+/// The shell that runs a command line.
+fn shell(line: &str) -> Command {
+    #[cfg(windows)]
+    {
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        let mut command = Command::new("cmd");
+        command.args(["/C", line]).creation_flags(CREATE_NO_WINDOW);
+        command
+    }
+    #[cfg(not(windows))]
+    {
+        let mut command = Command::new("sh");
+        command.args(["-c", line]);
+        command
+    }
+}
+
+/// Where a command may run: inside the folders shared with the assistant when there are
+/// any, else anywhere. Without a `cwd`, the first shared folder or the home folder.
+fn working_dir(app: &tauri::AppHandle, cwd: Option<&Path>) -> Result<PathBuf, AppError> {
+    let allowed = settings::current(app).tools.allowed_dirs;
+    let Some(cwd) = cwd else {
+        return match allowed.first() {
+            Some(dir) => Ok(dir.clone()),
+            None => app.path().home_dir().map_err(AppError::from),
+        };
+    };
+    if !cwd.is_absolute() {
+        return Err(AppError::InvalidInput(
+            "The working directory must be an absolute path".to_string(),
+        ));
+    }
+    let resolved = fs::canonicalize(cwd).map_err(|e| format!("{}: {e}", cwd.display()))?;
+    let inside = allowed.is_empty()
+        || allowed
+            .iter()
+            .any(|dir| fs::canonicalize(dir).is_ok_and(|dir| resolved.starts_with(dir)));
+    if !inside {
+        return Err(AppError::PermissionDenied(format!(
+            "{} is outside the folders the assistant may use",
+            cwd.display()
+        )));
+    }
+    Ok(resolved)
+}
+
+/// Forward a child's output line by line, lossily decoded so odd bytes can't stop it.
+fn forward(
+    reader: impl AsyncRead + Unpin + Send + 'static,
+    stream: Stream,
+    lines: mpsc::UnboundedSender<(Stream, String)>,
+) {
+    tauri::async_runtime::spawn(async move {
+        let mut reader = BufReader::new(reader);
+        let mut line = Vec::new();
+        while let Ok(read) = reader.read_until(b'\n', &mut line).await {
+            if read == 0 {
+                return;
+            }
+            let _ = lines.send((stream, String::from_utf8_lossy(&line).into_owned()));
+            line.clear();
+        }
+    });
+}
+
+/// Run a shell command once the user approves it. Emits `command-confirmation`, then
+/// `command-output` as output arrives and `command-finished` at the end; every run is
+/// kept in the command history. Dropping the future kills the command.
+pub async fn run_command(
+    app: &tauri::AppHandle,
+    request_id: Option<&str>,
+    command: &str,
+    cwd: Option<&Path>,
+) -> Result<CommandOutput, AppError> {
+    let cwd = working_dir(app, cwd)?;
+    let cwd_text = cwd.to_string_lossy().into_owned();
+    let id = requests::new_id();
+    app.state::<Database>().with(|conn| {
+        conn.execute(
+            "INSERT INTO command_runs (id, request_id, command, cwd, status, started_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                id,
+                request_id,
+                command,
+                cwd_text,
+                RunStatus::Pending.as_str(),
+                db::now_ms()
+            ],
+        )
+    })?;
+    let mut record = Record {
+        app: app.clone(),
+        id: id.clone(),
+        finished: false,
+    };
+
+    let (decision, decided) = oneshot::channel();
+    app.state::<PendingCommands>()
+        .0
+        .lock()
+        .unwrap()
+        .insert(id.clone(), decision);
+    let _ = app.emit(
+        "command-confirmation",
+        ConfirmationRequest {
+            id: &id,
+            request_id,
+            command,
+            cwd: &cwd_text,
+        },
+    );
+    let approved = matches!(
+        tokio::time::timeout(APPROVAL_TIMEOUT, decided).await,
+        Ok(Ok(true))
+    );
+    app.state::<PendingCommands>().0.lock().unwrap().remove(&id);
+    if !approved {
+        record.finish(RunStatus::Denied, &CommandOutput::default());
+        return Err(AppError::PermissionDenied(
+            "The user declined to run the command".to_string(),
+        ));
+    }
+
+    record.set_status(RunStatus::Running);
+    let mut child = match shell(command)
+        .current_dir(&cwd)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(error) => {
+            let output = CommandOutput {
+                stderr: error.to_string(),
+                ..Default::default()
+            };
+            record.finish(RunStatus::Failed, &output);
+            return Err(format!("Could not run the command: {error}").into());
+        }
+    };
+    let (lines, mut received) = mpsc::unbounded_channel();
+    if let Some(stdout) = child.stdout.take() {
+        forward(stdout, Stream::Stdout, lines.clone());
+    }
+    if let Some(stderr) = child.stderr.take() {
+        forward(stderr, Stream::Stderr, lines);
+    }
+
+    let mut output = CommandOutput::default();
+    let limit = Duration::from_secs(settings::current(app).tools.command_timeout_secs);
+    let collected = tokio::time::timeout(limit, async {
+        while let Some((stream, text)) = received.recv().await {
+            let buffer = match stream {
+                Stream::Stdout => &mut output.stdout,
+                Stream::Stderr => &mut output.stderr,
+            };
+            if buffer.len() + text.len() > MAX_CAPTURE_BYTES {
+                continue;
+            }
+            buffer.push_str(&text);
+            let _ = app.emit(
+                "command-output",
+                OutputChunk {
+                    id: &id,
+                    stream,
+                    text: &text,
+                },
+            );
+        }
+        child.wait().await
+    })
+    .await;
+
+    match collected {
+        Ok(Ok(status)) => {
+            output.exit_code = status.code();
+            record.finish(RunStatus::Completed, &output);
+            Ok(output)
+        }
+        Ok(Err(error)) => {
+            record.finish(RunStatus::Failed, &output);
+            Err(error.to_string().into())
+        }
+        Err(_) => {
+            let _ = child.start_kill();
+            record.finish(RunStatus::Timeout, &output);
+            Err(AppError::Timeout(limit.as_secs()))
+        }
+    }
+}
+
+fn decide(state: &PendingCommands, id: &str, approve: bool) -> bool {
+    match state.0.lock().unwrap().remove(id) {
+        Some(decision) => decision.send(approve).is_ok(),
+        None => false,
+    }
+}
+
+/// Let a command waiting on `command-confirmation` run. Returns `false` if it had
+/// already been answered or given up on.
+#[tauri::command]
+pub fn approve_command(state: State<'_, PendingCommands>, id: String) -> bool {
+    decide(&state, &id, true)
+}
+
+#[tauri::command]
+pub fn deny_command(state: State<'_, PendingCommands>, id: String) -> bool {
+    decide(&state, &id, false)
+}
+
+/// Past command runs, newest first.
+#[tauri::command]
+pub async fn list_command_runs(
+    db: State<'_, Database>,
+    limit: Option<u32>,
+    offset: Option<u32>,
+) -> Result<Vec<CommandRun>, AppError> {
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).min(500);
+    let offset = offset.unwrap_or(0);
+    db.with(|conn| {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {RUN_COLUMNS} FROM command_runs
+             ORDER BY started_at DESC LIMIT ?1 OFFSET ?2"
+        ))?;
+        let runs = stmt
+            .query_map(params![limit, offset], run_from_row)?
+            .collect();
+        runs
+    })
+    .map_err(AppError::from)
+}
+
+/// Delete the command history, except runs still waiting or running. Returns how many
+/// were deleted.
+#[tauri::command]
+pub async fn clear_command_runs(db: State<'_, Database>) -> Result<usize, AppError> {
+    db.with(|conn| {
+        conn.execute(
+            "DELETE FROM command_runs WHERE status NOT IN ('pending', 'running')",
+            [],
+        )
+    })
+    .map_err(AppError::from)
+}
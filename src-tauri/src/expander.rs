@@ -0,0 +1,678 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, Listener, Manager};
+
+use crate::capabilities::{self, Capability};
+use crate::error::AppError;
+use crate::llm::{self, ChatMessage};
+use crate::{focus, insert, keyboard, persist, settings, templates};
+
+const SNIPPETS_FILE: &str = "snippets.json";
+/// How much typing is kept to match triggers against; longer triggers can't match.
+const MAX_TRIGGER: usize = 32;
+
+/// What a snippet's trigger is replaced with.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum Expansion {
+    Text {
+        text: String,
+    },
+    /// The model's answer to a saved prompt template.
+    Template {
+        #[serde(rename = "templateId")]
+        template_id: String,
+    },
+}
+
+/// Text typed anywhere that is swapped for something else, like `;sig` for a signature.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Snippet {
+    /// Assigned by `save_snippet` when empty.
+    #[serde(default)]
+    pub id: String,
+    pub trigger: String,
+    pub expansion: Expansion,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExpandedPayload<'a> {
+    snippet_id: &'a str,
+    trigger: &'a str,
+}
+
+pub struct SnippetStore {
+    path: PathBuf,
+    snippets: Mutex<Vec<Snippet>>,
+    /// Mirrors `expander.enabled`, read on every key.
+    enabled: AtomicBool,
+    /// Whether the keyboard hook has been installed; it stays until the app quits.
+    watching: AtomicBool,
+    /// An expansion is being typed, so the keys seen now are our own.
+    expanding: AtomicBool,
+}
+
+impl SnippetStore {
+    pub fn load(app: &tauri::AppHandle) -> Result<Self, String> {
+        let path = persist::data_file(app, SNIPPETS_FILE)?;
+        let snippets = persist::load_json(&path);
+        Ok(Self {
+            path,
+            snippets: Mutex::new(snippets),
+            enabled: AtomicBool::new(false),
+            watching: AtomicBool::new(false),
+            expanding: AtomicBool::new(false),
+        })
+    }
+}
+
+/// A key press as far as matching triggers goes.
+enum Key {
+    Char(char),
+    Backspace,
+    /// Anything that may have moved the caret or ended the word, like Enter, an arrow
+    /// key or a shortcut.
+    Reset,
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::sync::mpsc::Sender;
+    use std::sync::Mutex;
+
+    use windows::Win32::Foundation::{LPARAM, LRESULT, WPARAM};
+    use windows::Win32::UI::Input::KeyboardAndMouse::{
+        GetAsyncKeyState, GetKeyState, GetKeyboardLayout, ToUnicodeEx, VIRTUAL_KEY, VK_BACK,
+        VK_CAPITAL, VK_CONTROL, VK_LCONTROL, VK_LMENU, VK_LSHIFT, VK_LWIN, VK_MENU, VK_RCONTROL,
+        VK_RMENU, VK_RSHIFT, VK_RWIN, VK_SHIFT,
+    };
+    use windows::Win32::UI::WindowsAndMessaging::{
+        CallNextHookEx, GetForegroundWindow, GetMessageW, GetWindowThreadProcessId,
+        SetWindowsHookExW, KBDLLHOOKSTRUCT, LLKHF_INJECTED, MSG, WH_KEYBOARD_LL, WM_KEYDOWN,
+        WM_SYSKEYDOWN,
+    };
+
+    use super::Key;
+
+    const MODIFIER_KEYS: [VIRTUAL_KEY; 14] = [
+        VK_SHIFT,
+        VK_CONTROL,
+        VK_MENU,
+        VK_LWIN,
+        VK_RWIN,
+        VK_LSHIFT,
+        VK_RSHIFT,
+        VK_LCONTROL,
+        VK_RCONTROL,
+        VK_LMENU,
+        VK_RMENU,
+        VK_CAPITAL,
+        VIRTUAL_KEY(0xE5), // VK_PROCESSKEY, sent while an IME composes
+        VIRTUAL_KEY(0xFF),
+    ];
+    /// Leave the kernel's dead key state alone, so an accent typed next still works.
+    const NO_KEYBOARD_STATE_CHANGE: u32 = 0x4;
+
+    static SINK: Mutex<Option<Sender<Key>>> = Mutex::new(None);
+
+    fn down(key: VIRTUAL_KEY) -> bool {
+        // SAFETY: GetAsyncKeyState only reads the key state; the high bit means "down".
+        unsafe { GetAsyncKeyState(i32::from(key.0)) as u16 & 0x8000 != 0 }
+    }
+
+    fn translate(hook: &KBDLLHOOKSTRUCT) -> Option<Key> {
+        let key = VIRTUAL_KEY(hook.vkCode as u16);
+        if MODIFIER_KEYS.contains(&key) {
+            return None;
+        }
+        let (ctrl, alt) = (down(VK_CONTROL), down(VK_MENU));
+        // Ctrl+Alt is AltGr, which types characters on many layouts.
+        if ctrl != alt || down(VK_LWIN) || down(VK_RWIN) {
+            return Some(Key::Reset);
+        }
+        if key == VK_BACK {
+            return Some(Key::Backspace);
+        }
+        let mut state = [0u8; 256];
+        for modifier in [VK_SHIFT, VK_CONTROL, VK_MENU] {
+            if down(modifier) {
+                state[usize::from(modifier.0)] = 0x80;
+            }
+        }
+        let mut buffer = [0u16; 8];
+        // SAFETY: plain queries, and a conversion into a buffer we own. The low bit of
+        // GetKeyState is the toggle state.
+        let written = unsafe {
+            state[usize::from(VK_CAPITAL.0)] = (GetKeyState(i32::from(VK_CAPITAL.0)) & 1) as u8;
+            let thread = GetWindowThreadProcessId(GetForegroundWindow(), None);
+            ToUnicodeEx(
+                hook.vkCode,
+                hook.scanCode,
+                &state,
+                &mut buffer,
+                NO_KEYBOARD_STATE_CHANGE,
+                Some(GetKeyboardLayout(thread)),
+            )
+        };
+        let typed = usize::try_from(written).ok().filter(|&n| n > 0)?;
+        match char::decode_utf16(buffer[..typed].iter().copied()).next() {
+            Some(Ok(c)) if !c.is_control() => Some(Key::Char(c)),
+            _ => Some(Key::Reset),
+        }
+    }
+
+    unsafe extern "system" fn hook(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+        let pressed = matches!(wparam.0 as u32, WM_KEYDOWN | WM_SYSKEYDOWN);
+        if code >= 0 && pressed {
+            // SAFETY: for WH_KEYBOARD_LL, lparam points at a KBDLLHOOKSTRUCT.
+            let info = &*(lparam.0 as *const KBDLLHOOKSTRUCT);
+            // Keys we synthesize ourselves, or another tool does, aren't typing.
+            if info.flags.0 & LLKHF_INJECTED.0 == 0 {
+                if let Some(key) = translate(info) {
+                    if let Some(sink) = SINK.lock().unwrap().as_ref() {
+                        let _ = sink.send(key);
+                    }
+                }
+            }
+        }
+        CallNextHookEx(None, code, wparam, lparam)
+    }
+
+    /// A low-level keyboard hook, which needs a message loop on the thread that set it.
+    pub fn listen(sink: Sender<Key>) -> Result<(), String> {
+        *SINK.lock().unwrap() = Some(sink);
+        // SAFETY: the hook procedure matches HOOKPROC and lives for the whole program.
+        unsafe { SetWindowsHookExW(WH_KEYBOARD_LL, Some(hook), None, 0) }
+            .map_err(|e| e.to_string())?;
+        let mut message = MSG::default();
+        // SAFETY: a plain message loop on this thread.
+        while unsafe { GetMessageW(&mut message, None, 0, 0) }.as_bool() {}
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::ffi::c_void;
+    use std::ptr;
+    use std::sync::atomic::{AtomicPtr, Ordering};
+    use std::sync::mpsc::Sender;
+    use std::sync::Mutex;
+
+    use core_foundation::base::{kCFAllocatorDefault, TCFType};
+    use core_foundation::mach_port::{CFMachPortCreateRunLoopSource, CFMachPortRef};
+    use core_foundation::runloop::{kCFRunLoopCommonModes, CFRunLoop, CFRunLoopSource};
+
+    use super::Key;
+
+    type CGEventRef = *mut c_void;
+    type TapCallback = extern "C" fn(*mut c_void, u32, CGEventRef, *mut c_void) -> CGEventRef;
+
+    const SESSION_EVENT_TAP: u32 = 1;
+    const HEAD_INSERT: u32 = 0;
+    const LISTEN_ONLY: u32 = 1;
+    const KEY_DOWN: u32 = 10;
+    const TAP_DISABLED_BY_TIMEOUT: u32 = 0xFFFF_FFFE;
+    const TAP_DISABLED_BY_USER_INPUT: u32 = 0xFFFF_FFFF;
+    const KEYCODE_FIELD: u32 = 9;
+    const DELETE_KEY: i64 = 51;
+    const COMMAND: u64 = 0x0010_0000;
+    const CONTROL: u64 = 0x0004_0000;
+
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        fn CGEventTapCreate(
+            tap: u32,
+            place: u32,
+            options: u32,
+            events: u64,
+            callback: TapCallback,
+            user_info: *mut c_void,
+        ) -> CFMachPortRef;
+        fn CGEventTapEnable(tap: CFMachPortRef, enable: bool);
+        fn CGEventGetIntegerValueField(event: CGEventRef, field: u32) -> i64;
+        fn CGEventGetFlags(event: CGEventRef) -> u64;
+        fn CGEventKeyboardGetUnicodeString(
+            event: CGEventRef,
+            max: usize,
+            length: *mut usize,
+            buffer: *mut u16,
+        );
+    }
+
+    static SINK: Mutex<Option<Sender<Key>>> = Mutex::new(None);
+    static TAP: AtomicPtr<c_void> = AtomicPtr::new(ptr::null_mut());
+
+    fn translate(event: CGEventRef) -> Key {
+        // SAFETY: reading fields of the event the tap was handed.
+        unsafe {
+            if CGEventGetFlags(event) & (COMMAND | CONTROL) != 0 {
+                return Key::Reset;
+            }
+            if CGEventGetIntegerValueField(event, KEYCODE_FIELD) == DELETE_KEY {
+                return Key::Backspace;
+            }
+            let mut buffer = [0u16; 8];
+            let mut length = 0;
+            CGEventKeyboardGetUnicodeString(event, buffer.len(), &mut length, buffer.as_mut_ptr());
+            match char::decode_utf16(buffer[..length.min(buffer.len())].iter().copied()).next() {
+                Some(Ok(c)) if !c.is_control() => Key::Char(c),
+                _ => Key::Reset,
+            }
+        }
+    }
+
+    extern "C" fn on_event(
+        _proxy: *mut c_void,
+        kind: u32,
+        event: CGEventRef,
+        _user_info: *mut c_void,
+    ) -> CGEventRef {
+        match kind {
+            // The system turns off a tap it thinks is slow; turn it back on.
+            TAP_DISABLED_BY_TIMEOUT | TAP_DISABLED_BY_USER_INPUT => {
+                let tap = TAP.load(Ordering::SeqCst);
+                if !tap.is_null() {
+                    // SAFETY: the tap created in `listen`, which is never released.
+                    unsafe { CGEventTapEnable(tap.cast(), true) };
+                }
+            }
+            KEY_DOWN => {
+                if let Some(sink) = SINK.lock().unwrap().as_ref() {
+                    let _ = sink.send(translate(event));
+                }
+            }
+            _ => {}
+        }
+        event
+    }
+
+    /// A listen-only event tap, which needs Input Monitoring permission.
+    pub fn listen(sink: Sender<Key>) -> Result<(), String> {
+        *SINK.lock().unwrap() = Some(sink);
+        // SAFETY: the callback matches CGEventTapCallBack and lives for the whole program.
+        let tap = unsafe {
+            CGEventTapCreate(
+                SESSION_EVENT_TAP,
+                HEAD_INSERT,
+                LISTEN_ONLY,
+                1 << KEY_DOWN,
+                on_event,
+                ptr::null_mut(),
+            )
+        };
+        if tap.is_null() {
+            return Err(
+                "Allow Aikeya under Input Monitoring in System Settings to expand snippets"
+                    .to_string(),
+            );
+        }
+        TAP.store(tap.cast(), Ordering::SeqCst);
+        // SAFETY: `tap` is a valid mach port; the source is owned by the wrapper.
+        let source = unsafe {
+            CFRunLoopSource::wrap_under_create_rule(CFMachPortCreateRunLoopSource(
+                kCFAllocatorDefault,
+                tap,
+                0,
+            ))
+        };
+        let run_loop = CFRunLoop::get_current();
+        // SAFETY: a constant from CoreFoundation.
+        run_loop.add_source(&source, unsafe { kCFRunLoopCommonModes });
+        CFRunLoop::run_current();
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::sync::mpsc::Sender;
+
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xinput::{self, ConnectionExt as _, KeyEventFlags, XIEventMask};
+    use x11rb::protocol::xproto::{ConnectionExt as _, KeyButMask, Keycode};
+    use x11rb::protocol::Event;
+    use x11rb::rust_connection::RustConnection;
+
+    use super::Key;
+    use crate::x11;
+
+    const BACKSPACE: u32 = 0xFF08;
+
+    /// The keysyms of every keycode, as the server maps them now.
+    struct Keymap {
+        min_keycode: Keycode,
+        per_keycode: usize,
+        keysyms: Vec<u32>,
+    }
+
+    impl Keymap {
+        fn load(conn: &RustConnection) -> Result<Self, String> {
+            let setup = conn.setup();
+            let (min, max) = (setup.min_keycode, setup.max_keycode);
+            let reply = conn
+                .get_keyboard_mapping(min, max - min + 1)
+                .map_err(|e| e.to_string())?
+                .reply()
+                .map_err(|e| e.to_string())?;
+            Ok(Self {
+                min_keycode: min,
+                per_keycode: usize::from(reply.keysyms_per_keycode),
+                keysyms: reply.keysyms,
+            })
+        }
+
+        fn keysym(&self, code: Keycode, column: usize) -> Option<u32> {
+            let row = usize::from(code.checked_sub(self.min_keycode)?) * self.per_keycode;
+            let columns = self.keysyms.get(row..row + self.per_keycode)?;
+            // Keys with one symbol, like letters, are listed without their shifted form.
+            columns
+                .get(column)
+                .or_else(|| columns.get(column & !1))
+                .copied()
+                .filter(|&keysym| keysym != 0)
+        }
+    }
+
+    fn character(keysym: u32) -> Option<char> {
+        match keysym {
+            0x20..=0x7E | 0xA0..=0xFF => char::from_u32(keysym),
+            0x0100_0000..=0x0110_FFFF => char::from_u32(keysym - 0x0100_0000),
+            _ => None,
+        }
+    }
+
+    /// Shift, Caps Lock, Control, Mode_switch and the Level 3 shift, which only pick a
+    /// symbol rather than typing one.
+    fn is_modifier(keysym: u32) -> bool {
+        matches!(keysym, 0xFFE1..=0xFFEE | 0xFE01..=0xFE0F | 0xFF7E)
+    }
+
+    fn translate(keymap: &Keymap, code: Keycode, mask: KeyButMask) -> Option<Key> {
+        let base = keymap.keysym(code, 0)?;
+        if is_modifier(base) {
+            return None;
+        }
+        if mask.contains(KeyButMask::CONTROL) || mask.contains(KeyButMask::MOD4) {
+            return Some(Key::Reset);
+        }
+        if base == BACKSPACE {
+            return Some(Key::Backspace);
+        }
+        let letter = character(base).is_some_and(char::is_alphabetic);
+        let shifted =
+            mask.contains(KeyButMask::SHIFT) != (letter && mask.contains(KeyButMask::LOCK));
+        // AltGr picks the third and fourth symbols of the first group.
+        let level3 = if mask.contains(KeyButMask::MOD5) {
+            4
+        } else {
+            0
+        };
+        let keysym = keymap.keysym(code, level3 + usize::from(shifted))?;
+        Some(character(keysym).map_or(Key::Reset, Key::Char))
+    }
+
+    /// Raw key presses from XInput 2, which X11 sends for every app. Wayland sends
+    /// XWayland only the keys meant for X11 apps, so there it doesn't work.
+    pub fn listen(sink: Sender<Key>) -> Result<(), String> {
+        if x11::is_wayland() {
+            return Err("Snippets can't be expanded on Wayland".to_string());
+        }
+        let (conn, root) = x11::connect()?;
+        conn.xinput_xi_query_version(2, 0)
+            .map_err(|e| e.to_string())?
+            .reply()
+            .map_err(|_| "The X server doesn't support XInput 2".to_string())?;
+        conn.xinput_xi_select_events(
+            root,
+            &[xinput::EventMask {
+                deviceid: xinput::Device::ALL_MASTER.into(),
+                mask: vec![XIEventMask::RAW_KEY_PRESS],
+            }],
+        )
+        .map_err(|e| e.to_string())?
+        .check()
+        .map_err(|e| e.to_string())?;
+        let mut keymap = Keymap::load(&conn)?;
+        loop {
+            match conn.wait_for_event().map_err(|e| e.to_string())? {
+                Event::MappingNotify(_) => keymap = Keymap::load(&conn)?,
+                Event::XinputRawKeyPress(event) => {
+                    if event.flags.contains(KeyEventFlags::KEY_REPEAT) {
+                        continue;
+                    }
+                    let Ok(code) = Keycode::try_from(event.detail) else {
+                        continue;
+                    };
+                    // Raw events don't carry the modifiers, so ask for them.
+                    let mask = conn
+                        .query_pointer(root)
+                        .ok()
+                        .and_then(|cookie| cookie.reply().ok())
+                        .map_or(KeyButMask::default(), |pointer| pointer.mask);
+                    if let Some(key) = translate(&keymap, code, mask) {
+                        if sink.send(key).is_err() {
+                            return Ok(());
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// The end of what was typed since the caret last moved, compared with the triggers.
+#[derive(Default)]
+struct Typed(String);
+
+impl Typed {
+    fn feed(&mut self, key: Key) {
+        match key {
+            Key::Char(c) => {
+                self.0.push(c);
+                if self.0.chars().count() > MAX_TRIGGER {
+                    self.0.remove(0);
+                }
+            }
+            Key::Backspace => {
+                self.0.pop();
+            }
+            Key::Reset => self.0.clear(),
+        }
+    }
+}
+
+/// Erase the trigger just typed and type the snippet in its place, unless the app in
+/// front is excluded. The trigger stays put until there is text to replace it with. Apps that can't be told apart, like our own windows, are left alone.
+fn expand(app: &tauri::AppHandle, snippet: &Snippet) -> Result<(), String> {
+    let Some(context) = focus::current_context() else {
+        return Ok(());
+    };
+    let excluded = settings::current(app).expander.excluded_apps;
+    if excluded.iter().any(|excluded| context.is_app(excluded)) {
+        return Ok(());
+    }
+    capabilities::check(
+        app,
+        Capability::Accessibility,
+        &format!("Expand the snippet {}", snippet.trigger),
+    )?;
+    let text = match &snippet.expansion {
+        Expansion::Text { text } => text.clone(),
+        Expansion::Template { template_id } => {
+            let vars = HashMap::from([(
+                "app".to_string(),
+                context.app_name.clone().unwrap_or_default(),
+            )]);
+            let prompt = templates::render_saved(app, template_id, vars)?;
+            let messages = vec![ChatMessage {
+                role: "user".to_string(),
+                content: prompt,
+                images: Vec::new(),
+            }];
+            tauri::async_runtime::block_on(llm::complete(app, None, messages))?
+        }
+    };
+    if text.is_empty() {
+        return Err(format!(
+            "The snippet {} expanded to nothing",
+            snippet.trigger
+        ));
+    }
+    keyboard::erase(snippet.trigger.chars().count())?;
+    insert::paste(app, &text)?;
+    let _ = app.emit(
+        "snippet-expanded",
+        ExpandedPayload {
+            snippet_id: &snippet.id,
+            trigger: &snippet.trigger,
+        },
+    );
+    Ok(())
+}
+
+/// Match keys from the hook against the triggers, one expansion at a time.
+fn watch(app: tauri::AppHandle, keys: mpsc::Receiver<Key>) {
+    let store = app.state::<SnippetStore>();
+    let mut typed = Typed::default();
+    for key in keys {
+        if !store.enabled.load(Ordering::SeqCst) || store.expanding.load(Ordering::SeqCst) {
+            typed = Typed::default();
+            continue;
+        }
+        typed.feed(key);
+        let matched = store
+            .snippets
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|snippet| typed.0.ends_with(&snippet.trigger))
+            .max_by_key(|snippet| snippet.trigger.len())
+            .cloned();
+        let Some(snippet) = matched else {
+            continue;
+        };
+        typed = Typed::default();
+        store.expanding.store(true, Ordering::SeqCst);
+        let app = app.clone();
+        thread::spawn(move || {
+            if let Err(error) = expand(&app, &snippet) {
+                tracing::warn!("Could not expand {}: {error}", snippet.trigger);
+            }
+            app.state::<SnippetStore>()
+                .expanding
+                .store(false, Ordering::SeqCst);
+        });
+    }
+}
+
+/// Follow `expander.enabled`, hooking the keyboard the first time it is turned on.
+fn sync(app: &tauri::AppHandle) {
+    let enabled = settings::current(app).expander.enabled;
+    let store = app.state::<SnippetStore>();
+    store.enabled.store(enabled, Ordering::SeqCst);
+    if !enabled || store.watching.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let (sink, keys) = mpsc::channel();
+    let hook_app = app.clone();
+    let hooked = thread::Builder::new()
+        .name("expander-hook".to_string())
+        .spawn(move || {
+            if let Err(error) = platform::listen(sink) {
+                tracing::warn!("Not expanding snippets: {error}");
+                hook_app
+                    .state::<SnippetStore>()
+                    .watching
+                    .store(false, Ordering::SeqCst);
+            }
+        });
+    let watcher = app.clone();
+    let watching = hooked.and_then(|_| {
+        thread::Builder::new()
+            .name("expander".to_string())
+            .spawn(move || watch(watcher, keys))
+    });
+    if let Err(error) = watching {
+        tracing::error!("Failed to start the snippet expander: {error}");
+        store.watching.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Expand snippets while `expander.enabled` is on.
+pub fn init(app: &tauri::AppHandle) {
+    sync(app);
+    let handle = app.clone();
+    app.listen("settings-changed", move |_| sync(&handle));
+}
+
+#[tauri::command]
+pub fn list_snippets(app: tauri::AppHandle) -> Vec<Snippet> {
+    app.state::<SnippetStore>().snippets.lock().unwrap().clone()
+}
+
+/// Create a snippet, or replace the one with the same id. Triggers are up to 32
+/// characters without spaces, and each belongs to one snippet.
+#[tauri::command]
+pub fn save_snippet(app: tauri::AppHandle, mut snippet: Snippet) -> Result<Snippet, AppError> {
+    let trigger = snippet.trigger.trim().to_string();
+    let length = trigger.chars().count();
+    if !(2..=MAX_TRIGGER).contains(&length) || trigger.chars().any(char::is_whitespace) {
+        return Err(AppError::InvalidInput(format!(
+            "A trigger must be 2 to {MAX_TRIGGER} characters without spaces"
+        )));
+    }
+    snippet.trigger = trigger;
+    if let Expansion::Text { text } = &snippet.expansion {
+        if text.is_empty() {
+            return Err(AppError::InvalidInput(
+                "Snippet text must not be empty".to_string(),
+            ));
+        }
+    }
+    if snippet.id.is_empty() {
+        snippet.id = uuid::Uuid::new_v4().to_string();
+    }
+
+    let store = app.state::<SnippetStore>();
+    let mut snippets = store.snippets.lock().unwrap();
+    if snippets
+        .iter()
+        .any(|other| other.id != snippet.id && other.trigger == snippet.trigger)
+    {
+        return Err(AppError::InvalidInput(format!(
+            "Another snippet already uses {}",
+            snippet.trigger
+        )));
+    }
+    match snippets.iter_mut().find(|s| s.id == snippet.id) {
+        Some(existing) => *existing = snippet.clone(),
+        None => snippets.push(snippet.clone()),
+    }
+    persist::save_json(&store.path, &*snippets)?;
+    let _ = app.emit("snippets-changed", &*snippets);
+    Ok(snippet)
+}
+
+/// Returns whether a snippet was deleted.
+#[tauri::command]
+pub fn delete_snippet(app: tauri::AppHandle, id: String) -> Result<bool, AppError> {
+    let store = app.state::<SnippetStore>();
+    let mut snippets = store.snippets.lock().unwrap();
+    let before = snippets.len();
+    snippets.retain(|s| s.id != id);
+    if snippets.len() == before {
+        return Ok(false);
+    }
+    persist::save_json(&store.path, &*snippets)?;
+    let _ = app.emit("snippets-changed", &*snippets);
+    Ok(true)
+}
@@ -0,0 +1,475 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+
+use chrono::{Local, TimeZone};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::{Manager, State};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::clipboard::{self, ClipboardContent};
+use crate::db::Database;
+use crate::error::AppError;
+use crate::history::{self, Conversation, ConversationExport, Message};
+use crate::jobs::{self, JobSpec};
+use crate::providers::service_error_for_status;
+use crate::{capabilities, http, secrets};
+
+/// Bump when the layout of a JSON export changes.
+const EXPORT_VERSION: u32 = 1;
+const GISTS_URL: &str = "https://api.github.com/gists";
+/// The keychain name of the GitHub token, saved with `save_api_key` like any key.
+const GITHUB_TOKEN: &str = "github";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Markdown,
+    Json,
+    Html,
+}
+
+/// An image or file a message links to. Only the reference is exported, not the file.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Attachment {
+    pub message_id: String,
+    pub name: String,
+    /// The link target; inline `data:` URIs are cut down to their media type.
+    pub target: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonExport<'a> {
+    version: u32,
+    exported_at: i64,
+    #[serde(flatten)]
+    export: &'a ConversationExport,
+    attachments: Vec<Attachment>,
+}
+
+/// Where `share_conversation` sends a conversation.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ShareTarget {
+    /// A standalone HTML page, written to `path` or else the downloads folder.
+    Html { path: Option<PathBuf> },
+    /// A Markdown transcript, copied to the clipboard.
+    Clipboard,
+    /// The Markdown transcript as a GitHub Gist, secret unless `public`.
+    Gist {
+        #[serde(default)]
+        public: bool,
+    },
+}
+
+/// Where a shared conversation ended up.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Shared {
+    File { path: PathBuf },
+    Clipboard,
+    Gist { url: String },
+}
+
+/// What `export_all` wrote.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupSummary {
+    pub path: PathBuf,
+    pub conversations: usize,
+    pub messages: usize,
+}
+
+fn timestamp(ms: i64) -> String {
+    Local
+        .timestamp_millis_opt(ms)
+        .single()
+        .map(|time| time.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default()
+}
+
+fn role_label(role: &str) -> String {
+    let mut chars = role.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+/// Images (`![alt](target)`) and `file:` links in a message's Markdown.
+fn attachments_in(message: &Message) -> Vec<Attachment> {
+    let content = &message.content;
+    let mut found = Vec::new();
+    let mut rest = 0;
+    while let Some(offset) = content[rest..].find("](") {
+        let close = rest + offset;
+        let target_start = close + 2;
+        let Some(target_len) = content[target_start..].find(')') else {
+            break;
+        };
+        let target = content[target_start..target_start + target_len].trim();
+        rest = target_start + target_len;
+        let Some(open) = content[..close].rfind('[') else {
+            continue;
+        };
+        let image = content[..open].ends_with('!');
+        if !image && !target.starts_with("file:") {
+            continue;
+        }
+        let target = match target.strip_prefix("data:") {
+            Some(data) => format!("data:{}", data.split([';', ',']).next().unwrap_or_default()),
+            None => target.to_string(),
+        };
+        found.push(Attachment {
+            message_id: message.id.clone(),
+            name: content[open + 1..close].to_string(),
+            target,
+        });
+    }
+    found
+}
+
+fn attachments(export: &ConversationExport) -> Vec<Attachment> {
+    export.messages.iter().flat_map(attachments_in).collect()
+}
+
+fn render_markdown(export: &ConversationExport) -> String {
+    let conversation = &export.conversation;
+    let mut out = format!(
+        "# {}\n\n_Started {}, last updated {}_\n",
+        conversation.title,
+        timestamp(conversation.created_at),
+        timestamp(conversation.updated_at)
+    );
+    for message in &export.messages {
+        let _ = write!(
+            out,
+            "\n## {} · {}\n\n{}\n",
+            role_label(&message.role),
+            timestamp(message.created_at),
+            message.content.trim_end()
+        );
+    }
+    let attachments = attachments(export);
+    if !attachments.is_empty() {
+        out.push_str("\n## Attachments\n\n");
+        for attachment in attachments {
+            let _ = writeln!(out, "- {}: `{}`", attachment.name, attachment.target);
+        }
+    }
+    out
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Just enough Markdown for an export to read well: fenced code blocks and paragraphs.
+/// Everything else is shown as written.
+fn markdown_to_html(text: &str) -> String {
+    fn flush(html: &mut String, paragraph: &mut Vec<&str>) {
+        if !paragraph.is_empty() {
+            let _ = writeln!(
+                html,
+                "<p>{}</p>",
+                escape_html(&paragraph.join("\n")).replace('\n', "<br>\n")
+            );
+            paragraph.clear();
+        }
+    }
+
+    let mut html = String::new();
+    let mut paragraph = Vec::new();
+    let mut code: Option<(String, Vec<&str>)> = None;
+    for line in text.lines() {
+        let fence = line.trim_start().strip_prefix("```");
+        if let Some((language, lines)) = &mut code {
+            if fence.is_some() {
+                let class = if language.is_empty() {
+                    String::new()
+                } else {
+                    format!(" class=\"language-{}\"", escape_html(language))
+                };
+                let _ = writeln!(
+                    html,
+                    "<pre><code{class}>{}</code></pre>",
+                    escape_html(&lines.join("\n"))
+                );
+                code = None;
+            } else {
+                lines.push(line);
+            }
+        } else if let Some(language) = fence {
+            flush(&mut html, &mut paragraph);
+            code = Some((language.trim().to_string(), Vec::new()));
+        } else if line.trim().is_empty() {
+            flush(&mut html, &mut paragraph);
+        } else {
+            paragraph.push(line);
+        }
+    }
+    // A fence left open still reads best as code.
+    if let Some((_, lines)) = code {
+        let _ = writeln!(
+            html,
+            "<pre><code>{}</code></pre>",
+            escape_html(&lines.join("\n"))
+        );
+    }
+    flush(&mut html, &mut paragraph);
+    html
+}
+
+const HTML_STYLE: &str = "body{font-family:system-ui,sans-serif;max-width:48rem;margin:2rem auto;\
+padding:0 1rem;line-height:1.5;color:#1f2328}\
+.message{border-top:1px solid #d0d7de;padding:.5rem 0}\
+.meta{color:#656d76;font-size:.875rem}\
+pre{background:#f6f8fa;padding:.75rem;overflow:auto;border-radius:6px}";
+
+fn render_html(export: &ConversationExport) -> String {
+    let conversation = &export.conversation;
+    let title = escape_html(&conversation.title);
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n\
+         <style>{HTML_STYLE}</style>\n</head>\n<body>\n<h1>{title}</h1>\n\
+         <p class=\"meta\">Started {}, last updated {}</p>\n",
+        timestamp(conversation.created_at),
+        timestamp(conversation.updated_at)
+    );
+    for message in &export.messages {
+        let _ = write!(
+            out,
+            "<section class=\"message {}\">\n<p class=\"meta\">{} · {}</p>\n{}</section>\n",
+            escape_html(&message.role),
+            escape_html(&role_label(&message.role)),
+            timestamp(message.created_at),
+            markdown_to_html(&message.content)
+        );
+    }
+    let attachments = attachments(export);
+    if !attachments.is_empty() {
+        out.push_str("<h2>Attachments</h2>\n<ul>\n");
+        for attachment in attachments {
+            let _ = writeln!(
+                out,
+                "<li>{}: <code>{}</code></li>",
+                escape_html(&attachment.name),
+                escape_html(&attachment.target)
+            );
+        }
+        out.push_str("</ul>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+fn render_json(export: &ConversationExport) -> Result<String, String> {
+    serde_json::to_string_pretty(&JsonExport {
+        version: EXPORT_VERSION,
+        exported_at: crate::db::now_ms(),
+        export,
+        attachments: attachments(export),
+    })
+    .map_err(|e| e.to_string())
+}
+
+pub fn render(export: &ConversationExport, format: ExportFormat) -> Result<String, String> {
+    match format {
+        ExportFormat::Markdown => Ok(render_markdown(export)),
+        ExportFormat::Json => render_json(export),
+        ExportFormat::Html => Ok(render_html(export)),
+    }
+}
+
+/// A file name for a conversation that any file system accepts and that stays unique.
+fn file_stem(conversation: &Conversation) -> String {
+    let mut slug: String = conversation
+        .title
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '-' })
+        .take(48)
+        .collect();
+    slug = slug.trim_matches('-').to_lowercase();
+    let short_id: String = conversation.id.chars().take(8).collect();
+    if slug.is_empty() {
+        short_id
+    } else {
+        format!("{slug}-{short_id}")
+    }
+}
+
+/// Write a conversation to `path` as Markdown, JSON or a standalone HTML page.
+#[tauri::command]
+pub async fn export_conversation(
+    app: tauri::AppHandle,
+    db: State<'_, Database>,
+    id: String,
+    format: ExportFormat,
+    path: PathBuf,
+) -> Result<(), AppError> {
+    let export = history::conversation_export(&db, &id)?;
+    let contents = render(&export, format)?;
+    capabilities::require_path(&app, &path, "Export a conversation").await?;
+    fs::write(&path, contents).map_err(|e| AppError::from(e.to_string()))
+}
+
+/// Publish the Markdown transcript as a gist with the saved GitHub token, returning
+/// the gist's URL.
+async fn publish_gist(
+    app: &tauri::AppHandle,
+    export: &ConversationExport,
+    public: bool,
+) -> Result<String, AppError> {
+    let token = secrets::api_key(GITHUB_TOKEN)?.ok_or_else(|| AppError::ProviderAuth {
+        provider: GITHUB_TOKEN.to_string(),
+        message: "No GitHub token saved; save one with the gist scope to share gists".to_string(),
+    })?;
+    let file = format!("{}.md", file_stem(&export.conversation));
+    let request = http::client(app)?
+        .post(GISTS_URL)
+        .bearer_auth(token)
+        .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+        .header(
+            reqwest::header::USER_AGENT,
+            concat!("aikeya/", env!("CARGO_PKG_VERSION")),
+        )
+        .header("X-GitHub-Api-Version", "2022-11-28")
+        .json(&json!({
+            "description": export.conversation.title,
+            "public": public,
+            "files": { file: { "content": render_markdown(export) } },
+        }));
+    let response = http::send(app, GITHUB_TOKEN, None, request)
+        .await
+        .map_err(|e| AppError::Network(format!("Could not reach GitHub: {e}")))?;
+    let gist: Value = service_error_for_status(GITHUB_TOKEN, "GitHub", response)
+        .await?
+        .json()
+        .await?;
+    gist["html_url"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| AppError::from("GitHub sent no gist URL"))
+}
+
+/// Share a conversation as a standalone HTML file, a Markdown transcript on the
+/// clipboard or a GitHub Gist, returning the file's path or the gist's URL.
+#[tauri::command]
+pub async fn share_conversation(
+    app: tauri::AppHandle,
+    id: String,
+    target: ShareTarget,
+) -> Result<Shared, AppError> {
+    let export = history::conversation_export(&app.state::<Database>(), &id)?;
+    match target {
+        ShareTarget::Html { path } => {
+            let path = match path {
+                Some(path) => path,
+                None => app
+                    .path()
+                    .download_dir()
+                    .map_err(|e| AppError::from(e.to_string()))?
+                    .join(format!("{}.html", file_stem(&export.conversation))),
+            };
+            capabilities::require_path(&app, &path, "Share a conversation").await?;
+            fs::write(&path, render_html(&export)).map_err(|e| AppError::from(e.to_string()))?;
+            Ok(Shared::File { path })
+        }
+        ShareTarget::Clipboard => {
+            let text = render_markdown(&export);
+            clipboard::write(&app, &ClipboardContent::Text { text })?;
+            Ok(Shared::Clipboard)
+        }
+        ShareTarget::Gist { public } => {
+            let url = publish_gist(&app, &export, public).await?;
+            tracing::info!("Shared conversation {id} as {url}");
+            Ok(Shared::Gist { url })
+        }
+    }
+}
+
+fn write_backup(path: &Path, exports: &[ConversationExport]) -> Result<(), String> {
+    let partial = path.with_extension("zip.part");
+    let file = fs::File::create(&partial).map_err(|e| e.to_string())?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    for export in exports {
+        let stem = file_stem(&export.conversation);
+        for (dir, format, extension) in [
+            ("json", ExportFormat::Json, "json"),
+            ("markdown", ExportFormat::Markdown, "md"),
+        ] {
+            zip.start_file(format!("{dir}/{stem}.{extension}"), options)
+                .map_err(|e| e.to_string())?;
+            zip.write_all(render(export, format)?.as_bytes())
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    zip.finish().map_err(|e| e.to_string())?;
+    fs::rename(&partial, path).map_err(|e| e.to_string())
+}
+
+/// Write every conversation into the archive at `path`, the job behind `export_all`.
+pub(crate) async fn write_all(
+    app: tauri::AppHandle,
+    path: PathBuf,
+) -> Result<BackupSummary, AppError> {
+    let exports = app.state::<Database>().with(|conn| {
+        let ids = conn
+            .prepare("SELECT id FROM conversations ORDER BY created_at")?
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let mut exports = Vec::with_capacity(ids.len());
+        for id in ids {
+            exports.extend(history::load_export(conn, &id)?);
+        }
+        Ok(exports)
+    })?;
+    let summary = BackupSummary {
+        path: path.clone(),
+        conversations: exports.len(),
+        messages: exports.iter().map(|export| export.messages.len()).sum(),
+    };
+    tauri::async_runtime::spawn_blocking(move || write_backup(&path, &exports))
+        .await?
+        .map_err(AppError::from)?;
+    tracing::info!(
+        "Backed up {} conversations to {}",
+        summary.conversations,
+        summary.path.display()
+    );
+    Ok(summary)
+}
+
+/// Back up every conversation into one zip archive as a job, as JSON for restoring and
+/// Markdown for reading. Without a `path` it goes to the downloads folder.
+#[tauri::command]
+pub async fn export_all(
+    app: tauri::AppHandle,
+    path: Option<PathBuf>,
+) -> Result<BackupSummary, AppError> {
+    let path = match path {
+        Some(path) => path,
+        None => app
+            .path()
+            .download_dir()
+            .map_err(|e| AppError::from(e.to_string()))?
+            .join(format!(
+                "aikeya-backup-{}.zip",
+                Local::now().format("%Y%m%d-%H%M%S")
+            )),
+    };
+    capabilities::require_path(&app, &path, "Export history").await?;
+    let task_app = app.clone();
+    let spec = JobSpec::ExportAll { path: path.clone() };
+    jobs::run(&app, None, spec, move |_| write_all(task_app, path)).await
+}
@@ -0,0 +1,489 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::State as Extract;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, Listener, Manager, State};
+use tokio::sync::mpsc;
+
+use crate::error::AppError;
+use crate::local_api::{self, Running};
+use crate::{db, secrets, settings};
+
+/// Keychain entry holding the paired clients as JSON.
+const CLIENTS_SECRET: &str = "extension-clients";
+const PAIRING_TTL: Duration = Duration::from_secs(120);
+/// Only extension pages may connect; a website's origin is always http(s).
+const ALLOWED_ORIGINS: &[&str] = &["chrome-extension://", "moz-extension://"];
+
+/// The WebSocket server the browser extension talks to, and its open connections.
+#[derive(Default)]
+pub struct ExtensionBridge(Mutex<Bridge>);
+
+#[derive(Default)]
+struct Bridge {
+    server: Option<Running>,
+    connections: HashMap<u64, Connection>,
+    /// Pairing requests waiting for the user, by code.
+    pairings: HashMap<String, Pairing>,
+    context: Option<BrowserContext>,
+    next_connection: u64,
+}
+
+struct Connection {
+    /// The paired client this connection has said hello as, if any.
+    client_id: Option<String>,
+    outgoing: mpsc::UnboundedSender<Outgoing>,
+}
+
+struct Pairing {
+    connection: u64,
+    name: String,
+    expires: Instant,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredClient {
+    id: String,
+    name: String,
+    token: String,
+    paired_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtensionClient {
+    pub id: String,
+    pub name: String,
+    pub paired_at: i64,
+    pub connected: bool,
+}
+
+/// What the extension last reported about the page the user is on.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BrowserContext {
+    pub client_id: String,
+    pub url: String,
+    pub title: Option<String>,
+    pub selection: Option<String>,
+    pub received_at: i64,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum Incoming {
+    /// Authenticate with the token handed out at pairing.
+    Hello { token: String },
+    /// Ask the user to pair this extension.
+    Pair { name: String },
+    Context {
+        url: String,
+        title: Option<String>,
+        selection: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum Outgoing {
+    #[serde(rename_all = "camelCase")]
+    Welcome {
+        client_id: String,
+    },
+    Pairing {
+        code: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    Paired {
+        token: String,
+        client_id: String,
+    },
+    /// Text for the extension to insert into the page.
+    Insert {
+        text: String,
+    },
+    Error {
+        message: String,
+    },
+}
+
+fn error(message: impl Into<String>) -> Outgoing {
+    Outgoing::Error {
+        message: message.into(),
+    }
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PairingRequest<'a> {
+    code: &'a str,
+    name: &'a str,
+}
+
+fn load_clients() -> Result<Vec<StoredClient>, String> {
+    match secrets::app_secret(CLIENTS_SECRET)? {
+        Some(json) => serde_json::from_str(&json).map_err(|e| e.to_string()),
+        None => Ok(Vec::new()),
+    }
+}
+
+fn save_clients(clients: &[StoredClient]) -> Result<(), String> {
+    let json = serde_json::to_string(clients).map_err(|e| e.to_string())?;
+    secrets::set_app_secret(CLIENTS_SECRET, &json)
+}
+
+fn pairing_code() -> String {
+    format!("{:06}", uuid::Uuid::new_v4().as_u128() % 1_000_000)
+}
+
+fn send(bridge: &Bridge, connection: u64, message: Outgoing) {
+    if let Some(connection) = bridge.connections.get(&connection) {
+        let _ = connection.outgoing.send(message);
+    }
+}
+
+fn handle(app: &tauri::AppHandle, connection: u64, incoming: Incoming) -> Option<Outgoing> {
+    let state = app.state::<ExtensionBridge>();
+    match incoming {
+        Incoming::Hello { token } => {
+            let clients = match load_clients() {
+                Ok(clients) => clients,
+                Err(e) => return Some(error(e)),
+            };
+            let Some(client) = clients
+                .into_iter()
+                .find(|client| local_api::same_token(&client.token, &token))
+            else {
+                return Some(error("Unknown token, pair again"));
+            };
+            let mut bridge = state.0.lock().unwrap();
+            if let Some(connection) = bridge.connections.get_mut(&connection) {
+                connection.client_id = Some(client.id.clone());
+            }
+            Some(Outgoing::Welcome {
+                client_id: client.id,
+            })
+        }
+        Incoming::Pair { name } => {
+            let code = pairing_code();
+            let mut bridge = state.0.lock().unwrap();
+            bridge.pairings.retain(|_, p| p.expires > Instant::now());
+            bridge.pairings.insert(
+                code.clone(),
+                Pairing {
+                    connection,
+                    name: name.clone(),
+                    expires: Instant::now() + PAIRING_TTL,
+                },
+            );
+            let _ = app.emit(
+                "extension-pairing-request",
+                PairingRequest {
+                    code: &code,
+                    name: &name,
+                },
+            );
+            Some(Outgoing::Pairing { code })
+        }
+        Incoming::Context {
+            url,
+            title,
+            selection,
+        } => {
+            let mut bridge = state.0.lock().unwrap();
+            let Some(client_id) = bridge
+                .connections
+                .get(&connection)
+                .and_then(|c| c.client_id.clone())
+            else {
+                return Some(error("Say hello before sending context"));
+            };
+            let context = BrowserContext {
+                client_id,
+                url,
+                title,
+                selection: selection.filter(|s| !s.trim().is_empty()),
+                received_at: db::now_ms(),
+            };
+            let _ = app.emit("browser-context", &context);
+            bridge.context = Some(context);
+            None
+        }
+    }
+}
+
+/// One extension connection: replies and pushes go through the connection's channel, so
+/// dropping it from the bridge (on revoke or shutdown) closes the socket.
+async fn session(app: tauri::AppHandle, socket: WebSocket) {
+    let (mut sink, mut stream) = socket.split();
+    let (outgoing, mut queue) = mpsc::unbounded_channel::<Outgoing>();
+    let id = {
+        let state = app.state::<ExtensionBridge>();
+        let mut bridge = state.0.lock().unwrap();
+        bridge.next_connection += 1;
+        let id = bridge.next_connection;
+        bridge.connections.insert(
+            id,
+            Connection {
+                client_id: None,
+                outgoing,
+            },
+        );
+        id
+    };
+    let writer = tauri::async_runtime::spawn(async move {
+        while let Some(message) = queue.recv().await {
+            let Ok(text) = serde_json::to_string(&message) else {
+                continue;
+            };
+            if sink.send(Message::Text(text.into())).await.is_err() {
+                return;
+            }
+        }
+        let _ = sink.send(Message::Close(None)).await;
+    });
+
+    while let Some(Ok(message)) = stream.next().await {
+        let text = match message {
+            Message::Text(text) => text,
+            Message::Close(_) => break,
+            _ => continue,
+        };
+        let reply = match serde_json::from_str::<Incoming>(&text) {
+            Ok(incoming) => handle(&app, id, incoming),
+            Err(e) => Some(error(format!("Unrecognised message: {e}"))),
+        };
+        if let Some(reply) = reply {
+            send(&app.state::<ExtensionBridge>().0.lock().unwrap(), id, reply);
+        }
+    }
+
+    let state = app.state::<ExtensionBridge>();
+    let mut bridge = state.0.lock().unwrap();
+    bridge.connections.remove(&id);
+    bridge.pairings.retain(|_, p| p.connection != id);
+    drop(bridge);
+    writer.abort();
+}
+
+async fn connect(
+    Extract(app): Extract<tauri::AppHandle>,
+    headers: HeaderMap,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    let origin = headers
+        .get(header::ORIGIN)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    if !ALLOWED_ORIGINS
+        .iter()
+        .any(|prefix| origin.starts_with(prefix))
+    {
+        return (
+            StatusCode::FORBIDDEN,
+            "Only the browser extension may connect",
+        )
+            .into_response();
+    }
+    upgrade.on_upgrade(move |socket| session(app, socket))
+}
+
+/// Start, stop or move the server so it matches the extension settings.
+fn sync(app: &tauri::AppHandle) -> Result<(), String> {
+    let config = settings::current(app).extension;
+    let wanted = config.enabled.then_some(config.port);
+    let state = app.state::<ExtensionBridge>();
+    let mut bridge = state.0.lock().unwrap();
+    if bridge.server.as_ref().map(|r| r.port) == wanted {
+        return Ok(());
+    }
+    bridge.server = None;
+    // Upgraded sockets outlive the server, so close them explicitly.
+    bridge.connections.clear();
+    bridge.pairings.clear();
+    if let Some(port) = wanted {
+        let router = Router::new()
+            .route("/", get(connect))
+            .with_state(app.clone());
+        bridge.server = Some(local_api::serve("Extension bridge", port, router)?);
+    }
+    Ok(())
+}
+
+/// Serve the bridge if it's enabled, and follow the setting from then on.
+pub fn init(app: &tauri::AppHandle) {
+    if let Err(error) = sync(app) {
+        tracing::error!("{error}");
+    }
+    let handle = app.clone();
+    app.listen("settings-changed", move |_| {
+        if let Err(error) = sync(&handle) {
+            tracing::error!("{error}");
+        }
+    });
+}
+
+/// Pair the extension that showed `code`, handing it a token for future connections.
+#[tauri::command]
+pub async fn approve_extension_pairing(
+    state: State<'_, ExtensionBridge>,
+    code: String,
+) -> Result<ExtensionClient, AppError> {
+    let pairing = {
+        let mut bridge = state.0.lock().unwrap();
+        bridge
+            .pairings
+            .remove(&code)
+            .filter(|p| p.expires > Instant::now())
+            .ok_or("That pairing code has expired")?
+    };
+    let client = StoredClient {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: pairing.name,
+        token: local_api::new_token(),
+        paired_at: db::now_ms(),
+    };
+    let mut clients = load_clients()?;
+    clients.push(client.clone());
+    save_clients(&clients)?;
+
+    let mut bridge = state.0.lock().unwrap();
+    let connected = match bridge.connections.get_mut(&pairing.connection) {
+        Some(connection) => {
+            connection.client_id = Some(client.id.clone());
+            true
+        }
+        None => false,
+    };
+    send(
+        &bridge,
+        pairing.connection,
+        Outgoing::Paired {
+            token: client.token,
+            client_id: client.id.clone(),
+        },
+    );
+    tracing::info!("Paired browser extension {}", client.name);
+    Ok(ExtensionClient {
+        id: client.id,
+        name: client.name,
+        paired_at: client.paired_at,
+        connected,
+    })
+}
+
+/// Turn down a pairing request. Returns `false` if the code wasn't pending.
+#[tauri::command]
+pub fn reject_extension_pairing(state: State<'_, ExtensionBridge>, code: String) -> bool {
+    let mut bridge = state.0.lock().unwrap();
+    let Some(pairing) = bridge.pairings.remove(&code) else {
+        return false;
+    };
+    send(&bridge, pairing.connection, error("Pairing was declined"));
+    true
+}
+
+#[tauri::command]
+pub async fn list_extension_clients(
+    state: State<'_, ExtensionBridge>,
+) -> Result<Vec<ExtensionClient>, AppError> {
+    let clients = load_clients()?;
+    let bridge = state.0.lock().unwrap();
+    Ok(clients
+        .into_iter()
+        .map(|client| ExtensionClient {
+            connected: bridge
+                .connections
+                .values()
+                .any(|c| c.client_id.as_deref() == Some(client.id.as_str())),
+            id: client.id,
+            name: client.name,
+            paired_at: client.paired_at,
+        })
+        .collect())
+}
+
+/// Forget a paired client and disconnect it. Returns whether it was paired.
+#[tauri::command]
+pub async fn revoke_extension_client(
+    state: State<'_, ExtensionBridge>,
+    id: String,
+) -> Result<bool, AppError> {
+    let mut clients = load_clients()?;
+    let before = clients.len();
+    clients.retain(|client| client.id != id);
+    if clients.len() == before {
+        return Ok(false);
+    }
+    save_clients(&clients)?;
+    let mut bridge = state.0.lock().unwrap();
+    bridge
+        .connections
+        .retain(|_, c| c.client_id.as_deref() != Some(id.as_str()));
+    if bridge.context.as_ref().is_some_and(|c| c.client_id == id) {
+        bridge.context = None;
+    }
+    Ok(true)
+}
+
+#[tauri::command]
+pub fn get_browser_context(state: State<'_, ExtensionBridge>) -> Option<BrowserContext> {
+    state.0.lock().unwrap().context.clone()
+}
+
+/// Have the extension insert `text` into the page, on one client or all of them. Returns
+/// how many connections it was sent to.
+#[tauri::command]
+pub fn send_to_extension(
+    state: State<'_, ExtensionBridge>,
+    text: String,
+    client_id: Option<String>,
+) -> usize {
+    let bridge = state.0.lock().unwrap();
+    bridge
+        .connections
+        .values()
+        .filter(|c| match (&c.client_id, &client_id) {
+            (Some(id), Some(wanted)) => id == wanted,
+            (Some(_), None) => true,
+            (None, _) => false,
+        })
+        .filter(|c| {
+            c.outgoing
+                .send(Outgoing::Insert { text: text.clone() })
+                .is_ok()
+        })
+        .count()
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtensionBridgeStatus {
+    pub running: bool,
+    pub url: Option<String>,
+    /// Connections that have authenticated as a paired client.
+    pub connected: usize,
+}
+
+#[tauri::command]
+pub fn get_extension_bridge_status(state: State<'_, ExtensionBridge>) -> ExtensionBridgeStatus {
+    let bridge = state.0.lock().unwrap();
+    let port = bridge.server.as_ref().map(|r| r.port);
+    ExtensionBridgeStatus {
+        running: port.is_some(),
+        url: port.map(|port| format!("ws://127.0.0.1:{port}")),
+        connected: bridge
+            .connections
+            .values()
+            .filter(|c| c.client_id.is_some())
+            .count(),
+    }
+}
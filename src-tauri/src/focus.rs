@@ -0,0 +1,447 @@
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::Serialize;
+use tauri::Manager;
+
+use crate::settings;
+
+/// The window that had focus before the overlay took it. Opaque outside the platform
+/// module: an HWND on Windows, a pid on macOS and an X11 window id on Linux.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ForegroundWindow(u64);
+
+impl ForegroundWindow {
+    /// The platform's handle, for modules that act on the window itself.
+    pub(crate) fn raw(self) -> u64 {
+        self.0
+    }
+}
+
+/// What the user was working in when they summoned the overlay.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppContext {
+    pub app_name: Option<String>,
+    pub window_title: Option<String>,
+    /// Bundle identifier on macOS, `WM_CLASS` on X11; Windows apps are told apart by
+    /// `executable_path`.
+    pub app_id: Option<String>,
+    pub executable_path: Option<PathBuf>,
+    pub pid: Option<u32>,
+}
+
+impl AppContext {
+    /// Whether this is `app`, compared case-insensitively with the app id, the
+    /// executable's file name (with or without extension) and the app name.
+    pub fn is_app(&self, app: &str) -> bool {
+        let wanted = app.trim();
+        let executable = self.executable_path.as_deref();
+        [
+            self.app_id.as_deref(),
+            executable
+                .and_then(|path| path.file_name())
+                .and_then(|name| name.to_str()),
+            executable
+                .and_then(|path| path.file_stem())
+                .and_then(|stem| stem.to_str()),
+            self.app_name.as_deref(),
+        ]
+        .into_iter()
+        .flatten()
+        .any(|candidate| candidate.eq_ignore_ascii_case(wanted))
+    }
+}
+
+/// Whether now is a bad time to interrupt the user.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FocusState {
+    /// Do not disturb, focus assist or a Focus mode is on; `None` where it can't be read.
+    pub do_not_disturb: Option<bool>,
+    /// The app in front fills the screen, like a game or a presentation; `None` where
+    /// other windows can't be inspected.
+    pub full_screen: Option<bool>,
+    /// Notifications and automatic overlay popups are being held back, per
+    /// `general.quietWhenBusy`.
+    pub suppressing: bool,
+}
+
+struct Previous {
+    window: ForegroundWindow,
+    context: AppContext,
+}
+
+#[derive(Default)]
+pub struct FocusTracker(Mutex<Option<Previous>>);
+
+#[cfg(windows)]
+mod platform {
+    use std::ffi::c_void;
+    use std::path::PathBuf;
+    use std::ptr;
+
+    use windows::core::PWSTR;
+    use windows::Win32::Foundation::{CloseHandle, HWND};
+    use windows::Win32::System::Threading::{
+        OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32,
+        PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+    use windows::Win32::UI::Shell::{
+        SHQueryUserNotificationState, QUNS_BUSY, QUNS_PRESENTATION_MODE,
+        QUNS_RUNNING_D3D_FULL_SCREEN,
+    };
+    use windows::Win32::UI::WindowsAndMessaging::{
+        GetForegroundWindow, GetWindowTextW, GetWindowThreadProcessId, IsIconic,
+        SetForegroundWindow, ShowWindow, SW_RESTORE,
+    };
+
+    use super::{AppContext, ForegroundWindow};
+
+    /// `WNF_SHEL_QUIETHOURS_ACTIVE_PROFILE_CHANGED`, where the shell publishes the focus
+    /// assist profile: 0 when off, 1 for priority only and 2 for alarms only.
+    const QUIET_HOURS_STATE: u64 = 0x0D83_063E_A3BF_1C75;
+
+    #[link(name = "ntdll")]
+    extern "system" {
+        fn NtQueryWnfStateData(
+            state_name: *const u64,
+            type_id: *const c_void,
+            explicit_scope: *const c_void,
+            change_stamp: *mut u32,
+            buffer: *mut c_void,
+            buffer_size: *mut u32,
+        ) -> i32;
+    }
+
+    fn executable_path(pid: u32) -> Option<PathBuf> {
+        // SAFETY: the process handle is closed before returning and the buffer length is
+        // passed alongside it.
+        unsafe {
+            let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+            let mut buffer = [0u16; 1024];
+            let mut len = buffer.len() as u32;
+            let result = QueryFullProcessImageNameW(
+                process,
+                PROCESS_NAME_WIN32,
+                PWSTR(buffer.as_mut_ptr()),
+                &mut len,
+            );
+            let _ = CloseHandle(process);
+            result.ok()?;
+            Some(PathBuf::from(String::from_utf16_lossy(
+                &buffer[..len as usize],
+            )))
+        }
+    }
+
+    pub fn foreground() -> Option<(ForegroundWindow, AppContext)> {
+        // SAFETY: plain Win32 queries on a window handle the system just gave us.
+        let (hwnd, pid, title) = unsafe {
+            let hwnd = GetForegroundWindow();
+            if hwnd.is_invalid() {
+                return None;
+            }
+            let mut pid = 0;
+            GetWindowThreadProcessId(hwnd, Some(&mut pid));
+            let mut buffer = [0u16; 512];
+            let len = GetWindowTextW(hwnd, &mut buffer).max(0) as usize;
+            (hwnd, pid, String::from_utf16_lossy(&buffer[..len]))
+        };
+        if pid == std::process::id() {
+            return None;
+        }
+        let executable_path = executable_path(pid);
+        let context = AppContext {
+            app_name: executable_path
+                .as_ref()
+                .and_then(|path| path.file_stem())
+                .map(|stem| stem.to_string_lossy().into_owned()),
+            window_title: Some(title).filter(|t| !t.is_empty()),
+            app_id: None,
+            executable_path,
+            pid: Some(pid),
+        };
+        Some((ForegroundWindow(hwnd.0 as u64), context))
+    }
+
+    pub fn activate(window: ForegroundWindow) -> Result<(), String> {
+        let hwnd = HWND(window.0 as *mut _);
+        // SAFETY: a stale handle just makes these calls fail.
+        unsafe {
+            if IsIconic(hwnd).as_bool() {
+                let _ = ShowWindow(hwnd, SW_RESTORE);
+            }
+            // We own the foreground right now, which is what lets us hand it on.
+            if SetForegroundWindow(hwnd).as_bool() {
+                Ok(())
+            } else {
+                Err("Could not focus the previous window".to_string())
+            }
+        }
+    }
+
+    /// Focus assist, called do not disturb on Windows 11, has no public API, so this reads
+    /// the state the shell itself watches.
+    pub fn do_not_disturb() -> Option<bool> {
+        let mut profile = 0u32;
+        let mut size = std::mem::size_of::<u32>() as u32;
+        let mut change_stamp = 0;
+        // SAFETY: the buffer and its size describe `profile`, which outlives the call.
+        let status = unsafe {
+            NtQueryWnfStateData(
+                &QUIET_HOURS_STATE,
+                ptr::null(),
+                ptr::null(),
+                &mut change_stamp,
+                ptr::addr_of_mut!(profile).cast(),
+                &mut size,
+            )
+        };
+        (status >= 0).then_some(profile != 0)
+    }
+
+    pub fn full_screen() -> Option<bool> {
+        // SAFETY: takes no input; the shell reports on whatever is in front.
+        let state = unsafe { SHQueryUserNotificationState() }.ok()?;
+        Some(
+            [
+                QUNS_BUSY,
+                QUNS_RUNNING_D3D_FULL_SCREEN,
+                QUNS_PRESENTATION_MODE,
+            ]
+            .contains(&state),
+        )
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::path::PathBuf;
+
+    use accessibility_sys::{
+        kAXErrorSuccess, kAXFocusedWindowAttribute, kAXTitleAttribute,
+        AXUIElementCopyAttributeValue, AXUIElementCreateApplication, AXUIElementRef,
+    };
+    use core_foundation::base::{CFType, CFTypeRef, TCFType};
+    use core_foundation::boolean::CFBoolean;
+    use core_foundation::string::CFString;
+    use objc2_app_kit::{NSApplicationActivationOptions, NSRunningApplication, NSWorkspace};
+
+    use super::{AppContext, ForegroundWindow};
+
+    unsafe fn copy_attribute(element: &CFType, name: &str) -> Option<CFType> {
+        let attribute = CFString::new(name);
+        let mut value: CFTypeRef = std::ptr::null();
+        let status = AXUIElementCopyAttributeValue(
+            element.as_CFTypeRef() as AXUIElementRef,
+            attribute.as_concrete_TypeRef(),
+            &mut value,
+        );
+        if status != kAXErrorSuccess || value.is_null() {
+            return None;
+        }
+        Some(CFType::wrap_under_create_rule(value))
+    }
+
+    /// An attribute of the app's focused window. Needs the accessibility permission, so
+    /// this is `None` until the user has granted it.
+    fn focused_window_attribute(pid: i32, name: &str) -> Option<CFType> {
+        // SAFETY: every returned reference is owned by a CFType wrapper that releases it.
+        unsafe {
+            let app =
+                CFType::wrap_under_create_rule(AXUIElementCreateApplication(pid) as CFTypeRef);
+            let window = copy_attribute(&app, kAXFocusedWindowAttribute)?;
+            copy_attribute(&window, name)
+        }
+    }
+
+    fn focused_window_title(pid: i32) -> Option<String> {
+        focused_window_attribute(pid, kAXTitleAttribute)?
+            .downcast::<CFString>()
+            .map(|s| s.to_string())
+    }
+
+    pub fn foreground() -> Option<(ForegroundWindow, AppContext)> {
+        let app = NSWorkspace::sharedWorkspace().frontmostApplication()?;
+        let pid = app.processIdentifier();
+        if pid <= 0 || pid as u32 == std::process::id() {
+            return None;
+        }
+        let context = AppContext {
+            app_name: app.localizedName().map(|name| name.to_string()),
+            window_title: focused_window_title(pid),
+            app_id: app.bundleIdentifier().map(|id| id.to_string()),
+            executable_path: app
+                .executableURL()
+                .and_then(|url| url.path())
+                .map(|path| PathBuf::from(path.to_string())),
+            pid: Some(pid as u32),
+        };
+        Some((ForegroundWindow(pid as u64), context))
+    }
+
+    pub fn activate(window: ForegroundWindow) -> Result<(), String> {
+        let app = NSRunningApplication::runningApplicationWithProcessIdentifier(window.0 as i32)
+            .ok_or_else(|| "The previous application is no longer running".to_string())?;
+        if app.activateWithOptions(NSApplicationActivationOptions::ActivateAllWindows) {
+            Ok(())
+        } else {
+            Err("Could not activate the previous application".to_string())
+        }
+    }
+
+    /// Focus modes have no public API. Since macOS 12 the ones switched on by hand are
+    /// recorded in this file, which may need Full Disk Access to read.
+    pub fn do_not_disturb() -> Option<bool> {
+        let path = PathBuf::from(std::env::var_os("HOME")?)
+            .join("Library/DoNotDisturb/DB/Assertions.json");
+        let assertions: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(path).ok()?).ok()?;
+        let mut stores = assertions["data"].as_array()?.iter();
+        Some(stores.any(|store| {
+            store["storeAssertionRecords"]
+                .as_array()
+                .is_some_and(|records| !records.is_empty())
+        }))
+    }
+
+    pub fn full_screen() -> Option<bool> {
+        let app = NSWorkspace::sharedWorkspace().frontmostApplication()?;
+        let pid = app.processIdentifier();
+        if pid as u32 == std::process::id() {
+            return Some(false);
+        }
+        focused_window_attribute(pid, "AXFullScreen")?
+            .downcast::<CFBoolean>()
+            .map(bool::from)
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::process::Command;
+
+    use super::{AppContext, ForegroundWindow};
+    use crate::x11;
+
+    pub fn foreground() -> Option<(ForegroundWindow, AppContext)> {
+        let (conn, root) = x11::connect_for_windows().ok()?;
+        let window = x11::foreground_window(&conn, root).ok()?;
+        let pid = x11::window_pid(&conn, window);
+        let executable_path =
+            pid.and_then(|pid| std::fs::read_link(format!("/proc/{pid}/exe")).ok());
+        let app_id = x11::window_class(&conn, window);
+        let context = AppContext {
+            app_name: app_id.clone().or_else(|| {
+                executable_path
+                    .as_ref()
+                    .and_then(|path| path.file_name())
+                    .map(|name| name.to_string_lossy().into_owned())
+            }),
+            window_title: x11::window_title(&conn, window),
+            app_id,
+            executable_path,
+            pid,
+        };
+        Some((ForegroundWindow(window.into()), context))
+    }
+
+    pub fn activate(window: ForegroundWindow) -> Result<(), String> {
+        let (conn, root) = x11::connect_for_windows()?;
+        x11::activate(&conn, root, window.0 as u32)
+    }
+
+    /// GNOME turns notification banners off for do not disturb. Other desktops keep it to
+    /// themselves.
+    pub fn do_not_disturb() -> Option<bool> {
+        let output = Command::new("gsettings")
+            .args(["get", "org.gnome.desktop.notifications", "show-banners"])
+            .output()
+            .ok()?;
+        match String::from_utf8_lossy(&output.stdout).trim() {
+            "false" => Some(true),
+            "true" => Some(false),
+            _ => None,
+        }
+    }
+
+    pub fn full_screen() -> Option<bool> {
+        let (conn, root) = x11::connect_for_windows().ok()?;
+        let window = x11::foreground_window(&conn, root).ok()?;
+        Some(x11::is_fullscreen(&conn, window))
+    }
+}
+
+/// Note which window is in front and what app it belongs to, unless it is one of ours.
+/// Called right before the overlay is shown.
+pub fn remember(app: &tauri::AppHandle) {
+    if let Some((window, context)) = platform::foreground() {
+        *app.state::<FocusTracker>().0.lock().unwrap() = Some(Previous { window, context });
+    }
+}
+
+/// The app in front right now, unless it is one of ours.
+pub fn current_context() -> Option<AppContext> {
+    platform::foreground().map(|(_, context)| context)
+}
+
+/// The window in front right now, or the one the overlay was summoned over when one of
+/// ours is in front.
+pub fn target_window(app: &tauri::AppHandle) -> Option<ForegroundWindow> {
+    platform::foreground()
+        .map(|(window, _)| window)
+        .or_else(|| {
+            let tracker = app.state::<FocusTracker>();
+            let previous = tracker.0.lock().unwrap();
+            previous.as_ref().map(|p| p.window)
+        })
+}
+
+/// The app the user was in before the overlay was last shown.
+pub fn previous_context(app: &tauri::AppHandle) -> Option<AppContext> {
+    let tracker = app.state::<FocusTracker>();
+    let previous = tracker.0.lock().unwrap();
+    previous.as_ref().map(|p| p.context.clone())
+}
+
+/// Give focus back to the window that was in front before the overlay appeared.
+pub fn restore(app: &tauri::AppHandle) -> Result<(), String> {
+    let window = app
+        .state::<FocusTracker>()
+        .0
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|p| p.window)
+        .ok_or_else(|| "No previously focused window".to_string())?;
+    platform::activate(window)
+}
+
+/// Whether do not disturb is on and the app in front is full screen, right now.
+pub fn state(app: &tauri::AppHandle) -> FocusState {
+    let do_not_disturb = platform::do_not_disturb();
+    let full_screen = platform::full_screen();
+    let busy = do_not_disturb == Some(true) || full_screen == Some(true);
+    FocusState {
+        do_not_disturb,
+        full_screen,
+        suppressing: busy && settings::current(app).general.quiet_when_busy,
+    }
+}
+
+/// Whether notifications and popups the user didn't ask for should be held back. The
+/// setting is checked first so the platform is only asked when it matters.
+pub fn should_stay_quiet(app: &tauri::AppHandle) -> bool {
+    settings::current(app).general.quiet_when_busy && state(app).suppressing
+}
+
+#[tauri::command]
+pub async fn get_focus_state(app: tauri::AppHandle) -> FocusState {
+    state(&app)
+}
+
+#[tauri::command]
+pub fn get_previous_app_context(app: tauri::AppHandle) -> Option<AppContext> {
+    previous_context(&app)
+}
@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::{Emitter, Manager, State};
+use tokio::sync::oneshot;
+
+use crate::error::AppError;
+use crate::imaging::{EncodedImage, ImageSource};
+use crate::llm::ChatMessage;
+use crate::providers::ProviderInfo;
+use crate::{capabilities, screenshot, settings};
+
+/// Unanswered confirmations count as a no after this long.
+const CONFIRM_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Prompts waiting for `confirm_send` or `cancel_send`, by request id.
+#[derive(Default)]
+pub struct PendingSends(Mutex<HashMap<String, oneshot::Sender<bool>>>);
+
+/// Forgets a prompt's confirmation once it stops waiting, answered or aborted.
+struct Waiting<'a>(&'a PendingSends, &'a str);
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.0 .0.lock().unwrap().remove(self.1);
+    }
+}
+
+/// Why a prompt needs confirming before it is sent.
+#[derive(Debug, Clone, Serialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum GuardrailFlag {
+    /// Text and images together come to more than `guardrails.maxPayloadKb`.
+    LargePayload { size_kb: u64, limit_kb: u32 },
+    /// A file attached from outside `guardrails.allowedDirs`.
+    OutsideFile { path: PathBuf },
+    /// A screenshot taken while one of `guardrails.excludedApps` was in front.
+    ExcludedApp { app: String },
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ConfirmationRequest<'a> {
+    request_id: &'a str,
+    provider: &'a str,
+    flags: &'a [GuardrailFlag],
+}
+
+fn flags(
+    app: &tauri::AppHandle,
+    messages: &[ChatMessage],
+    images: &[Vec<EncodedImage>],
+) -> Vec<GuardrailFlag> {
+    let settings = settings::current(app).guardrails;
+    let mut flags = Vec::new();
+
+    let size: usize = messages.iter().map(|m| m.content.len()).sum::<usize>()
+        + images
+            .iter()
+            .flatten()
+            .map(|i| i.base64.len())
+            .sum::<usize>();
+    let size_kb = ((size + 1023) / 1024) as u64;
+    if size_kb > u64::from(settings.max_payload_kb) {
+        flags.push(GuardrailFlag::LargePayload {
+            size_kb,
+            limit_kb: settings.max_payload_kb,
+        });
+    }
+
+    let allowed: Vec<PathBuf> = settings
+        .allowed_dirs
+        .iter()
+        .map(|dir| fs::canonicalize(dir).unwrap_or_else(|_| dir.clone()))
+        .chain(capabilities::app_dirs(app))
+        .collect();
+    for source in messages.iter().flat_map(|m| &m.images) {
+        let screenshot_id = match source {
+            ImageSource::Path { path } => {
+                let resolved = fs::canonicalize(path).unwrap_or_else(|_| path.clone());
+                if !settings.allowed_dirs.is_empty()
+                    && !allowed.iter().any(|dir| resolved.starts_with(dir))
+                {
+                    flags.push(GuardrailFlag::OutsideFile { path: path.clone() });
+                }
+                // A screenshot can also be attached by its file.
+                path.file_stem().and_then(|stem| stem.to_str())
+            }
+            ImageSource::Screenshot { id } => Some(id.as_str()),
+            ImageSource::Base64 { .. } | ImageSource::Clipboard => None,
+        };
+        let Some(shown) = screenshot_id.and_then(screenshot::shown_app) else {
+            continue;
+        };
+        if let Some(excluded) = settings
+            .excluded_apps
+            .iter()
+            .find(|excluded| shown.is_app(excluded))
+        {
+            flags.push(GuardrailFlag::ExcludedApp {
+                app: excluded.clone(),
+            });
+        }
+    }
+    flags
+}
+
+/// With `guardrails.enabled`, hold a prompt bound for a provider that takes an API key
+/// until the user answers `send-confirmation` if it is too big, attaches a file from
+/// outside the allowed folders or a screenshot of an excluded app.
+pub async fn check(
+    app: &tauri::AppHandle,
+    request_id: &str,
+    provider: &ProviderInfo,
+    messages: &[ChatMessage],
+    images: &[Vec<EncodedImage>],
+) -> Result<(), AppError> {
+    if !provider.requires_api_key || !settings::current(app).guardrails.enabled {
+        return Ok(());
+    }
+    let flags = flags(app, messages, images);
+    if flags.is_empty() {
+        return Ok(());
+    }
+
+    let pending = app.state::<PendingSends>();
+    let (decision, decided) = oneshot::channel();
+    pending
+        .0
+        .lock()
+        .unwrap()
+        .insert(request_id.to_string(), decision);
+    let _waiting = Waiting(&pending, request_id);
+    let _ = app.emit(
+        "send-confirmation",
+        ConfirmationRequest {
+            request_id,
+            provider: &provider.id,
+            flags: &flags,
+        },
+    );
+    let confirmed = matches!(
+        tokio::time::timeout(CONFIRM_TIMEOUT, decided).await,
+        Ok(Ok(true))
+    );
+    if !confirmed {
+        return Err(AppError::PermissionDenied(
+            "The user chose not to send the prompt".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn decide(state: &PendingSends, request_id: &str, send: bool) -> bool {
+    match state.0.lock().unwrap().remove(request_id) {
+        Some(decision) => decision.send(send).is_ok(),
+        None => false,
+    }
+}
+
+/// Let a prompt waiting on `send-confirmation` go to the provider. Returns `false` if it
+/// had already been answered or given up on.
+#[tauri::command]
+pub fn confirm_send(state: State<'_, PendingSends>, request_id: String) -> bool {
+    decide(&state, &request_id, true)
+}
+
+#[tauri::command]
+pub fn cancel_send(state: State<'_, PendingSends>, request_id: String) -> bool {
+    decide(&state, &request_id, false)
+}
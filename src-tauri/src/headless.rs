@@ -0,0 +1,40 @@
+use std::sync::OnceLock;
+
+use crate::error::AppError;
+
+/// Runs the app as a background service: no windows, tray, hotkeys or clipboard watching,
+/// only the local API, the extension bridge, the scheduler and the providers they call.
+pub const FLAG: &str = "--headless";
+
+pub fn active() -> bool {
+    static HEADLESS: OnceLock<bool> = OnceLock::new();
+    *HEADLESS.get_or_init(|| std::env::args().skip(1).any(|arg| arg == FLAG))
+}
+
+/// Keep the windows in the config from being built at launch. Tauri still needs a
+/// display server on Linux, so say so up front instead of letting GTK fail; on a machine
+/// without one, run under a virtual display such as `xvfb-run`.
+pub fn prepare<R: tauri::Runtime>(context: &mut tauri::Context<R>) -> Result<(), String> {
+    #[cfg(target_os = "linux")]
+    if std::env::var_os("DISPLAY").is_none() && std::env::var_os("WAYLAND_DISPLAY").is_none() {
+        return Err(
+            "--headless still needs a display server on Linux; try xvfb-run aikeya --headless"
+                .to_string(),
+        );
+    }
+    for window in &mut context.config_mut().app.windows {
+        window.create = false;
+    }
+    Ok(())
+}
+
+/// Refuse to build a window when running headless.
+pub fn forbid_windows(label: &str) -> Result<(), AppError> {
+    if active() {
+        return Err(AppError::WindowMissing {
+            window: label.to_string(),
+            message: format!("The {label} window isn't available in headless mode"),
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,423 @@
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::Serialize;
+use tauri::State;
+
+use crate::db::{self, Database};
+use crate::error::AppError;
+use crate::{drafts, session, state, summaries};
+
+const DEFAULT_PAGE_SIZE: u32 = 50;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Conversation {
+    pub id: String,
+    pub title: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub message_count: i64,
+    /// Set with `set_conversation_persona`.
+    pub persona_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Message {
+    pub id: String,
+    pub conversation_id: String,
+    pub role: String,
+    pub content: String,
+    pub created_at: i64,
+    /// The message this one follows. Siblings are alternatives to each other.
+    pub parent_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConversationPage {
+    pub conversations: Vec<Conversation>,
+    pub total: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConversationExport {
+    pub conversation: Conversation,
+    pub messages: Vec<Message>,
+}
+
+/// A message matching a history search, with enough of its conversation to list it.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHit {
+    pub message_id: String,
+    pub conversation_id: String,
+    pub conversation_title: String,
+    pub conversation_updated_at: i64,
+    pub role: String,
+    pub snippet: String,
+    pub created_at: i64,
+}
+
+const CONVERSATION_COLUMNS: &str = "c.id, c.title, c.created_at, c.updated_at,
+    (SELECT COUNT(*) FROM messages m WHERE m.conversation_id = c.id), c.persona_id";
+
+const MESSAGE_COLUMNS: &str =
+    "m.id, m.conversation_id, m.role, m.content, m.created_at, m.parent_id";
+
+/// The leaf a conversation shows: the one last chosen, or else its newest message.
+const ACTIVE_LEAF: &str = "COALESCE(
+    (SELECT active_leaf_id FROM conversations WHERE id = ?1),
+    (SELECT id FROM messages WHERE conversation_id = ?1 ORDER BY created_at DESC, rowid DESC LIMIT 1))";
+
+fn conversation_from_row(row: &Row) -> rusqlite::Result<Conversation> {
+    Ok(Conversation {
+        id: row.get(0)?,
+        title: row.get(1)?,
+        created_at: row.get(2)?,
+        updated_at: row.get(3)?,
+        message_count: row.get(4)?,
+        persona_id: row.get(5)?,
+    })
+}
+
+fn message_from_row(row: &Row) -> rusqlite::Result<Message> {
+    Ok(Message {
+        id: row.get(0)?,
+        conversation_id: row.get(1)?,
+        role: row.get(2)?,
+        content: row.get(3)?,
+        created_at: row.get(4)?,
+        parent_id: row.get(5)?,
+    })
+}
+
+pub fn find_message(conn: &Connection, id: &str) -> rusqlite::Result<Option<Message>> {
+    conn.query_row(
+        &format!("SELECT {MESSAGE_COLUMNS} FROM messages m WHERE m.id = ?1"),
+        [id],
+        message_from_row,
+    )
+    .optional()
+}
+
+pub fn find_conversation(conn: &Connection, id: &str) -> rusqlite::Result<Option<Conversation>> {
+    conn.query_row(
+        &format!("SELECT {CONVERSATION_COLUMNS} FROM conversations c WHERE c.id = ?1"),
+        [id],
+        conversation_from_row,
+    )
+    .optional()
+}
+
+/// The messages from the first one down to `id`, in order.
+pub fn path_to(conn: &Connection, id: &str) -> rusqlite::Result<Vec<Message>> {
+    let mut stmt = conn.prepare(&format!(
+        "WITH RECURSIVE path(id, depth) AS (
+             SELECT ?1, 0
+             UNION ALL
+             SELECT m.parent_id, path.depth + 1 FROM messages m JOIN path ON m.id = path.id
+             WHERE m.parent_id IS NOT NULL
+         )
+         SELECT {MESSAGE_COLUMNS} FROM path JOIN messages m ON m.id = path.id
+         ORDER BY path.depth DESC"
+    ))?;
+    let messages = stmt.query_map([id], message_from_row)?.collect();
+    messages
+}
+
+pub fn active_leaf(conn: &Connection, conversation_id: &str) -> rusqlite::Result<Option<String>> {
+    conn.query_row(&format!("SELECT {ACTIVE_LEAF}"), [conversation_id], |row| {
+        row.get(0)
+    })
+}
+
+/// The branch of a conversation that is shown, in order.
+pub fn conversation_messages(conn: &Connection, id: &str) -> rusqlite::Result<Vec<Message>> {
+    match active_leaf(conn, id)? {
+        Some(leaf) => path_to(conn, &leaf),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Add a message after `parent_id` and show the branch it ends.
+pub fn insert_reply(
+    conn: &Connection,
+    conversation_id: &str,
+    parent_id: Option<&str>,
+    role: &str,
+    content: &str,
+) -> rusqlite::Result<Message> {
+    let message = Message {
+        id: uuid::Uuid::new_v4().to_string(),
+        conversation_id: conversation_id.to_string(),
+        role: role.to_string(),
+        content: content.to_string(),
+        created_at: db::now_ms(),
+        parent_id: parent_id.map(str::to_string),
+    };
+    conn.execute(
+        "INSERT INTO messages (id, conversation_id, role, content, created_at, parent_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            message.id,
+            message.conversation_id,
+            message.role,
+            message.content,
+            message.created_at,
+            message.parent_id
+        ],
+    )?;
+    conn.execute(
+        "UPDATE conversations SET updated_at = ?2, active_leaf_id = ?3 WHERE id = ?1",
+        params![conversation_id, message.created_at, message.id],
+    )?;
+    Ok(message)
+}
+
+/// Add a message to the end of the branch that is shown.
+pub fn insert_message(
+    conn: &Connection,
+    conversation_id: &str,
+    role: &str,
+    content: &str,
+) -> rusqlite::Result<Message> {
+    let parent = active_leaf(conn, conversation_id)?;
+    insert_reply(conn, conversation_id, parent.as_deref(), role, content)
+}
+
+#[tauri::command]
+/// Start a conversation and make it the active one.
+pub async fn create_conversation(
+    app: tauri::AppHandle,
+    db: State<'_, Database>,
+    title: Option<String>,
+) -> Result<Conversation, AppError> {
+    let now = db::now_ms();
+    let conversation = Conversation {
+        id: uuid::Uuid::new_v4().to_string(),
+        title: title
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+            .unwrap_or_else(|| "New conversation".to_string()),
+        created_at: now,
+        updated_at: now,
+        message_count: 0,
+        persona_id: None,
+    };
+    db.with(|conn| {
+        conn.execute(
+            "INSERT INTO conversations (id, title, created_at, updated_at) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                conversation.id,
+                conversation.title,
+                conversation.created_at,
+                conversation.updated_at
+            ],
+        )
+    })?;
+    let id = conversation.id.clone();
+    state::update(&app, |session| session.active_conversation = Some(id)).await;
+    Ok(conversation)
+}
+
+/// Add a message to the shown branch. Once an answer is added the older messages may be
+/// summarized in the background, per `history.autoSummarize`.
+#[tauri::command]
+pub async fn append_message(
+    app: tauri::AppHandle,
+    db: State<'_, Database>,
+    conversation_id: String,
+    role: String,
+    content: String,
+) -> Result<Message, AppError> {
+    if !matches!(role.as_str(), "system" | "user" | "assistant" | "tool") {
+        return Err(AppError::InvalidInput(format!(
+            "Unknown message role: {role}"
+        )));
+    }
+    let message = db
+        .with(|conn| {
+            let tx = conn.transaction()?;
+            if find_conversation(&tx, &conversation_id)?.is_none() {
+                return Err(rusqlite::Error::QueryReturnedNoRows);
+            }
+            let message = insert_message(&tx, &conversation_id, &role, &content)?;
+            tx.commit()?;
+            Ok(message)
+        })
+        .map_err(|_| {
+            AppError::NotFound(format!(
+                "Could not add message to conversation {conversation_id}"
+            ))
+        })?;
+    if role == "assistant" {
+        summaries::schedule(&app, &conversation_id);
+    }
+    Ok(message)
+}
+
+#[tauri::command]
+pub async fn list_conversations(
+    db: State<'_, Database>,
+    limit: Option<u32>,
+    offset: Option<u32>,
+) -> Result<ConversationPage, AppError> {
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).min(500);
+    let offset = offset.unwrap_or(0);
+    db.with(|conn| {
+        let total = conn.query_row("SELECT COUNT(*) FROM conversations", [], |row| row.get(0))?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {CONVERSATION_COLUMNS} FROM conversations c
+             ORDER BY c.updated_at DESC LIMIT ?1 OFFSET ?2"
+        ))?;
+        let conversations = stmt
+            .query_map(params![limit, offset], conversation_from_row)?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(ConversationPage {
+            conversations,
+            total,
+        })
+    })
+    .map_err(AppError::from)
+}
+
+/// Conversations whose title or messages contain `query`, most recent first.
+#[tauri::command]
+pub async fn search_conversations(
+    db: State<'_, Database>,
+    query: String,
+    limit: Option<u32>,
+) -> Result<Vec<Conversation>, AppError> {
+    let pattern = format!(
+        "%{}%",
+        query
+            .trim()
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_")
+    );
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).min(500);
+    db.with(|conn| {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {CONVERSATION_COLUMNS} FROM conversations c
+             WHERE c.title LIKE ?1 ESCAPE '\\'
+                OR EXISTS (SELECT 1 FROM messages m
+                           WHERE m.conversation_id = c.id AND m.content LIKE ?1 ESCAPE '\\')
+             ORDER BY c.updated_at DESC LIMIT ?2"
+        ))?;
+        let conversations = stmt
+            .query_map(params![pattern, limit], conversation_from_row)?
+            .collect();
+        conversations
+    })
+    .map_err(AppError::from)
+}
+
+/// Turn free-form user input into an FTS5 query: every word must match, and the last one
+/// is treated as a prefix so results update while typing. Quoting keeps FTS5 syntax
+/// characters from being interpreted.
+fn fts_query(input: &str) -> Option<String> {
+    let terms: Vec<String> = input
+        .split_whitespace()
+        .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+        .collect();
+    let last = terms.len().checked_sub(1)?;
+    Some(
+        terms
+            .iter()
+            .enumerate()
+            .map(|(i, term)| {
+                if i == last {
+                    format!("{term}*")
+                } else {
+                    term.clone()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" "),
+    )
+}
+
+/// Messages matching `query`, best matches first, with the hit highlighted in `snippet`
+/// between `<mark>` tags.
+#[tauri::command]
+pub async fn search_history(
+    db: State<'_, Database>,
+    query: String,
+    limit: Option<u32>,
+    offset: Option<u32>,
+) -> Result<Vec<SearchHit>, AppError> {
+    let Some(query) = fts_query(&query) else {
+        return Ok(Vec::new());
+    };
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).min(500);
+    let offset = offset.unwrap_or(0);
+    db.with(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT m.id, m.conversation_id, c.title, c.updated_at, m.role,
+                    snippet(messages_fts, 0, '<mark>', '</mark>', '…', 16), m.created_at
+             FROM messages_fts
+             JOIN messages m ON m.rowid = messages_fts.rowid
+             JOIN conversations c ON c.id = m.conversation_id
+             WHERE messages_fts MATCH ?1
+             ORDER BY bm25(messages_fts), m.created_at DESC
+             LIMIT ?2 OFFSET ?3",
+        )?;
+        let hits = stmt
+            .query_map(params![query, limit, offset], |row| {
+                Ok(SearchHit {
+                    message_id: row.get(0)?,
+                    conversation_id: row.get(1)?,
+                    conversation_title: row.get(2)?,
+                    conversation_updated_at: row.get(3)?,
+                    role: row.get(4)?,
+                    snippet: row.get(5)?,
+                    created_at: row.get(6)?,
+                })
+            })?
+            .collect();
+        hits
+    })
+    .map_err(AppError::from)
+}
+
+#[tauri::command]
+pub async fn delete_conversation(
+    app: tauri::AppHandle,
+    db: State<'_, Database>,
+    id: String,
+) -> Result<bool, AppError> {
+    let deleted = db.with(|conn| conn.execute("DELETE FROM conversations WHERE id = ?1", [&id]))?;
+    drafts::discard(&app, &id);
+    session::discard(&app, &id);
+    if state::session(&app).await.active_conversation.as_ref() == Some(&id) {
+        state::update(&app, |session| session.active_conversation = None).await;
+    }
+    Ok(deleted > 0)
+}
+
+pub fn load_export(conn: &Connection, id: &str) -> rusqlite::Result<Option<ConversationExport>> {
+    let Some(conversation) = find_conversation(conn, id)? else {
+        return Ok(None);
+    };
+    let messages = conversation_messages(conn, id)?;
+    Ok(Some(ConversationExport {
+        conversation,
+        messages,
+    }))
+}
+
+/// A conversation with the messages of the branch that is shown.
+pub fn conversation_export(db: &Database, id: &str) -> Result<ConversationExport, AppError> {
+    db.with(|conn| load_export(conn, id))?
+        .ok_or_else(|| AppError::NotFound(format!("Conversation {id} not found")))
+}
+
+/// Reopen a conversation: it and the messages of the branch that is shown.
+#[tauri::command]
+pub async fn get_conversation(
+    db: State<'_, Database>,
+    id: String,
+) -> Result<ConversationExport, AppError> {
+    conversation_export(&db, &id)
+}
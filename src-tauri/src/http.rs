@@ -0,0 +1,168 @@
+use std::collections::hash_map::RandomState;
+use std::fs;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use reqwest::{Certificate, Client, NoProxy, Proxy, RequestBuilder, Response, StatusCode};
+use serde::Serialize;
+use tauri::{Emitter, Manager};
+
+use crate::error::AppError;
+use crate::offline;
+use crate::providers::status;
+use crate::settings::{self, NetworkSettings, ProxyMode};
+
+/// The shared HTTP client, rebuilt whenever the network settings it was built from change.
+#[derive(Default)]
+pub struct HttpClient(Mutex<Option<(NetworkSettings, Client)>>);
+
+fn build(network: &NetworkSettings) -> Result<Client, String> {
+    let mut builder = Client::builder();
+    match network.proxy_mode {
+        ProxyMode::System => {}
+        ProxyMode::None => builder = builder.no_proxy(),
+        ProxyMode::Manual => {
+            let url = network.proxy_url.as_deref().unwrap_or_default();
+            let proxy = Proxy::all(url)
+                .map_err(|e| format!("Invalid proxy URL: {e}"))?
+                .no_proxy(network.no_proxy.as_deref().and_then(NoProxy::from_string));
+            builder = builder.proxy(proxy);
+        }
+    }
+    if let Some(path) = &network.ca_bundle {
+        let pem = fs::read(path)
+            .map_err(|e| format!("Could not read CA bundle {}: {e}", path.display()))?;
+        let certs = Certificate::from_pem_bundle(&pem)
+            .map_err(|e| format!("Invalid CA bundle {}: {e}", path.display()))?;
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+    builder
+        .danger_accept_invalid_certs(network.accept_invalid_certs)
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+/// An HTTP client honoring the proxy and TLS settings. Every outgoing request should go
+/// through one of these.
+pub fn client(app: &tauri::AppHandle) -> Result<Client, String> {
+    let network = settings::current(app).network;
+    let state = app.state::<HttpClient>();
+    let mut cached = state.0.lock().unwrap();
+    if let Some((built_from, client)) = cached.as_ref() {
+        if *built_from == network {
+            return Ok(client.clone());
+        }
+    }
+    let client = build(&network)?;
+    *cached = Some((network, client.clone()));
+    Ok(client)
+}
+
+const BASE_DELAY: Duration = Duration::from_millis(500);
+const MAX_DELAY: Duration = Duration::from_secs(30);
+/// Waits a server asks for beyond this fail the request instead of stalling it.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(120);
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RetryPayload<'a> {
+    request_id: Option<&'a str>,
+    provider: &'a str,
+    attempt: u32,
+    max_retries: u32,
+    delay_ms: u64,
+    reason: &'a str,
+}
+
+/// Rate limits, timeouts and the usual transient server errors, Anthropic's 529
+/// "overloaded" included.
+fn is_retryable(status: StatusCode) -> bool {
+    matches!(status.as_u16(), 408 | 429 | 500 | 502 | 503 | 504 | 529)
+}
+
+/// Exponential backoff with jitter: somewhere between half and all of
+/// `BASE_DELAY * 2^attempt`, capped at `MAX_DELAY`.
+fn backoff(attempt: u32) -> Duration {
+    let ceiling = BASE_DELAY
+        .saturating_mul(1 << attempt.min(16))
+        .min(MAX_DELAY);
+    let random = RandomState::new().build_hasher().finish() as f64 / u64::MAX as f64;
+    ceiling.mul_f64(0.5 + random / 2.0)
+}
+
+async fn send_once(
+    app: &tauri::AppHandle,
+    provider: &str,
+    request: RequestBuilder,
+) -> Result<Response, reqwest::Error> {
+    let result = request.send().await;
+    match &result {
+        Ok(response) => status::record_response(app, provider, response),
+        Err(e) => {
+            status::record_error(app, provider, &e.to_string());
+            if e.is_connect() {
+                offline::recheck(app);
+            }
+        }
+    }
+    result
+}
+
+/// Send `request`, retrying transient failures up to `network.maxRetries` times and
+/// emitting `request-retry` before each wait. `provider` keys the rate-limit state shown
+/// by `get_provider_status`. Non-retryable and final error responses are returned as
+/// they are, for the caller to report.
+pub async fn send(
+    app: &tauri::AppHandle,
+    provider: &str,
+    request_id: Option<&str>,
+    request: RequestBuilder,
+) -> Result<Response, AppError> {
+    let max_retries = settings::current(app).network.max_retries;
+    let mut attempt = 0;
+    loop {
+        // Streaming bodies can't be replayed; send those exactly once.
+        let Some(this) = request.try_clone() else {
+            return Ok(send_once(app, provider, request).await?);
+        };
+        let retryable = attempt < max_retries;
+        let (delay, reason) = match send_once(app, provider, this).await {
+            Ok(response) => {
+                let code = response.status();
+                if !retryable || !is_retryable(code) {
+                    return Ok(response);
+                }
+                let delay =
+                    status::retry_after(response.headers()).unwrap_or_else(|| backoff(attempt));
+                if delay > MAX_RETRY_AFTER {
+                    return Ok(response);
+                }
+                (delay, code.to_string())
+            }
+            Err(e) => {
+                let message = e.to_string();
+                if !retryable || !(e.is_connect() || e.is_timeout()) {
+                    return Err(e.into());
+                }
+                (backoff(attempt), message)
+            }
+        };
+        attempt += 1;
+        tracing::warn!("Retrying {provider} request in {delay:?} ({reason}), attempt {attempt} of {max_retries}");
+        let _ = app.emit(
+            "request-retry",
+            RetryPayload {
+                request_id,
+                provider,
+                attempt,
+                max_retries,
+                delay_ms: delay.as_millis() as u64,
+                reason: &reason,
+            },
+        );
+        tokio::time::sleep(delay).await;
+    }
+}
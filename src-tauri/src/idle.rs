@@ -0,0 +1,172 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::{Emitter, Manager};
+
+use crate::clipboard_history;
+use crate::db::Database;
+use crate::secrets;
+use crate::settings;
+use crate::window_manager::{self, AppWindow};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Whether the idle lock has run since the user was last active.
+#[derive(Default)]
+pub struct IdleState(AtomicBool);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdleStatus {
+    /// Time since the last keyboard or mouse input; `None` where it can't be read.
+    pub idle_secs: Option<u64>,
+    pub locked: bool,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct IdleLockPayload {
+    idle_secs: u64,
+    cleared_clipboard_entries: usize,
+    locked_api_keys: bool,
+    hid_overlay: bool,
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::time::Duration;
+
+    use windows::Win32::System::SystemInformation::GetTickCount;
+    use windows::Win32::UI::Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO};
+
+    pub fn idle_time() -> Option<Duration> {
+        let mut info = LASTINPUTINFO {
+            cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32,
+            dwTime: 0,
+        };
+        // SAFETY: `info` is a properly sized LASTINPUTINFO that outlives the call.
+        let ok = unsafe { GetLastInputInfo(&mut info) }.as_bool();
+        // Both are milliseconds since boot and wrap after 49 days together.
+        // SAFETY: takes no arguments.
+        let now = unsafe { GetTickCount() };
+        ok.then(|| Duration::from_millis(now.wrapping_sub(info.dwTime).into()))
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::time::Duration;
+
+    const COMBINED_SESSION_STATE: i32 = 0;
+    const ANY_INPUT_EVENT: u32 = u32::MAX;
+
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        fn CGEventSourceSecondsSinceLastEventType(state: i32, event_type: u32) -> f64;
+    }
+
+    pub fn idle_time() -> Option<Duration> {
+        // SAFETY: a plain query with constant arguments.
+        let secs = unsafe {
+            CGEventSourceSecondsSinceLastEventType(COMBINED_SESSION_STATE, ANY_INPUT_EVENT)
+        };
+        (secs.is_finite() && secs >= 0.0).then(|| Duration::from_secs_f64(secs))
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::time::Duration;
+
+    use x11rb::protocol::screensaver::ConnectionExt;
+
+    use crate::x11;
+
+    /// From the X screensaver extension. On Wayland XWayland only sees input to X11
+    /// clients, so it can't say.
+    pub fn idle_time() -> Option<Duration> {
+        if x11::is_wayland() {
+            return None;
+        }
+        let (conn, root) = x11::connect().ok()?;
+        let info = conn.screensaver_query_info(root).ok()?.reply().ok()?;
+        Some(Duration::from_millis(info.ms_since_user_input.into()))
+    }
+}
+
+/// How long since the user last pressed a key or moved the mouse, anywhere.
+pub fn idle_time() -> Option<Duration> {
+    platform::idle_time()
+}
+
+/// Clear what the idle settings ask for and emit `idle-lock` with what was done.
+fn lock(app: &tauri::AppHandle, idle: Duration) {
+    let policy = settings::current(app).idle;
+    let mut cleared_clipboard_entries = 0;
+    if policy.clear_clipboard_history {
+        match app
+            .state::<Database>()
+            .with(|conn| clipboard_history::clear(conn, false))
+        {
+            Ok(cleared) => cleared_clipboard_entries = cleared,
+            Err(error) => tracing::error!("Could not clear the clipboard history: {error}"),
+        }
+    }
+    if policy.lock_api_keys {
+        secrets::lock_cache();
+    }
+    let hid_overlay = policy.hide_overlay && window_manager::is_visible(app, AppWindow::Overlay);
+    if hid_overlay {
+        if let Err(error) = window_manager::hide_overlay(app) {
+            tracing::error!("Could not hide the overlay on idle: {error}");
+        }
+    }
+    tracing::info!("Idle for {}s, locked", idle.as_secs());
+    let _ = app.emit(
+        "idle-lock",
+        IdleLockPayload {
+            idle_secs: idle.as_secs(),
+            cleared_clipboard_entries,
+            locked_api_keys: policy.lock_api_keys,
+            hid_overlay,
+        },
+    );
+}
+
+/// Check the idle time for as long as the app runs, and lock once it passes
+/// `idle.timeoutMinutes` while `idle.enabled` is on. Locks again only after the user has
+/// been back.
+pub fn start(app: &tauri::AppHandle) {
+    let app = app.clone();
+    let spawned = thread::Builder::new()
+        .name("idle-tracker".to_string())
+        .spawn(move || loop {
+            thread::sleep(POLL_INTERVAL);
+            let policy = settings::current(&app).idle;
+            if !policy.enabled {
+                continue;
+            }
+            let Some(idle) = idle_time() else {
+                continue;
+            };
+            let locked = &app.state::<IdleState>().0;
+            if idle < Duration::from_secs(policy.timeout_minutes * 60) {
+                locked.store(false, Ordering::SeqCst);
+            } else if !locked.swap(true, Ordering::SeqCst) {
+                lock(&app, idle);
+            }
+        });
+    if let Err(error) = spawned {
+        tracing::error!("Failed to start the idle tracker: {error}");
+    }
+}
+
+#[tauri::command]
+pub async fn get_idle_state(app: tauri::AppHandle) -> IdleStatus {
+    IdleStatus {
+        idle_secs: idle_time().map(|idle| idle.as_secs()),
+        locked: app.state::<IdleState>().0.load(Ordering::SeqCst),
+    }
+}
@@ -0,0 +1,415 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use rusqlite::{params, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::{Manager, State};
+
+use crate::db::{self, Database};
+use crate::error::AppError;
+use crate::providers::{self, service_error_for_status};
+use crate::{http, offline, persist, requests, secrets};
+
+const GALLERY_DIR: &str = "gallery";
+const THUMBNAIL_SIZE: u32 = 256;
+const MAX_IMAGES: u32 = 4;
+const DEFAULT_SIZE: &str = "1024x1024";
+const DEFAULT_PAGE_SIZE: u32 = 50;
+/// Generating takes far longer than a chat reply, so never allow less than this.
+const MIN_TIMEOUT: Duration = Duration::from_secs(120);
+
+const OPENAI_DEFAULT_MODEL: &str = "gpt-image-1";
+const STABILITY_URL: &str = "https://api.stability.ai/v2beta/stable-image/generate/core";
+/// The AUTOMATIC1111 / Forge web UI API, started with `--api`.
+const LOCAL_DEFAULT_URL: &str = "http://127.0.0.1:7860";
+const STABILITY_ASPECT_RATIOS: [(u32, u32); 9] = [
+    (21, 9),
+    (16, 9),
+    (3, 2),
+    (5, 4),
+    (1, 1),
+    (4, 5),
+    (2, 3),
+    (9, 16),
+    (9, 21),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageProvider {
+    /// OpenAI Images, with the key saved for the `openai` chat provider.
+    OpenAi,
+    /// Stability AI, with a key saved under `stability`.
+    Stability,
+    /// A Stable Diffusion web UI on this machine.
+    Local,
+}
+
+impl ImageProvider {
+    fn id(self) -> &'static str {
+        match self {
+            ImageProvider::OpenAi => "openai",
+            ImageProvider::Stability => "stability",
+            ImageProvider::Local => "local",
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            ImageProvider::OpenAi => "OpenAI",
+            ImageProvider::Stability => "Stability AI",
+            ImageProvider::Local => "Stable Diffusion",
+        }
+    }
+}
+
+/// Less common knobs for `generate_image`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ImageOptions {
+    /// The OpenAI model, `gpt-image-1` by default. Ignored elsewhere.
+    pub model: Option<String>,
+    /// Send OpenAI, Stability or the local web UI requests somewhere else.
+    pub base_url: Option<String>,
+}
+
+/// A generated image in the gallery folder. Both files are PNG.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeneratedImage {
+    pub id: String,
+    pub prompt: String,
+    pub provider: String,
+    pub model: Option<String>,
+    pub width: u32,
+    pub height: u32,
+    pub path: PathBuf,
+    pub thumbnail_path: PathBuf,
+    pub created_at: i64,
+}
+
+const IMAGE_COLUMNS: &str =
+    "id, prompt, provider, model, width, height, path, thumbnail_path, created_at";
+
+fn image_from_row(row: &Row) -> rusqlite::Result<GeneratedImage> {
+    Ok(GeneratedImage {
+        id: row.get(0)?,
+        prompt: row.get(1)?,
+        provider: row.get(2)?,
+        model: row.get(3)?,
+        width: row.get(4)?,
+        height: row.get(5)?,
+        path: PathBuf::from(row.get::<_, String>(6)?),
+        thumbnail_path: PathBuf::from(row.get::<_, String>(7)?),
+        created_at: row.get(8)?,
+    })
+}
+
+/// What was asked for, once checked.
+struct Generation {
+    provider: ImageProvider,
+    prompt: String,
+    model: Option<String>,
+    width: u32,
+    height: u32,
+    count: u32,
+    base_url: Option<String>,
+}
+
+fn parse_size(size: &str) -> Option<(u32, u32)> {
+    let (width, height) = size.split_once('x')?;
+    let width = width.trim().parse().ok().filter(|&w| w > 0)?;
+    let height = height.trim().parse().ok().filter(|&h| h > 0)?;
+    Some((width, height))
+}
+
+/// Stability takes an aspect ratio rather than a size; use the closest one it offers.
+fn aspect_ratio(width: u32, height: u32) -> String {
+    let wanted = (width as f64 / height as f64).ln();
+    let (w, h) = STABILITY_ASPECT_RATIOS
+        .iter()
+        .copied()
+        .min_by(|a, b| {
+            let distance = |(w, h): (u32, u32)| ((w as f64 / h as f64).ln() - wanted).abs();
+            distance(*a).total_cmp(&distance(*b))
+        })
+        .unwrap_or((1, 1));
+    format!("{w}:{h}")
+}
+
+fn decode(base64: &str) -> Result<Vec<u8>, AppError> {
+    BASE64
+        .decode(base64)
+        .map_err(|e| AppError::from(format!("The image came back garbled: {e}")))
+}
+
+async fn generate_openai(
+    app: &tauri::AppHandle,
+    request_id: &str,
+    generation: &Generation,
+) -> Result<Vec<Vec<u8>>, AppError> {
+    let provider = providers::resolve(app, "openai", generation.base_url.as_deref())?;
+    let key = providers::api_key(provider.as_ref())?;
+    let info = provider.info();
+    let model = generation.model.as_deref().unwrap_or(OPENAI_DEFAULT_MODEL);
+    let mut body = json!({
+        "model": model,
+        "prompt": generation.prompt,
+        "n": generation.count,
+        "size": format!("{}x{}", generation.width, generation.height),
+    });
+    // Only the DALL-E models need asking for base64; the GPT image models always send it.
+    if model.starts_with("dall-e") {
+        body["response_format"] = json!("b64_json");
+    }
+    let mut request = http::client(app)?
+        .post(format!("{}/images/generations", info.base_url))
+        .json(&body);
+    if let Some(key) = key {
+        request = request.bearer_auth(key);
+    }
+    let response = http::send(app, &info.id, Some(request_id), request).await?;
+    let response: Value = providers::error_for_status(info, response)
+        .await?
+        .json()
+        .await?;
+    let mut images = Vec::new();
+    for entry in response["data"].as_array().into_iter().flatten() {
+        if let Some(data) = entry.get("b64_json").and_then(Value::as_str) {
+            images.push(decode(data)?);
+        } else if let Some(url) = entry.get("url").and_then(Value::as_str) {
+            let download =
+                http::send(app, &info.id, Some(request_id), http::client(app)?.get(url)).await?;
+            images.push(download.error_for_status()?.bytes().await?.to_vec());
+        }
+    }
+    Ok(images)
+}
+
+async fn generate_stability(
+    app: &tauri::AppHandle,
+    request_id: &str,
+    generation: &Generation,
+) -> Result<Vec<Vec<u8>>, AppError> {
+    let provider = ImageProvider::Stability;
+    let key = secrets::api_key(provider.id())?.ok_or_else(|| AppError::ProviderAuth {
+        provider: provider.id().to_string(),
+        message: format!("No API key saved for {}", provider.name()),
+    })?;
+    let url = generation.base_url.as_deref().unwrap_or(STABILITY_URL);
+    // Each request makes one image.
+    let mut images = Vec::new();
+    for _ in 0..generation.count {
+        let form = reqwest::multipart::Form::new()
+            .text("prompt", generation.prompt.clone())
+            .text(
+                "aspect_ratio",
+                aspect_ratio(generation.width, generation.height),
+            )
+            .text("output_format", "png");
+        let request = http::client(app)?
+            .post(url)
+            .bearer_auth(&key)
+            .header(reqwest::header::ACCEPT, "application/json")
+            .multipart(form);
+        let response = http::send(app, provider.id(), Some(request_id), request).await?;
+        let response: Value = service_error_for_status(provider.id(), provider.name(), response)
+            .await?
+            .json()
+            .await?;
+        let data = response["image"]
+            .as_str()
+            .ok_or_else(|| AppError::from("Stability AI sent no image"))?;
+        images.push(decode(data)?);
+    }
+    Ok(images)
+}
+
+async fn generate_local(
+    app: &tauri::AppHandle,
+    request_id: &str,
+    generation: &Generation,
+) -> Result<Vec<Vec<u8>>, AppError> {
+    let provider = ImageProvider::Local;
+    let base = generation
+        .base_url
+        .as_deref()
+        .unwrap_or(LOCAL_DEFAULT_URL)
+        .trim_end_matches('/');
+    let request = http::client(app)?
+        .post(format!("{base}/sdapi/v1/txt2img"))
+        .json(&json!({
+            "prompt": generation.prompt,
+            "width": generation.width,
+            "height": generation.height,
+            "batch_size": generation.count,
+        }));
+    let response = http::send(app, provider.id(), Some(request_id), request)
+        .await
+        .map_err(|e| AppError::Network(format!("Could not reach Stable Diffusion: {e}")))?;
+    let response: Value = service_error_for_status(provider.id(), provider.name(), response)
+        .await?
+        .json()
+        .await?;
+    response["images"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .map(decode)
+        .collect()
+}
+
+/// Write each image and its thumbnail to the gallery folder and record them.
+fn save(
+    app: &tauri::AppHandle,
+    generation: &Generation,
+    images: Vec<Vec<u8>>,
+) -> Result<Vec<GeneratedImage>, String> {
+    let dir = persist::data_file(app, GALLERY_DIR)?;
+    let thumbnails = dir.join("thumbnails");
+    fs::create_dir_all(&thumbnails).map_err(|e| e.to_string())?;
+    let mut saved = Vec::with_capacity(images.len());
+    for bytes in images {
+        let image = image::load_from_memory(&bytes).map_err(|e| e.to_string())?;
+        let id = uuid::Uuid::new_v4().to_string();
+        let path = dir.join(format!("{id}.png"));
+        let thumbnail_path = thumbnails.join(format!("{id}.png"));
+        image.save(&path).map_err(|e| e.to_string())?;
+        image
+            .thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+            .save(&thumbnail_path)
+            .map_err(|e| e.to_string())?;
+        let generated = GeneratedImage {
+            id,
+            prompt: generation.prompt.clone(),
+            provider: generation.provider.id().to_string(),
+            model: generation.model.clone(),
+            width: image.width(),
+            height: image.height(),
+            path,
+            thumbnail_path,
+            created_at: db::now_ms(),
+        };
+        app.state::<Database>().with(|conn| {
+            conn.execute(
+                &format!("INSERT INTO generated_images ({IMAGE_COLUMNS}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)"),
+                params![
+                    generated.id,
+                    generated.prompt,
+                    generated.provider,
+                    generated.model,
+                    generated.width,
+                    generated.height,
+                    generated.path.to_string_lossy(),
+                    generated.thumbnail_path.to_string_lossy(),
+                    generated.created_at
+                ],
+            )
+        })?;
+        saved.push(generated);
+    }
+    Ok(saved)
+}
+
+/// Generate `n` images (1 to 4, default 1) from `prompt` and add them to the gallery.
+/// `size` is `WIDTHxHEIGHT`, 1024x1024 by default; Stability picks the nearest aspect
+/// ratio it supports.
+#[tauri::command]
+pub async fn generate_image(
+    app: tauri::AppHandle,
+    prompt: String,
+    provider: ImageProvider,
+    size: Option<String>,
+    n: Option<u32>,
+    options: Option<ImageOptions>,
+    request_id: Option<String>,
+) -> Result<Vec<GeneratedImage>, AppError> {
+    let prompt = prompt.trim().to_string();
+    if prompt.is_empty() {
+        return Err(AppError::InvalidInput("The prompt is empty".into()));
+    }
+    let size = size.unwrap_or_else(|| DEFAULT_SIZE.to_string());
+    let (width, height) = parse_size(&size).ok_or_else(|| {
+        AppError::InvalidInput(format!("Invalid size {size}; expected WIDTHxHEIGHT"))
+    })?;
+    let count = n.unwrap_or(1);
+    if !(1..=MAX_IMAGES).contains(&count) {
+        return Err(AppError::InvalidInput(format!(
+            "n must be between 1 and {MAX_IMAGES}"
+        )));
+    }
+    let options = options.unwrap_or_default();
+    let generation = Generation {
+        provider,
+        prompt,
+        model: match provider {
+            ImageProvider::OpenAi => Some(
+                options
+                    .model
+                    .unwrap_or_else(|| OPENAI_DEFAULT_MODEL.to_string()),
+            ),
+            _ => None,
+        },
+        width,
+        height,
+        count,
+        base_url: options.base_url,
+    };
+    let request_id = request_id.unwrap_or_else(requests::new_id);
+    if generation.provider != ImageProvider::Local && offline::is_offline(&app) {
+        let what = format!("Image generation with {}", generation.provider.name());
+        let wait = offline::wait_until_online(app.clone(), request_id.clone(), what);
+        requests::run(&app, Some(request_id.clone()), None, wait).await?;
+    }
+    let timeout = requests::default_timeout(&app).max(MIN_TIMEOUT);
+    let task_app = app.clone();
+    let task_id = request_id.clone();
+    requests::run(&app, Some(request_id), Some(timeout), async move {
+        let images = match generation.provider {
+            ImageProvider::OpenAi => generate_openai(&task_app, &task_id, &generation).await?,
+            ImageProvider::Stability => {
+                generate_stability(&task_app, &task_id, &generation).await?
+            }
+            ImageProvider::Local => generate_local(&task_app, &task_id, &generation).await?,
+        };
+        if images.is_empty() {
+            return Err(AppError::from(format!(
+                "{} sent no images",
+                generation.provider.name()
+            )));
+        }
+        let saved =
+            tauri::async_runtime::spawn_blocking(move || save(&task_app, &generation, images))
+                .await??;
+        tracing::info!("Generated {} images", saved.len());
+        Ok(saved)
+    })
+    .await
+}
+
+/// The gallery, newest first.
+#[tauri::command]
+pub async fn list_generated_images(
+    db: State<'_, Database>,
+    limit: Option<u32>,
+    offset: Option<u32>,
+) -> Result<Vec<GeneratedImage>, AppError> {
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).min(500);
+    let offset = offset.unwrap_or(0);
+    db.with(|conn| {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {IMAGE_COLUMNS} FROM generated_images
+             ORDER BY created_at DESC LIMIT ?1 OFFSET ?2"
+        ))?;
+        let images = stmt
+            .query_map(params![limit, offset], image_from_row)?
+            .collect();
+        images
+    })
+    .map_err(AppError::from)
+}
@@ -0,0 +1,145 @@
+use std::io::Cursor;
+use std::path::PathBuf;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::{clipboard, screenshot};
+
+/// Tried in turn until the image fits.
+const JPEG_QUALITIES: [u8; 3] = [85, 70, 55];
+/// Give up shrinking an image that still doesn't fit below this size.
+const MIN_DIMENSION: u32 = 64;
+
+/// Where an image handed to a backend command comes from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ImageSource {
+    Path {
+        path: PathBuf,
+    },
+    Base64 {
+        data: String,
+    },
+    Screenshot {
+        id: String,
+    },
+    /// Whatever image is on the clipboard when it is loaded.
+    Clipboard,
+}
+
+impl ImageSource {
+    pub fn load(&self, app: &tauri::AppHandle) -> Result<RgbaImage, String> {
+        let image = match self {
+            Self::Path { path } => image::open(path).map_err(|e| e.to_string())?,
+            Self::Base64 { data } => {
+                // Accept data URLs as well as bare base64.
+                let data = data
+                    .split_once(";base64,")
+                    .map_or(data.as_str(), |(_, d)| d);
+                let bytes = BASE64.decode(data).map_err(|e| e.to_string())?;
+                image::load_from_memory(&bytes).map_err(|e| e.to_string())?
+            }
+            Self::Screenshot { id } => {
+                let path = screenshot::screenshot_path(app, id)?;
+                image::open(path).map_err(|e| e.to_string())?
+            }
+            Self::Clipboard => {
+                return clipboard::read_image(app)?
+                    .ok_or_else(|| "There is no image on the clipboard".to_string())
+            }
+        };
+        Ok(image.to_rgba8())
+    }
+}
+
+/// The largest image a provider accepts; bigger ones are scaled down and recompressed.
+#[derive(Debug, Clone, Copy)]
+pub struct ImageLimits {
+    /// Longest edge, in pixels.
+    pub max_dimension: u32,
+    /// Size once base64-encoded.
+    pub max_bytes: usize,
+}
+
+impl Default for ImageLimits {
+    fn default() -> Self {
+        Self {
+            max_dimension: 2048,
+            max_bytes: 20 * 1024 * 1024,
+        }
+    }
+}
+
+/// An image ready to go into a request.
+#[derive(Debug, Clone)]
+pub struct EncodedImage {
+    pub media_type: &'static str,
+    pub base64: String,
+}
+
+impl EncodedImage {
+    pub fn data_url(&self) -> String {
+        format!("data:{};base64,{}", self.media_type, self.base64)
+    }
+}
+
+fn encode_jpeg(image: &RgbaImage, quality: u8) -> Result<Vec<u8>, String> {
+    let rgb = DynamicImage::ImageRgba8(image.clone()).to_rgb8();
+    let mut jpeg = Vec::new();
+    JpegEncoder::new_with_quality(&mut jpeg, quality)
+        .encode_image(&rgb)
+        .map_err(|e| e.to_string())?;
+    Ok(jpeg)
+}
+
+/// Shrink `image` to fit `limits` and encode it for upload: as PNG when it has
+/// transparency to keep, otherwise as JPEG, lowering the quality and then the size
+/// until it fits.
+pub fn encode_for_upload(image: &RgbaImage, limits: ImageLimits) -> Result<EncodedImage, String> {
+    let transparent = image.pixels().any(|pixel| pixel.0[3] < u8::MAX);
+    let mut image = image.clone();
+    let mut max_dimension = limits.max_dimension;
+    loop {
+        if image.width().max(image.height()) > max_dimension {
+            image = DynamicImage::ImageRgba8(image)
+                .resize(max_dimension, max_dimension, FilterType::Triangle)
+                .to_rgba8();
+        }
+        // Base64 turns every 3 bytes into 4.
+        let fits = |bytes: &[u8]| (bytes.len() + 2) / 3 * 4 <= limits.max_bytes;
+        let encoded = |media_type, bytes: Vec<u8>| EncodedImage {
+            media_type,
+            base64: BASE64.encode(bytes),
+        };
+        if transparent {
+            let png = encode_png(&image)?;
+            if fits(&png) {
+                return Ok(encoded("image/png", png));
+            }
+        } else {
+            for quality in JPEG_QUALITIES {
+                let jpeg = encode_jpeg(&image, quality)?;
+                if fits(&jpeg) {
+                    return Ok(encoded("image/jpeg", jpeg));
+                }
+            }
+        }
+        max_dimension = image.width().max(image.height()) * 3 / 4;
+        if max_dimension < MIN_DIMENSION {
+            return Err("The image is too large to send, even scaled down".to_string());
+        }
+    }
+}
+
+pub fn encode_png(image: &RgbaImage) -> Result<Vec<u8>, String> {
+    let mut png = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .map_err(|e| e.to_string())?;
+    Ok(png)
+}
@@ -0,0 +1,406 @@
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use chrono::DateTime;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::Manager;
+use uuid::Uuid;
+use zip::ZipArchive;
+
+use crate::capabilities;
+use crate::db::{self, Database};
+use crate::error::AppError;
+
+/// Both services put the conversations in this file, at the root of their export zip.
+const CONVERSATIONS_FILE: &str = "conversations.json";
+
+/// Namespace for the ids given to imported conversations and messages, so importing the
+/// same export again finds what is already there.
+const IMPORT_NAMESPACE: Uuid = Uuid::from_u128(0x6b1f_3c2e_9a4d_4e0b_8f57_2d61_c0a9_e3b4);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportSource {
+    /// OpenAI's data export.
+    ChatGpt,
+    /// Anthropic's data export.
+    Claude,
+}
+
+impl ImportSource {
+    fn name(self) -> &'static str {
+        match self {
+            ImportSource::ChatGpt => "chatgpt",
+            ImportSource::Claude => "claude",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportStatus {
+    Imported,
+    /// Already imported; messages added since then were appended.
+    Updated,
+    /// Already imported with every message.
+    Skipped,
+    Failed,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConversationImport {
+    pub external_id: String,
+    pub title: String,
+    /// The local conversation, unless the import failed.
+    pub conversation_id: Option<String>,
+    pub status: ImportStatus,
+    pub messages_added: usize,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    pub imported: usize,
+    pub updated: usize,
+    pub skipped: usize,
+    pub failed: usize,
+    pub conversations: Vec<ConversationImport>,
+}
+
+/// A conversation from an export, mapped onto the local schema.
+struct External {
+    id: String,
+    title: String,
+    created_at: i64,
+    messages: Vec<ExternalMessage>,
+}
+
+struct ExternalMessage {
+    id: String,
+    role: String,
+    content: String,
+    created_at: Option<i64>,
+}
+
+fn str_field<'a>(value: &'a Value, key: &str) -> Option<&'a str> {
+    value.get(key).and_then(Value::as_str)
+}
+
+/// ChatGPT stores times as fractional Unix seconds.
+fn unix_secs_ms(value: Option<&Value>) -> Option<i64> {
+    value
+        .and_then(Value::as_f64)
+        .map(|secs| (secs * 1000.0) as i64)
+}
+
+/// Claude stores times as RFC 3339 strings.
+fn rfc3339_ms(value: Option<&str>) -> Option<i64> {
+    value
+        .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+        .map(|time| time.timestamp_millis())
+}
+
+/// The text of a ChatGPT message. Images and other non-text parts are left out.
+fn chatgpt_text(content: &Value) -> String {
+    if let Some(text) = str_field(content, "text") {
+        return text.to_string();
+    }
+    content
+        .get("parts")
+        .and_then(Value::as_array)
+        .map(|parts| {
+            parts
+                .iter()
+                .filter_map(Value::as_str)
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .unwrap_or_default()
+}
+
+/// A ChatGPT conversation is a tree of edits and regenerations; keep the branch that ends
+/// at `current_node`, the one the user last saw.
+fn parse_chatgpt(value: &Value) -> Result<External, String> {
+    let id = str_field(value, "conversation_id")
+        .or_else(|| str_field(value, "id"))
+        .ok_or("missing id")?;
+    let mapping = value
+        .get("mapping")
+        .and_then(Value::as_object)
+        .ok_or("missing mapping")?;
+    let mut messages = Vec::new();
+    let mut node = str_field(value, "current_node");
+    while let Some(node_id) = node {
+        let Some(entry) = mapping.get(node_id) else {
+            break;
+        };
+        node = str_field(entry, "parent");
+        let Some(message) = entry.get("message").filter(|m| !m.is_null()) else {
+            continue;
+        };
+        let hidden = message
+            .pointer("/metadata/is_visually_hidden_from_conversation")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        let role = message
+            .pointer("/author/role")
+            .and_then(Value::as_str)
+            .unwrap_or("user");
+        let content = message.get("content").map(chatgpt_text).unwrap_or_default();
+        if hidden || content.trim().is_empty() {
+            continue;
+        }
+        messages.push(ExternalMessage {
+            id: str_field(message, "id").unwrap_or(node_id).to_string(),
+            role: match role {
+                "system" | "assistant" | "tool" => role,
+                _ => "user",
+            }
+            .to_string(),
+            content,
+            created_at: unix_secs_ms(message.get("create_time")),
+        });
+    }
+    messages.reverse();
+    Ok(External {
+        id: id.to_string(),
+        title: str_field(value, "title").unwrap_or_default().to_string(),
+        created_at: unix_secs_ms(value.get("create_time")).unwrap_or_else(db::now_ms),
+        messages,
+    })
+}
+
+/// The text of a Claude message, from its content blocks when it has them.
+fn claude_text(message: &Value) -> String {
+    let blocks = message
+        .get("content")
+        .and_then(Value::as_array)
+        .map(|blocks| {
+            blocks
+                .iter()
+                .filter(|block| str_field(block, "type") == Some("text"))
+                .filter_map(|block| str_field(block, "text"))
+                .collect::<Vec<_>>()
+                .join("\n\n")
+        })
+        .unwrap_or_default();
+    if blocks.trim().is_empty() {
+        str_field(message, "text").unwrap_or_default().to_string()
+    } else {
+        blocks
+    }
+}
+
+fn parse_claude(value: &Value) -> Result<External, String> {
+    let id = str_field(value, "uuid").ok_or("missing uuid")?;
+    let messages = value
+        .get("chat_messages")
+        .and_then(Value::as_array)
+        .ok_or("missing chat_messages")?
+        .iter()
+        .enumerate()
+        .filter_map(|(index, message)| {
+            let content = claude_text(message);
+            if content.trim().is_empty() {
+                return None;
+            }
+            Some(ExternalMessage {
+                id: str_field(message, "uuid")
+                    .map(str::to_string)
+                    .unwrap_or_else(|| index.to_string()),
+                role: match str_field(message, "sender") {
+                    Some("assistant") => "assistant",
+                    _ => "user",
+                }
+                .to_string(),
+                content,
+                created_at: rfc3339_ms(str_field(message, "created_at")),
+            })
+        })
+        .collect();
+    Ok(External {
+        id: id.to_string(),
+        title: str_field(value, "name").unwrap_or_default().to_string(),
+        created_at: rfc3339_ms(str_field(value, "created_at")).unwrap_or_else(db::now_ms),
+        messages,
+    })
+}
+
+/// The conversations from `conversations.json`, or from the export zip that holds it.
+fn read_conversations(path: &Path) -> Result<Vec<Value>, AppError> {
+    let is_zip = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("zip"));
+    let contents = if is_zip {
+        let file = File::open(path).map_err(|e| AppError::from(e.to_string()))?;
+        let mut archive = ZipArchive::new(file).map_err(|e| AppError::from(e.to_string()))?;
+        let mut entry = archive.by_name(CONVERSATIONS_FILE).map_err(|_| {
+            AppError::InvalidInput(format!("The export has no {CONVERSATIONS_FILE}"))
+        })?;
+        let mut contents = String::new();
+        entry
+            .read_to_string(&mut contents)
+            .map_err(|e| AppError::from(e.to_string()))?;
+        contents
+    } else {
+        fs::read_to_string(path).map_err(|e| AppError::from(e.to_string()))?
+    };
+    match serde_json::from_str(&contents) {
+        Ok(Value::Array(conversations)) => Ok(conversations),
+        Ok(_) => Err(AppError::InvalidInput(
+            "Expected a list of conversations".into(),
+        )),
+        Err(error) => Err(AppError::InvalidInput(format!(
+            "Not a conversations export: {error}"
+        ))),
+    }
+}
+
+fn local_id(source: ImportSource, kind: &str, external_id: &str) -> String {
+    Uuid::new_v5(
+        &IMPORT_NAMESPACE,
+        format!("{}:{kind}:{external_id}", source.name()).as_bytes(),
+    )
+    .to_string()
+}
+
+/// Store one conversation, adding only the messages a previous import didn't.
+fn store(
+    conn: &mut Connection,
+    source: ImportSource,
+    external: &External,
+) -> rusqlite::Result<(String, ImportStatus, usize)> {
+    let id = local_id(source, "conversation", &external.id);
+    let tx = conn.transaction()?;
+    let existed = tx
+        .query_row("SELECT 1 FROM conversations WHERE id = ?1", [&id], |_| {
+            Ok(())
+        })
+        .optional()?
+        .is_some();
+    let title = match external.title.trim() {
+        "" => "Imported conversation",
+        title => title,
+    };
+    if !existed {
+        tx.execute(
+            "INSERT INTO conversations (id, title, created_at, updated_at) VALUES (?1, ?2, ?3, ?3)",
+            params![id, title, external.created_at],
+        )?;
+    }
+    let mut added = 0;
+    let mut updated_at = external.created_at;
+    let mut parent: Option<String> = None;
+    for message in &external.messages {
+        // Keep the export's order for messages that share, or lack, a timestamp.
+        let created_at = message.created_at.unwrap_or(updated_at).max(updated_at);
+        updated_at = created_at;
+        let message_id = local_id(
+            source,
+            "message",
+            &format!("{}:{}", external.id, message.id),
+        );
+        added += tx.execute(
+            "INSERT OR IGNORE INTO messages (id, conversation_id, role, content, created_at, parent_id)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                message_id,
+                id,
+                message.role,
+                message.content,
+                created_at,
+                parent
+            ],
+        )?;
+        parent = Some(message_id);
+    }
+    if added > 0 {
+        tx.execute(
+            "UPDATE conversations SET updated_at = MAX(updated_at, ?2), active_leaf_id = ?3
+             WHERE id = ?1",
+            params![id, updated_at, parent],
+        )?;
+    }
+    tx.commit()?;
+    let status = match (existed, added) {
+        (false, _) => ImportStatus::Imported,
+        (true, 0) => ImportStatus::Skipped,
+        (true, _) => ImportStatus::Updated,
+    };
+    Ok((id, status, added))
+}
+
+/// Import the conversations in a ChatGPT or Claude data export, given as the export zip
+/// or the `conversations.json` inside it. Importing the same export again only adds what
+/// is new, and one conversation that can't be read doesn't stop the rest.
+#[tauri::command]
+pub async fn import_external_history(
+    app: tauri::AppHandle,
+    path: PathBuf,
+    source: ImportSource,
+) -> Result<ImportReport, AppError> {
+    capabilities::require_path(&app, &path, "Import history").await?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let conversations = read_conversations(&path)?;
+        let mut report = ImportReport::default();
+        for value in &conversations {
+            let parsed = match source {
+                ImportSource::ChatGpt => parse_chatgpt(value),
+                ImportSource::Claude => parse_claude(value),
+            };
+            let external_id = str_field(value, "conversation_id")
+                .or_else(|| str_field(value, "id"))
+                .or_else(|| str_field(value, "uuid"))
+                .unwrap_or_default()
+                .to_string();
+            let title = str_field(value, "title")
+                .or_else(|| str_field(value, "name"))
+                .unwrap_or_default()
+                .to_string();
+            let stored = parsed.and_then(|external| {
+                app.state::<Database>()
+                    .with(|conn| store(conn, source, &external))
+            });
+            let entry = match stored {
+                Ok((conversation_id, status, messages_added)) => ConversationImport {
+                    external_id,
+                    title,
+                    conversation_id: Some(conversation_id),
+                    status,
+                    messages_added,
+                    error: None,
+                },
+                Err(error) => ConversationImport {
+                    external_id,
+                    title,
+                    conversation_id: None,
+                    status: ImportStatus::Failed,
+                    messages_added: 0,
+                    error: Some(error),
+                },
+            };
+            match entry.status {
+                ImportStatus::Imported => report.imported += 1,
+                ImportStatus::Updated => report.updated += 1,
+                ImportStatus::Skipped => report.skipped += 1,
+                ImportStatus::Failed => report.failed += 1,
+            }
+            report.conversations.push(entry);
+        }
+        tracing::info!(
+            "Imported {} conversations from {} ({} updated, {} skipped, {} failed)",
+            report.imported,
+            source.name(),
+            report.updated,
+            report.skipped,
+            report.failed
+        );
+        Ok::<_, AppError>(report)
+    })
+    .await?
+}
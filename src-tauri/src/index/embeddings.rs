@@ -0,0 +1,97 @@
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::providers::{self, ProviderKind};
+use crate::settings::IndexSettings;
+use crate::{http, requests, usage};
+
+/// Inputs per request; providers cap batch sizes and a failed batch is cheaper to redo.
+const BATCH_SIZE: usize = 32;
+
+#[derive(Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+    usage: Option<EmbeddingUsage>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingUsage {
+    prompt_tokens: u64,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+/// Embed `inputs` through the provider's OpenAI-compatible `/embeddings` endpoint, one
+/// vector per input in order.
+pub async fn embed(
+    app: &tauri::AppHandle,
+    config: &IndexSettings,
+    inputs: &[String],
+) -> Result<Vec<Vec<f32>>, String> {
+    let provider = providers::resolve(app, &config.provider, config.base_url.as_deref())?;
+    let info = provider.info();
+    if info.kind == ProviderKind::Anthropic {
+        return Err("Anthropic has no embeddings API; pick another provider for the index".into());
+    }
+    if info.kind == ProviderKind::LlamaCpp {
+        return Err(
+            "llama.cpp chat models can't embed; pick another provider for the index".into(),
+        );
+    }
+    let base_url = &info.base_url;
+    let api_key = providers::api_key(provider.as_ref())?;
+    let client = http::client(app)?;
+    let timeout = requests::default_timeout(app);
+
+    let mut vectors = Vec::with_capacity(inputs.len());
+    for batch in inputs.chunks(BATCH_SIZE) {
+        let mut request = client
+            .post(format!("{base_url}/embeddings"))
+            .json(&json!({ "model": config.model, "input": batch }))
+            .timeout(timeout);
+        if let Some(key) = &api_key {
+            request = request.bearer_auth(key);
+        }
+        let response = http::send(app, &info.id, None, request).await?;
+        let mut response: EmbeddingResponse = providers::error_for_status(info, response)
+            .await?
+            .json()
+            .await
+            .map_err(|e| e.to_string())?;
+        if response.data.len() != batch.len() {
+            return Err(format!(
+                "{} returned {} embeddings for {} inputs",
+                info.name,
+                response.data.len(),
+                batch.len()
+            ));
+        }
+        usage::record_embedding(
+            app,
+            (&info.id, !info.requires_api_key),
+            &config.model,
+            batch,
+            response.usage.map(|u| u.prompt_tokens),
+        );
+        response.data.sort_by_key(|d| d.index);
+        vectors.extend(response.data.into_iter().map(|d| d.embedding));
+    }
+    Ok(vectors)
+}
+
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let (mut dot, mut norm_a, mut norm_b) = (0.0, 0.0, 0.0);
+    for (x, y) in a.iter().zip(b) {
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a.sqrt() * norm_b.sqrt())
+}
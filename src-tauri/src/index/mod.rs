@@ -0,0 +1,394 @@
+mod embeddings;
+mod store;
+mod watcher;
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use serde::Serialize;
+use tauri::{Emitter, Manager, State};
+
+use crate::capabilities;
+use crate::db::Database;
+use crate::documents::{self, DocumentFormat};
+use crate::error::AppError;
+use crate::jobs::{self, JobSpec, Progress};
+use crate::settings::{self, IndexSettings};
+use store::FileStamp;
+
+pub use store::IndexSource;
+pub use watcher::{sync as sync_watches, IndexWatcher};
+
+/// Stop walking a source after this many files so a mistaken pick (say, the home folder)
+/// can't run for hours.
+const MAX_FILES_PER_SOURCE: usize = 10_000;
+const DEFAULT_SEARCH_RESULTS: usize = 5;
+
+/// Held for the duration of an indexing run so two runs never embed the same file at once.
+#[derive(Default)]
+pub struct Indexer(tokio::sync::Mutex<()>);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexFailure {
+    pub path: String,
+    pub error: String,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexReport {
+    pub indexed: usize,
+    pub unchanged: usize,
+    pub removed: usize,
+    pub failed: Vec<IndexFailure>,
+}
+
+impl IndexReport {
+    fn merge(&mut self, other: IndexReport) {
+        self.indexed += other.indexed;
+        self.unchanged += other.unchanged;
+        self.removed += other.removed;
+        self.failed.extend(other.failed);
+    }
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct IndexProgress<'a> {
+    source: &'a str,
+    path: &'a str,
+    completed: usize,
+    total: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SemanticMatch {
+    pub path: String,
+    pub chunk_index: i64,
+    pub text: String,
+    pub score: f32,
+}
+
+/// Supported documents under `root` (or `root` itself), skipping hidden files and folders.
+fn collect_files(root: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(path) = pending.pop() {
+        if files.len() >= MAX_FILES_PER_SOURCE {
+            break;
+        }
+        if path.is_dir() {
+            let Ok(entries) = fs::read_dir(&path) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                if !entry.file_name().to_string_lossy().starts_with('.') {
+                    pending.push(entry.path());
+                }
+            }
+        } else if DocumentFormat::from_path(&path).is_some() {
+            files.push(path);
+        }
+    }
+    files.sort();
+    files
+}
+
+fn file_stamp(path: &Path, model: &str) -> Result<FileStamp, String> {
+    let metadata = fs::metadata(path).map_err(|e| e.to_string())?;
+    let modified_at = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default();
+    Ok(FileStamp {
+        modified_at,
+        size: metadata.len() as i64,
+        model: model.to_string(),
+    })
+}
+
+/// Extract, embed and store one file. Returns `false` when the stored copy is current.
+async fn index_file(
+    app: &tauri::AppHandle,
+    source: &str,
+    path: &Path,
+    known: Option<&FileStamp>,
+    config: &IndexSettings,
+) -> Result<bool, String> {
+    let stamp = file_stamp(path, &config.model)?;
+    if known == Some(&stamp) {
+        return Ok(false);
+    }
+    let owned = path.to_path_buf();
+    let document =
+        tauri::async_runtime::spawn_blocking(move || documents::extract(&owned, &|_, _| {}))
+            .await
+            .map_err(|e| e.to_string())??;
+    let texts: Vec<String> = document.chunks.iter().map(|c| c.text.clone()).collect();
+    let vectors = embeddings::embed(app, config, &texts).await?;
+    let key = path.to_string_lossy();
+    app.state::<Database>()
+        .with(|conn| store::replace_file(conn, source, &key, &stamp, &document.chunks, &vectors))?;
+    Ok(true)
+}
+
+/// Bring one source up to date: embed new and changed files and drop deleted ones,
+/// emitting `index-progress` and reporting to the job after each file.
+async fn index_source(
+    app: &tauri::AppHandle,
+    source: &str,
+    config: &IndexSettings,
+    progress: &Progress,
+) -> Result<IndexReport, String> {
+    let root = PathBuf::from(source);
+    let files = tauri::async_runtime::spawn_blocking(move || collect_files(&root))
+        .await
+        .map_err(|e| e.to_string())?;
+    let known = app
+        .state::<Database>()
+        .with(|conn| store::file_stamps(conn, source))?;
+    let mut report = IndexReport::default();
+
+    let present: HashSet<String> = files
+        .iter()
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect();
+    for path in known.keys().filter(|path| !present.contains(*path)) {
+        if app
+            .state::<Database>()
+            .with(|conn| store::remove_file(conn, path))?
+        {
+            report.removed += 1;
+        }
+    }
+
+    for (i, path) in files.iter().enumerate() {
+        let key = path.to_string_lossy();
+        match index_file(app, source, path, known.get(key.as_ref()), config).await {
+            Ok(true) => report.indexed += 1,
+            Ok(false) => report.unchanged += 1,
+            Err(error) => report.failed.push(IndexFailure {
+                path: key.to_string(),
+                error,
+            }),
+        }
+        let _ = app.emit(
+            "index-progress",
+            IndexProgress {
+                source,
+                path: &key,
+                completed: i + 1,
+                total: files.len(),
+            },
+        );
+        progress.report(i as u64 + 1, Some(files.len() as u64));
+    }
+    Ok(report)
+}
+
+/// Re-embed or drop paths the watcher reported as changed, emitting `index-progress`.
+async fn refresh_paths(app: &tauri::AppHandle, paths: HashSet<PathBuf>) {
+    let indexer = app.state::<Indexer>();
+    let _running = indexer.0.lock().await;
+    let db = app.state::<Database>();
+    let config = settings::current(app).index;
+
+    let mut paths: Vec<PathBuf> = paths.into_iter().collect();
+    paths.sort();
+    let mut files = Vec::new();
+    for path in paths {
+        let key = path.to_string_lossy().into_owned();
+        let Ok(Some(source)) = db.with(|conn| store::source_containing(conn, &key)) else {
+            continue;
+        };
+        let hidden = path.strip_prefix(&source).is_ok_and(|relative| {
+            relative
+                .components()
+                .any(|part| part.as_os_str().to_string_lossy().starts_with('.'))
+        });
+        if hidden {
+            continue;
+        }
+        if path.is_dir() {
+            let found = tauri::async_runtime::spawn_blocking(move || collect_files(&path))
+                .await
+                .unwrap_or_default();
+            files.extend(found.into_iter().map(|file| (source.clone(), file)));
+        } else if path.is_file() {
+            if DocumentFormat::from_path(&path).is_some() {
+                files.push((source, path));
+            }
+        } else if let Err(error) = db.with(|conn| store::remove_files_under(conn, &key)) {
+            tracing::error!("Failed to drop {key} from the index: {error}");
+        }
+    }
+    files.dedup();
+
+    for (i, (source, path)) in files.iter().enumerate() {
+        let key = path.to_string_lossy();
+        let known = db.with(|conn| store::file_stamp(conn, &key)).ok().flatten();
+        if let Err(error) = index_file(app, source, path, known.as_ref(), &config).await {
+            tracing::error!("Failed to index {key}: {error}");
+        }
+        let _ = app.emit(
+            "index-progress",
+            IndexProgress {
+                source,
+                path: &key,
+                completed: i + 1,
+                total: files.len(),
+            },
+        );
+    }
+}
+
+/// Bring `sources` up to date, or every source when `None`, as the job for indexing.
+pub(crate) async fn run_index(
+    app: tauri::AppHandle,
+    sources: Option<Vec<String>>,
+    progress: Progress,
+) -> Result<IndexReport, String> {
+    let indexer = app.state::<Indexer>();
+    let _running = indexer.0.lock().await;
+    let sources = match sources {
+        Some(sources) => sources,
+        None => app
+            .state::<Database>()
+            .with(|conn| store::sources(conn))?
+            .into_iter()
+            .map(|source| source.path)
+            .collect(),
+    };
+    let config = settings::current(&app).index;
+    let mut report = IndexReport::default();
+    for source in &sources {
+        report.merge(index_source(&app, source, &config, &progress).await?);
+    }
+    Ok(report)
+}
+
+/// Add folders or files to the index and embed them, as a job. A path inside an already
+/// indexed folder refreshes that folder instead of becoming a source of its own.
+#[tauri::command]
+pub async fn index_paths(
+    app: tauri::AppHandle,
+    paths: Vec<PathBuf>,
+) -> Result<IndexReport, AppError> {
+    let db = app.state::<Database>();
+    let mut sources = Vec::new();
+    for path in paths {
+        if !path.is_absolute() || !path.exists() {
+            return Err(AppError::InvalidInput(format!(
+                "{} is not an existing absolute path",
+                path.display()
+            )));
+        }
+        capabilities::require_path(&app, &path, "Index for search").await?;
+        let path = path.to_string_lossy().into_owned();
+        let source = db.with(|conn| {
+            let source = store::source_containing(conn, &path)?;
+            if source.is_none() {
+                store::add_source(conn, &path)?;
+            }
+            Ok(source.unwrap_or(path))
+        })?;
+        if !sources.contains(&source) {
+            sources.push(source);
+        }
+    }
+    watcher::sync(&app);
+
+    let task = app.clone();
+    let spec = JobSpec::Index {
+        sources: Some(sources.clone()),
+    };
+    jobs::run(&app, None, spec, move |progress| {
+        run_index(task, Some(sources), progress)
+    })
+    .await
+}
+
+/// Re-check every source for new, changed and deleted files, as a job.
+#[tauri::command]
+pub async fn reindex(app: tauri::AppHandle) -> Result<IndexReport, AppError> {
+    let task = app.clone();
+    let spec = JobSpec::Index { sources: None };
+    jobs::run(&app, None, spec, move |progress| {
+        run_index(task, None, progress)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn list_index_sources(db: State<'_, Database>) -> Result<Vec<IndexSource>, AppError> {
+    db.with(|conn| store::sources(conn)).map_err(AppError::from)
+}
+
+/// Stop indexing a source and drop everything stored for it. Returns whether it existed.
+#[tauri::command]
+pub async fn remove_index_source(
+    app: tauri::AppHandle,
+    indexer: State<'_, Indexer>,
+    path: String,
+) -> Result<bool, AppError> {
+    let _running = indexer.0.lock().await;
+    let removed = app
+        .state::<Database>()
+        .with(|conn| store::remove_source(conn, &path))?;
+    watcher::sync(&app);
+    Ok(removed)
+}
+
+/// Turn live re-indexing of a source on or off. Returns whether the source exists.
+#[tauri::command]
+pub async fn set_index_source_watched(
+    app: tauri::AppHandle,
+    path: String,
+    watched: bool,
+) -> Result<bool, AppError> {
+    let found = app
+        .state::<Database>()
+        .with(|conn| store::set_watched(conn, &path, watched))?;
+    watcher::sync(&app);
+    Ok(found)
+}
+
+/// The `k` indexed chunks closest in meaning to `query`, best first.
+#[tauri::command]
+pub async fn semantic_search(
+    app: tauri::AppHandle,
+    query: String,
+    k: Option<usize>,
+) -> Result<Vec<SemanticMatch>, AppError> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+    let config = settings::current(&app).index;
+    let query_vector = embeddings::embed(&app, &config, &[query.to_string()])
+        .await?
+        .pop()
+        .unwrap_or_default();
+    let chunks = app
+        .state::<Database>()
+        .with(|conn| store::chunks_for_model(conn, &config.model))?;
+
+    let mut matches: Vec<SemanticMatch> = chunks
+        .into_iter()
+        .filter(|chunk| chunk.embedding.len() == query_vector.len())
+        .map(|chunk| SemanticMatch {
+            score: embeddings::cosine_similarity(&query_vector, &chunk.embedding),
+            path: chunk.path,
+            chunk_index: chunk.chunk_index,
+            text: chunk.text,
+        })
+        .collect();
+    matches.sort_by(|a, b| b.score.total_cmp(&a.score));
+    matches.truncate(k.unwrap_or(DEFAULT_SEARCH_RESULTS).clamp(1, 50));
+    Ok(matches)
+}
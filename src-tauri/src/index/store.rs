@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+
+use crate::db;
+use crate::documents::DocumentChunk;
+
+/// A folder or file the user asked to have indexed.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexSource {
+    pub path: String,
+    pub created_at: i64,
+    /// Whether file changes are picked up as they happen.
+    pub watched: bool,
+    pub file_count: i64,
+    pub chunk_count: i64,
+}
+
+/// What an indexed file looked like when it was embedded, to tell whether it changed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileStamp {
+    pub modified_at: i64,
+    pub size: i64,
+    pub model: String,
+}
+
+/// A stored chunk with its embedding decoded.
+pub struct StoredChunk {
+    pub path: String,
+    pub chunk_index: i64,
+    pub text: String,
+    pub embedding: Vec<f32>,
+}
+
+fn encode_embedding(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn decode_embedding(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+/// Register a source. Sources inside it are folded into it, keeping their embeddings.
+pub fn add_source(conn: &mut Connection, path: &str) -> rusqlite::Result<()> {
+    const NESTED: &str = "SELECT path FROM index_sources
+        WHERE substr(path, 1, length(?1) + 1) IN (?1 || '/', ?1 || '\\')";
+    let tx = conn.transaction()?;
+    tx.execute(
+        "INSERT OR IGNORE INTO index_sources (path, created_at) VALUES (?1, ?2)",
+        params![path, db::now_ms()],
+    )?;
+    tx.execute(
+        &format!("UPDATE index_files SET source = ?1 WHERE source IN ({NESTED})"),
+        [path],
+    )?;
+    tx.execute(
+        &format!("DELETE FROM index_sources WHERE path IN ({NESTED})"),
+        [path],
+    )?;
+    tx.commit()
+}
+
+pub fn remove_source(conn: &Connection, path: &str) -> rusqlite::Result<bool> {
+    Ok(conn.execute("DELETE FROM index_sources WHERE path = ?1", [path])? > 0)
+}
+
+pub fn sources(conn: &Connection) -> rusqlite::Result<Vec<IndexSource>> {
+    let mut stmt = conn.prepare(
+        "SELECT s.path, s.created_at, s.watched,
+            (SELECT COUNT(*) FROM index_files f WHERE f.source = s.path),
+            (SELECT COUNT(*) FROM index_chunks c JOIN index_files f ON f.id = c.file_id
+             WHERE f.source = s.path)
+         FROM index_sources s ORDER BY s.path",
+    )?;
+    let sources = stmt
+        .query_map([], |row| {
+            Ok(IndexSource {
+                path: row.get(0)?,
+                created_at: row.get(1)?,
+                watched: row.get(2)?,
+                file_count: row.get(3)?,
+                chunk_count: row.get(4)?,
+            })
+        })?
+        .collect();
+    sources
+}
+
+pub fn file_stamps(
+    conn: &Connection,
+    source: &str,
+) -> rusqlite::Result<HashMap<String, FileStamp>> {
+    let mut stmt =
+        conn.prepare("SELECT path, modified_at, size, model FROM index_files WHERE source = ?1")?;
+    let stamps = stmt
+        .query_map([source], |row| {
+            Ok((
+                row.get(0)?,
+                FileStamp {
+                    modified_at: row.get(1)?,
+                    size: row.get(2)?,
+                    model: row.get(3)?,
+                },
+            ))
+        })?
+        .collect();
+    stamps
+}
+
+pub fn set_watched(conn: &Connection, path: &str, watched: bool) -> rusqlite::Result<bool> {
+    Ok(conn.execute(
+        "UPDATE index_sources SET watched = ?2 WHERE path = ?1",
+        params![path, watched],
+    )? > 0)
+}
+
+pub fn file_stamp(conn: &Connection, path: &str) -> rusqlite::Result<Option<FileStamp>> {
+    conn.query_row(
+        "SELECT modified_at, size, model FROM index_files WHERE path = ?1",
+        [path],
+        |row| {
+            Ok(FileStamp {
+                modified_at: row.get(0)?,
+                size: row.get(1)?,
+                model: row.get(2)?,
+            })
+        },
+    )
+    .optional()
+}
+
+pub fn remove_file(conn: &Connection, path: &str) -> rusqlite::Result<bool> {
+    Ok(conn.execute("DELETE FROM index_files WHERE path = ?1", [path])? > 0)
+}
+
+/// Drop `path` and, if it was a folder, every file that was under it.
+pub fn remove_files_under(conn: &Connection, path: &str) -> rusqlite::Result<usize> {
+    conn.execute(
+        "DELETE FROM index_files
+         WHERE path = ?1 OR substr(path, 1, length(?1) + 1) IN (?1 || '/', ?1 || '\\')",
+        [path],
+    )
+}
+
+/// Replace whatever was stored for `path` with freshly embedded chunks.
+pub fn replace_file(
+    conn: &mut Connection,
+    source: &str,
+    path: &str,
+    stamp: &FileStamp,
+    chunks: &[DocumentChunk],
+    embeddings: &[Vec<f32>],
+) -> rusqlite::Result<()> {
+    let tx = conn.transaction()?;
+    tx.execute("DELETE FROM index_files WHERE path = ?1", [path])?;
+    tx.execute(
+        "INSERT INTO index_files (source, path, modified_at, size, model, indexed_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            source,
+            path,
+            stamp.modified_at,
+            stamp.size,
+            stamp.model,
+            db::now_ms()
+        ],
+    )?;
+    let file_id = tx.last_insert_rowid();
+    {
+        let mut insert = tx.prepare(
+            "INSERT INTO index_chunks (file_id, chunk_index, offset, text, embedding)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?;
+        for (chunk, embedding) in chunks.iter().zip(embeddings) {
+            insert.execute(params![
+                file_id,
+                chunk.index,
+                chunk.offset,
+                chunk.text,
+                encode_embedding(embedding)
+            ])?;
+        }
+    }
+    tx.commit()
+}
+
+/// Every chunk embedded with `model`; vectors from other models aren't comparable.
+pub fn chunks_for_model(conn: &Connection, model: &str) -> rusqlite::Result<Vec<StoredChunk>> {
+    let mut stmt = conn.prepare(
+        "SELECT f.path, c.chunk_index, c.text, c.embedding
+         FROM index_chunks c JOIN index_files f ON f.id = c.file_id
+         WHERE f.model = ?1",
+    )?;
+    let chunks = stmt
+        .query_map([model], |row| {
+            Ok(StoredChunk {
+                path: row.get(0)?,
+                chunk_index: row.get(1)?,
+                text: row.get(2)?,
+                embedding: decode_embedding(&row.get::<_, Vec<u8>>(3)?),
+            })
+        })?
+        .collect();
+    chunks
+}
+
+/// The registered source that covers `path`, if any.
+pub fn source_containing(conn: &Connection, path: &str) -> rusqlite::Result<Option<String>> {
+    conn.query_row(
+        "SELECT path FROM index_sources
+         WHERE ?1 = path OR substr(?1, 1, length(path) + 1) IN (path || '/', path || '\\')
+         ORDER BY length(path) DESC LIMIT 1",
+        [path],
+        |row| row.get(0),
+    )
+    .optional()
+}
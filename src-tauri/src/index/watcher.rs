@@ -0,0 +1,94 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use tauri::Manager;
+use tokio::sync::mpsc;
+
+use super::store;
+use crate::db::Database;
+
+/// Editors save in bursts (temp file, rename, metadata touch), so changes are collected
+/// until the folder has been quiet for this long.
+const DEBOUNCE: Duration = Duration::from_secs(2);
+
+struct Watching {
+    watcher: RecommendedWatcher,
+    paths: Vec<PathBuf>,
+}
+
+/// File system watches on every source that has watching turned on.
+pub struct IndexWatcher(Mutex<Watching>);
+
+impl IndexWatcher {
+    pub fn start(app: &tauri::AppHandle) -> Result<Self, String> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let watcher =
+            notify::recommended_watcher(
+                move |result: notify::Result<notify::Event>| match result {
+                    Ok(event) if !matches!(event.kind, EventKind::Access(_)) => {
+                        let _ = tx.send(event.paths);
+                    }
+                    Ok(_) => {}
+                    Err(error) => tracing::warn!("Index watcher error: {error}"),
+                },
+            )
+            .map_err(|e| e.to_string())?;
+        tauri::async_runtime::spawn(debounce(app.clone(), rx));
+        Ok(Self(Mutex::new(Watching {
+            watcher,
+            paths: Vec::new(),
+        })))
+    }
+}
+
+async fn debounce(app: tauri::AppHandle, mut rx: mpsc::UnboundedReceiver<Vec<PathBuf>>) {
+    while let Some(paths) = rx.recv().await {
+        let mut pending: HashSet<PathBuf> = paths.into_iter().collect();
+        loop {
+            match tokio::time::timeout(DEBOUNCE, rx.recv()).await {
+                Ok(Some(paths)) => pending.extend(paths),
+                Ok(None) => return,
+                Err(_) => break,
+            }
+        }
+        super::refresh_paths(&app, pending).await;
+    }
+}
+
+/// Point the watches at the sources that currently want them. Call after sources are
+/// added or removed or their watch flag changes.
+pub fn sync(app: &tauri::AppHandle) {
+    let wanted: Vec<PathBuf> = match app.state::<Database>().with(|conn| store::sources(conn)) {
+        Ok(sources) => sources
+            .into_iter()
+            .filter(|source| source.watched)
+            .map(|source| PathBuf::from(source.path))
+            .collect(),
+        Err(error) => {
+            tracing::warn!("Failed to read index sources: {error}");
+            return;
+        }
+    };
+
+    let state = app.state::<IndexWatcher>();
+    let mut watching = state.0.lock().unwrap();
+    let Watching { watcher, paths } = &mut *watching;
+    for path in paths.iter().filter(|path| !wanted.contains(path)) {
+        let _ = watcher.unwatch(path);
+    }
+    let mut watched = Vec::new();
+    for path in wanted {
+        if paths.contains(&path) {
+            watched.push(path);
+            continue;
+        }
+        match watcher.watch(&path, RecursiveMode::Recursive) {
+            Ok(()) => watched.push(path),
+            Err(error) => tracing::warn!("Could not watch {}: {error}", path.display()),
+        }
+    }
+    *paths = watched;
+}
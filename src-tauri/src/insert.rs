@@ -0,0 +1,68 @@
+use std::thread;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::capabilities::{self, Capability};
+use crate::clipboard::{self, ClipboardContent};
+use crate::error::AppError;
+use crate::{focus, keyboard, window_manager};
+
+/// Time for the previous app to come back to the front and take keyboard focus.
+const FOCUS_SETTLE_DELAY: Duration = Duration::from_millis(150);
+/// Time for the target app to read the clipboard before it is put back.
+const PASTE_SETTLE_DELAY: Duration = Duration::from_millis(300);
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InsertMethod {
+    /// Put the text on the clipboard and send Ctrl/Cmd+V. Fast and handles any text.
+    #[default]
+    Paste,
+    /// Synthesize a key event per character. Works where pasting is blocked, but slowly.
+    Type,
+}
+
+/// Paste `text` into whatever has keyboard focus, putting the clipboard back afterwards.
+pub fn paste(app: &tauri::AppHandle, text: &str) -> Result<(), String> {
+    let _borrow = clipboard::borrow(app);
+    let saved = clipboard::read(app).ok();
+    clipboard::write(
+        app,
+        &ClipboardContent::Text {
+            text: text.to_string(),
+        },
+    )?;
+    let result = keyboard::send_paste();
+    thread::sleep(PASTE_SETTLE_DELAY);
+    if let Some(saved) = saved {
+        let _ = clipboard::write(app, &saved);
+    }
+    result
+}
+
+/// Hide the overlay, give focus back to the app that had it before, and insert `text`
+/// there as if the user had typed it.
+#[tauri::command]
+pub async fn insert_text_into_active_app(
+    app: tauri::AppHandle,
+    text: String,
+    method: Option<InsertMethod>,
+) -> Result<(), AppError> {
+    capabilities::require(&app, Capability::Accessibility, "Insert text into an app").await?;
+    tauri::async_runtime::spawn_blocking(move || {
+        window_manager::hide_overlay(&app)?;
+        // Hiding usually hands focus back on its own; activating explicitly covers window
+        // managers that pick something else.
+        if let Err(error) = focus::restore(&app) {
+            tracing::warn!("Falling back to the window manager's choice of focus: {error}");
+        }
+        thread::sleep(FOCUS_SETTLE_DELAY);
+        match method.unwrap_or_default() {
+            InsertMethod::Paste => paste(&app, &text),
+            InsertMethod::Type => keyboard::type_text(&text),
+        }
+    })
+    .await?
+    .map_err(AppError::from)
+}
@@ -0,0 +1,346 @@
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, Manager};
+use tokio::sync::Notify;
+
+use crate::error::AppError;
+use crate::models::{self, ModelKind};
+use crate::{db, export, index, ollama, persist, requests, settings, whisper};
+
+const JOBS_FILE: &str = "jobs.json";
+/// Finished jobs kept for `list_jobs`, newest first.
+const FINISHED_KEPT: usize = 50;
+
+/// What a job does, with everything needed to run it again after a restart.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum JobSpec {
+    /// Bring these index sources up to date, or every source when `None`.
+    Index {
+        sources: Option<Vec<String>>,
+    },
+    Transcribe {
+        path: PathBuf,
+        language: Option<String>,
+    },
+    DownloadWhisperModel {
+        name: String,
+    },
+    PullOllamaModel {
+        model: String,
+        base_url: Option<String>,
+    },
+    ExportAll {
+        path: PathBuf,
+    },
+    DownloadModel {
+        kind: ModelKind,
+        file: String,
+        url: String,
+        sha256: Option<String>,
+    },
+}
+
+impl JobSpec {
+    fn title(&self) -> String {
+        let name = |path: &Path| {
+            path.file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        };
+        match self {
+            Self::Index { sources: None } => "Re-index every folder".to_string(),
+            Self::Index {
+                sources: Some(sources),
+            } => match sources.as_slice() {
+                [source] => format!("Index {}", name(Path::new(source))),
+                _ => format!("Index {} folders", sources.len()),
+            },
+            Self::Transcribe { path, .. } => format!("Transcribe {}", name(path)),
+            Self::DownloadWhisperModel { name } => format!("Download the Whisper model {name}"),
+            Self::PullOllamaModel { model, .. } => format!("Download {model} into Ollama"),
+            Self::ExportAll { path } => format!("Export history to {}", name(path)),
+            Self::DownloadModel { file, .. } => format!("Download {file}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum JobState {
+    /// Waiting for one of the `jobs.maxConcurrent` slots.
+    Queued,
+    Running,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Job {
+    /// Also a request id, so `cancel_request` stops it too.
+    pub id: String,
+    #[serde(flatten)]
+    pub spec: JobSpec,
+    pub title: String,
+    pub state: JobState,
+    /// Bytes, files or pages, depending on the job; `total` is missing when unknown.
+    pub completed: Option<u64>,
+    pub total: Option<u64>,
+    pub error: Option<String>,
+    pub created_at: i64,
+    pub started_at: Option<i64>,
+    pub finished_at: Option<i64>,
+}
+
+impl Job {
+    fn finished(&self) -> bool {
+        !matches!(self.state, JobState::Queued | JobState::Running)
+    }
+}
+
+/// Every job this run has seen. Unfinished ones are saved, to start again on the next
+/// launch when the app quits or crashes while they wait or run.
+pub struct JobQueue {
+    path: PathBuf,
+    jobs: Mutex<Vec<Job>>,
+    /// Left over from the last run, until `resume` takes them.
+    pending: Mutex<Vec<Job>>,
+    running: Mutex<usize>,
+    freed: Notify,
+}
+
+impl JobQueue {
+    pub fn load(app: &tauri::AppHandle) -> Result<Self, String> {
+        let path = persist::data_file(app, JOBS_FILE)?;
+        let pending: Vec<Job> = persist::load_json(&path);
+        Ok(Self {
+            path,
+            jobs: Mutex::new(Vec::new()),
+            pending: Mutex::new(pending),
+            running: Mutex::new(0),
+            freed: Notify::new(),
+        })
+    }
+
+    fn persist(&self, jobs: &[Job]) {
+        let unfinished: Vec<&Job> = jobs.iter().filter(|job| !job.finished()).collect();
+        if let Err(error) = persist::save_json(&self.path, &unfinished) {
+            tracing::warn!("Could not save the job queue: {error}");
+        }
+    }
+}
+
+/// Change a job, emit `job-updated` with it, and save the queue when `save` is set.
+fn update(app: &tauri::AppHandle, id: &str, save: bool, change: impl FnOnce(&mut Job)) {
+    let queue = app.state::<JobQueue>();
+    let mut jobs = queue.jobs.lock().unwrap();
+    let Some(job) = jobs.iter_mut().find(|job| job.id == id) else {
+        return;
+    };
+    change(job);
+    let _ = app.emit("job-updated", &*job);
+    if save {
+        queue.persist(&jobs);
+    }
+}
+
+/// Lets a running job report how far along it is.
+#[derive(Clone)]
+pub struct Progress {
+    app: tauri::AppHandle,
+    id: String,
+}
+
+impl Progress {
+    pub fn report(&self, completed: u64, total: Option<u64>) {
+        update(&self.app, &self.id, false, |job| {
+            job.completed = Some(completed);
+            job.total = total;
+        });
+    }
+}
+
+/// One of the `jobs.maxConcurrent` slots, given back when dropped, including when the
+/// job is cancelled.
+struct Slot(tauri::AppHandle);
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        let queue = self.0.state::<JobQueue>();
+        *queue.running.lock().unwrap() -= 1;
+        queue.freed.notify_waiters();
+    }
+}
+
+async fn acquire(app: &tauri::AppHandle) -> Slot {
+    let queue = app.state::<JobQueue>();
+    loop {
+        // Created before checking, so a slot freed in between still wakes us.
+        let freed = queue.freed.notified();
+        {
+            let mut running = queue.running.lock().unwrap();
+            if *running < settings::current(app).jobs.max_concurrent {
+                *running += 1;
+                return Slot(app.clone());
+            }
+        }
+        freed.await;
+    }
+}
+
+/// Run `work` as a job: queued until a slot is free, then running with `Progress` to
+/// report through, listed by `list_jobs` and stopped by `cancel_job`. Returns its
+/// result to the caller like any request. `id` defaults to a new one.
+pub async fn run<T, E, F, Fut>(
+    app: &tauri::AppHandle,
+    id: Option<String>,
+    spec: JobSpec,
+    work: F,
+) -> Result<T, AppError>
+where
+    T: Send + 'static,
+    E: Into<AppError> + Send + 'static,
+    F: FnOnce(Progress) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<T, E>> + Send + 'static,
+{
+    let id = id.unwrap_or_else(requests::new_id);
+    let job = Job {
+        id: id.clone(),
+        title: spec.title(),
+        spec,
+        state: JobState::Queued,
+        completed: None,
+        total: None,
+        error: None,
+        created_at: db::now_ms(),
+        started_at: None,
+        finished_at: None,
+    };
+    {
+        let queue = app.state::<JobQueue>();
+        let mut jobs = queue.jobs.lock().unwrap();
+        jobs.retain(|other| other.id != id);
+        jobs.insert(0, job.clone());
+        let mut finished = 0;
+        jobs.retain(|job| {
+            finished += usize::from(job.finished());
+            !job.finished() || finished <= FINISHED_KEPT
+        });
+        queue.persist(&jobs);
+    }
+    let _ = app.emit("job-updated", &job);
+
+    let task_app = app.clone();
+    let progress = Progress {
+        app: app.clone(),
+        id: id.clone(),
+    };
+    let result = requests::run(app, Some(id.clone()), None, async move {
+        let _slot = acquire(&task_app).await;
+        update(&task_app, &progress.id, false, |job| {
+            job.state = JobState::Running;
+            job.started_at = Some(db::now_ms());
+        });
+        work(progress).await.map_err(Into::into)
+    })
+    .await;
+
+    update(app, &id, true, |job| {
+        job.finished_at = Some(db::now_ms());
+        match &result {
+            Ok(_) => job.state = JobState::Done,
+            Err(AppError::Cancelled) => job.state = JobState::Cancelled,
+            Err(error) => {
+                job.state = JobState::Failed;
+                job.error = Some(error.to_string());
+            }
+        }
+    });
+    result
+}
+
+/// Run a job again from its spec alone, for one left over from the last run.
+async fn execute(app: tauri::AppHandle, id: String, spec: JobSpec) -> Result<(), AppError> {
+    let task = app.clone();
+    let id = Some(id);
+    match spec.clone() {
+        JobSpec::Index { sources } => {
+            run(&app, id, spec, move |progress| {
+                index::run_index(task, sources, progress)
+            })
+            .await?;
+        }
+        JobSpec::Transcribe { path, language } => {
+            run(&app, id, spec, move |_| {
+                whisper::transcribe(task, path, language)
+            })
+            .await?;
+        }
+        JobSpec::DownloadWhisperModel { name } => {
+            run(&app, id, spec, move |progress| {
+                whisper::download(task, name, progress)
+            })
+            .await?;
+        }
+        JobSpec::PullOllamaModel { model, base_url } => {
+            run(&app, id, spec, move |progress| {
+                ollama::pull(task, model, base_url, progress)
+            })
+            .await?;
+        }
+        JobSpec::ExportAll { path } => {
+            run(&app, id, spec, move |_| export::write_all(task, path)).await?;
+        }
+        JobSpec::DownloadModel {
+            kind,
+            file,
+            url,
+            sha256,
+        } => {
+            run(&app, id, spec, move |progress| {
+                models::download(task, kind, file, url, sha256, progress)
+            })
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Start again the jobs the last run left unfinished. Their results only show up as
+/// `job-updated` events, since whoever asked for them is gone.
+pub fn resume(app: &tauri::AppHandle) {
+    let pending = std::mem::take(&mut *app.state::<JobQueue>().pending.lock().unwrap());
+    for job in pending {
+        tracing::info!("Resuming job {} ({})", job.title, job.id);
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(error) = execute(app, job.id.clone(), job.spec).await {
+                tracing::warn!("Resumed job {} failed: {error}", job.id);
+            }
+        });
+    }
+}
+
+/// Jobs still waiting or running, then finished ones, newest first.
+#[tauri::command]
+pub fn list_jobs(app: tauri::AppHandle) -> Vec<Job> {
+    let mut jobs = app.state::<JobQueue>().jobs.lock().unwrap().clone();
+    jobs.sort_by_key(|job| (job.finished(), std::cmp::Reverse(job.created_at)));
+    jobs
+}
+
+/// Stop a job, whether it is waiting or running. Returns `false` if it had already
+/// finished.
+#[tauri::command]
+pub fn cancel_job(app: tauri::AppHandle, id: String) -> bool {
+    requests::cancel(&app, &id)
+}
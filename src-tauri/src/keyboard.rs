@@ -0,0 +1,65 @@
+use enigo::{Direction, Enigo, Key, Keyboard, Settings};
+
+/// The modifier used for copy/paste on this platform.
+#[cfg(target_os = "macos")]
+const PRIMARY_MODIFIER: Key = Key::Meta;
+#[cfg(not(target_os = "macos"))]
+const PRIMARY_MODIFIER: Key = Key::Control;
+
+fn enigo() -> Result<Enigo, String> {
+    Enigo::new(&Settings::default()).map_err(|e| e.to_string())
+}
+
+/// Release modifiers the user may still be holding from the global hotkey, so they don't
+/// combine with the keystrokes we synthesize (Ctrl+Shift+C is not copy in most apps).
+fn release_modifiers(enigo: &mut Enigo) -> Result<(), String> {
+    for key in [Key::Shift, Key::Alt, Key::Control, Key::Meta] {
+        enigo
+            .key(key, Direction::Release)
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+fn send_primary_chord(key: char) -> Result<(), String> {
+    let mut enigo = enigo()?;
+    release_modifiers(&mut enigo)?;
+    enigo
+        .key(PRIMARY_MODIFIER, Direction::Press)
+        .map_err(|e| e.to_string())?;
+    let result = enigo.key(Key::Unicode(key), Direction::Click);
+    // Always release the modifier, even if the key press failed, so it doesn't get stuck.
+    enigo
+        .key(PRIMARY_MODIFIER, Direction::Release)
+        .map_err(|e| e.to_string())?;
+    result.map_err(|e| e.to_string())
+}
+
+/// Send Ctrl+C (Cmd+C on macOS) to the focused application.
+pub fn send_copy() -> Result<(), String> {
+    send_primary_chord('c')
+}
+
+/// Send Ctrl+V (Cmd+V on macOS) to the focused application.
+pub fn send_paste() -> Result<(), String> {
+    send_primary_chord('v')
+}
+
+/// Press Backspace `count` times in the focused application.
+pub fn erase(count: usize) -> Result<(), String> {
+    let mut enigo = enigo()?;
+    release_modifiers(&mut enigo)?;
+    for _ in 0..count {
+        enigo
+            .key(Key::Backspace, Direction::Click)
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Type `text` into the focused application as individual key events.
+pub fn type_text(text: &str) -> Result<(), String> {
+    let mut enigo = enigo()?;
+    release_modifiers(&mut enigo)?;
+    enigo.text(text).map_err(|e| e.to_string())
+}
@@ -0,0 +1,447 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::Emitter;
+use tauri_plugin_global_shortcut::{Code, Modifiers, Shortcut};
+
+use crate::{quick_actions, shortcuts};
+
+/// How often the layout is checked for a change.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// The keyboard layout in use.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyboardLayout {
+    /// Changes whenever the layout does; not meant to be shown.
+    pub id: String,
+    pub name: Option<String>,
+}
+
+#[cfg(windows)]
+mod platform {
+    use tauri_plugin_global_shortcut::Code;
+    use windows::Win32::UI::Input::KeyboardAndMouse::{
+        GetKeyboardLayout, MapVirtualKeyExW, HKL, MAPVK_VK_TO_CHAR, VIRTUAL_KEY, VK_OEM_1,
+        VK_OEM_102, VK_OEM_2, VK_OEM_3, VK_OEM_4, VK_OEM_5, VK_OEM_6, VK_OEM_7, VK_OEM_COMMA,
+        VK_OEM_MINUS, VK_OEM_PERIOD, VK_OEM_PLUS,
+    };
+    use windows::Win32::UI::WindowsAndMessaging::{GetForegroundWindow, GetWindowThreadProcessId};
+
+    use super::KeyboardLayout;
+
+    /// Each thread has its own layout, and the one that counts is the app in front's.
+    fn layout() -> HKL {
+        // SAFETY: plain queries; a missing window gives thread 0, this thread's layout.
+        unsafe { GetKeyboardLayout(GetWindowThreadProcessId(GetForegroundWindow(), None)) }
+    }
+
+    pub fn current(_app: &tauri::AppHandle) -> Option<KeyboardLayout> {
+        Some(KeyboardLayout {
+            id: format!("{:08x}", layout().0 as usize),
+            name: None,
+        })
+    }
+
+    /// Shortcuts are registered by virtual key, which letters and digits keep across
+    /// layouts. Punctuation keys are named for what the layout puts on them.
+    pub fn key_label(_app: &tauri::AppHandle, code: Code) -> Option<String> {
+        let key: VIRTUAL_KEY = match code {
+            Code::Minus => VK_OEM_MINUS,
+            Code::Equal => VK_OEM_PLUS,
+            Code::BracketLeft => VK_OEM_4,
+            Code::BracketRight => VK_OEM_6,
+            Code::Backslash => VK_OEM_5,
+            Code::Semicolon => VK_OEM_1,
+            Code::Quote => VK_OEM_7,
+            Code::Comma => VK_OEM_COMMA,
+            Code::Period => VK_OEM_PERIOD,
+            Code::Slash => VK_OEM_2,
+            Code::Backquote => VK_OEM_3,
+            Code::IntlBackslash => VK_OEM_102,
+            _ => return None,
+        };
+        // SAFETY: a plain query. The top bit marks a dead key, which still has its char.
+        let mapped = unsafe { MapVirtualKeyExW(key.0.into(), MAPVK_VK_TO_CHAR, Some(layout())) };
+        char::from_u32(mapped & 0x7fff_ffff)
+            .filter(|c| *c != '\0')
+            .map(|c| c.to_uppercase().to_string())
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::ffi::c_void;
+    use std::sync::mpsc;
+
+    use core_foundation::base::{CFType, TCFType};
+    use core_foundation::data::{CFData, CFDataRef};
+    use core_foundation::string::{CFString, CFStringRef};
+    use tauri_plugin_global_shortcut::Code;
+
+    use super::KeyboardLayout;
+
+    const KEY_ACTION_DISPLAY: u16 = 3;
+    const NO_DEAD_KEYS: u32 = 1;
+
+    #[link(name = "Carbon", kind = "framework")]
+    extern "C" {
+        static kTISPropertyInputSourceID: CFStringRef;
+        static kTISPropertyLocalizedName: CFStringRef;
+        static kTISPropertyUnicodeKeyLayoutData: CFStringRef;
+        fn TISCopyCurrentKeyboardLayoutInputSource() -> *const c_void;
+        fn TISGetInputSourceProperty(source: *const c_void, key: CFStringRef) -> *const c_void;
+        fn LMGetKbdType() -> u8;
+        #[allow(clippy::too_many_arguments)]
+        fn UCKeyTranslate(
+            layout: *const u8,
+            virtual_key: u16,
+            action: u16,
+            modifiers: u32,
+            keyboard_type: u32,
+            options: u32,
+            dead_key_state: *mut u32,
+            max_len: usize,
+            actual_len: *mut usize,
+            chars: *mut u16,
+        ) -> i32;
+    }
+
+    /// Text Input Sources may only be asked from the main thread.
+    fn on_main_thread<T: Send + 'static>(
+        app: &tauri::AppHandle,
+        f: impl FnOnce() -> Option<T> + Send + 'static,
+    ) -> Option<T> {
+        let (tx, rx) = mpsc::channel();
+        app.run_on_main_thread(move || {
+            let _ = tx.send(f());
+        })
+        .ok()?;
+        rx.recv().ok().flatten()
+    }
+
+    /// The current layout, released when dropped.
+    fn with_layout<T>(f: impl FnOnce(*const c_void) -> Option<T>) -> Option<T> {
+        // SAFETY: returns a retained input source or null.
+        let source = unsafe { TISCopyCurrentKeyboardLayoutInputSource() };
+        if source.is_null() {
+            return None;
+        }
+        // SAFETY: the copy above is ours to release, which dropping this does.
+        let _owned = unsafe { CFType::wrap_under_create_rule(source.cast()) };
+        f(source)
+    }
+
+    fn string_property(source: *const c_void, key: CFStringRef) -> Option<String> {
+        // SAFETY: the property belongs to `source`, which outlives it here.
+        let value = unsafe { TISGetInputSourceProperty(source, key) };
+        // SAFETY: both properties read with this are CFStrings.
+        (!value.is_null())
+            .then(|| unsafe { CFString::wrap_under_get_rule(value.cast()) }.to_string())
+    }
+
+    pub fn current(app: &tauri::AppHandle) -> Option<KeyboardLayout> {
+        on_main_thread(app, || {
+            with_layout(|source| {
+                // SAFETY: constants exported by the framework.
+                let (id_key, name_key) =
+                    unsafe { (kTISPropertyInputSourceID, kTISPropertyLocalizedName) };
+                Some(KeyboardLayout {
+                    id: string_property(source, id_key)?,
+                    name: string_property(source, name_key),
+                })
+            })
+        })
+    }
+
+    /// The key code of a character key, which is the key's position.
+    fn key_code(code: Code) -> Option<u16> {
+        Some(match code {
+            Code::KeyA => 0x00,
+            Code::KeyS => 0x01,
+            Code::KeyD => 0x02,
+            Code::KeyF => 0x03,
+            Code::KeyH => 0x04,
+            Code::KeyG => 0x05,
+            Code::KeyZ => 0x06,
+            Code::KeyX => 0x07,
+            Code::KeyC => 0x08,
+            Code::KeyV => 0x09,
+            Code::IntlBackslash => 0x0a,
+            Code::KeyB => 0x0b,
+            Code::KeyQ => 0x0c,
+            Code::KeyW => 0x0d,
+            Code::KeyE => 0x0e,
+            Code::KeyR => 0x0f,
+            Code::KeyY => 0x10,
+            Code::KeyT => 0x11,
+            Code::Digit1 => 0x12,
+            Code::Digit2 => 0x13,
+            Code::Digit3 => 0x14,
+            Code::Digit4 => 0x15,
+            Code::Digit6 => 0x16,
+            Code::Digit5 => 0x17,
+            Code::Equal => 0x18,
+            Code::Digit9 => 0x19,
+            Code::Digit7 => 0x1a,
+            Code::Minus => 0x1b,
+            Code::Digit8 => 0x1c,
+            Code::Digit0 => 0x1d,
+            Code::BracketRight => 0x1e,
+            Code::KeyO => 0x1f,
+            Code::KeyU => 0x20,
+            Code::BracketLeft => 0x21,
+            Code::KeyI => 0x22,
+            Code::KeyP => 0x23,
+            Code::KeyL => 0x25,
+            Code::KeyJ => 0x26,
+            Code::Quote => 0x27,
+            Code::KeyK => 0x28,
+            Code::Semicolon => 0x29,
+            Code::Backslash => 0x2a,
+            Code::Comma => 0x2b,
+            Code::Slash => 0x2c,
+            Code::KeyN => 0x2d,
+            Code::KeyM => 0x2e,
+            Code::Period => 0x2f,
+            Code::Backquote => 0x32,
+            _ => return None,
+        })
+    }
+
+    /// Shortcuts are registered by key position, so the key is named for what the
+    /// layout puts there: ⌘Q on AZERTY is the key labelled A.
+    pub fn key_label(app: &tauri::AppHandle, code: Code) -> Option<String> {
+        let key_code = key_code(code)?;
+        on_main_thread(app, move || {
+            with_layout(|source| {
+                // SAFETY: a constant exported by the framework.
+                let key = unsafe { kTISPropertyUnicodeKeyLayoutData };
+                // SAFETY: the property belongs to `source`, which outlives it here.
+                let data = unsafe { TISGetInputSourceProperty(source, key) };
+                if data.is_null() {
+                    return None;
+                }
+                // SAFETY: the layout data is a CFData.
+                let data = unsafe { CFData::wrap_under_get_rule(data as CFDataRef) };
+                let mut dead_keys = 0;
+                let mut len = 0;
+                let mut chars = [0u16; 4];
+                // SAFETY: the buffers are as long as passed and outlive the call.
+                let status = unsafe {
+                    UCKeyTranslate(
+                        data.bytes().as_ptr(),
+                        key_code,
+                        KEY_ACTION_DISPLAY,
+                        0,
+                        LMGetKbdType().into(),
+                        NO_DEAD_KEYS,
+                        &mut dead_keys,
+                        chars.len(),
+                        &mut len,
+                        chars.as_mut_ptr(),
+                    )
+                };
+                let label = String::from_utf16(&chars[..len.min(chars.len())]).ok()?;
+                (status == 0 && !label.trim().is_empty()).then(|| label.to_uppercase())
+            })
+        })
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    use tauri_plugin_global_shortcut::Code;
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xproto::ConnectionExt;
+
+    use super::KeyboardLayout;
+    use crate::x11;
+
+    /// Shortcuts are grabbed by the key the mapping puts their symbol on, so a new
+    /// mapping is a new layout, whatever it is called.
+    pub fn current(_app: &tauri::AppHandle) -> Option<KeyboardLayout> {
+        let (conn, root) = x11::connect().ok()?;
+        let setup = conn.setup();
+        let (min, max) = (setup.min_keycode, setup.max_keycode);
+        let mapping = conn
+            .get_keyboard_mapping(min, max - min + 1)
+            .ok()?
+            .reply()
+            .ok()?;
+        let mut hasher = DefaultHasher::new();
+        mapping.keysyms.hash(&mut hasher);
+        // Rules, model, layout, variant and options, each ending in a NUL.
+        let names = x11::atom(&conn, "_XKB_RULES_NAMES")
+            .ok()
+            .and_then(|atom| x11::property_string(&conn, root, atom))
+            .unwrap_or_default();
+        let names: Vec<String> = names
+            .split(|b| *b == 0)
+            .map(|name| String::from_utf8_lossy(name).into_owned())
+            .collect();
+        let name = match (names.get(2), names.get(3)) {
+            (Some(layout), Some(variant)) if !variant.is_empty() => {
+                Some(format!("{layout} ({variant})"))
+            }
+            (Some(layout), _) if !layout.is_empty() => Some(layout.clone()),
+            _ => None,
+        };
+        Some(KeyboardLayout {
+            id: format!("{:016x}", hasher.finish()),
+            name,
+        })
+    }
+
+    /// Shortcuts are grabbed by symbol, so a key is what the accelerator calls it.
+    pub fn key_label(_app: &tauri::AppHandle, _code: Code) -> Option<String> {
+        None
+    }
+}
+
+pub fn current(app: &tauri::AppHandle) -> Option<KeyboardLayout> {
+    platform::current(app)
+}
+
+/// What a key is called when the layout has nothing to say about it.
+fn default_label(code: Code) -> String {
+    let name = code.to_string();
+    let label = match code {
+        Code::Space => "Space",
+        Code::Enter => "Enter",
+        Code::Escape => "Esc",
+        Code::Backspace => "Backspace",
+        Code::Delete => "Del",
+        Code::ArrowUp => "↑",
+        Code::ArrowDown => "↓",
+        Code::ArrowLeft => "←",
+        Code::ArrowRight => "→",
+        Code::PageUp => "PgUp",
+        Code::PageDown => "PgDn",
+        Code::Minus => "-",
+        Code::Equal => "=",
+        Code::BracketLeft => "[",
+        Code::BracketRight => "]",
+        Code::Backslash | Code::IntlBackslash => "\\",
+        Code::Semicolon => ";",
+        Code::Quote => "'",
+        Code::Comma => ",",
+        Code::Period => ".",
+        Code::Slash => "/",
+        Code::Backquote => "`",
+        _ => {
+            return match (name.strip_prefix("Key"), name.strip_prefix("Digit")) {
+                (Some(letter), _) => letter.to_string(),
+                (_, Some(digit)) => digit.to_string(),
+                _ => match name.strip_prefix("Numpad") {
+                    Some(key) => format!("Num {key}"),
+                    None => name,
+                },
+            }
+        }
+    };
+    label.to_string()
+}
+
+/// The modifiers as the platform writes them, in its usual order.
+pub fn modifiers_label(mods: Modifiers) -> Vec<&'static str> {
+    let names: [(Modifiers, &str); 4] = if cfg!(target_os = "macos") {
+        [
+            (Modifiers::CONTROL, "⌃"),
+            (Modifiers::ALT, "⌥"),
+            (Modifiers::SHIFT, "⇧"),
+            (Modifiers::SUPER | Modifiers::META, "⌘"),
+        ]
+    } else {
+        [
+            (Modifiers::CONTROL, "Ctrl"),
+            (Modifiers::ALT, "Alt"),
+            (Modifiers::SHIFT, "Shift"),
+            (
+                Modifiers::SUPER | Modifiers::META,
+                if cfg!(windows) { "Win" } else { "Super" },
+            ),
+        ]
+    };
+    names
+        .into_iter()
+        .filter(|(modifier, _)| mods.intersects(*modifier))
+        .map(|(_, name)| name)
+        .collect()
+}
+
+/// How `shortcut` reads on the current layout, such as `Ctrl+Shift+Space` or `⌘⇧K`.
+pub fn describe(app: &tauri::AppHandle, shortcut: Shortcut) -> String {
+    let key = platform::key_label(app, shortcut.key).unwrap_or_else(|| default_label(shortcut.key));
+    let mut parts = modifiers_label(shortcut.mods);
+    parts.push(&key);
+    parts.join(if cfg!(target_os = "macos") { "" } else { "+" })
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LayoutChangedPayload {
+    layout: KeyboardLayout,
+    /// Every bound shortcut, as it now reads, by action.
+    shortcuts: HashMap<shortcuts::ShortcutAction, String>,
+}
+
+/// Watch for the layout changing for as long as the app runs, emitting
+/// `keyboard-layout-changed`. On X11, where a shortcut is grabbed on whichever key its
+/// symbol was on when it was registered, every shortcut is registered again.
+pub fn start(app: &tauri::AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut last: Option<KeyboardLayout> = None;
+        loop {
+            let handle = app.clone();
+            let layout = tauri::async_runtime::spawn_blocking(move || current(&handle))
+                .await
+                .ok()
+                .flatten();
+            if let Some(layout) = layout.filter(|layout| last.as_ref() != Some(layout)) {
+                if last.is_some() {
+                    changed(&app, &layout).await;
+                }
+                last = Some(layout);
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    });
+}
+
+async fn changed(app: &tauri::AppHandle, layout: &KeyboardLayout) {
+    tracing::info!(
+        "Keyboard layout changed to {}",
+        layout.name.as_deref().unwrap_or(&layout.id)
+    );
+    let handle = app.clone();
+    let shortcuts = tauri::async_runtime::spawn_blocking(move || {
+        if cfg!(target_os = "linux") {
+            shortcuts::rebind_all(&handle);
+            quick_actions::rebind_all(&handle);
+        }
+        shortcuts::describe_bound(&handle)
+    })
+    .await
+    .unwrap_or_default();
+    let _ = app.emit(
+        "keyboard-layout-changed",
+        LayoutChangedPayload {
+            layout: layout.clone(),
+            shortcuts,
+        },
+    );
+}
+
+#[tauri::command]
+pub async fn get_keyboard_layout(app: tauri::AppHandle) -> Option<KeyboardLayout> {
+    tauri::async_runtime::spawn_blocking(move || current(&app))
+        .await
+        .ok()
+        .flatten()
+}
@@ -0,0 +1,221 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::{Manager, State};
+
+use crate::db::{self, Database};
+use crate::error::AppError;
+use crate::usage::UsageRange;
+
+/// Spans older than this are dropped as new ones are recorded.
+const KEEP_MS: i64 = 30 * 24 * 60 * 60 * 1000;
+
+/// A stage of answering, timed from where it starts: the hotkey for the first two and
+/// the start of the request for the rest. Also the order they are reported in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LatencyStage {
+    /// The selection, clipboard and app in front have been read.
+    ContextCapture,
+    /// The overlay is up.
+    Summon,
+    /// The prompt has been filtered, fitted and had its images encoded, and is sent.
+    Prepare,
+    /// The provider has answered with response headers.
+    Response,
+    FirstToken,
+    /// The whole answer, tool calls included.
+    Completion,
+}
+
+impl LatencyStage {
+    const ALL: [LatencyStage; 6] = [
+        Self::ContextCapture,
+        Self::Summon,
+        Self::Prepare,
+        Self::Response,
+        Self::FirstToken,
+        Self::Completion,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::ContextCapture => "contextCapture",
+            Self::Summon => "summon",
+            Self::Prepare => "prepare",
+            Self::Response => "response",
+            Self::FirstToken => "firstToken",
+            Self::Completion => "completion",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|stage| stage.name() == name)
+    }
+}
+
+fn millis(duration: Duration) -> i64 {
+    duration.as_millis().try_into().unwrap_or(i64::MAX)
+}
+
+/// Store how long `stage` took. Failing to is only logged.
+pub fn record(
+    app: &tauri::AppHandle,
+    request_id: Option<&str>,
+    stage: LatencyStage,
+    provider: Option<&str>,
+    duration: Duration,
+) {
+    let Some(db) = app.try_state::<Database>() else {
+        return;
+    };
+    let now = db::now_ms();
+    let inserted = db.with(|conn| {
+        conn.execute(
+            "INSERT INTO latency_spans (request_id, stage, provider, duration_ms, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![request_id, stage.name(), provider, millis(duration), now],
+        )?;
+        conn.execute(
+            "DELETE FROM latency_spans WHERE created_at < ?1",
+            [now - KEEP_MS],
+        )
+    });
+    if let Err(error) = inserted {
+        tracing::error!("Failed to record latency: {error}");
+    }
+}
+
+/// Times one request to a provider, recording each stage the first time it is reached.
+pub struct RequestTimer {
+    request_id: String,
+    provider: String,
+    started: Instant,
+    responded: bool,
+    first_token: bool,
+}
+
+impl RequestTimer {
+    pub fn start(request_id: &str, provider: &str) -> Self {
+        Self {
+            request_id: request_id.to_string(),
+            provider: provider.to_string(),
+            started: Instant::now(),
+            responded: false,
+            first_token: false,
+        }
+    }
+
+    fn record(&self, app: &tauri::AppHandle, stage: LatencyStage) {
+        record(
+            app,
+            Some(&self.request_id),
+            stage,
+            Some(&self.provider),
+            self.started.elapsed(),
+        );
+    }
+
+    /// Leave time spent waiting on the user, such as for a confirmation, out of the stages
+    /// still to come.
+    pub fn exclude(&mut self, waited: Duration) {
+        self.started += waited;
+    }
+
+    pub fn prepared(&self, app: &tauri::AppHandle) {
+        self.record(app, LatencyStage::Prepare);
+    }
+
+    pub fn responded(&mut self, app: &tauri::AppHandle) {
+        if !std::mem::replace(&mut self.responded, true) {
+            self.record(app, LatencyStage::Response);
+        }
+    }
+
+    pub fn token(&mut self, app: &tauri::AppHandle) {
+        if !std::mem::replace(&mut self.first_token, true) {
+            self.record(app, LatencyStage::FirstToken);
+        }
+    }
+
+    pub fn finished(&self, app: &tauri::AppHandle) {
+        self.record(app, LatencyStage::Completion);
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StageLatency {
+    pub stage: LatencyStage,
+    /// `None` for the stage across every provider, and for the stages before a request.
+    pub provider: Option<String>,
+    pub count: usize,
+    pub p50_ms: i64,
+    pub p95_ms: i64,
+    pub max_ms: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PerformanceReport {
+    /// Spans counted are from this time on, in milliseconds since the epoch.
+    pub since: i64,
+    pub stages: Vec<StageLatency>,
+}
+
+/// The nearest-rank percentile of sorted `durations`.
+fn percentile(durations: &[i64], percent: usize) -> i64 {
+    let rank = ((durations.len() * percent + 99) / 100).max(1);
+    durations[rank - 1]
+}
+
+fn report(conn: &Connection, since: i64) -> rusqlite::Result<PerformanceReport> {
+    let mut stmt = conn
+        .prepare("SELECT stage, provider, duration_ms FROM latency_spans WHERE created_at >= ?1")?;
+    let rows = stmt.query_map([since], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, Option<String>>(1)?,
+            row.get::<_, i64>(2)?,
+        ))
+    })?;
+    let mut groups: BTreeMap<(LatencyStage, Option<String>), Vec<i64>> = BTreeMap::new();
+    for row in rows {
+        let (stage, provider, duration) = row?;
+        let Some(stage) = LatencyStage::from_name(&stage) else {
+            continue;
+        };
+        if provider.is_some() {
+            groups.entry((stage, None)).or_default().push(duration);
+        }
+        groups.entry((stage, provider)).or_default().push(duration);
+    }
+    let stages = groups
+        .into_iter()
+        .map(|((stage, provider), mut durations)| {
+            durations.sort_unstable();
+            StageLatency {
+                stage,
+                provider,
+                count: durations.len(),
+                p50_ms: percentile(&durations, 50),
+                p95_ms: percentile(&durations, 95),
+                max_ms: durations[durations.len() - 1],
+            }
+        })
+        .collect();
+    Ok(PerformanceReport { since, stages })
+}
+
+/// Median and 95th percentile latency of each stage from the hotkey to the last token,
+/// overall and per provider, within `range` (the last week by default).
+#[tauri::command]
+pub async fn get_performance_report(
+    db: State<'_, Database>,
+    range: Option<UsageRange>,
+) -> Result<PerformanceReport, AppError> {
+    let since = range.unwrap_or(UsageRange::Week).since();
+    db.with(|conn| report(conn, since)).map_err(AppError::from)
+}
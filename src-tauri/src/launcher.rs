@@ -0,0 +1,654 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use serde::Serialize;
+use tauri::Manager;
+use tauri_plugin_opener::OpenerExt;
+
+use crate::error::AppError;
+use crate::settings::{self, LauncherSettings};
+
+/// The index is used as is for this long, then rebuilt in the background.
+const REFRESH_AFTER: Duration = Duration::from_secs(5 * 60);
+const DEFAULT_LIMIT: usize = 20;
+/// Recently used files kept in the index, newest first.
+const MAX_RECENT_FILES: usize = 200;
+/// How deep application folders are searched.
+const MAX_DEPTH: usize = 4;
+
+/// Also the order items of equal score are listed in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LaunchableKind {
+    Application,
+    File,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LaunchableItem {
+    pub kind: LaunchableKind,
+    /// What to pass to `launch`.
+    pub id: String,
+    pub name: String,
+    /// An application's description or a file's folder.
+    pub subtitle: Option<String>,
+    pub path: PathBuf,
+    /// When a recent file was last used.
+    pub used_at: Option<i64>,
+    pub score: i64,
+    /// Character positions in `name` that matched, for highlighting.
+    pub matches: Vec<usize>,
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    kind: LaunchableKind,
+    name: String,
+    subtitle: Option<String>,
+    path: PathBuf,
+    used_at: Option<i64>,
+    /// Matched as well as the name, at half weight.
+    keywords: String,
+    /// The command line from a desktop entry; everything else is opened with the system's
+    /// default handler.
+    command: Option<Vec<String>>,
+}
+
+impl Entry {
+    fn new(kind: LaunchableKind, name: impl Into<String>, path: PathBuf) -> Self {
+        Self {
+            kind,
+            name: name.into(),
+            subtitle: None,
+            path,
+            used_at: None,
+            keywords: String::new(),
+            command: None,
+        }
+    }
+
+    fn id(&self) -> String {
+        let prefix = match self.kind {
+            LaunchableKind::Application => "app",
+            LaunchableKind::File => "file",
+        };
+        format!("{prefix}:{}", self.path.display())
+    }
+
+    fn to_item(&self, score: i64, matches: Vec<usize>) -> LaunchableItem {
+        LaunchableItem {
+            kind: self.kind,
+            id: self.id(),
+            name: self.name.clone(),
+            subtitle: self.subtitle.clone(),
+            path: self.path.clone(),
+            used_at: self.used_at,
+            score,
+            matches,
+        }
+    }
+}
+
+struct Index {
+    entries: Arc<Vec<Entry>>,
+    built_at: Instant,
+}
+
+/// Installed applications and recently used files, gathered when first searched and
+/// rebuilt once they get old.
+#[derive(Default)]
+pub struct LauncherState {
+    index: Mutex<Option<Index>>,
+    rebuilding: AtomicBool,
+}
+
+fn modified_ms(path: &Path) -> Option<i64> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    let since = modified.duration_since(UNIX_EPOCH).ok()?;
+    i64::try_from(since.as_millis()).ok()
+}
+
+/// Call `visit` for every path under `dir`, down to `MAX_DEPTH`. Directories it returns
+/// `true` for are taken as items, like `.app` bundles, and not searched.
+fn walk(dir: &Path, depth: usize, visit: &mut impl FnMut(&Path) -> bool) {
+    let Ok(read) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in read.flatten() {
+        let path = entry.path();
+        if visit(&path) || depth >= MAX_DEPTH {
+            continue;
+        }
+        if entry.file_type().is_ok_and(|kind| kind.is_dir()) {
+            walk(&path, depth + 1, visit);
+        }
+    }
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::collections::HashSet;
+    use std::env;
+    use std::path::{Path, PathBuf};
+
+    use chrono::DateTime;
+    use quick_xml::events::Event;
+    use quick_xml::Reader;
+
+    use super::{walk, Entry, LaunchableKind};
+
+    fn data_home() -> Option<PathBuf> {
+        env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| dirs::home_dir().map(|home| home.join(".local/share")))
+    }
+
+    /// The `applications` folders of the XDG data dirs, most important first.
+    pub fn application_dirs() -> Vec<PathBuf> {
+        let data_dirs = env::var("XDG_DATA_DIRS")
+            .ok()
+            .filter(|dirs| !dirs.is_empty())
+            .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+        data_home()
+            .into_iter()
+            .chain(env::split_paths(&data_dirs))
+            .chain([PathBuf::from("/var/lib/flatpak/exports/share")])
+            .chain(data_home().map(|home| home.join("flatpak/exports/share")))
+            .map(|dir| dir.join("applications"))
+            .collect()
+    }
+
+    /// `Exec` split into arguments, with field codes for files and URLs dropped.
+    fn command(exec: &str, name: &str, path: &Path) -> Vec<String> {
+        let mut args = Vec::new();
+        let mut current = String::new();
+        let mut quoted = false;
+        let mut started = false;
+        let mut chars = exec.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => {
+                    quoted = !quoted;
+                    started = true;
+                }
+                '\\' if quoted => current.extend(chars.next()),
+                c if c.is_whitespace() && !quoted => {
+                    if started {
+                        args.push(std::mem::take(&mut current));
+                        started = false;
+                    }
+                }
+                c => {
+                    current.push(c);
+                    started = true;
+                }
+            }
+        }
+        if started {
+            args.push(current);
+        }
+        args.into_iter()
+            .filter_map(|arg| match arg.as_str() {
+                "%f" | "%F" | "%u" | "%U" | "%d" | "%D" | "%n" | "%N" | "%v" | "%m" | "%i" => None,
+                "%c" => Some(name.to_string()),
+                "%k" => Some(path.to_string_lossy().into_owned()),
+                _ => Some(arg.replace("%%", "%")),
+            })
+            .collect()
+    }
+
+    /// An application from a desktop entry, unless it is hidden, runs in a terminal or
+    /// isn't an application at all.
+    fn desktop_entry(path: &Path) -> Option<Entry> {
+        let text = std::fs::read_to_string(path).ok()?;
+        let mut in_entry = false;
+        let (mut name, mut generic, mut comment, mut keywords, mut exec) =
+            (None, None, None, String::new(), None);
+        for line in text.lines().map(str::trim) {
+            if line.starts_with('[') {
+                in_entry = line == "[Desktop Entry]";
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=').filter(|_| in_entry) else {
+                continue;
+            };
+            let value = value.trim().to_string();
+            match key.trim() {
+                "Type" if value != "Application" => return None,
+                "NoDisplay" | "Hidden" | "Terminal" if value == "true" => return None,
+                "Name" => name = Some(value),
+                "GenericName" => generic = Some(value),
+                "Comment" => comment = Some(value),
+                "Keywords" => keywords = value.replace(';', " "),
+                "Exec" => exec = Some(value),
+                _ => {}
+            }
+        }
+        let name = name?;
+        let command = command(&exec?, &name, path);
+        if command.is_empty() {
+            return None;
+        }
+        let mut entry = Entry::new(LaunchableKind::Application, &name, path.to_path_buf());
+        entry.command = Some(command);
+        entry.keywords = format!("{} {keywords}", generic.as_deref().unwrap_or_default());
+        entry.subtitle = generic.or(comment);
+        Some(entry)
+    }
+
+    /// Desktop entries in `dirs`. The same id in a later folder is shadowed, as the XDG
+    /// spec has it.
+    pub fn applications(dirs: &[PathBuf]) -> Vec<Entry> {
+        let mut seen = HashSet::new();
+        let mut entries = Vec::new();
+        for dir in dirs {
+            walk(dir, 0, &mut |path| {
+                if path.extension().is_some_and(|ext| ext == "desktop") {
+                    let id = path.strip_prefix(dir).unwrap_or(path).to_path_buf();
+                    if seen.insert(id) {
+                        entries.extend(desktop_entry(path));
+                    }
+                }
+                false
+            });
+        }
+        entries
+    }
+
+    /// Files in `recently-used.xbel`, with when each was last touched.
+    pub fn recent_files() -> Vec<(PathBuf, Option<i64>)> {
+        let Some(path) = data_home().map(|home| home.join("recently-used.xbel")) else {
+            return Vec::new();
+        };
+        let Ok(mut reader) = Reader::from_file(&path) else {
+            return Vec::new();
+        };
+        let mut buffer = Vec::new();
+        let mut files = Vec::new();
+        loop {
+            match reader.read_event_into(&mut buffer) {
+                Ok(Event::Start(e) | Event::Empty(e)) if e.local_name().as_ref() == b"bookmark" => {
+                    let attr = |key: &[u8]| {
+                        e.try_get_attribute(key)
+                            .ok()
+                            .flatten()
+                            .and_then(|attr| attr.unescape_value().ok())
+                            .map(|value| value.into_owned())
+                    };
+                    let file = attr(b"href")
+                        .and_then(|href| tauri::Url::parse(&href).ok())
+                        .filter(|url| url.scheme() == "file")
+                        .and_then(|url| url.to_file_path().ok());
+                    let used_at = attr(b"modified")
+                        .or_else(|| attr(b"visited"))
+                        .and_then(|time| DateTime::parse_from_rfc3339(&time).ok())
+                        .map(|time| time.timestamp_millis());
+                    files.extend(file.map(|file| (file, used_at)));
+                }
+                Ok(Event::Eof) | Err(_) => break,
+                _ => {}
+            }
+            buffer.clear();
+        }
+        files
+    }
+
+    pub fn subtitle(path: &Path) -> Option<String> {
+        path.parent().map(|dir| dir.display().to_string())
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::path::{Path, PathBuf};
+
+    use super::{file_stem, modified_ms, walk, Entry, LaunchableKind};
+
+    pub fn application_dirs() -> Vec<PathBuf> {
+        ["/Applications", "/System/Applications"]
+            .into_iter()
+            .map(PathBuf::from)
+            .chain(dirs::home_dir().map(|home| home.join("Applications")))
+            .collect()
+    }
+
+    /// The `.app` bundles in `dirs` and the folders under them.
+    pub fn applications(dirs: &[PathBuf]) -> Vec<Entry> {
+        let mut entries = Vec::new();
+        for dir in dirs {
+            walk(dir, 0, &mut |path| {
+                let bundle = path.extension().is_some_and(|ext| ext == "app");
+                if bundle {
+                    entries.push(Entry::new(
+                        LaunchableKind::Application,
+                        file_stem(path),
+                        path.to_path_buf(),
+                    ));
+                }
+                bundle
+            });
+        }
+        entries
+    }
+
+    /// macOS keeps its recent items in a private format, so this is the files changed
+    /// most recently at the top of Desktop, Documents and Downloads instead.
+    pub fn recent_files() -> Vec<(PathBuf, Option<i64>)> {
+        let folders = [
+            dirs::desktop_dir(),
+            dirs::document_dir(),
+            dirs::download_dir(),
+        ];
+        folders
+            .into_iter()
+            .flatten()
+            .filter_map(|dir| std::fs::read_dir(dir).ok())
+            .flat_map(|read| read.flatten())
+            .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_file()))
+            .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+            .map(|entry| {
+                let path = entry.path();
+                let used_at = modified_ms(&path);
+                (path, used_at)
+            })
+            .collect()
+    }
+
+    pub fn subtitle(path: &Path) -> Option<String> {
+        path.parent().map(|dir| dir.display().to_string())
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::path::{Path, PathBuf};
+
+    use super::{file_stem, modified_ms, walk, Entry, LaunchableKind};
+
+    const START_MENU: &str = r"Microsoft\Windows\Start Menu\Programs";
+
+    pub fn application_dirs() -> Vec<PathBuf> {
+        [
+            dirs::data_dir(),
+            std::env::var_os("ProgramData").map(PathBuf::from),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|dir| dir.join(START_MENU))
+        .collect()
+    }
+
+    /// The Start Menu shortcuts in `dirs`, leaving out uninstallers.
+    pub fn applications(dirs: &[PathBuf]) -> Vec<Entry> {
+        let mut entries = Vec::new();
+        for dir in dirs {
+            walk(dir, 0, &mut |path| {
+                let shortcut = path.extension().is_some_and(|ext| {
+                    ext.eq_ignore_ascii_case("lnk") || ext.eq_ignore_ascii_case("url")
+                });
+                let name = file_stem(path);
+                if shortcut && !name.to_lowercase().contains("uninstall") {
+                    let mut entry =
+                        Entry::new(LaunchableKind::Application, name, path.to_path_buf());
+                    entry.subtitle = path
+                        .parent()
+                        .and_then(|parent| parent.strip_prefix(dir).ok())
+                        .map(|folder| folder.display().to_string())
+                        .filter(|folder| !folder.is_empty());
+                    entries.push(entry);
+                }
+                false
+            });
+        }
+        entries
+    }
+
+    /// The shortcuts in the Recent folder, each named after the file it opens.
+    pub fn recent_files() -> Vec<(PathBuf, Option<i64>)> {
+        let Some(dir) = dirs::data_dir().map(|dir| dir.join(r"Microsoft\Windows\Recent")) else {
+            return Vec::new();
+        };
+        let Ok(read) = std::fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        read.flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("lnk"))
+            })
+            .map(|path| {
+                let used_at = modified_ms(&path);
+                (path, used_at)
+            })
+            .collect()
+    }
+
+    /// Recent shortcuts don't say where their file is without resolving them.
+    pub fn subtitle(_path: &Path) -> Option<String> {
+        None
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+mod platform {
+    use std::path::{Path, PathBuf};
+
+    use super::Entry;
+
+    pub fn application_dirs() -> Vec<PathBuf> {
+        Vec::new()
+    }
+
+    pub fn applications(_dirs: &[PathBuf]) -> Vec<Entry> {
+        Vec::new()
+    }
+
+    pub fn recent_files() -> Vec<(PathBuf, Option<i64>)> {
+        Vec::new()
+    }
+
+    pub fn subtitle(_path: &Path) -> Option<String> {
+        None
+    }
+}
+
+fn build(settings: &LauncherSettings) -> Vec<Entry> {
+    let started = Instant::now();
+    let mut dirs = platform::application_dirs();
+    dirs.extend(settings.folders.iter().cloned());
+    let mut entries = platform::applications(&dirs);
+    entries.sort_by_cached_key(|entry| entry.name.to_lowercase());
+    entries.dedup_by(|a, b| a.name == b.name && a.command == b.command);
+    let applications = entries.len();
+
+    if settings.recent_files {
+        let mut recent: Vec<_> = platform::recent_files()
+            .into_iter()
+            .filter(|(path, _)| path.exists())
+            .map(|(path, used_at)| {
+                let used_at = used_at.or_else(|| modified_ms(&path));
+                (path, used_at)
+            })
+            .collect();
+        recent.sort_by_key(|(_, used_at)| std::cmp::Reverse(*used_at));
+        recent.truncate(MAX_RECENT_FILES);
+        entries.extend(recent.into_iter().map(|(path, used_at)| {
+            let mut entry = Entry::new(LaunchableKind::File, file_stem_with_extension(&path), path);
+            entry.subtitle = platform::subtitle(&entry.path);
+            entry.keywords = entry.subtitle.clone().unwrap_or_default();
+            entry.used_at = used_at;
+            entry
+        }));
+    }
+    tracing::debug!(
+        "Indexed {applications} applications and {} recent files in {:?}",
+        entries.len() - applications,
+        started.elapsed()
+    );
+    entries
+}
+
+/// A file's name as shown: with its extension, except for Windows shortcuts.
+fn file_stem_with_extension(path: &Path) -> String {
+    match path.extension() {
+        Some(ext) if ext.eq_ignore_ascii_case("lnk") => file_stem(path),
+        _ => path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+    }
+}
+
+/// Rebuild the index in the background, unless that is already happening.
+fn rebuild(app: &tauri::AppHandle) {
+    let state = app.state::<LauncherState>();
+    if state.rebuilding.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let entries = build(&settings::current(&app).launcher);
+        let state = app.state::<LauncherState>();
+        *state.index.lock().unwrap() = Some(Index {
+            entries: Arc::new(entries),
+            built_at: Instant::now(),
+        });
+        state.rebuilding.store(false, Ordering::SeqCst);
+    });
+}
+
+/// The index, built now if there isn't one yet and refreshed in the background when old.
+fn entries(app: &tauri::AppHandle) -> Arc<Vec<Entry>> {
+    let state = app.state::<LauncherState>();
+    let current = state.index.lock().unwrap().as_ref().map(|index| {
+        (
+            index.entries.clone(),
+            index.built_at.elapsed() > REFRESH_AFTER,
+        )
+    });
+    match current {
+        Some((entries, stale)) => {
+            if stale {
+                rebuild(app);
+            }
+            entries
+        }
+        None => {
+            let entries = Arc::new(build(&settings::current(app).launcher));
+            *state.index.lock().unwrap() = Some(Index {
+                entries: entries.clone(),
+                built_at: Instant::now(),
+            });
+            entries
+        }
+    }
+}
+
+fn score(matcher: &SkimMatcherV2, entry: &Entry, pattern: &str) -> Option<(i64, Vec<usize>)> {
+    let name = matcher.fuzzy_indices(&entry.name, pattern);
+    let keywords = (!entry.keywords.trim().is_empty())
+        .then(|| matcher.fuzzy_match(&entry.keywords, pattern))
+        .flatten()
+        .map(|score| (score / 2, Vec::new()));
+    match (name, keywords) {
+        (Some(name), Some(keywords)) if keywords.0 > name.0 => Some(keywords),
+        (Some(name), _) => Some(name),
+        (None, keywords) => keywords,
+    }
+}
+
+/// Applications and recent files matching `input`, best match first. With no input it
+/// lists the most recently used files instead.
+fn search(entries: &[Entry], input: &str, limit: usize) -> Vec<LaunchableItem> {
+    let pattern = input.trim();
+    if pattern.is_empty() {
+        return entries
+            .iter()
+            .filter(|entry| entry.kind == LaunchableKind::File)
+            .take(limit)
+            .map(|entry| entry.to_item(0, Vec::new()))
+            .collect();
+    }
+    let matcher = SkimMatcherV2::default().smart_case();
+    let mut items: Vec<LaunchableItem> = entries
+        .iter()
+        .filter_map(|entry| {
+            let (score, matches) = score(&matcher, entry, pattern)?;
+            Some(entry.to_item(score, matches))
+        })
+        .collect();
+    // Stable, so recent files of equal score stay newest first.
+    items.sort_by(|a, b| b.score.cmp(&a.score).then(a.kind.cmp(&b.kind)));
+    items.truncate(limit);
+    items
+}
+
+/// Search installed applications (Start Menu shortcuts, `.app` bundles or XDG desktop
+/// entries) and recently used files, for using the overlay as a launcher.
+#[tauri::command]
+pub async fn search_launchables(
+    app: tauri::AppHandle,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<LaunchableItem>, AppError> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(200);
+    let items = tauri::async_runtime::spawn_blocking(move || {
+        let entries = entries(&app);
+        search(&entries, &query, limit)
+    })
+    .await?;
+    Ok(items)
+}
+
+/// Start the application or open the file `search_launchables` returned as `item_id`.
+#[tauri::command]
+pub fn launch(app: tauri::AppHandle, item_id: String) -> Result<(), AppError> {
+    let entries = app
+        .state::<LauncherState>()
+        .index
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|index| index.entries.clone())
+        .unwrap_or_default();
+    let entry = entries
+        .iter()
+        .find(|entry| entry.id() == item_id)
+        .ok_or_else(|| AppError::NotFound(format!("Nothing to launch as {item_id}")))?;
+    if !entry.path.exists() {
+        rebuild(&app);
+        return Err(AppError::NotFound(format!(
+            "{} is no longer there",
+            entry.path.display()
+        )));
+    }
+    tracing::info!("Launching {}", entry.path.display());
+    if let Some([program, args @ ..]) = entry.command.as_deref() {
+        let mut child = std::process::Command::new(program)
+            .args(args)
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn()
+            .map_err(|e| format!("Could not start {}: {e}", entry.name))?;
+        // Reap it when it exits, so it doesn't linger as a zombie.
+        std::thread::spawn(move || child.wait());
+        return Ok(());
+    }
+    app.opener()
+        .open_path(entry.path.to_string_lossy(), None::<&str>)
+        .map_err(|e| AppError::from(e.to_string()))
+}
@@ -0,0 +1,110 @@
+use tauri::{PhysicalPosition, WebviewWindow};
+
+#[cfg(all(target_os = "linux", feature = "layer-shell"))]
+mod platform {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use gtk::prelude::*;
+    use gtk_layer_shell::{Edge, KeyboardMode, Layer, LayerShell};
+    use tauri::{PhysicalPosition, WebviewWindow};
+
+    /// Set once the compositor has been found to support layer shell.
+    static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+    /// Run `f` with the window's GTK window on the main thread, the only one GTK allows.
+    fn on_main_thread(
+        window: &WebviewWindow,
+        f: impl FnOnce(&gtk::ApplicationWindow) + Send + 'static,
+    ) -> Result<(), String> {
+        let target = window.clone();
+        window
+            .run_on_main_thread(move || match target.gtk_window() {
+                Ok(gtk_window) => f(&gtk_window),
+                Err(error) => tracing::warn!("No GTK window for {}: {error}", target.label()),
+            })
+            .map_err(|e| e.to_string())
+    }
+
+    pub fn init(window: &WebviewWindow) -> Result<(), String> {
+        on_main_thread(window, |gtk_window| {
+            if !gtk_layer_shell::is_supported() || gtk_window.is_layer_window() {
+                return;
+            }
+            // A layer surface has to be set up before the window is realized, and Tauri
+            // realizes windows as it creates them.
+            let visible = gtk_window.is_visible();
+            if visible {
+                gtk_window.hide();
+            }
+            gtk_window.unrealize();
+            gtk_window.init_layer_shell();
+            gtk_window.set_namespace("aikeya-overlay");
+            gtk_window.set_layer(Layer::Overlay);
+            gtk_window.set_keyboard_mode(KeyboardMode::OnDemand);
+            gtk_window.set_anchor(Edge::Top, true);
+            gtk_window.set_anchor(Edge::Left, true);
+            if visible {
+                gtk_window.show();
+            }
+            ACTIVE.store(true, Ordering::SeqCst);
+            tracing::info!("Showing the overlay as a layer surface");
+        })
+    }
+
+    pub fn set_position(
+        window: &WebviewWindow,
+        position: PhysicalPosition<i32>,
+        scale_factor: f64,
+    ) -> Result<(), String> {
+        if !ACTIVE.load(Ordering::SeqCst) {
+            return window.set_position(position).map_err(|e| e.to_string());
+        }
+        on_main_thread(window, move |gtk_window| {
+            // GDK works in logical pixels, and a layer surface is placed by its margins
+            // from the edges of the output it is on.
+            let x = (position.x as f64 / scale_factor).round() as i32;
+            let y = (position.y as f64 / scale_factor).round() as i32;
+            let Some(output) = gtk_window.display().monitor_at_point(x, y) else {
+                return;
+            };
+            let area = output.geometry();
+            gtk_window.set_monitor(&output);
+            gtk_window.set_layer_shell_margin(Edge::Left, x - area.x());
+            gtk_window.set_layer_shell_margin(Edge::Top, y - area.y());
+        })
+    }
+}
+
+#[cfg(not(all(target_os = "linux", feature = "layer-shell")))]
+mod platform {
+    use tauri::{PhysicalPosition, WebviewWindow};
+
+    pub fn init(_window: &WebviewWindow) -> Result<(), String> {
+        Ok(())
+    }
+
+    pub fn set_position(
+        window: &WebviewWindow,
+        position: PhysicalPosition<i32>,
+        _scale_factor: f64,
+    ) -> Result<(), String> {
+        window.set_position(position).map_err(|e| e.to_string())
+    }
+}
+
+/// On a Wayland compositor with wlr-layer-shell, and with the `layer-shell` feature, make
+/// the overlay a layer surface above every window, which can be placed anywhere and takes
+/// the keyboard when clicked. Elsewhere, including X11, the overlay stays a normal window.
+pub fn init(window: &WebviewWindow) -> Result<(), String> {
+    platform::init(window)
+}
+
+/// Move the overlay to `position` in desktop coordinates, on a monitor with
+/// `scale_factor`. Wayland ignores positions for normal windows, but not for layer surfaces.
+pub fn set_position(
+    window: &WebviewWindow,
+    position: PhysicalPosition<i32>,
+    scale_factor: f64,
+) -> Result<(), String> {
+    platform::set_position(window, position, scale_factor)
+}
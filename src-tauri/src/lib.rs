@@ -1,50 +1,603 @@
-use tauri::Manager;
+mod accessibility;
+mod animation;
+mod audio;
+mod autostart;
+mod backdrop;
+mod backup;
+mod branches;
+mod calculator;
+mod capabilities;
+mod cli;
+mod clipboard;
+mod clipboard_history;
+mod code_blocks;
+mod context_bundle;
+mod crash;
+mod db;
+mod deep_link;
+mod dock;
+mod documents;
+mod drafts;
+mod drop_folder;
+mod error;
+mod exec;
+mod expander;
+mod export;
+mod extension_bridge;
+mod focus;
+mod guardrails;
+mod headless;
+mod history;
+mod http;
+mod idle;
+mod image_generation;
+mod imaging;
+mod import;
+mod index;
+mod insert;
+mod jobs;
+mod keyboard;
+mod keyboard_layout;
+mod latency;
+mod launcher;
+mod layer_shell;
+mod llm;
+mod local_api;
+mod locale;
+mod logging;
+mod mcp;
+mod migrations;
+mod models;
+mod modifier_taps;
+mod mouse_trigger;
+mod notifications;
+mod ocr;
+mod offline;
+mod ollama;
+mod overlay;
+mod palette;
+mod panel;
+mod patch;
+mod persist;
+mod personas;
+mod plugins;
+mod privacy;
+mod profiles;
+mod providers;
+mod quick_actions;
+mod readability;
+mod requests;
+mod scheduler;
+mod screenshot;
+mod search;
+mod secrets;
+mod selection;
+mod session;
+mod settings;
+mod shortcuts;
+mod startup;
+mod state;
+mod summaries;
+mod templates;
+mod theme;
+mod tokens;
+mod tools;
+mod tray;
+mod tts;
+mod updater;
+mod usage;
+mod whisper;
+mod window_layout;
+mod window_manager;
+mod window_state;
+mod workspaces;
+#[cfg(target_os = "linux")]
+mod x11;
 
-#[tauri::command]
-fn show_overlay(app: tauri::AppHandle) -> Result<(), String> {
-    if let Some(window) = app.get_webview_window("overlay") {
-        window.show().map_err(|e| e.to_string())?;
-        window.set_focus().map_err(|e| e.to_string())?;
+use tauri::{Emitter, Manager};
+
+use animation::AnimationState;
+use audio::AudioState;
+use calculator::ExchangeRates;
+use capabilities::CapabilityStore;
+use clipboard::ClipboardState;
+use db::Database;
+use deep_link::DeepLinkState;
+use dock::DockState;
+use drafts::DraftStore;
+use drop_folder::DropFolder;
+use error::AppError;
+use exec::PendingCommands;
+use expander::SnippetStore;
+use extension_bridge::ExtensionBridge;
+use focus::FocusTracker;
+use guardrails::PendingSends;
+use http::HttpClient;
+use idle::IdleState;
+use index::{IndexWatcher, Indexer};
+use jobs::JobQueue;
+use launcher::LauncherState;
+use local_api::ApiServer;
+use mcp::McpState;
+use models::ModelStore;
+use offline::NetworkMonitor;
+use overlay::OverlayState;
+use personas::PersonaStore;
+use plugins::PluginState;
+use profiles::ProfileStore;
+use providers::status::ProviderStatuses;
+use quick_actions::QuickActionStore;
+use requests::RequestRegistry;
+use scheduler::Scheduler;
+use screenshot::eyedropper::{ColorHistory, EyedropperState};
+use screenshot::picker::RegionPickerState;
+use selection::SelectionState;
+use session::{SessionEnd, SessionStore};
+use settings::SettingsStore;
+use shortcuts::ShortcutRegistry;
+use startup::StartupMetrics;
+use state::AppState;
+use summaries::Summarizer;
+use templates::TemplateStore;
+use theme::ThemeState;
+use tools::ToolPermissions;
+use tts::SpeechState;
+use updater::UpdaterState;
+use window_manager::{AppWindow, WindowMessages};
+use window_state::WindowStateStore;
+use workspaces::WorkspaceStore;
+
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SecondInstancePayload {
+    args: Vec<String>,
+    cwd: String,
+}
+
+/// A second launch hands its arguments to us and exits; bring the overlay up instead.
+/// A launch to open a link is left to the deep link handler, which decides what to show.
+fn on_second_instance(app: &tauri::AppHandle, args: Vec<String>, cwd: String) {
+    let opens_link = args.iter().skip(1).any(|arg| deep_link::is_deep_link(arg));
+    if !opens_link {
+        window_manager::spawn(app, |app| {
+            if let Err(error) = window_manager::show_overlay(app) {
+                tracing::error!("Failed to show overlay for second instance: {error}");
+            }
+        });
     }
-    Ok(())
+    let _ = app.emit("second-instance", SecondInstancePayload { args, cwd });
 }
 
+// Async like the window manager's commands, since showing may rebuild a closed overlay.
 #[tauri::command]
-fn hide_overlay(app: tauri::AppHandle) -> Result<(), String> {
-    if let Some(window) = app.get_webview_window("overlay") {
-        window.hide().map_err(|e| e.to_string())?;
-    }
-    Ok(())
+async fn show_overlay(app: tauri::AppHandle) -> Result<(), AppError> {
+    window_manager::show_overlay(&app)
 }
 
+/// Show the overlay without taking focus from the frontmost app, on macOS; elsewhere the
+/// same as `show_overlay`.
 #[tauri::command]
-fn toggle_overlay(app: tauri::AppHandle) -> Result<bool, String> {
-    if let Some(window) = app.get_webview_window("overlay") {
-        let visible = window.is_visible().map_err(|e| e.to_string())?;
-        if visible {
-            window.hide().map_err(|e| e.to_string())?;
-            Ok(false)
-        } else {
-            window.show().map_err(|e| e.to_string())?;
-            window.set_focus().map_err(|e| e.to_string())?;
-            Ok(true)
-        }
+async fn show_overlay_nonactivating(app: tauri::AppHandle) -> Result<(), AppError> {
+    window_manager::show_overlay_nonactivating(&app, None)
+}
+
+#[tauri::command]
+async fn hide_overlay(app: tauri::AppHandle) -> Result<(), AppError> {
+    window_manager::hide_overlay(&app)
+}
+
+#[tauri::command]
+async fn toggle_overlay(app: tauri::AppHandle) -> Result<bool, AppError> {
+    window_manager::toggle_overlay(&app)
+}
+
+/// Bring back an overlay whose webview was closed or, with `reload`, one that stopped
+/// responding. Returns whether the window was rebuilt.
+#[tauri::command]
+async fn ensure_overlay(app: tauri::AppHandle, reload: Option<bool>) -> Result<bool, AppError> {
+    window_manager::ensure(&app, AppWindow::Overlay, reload.unwrap_or(false))
+}
+
+/// Every command the frontend can invoke.
+fn commands() -> impl Fn(tauri::ipc::Invoke) -> bool + Send + Sync + 'static {
+    tauri::generate_handler![
+        show_overlay,
+        show_overlay_nonactivating,
+        animation::show_overlay_animated,
+        hide_overlay,
+        toggle_overlay,
+        ensure_overlay,
+        window_manager::show_window,
+        window_manager::hide_window,
+        window_manager::toggle_window,
+        window_manager::list_windows,
+        window_manager::send_to_window,
+        window_manager::take_window_messages,
+        state::get_app_state,
+        state::set_active_conversation,
+        state::set_session_model,
+        shortcuts::register_overlay_shortcut,
+        shortcuts::unregister_overlay_shortcut,
+        shortcuts::list_shortcuts,
+        shortcuts::register_shortcut,
+        shortcuts::unregister_shortcut,
+        overlay::set_overlay_click_through,
+        overlay::get_overlay_click_through,
+        overlay::pin_overlay,
+        backdrop::set_overlay_backdrop,
+        overlay::is_overlay_pinned,
+        overlay::set_overlay_dialog_open,
+        overlay::show_overlay_at_selection,
+        secrets::save_api_key,
+        secrets::get_api_key,
+        secrets::delete_api_key,
+        llm::chat_completion,
+        llm::abort_completion,
+        requests::cancel_request,
+        tokens::count_tokens,
+        usage::get_usage_stats,
+        logging::get_recent_logs,
+        logging::open_log_folder,
+        crash::get_last_crash_report,
+        crash::clear_crash_reports,
+        updater::check_for_updates,
+        updater::download_update,
+        updater::install_and_restart,
+        updater::defer_update,
+        updater::remind_me_later,
+        deep_link::take_pending_ask,
+        local_api::get_api_server_status,
+        local_api::get_api_token,
+        local_api::regenerate_api_token,
+        extension_bridge::approve_extension_pairing,
+        extension_bridge::reject_extension_pairing,
+        extension_bridge::list_extension_clients,
+        extension_bridge::revoke_extension_client,
+        extension_bridge::get_browser_context,
+        extension_bridge::send_to_extension,
+        extension_bridge::get_extension_bridge_status,
+        mcp::list_mcp_servers,
+        mcp::restart_mcp_server,
+        mcp::list_mcp_resources,
+        mcp::read_mcp_resource,
+        mcp::call_mcp_tool,
+        tools::list_builtin_tools,
+        tools::respond_tool_permission,
+        search::web_search,
+        scheduler::list_scheduled_jobs,
+        scheduler::create_scheduled_job,
+        scheduler::update_scheduled_job,
+        scheduler::set_scheduled_job_enabled,
+        scheduler::delete_scheduled_job,
+        scheduler::run_scheduled_job,
+        readability::fetch_page,
+        exec::approve_command,
+        exec::deny_command,
+        exec::list_command_runs,
+        exec::clear_command_runs,
+        providers::list_providers,
+        providers::list_provider_models,
+        providers::test_provider,
+        providers::select_model,
+        providers::status::get_provider_status,
+        ollama::ollama_list_models,
+        ollama::ollama_pull_model,
+        ollama::ollama_chat,
+        clipboard::read_clipboard,
+        clipboard::write_clipboard,
+        clipboard_history::list_clipboard_history,
+        clipboard_history::pin_clipboard_entry,
+        clipboard_history::clear_clipboard_history,
+        selection::get_selected_text,
+        screenshot::capture_screenshot,
+        screenshot::picker::pick_screen_region,
+        screenshot::picker::get_region_picker_background,
+        screenshot::picker::finish_region_pick,
+        ocr::ocr_image,
+        documents::parse_document,
+        index::index_paths,
+        index::reindex,
+        index::list_index_sources,
+        index::remove_index_source,
+        index::set_index_source_watched,
+        index::semantic_search,
+        history::create_conversation,
+        history::append_message,
+        history::list_conversations,
+        history::search_conversations,
+        history::search_history,
+        history::get_conversation,
+        history::delete_conversation,
+        export::export_conversation,
+        export::export_all,
+        backup::create_backup,
+        backup::restore_backup,
+        import::import_external_history,
+        image_generation::generate_image,
+        image_generation::list_generated_images,
+        quick_actions::list_quick_actions,
+        quick_actions::save_quick_action,
+        quick_actions::delete_quick_action,
+        palette::query_commands,
+        plugins::list_plugins,
+        plugins::reload_plugins,
+        plugins::set_plugin_enabled,
+        plugins::run_plugin_action,
+        capabilities::respond_capability_consent,
+        capabilities::list_capability_grants,
+        capabilities::set_capability_grant,
+        capabilities::get_capability_audit,
+        privacy::preview_redaction,
+        offline::get_network_status,
+        offline::set_offline_mode,
+        code_blocks::extract_code_blocks,
+        code_blocks::save_code_block,
+        patch::apply_unified_diff,
+        context_bundle::capture_context_bundle,
+        branches::edit_message,
+        branches::regenerate_message,
+        branches::switch_branch,
+        branches::list_branches,
+        personas::list_personas,
+        personas::save_persona,
+        personas::delete_persona,
+        personas::set_default_persona,
+        personas::set_conversation_persona,
+        personas::get_effective_persona,
+        shortcuts::describe_shortcut,
+        keyboard_layout::get_keyboard_layout,
+        dock::dock_overlay,
+        dock::undock_overlay,
+        dock::get_overlay_dock,
+        startup::get_startup_metrics,
+        drafts::save_draft,
+        drafts::get_draft,
+        locale::get_locale,
+        locale::set_locale,
+        locale::translate_text,
+        theme::get_system_theme,
+        session::set_scroll_position,
+        session::restore_session,
+        expander::list_snippets,
+        expander::save_snippet,
+        expander::delete_snippet,
+        jobs::list_jobs,
+        jobs::cancel_job,
+        models::list_local_models,
+        models::download_model,
+        models::verify_model,
+        models::delete_model,
+        export::share_conversation,
+        screenshot::eyedropper::pick_color,
+        screenshot::eyedropper::get_eyedropper_background,
+        screenshot::eyedropper::finish_color_pick,
+        screenshot::eyedropper::list_picked_colors,
+        screenshot::eyedropper::clear_picked_colors,
+        calculator::evaluate_expression,
+        launcher::search_launchables,
+        launcher::launch,
+        window_layout::arrange_foreground_window,
+        summaries::summarize_conversation,
+        latency::get_performance_report,
+        workspaces::list_workspaces,
+        workspaces::create_workspace,
+        workspaces::switch_workspace,
+        guardrails::confirm_send,
+        guardrails::cancel_send,
+        accessibility::announce_to_screen_reader,
+        accessibility::set_reduced_motion,
+        accessibility::return_focus,
+        settings::get_settings,
+        settings::update_settings,
+        autostart::get_autostart,
+        autostart::set_autostart,
+        audio::start_recording,
+        audio::stop_recording,
+        whisper::list_whisper_models,
+        whisper::download_whisper_model,
+        whisper::delete_whisper_model,
+        whisper::transcribe_audio,
+        tts::speak,
+        tts::stop_speaking,
+        insert::insert_text_into_active_app,
+        focus::get_previous_app_context,
+        focus::get_focus_state,
+        idle::get_idle_state,
+        profiles::list_app_profiles,
+        profiles::save_app_profile,
+        profiles::delete_app_profile,
+        profiles::get_active_app_profile,
+        templates::list_templates,
+        templates::save_template,
+        templates::delete_template,
+        templates::render_template
+    ]
+}
+
+/// Report a startup failure where the user can find it, then quit.
+fn exit_with_error(error: &tauri::Error) -> ! {
+    // Setup may have failed before logging was up.
+    if tracing::dispatcher::has_been_set() {
+        tracing::error!("Failed to start: {error}");
     } else {
-        Err("Overlay window not found".to_string())
+        eprintln!("Failed to start: {error}");
     }
+    std::process::exit(1)
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    // Before single-instance, which would hand the command to the running app and exit.
+    if let Some(code) = cli::run() {
+        std::process::exit(code);
+    }
+    let mut context = tauri::generate_context!();
+    let headless = headless::active();
+    if headless {
+        if let Err(error) = headless::prepare(&mut context) {
+            eprintln!("aikeya: {error}");
+            std::process::exit(1);
+        }
+    }
     tauri::Builder::default()
+        // Must be registered first so a second launch exits before anything else starts.
+        .plugin(tauri_plugin_single_instance::init(on_second_instance))
+        // After single-instance, which forwards a second launch's link to it.
+        .plugin(tauri_plugin_deep_link::init())
         .plugin(tauri_plugin_global_shortcut::Builder::new().build())
+        .plugin(tauri_plugin_autostart::init(
+            tauri_plugin_autostart::MacosLauncher::LaunchAgent,
+            Some(vec![autostart::HIDDEN_FLAG]),
+        ))
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_opener::init())
-        .invoke_handler(tauri::generate_handler![
-            show_overlay,
-            hide_overlay,
-            toggle_overlay
-        ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .plugin(tauri_plugin_notification::init())
+        .plugin(tauri_plugin_updater::Builder::new().build())
+        .manage(AnimationState::default())
+        .manage(ApiServer::default())
+        .manage(AppState::default())
+        .manage(AudioState::default())
+        .manage(ClipboardState::default())
+        .manage(DeepLinkState::default())
+        .manage(DockState::default())
+        .manage(ExtensionBridge::default())
+        .manage(EyedropperState::default())
+        .manage(FocusTracker::default())
+        .manage(HttpClient::default())
+        .manage(IdleState::default())
+        .manage(Indexer::default())
+        .manage(LauncherState::default())
+        .manage(McpState::default())
+        .manage(NetworkMonitor::default())
+        .manage(OverlayState::default())
+        .manage(PendingCommands::default())
+        .manage(PendingSends::default())
+        .manage(PluginState::default())
+        .manage(ProviderStatuses::default())
+        .manage(RegionPickerState::default())
+        .manage(RequestRegistry::default())
+        .manage(Scheduler::default())
+        .manage(SelectionState::default())
+        .manage(SpeechState::default())
+        .manage(StartupMetrics::default())
+        .manage(Summarizer::default())
+        .manage(ThemeState::default())
+        .manage(ToolPermissions::default())
+        .manage(UpdaterState::default())
+        .manage(WindowMessages::default())
+        .setup(move |app| {
+            #[cfg(target_os = "macos")]
+            if headless {
+                app.set_activation_policy(tauri::ActivationPolicy::Accessory);
+            }
+            let handle = app.handle();
+            app.manage(logging::init(handle)?);
+            crash::install(handle)?;
+            startup::mark(handle, "logging");
+            app.manage(WorkspaceStore::load(handle)?);
+            app.manage(SettingsStore::load(handle)?);
+            app.manage(CapabilityStore::load(handle)?);
+            app.manage(WindowStateStore::load(handle)?);
+            locale::init(handle);
+            if !headless {
+                // The overlay isn't built until it's first summoned or prewarmed.
+                window_state::restore_all(handle);
+                backdrop::init(handle);
+                theme::init(handle);
+                overlay::init(handle);
+                startup::mark(handle, "windows");
+            }
+            app.manage(ShortcutRegistry::load(handle)?);
+            app.manage(QuickActionStore::load(handle)?);
+            if !headless {
+                shortcuts::restore(handle);
+                modifier_taps::start(handle);
+                mouse_trigger::start(handle);
+                quick_actions::restore(handle);
+                keyboard_layout::start(handle);
+                startup::mark(handle, "shortcuts");
+            }
+            app.manage(ProfileStore::load(handle)?);
+            app.manage(TemplateStore::load(handle)?);
+            app.manage(PersonaStore::load(handle)?);
+            app.manage(DraftStore::load(handle)?);
+            drafts::start(handle);
+            app.manage(SnippetStore::load(handle)?);
+            if !headless {
+                expander::init(handle);
+            }
+            app.manage(ColorHistory::load(handle)?);
+            app.manage(ExchangeRates::load(handle)?);
+            app.manage(SessionStore::load(handle)?);
+            session::start(handle);
+            let loader = handle.clone();
+            tauri::async_runtime::spawn_blocking(move || {
+                startup::background(&loader, "plugins", || {
+                    if let Err(error) = plugins::load(&loader) {
+                        tracing::warn!("Plugins were not loaded: {error}");
+                    }
+                })
+            });
+            if !headless {
+                tray::init(handle)?;
+            }
+            startup::mark(handle, "stores");
+            // The main window starts hidden so a login launch can stay in the tray.
+            if !headless && !autostart::launched_hidden() {
+                window_manager::show(handle, AppWindow::Main)?;
+            }
+            app.manage(Database::open(handle)?);
+            startup::mark(handle, "database");
+            if !headless {
+                clipboard_history::start_watcher(handle);
+                idle::start(handle);
+            }
+            offline::start(handle);
+            scheduler::start(handle);
+            app.manage(DropFolder::start(handle));
+            app.manage(ModelStore::load(handle)?);
+            app.manage(JobQueue::load(handle)?);
+            jobs::resume(handle);
+            drop_folder::init(handle);
+            app.manage(IndexWatcher::start(handle)?);
+            index::sync_watches(handle);
+            if !headless {
+                updater::check_on_startup(handle);
+                deep_link::init(handle);
+            }
+            local_api::init(handle);
+            extension_bridge::init(handle);
+            mcp::init(handle);
+            if headless {
+                tracing::info!("Running headless");
+            } else {
+                session::restore(handle);
+                startup::prewarm_overlay(handle);
+            }
+            startup::finish_setup(handle);
+            Ok(())
+        })
+        .on_window_event(|window, event| {
+            window_state::on_window_event(window, event);
+            overlay::on_window_event(window, event);
+            window_manager::on_window_event(window, event);
+        })
+        .invoke_handler({
+            let commands = commands();
+            move |invoke| {
+                crash::record_command(invoke.message.command());
+                commands(invoke)
+            }
+        })
+        .build(context)
+        .unwrap_or_else(|error| exit_with_error(&error))
+        .run(|app, event| {
+            if let tauri::RunEvent::Exit = event {
+                let _ = app.state::<WindowStateStore>().flush();
+                let _ = app.state::<DraftStore>().flush();
+                session::end(app, SessionEnd::Quit);
+                dock::forget(app);
+                updater::install_deferred(app);
+            }
+        });
 }
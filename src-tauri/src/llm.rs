@@ -0,0 +1,598 @@
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::Emitter;
+
+use crate::error::AppError;
+use crate::imaging::{self, EncodedImage, ImageLimits, ImageSource};
+use crate::latency::RequestTimer;
+use crate::offline::{self, Route};
+use crate::personas::{self, Persona};
+use crate::privacy::Restorer;
+use crate::providers::params::{self, GenerationParams};
+use crate::providers::{self, ChatRequest, Provider, TokenStream};
+use crate::state::ActiveModel;
+use crate::usage::{self, TokenUsage};
+use crate::{
+    capabilities, guardrails, http, notifications, plugins, privacy, requests, settings, state,
+    summaries, tokens, tools, workspaces,
+};
+
+/// Which provider to call. `provider` doubles as the keychain name of its API key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderConfig {
+    pub provider: String,
+    pub model: String,
+    pub base_url: Option<String>,
+    /// Overrides the network request timeout for this call.
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: String,
+    pub content: String,
+    /// Images sent along with the text, for vision models.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<ImageSource>,
+}
+
+/// A tool the model may call, with a JSON Schema for its arguments.
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolSpec {
+    pub name: String,
+    pub description: String,
+    pub parameters: Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolCall {
+    pub id: String,
+    pub name: String,
+    pub arguments: Value,
+}
+
+#[derive(Debug, Clone)]
+pub struct ToolResult {
+    pub call_id: String,
+    pub content: String,
+    pub is_error: bool,
+}
+
+/// One round of tool use: what the model said and asked for, and what the tools returned.
+#[derive(Debug, Clone)]
+pub struct ToolTurn {
+    pub text: String,
+    pub calls: Vec<ToolCall>,
+    pub results: Vec<ToolResult>,
+}
+
+/// Part of a streamed tool call. Calls arrive in pieces, tied together by `index`.
+#[derive(Debug, Clone, Default)]
+pub struct ToolCallDelta {
+    pub index: usize,
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub arguments: String,
+}
+
+/// Put streamed tool call pieces back together, in the order the model made the calls.
+#[derive(Default)]
+struct ToolCallBuffer(Vec<ToolCallDelta>);
+
+impl ToolCallBuffer {
+    fn push(&mut self, delta: ToolCallDelta) {
+        let Some(call) = self.0.iter_mut().find(|call| call.index == delta.index) else {
+            self.0.push(delta);
+            return;
+        };
+        if delta.id.is_some() {
+            call.id = delta.id;
+        }
+        if delta.name.is_some() {
+            call.name = delta.name;
+        }
+        call.arguments.push_str(&delta.arguments);
+    }
+
+    fn finish(self) -> Vec<ToolCall> {
+        self.0
+            .into_iter()
+            .filter_map(|call| {
+                let arguments = match call.arguments.trim() {
+                    "" => Value::Object(Default::default()),
+                    raw => serde_json::from_str(raw).unwrap_or(Value::String(raw.to_string())),
+                };
+                Some(ToolCall {
+                    id: call.id.unwrap_or_else(requests::new_id),
+                    name: call.name?,
+                    arguments,
+                })
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ChatTokenPayload<'a> {
+    request_id: &'a str,
+    token: &'a str,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ChatDonePayload<'a> {
+    request_id: &'a str,
+    content: &'a str,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ChatErrorPayload<'a> {
+    request_id: &'a str,
+    message: &'a str,
+    /// The `AppError` code, e.g. `providerAuth` to offer setting a key.
+    code: &'static str,
+}
+
+/// Stop feeding tool results back after this many rounds, in case the model keeps asking.
+const MAX_TOOL_ROUNDS: usize = 8;
+
+/// Load every message's images and shrink them to what the provider takes, off the async
+/// runtime. Empty when no message has any.
+pub(crate) async fn encode_images(
+    app: &tauri::AppHandle,
+    limits: ImageLimits,
+    messages: &[ChatMessage],
+) -> Result<Vec<Vec<EncodedImage>>, AppError> {
+    if messages.iter().all(|message| message.images.is_empty()) {
+        return Ok(Vec::new());
+    }
+    for source in messages.iter().flat_map(|message| &message.images) {
+        if let ImageSource::Path { path } = source {
+            capabilities::require_path(app, path, "Attach an image").await?;
+        }
+    }
+    let app = app.clone();
+    let sources: Vec<Vec<ImageSource>> = messages
+        .iter()
+        .map(|message| message.images.clone())
+        .collect();
+    tauri::async_runtime::spawn_blocking(move || {
+        sources
+            .iter()
+            .map(|images| {
+                images
+                    .iter()
+                    .map(|source| imaging::encode_for_upload(&source.load(&app)?, limits))
+                    .collect::<Result<Vec<_>, String>>()
+            })
+            .collect::<Result<Vec<_>, String>>()
+    })
+    .await?
+    .map_err(|e| AppError::InvalidInput(format!("Could not attach an image: {e}")))
+}
+
+async fn stream_completion(
+    app: &tauri::AppHandle,
+    request_id: &str,
+    config: &ProviderConfig,
+    messages: &[ChatMessage],
+    params: &GenerationParams,
+) -> Result<String, AppError> {
+    let mut timer = RequestTimer::start(request_id, &config.provider);
+    let provider = providers::resolve(app, &config.provider, config.base_url.as_deref())?;
+    let api_key = providers::api_key(provider.as_ref())?;
+    let messages = plugins::on_prompt(app, messages).await;
+    workspaces::guard_prompt(app, &messages)?;
+    let (messages, masked) = privacy::filter(app, request_id, provider.info(), messages)?;
+    let messages = tokens::fit(app, request_id, &config.model, &messages);
+    let images = encode_images(app, provider.image_limits(), &messages).await?;
+    let tool_specs = tools::chat_tools(app);
+    let confirming = Instant::now();
+    guardrails::check(app, request_id, provider.info(), &messages, &images).await?;
+    timer.exclude(confirming.elapsed());
+    timer.prepared(app);
+    let mut turns: Vec<ToolTurn> = Vec::new();
+    let mut content = String::new();
+    let mut restorer = masked.restorer();
+    loop {
+        let chat = ChatRequest {
+            model: &config.model,
+            messages: &messages,
+            images: &images,
+            tools: &tool_specs,
+            turns: &turns,
+            params,
+        };
+        let (text, calls) = stream_round(
+            app,
+            request_id,
+            provider.as_ref(),
+            &chat,
+            api_key.as_deref(),
+            &mut restorer,
+            &mut timer,
+        )
+        .await?;
+        content.push_str(&text);
+        if calls.is_empty() || turns.len() >= MAX_TOOL_ROUNDS {
+            break;
+        }
+        let mut results = Vec::new();
+        for call in &calls {
+            results.push(tools::run(app, request_id, call).await);
+        }
+        turns.push(ToolTurn {
+            text,
+            calls,
+            results,
+        });
+    }
+    timer.finished(app);
+    Ok(plugins::on_response(app, masked.restore(&content)).await)
+}
+
+/// Stream one model response, returning its text and any tool calls it asked for. The
+/// text is returned as the model wrote it, placeholders and all, and emitted restored.
+async fn stream_round(
+    app: &tauri::AppHandle,
+    request_id: &str,
+    provider: &dyn Provider,
+    chat: &ChatRequest<'_>,
+    api_key: Option<&str>,
+    restorer: &mut Restorer<'_>,
+    timer: &mut RequestTimer,
+) -> Result<(String, Vec<ToolCall>), AppError> {
+    let info = provider.info();
+    if let Some(tokens) = provider.generate(app, chat) {
+        let content = stream_generated(app, request_id, tokens?, restorer, timer).await?;
+        usage::record_chat(
+            app,
+            request_id,
+            (&info.id, !info.requires_api_key),
+            chat.model,
+            chat.messages,
+            &content,
+            TokenUsage::default(),
+        );
+        return Ok((content, Vec::new()));
+    }
+    let request = provider.chat_request(&http::client(app)?, chat, api_key);
+    let response = http::send(app, &info.id, Some(request_id), request).await?;
+    let response = providers::error_for_status(info, response).await?;
+    timer.responded(app);
+
+    let mut stream = response.bytes_stream();
+    let mut lines = LineBuffer::default();
+    let mut content = String::new();
+    let mut calls = ToolCallBuffer::default();
+    let mut reported = TokenUsage::default();
+    'stream: while let Some(chunk) = stream.next().await {
+        lines.push(&chunk?);
+
+        while let Some(line) = lines.next_line() {
+            let Some(data) = line.trim().strip_prefix("data:") else {
+                continue;
+            };
+            let data = data.trim();
+            if data == "[DONE]" {
+                break 'stream;
+            }
+            let Ok(event) = serde_json::from_str::<Value>(data) else {
+                continue;
+            };
+            if let Some(token) = provider.stream_token(&event) {
+                timer.token(app);
+                content.push_str(&token);
+                let shown = restorer.push(&token);
+                if !shown.is_empty() {
+                    emit_token(app, request_id, &shown);
+                }
+            }
+            for delta in provider.stream_tool_calls(&event) {
+                calls.push(delta);
+            }
+            if let Some(usage) = provider.stream_usage(&event) {
+                reported.merge(usage);
+            }
+        }
+    }
+
+    let held = restorer.flush();
+    if !held.is_empty() {
+        emit_token(app, request_id, &held);
+    }
+    usage::record_chat(
+        app,
+        request_id,
+        (&info.id, !info.requires_api_key),
+        chat.model,
+        chat.messages,
+        &content,
+        reported,
+    );
+    Ok((content, calls.finish()))
+}
+
+/// Emit a response generated in-process as it arrives, returning its text.
+async fn stream_generated(
+    app: &tauri::AppHandle,
+    request_id: &str,
+    mut tokens: TokenStream,
+    restorer: &mut Restorer<'_>,
+    timer: &mut RequestTimer,
+) -> Result<String, AppError> {
+    let mut content = String::new();
+    while let Some(token) = tokens.recv().await {
+        let token = token?;
+        timer.token(app);
+        content.push_str(&token);
+        let shown = restorer.push(&token);
+        if !shown.is_empty() {
+            emit_token(app, request_id, &shown);
+        }
+    }
+    let held = restorer.flush();
+    if !held.is_empty() {
+        emit_token(app, request_id, &held);
+    }
+    Ok(content)
+}
+
+/// Splits a streamed response body into lines. Raw bytes are buffered because a
+/// multi-byte character can be split across network chunks.
+#[derive(Default)]
+pub(crate) struct LineBuffer(Vec<u8>);
+
+impl LineBuffer {
+    pub fn push(&mut self, chunk: &[u8]) {
+        self.0.extend_from_slice(chunk);
+    }
+
+    pub fn next_line(&mut self) -> Option<String> {
+        let newline = self.0.iter().position(|&b| b == b'\n')?;
+        let line: Vec<u8> = self.0.drain(..=newline).collect();
+        Some(String::from_utf8_lossy(&line).into_owned())
+    }
+}
+
+pub(crate) fn emit_token(app: &tauri::AppHandle, request_id: &str, token: &str) {
+    let _ = app.emit("chat-token", ChatTokenPayload { request_id, token });
+}
+
+/// Run a completion in the background under a fresh request id, emitting `chat-done` or
+/// `chat-error` when it settles. It fails once `timeout` has passed, and can be stopped
+/// with `cancel_request` or `abort_completion`. With `queue` it first waits out any time
+/// offline, described as `queue`, and the timeout starts once it is back online.
+pub(crate) fn spawn_completion<F, Fut, E>(
+    app: &tauri::AppHandle,
+    timeout: Duration,
+    queue: Option<String>,
+    run: F,
+) -> String
+where
+    F: FnOnce(tauri::AppHandle, String) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<String, E>> + Send + 'static,
+    E: Into<AppError>,
+{
+    let request_id = requests::new_id();
+    let task_app = app.clone();
+    let task_id = request_id.clone();
+    requests::spawn(app, &request_id, async move {
+        let online = match queue {
+            Some(what) => offline::wait_until_online(task_app.clone(), task_id.clone(), what).await,
+            None => Ok(()),
+        };
+        let result = match online {
+            Ok(()) => {
+                let completion = run(task_app.clone(), task_id.clone());
+                requests::with_timeout(&task_app, &task_id, timeout, completion).await
+            }
+            Err(error) => Err(error),
+        };
+        match result {
+            Ok(content) => {
+                let _ = task_app.emit(
+                    "chat-done",
+                    ChatDonePayload {
+                        request_id: &task_id,
+                        content: &content,
+                    },
+                );
+                notifications::completion_finished(&task_app, &task_id, &content);
+            }
+            Err(error) => {
+                let _ = task_app.emit(
+                    "chat-error",
+                    ChatErrorPayload {
+                        request_id: &task_id,
+                        message: &error.to_string(),
+                        code: error.code(),
+                    },
+                );
+            }
+        }
+    });
+    request_id
+}
+
+/// The session's model, else the persona's, else the one selected in settings.
+async fn active_config(
+    app: &tauri::AppHandle,
+    persona: Option<&Persona>,
+) -> Result<ProviderConfig, AppError> {
+    let picked = state::session(app).await.model_override.is_some();
+    let model = match persona.filter(|_| !picked) {
+        Some(Persona {
+            provider,
+            model: Some(model),
+            ..
+        }) => {
+            let assistant = settings::current(app).assistant;
+            // The base URL in settings belongs to the provider in settings.
+            let (provider, base_url) = match provider {
+                Some(provider) if *provider != assistant.provider => (provider.clone(), None),
+                _ => (assistant.provider, assistant.base_url),
+            };
+            Some(ActiveModel {
+                provider,
+                model: model.clone(),
+                base_url,
+            })
+        }
+        _ => state::active_model(app).await,
+    };
+    let model = model.ok_or_else(|| AppError::InvalidInput("No model selected".to_string()))?;
+    Ok(ProviderConfig {
+        provider: model.provider,
+        model: model.model,
+        base_url: model.base_url,
+        timeout_ms: None,
+    })
+}
+
+/// Hold `params` to what the provider in `config` accepts.
+fn check_params(
+    app: &tauri::AppHandle,
+    config: &ProviderConfig,
+    messages: &[ChatMessage],
+    params: GenerationParams,
+) -> Result<GenerationParams, AppError> {
+    let provider = providers::resolve(app, &config.provider, config.base_url.as_deref())?;
+    params::check(provider.info(), provider.param_limits(), messages, params)
+}
+
+fn timeout(app: &tauri::AppHandle, config: &ProviderConfig) -> Duration {
+    config
+        .timeout_ms
+        .map(Duration::from_millis)
+        .unwrap_or_else(|| requests::default_timeout(app))
+}
+
+/// Run a completion to the end and return its text, for callers outside the UI. Tokens
+/// are still emitted as `chat-token` under the returned request's id. While offline it
+/// fails rather than wait, unless there is a local model to fall back to.
+pub(crate) async fn complete(
+    app: &tauri::AppHandle,
+    config: Option<ProviderConfig>,
+    messages: Vec<ChatMessage>,
+) -> Result<String, AppError> {
+    let persona = personas::effective(app, None).await.map(|p| p.persona);
+    let (messages, temperature) = personas::apply(app, persona.as_ref(), messages);
+    let config = match config {
+        Some(config) => config,
+        None => active_config(app, persona.as_ref()).await?,
+    };
+    let params = GenerationParams {
+        temperature,
+        ..GenerationParams::default()
+    };
+    let config = match offline::route(app, config)? {
+        Route::Send(config) => config,
+        Route::Queue(config) => {
+            return Err(AppError::Network(format!(
+                "Offline, and no local model is set to use instead of {}",
+                config.provider
+            )));
+        }
+    };
+    let params = check_params(app, &config, &messages, params)?;
+    let timeout = timeout(app, &config);
+    let request_id = requests::new_id();
+    let task_app = app.clone();
+    let task_id = request_id.clone();
+    requests::run(app, Some(request_id), Some(timeout), async move {
+        stream_completion(&task_app, &task_id, &config, &messages, &params).await
+    })
+    .await
+}
+
+/// Start a streaming chat as `chat_completion` does, calling `finish` with the response
+/// before `chat-done` is emitted. An error from it is emitted as `chat-error` instead.
+/// The persona is that of `conversation_id`, or of the active conversation without one.
+pub(crate) async fn start_chat<F>(
+    app: &tauri::AppHandle,
+    config: Option<ProviderConfig>,
+    conversation_id: Option<String>,
+    messages: Vec<ChatMessage>,
+    params: GenerationParams,
+    finish: F,
+) -> Result<String, AppError>
+where
+    F: FnOnce(&tauri::AppHandle, &str) -> Result<(), AppError> + Send + 'static,
+{
+    let persona = personas::effective(app, conversation_id)
+        .await
+        .map(|p| p.persona);
+    let (messages, temperature) = personas::apply(app, persona.as_ref(), messages);
+    let params = GenerationParams {
+        temperature: params.temperature.or(temperature),
+        ..params
+    };
+    let config = match config {
+        Some(config) => config,
+        None => active_config(app, persona.as_ref()).await?,
+    };
+    let (config, queue) = match offline::route(app, config)? {
+        Route::Send(config) => (config, None),
+        Route::Queue(config) => {
+            let what = format!("Chat with {}", config.provider);
+            (config, Some(what))
+        }
+    };
+    let params = check_params(app, &config, &messages, params)?;
+    let timeout = timeout(app, &config);
+    Ok(spawn_completion(
+        app,
+        timeout,
+        queue,
+        move |app, request_id| async move {
+            let content = stream_completion(&app, &request_id, &config, &messages, &params).await?;
+            finish(&app, &content)?;
+            Ok::<_, AppError>(content)
+        },
+    ))
+}
+
+/// Start a streaming completion and return its request id. Tokens arrive as `chat-token`
+/// events, followed by a single `chat-done` or `chat-error`. Without a `config` the
+/// model selected in settings is used. While offline a cloud model is swapped for the
+/// local one in `offline.fallbackModel`, or without one the chat waits for the network.
+/// `params` are checked against what the provider takes before anything is sent. With
+/// the `conversation_id` the messages came from, those its summary covers are sent as
+/// the summary instead, and its persona is used.
+#[tauri::command]
+pub async fn chat_completion(
+    app: tauri::AppHandle,
+    config: Option<ProviderConfig>,
+    messages: Vec<ChatMessage>,
+    params: Option<GenerationParams>,
+    conversation_id: Option<String>,
+) -> Result<String, AppError> {
+    let messages = match &conversation_id {
+        Some(id) => summaries::condense(&app, id, messages),
+        None => messages,
+    };
+    start_chat(
+        &app,
+        config,
+        conversation_id,
+        messages,
+        params.unwrap_or_default(),
+        |_, _| Ok(()),
+    )
+    .await
+}
+
+/// Stop a running completion. Returns `false` if it had already finished.
+#[tauri::command]
+pub fn abort_completion(app: tauri::AppHandle, id: String) -> bool {
+    requests::cancel(&app, &id)
+}
@@ -0,0 +1,372 @@
+use std::net::{Ipv4Addr, SocketAddr, TcpListener};
+use std::sync::{Arc, Mutex, RwLock};
+
+use axum::extract::{Path, Query, Request, State as Extract};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tauri::{Listener, Manager, State};
+use tokio::sync::oneshot;
+
+use crate::error::AppError;
+use crate::export::{self, ExportFormat};
+use crate::history::{self, ConversationExport, ConversationPage};
+use crate::llm::{self, ChatMessage, ProviderConfig};
+use crate::{deep_link, headless, secrets, settings, window_manager};
+
+const TOKEN_SECRET: &str = "local-api-token";
+
+/// The localhost automation API, when it is enabled.
+#[derive(Default)]
+pub struct ApiServer(Mutex<Option<Server>>);
+
+struct Server {
+    running: Running,
+    /// Shared with the server's requests, so a new token takes effect without a restart.
+    token: Arc<RwLock<String>>,
+}
+
+/// A server started by `serve`, stopped when this is dropped.
+pub(crate) struct Running {
+    pub port: u16,
+    _shutdown: oneshot::Sender<()>,
+}
+
+#[derive(Clone)]
+struct Context {
+    app: tauri::AppHandle,
+    token: Arc<RwLock<String>>,
+}
+
+/// An error response with a JSON `{ "error": … }` body.
+struct ApiError(StatusCode, String);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(json!({ "error": self.1 }))).into_response()
+    }
+}
+
+impl From<String> for ApiError {
+    fn from(message: String) -> Self {
+        Self(StatusCode::INTERNAL_SERVER_ERROR, message)
+    }
+}
+
+impl From<AppError> for ApiError {
+    fn from(error: AppError) -> Self {
+        let status = match error {
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::InvalidInput(_) => StatusCode::BAD_REQUEST,
+            AppError::PermissionDenied(_) => StatusCode::FORBIDDEN,
+            AppError::Network(_) | AppError::ProviderAuth { .. } | AppError::Provider { .. } => {
+                StatusCode::BAD_GATEWAY
+            }
+            AppError::Timeout(_) => StatusCode::GATEWAY_TIMEOUT,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        Self(status, error.to_string())
+    }
+}
+
+pub(crate) fn new_token() -> String {
+    format!(
+        "{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    )
+}
+
+/// The bearer token clients must send, created the first time it's needed.
+fn token() -> Result<String, String> {
+    if let Some(token) = secrets::app_secret(TOKEN_SECRET)? {
+        return Ok(token);
+    }
+    let token = new_token();
+    secrets::set_app_secret(TOKEN_SECRET, &token)?;
+    Ok(token)
+}
+
+/// The token as saved, without creating one; for the command line, which only calls a
+/// server that already has it.
+pub(crate) fn saved_token() -> Result<Option<String>, String> {
+    secrets::app_secret(TOKEN_SECRET)
+}
+
+/// Compare without bailing at the first differing byte, so timing gives nothing away.
+pub(crate) fn same_token(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |diff, (x, y)| diff | (x ^ y))
+            == 0
+}
+
+async fn authorize(Extract(context): Extract<Context>, request: Request, next: Next) -> Response {
+    let presented = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    let authorized =
+        presented.is_some_and(|token| same_token(token.trim(), &context.token.read().unwrap()));
+    if !authorized {
+        return ApiError(
+            StatusCode::UNAUTHORIZED,
+            "Missing or wrong API token".into(),
+        )
+        .into_response();
+    }
+    next.run(request).await
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AskRequest {
+    text: String,
+    system: Option<String>,
+    provider: Option<String>,
+    model: Option<String>,
+    /// Hand the prompt to the overlay instead of answering here.
+    #[serde(default)]
+    show: bool,
+}
+
+#[derive(Serialize)]
+struct AskResponse {
+    content: String,
+}
+
+async fn ask(
+    Extract(context): Extract<Context>,
+    Json(body): Json<AskRequest>,
+) -> Result<Response, ApiError> {
+    if body.text.trim().is_empty() {
+        return Err(ApiError(
+            StatusCode::BAD_REQUEST,
+            "text must not be empty".into(),
+        ));
+    }
+    if body.show {
+        deep_link::ask(&context.app, body.text, false)?;
+        return Ok(StatusCode::ACCEPTED.into_response());
+    }
+    let config = match (body.provider, body.model) {
+        (Some(provider), Some(model)) => Some(ProviderConfig {
+            provider,
+            model,
+            base_url: None,
+            timeout_ms: None,
+        }),
+        (None, None) => None,
+        _ => {
+            return Err(ApiError(
+                StatusCode::BAD_REQUEST,
+                "provider and model must be given together".into(),
+            ))
+        }
+    };
+    let mut messages = Vec::new();
+    if let Some(system) = body.system {
+        messages.push(ChatMessage {
+            role: "system".to_string(),
+            content: system,
+            images: Vec::new(),
+        });
+    }
+    messages.push(ChatMessage {
+        role: "user".to_string(),
+        content: body.text,
+        images: Vec::new(),
+    });
+    let content = llm::complete(&context.app, config, messages).await?;
+    Ok(Json(AskResponse { content }).into_response())
+}
+
+#[derive(Serialize)]
+struct Visibility {
+    visible: bool,
+}
+
+async fn show(Extract(context): Extract<Context>) -> Result<Json<Visibility>, ApiError> {
+    window_manager::show_overlay(&context.app)?;
+    Ok(Json(Visibility { visible: true }))
+}
+
+async fn hide(Extract(context): Extract<Context>) -> Result<Json<Visibility>, ApiError> {
+    window_manager::hide_overlay(&context.app)?;
+    Ok(Json(Visibility { visible: false }))
+}
+
+async fn toggle(Extract(context): Extract<Context>) -> Result<Json<Visibility>, ApiError> {
+    let visible = window_manager::toggle_overlay(&context.app)?;
+    Ok(Json(Visibility { visible }))
+}
+
+#[derive(Deserialize)]
+struct Page {
+    limit: Option<u32>,
+    offset: Option<u32>,
+}
+
+async fn list_history(
+    Extract(context): Extract<Context>,
+    Query(page): Query<Page>,
+) -> Result<Json<ConversationPage>, ApiError> {
+    let page = history::list_conversations(context.app.state(), page.limit, page.offset).await?;
+    Ok(Json(page))
+}
+
+async fn conversation(
+    Extract(context): Extract<Context>,
+    Path(id): Path<String>,
+) -> Result<Json<ConversationExport>, ApiError> {
+    let conversation = history::conversation_export(&context.app.state(), &id)?;
+    Ok(Json(conversation))
+}
+
+#[derive(Deserialize)]
+struct ExportQuery {
+    #[serde(default)]
+    format: ExportFormat,
+}
+
+/// A conversation rendered like `export_conversation` writes it, Markdown by default.
+async fn export_conversation(
+    Extract(context): Extract<Context>,
+    Path(id): Path<String>,
+    Query(query): Query<ExportQuery>,
+) -> Result<Response, ApiError> {
+    let conversation = history::conversation_export(&context.app.state(), &id)?;
+    let content_type = match query.format {
+        ExportFormat::Markdown => "text/markdown; charset=utf-8",
+        ExportFormat::Json => "application/json",
+        ExportFormat::Html => "text/html; charset=utf-8",
+    };
+    let body = export::render(&conversation, query.format)?;
+    Ok(([(header::CONTENT_TYPE, content_type)], body).into_response())
+}
+
+fn router(context: Context) -> Router {
+    Router::new()
+        .route("/ask", post(ask))
+        .route("/show", post(show))
+        .route("/hide", post(hide))
+        .route("/toggle", post(toggle))
+        .route("/history", get(list_history))
+        .route("/history/{id}", get(conversation))
+        .route("/history/{id}/export", get(export_conversation))
+        .layer(middleware::from_fn_with_state(context.clone(), authorize))
+        .with_state(context)
+}
+
+/// Serve `router` on localhost `port` in the background. `name` labels log messages.
+pub(crate) fn serve(name: &'static str, port: u16, router: Router) -> Result<Running, String> {
+    // Bind here rather than in the task so a taken port is reported right away.
+    let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, port)))
+        .map_err(|e| format!("Could not listen on port {port}: {e}"))?;
+    listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+    let (shutdown, stopped) = oneshot::channel::<()>();
+    tauri::async_runtime::spawn(async move {
+        let listener = match tokio::net::TcpListener::from_std(listener) {
+            Ok(listener) => listener,
+            Err(error) => {
+                tracing::error!("{name} failed to start: {error}");
+                return;
+            }
+        };
+        // Dropping the sender resolves this too, which is how `Running` stops the server.
+        let served = axum::serve(listener, router)
+            .with_graceful_shutdown(async {
+                let _ = stopped.await;
+            })
+            .await;
+        if let Err(error) = served {
+            tracing::error!("{name} stopped: {error}");
+        }
+    });
+    tracing::info!("{name} listening on 127.0.0.1:{port}");
+    Ok(Running {
+        port,
+        _shutdown: shutdown,
+    })
+}
+
+fn start(app: &tauri::AppHandle, port: u16) -> Result<Server, String> {
+    let token = Arc::new(RwLock::new(token()?));
+    let router = router(Context {
+        app: app.clone(),
+        token: token.clone(),
+    });
+    let running = serve("Local API", port, router)?;
+    Ok(Server { running, token })
+}
+
+/// Start, stop or move the server to match the settings.
+fn sync(app: &tauri::AppHandle) -> Result<(), String> {
+    let config = settings::current(app).api;
+    // Headless, the API is the only way in.
+    let wanted = (config.enabled || headless::active()).then_some(config.port);
+    let state = app.state::<ApiServer>();
+    let mut running = state.0.lock().unwrap();
+    if running.as_ref().map(|r| r.running.port) == wanted {
+        return Ok(());
+    }
+    // The old server finishes the requests it has in the background, on its own port.
+    *running = None;
+    if let Some(port) = wanted {
+        *running = Some(start(app, port)?);
+    }
+    Ok(())
+}
+
+/// Serve the API if it's enabled, and follow the setting from then on.
+pub fn init(app: &tauri::AppHandle) {
+    if let Err(error) = sync(app) {
+        tracing::error!("{error}");
+    }
+    let handle = app.clone();
+    app.listen("settings-changed", move |_| {
+        if let Err(error) = sync(&handle) {
+            tracing::error!("{error}");
+        }
+    });
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiServerStatus {
+    pub running: bool,
+    pub url: Option<String>,
+}
+
+#[tauri::command]
+pub fn get_api_server_status(state: State<'_, ApiServer>) -> ApiServerStatus {
+    let port = state.0.lock().unwrap().as_ref().map(|r| r.running.port);
+    ApiServerStatus {
+        running: port.is_some(),
+        url: port.map(|port| format!("http://127.0.0.1:{port}")),
+    }
+}
+
+/// The token to put in `Authorization: Bearer …` when calling the API.
+#[tauri::command]
+pub async fn get_api_token() -> Result<String, AppError> {
+    Ok(token()?)
+}
+
+/// Replace the token, locking out every client that had the old one. A running server
+/// switches to it straight away.
+#[tauri::command]
+pub async fn regenerate_api_token(state: State<'_, ApiServer>) -> Result<String, AppError> {
+    let token = new_token();
+    secrets::set_app_secret(TOKEN_SECRET, &token)?;
+    if let Some(server) = state.0.lock().unwrap().as_ref() {
+        *server.token.write().unwrap() = token.clone();
+    }
+    Ok(token)
+}
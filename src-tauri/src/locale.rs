@@ -0,0 +1,161 @@
+use std::sync::Mutex;
+
+use serde::Serialize;
+use serde_json::json;
+use tauri::{Emitter, Listener};
+
+use crate::error::AppError;
+use crate::llm::{self, ChatMessage};
+use crate::settings;
+
+/// Used when neither the settings nor the system name a language we can read.
+const FALLBACK_LOCALE: &str = "en";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocaleInfo {
+    /// The BCP 47 tag the UI uses.
+    pub locale: String,
+    /// What the OS is set to, if it says.
+    pub system: Option<String>,
+    /// Whether `locale` comes from the OS rather than `general.language`.
+    pub follows_system: bool,
+}
+
+/// `tag` as a BCP 47 tag in its usual case, such as `pt-BR` or `zh-Hant-TW`. Also takes
+/// POSIX names like `de_DE.UTF-8`. `None` for something that isn't a language tag.
+pub fn normalize(tag: &str) -> Option<String> {
+    let tag = tag.trim();
+    let tag = tag
+        .split(['.', '@'])
+        .next()
+        .unwrap_or(tag)
+        .replace('_', "-");
+    let mut subtags = tag.split('-');
+    let language = subtags.next()?;
+    let is_language =
+        (2..=3).contains(&language.len()) && language.chars().all(|c| c.is_ascii_alphabetic());
+    if !is_language {
+        return None;
+    }
+    let mut parts = vec![language.to_ascii_lowercase()];
+    for subtag in subtags {
+        let alphabetic = subtag.chars().all(|c| c.is_ascii_alphabetic());
+        let part = match subtag.len() {
+            4 if alphabetic => {
+                let (first, rest) = subtag.split_at(1);
+                format!(
+                    "{}{}",
+                    first.to_ascii_uppercase(),
+                    rest.to_ascii_lowercase()
+                )
+            }
+            2 if alphabetic => subtag.to_ascii_uppercase(),
+            3 if subtag.chars().all(|c| c.is_ascii_digit()) => subtag.to_string(),
+            5..=8 if subtag.chars().all(|c| c.is_ascii_alphanumeric()) => {
+                subtag.to_ascii_lowercase()
+            }
+            _ => return None,
+        };
+        parts.push(part);
+    }
+    Some(parts.join("-"))
+}
+
+fn system_locale() -> Option<String> {
+    sys_locale::get_locale().and_then(|tag| normalize(&tag))
+}
+
+pub fn current(app: &tauri::AppHandle) -> LocaleInfo {
+    let system = system_locale();
+    match settings::current(app)
+        .general
+        .language
+        .as_deref()
+        .and_then(normalize)
+    {
+        Some(locale) => LocaleInfo {
+            locale,
+            system,
+            follows_system: false,
+        },
+        None => LocaleInfo {
+            locale: system
+                .clone()
+                .unwrap_or_else(|| FALLBACK_LOCALE.to_string()),
+            system,
+            follows_system: true,
+        },
+    }
+}
+
+/// Emit `locale-changed` to every window whenever the UI language changes, however the
+/// settings were changed.
+pub fn init(app: &tauri::AppHandle) {
+    let applied = Mutex::new(current(app));
+    tracing::info!("UI locale is {}", applied.lock().unwrap().locale);
+    let handle = app.clone();
+    app.listen("settings-changed", move |_| {
+        let locale = current(&handle);
+        let mut applied = applied.lock().unwrap();
+        if *applied != locale {
+            *applied = locale.clone();
+            let _ = handle.emit("locale-changed", locale);
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_locale(app: tauri::AppHandle) -> LocaleInfo {
+    current(&app)
+}
+
+/// Use `locale` for the UI, or follow the OS with `None`.
+#[tauri::command]
+pub fn set_locale(app: tauri::AppHandle, locale: Option<String>) -> Result<LocaleInfo, AppError> {
+    let locale = match locale {
+        Some(tag) => Some(normalize(&tag).ok_or_else(|| {
+            AppError::InvalidInput(format!("\"{tag}\" is not a language tag like en or pt-BR"))
+        })?),
+        None => None,
+    };
+    settings::update(&app, &json!({ "general": { "language": locale } }))?;
+    Ok(current(&app))
+}
+
+/// Translate `text` with the configured model into `target_lang`, a BCP 47 tag, or
+/// the UI language when left out. Returns only the translation.
+#[tauri::command]
+pub async fn translate_text(
+    app: tauri::AppHandle,
+    text: String,
+    target_lang: Option<String>,
+) -> Result<String, AppError> {
+    if text.trim().is_empty() {
+        return Err(AppError::InvalidInput("Nothing to translate".to_string()));
+    }
+    let target = match target_lang {
+        Some(tag) => normalize(&tag).ok_or_else(|| {
+            AppError::InvalidInput(format!("\"{tag}\" is not a language tag like en or pt-BR"))
+        })?,
+        None => current(&app).locale,
+    };
+    let messages = vec![
+        ChatMessage {
+            role: "system".to_string(),
+            content: format!(
+                "Translate the user's text into the language with the BCP 47 tag {target}, \
+                 keeping its tone and formatting. If it is already in that language, return \
+                 it unchanged. Reply with only the translation."
+            ),
+            images: Vec::new(),
+        },
+        ChatMessage {
+            role: "user".to_string(),
+            content: text,
+            images: Vec::new(),
+        },
+    ];
+    let translation = llm::complete(&app, None, messages).await?;
+    Ok(translation.trim().to_string())
+}
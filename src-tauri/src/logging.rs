@@ -0,0 +1,159 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use tauri::Manager;
+use tauri_plugin_opener::OpenerExt;
+use tracing::Level;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter};
+
+use crate::error::AppError;
+
+const LOG_PREFIX: &str = "aikeya";
+const LOG_SUFFIX: &str = "log";
+/// Days of logs kept; the appender deletes older files as it rotates.
+const KEEP_LOG_FILES: usize = 7;
+const DEFAULT_LIMIT: usize = 200;
+const MAX_LIMIT: usize = 5000;
+
+/// Keeps the background log writer running. Lines logged after it drops are lost, so it
+/// lives in managed state for the life of the app.
+pub struct LogGuard {
+    _writer: WorkerGuard,
+}
+
+fn log_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    app.path().app_log_dir().map_err(|e| e.to_string())
+}
+
+/// Send `tracing` output to daily-rotated JSON files in the app log dir, and as text to
+/// stderr. `RUST_LOG` overrides the default `info` filter.
+pub fn init(app: &tauri::AppHandle) -> Result<LogGuard, String> {
+    let dir = log_dir(app)?;
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_PREFIX)
+        .filename_suffix(LOG_SUFFIX)
+        .max_log_files(KEEP_LOG_FILES)
+        .build(&dir)
+        .map_err(|e| e.to_string())?;
+    let (writer, guard) = tracing_appender::non_blocking(appender);
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer().json().flatten_event(true).with_writer(writer))
+        .with(fmt::layer().with_writer(std::io::stderr))
+        .try_init()
+        .map_err(|e| e.to_string())?;
+    Ok(LogGuard { _writer: guard })
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogEntry {
+    pub timestamp: String,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+    /// Any structured fields logged alongside the message.
+    pub fields: Map<String, Value>,
+}
+
+fn take_string(fields: &mut Map<String, Value>, key: &str) -> String {
+    match fields.remove(key) {
+        Some(Value::String(value)) => value,
+        _ => String::new(),
+    }
+}
+
+fn parse_entry(line: &str) -> Option<(Level, LogEntry)> {
+    let Ok(Value::Object(mut fields)) = serde_json::from_str(line) else {
+        return None;
+    };
+    let level = take_string(&mut fields, "level");
+    let severity = Level::from_str(&level).ok()?;
+    let entry = LogEntry {
+        timestamp: take_string(&mut fields, "timestamp"),
+        target: take_string(&mut fields, "target"),
+        message: take_string(&mut fields, "message"),
+        level,
+        fields,
+    };
+    Some((severity, entry))
+}
+
+/// The newest `limit` entries at `min_level` or more severe, newest first.
+fn recent(dir: &Path, min_level: Level, limit: usize) -> Result<Vec<LogEntry>, String> {
+    let mut files: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with(LOG_PREFIX) && name.ends_with(LOG_SUFFIX))
+            })
+            .collect(),
+        Err(_) => return Ok(Vec::new()),
+    };
+    // File names end in the date, so they sort chronologically.
+    files.sort_unstable_by(|a, b| b.cmp(a));
+
+    let mut entries = Vec::new();
+    for file in files {
+        let Ok(bytes) = fs::read(&file) else {
+            continue;
+        };
+        let text = String::from_utf8_lossy(&bytes);
+        for line in text.lines().rev() {
+            let Some((severity, entry)) = parse_entry(line) else {
+                continue;
+            };
+            if severity > min_level {
+                continue;
+            }
+            entries.push(entry);
+            if entries.len() == limit {
+                return Ok(entries);
+            }
+        }
+    }
+    Ok(entries)
+}
+
+/// Recent log entries, newest first. `level` is the least severe level included and
+/// defaults to `info`.
+#[tauri::command]
+pub async fn get_recent_logs(
+    app: tauri::AppHandle,
+    level: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<LogEntry>, AppError> {
+    let min_level = match level {
+        Some(level) => {
+            Level::from_str(&level).map_err(|_| format!("Unknown log level: {level}"))?
+        }
+        None => Level::INFO,
+    };
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let dir = log_dir(&app)?;
+    tauri::async_runtime::spawn_blocking(move || recent(&dir, min_level, limit))
+        .await?
+        .map_err(AppError::from)
+}
+
+/// Show the log folder in the system file manager.
+#[tauri::command]
+pub fn open_log_folder(app: tauri::AppHandle) -> Result<(), AppError> {
+    let dir = log_dir(&app)?;
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    app.opener()
+        .open_path(dir.to_string_lossy(), None::<&str>)
+        .map_err(|e| AppError::from(e.to_string()))
+}
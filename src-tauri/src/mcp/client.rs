@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde_json::{json, Value};
+use tauri::async_runtime::JoinHandle;
+use tokio::sync::{mpsc, oneshot};
+
+use super::transport::{self, Guard, Transport};
+use crate::settings::McpTransport;
+
+const PROTOCOL_VERSION: &str = "2024-11-05";
+/// For everything but tool calls, which fall under the chat request's timeout instead.
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+const DISCONNECTED: &str = "The MCP server disconnected";
+
+type Pending = Arc<Mutex<HashMap<u64, oneshot::Sender<Result<Value, String>>>>>;
+
+/// Something the server sent that wasn't an answer to one of our requests.
+pub enum ServerEvent {
+    Notification { method: String, params: Value },
+    Closed,
+}
+
+/// A JSON-RPC session with one MCP server. Dropping it disconnects.
+pub struct Client {
+    outgoing: mpsc::UnboundedSender<Value>,
+    pending: Pending,
+    next_id: AtomicU64,
+    closed: Arc<AtomicBool>,
+    reader: JoinHandle<()>,
+    _guard: Guard,
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
+
+/// Route one incoming message: a response to its waiter, a server request to a reply,
+/// and anything else to `on_event`.
+fn dispatch(
+    message: Value,
+    pending: &Pending,
+    replies: &mpsc::UnboundedSender<Value>,
+    on_event: &impl Fn(ServerEvent),
+) {
+    let method = message.get("method").and_then(Value::as_str);
+    match (message.get("id"), method) {
+        (Some(id), None) => {
+            let Some(waiter) = id
+                .as_u64()
+                .and_then(|id| pending.lock().unwrap().remove(&id))
+            else {
+                return;
+            };
+            let result = match message.get("error") {
+                Some(error) => Err(error
+                    .get("message")
+                    .and_then(Value::as_str)
+                    .unwrap_or("Unknown error")
+                    .to_string()),
+                None => Ok(message.get("result").cloned().unwrap_or(Value::Null)),
+            };
+            let _ = waiter.send(result);
+        }
+        (Some(id), Some(method)) => {
+            let reply = match method {
+                "ping" => json!({ "jsonrpc": "2.0", "id": id, "result": {} }),
+                _ => json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "error": { "code": -32601, "message": format!("{method} is not supported") },
+                }),
+            };
+            let _ = replies.send(reply);
+        }
+        (None, Some(method)) => on_event(ServerEvent::Notification {
+            method: method.to_string(),
+            params: message.get("params").cloned().unwrap_or(Value::Null),
+        }),
+        (None, None) => {}
+    }
+}
+
+impl Client {
+    /// Connect and run the `initialize` handshake. `on_event` is called from a background
+    /// task, ending with `Closed` if the server goes away.
+    pub async fn connect(
+        app: &tauri::AppHandle,
+        server: &str,
+        config: &McpTransport,
+        on_event: impl Fn(ServerEvent) + Send + 'static,
+    ) -> Result<Client, String> {
+        let Transport {
+            outgoing,
+            mut incoming,
+            guard,
+        } = transport::connect(app, server, config).await?;
+        let pending = Pending::default();
+        let closed = Arc::new(AtomicBool::new(false));
+        let (reader_pending, reader_closed, replies) =
+            (pending.clone(), closed.clone(), outgoing.clone());
+        let reader = tauri::async_runtime::spawn(async move {
+            while let Some(message) = incoming.recv().await {
+                dispatch(message, &reader_pending, &replies, &on_event);
+            }
+            let mut pending = reader_pending.lock().unwrap();
+            reader_closed.store(true, Ordering::SeqCst);
+            for (_, waiter) in pending.drain() {
+                let _ = waiter.send(Err(DISCONNECTED.to_string()));
+            }
+            drop(pending);
+            on_event(ServerEvent::Closed);
+        });
+        let client = Client {
+            outgoing,
+            pending,
+            next_id: AtomicU64::new(1),
+            closed,
+            reader,
+            _guard: guard,
+        };
+        let params = json!({
+            "protocolVersion": PROTOCOL_VERSION,
+            "capabilities": {},
+            "clientInfo": { "name": "aikeya", "version": app.package_info().version.to_string() },
+        });
+        client
+            .request("initialize", params, Some(REQUEST_TIMEOUT))
+            .await?;
+        client.notify("notifications/initialized", json!({}));
+        Ok(client)
+    }
+
+    /// Send a request and wait for its result, for at most `limit` if given.
+    pub async fn request(
+        &self,
+        method: &str,
+        params: Value,
+        limit: Option<Duration>,
+    ) -> Result<Value, String> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = oneshot::channel();
+        {
+            // Checked under the lock the reader drains on exit, so no waiter is left behind.
+            let mut pending = self.pending.lock().unwrap();
+            if self.closed.load(Ordering::SeqCst) {
+                return Err(DISCONNECTED.to_string());
+            }
+            pending.insert(id, tx);
+        }
+        let _ = self
+            .outgoing
+            .send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }));
+        let response = match limit {
+            Some(limit) => match tokio::time::timeout(limit, rx).await {
+                Ok(response) => response,
+                Err(_) => {
+                    self.pending.lock().unwrap().remove(&id);
+                    return Err(format!(
+                        "The MCP server did not answer {method} within {}s",
+                        limit.as_secs()
+                    ));
+                }
+            },
+            None => rx.await,
+        };
+        response.unwrap_or_else(|_| Err(DISCONNECTED.to_string()))
+    }
+
+    pub fn notify(&self, method: &str, params: Value) {
+        let _ = self
+            .outgoing
+            .send(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+
+    /// Every item of a paginated list, following `nextCursor` to the end.
+    pub async fn list_all(&self, method: &str, key: &str) -> Result<Vec<Value>, String> {
+        let mut items = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let params = match &cursor {
+                Some(cursor) => json!({ "cursor": cursor }),
+                None => json!({}),
+            };
+            let mut page = self.request(method, params, Some(REQUEST_TIMEOUT)).await?;
+            if let Value::Array(found) = page[key].take() {
+                items.extend(found);
+            }
+            cursor = page
+                .get("nextCursor")
+                .and_then(Value::as_str)
+                .map(str::to_string);
+            if cursor.is_none() {
+                return Ok(items);
+            }
+        }
+    }
+}
@@ -0,0 +1,497 @@
+mod client;
+mod transport;
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::{Emitter, Listener, Manager, State};
+
+use crate::error::AppError;
+use crate::llm::{ToolCall, ToolResult, ToolSpec};
+use crate::settings::{self, McpServerConfig};
+use crate::tools::{self, CallStatus};
+use client::{Client, ServerEvent};
+
+/// Providers reject longer function names.
+const MAX_TOOL_NAME: usize = 64;
+
+/// Bumped for every connection attempt, so a slow attempt can tell it has been replaced.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Connections to the MCP servers in settings.
+#[derive(Default)]
+pub struct McpState {
+    servers: Mutex<HashMap<String, Server>>,
+}
+
+struct Server {
+    config: McpServerConfig,
+    generation: u64,
+    state: ConnectionState,
+    error: Option<String>,
+    client: Option<Arc<Client>>,
+    tools: Vec<McpTool>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConnectionState {
+    Disabled,
+    Connecting,
+    Connected,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpTool {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub input_schema: Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpResource {
+    pub uri: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub mime_type: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpServerStatus {
+    pub id: String,
+    pub name: String,
+    pub state: ConnectionState,
+    pub error: Option<String>,
+    pub tools: Vec<McpTool>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpToolOutput {
+    pub content: String,
+    pub is_error: bool,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ToolProgress<'a> {
+    call_id: &'a str,
+    progress: Option<f64>,
+    total: Option<f64>,
+    message: Option<&'a str>,
+}
+
+/// The name a tool goes by in chat requests: `{server}__{tool}`, squeezed into the
+/// characters and length every provider accepts.
+fn qualified_name(server: &str, tool: &str) -> String {
+    format!("{server}__{tool}")
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .take(MAX_TOOL_NAME)
+        .collect()
+}
+
+fn statuses(app: &tauri::AppHandle) -> Vec<McpServerStatus> {
+    let configs = settings::current(app).mcp.servers;
+    let state = app.state::<McpState>();
+    let servers = state.servers.lock().unwrap();
+    configs
+        .into_iter()
+        .map(|config| {
+            let server = servers.get(&config.id).filter(|_| config.enabled);
+            McpServerStatus {
+                state: server.map_or(ConnectionState::Disabled, |s| s.state),
+                error: server.and_then(|s| s.error.clone()),
+                tools: server.map(|s| s.tools.clone()).unwrap_or_default(),
+                id: config.id,
+                name: config.name,
+            }
+        })
+        .collect()
+}
+
+fn emit_status(app: &tauri::AppHandle) {
+    let _ = app.emit("mcp-servers-changed", statuses(app));
+}
+
+fn parse_tools(tools: Vec<Value>) -> Vec<McpTool> {
+    tools
+        .into_iter()
+        .filter_map(|tool| serde_json::from_value(tool).ok())
+        .collect()
+}
+
+fn on_server_event(app: &tauri::AppHandle, id: &str, generation: u64, event: ServerEvent) {
+    match event {
+        ServerEvent::Notification { method, params } => match method.as_str() {
+            "notifications/progress" => {
+                // Tool calls are sent with their call id as the progress token.
+                let Some(call_id) = params.get("progressToken").and_then(Value::as_str) else {
+                    return;
+                };
+                let _ = app.emit(
+                    "tool-progress",
+                    ToolProgress {
+                        call_id,
+                        progress: params.get("progress").and_then(Value::as_f64),
+                        total: params.get("total").and_then(Value::as_f64),
+                        message: params.get("message").and_then(Value::as_str),
+                    },
+                );
+            }
+            "notifications/tools/list_changed" => {
+                let app = app.clone();
+                let id = id.to_string();
+                tauri::async_runtime::spawn(async move {
+                    if let Err(error) = refresh_tools(&app, &id).await {
+                        tracing::warn!("Could not refresh tools of MCP server {id}: {error}");
+                    }
+                });
+            }
+            _ => {}
+        },
+        ServerEvent::Closed => {
+            let state = app.state::<McpState>();
+            let mut servers = state.servers.lock().unwrap();
+            let Some(server) = servers
+                .get_mut(id)
+                .filter(|server| server.generation == generation)
+            else {
+                return;
+            };
+            tracing::warn!("MCP server {id} disconnected");
+            server.state = ConnectionState::Failed;
+            server.error = Some("The server disconnected".to_string());
+            server.client = None;
+            server.tools.clear();
+            drop(servers);
+            emit_status(app);
+        }
+    }
+}
+
+async fn refresh_tools(app: &tauri::AppHandle, id: &str) -> Result<(), String> {
+    let client = client_for(app, id)?;
+    let tools = parse_tools(client.list_all("tools/list", "tools").await?);
+    if let Some(server) = app.state::<McpState>().servers.lock().unwrap().get_mut(id) {
+        server.tools = tools;
+    }
+    emit_status(app);
+    Ok(())
+}
+
+/// Connect to one server in the background and record the outcome, unless the server has
+/// been removed or restarted in the meantime.
+fn connect(app: &tauri::AppHandle, config: McpServerConfig, generation: u64) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let events_app = app.clone();
+        let id = config.id.clone();
+        let result = async {
+            let client = Client::connect(&app, &config.id, &config.transport, move |event| {
+                on_server_event(&events_app, &id, generation, event)
+            })
+            .await?;
+            let tools = parse_tools(client.list_all("tools/list", "tools").await?);
+            Ok::<_, String>((client, tools))
+        }
+        .await;
+
+        let state = app.state::<McpState>();
+        let mut servers = state.servers.lock().unwrap();
+        let Some(server) = servers
+            .get_mut(&config.id)
+            .filter(|server| server.generation == generation)
+        else {
+            return;
+        };
+        match result {
+            Ok((client, tools)) => {
+                tracing::info!(
+                    "Connected to MCP server {} with {} tools",
+                    config.id,
+                    tools.len()
+                );
+                server.state = ConnectionState::Connected;
+                server.client = Some(Arc::new(client));
+                server.tools = tools;
+            }
+            Err(error) => {
+                tracing::warn!("Could not connect to MCP server {}: {error}", config.id);
+                server.state = ConnectionState::Failed;
+                server.error = Some(error);
+            }
+        }
+        drop(servers);
+        emit_status(&app);
+    });
+}
+
+/// Connect to newly enabled servers and drop removed ones. Servers whose transport
+/// changed are reconnected; other edits, like trusted tools, apply in place.
+fn sync(app: &tauri::AppHandle) {
+    let wanted: Vec<McpServerConfig> = settings::current(app)
+        .mcp
+        .servers
+        .into_iter()
+        .filter(|server| server.enabled)
+        .collect();
+    let state = app.state::<McpState>();
+    let mut servers = state.servers.lock().unwrap();
+    servers.retain(|id, server| {
+        wanted
+            .iter()
+            .any(|config| &config.id == id && config.transport == server.config.transport)
+    });
+    for config in wanted {
+        if let Some(server) = servers.get_mut(&config.id) {
+            server.config = config;
+            continue;
+        }
+        let generation = GENERATION.fetch_add(1, Ordering::Relaxed) + 1;
+        servers.insert(
+            config.id.clone(),
+            Server {
+                config: config.clone(),
+                generation,
+                state: ConnectionState::Connecting,
+                error: None,
+                client: None,
+                tools: Vec::new(),
+            },
+        );
+        connect(app, config, generation);
+    }
+    drop(servers);
+    emit_status(app);
+}
+
+/// Connect to the configured servers, and follow the settings from then on.
+pub fn init(app: &tauri::AppHandle) {
+    sync(app);
+    let handle = app.clone();
+    app.listen("settings-changed", move |_| sync(&handle));
+}
+
+fn client_for(app: &tauri::AppHandle, id: &str) -> Result<Arc<Client>, String> {
+    app.state::<McpState>()
+        .servers
+        .lock()
+        .unwrap()
+        .get(id)
+        .and_then(|server| server.client.clone())
+        .ok_or_else(|| format!("MCP server {id} isn't connected"))
+}
+
+/// Tools from every connected server, named for chat requests.
+pub fn chat_tools(app: &tauri::AppHandle) -> Vec<ToolSpec> {
+    let state = app.state::<McpState>();
+    let servers = state.servers.lock().unwrap();
+    let mut tools: Vec<ToolSpec> = servers
+        .values()
+        .filter(|server| server.client.is_some())
+        .flat_map(|server| {
+            server.tools.iter().map(|tool| ToolSpec {
+                name: qualified_name(&server.config.id, &tool.name),
+                description: tool
+                    .description
+                    .clone()
+                    .unwrap_or_else(|| format!("{} from {}", tool.name, server.config.name)),
+                parameters: match &tool.input_schema {
+                    Value::Object(_) => tool.input_schema.clone(),
+                    _ => json!({ "type": "object", "properties": {} }),
+                },
+            })
+        })
+        .collect();
+    tools.sort_by(|a, b| a.name.cmp(&b.name));
+    tools
+}
+
+/// Join a result's content blocks into text the model can read.
+fn render_content(content: &Value) -> String {
+    let Some(blocks) = content.as_array() else {
+        return String::new();
+    };
+    blocks
+        .iter()
+        .map(|block| match block.get("type").and_then(Value::as_str) {
+            Some("text") => block["text"].as_str().unwrap_or_default().to_string(),
+            Some("image") => format!("[image: {}]", block["mimeType"].as_str().unwrap_or("?")),
+            Some("resource") => match block.pointer("/resource/text").and_then(Value::as_str) {
+                Some(text) => text.to_string(),
+                None => format!(
+                    "[resource: {}]",
+                    block["resource"]["uri"].as_str().unwrap_or("?")
+                ),
+            },
+            _ => block.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+async fn invoke(
+    client: &Client,
+    tool: &str,
+    arguments: Value,
+    progress_token: Option<&str>,
+) -> Result<McpToolOutput, String> {
+    let mut params = json!({ "name": tool, "arguments": arguments });
+    if let Some(token) = progress_token {
+        params["_meta"] = json!({ "progressToken": token });
+    }
+    let result = client.request("tools/call", params, None).await?;
+    Ok(McpToolOutput {
+        content: render_content(&result["content"]),
+        is_error: result
+            .get("isError")
+            .and_then(Value::as_bool)
+            .unwrap_or(false),
+    })
+}
+
+/// Run a tool call the model asked for, asking the user first unless the tool is trusted.
+/// Progress is reported as `tool-call` and `tool-progress` events. Failures are returned
+/// to the model as error results rather than ending the completion.
+pub async fn call_tool(app: &tauri::AppHandle, request_id: &str, call: &ToolCall) -> ToolResult {
+    let found = {
+        let state = app.state::<McpState>();
+        let servers = state.servers.lock().unwrap();
+        servers.values().find_map(|server| {
+            let tool = server
+                .tools
+                .iter()
+                .find(|tool| qualified_name(&server.config.id, &tool.name) == call.name)?;
+            let trusted = server.config.trusted_tools.contains(&tool.name);
+            Some((
+                server.config.id.clone(),
+                tool.name.clone(),
+                server.client.clone()?,
+                trusted,
+            ))
+        })
+    };
+    let Some((server, tool, client, trusted)) = found else {
+        return ToolResult {
+            call_id: call.id.clone(),
+            content: format!("Unknown tool {}", call.name),
+            is_error: true,
+        };
+    };
+    let source = (Some(server.as_str()), tool.as_str());
+
+    if !trusted && !tools::ask_permission(app, request_id, call, source).await {
+        tools::emit_call(app, request_id, call, source, CallStatus::Denied, None);
+        return tools::declined(call);
+    }
+    tools::emit_call(app, request_id, call, source, CallStatus::Started, None);
+    let (content, is_error) =
+        match invoke(&client, &tool, call.arguments.clone(), Some(&call.id)).await {
+            Ok(output) => (output.content, output.is_error),
+            Err(error) => (error, true),
+        };
+    let status = if is_error {
+        CallStatus::Failed
+    } else {
+        CallStatus::Finished
+    };
+    tools::emit_call(app, request_id, call, source, status, Some(&content));
+    ToolResult {
+        call_id: call.id.clone(),
+        content,
+        is_error,
+    }
+}
+
+#[tauri::command]
+pub fn list_mcp_servers(app: tauri::AppHandle) -> Vec<McpServerStatus> {
+    statuses(&app)
+}
+
+/// Drop the connection to a server and connect again.
+#[tauri::command]
+pub fn restart_mcp_server(app: tauri::AppHandle, state: State<'_, McpState>, id: String) {
+    state.servers.lock().unwrap().remove(&id);
+    sync(&app);
+}
+
+/// Let the assistant call `tool` on `server` without asking first.
+pub fn trust(app: &tauri::AppHandle, server: &str, tool: &str) -> Result<(), String> {
+    let mut servers = settings::current(app).mcp.servers;
+    let Some(config) = servers.iter_mut().find(|s| s.id == server) else {
+        return Ok(());
+    };
+    if !config.trusted_tools.iter().any(|t| t == tool) {
+        config.trusted_tools.push(tool.to_string());
+        settings::update(app, &json!({ "mcp": { "servers": servers } }))?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn list_mcp_resources(
+    app: tauri::AppHandle,
+    server: String,
+) -> Result<Vec<McpResource>, AppError> {
+    let client = client_for(&app, &server)?;
+    let resources = client.list_all("resources/list", "resources").await?;
+    Ok(resources
+        .into_iter()
+        .filter_map(|resource| serde_json::from_value(resource).ok())
+        .collect())
+}
+
+/// The contents of a resource as the server returns them: `uri`, `mimeType` and either
+/// `text` or base64 `blob` for each part.
+#[tauri::command]
+pub async fn read_mcp_resource(
+    app: tauri::AppHandle,
+    server: String,
+    uri: String,
+) -> Result<Vec<Value>, AppError> {
+    let client = client_for(&app, &server)?;
+    let mut result = client
+        .request(
+            "resources/read",
+            json!({ "uri": uri }),
+            Some(client::REQUEST_TIMEOUT),
+        )
+        .await?;
+    match result["contents"].take() {
+        Value::Array(contents) => Ok(contents),
+        _ => Ok(Vec::new()),
+    }
+}
+
+/// Run a tool directly, e.g. from the settings page. No permission prompt: the user asked.
+#[tauri::command]
+pub async fn call_mcp_tool(
+    app: tauri::AppHandle,
+    server: String,
+    tool: String,
+    arguments: Option<Value>,
+) -> Result<McpToolOutput, AppError> {
+    let client = client_for(&app, &server)?;
+    let arguments = arguments.unwrap_or_else(|| json!({}));
+    invoke(&client, &tool, arguments, None)
+        .await
+        .map_err(AppError::from)
+}
@@ -0,0 +1,194 @@
+use std::process::Stdio;
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use serde_json::Value;
+use tauri::async_runtime::JoinHandle;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, Command};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::http;
+use crate::llm::LineBuffer;
+use crate::settings::McpTransport;
+
+/// How long an SSE server gets to announce the URL messages should be posted to.
+const ENDPOINT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A connection carrying JSON-RPC messages both ways.
+pub struct Transport {
+    pub outgoing: mpsc::UnboundedSender<Value>,
+    pub incoming: mpsc::UnboundedReceiver<Value>,
+    pub guard: Guard,
+}
+
+/// Dropping this stops the transport's tasks and kills a launched server.
+pub struct Guard {
+    tasks: Vec<JoinHandle<()>>,
+    _child: Option<Child>,
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+pub async fn connect(
+    app: &tauri::AppHandle,
+    server: &str,
+    transport: &McpTransport,
+) -> Result<Transport, String> {
+    match transport {
+        McpTransport::Stdio { command, args, env } => stdio(server, command, args, env),
+        McpTransport::Sse { url } => sse(app, url).await,
+    }
+}
+
+/// Launch the server as a child process, one JSON message per line on stdin and stdout.
+fn stdio(
+    server: &str,
+    program: &str,
+    args: &[String],
+    env: &std::collections::BTreeMap<String, String>,
+) -> Result<Transport, String> {
+    let mut command = Command::new(program);
+    command
+        .args(args)
+        .envs(env)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    #[cfg(windows)]
+    {
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        command.creation_flags(CREATE_NO_WINDOW);
+    }
+    let mut child = command
+        .spawn()
+        .map_err(|e| format!("Could not start {program}: {e}"))?;
+    let mut stdin = child.stdin.take().ok_or("stdin is piped")?;
+    let stdout = child.stdout.take().ok_or("stdout is piped")?;
+    let stderr = child.stderr.take().ok_or("stderr is piped")?;
+
+    let (outgoing, mut to_server) = mpsc::unbounded_channel::<Value>();
+    let (from_server, incoming) = mpsc::unbounded_channel();
+    let writer = tauri::async_runtime::spawn(async move {
+        while let Some(message) = to_server.recv().await {
+            let mut line = message.to_string();
+            line.push('\n');
+            if stdin.write_all(line.as_bytes()).await.is_err() || stdin.flush().await.is_err() {
+                return;
+            }
+        }
+    });
+    let reader = tauri::async_runtime::spawn(async move {
+        let mut lines = BufReader::new(stdout).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            match serde_json::from_str::<Value>(&line) {
+                Ok(message) => {
+                    let _ = from_server.send(message);
+                }
+                // Servers occasionally log to stdout; skip anything that isn't JSON.
+                Err(_) => tracing::debug!("Ignoring non-JSON output from an MCP server: {line}"),
+            }
+        }
+    });
+    let name = server.to_string();
+    let logger = tauri::async_runtime::spawn(async move {
+        let mut lines = BufReader::new(stderr).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            tracing::info!("MCP server {name}: {line}");
+        }
+    });
+    Ok(Transport {
+        outgoing,
+        incoming,
+        guard: Guard {
+            tasks: vec![writer, reader, logger],
+            _child: Some(child),
+        },
+    })
+}
+
+/// The HTTP+SSE transport: responses arrive as `message` events on a long-lived GET, and
+/// requests are POSTed to the URL the first `endpoint` event names.
+async fn sse(app: &tauri::AppHandle, url: &str) -> Result<Transport, String> {
+    let base = reqwest::Url::parse(url).map_err(|e| e.to_string())?;
+    let client = http::client(app)?;
+    let response = client
+        .get(base.clone())
+        .header(reqwest::header::ACCEPT, "text/event-stream")
+        .send()
+        .await
+        .map_err(|e| format!("Could not reach {url}: {e}"))?;
+    if !response.status().is_success() {
+        return Err(format!("{url} returned {}", response.status()));
+    }
+
+    let (endpoint_tx, endpoint_rx) = oneshot::channel::<String>();
+    let (from_server, incoming) = mpsc::unbounded_channel();
+    let reader = tauri::async_runtime::spawn(async move {
+        let mut endpoint_tx = Some(endpoint_tx);
+        let mut stream = response.bytes_stream();
+        let mut lines = LineBuffer::default();
+        let (mut event, mut data) = (String::new(), String::new());
+        while let Some(Ok(chunk)) = stream.next().await {
+            lines.push(&chunk);
+            while let Some(line) = lines.next_line() {
+                let line = line.trim_end_matches(['\r', '\n']);
+                if let Some(value) = line.strip_prefix("event:") {
+                    event = value.trim().to_string();
+                } else if let Some(value) = line.strip_prefix("data:") {
+                    if !data.is_empty() {
+                        data.push('\n');
+                    }
+                    data.push_str(value.strip_prefix(' ').unwrap_or(value));
+                } else if line.is_empty() && !data.is_empty() {
+                    if event == "endpoint" {
+                        if let Some(tx) = endpoint_tx.take() {
+                            let _ = tx.send(std::mem::take(&mut data));
+                        }
+                    } else if let Ok(message) = serde_json::from_str::<Value>(&data) {
+                        let _ = from_server.send(message);
+                    }
+                    event.clear();
+                    data.clear();
+                }
+            }
+        }
+    });
+
+    let endpoint = match tokio::time::timeout(ENDPOINT_TIMEOUT, endpoint_rx).await {
+        Ok(Ok(endpoint)) => base.join(&endpoint).ok(),
+        _ => None,
+    };
+    let Some(endpoint) = endpoint else {
+        reader.abort();
+        return Err(format!("{url} did not send a usable endpoint event"));
+    };
+    let (outgoing, mut to_server) = mpsc::unbounded_channel::<Value>();
+    let poster = tauri::async_runtime::spawn(async move {
+        while let Some(message) = to_server.recv().await {
+            let sent = client.post(endpoint.clone()).json(&message).send().await;
+            match sent {
+                Ok(response) if !response.status().is_success() => {
+                    tracing::warn!("MCP server at {endpoint} returned {}", response.status());
+                }
+                Err(error) => tracing::warn!("Could not post to {endpoint}: {error}"),
+                Ok(_) => {}
+            }
+        }
+    });
+    Ok(Transport {
+        outgoing,
+        incoming,
+        guard: Guard {
+            tasks: vec![reader, poster],
+            _child: None,
+        },
+    })
+}
@@ -0,0 +1,121 @@
+use std::path::{Path, PathBuf};
+
+use rusqlite::{Connection, DatabaseName};
+
+struct Migration {
+    version: u32,
+    name: &'static str,
+    sql: &'static str,
+}
+
+/// Every schema change, oldest first. Never edit one that has shipped; add a new one.
+/// The database's `user_version` is the last one applied.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial",
+        sql: include_str!("../migrations/0001_initial.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "generated_images",
+        sql: include_str!("../migrations/0002_generated_images.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "capability_audit",
+        sql: include_str!("../migrations/0003_capability_audit.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "message_branches",
+        sql: include_str!("../migrations/0004_message_branches.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "conversation_personas",
+        sql: include_str!("../migrations/0005_conversation_personas.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "conversation_summaries",
+        sql: include_str!("../migrations/0006_conversation_summaries.sql"),
+    },
+    Migration {
+        version: 7,
+        name: "latency_spans",
+        sql: include_str!("../migrations/0007_latency_spans.sql"),
+    },
+];
+
+/// The schema version this build creates and understands.
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map_or(0, |migration| migration.version)
+}
+
+pub fn version(conn: &Connection) -> rusqlite::Result<u32> {
+    conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+}
+
+/// False for a database that has just been created.
+fn has_tables(conn: &Connection) -> rusqlite::Result<bool> {
+    conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table')",
+        [],
+        |row| row.get(0),
+    )
+}
+
+fn backup_path(db_path: &Path, from: u32) -> PathBuf {
+    let mut name = db_path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".v{from}.bak"));
+    db_path.with_file_name(name)
+}
+
+/// Bring the schema up to date. All pending migrations run in one transaction, so a
+/// failure leaves the database exactly as it was. When `db_path` is given and there is
+/// data to lose, a copy is taken next to it first.
+pub fn run(conn: &mut Connection, db_path: Option<&Path>) -> Result<(), String> {
+    let current = version(conn).map_err(|e| e.to_string())?;
+    let latest = latest_version();
+    if current > latest {
+        return Err(format!(
+            "The database is at schema version {current}, newer than this version of Aikeya \
+             supports ({latest})"
+        ));
+    }
+    let pending: Vec<_> = MIGRATIONS
+        .iter()
+        .filter(|migration| migration.version > current)
+        .collect();
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    if let Some(db_path) = db_path {
+        if has_tables(conn).map_err(|e| e.to_string())? {
+            let backup = backup_path(db_path, current);
+            conn.backup(DatabaseName::Main, &backup, None)
+                .map_err(|e| format!("Could not back up the database before migrating: {e}"))?;
+            tracing::info!("Backed up the database to {}", backup.display());
+        }
+    }
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    for migration in pending {
+        tx.execute_batch(migration.sql).map_err(|e| {
+            format!(
+                "Migration {} ({}) failed, nothing was changed: {e}",
+                migration.version, migration.name
+            )
+        })?;
+        tx.pragma_update(None, "user_version", migration.version)
+            .map_err(|e| e.to_string())?;
+        tracing::info!(
+            "Applied database migration {} ({})",
+            migration.version,
+            migration.name
+        );
+    }
+    tx.commit().map_err(|e| e.to_string())
+}
@@ -0,0 +1,483 @@
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use futures_util::StreamExt;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::{Emitter, Manager};
+use tokio::io::AsyncWriteExt;
+
+use crate::error::AppError;
+use crate::jobs::{self, JobSpec, Progress};
+use crate::{db, http, persist};
+
+const MODELS_FILE: &str = "models.json";
+/// Left free on the disk after a download, so the rest of the system isn't starved.
+const DISK_MARGIN: u64 = 256 * 1024 * 1024;
+const PARTIAL_EXTENSION: &str = "part";
+/// How often progress is reported when the server doesn't say how big the file is.
+const UNSIZED_PROGRESS_STEP: u64 = 1024 * 1024;
+
+/// The kinds of model file kept under the managed models directory, one folder each.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ModelKind {
+    /// ggml files for whisper.cpp.
+    Whisper,
+    /// Local embedding models for the index.
+    Embedding,
+    /// GGUF files for llama.cpp.
+    Gguf,
+}
+
+impl ModelKind {
+    const ALL: [Self; 3] = [Self::Whisper, Self::Embedding, Self::Gguf];
+
+    fn dir_name(self) -> &'static str {
+        match self {
+            Self::Whisper => "whisper",
+            Self::Embedding => "embedding",
+            Self::Gguf => "gguf",
+        }
+    }
+}
+
+/// Where a model came from, saved when its download finishes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ModelRecord {
+    kind: ModelKind,
+    file: String,
+    url: String,
+    sha256: String,
+    downloaded_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalModel {
+    pub kind: ModelKind,
+    pub file: String,
+    pub path: PathBuf,
+    pub size: u64,
+    /// An interrupted download, which `download_model` picks up where it stopped.
+    pub partial: bool,
+    pub url: Option<String>,
+    /// As computed when the download finished.
+    pub sha256: Option<String>,
+    pub downloaded_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelCheck {
+    pub sha256: String,
+    /// The hash it was checked against: the one given, or else the one recorded.
+    pub expected: Option<String>,
+    pub matches: Option<bool>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DownloadProgressPayload<'a> {
+    kind: ModelKind,
+    file: &'a str,
+    downloaded: u64,
+    total: Option<u64>,
+}
+
+/// What is known about the downloaded models.
+pub struct ModelStore {
+    path: PathBuf,
+    records: Mutex<Vec<ModelRecord>>,
+}
+
+impl ModelStore {
+    pub fn load(app: &tauri::AppHandle) -> Result<Self, String> {
+        let path = persist::data_file(app, MODELS_FILE)?;
+        let records = persist::load_json(&path);
+        Ok(Self {
+            path,
+            records: Mutex::new(records),
+        })
+    }
+
+    fn record(&self, kind: ModelKind, file: &str) -> Option<ModelRecord> {
+        let records = self.records.lock().unwrap();
+        records
+            .iter()
+            .find(|record| record.kind == kind && record.file == file)
+            .cloned()
+    }
+
+    fn set(&self, kind: ModelKind, file: &str, record: Option<ModelRecord>) -> Result<(), String> {
+        let mut records = self.records.lock().unwrap();
+        records.retain(|record| record.kind != kind || record.file != file);
+        records.extend(record);
+        persist::save_json(&self.path, &*records)
+    }
+}
+
+/// The folder for `kind` under the app's models directory, created if needed.
+pub fn dir(app: &tauri::AppHandle, kind: ModelKind) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?
+        .join("models")
+        .join(kind.dir_name());
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir)
+}
+
+/// `file` inside the folder for `kind`, refusing anything that isn't a plain file name.
+fn model_path(app: &tauri::AppHandle, kind: ModelKind, file: &str) -> Result<PathBuf, AppError> {
+    let plain = Path::new(file)
+        .file_name()
+        .is_some_and(|name| name == file && !file.starts_with('.'));
+    if !plain || file.ends_with(&format!(".{PARTIAL_EXTENSION}")) {
+        return Err(AppError::InvalidInput(format!(
+            "{file} is not a valid model file name"
+        )));
+    }
+    Ok(dir(app, kind)?.join(file))
+}
+
+fn partial_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{PARTIAL_EXTENSION}"));
+    path.with_file_name(name)
+}
+
+/// Where a 206 response's `Content-Range` says its bytes start.
+fn range_start(response: &reqwest::Response) -> Option<u64> {
+    let range = response
+        .headers()
+        .get(reqwest::header::CONTENT_RANGE)?
+        .to_str()
+        .ok()?;
+    let (start, _) = range.strip_prefix("bytes ")?.split_once('-')?;
+    start.trim().parse().ok()
+}
+
+fn mb(bytes: u64) -> u64 {
+    bytes / 1024 / 1024
+}
+
+/// Hash a file as lowercase hex, reading it in chunks. Blocks.
+fn sha256_file(path: &Path) -> Result<String, String> {
+    let mut file = fs::File::open(path).map_err(|e| e.to_string())?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 1024 * 1024];
+    loop {
+        let read = file.read(&mut buffer).map_err(|e| e.to_string())?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+async fn hash(path: &Path) -> Result<String, String> {
+    let path = path.to_path_buf();
+    tauri::async_runtime::spawn_blocking(move || sha256_file(&path))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Download `url` to `path`, picking up from a `.part` file a previous attempt left, and
+/// return the file's SHA-256. `on_progress` is called with bytes done and the total
+/// whenever another percent arrives, or another megabyte when the total is unknown. Fails
+/// before downloading when the disk is too full, and drops the file when it doesn't hash
+/// to `sha256`.
+pub async fn fetch(
+    app: &tauri::AppHandle,
+    url: &str,
+    path: &Path,
+    sha256: Option<&str>,
+    progress: &Progress,
+    on_progress: impl Fn(u64, Option<u64>),
+) -> Result<String, String> {
+    let partial = partial_path(path);
+    let mut downloaded = tokio::fs::metadata(&partial)
+        .await
+        .map_or(0, |metadata| metadata.len());
+    let host = reqwest::Url::parse(url)
+        .map_err(|e| format!("{url} is not a valid URL: {e}"))?
+        .host_str()
+        .unwrap_or("models")
+        .to_string();
+    let mut request = http::client(app)?.get(url);
+    if downloaded > 0 {
+        request = request.header(reqwest::header::RANGE, format!("bytes={downloaded}-"));
+    }
+    let response = http::send(app, &host, None, request).await?;
+
+    let mut file = match response.status() {
+        // What was there already is the whole file.
+        StatusCode::RANGE_NOT_SATISFIABLE if downloaded > 0 => None,
+        status if !status.is_success() => return Err(format!("{url} answered {status}")),
+        StatusCode::PARTIAL_CONTENT => {
+            if range_start(&response) != Some(downloaded) {
+                // Appending would splice the file at the wrong place; the next try starts over.
+                let _ = tokio::fs::remove_file(&partial).await;
+                return Err(format!(
+                    "{url} resumed the download at a different offset than asked for"
+                ));
+            }
+            Some(
+                tokio::fs::OpenOptions::new()
+                    .append(true)
+                    .open(&partial)
+                    .await
+                    .map_err(|e| e.to_string())?,
+            )
+        }
+        _ => {
+            // The server ignored the range, so start over.
+            downloaded = 0;
+            Some(
+                tokio::fs::File::create(&partial)
+                    .await
+                    .map_err(|e| e.to_string())?,
+            )
+        }
+    };
+    if let Some(file) = file.as_mut() {
+        let remaining = response.content_length();
+        let total = remaining.map(|remaining| remaining + downloaded);
+        if let Some(remaining) = remaining {
+            let dir = path.parent().unwrap_or(path);
+            let available = fs4::available_space(dir).map_err(|e| e.to_string())?;
+            if available < remaining + DISK_MARGIN {
+                return Err(format!(
+                    "Not enough disk space: the download needs {} MB and {} MB are free",
+                    mb(remaining + DISK_MARGIN),
+                    mb(available)
+                ));
+            }
+        }
+
+        let mut stream = response.bytes_stream();
+        let mut last_step = None;
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.map_err(|e| e.to_string())?;
+            file.write_all(&chunk).await.map_err(|e| e.to_string())?;
+            downloaded += chunk.len() as u64;
+            let step = match total {
+                Some(total) => downloaded * 100 / total.max(1),
+                None => downloaded / UNSIZED_PROGRESS_STEP,
+            };
+            if last_step != Some(step) {
+                last_step = Some(step);
+                on_progress(downloaded, total);
+                progress.report(downloaded, total);
+            }
+        }
+        file.flush().await.map_err(|e| e.to_string())?;
+    }
+    drop(file);
+
+    let actual = hash(&partial).await?;
+    if let Some(expected) = sha256 {
+        if !actual.eq_ignore_ascii_case(expected.trim()) {
+            let _ = tokio::fs::remove_file(&partial).await;
+            return Err(format!(
+                "The download doesn't match its checksum: expected {expected}, got {actual}"
+            ));
+        }
+    }
+    tokio::fs::rename(&partial, path)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(actual)
+}
+
+/// The job behind `download_model`.
+pub(crate) async fn download(
+    app: tauri::AppHandle,
+    kind: ModelKind,
+    file: String,
+    url: String,
+    sha256: Option<String>,
+    progress: Progress,
+) -> Result<LocalModel, AppError> {
+    let path = model_path(&app, kind, &file)?;
+    let actual = fetch(
+        &app,
+        &url,
+        &path,
+        sha256.as_deref(),
+        &progress,
+        |downloaded, total| {
+            let _ = app.emit(
+                "model-download-progress",
+                DownloadProgressPayload {
+                    kind,
+                    file: &file,
+                    downloaded,
+                    total,
+                },
+            );
+        },
+    )
+    .await?;
+    let record = ModelRecord {
+        kind,
+        file: file.clone(),
+        url: url.clone(),
+        sha256: actual.clone(),
+        downloaded_at: db::now_ms(),
+    };
+    app.state::<ModelStore>()
+        .set(kind, &file, Some(record.clone()))?;
+    tracing::info!("Downloaded the {} model {file}", kind.dir_name());
+    Ok(LocalModel {
+        kind,
+        size: fs::metadata(&path).map_or(0, |metadata| metadata.len()),
+        file,
+        path,
+        partial: false,
+        url: Some(url),
+        sha256: Some(actual),
+        downloaded_at: Some(record.downloaded_at),
+    })
+}
+
+/// Record a model that was downloaded some other way, like the Whisper model list.
+pub(crate) fn remember(
+    app: &tauri::AppHandle,
+    kind: ModelKind,
+    file: &str,
+    url: &str,
+    sha256: String,
+) -> Result<(), String> {
+    let record = ModelRecord {
+        kind,
+        file: file.to_string(),
+        url: url.to_string(),
+        sha256,
+        downloaded_at: db::now_ms(),
+    };
+    app.state::<ModelStore>().set(kind, file, Some(record))
+}
+
+/// Every model file under the models directory, interrupted downloads included.
+#[tauri::command]
+pub fn list_local_models(app: tauri::AppHandle) -> Result<Vec<LocalModel>, AppError> {
+    let store = app.state::<ModelStore>();
+    let mut models = Vec::new();
+    for kind in ModelKind::ALL {
+        let Ok(entries) = fs::read_dir(dir(&app, kind)?) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if !metadata.is_file() || name.starts_with('.') {
+                continue;
+            }
+            let (file, partial) = match name.strip_suffix(&format!(".{PARTIAL_EXTENSION}")) {
+                Some(file) => (file.to_string(), true),
+                None => (name, false),
+            };
+            let record = store.record(kind, &file).filter(|_| !partial);
+            models.push(LocalModel {
+                kind,
+                file,
+                path: entry.path(),
+                size: metadata.len(),
+                partial,
+                url: record.as_ref().map(|record| record.url.clone()),
+                sha256: record.as_ref().map(|record| record.sha256.clone()),
+                downloaded_at: record.map(|record| record.downloaded_at),
+            });
+        }
+    }
+    models.sort_by(|a, b| (a.kind.dir_name(), &a.file).cmp(&(b.kind.dir_name(), &b.file)));
+    Ok(models)
+}
+
+/// Download a model file into the models directory as a job, emitting
+/// `model-download-progress`. Calling it again after an interruption resumes from where
+/// the download stopped. With `sha256` the file is checked before it is kept.
+#[tauri::command]
+pub async fn download_model(
+    app: tauri::AppHandle,
+    kind: ModelKind,
+    file: String,
+    url: String,
+    sha256: Option<String>,
+    request_id: Option<String>,
+) -> Result<LocalModel, AppError> {
+    model_path(&app, kind, &file)?;
+    let task_app = app.clone();
+    let spec = JobSpec::DownloadModel {
+        kind,
+        file: file.clone(),
+        url: url.clone(),
+        sha256: sha256.clone(),
+    };
+    jobs::run(&app, request_id, spec, move |progress| {
+        download(task_app, kind, file, url, sha256, progress)
+    })
+    .await
+}
+
+/// Hash a downloaded model and compare it with `sha256`, or with the hash recorded when
+/// it was downloaded.
+#[tauri::command]
+pub async fn verify_model(
+    app: tauri::AppHandle,
+    kind: ModelKind,
+    file: String,
+    sha256: Option<String>,
+) -> Result<ModelCheck, AppError> {
+    let path = model_path(&app, kind, &file)?;
+    if !path.is_file() {
+        return Err(AppError::NotFound(format!(
+            "No {} model {file}",
+            kind.dir_name()
+        )));
+    }
+    let actual = hash(&path).await?;
+    let expected = sha256.or_else(|| {
+        app.state::<ModelStore>()
+            .record(kind, &file)
+            .map(|record| record.sha256)
+    });
+    Ok(ModelCheck {
+        matches: expected
+            .as_ref()
+            .map(|expected| expected.trim().eq_ignore_ascii_case(&actual)),
+        sha256: actual,
+        expected,
+    })
+}
+
+/// Delete a model and any partial download of it. Returns whether there was anything to
+/// delete.
+#[tauri::command]
+pub fn delete_model(
+    app: tauri::AppHandle,
+    kind: ModelKind,
+    file: String,
+) -> Result<bool, AppError> {
+    let path = model_path(&app, kind, &file)?;
+    let mut deleted = false;
+    for path in [partial_path(&path), path] {
+        match fs::remove_file(&path) {
+            Ok(()) => deleted = true,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.to_string().into()),
+        }
+    }
+    app.state::<ModelStore>().set(kind, &file, None)?;
+    Ok(deleted)
+}
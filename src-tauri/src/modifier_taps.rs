@@ -0,0 +1,305 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::settings::Modifier;
+use crate::shortcuts;
+
+/// How often the keyboard is sampled while a double tap is bound. A tap lasts around
+/// a tenth of a second, so this has to be much quicker than the mouse trigger's.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+/// How often the bindings are checked while none is a double tap.
+const IDLE_INTERVAL: Duration = Duration::from_millis(500);
+/// Held any longer and it was a modifier the user meant to hold, not a tap.
+const MAX_TAP: Duration = Duration::from_millis(300);
+/// How soon after the first tap the second must end.
+const DOUBLE_TAP_WINDOW: Duration = Duration::from_millis(500);
+
+/// Modifier keys held down right now, as bits indexed by the `Modifier` variants, and
+/// whether any other key is down with them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Keys {
+    modifiers: u8,
+    other: bool,
+}
+
+impl Keys {
+    fn hold(&mut self, modifier: Modifier, down: bool) {
+        if down {
+            self.modifiers |= 1 << modifier as u8;
+        }
+    }
+
+    /// The one modifier that is down on its own, if that is all that is down.
+    fn lone_modifier(&self) -> Option<Modifier> {
+        if self.other || self.modifiers.count_ones() != 1 {
+            return None;
+        }
+        [
+            Modifier::Shift,
+            Modifier::Ctrl,
+            Modifier::Alt,
+            Modifier::Meta,
+        ]
+        .into_iter()
+        .find(|&modifier| self.modifiers == 1 << modifier as u8)
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use windows::Win32::UI::Input::KeyboardAndMouse::{
+        GetAsyncKeyState, VIRTUAL_KEY, VK_CONTROL, VK_LCONTROL, VK_LMENU, VK_LSHIFT, VK_LWIN,
+        VK_MENU, VK_RCONTROL, VK_RMENU, VK_RSHIFT, VK_RWIN, VK_SHIFT,
+    };
+
+    use super::Keys;
+    use crate::settings::Modifier;
+
+    const MODIFIER_KEYS: [VIRTUAL_KEY; 11] = [
+        VK_SHIFT,
+        VK_CONTROL,
+        VK_MENU,
+        VK_LWIN,
+        VK_RWIN,
+        VK_LSHIFT,
+        VK_RSHIFT,
+        VK_LCONTROL,
+        VK_RCONTROL,
+        VK_LMENU,
+        VK_RMENU,
+    ];
+
+    fn down(key: VIRTUAL_KEY) -> bool {
+        // SAFETY: GetAsyncKeyState only reads the key state; the high bit means "down".
+        unsafe { GetAsyncKeyState(i32::from(key.0)) as u16 & 0x8000 != 0 }
+    }
+
+    pub struct Keyboard;
+
+    impl Keyboard {
+        pub fn new() -> Self {
+            Self
+        }
+
+        pub fn sample(&mut self) -> Option<Keys> {
+            let mut keys = Keys::default();
+            keys.hold(Modifier::Shift, down(VK_SHIFT));
+            keys.hold(Modifier::Ctrl, down(VK_CONTROL));
+            keys.hold(Modifier::Alt, down(VK_MENU));
+            keys.hold(Modifier::Meta, down(VK_LWIN) || down(VK_RWIN));
+            // Everything from Backspace up; the codes below it are mouse buttons.
+            keys.other = (0x08..=0xFE)
+                .map(VIRTUAL_KEY)
+                .filter(|key| !MODIFIER_KEYS.contains(key))
+                .any(down);
+            Some(keys)
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::ops::RangeInclusive;
+
+    use objc2_app_kit::{NSEvent, NSEventModifierFlags};
+
+    use super::Keys;
+    use crate::settings::Modifier;
+
+    const COMBINED_SESSION_STATE: i32 = 0;
+    /// Virtual key codes of Command, Shift, Caps Lock, Option, Control and Fn.
+    const MODIFIER_KEYS: RangeInclusive<u16> = 0x36..=0x3F;
+    const LAST_KEY: u16 = 0x7E;
+
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        fn CGEventSourceKeyState(state: i32, key: u16) -> bool;
+    }
+
+    pub struct Keyboard;
+
+    impl Keyboard {
+        pub fn new() -> Self {
+            Self
+        }
+
+        pub fn sample(&mut self) -> Option<Keys> {
+            let flags = NSEvent::modifierFlags_class();
+            let mut keys = Keys::default();
+            keys.hold(Modifier::Shift, flags.contains(NSEventModifierFlags::Shift));
+            keys.hold(
+                Modifier::Ctrl,
+                flags.contains(NSEventModifierFlags::Control),
+            );
+            keys.hold(Modifier::Alt, flags.contains(NSEventModifierFlags::Option));
+            keys.hold(
+                Modifier::Meta,
+                flags.contains(NSEventModifierFlags::Command),
+            );
+            keys.other = (0..=LAST_KEY)
+                .filter(|key| !MODIFIER_KEYS.contains(key))
+                // SAFETY: a plain query with a constant state and an in-range key code.
+                .any(|key| unsafe { CGEventSourceKeyState(COMBINED_SESSION_STATE, key) });
+            Some(keys)
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use x11rb::protocol::xproto::{ConnectionExt, Keycode};
+    use x11rb::rust_connection::RustConnection;
+
+    use super::Keys;
+    use crate::settings::Modifier;
+    use crate::x11;
+
+    /// Rows of the X modifier map, in protocol order after Shift, Lock and Control.
+    const SHIFT: usize = 0;
+    const CONTROL: usize = 2;
+    const MOD1: usize = 3;
+    const MOD4: usize = 6;
+
+    struct Session {
+        conn: RustConnection,
+        /// The keycodes bound to each of the eight modifiers.
+        modifier_map: Vec<Vec<Keycode>>,
+    }
+
+    fn open() -> Option<Session> {
+        if x11::is_wayland() {
+            return None;
+        }
+        let (conn, _) = x11::connect().ok()?;
+        let reply = conn.get_modifier_mapping().ok()?.reply().ok()?;
+        let per_modifier = usize::from(reply.keycodes_per_modifier()).max(1);
+        let modifier_map = reply
+            .keycodes
+            .chunks(per_modifier)
+            .map(|codes| codes.iter().copied().filter(|&code| code != 0).collect())
+            .collect();
+        Some(Session { conn, modifier_map })
+    }
+
+    /// X11 only. On Wayland XWayland only sees keys sent to X11 clients, so it can't say.
+    pub struct Keyboard(Option<Session>);
+
+    impl Keyboard {
+        pub fn new() -> Self {
+            Self(None)
+        }
+
+        pub fn sample(&mut self) -> Option<Keys> {
+            if self.0.is_none() {
+                self.0 = open();
+            }
+            let session = self.0.as_ref()?;
+            let reply = session
+                .conn
+                .query_keymap()
+                .ok()
+                .and_then(|cookie| cookie.reply().ok());
+            let Some(reply) = reply else {
+                // Reconnect next time; the X server may have restarted.
+                self.0 = None;
+                return None;
+            };
+            let down = |code: Keycode| reply.keys[usize::from(code / 8)] & 1 << (code % 8) != 0;
+            let row_down = |row: usize| {
+                session
+                    .modifier_map
+                    .get(row)
+                    .is_some_and(|codes| codes.iter().any(|&code| down(code)))
+            };
+            let mut keys = Keys::default();
+            keys.hold(Modifier::Shift, row_down(SHIFT));
+            keys.hold(Modifier::Ctrl, row_down(CONTROL));
+            keys.hold(Modifier::Alt, row_down(MOD1));
+            keys.hold(Modifier::Meta, row_down(MOD4));
+            keys.other = (8..=Keycode::MAX).any(|code| {
+                down(code) && !session.modifier_map.iter().flatten().any(|&m| m == code)
+            });
+            Some(keys)
+        }
+    }
+}
+
+/// Turns key samples into double taps.
+#[derive(Default)]
+struct Taps {
+    /// The modifier held on its own since the last release, and since when.
+    down: Option<(Modifier, Instant)>,
+    /// The last clean tap: a modifier pressed and released quickly, on its own.
+    last: Option<(Modifier, Instant)>,
+    /// Something besides one modifier has been down since the last release, so this
+    /// press is part of a shortcut like Ctrl+C rather than a tap.
+    combined: bool,
+}
+
+impl Taps {
+    /// Feed one sample, and get back the modifier if this completed a double tap.
+    fn update(&mut self, keys: Keys, now: Instant) -> Option<Modifier> {
+        if keys == Keys::default() {
+            let tapped = self
+                .down
+                .take()
+                .filter(|(_, since)| !self.combined && now - *since <= MAX_TAP)
+                .map(|(modifier, _)| modifier);
+            self.combined = false;
+            let Some(modifier) = tapped else {
+                self.last = None;
+                return None;
+            };
+            let second = self.last.is_some_and(|(previous, at)| {
+                previous == modifier && now - at <= DOUBLE_TAP_WINDOW
+            });
+            // A third tap starts a new pair rather than firing again.
+            self.last = (!second).then_some((modifier, now));
+            return second.then_some(modifier);
+        }
+        match (keys.lone_modifier(), self.down) {
+            (Some(modifier), None) if !self.combined => self.down = Some((modifier, now)),
+            (Some(modifier), Some((held, _))) if modifier == held => {}
+            _ => {
+                self.combined = true;
+                self.last = None;
+            }
+        }
+        None
+    }
+}
+
+/// Watch the keyboard for as long as the app runs and run the actions bound to a double
+/// tap of a modifier, like Ctrl Ctrl. Global shortcuts can't be a bare modifier, so this
+/// samples the key state instead, the same way the mouse trigger samples the pointer.
+pub fn start(app: &tauri::AppHandle) {
+    let app = app.clone();
+    let spawned = thread::Builder::new()
+        .name("modifier-taps".to_string())
+        .spawn(move || {
+            let mut keyboard = platform::Keyboard::new();
+            let mut taps = Taps::default();
+            loop {
+                let bound = shortcuts::double_taps(&app);
+                if bound.is_empty() {
+                    taps = Taps::default();
+                    thread::sleep(IDLE_INTERVAL);
+                    continue;
+                }
+                thread::sleep(POLL_INTERVAL);
+                let Some(keys) = keyboard.sample() else {
+                    continue;
+                };
+                let Some(modifier) = taps.update(keys, Instant::now()) else {
+                    continue;
+                };
+                for (action, _) in bound.iter().filter(|(_, m)| *m == modifier) {
+                    tracing::debug!("Double tap of {modifier:?} for {action:?}");
+                    shortcuts::trigger(&app, *action);
+                }
+            }
+        });
+    if let Err(error) = spawned {
+        tracing::error!("Failed to start the modifier tap watcher: {error}");
+    }
+}
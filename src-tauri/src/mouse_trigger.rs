@@ -0,0 +1,255 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::settings::{self, Modifier, MouseButton, MouseChord, ScreenEdge};
+use crate::{focus, window_manager};
+
+/// How often the pointer is sampled while mouse activation is on.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// How often the settings are checked while it is off.
+const IDLE_INTERVAL: Duration = Duration::from_millis(500);
+/// How close to an edge, in physical pixels, counts as touching it.
+const EDGE_MARGIN: f64 = 1.0;
+
+/// Mouse buttons and modifier keys held down right now, as bit sets indexed by the
+/// `MouseButton` and `Modifier` variants.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Held {
+    buttons: u8,
+    modifiers: u8,
+}
+
+impl Held {
+    fn press(&mut self, button: MouseButton, down: bool) {
+        if down {
+            self.buttons |= 1 << button as u8;
+        }
+    }
+
+    fn hold(&mut self, modifier: Modifier, down: bool) {
+        if down {
+            self.modifiers |= 1 << modifier as u8;
+        }
+    }
+
+    /// The chord's button is down with exactly its modifiers, so Ctrl+Shift+click doesn't
+    /// also count as Ctrl+click.
+    fn matches(&self, chord: &MouseChord) -> bool {
+        let modifiers = chord
+            .modifiers
+            .iter()
+            .fold(0, |bits, &modifier| bits | 1 << modifier as u8);
+        self.buttons & 1 << chord.button as u8 != 0 && self.modifiers == modifiers
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use windows::Win32::UI::Input::KeyboardAndMouse::{
+        GetAsyncKeyState, VIRTUAL_KEY, VK_CONTROL, VK_LBUTTON, VK_LWIN, VK_MBUTTON, VK_MENU,
+        VK_RBUTTON, VK_RWIN, VK_SHIFT, VK_XBUTTON1, VK_XBUTTON2,
+    };
+
+    use super::Held;
+    use crate::settings::{Modifier, MouseButton};
+
+    fn down(key: VIRTUAL_KEY) -> bool {
+        // SAFETY: GetAsyncKeyState only reads the key state; the high bit means "down".
+        unsafe { GetAsyncKeyState(i32::from(key.0)) as u16 & 0x8000 != 0 }
+    }
+
+    pub struct Pointer;
+
+    impl Pointer {
+        pub fn new() -> Self {
+            Self
+        }
+
+        pub fn held(&mut self) -> Option<Held> {
+            let mut held = Held::default();
+            held.press(MouseButton::Left, down(VK_LBUTTON));
+            held.press(MouseButton::Right, down(VK_RBUTTON));
+            held.press(MouseButton::Middle, down(VK_MBUTTON));
+            held.press(MouseButton::Back, down(VK_XBUTTON1));
+            held.press(MouseButton::Forward, down(VK_XBUTTON2));
+            held.hold(Modifier::Shift, down(VK_SHIFT));
+            held.hold(Modifier::Ctrl, down(VK_CONTROL));
+            held.hold(Modifier::Alt, down(VK_MENU));
+            held.hold(Modifier::Meta, down(VK_LWIN) || down(VK_RWIN));
+            Some(held)
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use objc2_app_kit::{NSEvent, NSEventModifierFlags};
+
+    use super::Held;
+    use crate::settings::{Modifier, MouseButton};
+
+    pub struct Pointer;
+
+    impl Pointer {
+        pub fn new() -> Self {
+            Self
+        }
+
+        pub fn held(&mut self) -> Option<Held> {
+            let buttons = NSEvent::pressedMouseButtons();
+            let flags = NSEvent::modifierFlags_class();
+            let mut held = Held::default();
+            held.press(MouseButton::Left, buttons & 1 != 0);
+            held.press(MouseButton::Right, buttons & 1 << 1 != 0);
+            held.press(MouseButton::Middle, buttons & 1 << 2 != 0);
+            held.press(MouseButton::Back, buttons & 1 << 3 != 0);
+            held.press(MouseButton::Forward, buttons & 1 << 4 != 0);
+            held.hold(Modifier::Shift, flags.contains(NSEventModifierFlags::Shift));
+            held.hold(
+                Modifier::Ctrl,
+                flags.contains(NSEventModifierFlags::Control),
+            );
+            held.hold(Modifier::Alt, flags.contains(NSEventModifierFlags::Option));
+            held.hold(
+                Modifier::Meta,
+                flags.contains(NSEventModifierFlags::Command),
+            );
+            Some(held)
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use x11rb::protocol::xproto::{ConnectionExt, KeyButMask, Window};
+    use x11rb::rust_connection::RustConnection;
+
+    use super::Held;
+    use crate::settings::{Modifier, MouseButton};
+    use crate::x11;
+
+    /// X11 reports buttons 1 to 5 only, so the side buttons never show up. Wayland
+    /// doesn't share the pointer with other clients at all.
+    pub struct Pointer(Option<(RustConnection, Window)>);
+
+    impl Pointer {
+        pub fn new() -> Self {
+            Self(None)
+        }
+
+        pub fn held(&mut self) -> Option<Held> {
+            if self.0.is_none() {
+                self.0 = x11::connect_for_windows().ok();
+            }
+            let (conn, root) = self.0.as_ref()?;
+            let reply = conn
+                .query_pointer(*root)
+                .ok()
+                .and_then(|cookie| cookie.reply().ok());
+            let Some(reply) = reply else {
+                // Reconnect next time; the X server may have restarted.
+                self.0 = None;
+                return None;
+            };
+            let mask = reply.mask;
+            let mut held = Held::default();
+            held.press(MouseButton::Left, mask.contains(KeyButMask::BUTTON1));
+            held.press(MouseButton::Middle, mask.contains(KeyButMask::BUTTON2));
+            held.press(MouseButton::Right, mask.contains(KeyButMask::BUTTON3));
+            held.hold(Modifier::Shift, mask.contains(KeyButMask::SHIFT));
+            held.hold(Modifier::Ctrl, mask.contains(KeyButMask::CONTROL));
+            held.hold(Modifier::Alt, mask.contains(KeyButMask::MOD1));
+            held.hold(Modifier::Meta, mask.contains(KeyButMask::MOD4));
+            Some(held)
+        }
+    }
+}
+
+/// Whether the cursor is against `edge` of the monitor it is on.
+fn at_edge(app: &tauri::AppHandle, edge: ScreenEdge) -> bool {
+    let (Ok(cursor), Ok(monitors)) = (app.cursor_position(), app.available_monitors()) else {
+        return false;
+    };
+    monitors.iter().any(|monitor| {
+        let left = f64::from(monitor.position().x);
+        let top = f64::from(monitor.position().y);
+        let right = left + f64::from(monitor.size().width) - 1.0;
+        let bottom = top + f64::from(monitor.size().height) - 1.0;
+        let inside = (left..=right).contains(&cursor.x) && (top..=bottom).contains(&cursor.y);
+        let near_top = cursor.y <= top + EDGE_MARGIN;
+        let near_bottom = cursor.y >= bottom - EDGE_MARGIN;
+        let near_left = cursor.x <= left + EDGE_MARGIN;
+        let near_right = cursor.x >= right - EDGE_MARGIN;
+        inside
+            && match edge {
+                ScreenEdge::Top => near_top,
+                ScreenEdge::Bottom => near_bottom,
+                ScreenEdge::Left => near_left,
+                ScreenEdge::Right => near_right,
+                ScreenEdge::TopLeft => near_top && near_left,
+                ScreenEdge::TopRight => near_top && near_right,
+                ScreenEdge::BottomLeft => near_bottom && near_left,
+                ScreenEdge::BottomRight => near_bottom && near_right,
+            }
+    })
+}
+
+fn summon(app: &tauri::AppHandle, trigger: &str) {
+    tracing::debug!("Showing the overlay for a mouse {trigger}");
+    if let Err(error) = window_manager::show_overlay_nonactivating(app, None) {
+        tracing::error!("Failed to show overlay from a mouse {trigger}: {error}");
+    }
+}
+
+/// Watch the pointer for as long as the app runs and show the overlay when it rests
+/// against `mouse.edge` or `mouse.chord` is pressed, while `mouse.enabled` is on.
+/// Sampling keeps this free of global input hooks, which macOS and Wayland restrict.
+pub fn start(app: &tauri::AppHandle) {
+    let app = app.clone();
+    let spawned = thread::Builder::new()
+        .name("mouse-trigger".to_string())
+        .spawn(move || {
+            let mut pointer = platform::Pointer::new();
+            // When the cursor reached the edge, and whether it has fired since.
+            let mut at_edge_since: Option<Instant> = None;
+            let mut edge_fired = false;
+            let mut chord_down = false;
+            loop {
+                let settings = settings::current(&app).mouse;
+                if !settings.enabled {
+                    at_edge_since = None;
+                    chord_down = false;
+                    thread::sleep(IDLE_INTERVAL);
+                    continue;
+                }
+                thread::sleep(POLL_INTERVAL);
+                match settings.edge {
+                    Some(edge) if at_edge(&app, edge) => {
+                        let since = *at_edge_since.get_or_insert_with(Instant::now);
+                        let dwell = Duration::from_millis(settings.edge_dwell_ms);
+                        if !edge_fired && since.elapsed() >= dwell {
+                            edge_fired = true;
+                            // Games and presentations run the pointer into edges all the time.
+                            if !focus::should_stay_quiet(&app) {
+                                summon(&app, "edge");
+                            }
+                        }
+                    }
+                    _ => {
+                        at_edge_since = None;
+                        edge_fired = false;
+                    }
+                }
+                if let Some(chord) = &settings.chord {
+                    let down = pointer.held().is_some_and(|held| held.matches(chord));
+                    if down && !chord_down {
+                        summon(&app, "chord");
+                    }
+                    chord_down = down;
+                }
+            }
+        });
+    if let Err(error) = spawned {
+        tracing::error!("Failed to start the mouse trigger: {error}");
+    }
+}
@@ -0,0 +1,130 @@
+use std::thread;
+
+use notify_rust::Notification;
+use serde::Serialize;
+use tauri::Emitter;
+
+use crate::clipboard::{self, ClipboardContent};
+use crate::window_manager::{self, AppWindow};
+use crate::{focus, settings};
+
+/// Characters of a response shown in the body of its notification.
+const PREVIEW_CHARS: usize = 200;
+const VIEW: &str = "view";
+const COPY: &str = "copy";
+
+/// A finished response a notification offers to view or copy.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Completion {
+    pub request_id: Option<String>,
+    /// Where the response was saved, if it was.
+    pub conversation_id: Option<String>,
+    pub text: String,
+}
+
+/// The start of `text`, short enough for a notification body.
+pub fn preview(text: &str) -> String {
+    let mut preview: String = text.chars().take(PREVIEW_CHARS).collect();
+    if preview.len() < text.len() {
+        preview.push('…');
+    }
+    preview
+}
+
+/// Handle a click on a notification for `completion`. macOS reports the button's label
+/// rather than its identifier, hence the case-insensitive match.
+fn respond(app: &tauri::AppHandle, action: &str, completion: Completion) {
+    if action.eq_ignore_ascii_case(COPY) {
+        let text = ClipboardContent::Text {
+            text: completion.text,
+        };
+        if let Err(error) = clipboard::write(app, &text) {
+            tracing::warn!("Could not copy a response from its notification: {error}");
+        }
+    } else if action != "__closed" {
+        if let Err(error) = window_manager::show_overlay(app) {
+            tracing::error!("Failed to show overlay from a notification: {error}");
+        }
+        let _ = app.emit("notification-view", completion);
+    }
+}
+
+/// Show a native notification. With a `completion`, it gets "View" and "Copy" buttons:
+/// viewing, or clicking the notification itself, brings up the overlay and emits
+/// `notification-view`; copying puts the full text on the clipboard.
+pub fn show(app: &tauri::AppHandle, title: &str, body: &str, completion: Option<Completion>) {
+    if focus::should_stay_quiet(app) {
+        tracing::debug!("Holding back the \"{title}\" notification while the user is busy");
+        return;
+    }
+    let mut notification = Notification::new();
+    notification.summary(title).body(body).auto_icon();
+    if completion.is_some() {
+        // Freedesktop servers only report clicks on the body for a "default" action; the
+        // other platforms always do, and would show it as a third button.
+        #[cfg(not(any(target_os = "macos", windows)))]
+        notification.action("default", "View");
+        notification.action(VIEW, "View");
+        notification.action(COPY, "Copy");
+    }
+    let identifier = &app.config().identifier;
+    #[cfg(target_os = "macos")]
+    {
+        // Outside a bundle there is no identifier for macOS to attribute the notification to.
+        let bundle = if tauri::is_dev() {
+            "com.apple.Terminal"
+        } else {
+            identifier.as_str()
+        };
+        let _ = notify_rust::set_application(bundle);
+    }
+    #[cfg(windows)]
+    if !tauri::is_dev() {
+        notification.app_id(identifier);
+    }
+    #[cfg(not(any(target_os = "macos", windows)))]
+    notification.appname(identifier);
+
+    let app = app.clone();
+    // Waiting for a click blocks, so each notification gets a thread of its own.
+    let spawned = thread::Builder::new()
+        .name("notification".into())
+        .spawn(move || {
+            let handle = match notification.show() {
+                Ok(handle) => handle,
+                Err(error) => {
+                    tracing::warn!("Could not show a notification: {error}");
+                    return;
+                }
+            };
+            if let Some(completion) = completion {
+                handle.wait_for_action(|action| respond(&app, action, completion));
+            }
+        });
+    if let Err(error) = spawned {
+        tracing::error!("Failed to start a notification thread: {error}");
+    }
+}
+
+/// Let the user know a response is ready if they weren't looking: the overlay is hidden
+/// and `general.notifyBackgroundCompletions` is on.
+pub fn completion_finished(app: &tauri::AppHandle, request_id: &str, content: &str) {
+    if !settings::current(app).general.notify_background_completions {
+        return;
+    }
+    let visible = window_manager::is_visible(app, AppWindow::Overlay);
+    if visible || content.trim().is_empty() {
+        return;
+    }
+    show(
+        app,
+        "Response ready",
+        &preview(content),
+        Some(Completion {
+            request_id: Some(request_id.to_string()),
+            conversation_id: None,
+            text: content.to_string(),
+        }),
+    );
+}
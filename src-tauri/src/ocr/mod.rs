@@ -0,0 +1,88 @@
+use serde::Serialize;
+
+use crate::capabilities;
+use crate::error::AppError;
+use crate::imaging::ImageSource;
+
+#[cfg(target_os = "macos")]
+mod vision;
+#[cfg(target_os = "macos")]
+use vision as engine;
+
+#[cfg(windows)]
+mod windows;
+#[cfg(windows)]
+use self::windows as engine;
+
+#[cfg(target_os = "linux")]
+mod tesseract;
+#[cfg(target_os = "linux")]
+use tesseract as engine;
+
+/// A rectangle in image pixels, origin at the top-left corner.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct BoundingBox {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl BoundingBox {
+    // Vision already reports whole-line boxes, so only the other engines merge words.
+    #[cfg_attr(target_os = "macos", allow(dead_code))]
+    fn union(self, other: Self) -> Self {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        let right = (self.x + self.width).max(other.x + other.width);
+        let bottom = (self.y + self.height).max(other.y + other.height);
+        Self {
+            x,
+            y,
+            width: right - x,
+            height: bottom - y,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OcrLine {
+    pub text: String,
+    pub bbox: BoundingBox,
+    /// 0.0–1.0, when the engine reports it.
+    pub confidence: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OcrResult {
+    pub text: String,
+    pub lines: Vec<OcrLine>,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Recognize text in an image using the platform's OCR engine: Vision on macOS,
+/// Windows.Media.Ocr on Windows and Tesseract on Linux.
+#[tauri::command]
+pub async fn ocr_image(app: tauri::AppHandle, source: ImageSource) -> Result<OcrResult, AppError> {
+    if let ImageSource::Path { path } = &source {
+        capabilities::require_path(&app, path, "Read text in an image").await?;
+    }
+    tauri::async_runtime::spawn_blocking(move || {
+        let image = source.load(&app)?;
+        let lines = engine::recognize(&image)?;
+        let text = lines
+            .iter()
+            .map(|line| line.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+        Ok(OcrResult {
+            text,
+            lines,
+            width: image.width(),
+            height: image.height(),
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,77 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::process::Command;
+
+use image::RgbaImage;
+
+use super::{BoundingBox, OcrLine};
+
+/// Run the `tesseract` CLI and group its word-level TSV output into lines.
+pub fn recognize(image: &RgbaImage) -> Result<Vec<OcrLine>, String> {
+    let input = std::env::temp_dir().join(format!("aikeya-ocr-{}.png", uuid::Uuid::new_v4()));
+    image.save(&input).map_err(|e| e.to_string())?;
+    let output = Command::new("tesseract")
+        .arg(&input)
+        .args(["stdout", "tsv"])
+        .output();
+    let _ = fs::remove_file(&input);
+
+    let output = output.map_err(|e| {
+        if e.kind() == std::io::ErrorKind::NotFound {
+            "OCR needs Tesseract; install the tesseract-ocr package to enable it".to_string()
+        } else {
+            e.to_string()
+        }
+    })?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(parse_tsv(&String::from_utf8_lossy(&output.stdout)))
+}
+
+struct LineWords {
+    words: Vec<String>,
+    bbox: BoundingBox,
+    confidences: Vec<f32>,
+}
+
+/// Columns: level page block par line word left top width height conf text.
+fn parse_tsv(tsv: &str) -> Vec<OcrLine> {
+    // Keyed by (page, block, paragraph, line) so lines come out in reading order.
+    let mut lines: BTreeMap<(u32, u32, u32, u32), LineWords> = BTreeMap::new();
+
+    for row in tsv.lines().skip(1) {
+        let cols: Vec<&str> = row.splitn(12, '\t').collect();
+        if cols.len() < 12 || cols[0] != "5" || cols[11].trim().is_empty() {
+            continue;
+        }
+        let num = |i: usize| cols[i].parse::<f32>().unwrap_or(0.0);
+        let key = (num(1) as u32, num(2) as u32, num(3) as u32, num(4) as u32);
+        let bbox = BoundingBox {
+            x: num(6),
+            y: num(7),
+            width: num(8),
+            height: num(9),
+        };
+        let line = lines.entry(key).or_insert_with(|| LineWords {
+            words: Vec::new(),
+            bbox,
+            confidences: Vec::new(),
+        });
+        line.words.push(cols[11].trim().to_string());
+        line.bbox = line.bbox.union(bbox);
+        if num(10) >= 0.0 {
+            line.confidences.push(num(10) / 100.0);
+        }
+    }
+
+    lines
+        .into_values()
+        .map(|line| OcrLine {
+            text: line.words.join(" "),
+            bbox: line.bbox,
+            confidence: (!line.confidences.is_empty())
+                .then(|| line.confidences.iter().sum::<f32>() / line.confidences.len() as f32),
+        })
+        .collect()
+}
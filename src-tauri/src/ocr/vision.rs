@@ -0,0 +1,53 @@
+use image::RgbaImage;
+use objc2::AnyThread;
+use objc2_foundation::{NSArray, NSData, NSDictionary};
+use objc2_vision::{
+    VNImageRequestHandler, VNRecognizeTextRequest, VNRequest, VNRequestTextRecognitionLevel,
+};
+
+use super::{BoundingBox, OcrLine};
+use crate::imaging;
+
+pub fn recognize(image: &RgbaImage) -> Result<Vec<OcrLine>, String> {
+    let png = imaging::encode_png(image)?;
+    let (width, height) = (image.width() as f32, image.height() as f32);
+
+    let data = NSData::with_bytes(&png);
+    let handler = VNImageRequestHandler::initWithData_options(
+        VNImageRequestHandler::alloc(),
+        &data,
+        &NSDictionary::new(),
+    );
+    let request = VNRecognizeTextRequest::new();
+    request.setRecognitionLevel(VNRequestTextRecognitionLevel::Accurate);
+    request.setUsesLanguageCorrection(true);
+
+    let as_request: &VNRequest = &request;
+    handler
+        .performRequests_error(&NSArray::from_slice(&[as_request]))
+        .map_err(|e| e.localizedDescription().to_string())?;
+
+    let Some(observations) = request.results() else {
+        return Ok(Vec::new());
+    };
+    let mut lines = Vec::new();
+    for observation in observations.iter() {
+        let Some(candidate) = observation.topCandidates(1).firstObject() else {
+            continue;
+        };
+        // Vision boxes are normalized with the origin at the bottom-left.
+        // SAFETY: plain struct getter on a valid observation.
+        let rect = unsafe { observation.boundingBox() };
+        lines.push(OcrLine {
+            text: candidate.string().to_string(),
+            bbox: BoundingBox {
+                x: rect.origin.x as f32 * width,
+                y: (1.0 - rect.origin.y as f32 - rect.size.height as f32) * height,
+                width: rect.size.width as f32 * width,
+                height: rect.size.height as f32 * height,
+            },
+            confidence: Some(candidate.confidence()),
+        });
+    }
+    Ok(lines)
+}
@@ -0,0 +1,55 @@
+use image::RgbaImage;
+use windows::Graphics::Imaging::{BitmapAlphaMode, BitmapPixelFormat, SoftwareBitmap};
+use windows::Media::Ocr::OcrEngine;
+use windows::Security::Cryptography::CryptographicBuffer;
+
+use super::{BoundingBox, OcrLine};
+
+fn recognize_inner(image: &RgbaImage) -> windows::core::Result<Vec<OcrLine>> {
+    // OcrEngine wants BGRA8, so swap the red and blue channels.
+    let mut bgra = image.as_raw().clone();
+    for px in bgra.chunks_exact_mut(4) {
+        px.swap(0, 2);
+    }
+    let buffer = CryptographicBuffer::CreateFromByteArray(&bgra)?;
+    let bitmap = SoftwareBitmap::CreateCopyWithAlphaFromBuffer(
+        &buffer,
+        BitmapPixelFormat::Bgra8,
+        image.width() as i32,
+        image.height() as i32,
+        BitmapAlphaMode::Premultiplied,
+    )?;
+
+    let engine = OcrEngine::TryCreateFromUserProfileLanguages()?;
+    let result = engine.RecognizeAsync(&bitmap)?.get()?;
+
+    let mut lines = Vec::new();
+    for line in result.Lines()? {
+        let mut bbox: Option<BoundingBox> = None;
+        for word in line.Words()? {
+            let rect = word.BoundingRect()?;
+            let word_box = BoundingBox {
+                x: rect.X,
+                y: rect.Y,
+                width: rect.Width,
+                height: rect.Height,
+            };
+            bbox = Some(bbox.map_or(word_box, |b| b.union(word_box)));
+        }
+        lines.push(OcrLine {
+            text: line.Text()?.to_string(),
+            bbox: bbox.unwrap_or(BoundingBox {
+                x: 0.0,
+                y: 0.0,
+                width: 0.0,
+                height: 0.0,
+            }),
+            confidence: None,
+        });
+    }
+    Ok(lines)
+}
+
+pub fn recognize(image: &RgbaImage) -> Result<Vec<OcrLine>, String> {
+    recognize_inner(image).map_err(|e| e.message().to_string())
+}
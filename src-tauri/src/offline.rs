@@ -0,0 +1,260 @@
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Serialize;
+use serde_json::json;
+use tauri::{Emitter, Manager};
+use tokio::sync::{watch, Notify};
+
+use crate::db;
+use crate::error::AppError;
+use crate::llm::ProviderConfig;
+use crate::{http, providers, settings};
+
+/// How often the internet is checked for while it can be reached, and while it can't.
+const ONLINE_INTERVAL: Duration = Duration::from_secs(30);
+const OFFLINE_INTERVAL: Duration = Duration::from_secs(5);
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Whether the internet can be reached, and the requests waiting for it.
+pub struct NetworkMonitor {
+    reachable: AtomicBool,
+    checked_at: AtomicI64,
+    /// Whether the app is offline, by choice or not.
+    offline: watch::Sender<bool>,
+    /// Wakes the monitor for a check now rather than at the next interval.
+    wake: Notify,
+    queue: Mutex<Vec<QueuedOperation>>,
+}
+
+impl Default for NetworkMonitor {
+    fn default() -> Self {
+        Self {
+            reachable: AtomicBool::new(true),
+            checked_at: AtomicI64::new(0),
+            offline: watch::channel(false).0,
+            wake: Notify::new(),
+            queue: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueuedOperation {
+    pub request_id: String,
+    /// What is waiting, such as "Chat with OpenAI".
+    pub what: String,
+    pub queued_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkStatus {
+    pub offline: bool,
+    /// Offline because the user turned on `offline.enabled`.
+    pub forced: bool,
+    /// Whether the last check reached the internet.
+    pub reachable: bool,
+    /// When that was, in Unix milliseconds. 0 before the first check.
+    pub checked_at: i64,
+    pub queued: Vec<QueuedOperation>,
+}
+
+pub fn status(app: &tauri::AppHandle) -> NetworkStatus {
+    let monitor = app.state::<NetworkMonitor>();
+    let forced = settings::current(app).offline.enabled;
+    let reachable = monitor.reachable.load(Ordering::SeqCst);
+    let queued = monitor.queue.lock().unwrap().clone();
+    NetworkStatus {
+        offline: forced || !reachable,
+        forced,
+        reachable,
+        checked_at: monitor.checked_at.load(Ordering::SeqCst),
+        queued,
+    }
+}
+
+pub fn is_offline(app: &tauri::AppHandle) -> bool {
+    *app.state::<NetworkMonitor>().offline.borrow()
+}
+
+fn emit_status(app: &tauri::AppHandle) {
+    let _ = app.emit("network-status-changed", status(app));
+}
+
+/// Bring `offline` up to date, waking whatever waits on it and emitting
+/// `network-status-changed` if it changed.
+fn publish(app: &tauri::AppHandle) {
+    let offline = status(app).offline;
+    let changed = app
+        .state::<NetworkMonitor>()
+        .offline
+        .send_if_modified(|current| std::mem::replace(current, offline) != offline);
+    if changed {
+        tracing::info!("{}", if offline { "Offline" } else { "Back online" });
+        emit_status(app);
+    }
+}
+
+/// Check again now, such as after a request failed to connect.
+pub fn recheck(app: &tauri::AppHandle) {
+    if let Some(monitor) = app.try_state::<NetworkMonitor>() {
+        monitor.wake.notify_one();
+    }
+}
+
+/// Any answer at all from `offline.checkUrl` means the internet is there.
+async fn reachable(app: &tauri::AppHandle) -> bool {
+    let url = settings::current(app).offline.check_url;
+    let Ok(client) = http::client(app) else {
+        return false;
+    };
+    client.head(url).timeout(CHECK_TIMEOUT).send().await.is_ok()
+}
+
+/// Check for the internet for as long as the app runs. Nothing is sent while the user
+/// has chosen to be offline.
+pub fn start(app: &tauri::AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            if !settings::current(&app).offline.enabled {
+                let reachable = reachable(&app).await;
+                let monitor = app.state::<NetworkMonitor>();
+                monitor.reachable.store(reachable, Ordering::SeqCst);
+                monitor.checked_at.store(db::now_ms(), Ordering::SeqCst);
+            }
+            publish(&app);
+            let interval = if is_offline(&app) {
+                OFFLINE_INTERVAL
+            } else {
+                ONLINE_INTERVAL
+            };
+            let monitor = app.state::<NetworkMonitor>();
+            let _ = tokio::time::timeout(interval, monitor.wake.notified()).await;
+        }
+    });
+}
+
+/// Takes a request off the queue when it stops waiting, whether the network came back
+/// or it was cancelled.
+struct Queued {
+    app: tauri::AppHandle,
+    request_id: String,
+}
+
+impl Queued {
+    fn new(app: &tauri::AppHandle, request_id: &str, what: &str) -> Self {
+        tracing::info!("Offline, queueing {what} ({request_id})");
+        app.state::<NetworkMonitor>()
+            .queue
+            .lock()
+            .unwrap()
+            .push(QueuedOperation {
+                request_id: request_id.to_string(),
+                what: what.to_string(),
+                queued_at: db::now_ms(),
+            });
+        emit_status(app);
+        Self {
+            app: app.clone(),
+            request_id: request_id.to_string(),
+        }
+    }
+}
+
+impl Drop for Queued {
+    fn drop(&mut self) {
+        self.app
+            .state::<NetworkMonitor>()
+            .queue
+            .lock()
+            .unwrap()
+            .retain(|queued| queued.request_id != self.request_id);
+        emit_status(&self.app);
+    }
+}
+
+/// While offline, wait until the network is back, listed in `network-status-changed`
+/// as queued. Returns at once while online.
+pub(crate) async fn wait_until_online(
+    app: tauri::AppHandle,
+    request_id: String,
+    what: String,
+) -> Result<(), AppError> {
+    let mut offline = app.state::<NetworkMonitor>().offline.subscribe();
+    if !*offline.borrow_and_update() {
+        return Ok(());
+    }
+    let _queued = Queued::new(&app, &request_id, &what);
+    offline
+        .wait_for(|offline| !*offline)
+        .await
+        .map(|_| ())
+        .map_err(|_| AppError::Cancelled)
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FallbackPayload<'a> {
+    from_provider: &'a str,
+    from_model: &'a str,
+    provider: &'a str,
+    model: &'a str,
+}
+
+/// Where a chat goes.
+pub(crate) enum Route {
+    /// Send it now.
+    Send(ProviderConfig),
+    /// It needs a cloud provider and there is no local model to use instead, so it has
+    /// to wait for the network.
+    Queue(ProviderConfig),
+}
+
+/// Route a chat for `config`: as it is while online or to a local provider, and while
+/// offline to the model in `offline.fallbackModel`, emitting `offline-fallback`.
+pub(crate) fn route(app: &tauri::AppHandle, config: ProviderConfig) -> Result<Route, AppError> {
+    if !is_offline(app) {
+        return Ok(Route::Send(config));
+    }
+    let provider = providers::resolve(app, &config.provider, config.base_url.as_deref())?;
+    if !provider.info().requires_api_key {
+        return Ok(Route::Send(config));
+    }
+    let offline = settings::current(app).offline;
+    let Some(model) = offline.fallback_model else {
+        return Ok(Route::Queue(config));
+    };
+    let _ = app.emit(
+        "offline-fallback",
+        FallbackPayload {
+            from_provider: &config.provider,
+            from_model: &config.model,
+            provider: &offline.fallback_provider,
+            model: &model,
+        },
+    );
+    Ok(Route::Send(ProviderConfig {
+        provider: offline.fallback_provider,
+        model,
+        base_url: offline.fallback_base_url,
+        timeout_ms: config.timeout_ms,
+    }))
+}
+
+#[tauri::command]
+pub fn get_network_status(app: tauri::AppHandle) -> NetworkStatus {
+    status(&app)
+}
+
+/// Turn the explicit offline mode on or off. Turning it off checks the network at once.
+#[tauri::command]
+pub fn set_offline_mode(app: tauri::AppHandle, enabled: bool) -> Result<NetworkStatus, AppError> {
+    settings::update(&app, &json!({ "offline": { "enabled": enabled } }))?;
+    publish(&app);
+    recheck(&app);
+    Ok(status(&app))
+}
@@ -0,0 +1,232 @@
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::Emitter;
+
+use crate::error::AppError;
+use crate::imaging::ImageLimits;
+use crate::jobs::{self, JobSpec, Progress};
+use crate::latency::RequestTimer;
+use crate::llm::{self, ChatMessage, LineBuffer};
+use crate::usage::{self, TokenUsage};
+use crate::{http, requests, tokens, workspaces};
+
+const DEFAULT_BASE_URL: &str = "http://localhost:11434";
+
+fn resolve_base(base_url: Option<&str>) -> &str {
+    base_url.unwrap_or(DEFAULT_BASE_URL).trim_end_matches('/')
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OllamaModel {
+    pub name: String,
+    #[serde(default)]
+    pub size: u64,
+    #[serde(default, alias = "modified_at")]
+    pub modified_at: Option<String>,
+    #[serde(default)]
+    pub details: Option<Value>,
+}
+
+#[derive(Deserialize)]
+struct TagsResponse {
+    models: Vec<OllamaModel>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PullProgressPayload<'a> {
+    model: &'a str,
+    status: &'a str,
+    completed: Option<u64>,
+    total: Option<u64>,
+}
+
+/// Ollama reports failures as `{"error": "..."}`, both for whole responses and stream lines.
+fn check_error(value: &Value) -> Result<(), String> {
+    match value.get("error").and_then(Value::as_str) {
+        Some(error) => Err(format!("Ollama: {error}")),
+        None => Ok(()),
+    }
+}
+
+async fn error_for_status(response: reqwest::Response) -> Result<reqwest::Response, String> {
+    if response.status().is_success() {
+        return Ok(response);
+    }
+    let status = response.status();
+    let body: Value = response.json().await.unwrap_or_default();
+    check_error(&body)?;
+    Err(format!("Ollama returned {status}"))
+}
+
+#[tauri::command]
+pub async fn ollama_list_models(
+    app: tauri::AppHandle,
+    base_url: Option<String>,
+) -> Result<Vec<OllamaModel>, AppError> {
+    let request =
+        http::client(&app)?.get(format!("{}/api/tags", resolve_base(base_url.as_deref())));
+    let response = http::send(&app, "ollama", None, request)
+        .await
+        .map_err(|e| format!("Could not reach Ollama: {e}"))?;
+    let tags: TagsResponse = error_for_status(response)
+        .await?
+        .json()
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(tags.models)
+}
+
+/// Download a model as a job, emitting `ollama-pull-progress` for every status line Ollama
+/// reports. Pulls have no timeout but can be stopped with `cancel_job(requestId)`.
+#[tauri::command]
+pub async fn ollama_pull_model(
+    app: tauri::AppHandle,
+    model: String,
+    base_url: Option<String>,
+    request_id: Option<String>,
+) -> Result<(), AppError> {
+    let task_app = app.clone();
+    let spec = JobSpec::PullOllamaModel {
+        model: model.clone(),
+        base_url: base_url.clone(),
+    };
+    jobs::run(&app, request_id, spec, move |progress| {
+        pull(task_app, model, base_url, progress)
+    })
+    .await
+}
+
+pub(crate) async fn pull(
+    app: tauri::AppHandle,
+    model: String,
+    base_url: Option<String>,
+    progress: Progress,
+) -> Result<(), String> {
+    let request = http::client(&app)?
+        .post(format!("{}/api/pull", resolve_base(base_url.as_deref())))
+        .json(&json!({ "model": model, "stream": true }));
+    let response = http::send(&app, "ollama", None, request)
+        .await
+        .map_err(|e| format!("Could not reach Ollama: {e}"))?;
+    let mut stream = error_for_status(response).await?.bytes_stream();
+
+    let mut lines = LineBuffer::default();
+    while let Some(chunk) = stream.next().await {
+        lines.push(&chunk.map_err(|e| e.to_string())?);
+        while let Some(line) = lines.next_line() {
+            let Ok(update) = serde_json::from_str::<Value>(&line) else {
+                continue;
+            };
+            check_error(&update)?;
+            let status = update.get("status").and_then(Value::as_str).unwrap_or("");
+            let completed = update.get("completed").and_then(Value::as_u64);
+            let total = update.get("total").and_then(Value::as_u64);
+            let _ = app.emit(
+                "ollama-pull-progress",
+                PullProgressPayload {
+                    model: &model,
+                    status,
+                    completed,
+                    total,
+                },
+            );
+            if let Some(completed) = completed {
+                progress.report(completed, total);
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn stream_chat(
+    app: &tauri::AppHandle,
+    request_id: &str,
+    base: &str,
+    model: &str,
+    messages: &[ChatMessage],
+) -> Result<String, String> {
+    let mut timer = RequestTimer::start(request_id, "ollama");
+    workspaces::guard_prompt(app, messages).map_err(|e| e.to_string())?;
+    let messages = tokens::fit(app, request_id, model, messages);
+    let images = llm::encode_images(app, ImageLimits::default(), &messages)
+        .await
+        .map_err(|e| e.to_string())?;
+    timer.prepared(app);
+    // The native API takes a message's images as bare base64 strings.
+    let body: Vec<Value> = messages
+        .iter()
+        .enumerate()
+        .map(|(i, m)| {
+            let mut message = json!({ "role": m.role, "content": m.content });
+            if let Some(images) = images.get(i).filter(|images| !images.is_empty()) {
+                message["images"] = images.iter().map(|image| image.base64.clone()).collect();
+            }
+            message
+        })
+        .collect();
+    let request = http::client(app)?
+        .post(format!("{base}/api/chat"))
+        .json(&json!({ "model": model, "messages": body, "stream": true }));
+    let response = http::send(app, "ollama", Some(request_id), request)
+        .await
+        .map_err(|e| format!("Could not reach Ollama: {e}"))?;
+    let mut stream = error_for_status(response).await?.bytes_stream();
+    timer.responded(app);
+
+    let mut lines = LineBuffer::default();
+    let mut content = String::new();
+    let mut reported = TokenUsage::default();
+    'stream: while let Some(chunk) = stream.next().await {
+        lines.push(&chunk.map_err(|e| e.to_string())?);
+        while let Some(line) = lines.next_line() {
+            let Ok(update) = serde_json::from_str::<Value>(&line) else {
+                continue;
+            };
+            check_error(&update)?;
+            if let Some(token) = update.pointer("/message/content").and_then(Value::as_str) {
+                if !token.is_empty() {
+                    timer.token(app);
+                    content.push_str(token);
+                    llm::emit_token(app, request_id, token);
+                }
+            }
+            if update.get("done").and_then(Value::as_bool) == Some(true) {
+                reported = TokenUsage {
+                    prompt_tokens: update.get("prompt_eval_count").and_then(Value::as_u64),
+                    completion_tokens: update.get("eval_count").and_then(Value::as_u64),
+                };
+                break 'stream;
+            }
+        }
+    }
+    usage::record_chat(
+        app,
+        request_id,
+        ("ollama", true),
+        model,
+        &messages,
+        &content,
+        reported,
+    );
+    timer.finished(app);
+    Ok(content)
+}
+
+/// Stream a chat against the local Ollama server. Shares the `chat-token` / `chat-done`
+/// events and `abort_completion` with `chat_completion`.
+#[tauri::command]
+pub fn ollama_chat(
+    app: tauri::AppHandle,
+    model: String,
+    messages: Vec<ChatMessage>,
+    base_url: Option<String>,
+) -> String {
+    let timeout = requests::default_timeout(&app);
+    llm::spawn_completion(&app, timeout, None, move |app, request_id| async move {
+        let base = resolve_base(base_url.as_deref()).to_string();
+        stream_chat(&app, &request_id, &base, &model, &messages).await
+    })
+}
@@ -0,0 +1,428 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tauri::{
+    Emitter, Listener, Manager, Monitor, PhysicalPosition, PhysicalSize, WebviewWindow, Window,
+    WindowEvent,
+};
+
+use crate::error::AppError;
+use crate::screenshot::{self, Region};
+use crate::settings::{self, OverlayPosition};
+use crate::window_manager::{self, AppWindow};
+use crate::{accessibility, dock, layer_shell, selection, theme, tray};
+
+/// A monitor as far as sizing the overlay goes: its name, work area and scale factor.
+type MonitorFit = (Option<String>, PhysicalSize<u32>, f64);
+
+/// Backend-owned overlay modes, so hotkeys, the tray and the webview agree on them.
+#[derive(Default)]
+pub struct OverlayState {
+    click_through: AtomicBool,
+    pinned: AtomicBool,
+    dialog_open: AtomicBool,
+    /// Bumped whenever a pending auto-hide should be abandoned.
+    hide_generation: AtomicU64,
+    hide_pending: AtomicBool,
+    /// Bumped whenever the overlay is shown or hidden, so only the latest hide unloads it.
+    unload_generation: AtomicU64,
+    /// The monitor the overlay was last sized for.
+    fitted_to: Mutex<Option<MonitorFit>>,
+}
+
+pub fn click_through(app: &tauri::AppHandle) -> bool {
+    app.state::<OverlayState>()
+        .click_through
+        .load(Ordering::SeqCst)
+}
+
+/// Let mouse input pass through the overlay to whatever is underneath. The overlay stays
+/// visible but can't be clicked, so only the hotkey or the tray can turn this back off.
+pub fn set_click_through(app: &tauri::AppHandle, enabled: bool) -> Result<(), AppError> {
+    let window = window_manager::get_or_create(app, AppWindow::Overlay)?;
+    window
+        .set_ignore_cursor_events(enabled)
+        .map_err(|e| e.to_string())?;
+    app.state::<OverlayState>()
+        .click_through
+        .store(enabled, Ordering::SeqCst);
+    tray::sync_click_through(app, enabled);
+    let _ = app.emit("overlay-click-through-changed", enabled);
+    Ok(())
+}
+
+// Async, like every command that may build the overlay, so that never happens on the
+// main thread.
+#[tauri::command]
+pub async fn set_overlay_click_through(
+    app: tauri::AppHandle,
+    enabled: bool,
+) -> Result<(), AppError> {
+    set_click_through(&app, enabled)
+}
+
+#[tauri::command]
+pub fn get_overlay_click_through(app: tauri::AppHandle) -> bool {
+    click_through(&app)
+}
+
+pub fn pinned(app: &tauri::AppHandle) -> bool {
+    app.state::<OverlayState>().pinned.load(Ordering::SeqCst)
+}
+
+/// A pinned overlay is always on top and stays up when it loses focus. Unpinning returns
+/// always-on-top to the user's setting.
+pub fn set_pinned(app: &tauri::AppHandle, pinned: bool) -> Result<(), AppError> {
+    let window = window_manager::get_or_create(app, AppWindow::Overlay)?;
+    let always_on_top = pinned || settings::current(app).overlay.always_on_top;
+    window
+        .set_always_on_top(always_on_top)
+        .map_err(|e| e.to_string())?;
+    app.state::<OverlayState>()
+        .pinned
+        .store(pinned, Ordering::SeqCst);
+    if pinned {
+        cancel_auto_hide(app);
+    }
+    tray::sync_pinned(app, pinned);
+    accessibility::announce(
+        app,
+        if pinned {
+            "Overlay pinned"
+        } else {
+            "Overlay unpinned"
+        },
+    );
+    let _ = app.emit("overlay-pinned-changed", pinned);
+    Ok(())
+}
+
+/// Give a freshly built overlay the click-through and pinned modes the backend holds, so
+/// one rebuilt after it was unloaded or closed agrees with the tray and the webview.
+pub fn apply_modes(app: &tauri::AppHandle, window: &WebviewWindow) -> Result<(), AppError> {
+    window
+        .set_ignore_cursor_events(click_through(app))
+        .map_err(|e| e.to_string())?;
+    let always_on_top = pinned(app) || settings::current(app).overlay.always_on_top;
+    window
+        .set_always_on_top(always_on_top)
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn pin_overlay(app: tauri::AppHandle, pinned: bool) -> Result<(), AppError> {
+    set_pinned(&app, pinned)
+}
+
+#[tauri::command]
+pub fn is_overlay_pinned(app: tauri::AppHandle) -> bool {
+    pinned(&app)
+}
+
+/// Abandon a scheduled auto-hide, returning whether one was pending.
+pub fn cancel_auto_hide(app: &tauri::AppHandle) -> bool {
+    let state = app.state::<OverlayState>();
+    state.hide_generation.fetch_add(1, Ordering::SeqCst);
+    state.hide_pending.swap(false, Ordering::SeqCst)
+}
+
+fn schedule_auto_hide(app: &tauri::AppHandle) {
+    let state = app.state::<OverlayState>();
+    let overlay = settings::current(app).overlay;
+    if !overlay.auto_hide
+        || state.pinned.load(Ordering::SeqCst)
+        || dock::is_docked(app)
+        || state.dialog_open.load(Ordering::SeqCst)
+        || state.click_through.load(Ordering::SeqCst)
+    {
+        return;
+    }
+    let generation = state.hide_generation.fetch_add(1, Ordering::SeqCst) + 1;
+    state.hide_pending.store(true, Ordering::SeqCst);
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(overlay.auto_hide_delay_ms)).await;
+        let state = app.state::<OverlayState>();
+        if state.hide_generation.load(Ordering::SeqCst) != generation {
+            return;
+        }
+        state.hide_pending.store(false, Ordering::SeqCst);
+        // Focus may have come back without an event reaching us (e.g. during a drag).
+        if !window_manager::is_focused(&app, AppWindow::Overlay) {
+            let _ = window_manager::hide_overlay(&app);
+        }
+    });
+}
+
+/// The overlay was shown or hidden. Once it has stayed hidden for
+/// `overlay.unloadAfterMinutes` its webview is destroyed, and the next summon builds it
+/// again; the overlay keeps its draft in `drafts` to pick up after that.
+pub fn on_visibility(app: &tauri::AppHandle, visible: bool) {
+    let state = app.state::<OverlayState>();
+    let generation = state.unload_generation.fetch_add(1, Ordering::SeqCst) + 1;
+    let Some(minutes) = settings::current(app).overlay.unload_after_minutes else {
+        return;
+    };
+    if visible {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(Duration::from_secs(u64::from(minutes) * 60)).await;
+        let state = app.state::<OverlayState>();
+        if state.unload_generation.load(Ordering::SeqCst) != generation
+            || state.pinned.load(Ordering::SeqCst)
+            || state.click_through.load(Ordering::SeqCst)
+            || state.dialog_open.load(Ordering::SeqCst)
+            || dock::is_docked(&app)
+            || window_manager::is_visible(&app, AppWindow::Overlay)
+        {
+            return;
+        }
+        if let Some(window) = window_manager::get(&app, AppWindow::Overlay) {
+            tracing::info!("Unloading the overlay after {minutes} minutes hidden");
+            if let Err(error) = window.destroy() {
+                tracing::warn!("Could not unload the overlay: {error}");
+            }
+        }
+    });
+}
+
+/// Size the overlay for `monitor` as `overlay.size` says, unless it is docked. Without
+/// `force`, only when it was last sized for another monitor or scale factor, so a size
+/// the user dragged it to lasts until it moves.
+pub fn fit_to(window: &WebviewWindow, monitor: &Monitor, force: bool) -> Result<(), String> {
+    let app = window.app_handle();
+    let Some(size) = settings::current(app).overlay.size else {
+        return Ok(());
+    };
+    if dock::is_docked(app) {
+        return Ok(());
+    }
+    let area = monitor.work_area().size;
+    let scale = monitor.scale_factor();
+    let key = (monitor.name().cloned(), area, scale);
+    {
+        let state = app.state::<OverlayState>();
+        let mut fitted = state.fitted_to.lock().unwrap();
+        if !force && fitted.as_ref() == Some(&key) {
+            return Ok(());
+        }
+        *fitted = Some(key);
+    }
+    let span = |available: u32, percent: f64, min: u32, max: u32| {
+        let available = f64::from(available);
+        let (min, max) = (f64::from(min) * scale, f64::from(max) * scale);
+        (available * percent / 100.0)
+            .clamp(min, max)
+            .min(available)
+            .round() as u32
+    };
+    let target = PhysicalSize::new(
+        span(
+            area.width,
+            size.width_percent,
+            size.min_width,
+            size.max_width,
+        ),
+        span(
+            area.height,
+            size.height_percent,
+            size.min_height,
+            size.max_height,
+        ),
+    );
+    if window.inner_size().map_err(|e| e.to_string())? == target {
+        return Ok(());
+    }
+    window.set_size(target).map_err(|e| e.to_string())
+}
+
+/// Fit the overlay to the monitor it is on now.
+fn refit(app: &tauri::AppHandle, force: bool) {
+    let Some(window) = window_manager::get(app, AppWindow::Overlay) else {
+        return;
+    };
+    let Ok(Some(monitor)) = window.current_monitor() else {
+        return;
+    };
+    if let Err(error) = fit_to(&window, &monitor, force) {
+        tracing::warn!("Could not size the overlay: {error}");
+    }
+}
+
+/// Keep an unpinned overlay on top or not as `overlay.alwaysOnTop` says.
+fn apply_always_on_top(app: &tauri::AppHandle) {
+    let Some(window) = window_manager::get(app, AppWindow::Overlay) else {
+        return;
+    };
+    if pinned(app) {
+        return;
+    }
+    let always_on_top = settings::current(app).overlay.always_on_top;
+    if let Err(error) = window.set_always_on_top(always_on_top) {
+        tracing::warn!("Could not change whether the overlay stays on top: {error}");
+    }
+}
+
+/// Refit the overlay whenever `overlay.size` changes, and follow `overlay.alwaysOnTop`.
+pub fn init(app: &tauri::AppHandle) {
+    let overlay = settings::current(app).overlay;
+    let applied = Mutex::new((overlay.size, overlay.always_on_top));
+    let handle = app.clone();
+    app.listen("settings-changed", move |_| {
+        let overlay = settings::current(&handle).overlay;
+        let mut applied = applied.lock().unwrap();
+        if applied.0 != overlay.size {
+            applied.0 = overlay.size;
+            refit(&handle, true);
+        }
+        if applied.1 != overlay.always_on_top {
+            applied.1 = overlay.always_on_top;
+            apply_always_on_top(&handle);
+        }
+    });
+}
+
+pub fn on_window_event(window: &Window, event: &WindowEvent) {
+    if window.label() != AppWindow::Overlay.label() {
+        return;
+    }
+    match event {
+        // Click-through and pinning outlive the window, and `apply_modes` gives them to
+        // the next one; what belonged to this one goes with it.
+        WindowEvent::Destroyed => {
+            let app = window.app_handle();
+            cancel_auto_hide(app);
+            dock::forget(app);
+            let state = app.state::<OverlayState>();
+            state.dialog_open.store(false, Ordering::SeqCst);
+            *state.fitted_to.lock().unwrap() = None;
+        }
+        WindowEvent::ThemeChanged(_) => theme::refresh(window.app_handle()),
+        // Dragged onto another monitor, or this one changed its scale factor.
+        WindowEvent::Moved(_) | WindowEvent::ScaleFactorChanged { .. } => {
+            refit(window.app_handle(), false);
+        }
+        WindowEvent::Focused(true) => {
+            cancel_auto_hide(window.app_handle());
+        }
+        WindowEvent::Focused(false) if window.is_visible().unwrap_or(false) => {
+            schedule_auto_hide(window.app_handle());
+        }
+        _ => {}
+    }
+}
+
+/// Tell the backend a dialog owned by the overlay is open, so the focus it takes
+/// doesn't hide the overlay.
+#[tauri::command]
+pub fn set_overlay_dialog_open(app: tauri::AppHandle, open: bool) {
+    app.state::<OverlayState>()
+        .dialog_open
+        .store(open, Ordering::SeqCst);
+    if open {
+        cancel_auto_hide(&app);
+    }
+}
+
+pub fn monitor_containing(app: &tauri::AppHandle, x: i32, y: i32) -> Option<Monitor> {
+    app.available_monitors()
+        .ok()?
+        .into_iter()
+        .find(|m| Region::of_monitor(m).contains(x, y))
+}
+
+fn target_monitor(app: &tauri::AppHandle, strategy: OverlayPosition) -> Option<Monitor> {
+    match strategy {
+        OverlayPosition::Remember => None,
+        OverlayPosition::CursorMonitorCenter => screenshot::cursor_monitor(app).ok(),
+        OverlayPosition::ActiveWindowMonitor => screenshot::foreground_window_bounds()
+            .ok()
+            .and_then(|w| {
+                monitor_containing(app, w.x + w.width as i32 / 2, w.y + w.height as i32 / 2)
+            })
+            // Nothing else is open, or we can't see other windows (Wayland).
+            .or_else(|| screenshot::cursor_monitor(app).ok()),
+        OverlayPosition::Primary => app.primary_monitor().ok().flatten(),
+    }
+}
+
+/// Move the overlay to where the positioning setting says it should appear. Called just
+/// before it is shown; `remember` leaves the restored position alone.
+pub fn place(window: &WebviewWindow) -> Result<(), String> {
+    let app = window.app_handle();
+    let strategy = settings::current(app).overlay.position;
+    let Some(monitor) = target_monitor(app, strategy) else {
+        if let Some(monitor) = window.current_monitor().map_err(|e| e.to_string())? {
+            fit_to(window, &monitor, false)?;
+        }
+        return Ok(());
+    };
+    fit_to(window, &monitor, false)?;
+    let size = window.outer_size().map_err(|e| e.to_string())?;
+    let area = monitor.work_area();
+    let x = area.position.x + (area.size.width as i32 - size.width as i32) / 2;
+    let y = area.position.y + (area.size.height as i32 - size.height as i32) / 2;
+    let position = PhysicalPosition::new(x.max(area.position.x), y.max(area.position.y));
+    layer_shell::set_position(window, position, monitor.scale_factor())
+}
+
+/// Put the overlay just below `anchor`, left-aligned with it, or above it when there is no
+/// room below; always kept inside the work area of the anchor's monitor.
+pub fn place_near(window: &WebviewWindow, anchor: Region) -> Result<(), String> {
+    const GAP: i32 = 8;
+    let app = window.app_handle();
+    let monitor = monitor_containing(app, anchor.x, anchor.y)
+        .or_else(|| screenshot::cursor_monitor(app).ok())
+        .ok_or_else(|| "No monitor found".to_string())?;
+    fit_to(window, &monitor, false)?;
+    let area = monitor.work_area();
+    let (left, top) = (area.position.x, area.position.y);
+    let right = left + area.size.width as i32;
+    let bottom = top + area.size.height as i32;
+
+    let size = window.outer_size().map_err(|e| e.to_string())?;
+    let (width, height) = (size.width as i32, size.height as i32);
+    let below = anchor.y + anchor.height as i32 + GAP;
+    let y = if below + height <= bottom {
+        below
+    } else {
+        anchor.y - GAP - height
+    };
+    let x = anchor.x.clamp(left, (right - width).max(left));
+    let y = y.clamp(top, (bottom - height).max(top));
+    layer_shell::set_position(window, PhysicalPosition::new(x, y), monitor.scale_factor())
+}
+
+/// Where to anchor the overlay next to the user's selection: the selection or caret
+/// bounds, or the mouse pointer when the app doesn't expose them.
+pub fn selection_anchor(app: &tauri::AppHandle) -> Option<Region> {
+    selection::selection_bounds(app).or_else(|| {
+        let cursor = app.cursor_position().ok()?;
+        Some(Region {
+            x: cursor.x as i32,
+            y: cursor.y as i32,
+            width: 0,
+            height: 0,
+        })
+    })
+}
+
+/// Show the overlay as a popover next to the foreground app's selection or caret, falling
+/// back to the mouse pointer when the app doesn't expose its caret. Returns the selected
+/// text, which is also cached for `get_selected_text`.
+#[tauri::command]
+pub async fn show_overlay_at_selection(app: tauri::AppHandle) -> Result<Option<String>, AppError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let anchor = selection_anchor(&app);
+        let text = selection::capture(&app);
+        window_manager::show_overlay_nonactivating(&app, anchor)?;
+        Ok(text)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
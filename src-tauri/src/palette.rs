@@ -0,0 +1,292 @@
+use chrono::{DateTime, Local};
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use serde::Serialize;
+use serde_json::Value;
+use tauri::Manager;
+
+use crate::db::Database;
+use crate::error::AppError;
+use crate::{plugins, quick_actions, settings, templates};
+
+const DEFAULT_LIMIT: usize = 50;
+/// Conversations offered when nothing has been typed yet.
+const RECENT_CONVERSATIONS: u32 = 10;
+/// Upper bound on the history scanned per query, newest first.
+const MAX_CONVERSATIONS: u32 = 20_000;
+const MAX_SUBTITLE_CHARS: usize = 60;
+
+/// Things the palette can run that aren't stored anywhere, as id, title and extra words
+/// to match on. The overlay decides what each id does.
+const ACTIONS: &[(&str, &str, &str)] = &[
+    ("new-conversation", "New conversation", "chat start"),
+    (
+        "pick-screen-region",
+        "Capture screen region",
+        "screenshot snip ocr",
+    ),
+    ("capture-screen", "Capture full screen", "screenshot"),
+    (
+        "start-recording",
+        "Start voice prompt",
+        "dictate microphone speech",
+    ),
+    (
+        "toggle-click-through",
+        "Toggle click-through",
+        "mouse passthrough",
+    ),
+    ("generate-image", "Generate image", "picture art draw"),
+    ("open-gallery", "Open image gallery", "pictures generated"),
+    (
+        "import-history",
+        "Import ChatGPT or Claude history",
+        "export",
+    ),
+    ("export-history", "Export history", "markdown json"),
+    ("create-backup", "Back up data", "export save"),
+    ("restore-backup", "Restore a backup", "import"),
+    (
+        "window-left-half",
+        "Move window to left half",
+        "tile snap arrange",
+    ),
+    (
+        "window-right-half",
+        "Move window to right half",
+        "tile snap arrange",
+    ),
+    ("window-maximize", "Maximize window", "fill zoom arrange"),
+    (
+        "window-next-monitor",
+        "Move window to next monitor",
+        "display screen arrange",
+    ),
+    ("open-settings", "Open settings", "preferences options"),
+    ("check-for-updates", "Check for updates", "upgrade version"),
+];
+
+/// Also the order items of equal score are listed in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PaletteKind {
+    Action,
+    QuickAction,
+    PluginAction,
+    Template,
+    Conversation,
+    Setting,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaletteItem {
+    pub kind: PaletteKind,
+    /// The action, quick action, template or conversation id, `plugin-id:action-id` for
+    /// a plugin's action, or a settings key such as `overlay.opacity`.
+    pub id: String,
+    pub title: String,
+    pub subtitle: Option<String>,
+    pub score: i64,
+    /// Character positions in `title` that matched, for highlighting.
+    pub matches: Vec<usize>,
+}
+
+struct Candidate {
+    kind: PaletteKind,
+    id: String,
+    title: String,
+    subtitle: Option<String>,
+    /// Matched as well as the title, at half weight.
+    keywords: String,
+}
+
+impl Candidate {
+    fn new(kind: PaletteKind, id: impl Into<String>, title: impl Into<String>) -> Self {
+        Self {
+            kind,
+            id: id.into(),
+            title: title.into(),
+            subtitle: None,
+            keywords: String::new(),
+        }
+    }
+
+    fn into_item(self, score: i64, matches: Vec<usize>) -> PaletteItem {
+        PaletteItem {
+            kind: self.kind,
+            id: self.id,
+            title: self.title,
+            subtitle: self.subtitle,
+            score,
+            matches,
+        }
+    }
+}
+
+fn truncate(text: &str) -> String {
+    let mut chars = text.chars();
+    let mut out: String = chars.by_ref().take(MAX_SUBTITLE_CHARS).collect();
+    if chars.next().is_some() {
+        out.push('…');
+    }
+    out
+}
+
+/// `edgeDwellMs` as "Edge dwell ms".
+fn humanize(key: &str) -> String {
+    let mut out = String::with_capacity(key.len() + 4);
+    for (index, c) in key.chars().enumerate() {
+        if index == 0 {
+            out.extend(c.to_uppercase());
+        } else if c.is_uppercase() {
+            out.push(' ');
+            out.extend(c.to_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+fn value_preview(value: &Value) -> String {
+    match value {
+        Value::Null => "Not set".to_string(),
+        Value::String(text) => truncate(text),
+        Value::Array(items) if items.is_empty() => "None".to_string(),
+        Value::Array(items) => format!("{} items", items.len()),
+        other => truncate(&other.to_string()),
+    }
+}
+
+/// One entry per setting, keyed by its dotted path.
+fn setting_candidates(value: &Value, path: &str, title: &str, out: &mut Vec<Candidate>) {
+    let Value::Object(fields) = value else {
+        let mut candidate = Candidate::new(PaletteKind::Setting, path, title);
+        candidate.subtitle = Some(value_preview(value));
+        out.push(candidate);
+        return;
+    };
+    for (key, field) in fields {
+        if path.is_empty() && key == "version" {
+            continue;
+        }
+        let (path, title) = if path.is_empty() {
+            (key.clone(), humanize(key))
+        } else {
+            (
+                format!("{path}.{key}"),
+                format!("{title} › {}", humanize(key)),
+            )
+        };
+        setting_candidates(field, &path, &title, out);
+    }
+}
+
+fn conversation_candidates(app: &tauri::AppHandle, limit: u32) -> Result<Vec<Candidate>, String> {
+    app.state::<Database>().with(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, title, updated_at FROM conversations ORDER BY updated_at DESC LIMIT ?1",
+        )?;
+        let rows = stmt.query_map([limit], |row| {
+            let id: String = row.get(0)?;
+            let title: String = row.get(1)?;
+            let mut candidate = Candidate::new(PaletteKind::Conversation, id, title);
+            candidate.subtitle = DateTime::from_timestamp_millis(row.get(2)?)
+                .map(|time| time.with_timezone(&Local).format("%Y-%m-%d").to_string());
+            Ok(candidate)
+        })?;
+        rows.collect()
+    })
+}
+
+/// Everything but the conversations, which come from the database.
+fn static_candidates(app: &tauri::AppHandle) -> Vec<Candidate> {
+    let mut candidates: Vec<Candidate> = ACTIONS
+        .iter()
+        .map(|(id, title, keywords)| {
+            let mut candidate = Candidate::new(PaletteKind::Action, *id, *title);
+            candidate.keywords = keywords.to_string();
+            candidate
+        })
+        .collect();
+    for action in quick_actions::list_quick_actions(app.clone()) {
+        let mut candidate = Candidate::new(PaletteKind::QuickAction, action.id, action.name);
+        candidate.subtitle = action.shortcut;
+        candidates.push(candidate);
+    }
+    for (plugin_id, action) in plugins::palette_actions(app) {
+        let id = format!("{plugin_id}:{}", action.id);
+        let mut candidate = Candidate::new(PaletteKind::PluginAction, id, action.title);
+        candidate.subtitle = Some(plugin_id);
+        candidate.keywords = action.keywords;
+        candidates.push(candidate);
+    }
+    for template in templates::list_templates(app.clone()) {
+        let mut candidate = Candidate::new(PaletteKind::Template, template.id, template.name);
+        candidate.subtitle = Some(truncate(template.body.trim()));
+        candidates.push(candidate);
+    }
+    candidates
+}
+
+fn score(
+    matcher: &SkimMatcherV2,
+    candidate: &Candidate,
+    pattern: &str,
+) -> Option<(i64, Vec<usize>)> {
+    let title = matcher.fuzzy_indices(&candidate.title, pattern);
+    let keywords = (!candidate.keywords.is_empty())
+        .then(|| matcher.fuzzy_match(&candidate.keywords, pattern))
+        .flatten()
+        .map(|score| (score / 2, Vec::new()));
+    match (title, keywords) {
+        (Some(title), Some(keywords)) if keywords.0 > title.0 => Some(keywords),
+        (Some(title), _) => Some(title),
+        (None, keywords) => keywords,
+    }
+}
+
+/// Search actions, quick actions, templates, conversations and settings for the overlay's
+/// command palette, best match first. With no input it lists the actions, quick actions,
+/// plugin actions, templates and the most recent conversations instead.
+fn query(app: &tauri::AppHandle, input: &str, limit: usize) -> Result<Vec<PaletteItem>, String> {
+    let pattern = input.trim();
+    let mut candidates = static_candidates(app);
+    if pattern.is_empty() {
+        candidates.extend(conversation_candidates(app, RECENT_CONVERSATIONS)?);
+        return Ok(candidates
+            .into_iter()
+            .take(limit)
+            .map(|candidate| candidate.into_item(0, Vec::new()))
+            .collect());
+    }
+
+    candidates.extend(conversation_candidates(app, MAX_CONVERSATIONS)?);
+    let settings = serde_json::to_value(settings::current(app)).map_err(|e| e.to_string())?;
+    setting_candidates(&settings, "", "", &mut candidates);
+    let matcher = SkimMatcherV2::default().smart_case();
+    let mut items: Vec<PaletteItem> = candidates
+        .into_iter()
+        .filter_map(|candidate| {
+            let (score, matches) = score(&matcher, &candidate, pattern)?;
+            Some(candidate.into_item(score, matches))
+        })
+        .collect();
+    // Stable, so conversations of equal score stay newest first.
+    items.sort_by(|a, b| b.score.cmp(&a.score).then(a.kind.cmp(&b.kind)));
+    items.truncate(limit);
+    Ok(items)
+}
+
+#[tauri::command]
+pub async fn query_commands(
+    app: tauri::AppHandle,
+    input: String,
+    limit: Option<usize>,
+) -> Result<Vec<PaletteItem>, AppError> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(500);
+    tauri::async_runtime::spawn_blocking(move || query(&app, &input, limit))
+        .await?
+        .map_err(AppError::from)
+}
@@ -0,0 +1,110 @@
+use tauri::WebviewWindow;
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::ffi::CStr;
+    use std::sync::OnceLock;
+
+    use objc2::runtime::{AnyClass, AnyObject, Bool, ClassBuilder, Sel};
+    use objc2::{sel, ClassType};
+    use objc2_app_kit::{NSPanel, NSWindow, NSWindowCollectionBehavior, NSWindowStyleMask};
+    use tauri::WebviewWindow;
+
+    extern "C-unwind" fn yes(_: &AnyObject, _: Sel) -> Bool {
+        Bool::YES
+    }
+
+    extern "C-unwind" fn no(_: &AnyObject, _: Sel) -> Bool {
+        Bool::NO
+    }
+
+    /// An `NSPanel` that takes keyboard focus even though it is borderless, which a plain
+    /// panel refuses, and never becomes the main window.
+    fn panel_class() -> &'static AnyClass {
+        static CLASS: OnceLock<&'static AnyClass> = OnceLock::new();
+        CLASS.get_or_init(|| {
+            let name = CStr::from_bytes_with_nul(b"AikeyaOverlayPanel\0").unwrap();
+            let mut builder = ClassBuilder::new(name, NSPanel::class())
+                .expect("the overlay panel class is only registered once");
+            // SAFETY: both methods match the signatures NSWindow declares for them.
+            unsafe {
+                builder.add_method(
+                    sel!(canBecomeKeyWindow),
+                    yes as extern "C-unwind" fn(_, _) -> _,
+                );
+                builder.add_method(
+                    sel!(canBecomeMainWindow),
+                    no as extern "C-unwind" fn(_, _) -> _,
+                );
+            }
+            builder.register()
+        })
+    }
+
+    /// Run `f` with the window's `NSWindow` on the main thread, where AppKit wants it.
+    fn on_main_thread(
+        window: &WebviewWindow,
+        f: impl FnOnce(&NSWindow) + Send + 'static,
+    ) -> Result<(), String> {
+        let target = window.clone();
+        window
+            .run_on_main_thread(move || match target.ns_window() {
+                // SAFETY: Tauri hands out the NSWindow behind the webview window, which
+                // lives as long as `target` does, and this closure runs on the main thread.
+                Ok(ns_window) => f(unsafe { &*ns_window.cast::<NSWindow>() }),
+                Err(error) => tracing::warn!("No NSWindow for {}: {error}", target.label()),
+            })
+            .map_err(|e| e.to_string())
+    }
+
+    pub fn make_panel(window: &WebviewWindow) -> Result<(), String> {
+        on_main_thread(window, |ns_window| {
+            let class = panel_class();
+            if ns_window.class() != class {
+                // SAFETY: the panel class derives from NSPanel, an NSWindow subclass that
+                // adds no instance variables, and only overrides two boolean getters.
+                unsafe { AnyObject::set_class(ns_window, class) };
+            }
+            ns_window.setStyleMask(ns_window.styleMask() | NSWindowStyleMask::NonactivatingPanel);
+            ns_window.setCollectionBehavior(
+                NSWindowCollectionBehavior::CanJoinAllSpaces
+                    | NSWindowCollectionBehavior::FullScreenAuxiliary,
+            );
+            ns_window.setHidesOnDeactivate(false);
+        })
+    }
+
+    pub fn show(window: &WebviewWindow) -> Result<(), String> {
+        on_main_thread(window, |ns_window| {
+            ns_window.orderFrontRegardless();
+            ns_window.makeKeyWindow();
+        })
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+mod platform {
+    use tauri::WebviewWindow;
+
+    pub fn make_panel(_window: &WebviewWindow) -> Result<(), String> {
+        Ok(())
+    }
+
+    pub fn show(window: &WebviewWindow) -> Result<(), String> {
+        window.show().map_err(|e| e.to_string())?;
+        window.set_focus().map_err(|e| e.to_string())
+    }
+}
+
+/// On macOS, turn the overlay into a non-activating panel: it can be shown above
+/// full-screen apps and on every Space, and typed into, while the app the user was in
+/// stays frontmost. Elsewhere windows already behave closely enough.
+pub fn make_panel(window: &WebviewWindow) -> Result<(), String> {
+    platform::make_panel(window)
+}
+
+/// Bring up and focus a window made with `make_panel` without activating the app, so
+/// the frontmost app keeps its Space and stays active. Elsewhere, a regular show.
+pub fn show_without_activating(window: &WebviewWindow) -> Result<(), String> {
+    platform::show(window)
+}
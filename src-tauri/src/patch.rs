@@ -0,0 +1,435 @@
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::capabilities;
+use crate::error::AppError;
+use crate::persist;
+
+/// Larger files are refused rather than patched.
+const MAX_FILE_BYTES: u64 = 5 * 1024 * 1024;
+
+#[derive(Debug)]
+enum Line {
+    Context(String),
+    Remove(String),
+    Add(String),
+}
+
+#[derive(Debug, Default)]
+struct Hunk {
+    /// 1-based, as in the `@@` header. Where to start looking; the hunk may have moved.
+    old_start: usize,
+    lines: Vec<Line>,
+    /// `\ No newline at end of file` after the old side's last line, or the new side's.
+    old_no_newline: bool,
+    new_no_newline: bool,
+}
+
+impl Hunk {
+    fn before(&self) -> Vec<&str> {
+        self.lines
+            .iter()
+            .filter_map(|line| match line {
+                Line::Context(text) | Line::Remove(text) => Some(text.as_str()),
+                Line::Add(_) => None,
+            })
+            .collect()
+    }
+
+    fn after(&self) -> Vec<String> {
+        self.lines
+            .iter()
+            .filter_map(|line| match line {
+                Line::Context(text) | Line::Add(text) => Some(text.clone()),
+                Line::Remove(_) => None,
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Default)]
+struct FilePatch {
+    /// `None` for `/dev/null`: created, or on the new side deleted.
+    old: Option<String>,
+    new: Option<String>,
+    hunks: Vec<Hunk>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileChange {
+    Modified,
+    Created,
+    Deleted,
+    Renamed,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PatchedFile {
+    /// Relative to the base folder.
+    pub path: String,
+    /// Where it was before a rename.
+    pub from: Option<String>,
+    pub change: FileChange,
+    pub hunks: usize,
+    pub added: usize,
+    pub removed: usize,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PatchReport {
+    pub files: Vec<PatchedFile>,
+    /// `false` for a dry run.
+    pub applied: bool,
+}
+
+/// A `---` or `+++` path: without a timestamp or git's `a/` and `b/`, and `None` for
+/// `/dev/null`.
+fn header_path(text: &str, prefix: &str) -> Option<String> {
+    let path = text.split('\t').next().unwrap_or_default().trim();
+    let path = path.trim_matches('"');
+    if path == "/dev/null" || path.is_empty() {
+        return None;
+    }
+    Some(path.strip_prefix(prefix).unwrap_or(path).to_string())
+}
+
+/// `@@ -12,7 +12,8 @@ fn main()` to 12.
+fn hunk_start(line: &str) -> Result<usize, String> {
+    line.strip_prefix("@@ -")
+        .and_then(|rest| rest.split([',', ' ']).next())
+        .and_then(|start| start.parse().ok())
+        .ok_or_else(|| format!("Invalid hunk header: {line}"))
+}
+
+fn finish_hunk(file: &mut Option<FilePatch>, hunk: &mut Option<Hunk>) {
+    let Some(mut hunk) = hunk.take() else {
+        return;
+    };
+    // Blank lines a model or a Markdown block left after the last hunk aren't context.
+    while matches!(hunk.lines.last(), Some(Line::Context(text)) if text.is_empty()) {
+        hunk.lines.pop();
+    }
+    if let Some(file) = file {
+        file.hunks.push(hunk);
+    }
+}
+
+/// Read a unified diff of one or more files. The line counts in the `@@` headers are
+/// ignored, since models often get them wrong; a hunk runs until the next header.
+fn parse(diff: &str) -> Result<Vec<FilePatch>, String> {
+    let lines: Vec<&str> = diff
+        .lines()
+        .map(|line| line.strip_suffix('\r').unwrap_or(line))
+        .collect();
+    let mut files = Vec::new();
+    let mut file: Option<FilePatch> = None;
+    let mut hunk: Option<Hunk> = None;
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        let next = lines.get(i + 1).copied().unwrap_or_default();
+        if line.starts_with("--- ") && next.starts_with("+++ ") {
+            finish_hunk(&mut file, &mut hunk);
+            files.extend(file.take());
+            file = Some(FilePatch {
+                old: header_path(&line[4..], "a/"),
+                new: header_path(&next[4..], "b/"),
+                hunks: Vec::new(),
+            });
+            i += 2;
+            continue;
+        }
+        if line.starts_with("@@ ") {
+            finish_hunk(&mut file, &mut hunk);
+            if file.is_none() {
+                return Err("The diff has a hunk before any --- and +++ lines".to_string());
+            }
+            hunk = Some(Hunk {
+                old_start: hunk_start(line)?,
+                ..Hunk::default()
+            });
+            i += 1;
+            continue;
+        }
+        if let Some(current) = &mut hunk {
+            let mut chars = line.chars();
+            match chars.next() {
+                Some(' ') => current
+                    .lines
+                    .push(Line::Context(chars.as_str().to_string())),
+                None => current.lines.push(Line::Context(String::new())),
+                Some('-') => current.lines.push(Line::Remove(chars.as_str().to_string())),
+                Some('+') => current.lines.push(Line::Add(chars.as_str().to_string())),
+                Some('\\') => match current.lines.last() {
+                    Some(Line::Remove(_)) => current.old_no_newline = true,
+                    Some(Line::Add(_)) => current.new_no_newline = true,
+                    _ => {
+                        current.old_no_newline = true;
+                        current.new_no_newline = true;
+                    }
+                },
+                // `diff --git`, `index` and the like end the hunk.
+                _ => finish_hunk(&mut file, &mut hunk),
+            }
+        }
+        i += 1;
+    }
+    finish_hunk(&mut file, &mut hunk);
+    files.extend(file);
+    if files.iter().all(|file| file.hunks.is_empty()) {
+        return Err("The diff has no hunks to apply".to_string());
+    }
+    Ok(files)
+}
+
+/// `relative` joined to `base`, refusing anything that could end up outside it: absolute
+/// paths, `..`, and symlinks out.
+fn contained(base: &Path, relative: &str) -> Result<PathBuf, String> {
+    let path = Path::new(relative);
+    let plain = path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+    if !plain {
+        return Err(format!("{relative} is outside the folder being patched"));
+    }
+    let joined = base.join(path);
+    // The deepest part that exists decides where the path really leads. A dangling
+    // symlink counts as existing, and then fails to resolve.
+    let existing = joined
+        .ancestors()
+        .find(|ancestor| fs::symlink_metadata(ancestor).is_ok())
+        .unwrap_or(base);
+    let resolved = fs::canonicalize(existing).map_err(|e| e.to_string())?;
+    if !resolved.starts_with(base) {
+        return Err(format!("{relative} is outside the folder being patched"));
+    }
+    Ok(joined)
+}
+
+/// A text file as lines, with whether it used CRLF and ended in a newline.
+fn read_lines(path: &Path, relative: &str) -> Result<(Vec<String>, bool, bool), String> {
+    let metadata = fs::metadata(path).map_err(|_| format!("{relative} does not exist"))?;
+    if !metadata.is_file() {
+        return Err(format!("{relative} is not a file"));
+    }
+    if metadata.len() > MAX_FILE_BYTES {
+        return Err(format!("{relative} is too large to patch"));
+    }
+    let bytes = fs::read(path).map_err(|e| e.to_string())?;
+    let text = String::from_utf8(bytes).map_err(|_| format!("{relative} is not a text file"))?;
+    let crlf = text.contains("\r\n");
+    let newline = text.ends_with('\n');
+    let lines = text
+        .lines()
+        .map(|line| line.strip_suffix('\r').unwrap_or(line).to_string())
+        .collect();
+    Ok((lines, crlf, newline))
+}
+
+fn same(a: &str, b: &str) -> bool {
+    a.trim_end() == b.trim_end()
+}
+
+/// Where `old` appears in `lines` at or after `floor`, taking the place nearest
+/// `expected`. Trailing whitespace is ignored, since models tend to drop it.
+fn locate(lines: &[String], old: &[&str], expected: usize, floor: usize) -> Option<usize> {
+    if old.is_empty() {
+        return Some(expected.clamp(floor, lines.len()));
+    }
+    let last = lines.len().checked_sub(old.len())?;
+    if floor > last {
+        return None;
+    }
+    let matches = |at: usize| {
+        lines[at..at + old.len()]
+            .iter()
+            .zip(old)
+            .all(|(line, old)| same(line, old))
+    };
+    let expected = expected.clamp(floor, last);
+    (0..=last - floor).find_map(|distance| {
+        [
+            expected.checked_add(distance),
+            expected.checked_sub(distance),
+        ]
+        .into_iter()
+        .flatten()
+        .find(|&at| (floor..=last).contains(&at) && matches(at))
+    })
+}
+
+/// Apply every hunk in order, or fail without a change.
+fn apply_hunks(lines: &mut Vec<String>, hunks: &[Hunk], relative: &str) -> Result<(), String> {
+    let mut drift: isize = 0;
+    let mut floor = 0;
+    for (number, hunk) in hunks.iter().enumerate() {
+        let old = hunk.before();
+        let new = hunk.after();
+        let expected = (hunk.old_start.saturating_sub(1) as isize + drift).max(0) as usize;
+        let at = locate(lines, &old, expected, floor).ok_or_else(|| {
+            format!(
+                "Hunk {} of {relative} doesn't match the file; it may have changed since",
+                number + 1
+            )
+        })?;
+        drift = at as isize - hunk.old_start.saturating_sub(1) as isize + new.len() as isize
+            - old.len() as isize;
+        floor = at + new.len();
+        lines.splice(at..at + old.len(), new);
+    }
+    Ok(())
+}
+
+/// A file's new contents, worked out before anything is written.
+struct Planned {
+    target: PathBuf,
+    /// Removed after `target` is written, for a rename.
+    source: Option<PathBuf>,
+    /// `None` deletes the file.
+    contents: Option<String>,
+    report: PatchedFile,
+}
+
+fn plan(base: &Path, file: &FilePatch) -> Result<Planned, String> {
+    let added = file
+        .hunks
+        .iter()
+        .flat_map(|hunk| &hunk.lines)
+        .filter(|line| matches!(line, Line::Add(_)))
+        .count();
+    let removed = file
+        .hunks
+        .iter()
+        .flat_map(|hunk| &hunk.lines)
+        .filter(|line| matches!(line, Line::Remove(_)))
+        .count();
+    let last = file.hunks.last();
+    let (change, relative, from) = match (&file.old, &file.new) {
+        (None, None) => return Err("A file in the diff has no path".to_string()),
+        (None, Some(new)) => (FileChange::Created, new, None),
+        (Some(old), None) => (FileChange::Deleted, old, None),
+        (Some(old), Some(new)) if old != new => (FileChange::Renamed, new, Some(old.clone())),
+        (Some(_), Some(new)) => (FileChange::Modified, new, None),
+    };
+    let target = contained(base, relative)?;
+    let report = PatchedFile {
+        path: relative.clone(),
+        from: from.clone(),
+        change,
+        hunks: file.hunks.len(),
+        added,
+        removed,
+    };
+
+    let (mut lines, crlf, had_newline, source) = match (change, &from) {
+        (FileChange::Created, _) => {
+            if target.exists() {
+                return Err(format!("{relative} already exists"));
+            }
+            (Vec::new(), false, true, None)
+        }
+        (FileChange::Renamed, Some(from)) => {
+            if target.exists() {
+                return Err(format!("{relative} already exists"));
+            }
+            let source = contained(base, from)?;
+            let (lines, crlf, newline) = read_lines(&source, from)?;
+            (lines, crlf, newline, Some(source))
+        }
+        _ => {
+            let (lines, crlf, newline) = read_lines(&target, relative)?;
+            (lines, crlf, newline, None)
+        }
+    };
+    apply_hunks(&mut lines, &file.hunks, relative)?;
+    if matches!(change, FileChange::Deleted) {
+        return Ok(Planned {
+            target,
+            source: None,
+            contents: None,
+            report,
+        });
+    }
+
+    let newline = match last {
+        Some(hunk) if hunk.new_no_newline => false,
+        Some(hunk) if hunk.old_no_newline => true,
+        _ => had_newline,
+    };
+    let mut contents = lines.join(if crlf { "\r\n" } else { "\n" });
+    if newline && !contents.is_empty() {
+        contents.push_str(if crlf { "\r\n" } else { "\n" });
+    }
+    Ok(Planned {
+        target,
+        source,
+        contents: Some(contents),
+        report,
+    })
+}
+
+fn write(planned: &Planned) -> Result<(), String> {
+    match &planned.contents {
+        Some(contents) => {
+            if let Some(parent) = planned.target.parent() {
+                fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+            persist::write_atomic(&planned.target, contents.as_bytes())?;
+            if let Some(source) = &planned.source {
+                fs::remove_file(source).map_err(|e| e.to_string())?;
+            }
+            Ok(())
+        }
+        None => fs::remove_file(&planned.target).map_err(|e| e.to_string()),
+    }
+}
+
+/// Apply a unified diff, such as one from a model's response, to the files under
+/// `base_path`. Every hunk of every file is checked first and nothing is written unless
+/// all of them apply; `dry_run` stops there. Paths leading outside `base_path` are
+/// refused.
+#[tauri::command]
+pub async fn apply_unified_diff(
+    app: tauri::AppHandle,
+    diff: String,
+    base_path: PathBuf,
+    dry_run: Option<bool>,
+) -> Result<PatchReport, AppError> {
+    if !base_path.is_absolute() || !base_path.is_dir() {
+        return Err(AppError::InvalidInput(
+            "The folder to patch must be an absolute path to a folder".to_string(),
+        ));
+    }
+    capabilities::require_path(&app, &base_path, "Apply a diff").await?;
+    let dry_run = dry_run.unwrap_or(false);
+    tauri::async_runtime::spawn_blocking(move || {
+        let base = fs::canonicalize(&base_path).map_err(|e| e.to_string())?;
+        let files = parse(&diff).map_err(AppError::InvalidInput)?;
+        let planned = files
+            .iter()
+            .filter(|file| !file.hunks.is_empty())
+            .map(|file| plan(&base, file))
+            .collect::<Result<Vec<_>, String>>()
+            .map_err(AppError::InvalidInput)?;
+        if !dry_run {
+            for file in &planned {
+                write(file).map_err(|e| format!("Could not write {}: {e}", file.report.path))?;
+            }
+            tracing::info!(
+                "Applied a diff to {} files in {}",
+                planned.len(),
+                base.display()
+            );
+        }
+        Ok(PatchReport {
+            files: planned.into_iter().map(|file| file.report).collect(),
+            applied: !dry_run,
+        })
+    })
+    .await?
+}
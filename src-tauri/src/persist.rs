@@ -0,0 +1,58 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tauri::Manager;
+
+use crate::workspaces;
+
+/// Resolve a file inside the active workspace's data directory, creating the directory
+/// if needed.
+pub fn data_file(app: &tauri::AppHandle, name: &str) -> Result<PathBuf, String> {
+    let dir = workspaces::dir(app.path().app_data_dir().map_err(|e| e.to_string())?, name);
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir.join(name))
+}
+
+/// Resolve a file inside the active workspace's config directory, creating the directory
+/// if needed.
+pub fn config_file(app: &tauri::AppHandle, name: &str) -> Result<PathBuf, String> {
+    let dir = workspaces::dir(
+        app.path().app_config_dir().map_err(|e| e.to_string())?,
+        name,
+    );
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir.join(name))
+}
+
+/// Load a JSON file, falling back to the default value if it is missing or unreadable.
+pub fn load_json<T: DeserializeOwned + Default>(path: &Path) -> T {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+/// Write a value as JSON, going through a temp file so a crash never leaves a truncated file.
+pub fn save_json<T: Serialize>(path: &Path, value: &T) -> Result<(), String> {
+    let contents = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, contents).map_err(|e| e.to_string())?;
+    fs::rename(&tmp, path).map_err(|e| e.to_string())
+}
+
+/// Replace a file's contents through a temp file next to it, like `save_json`.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<(), String> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| format!("{} is not a file path", path.display()))?;
+    let mut tmp_name = name.to_os_string();
+    tmp_name.push(".tmp");
+    let tmp = path.with_file_name(tmp_name);
+    fs::write(&tmp, contents).map_err(|e| e.to_string())?;
+    fs::rename(&tmp, path).map_err(|e| {
+        let _ = fs::remove_file(&tmp);
+        e.to_string()
+    })
+}
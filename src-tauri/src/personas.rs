@@ -0,0 +1,262 @@
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tauri::{Emitter, Manager, State};
+
+use crate::db::Database;
+use crate::error::AppError;
+use crate::llm::ChatMessage;
+use crate::{focus, persist, profiles, settings, state};
+
+const PERSONAS_FILE: &str = "personas.json";
+
+/// A named system prompt, with the model and temperature it is meant for.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Persona {
+    /// Assigned by `save_persona` when empty.
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    pub system_prompt: String,
+    /// Used with `model`; the provider in settings when left out.
+    #[serde(default)]
+    pub provider: Option<String>,
+    /// Chats that don't name a model use this one, unless the session picked one.
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub temperature: Option<f32>,
+}
+
+pub struct PersonaStore {
+    path: PathBuf,
+    personas: Mutex<Vec<Persona>>,
+}
+
+impl PersonaStore {
+    pub fn load(app: &tauri::AppHandle) -> Result<Self, String> {
+        let path = persist::data_file(app, PERSONAS_FILE)?;
+        let personas = persist::load_json(&path);
+        Ok(Self {
+            path,
+            personas: Mutex::new(personas),
+        })
+    }
+}
+
+/// Where the persona a chat uses was picked.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PersonaSource {
+    Conversation,
+    AppProfile,
+    Default,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EffectivePersona {
+    pub persona: Persona,
+    pub source: PersonaSource,
+}
+
+fn find(app: &tauri::AppHandle, id: &str) -> Option<Persona> {
+    app.state::<PersonaStore>()
+        .personas
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|p| p.id == id)
+        .cloned()
+}
+
+fn require(app: &tauri::AppHandle, id: &str) -> Result<(), AppError> {
+    match find(app, id) {
+        Some(_) => Ok(()),
+        None => Err(AppError::NotFound(format!("Persona {id} not found"))),
+    }
+}
+
+/// The persona for a chat in `conversation_id`, or in the active conversation without
+/// one: the conversation's own, else that of the profile for the app the overlay was
+/// summoned over, else the default in settings. One that was deleted is skipped.
+pub async fn effective(
+    app: &tauri::AppHandle,
+    conversation_id: Option<String>,
+) -> Option<EffectivePersona> {
+    let conversation_id = match conversation_id {
+        Some(id) => Some(id),
+        None => state::session(app).await.active_conversation,
+    };
+    let conversation = conversation_id.and_then(|id| {
+        app.state::<Database>()
+            .with(|conn| {
+                conn.query_row(
+                    "SELECT persona_id FROM conversations WHERE id = ?1",
+                    [&id],
+                    |row| row.get::<_, Option<String>>(0),
+                )
+                .optional()
+            })
+            .ok()
+            .flatten()
+            .flatten()
+    });
+    let profile = focus::previous_context(app)
+        .and_then(|context| profiles::resolve(app, &context))
+        .and_then(|profile| profile.persona_id);
+    let default = settings::current(app).assistant.default_persona;
+    [
+        (conversation, PersonaSource::Conversation),
+        (profile, PersonaSource::AppProfile),
+        (default, PersonaSource::Default),
+    ]
+    .into_iter()
+    .find_map(|(id, source)| {
+        Some(EffectivePersona {
+            persona: find(app, &id?)?,
+            source,
+        })
+    })
+}
+
+/// Put the system prompt in front of `messages` unless they already have one:
+/// the persona's, or else the one in settings. Returns the temperature to use too.
+pub(crate) fn apply(
+    app: &tauri::AppHandle,
+    persona: Option<&Persona>,
+    mut messages: Vec<ChatMessage>,
+) -> (Vec<ChatMessage>, Option<f32>) {
+    let assistant = settings::current(app).assistant;
+    let temperature = persona
+        .and_then(|persona| persona.temperature)
+        .or(assistant.temperature);
+    let prompt = match persona {
+        Some(persona) => Some(persona.system_prompt.clone()),
+        None => assistant.system_prompt,
+    };
+    let has_system = messages.iter().any(|message| message.role == "system");
+    if let Some(prompt) = prompt.filter(|prompt| !prompt.trim().is_empty() && !has_system) {
+        messages.insert(
+            0,
+            ChatMessage {
+                role: "system".to_string(),
+                content: prompt,
+                images: Vec::new(),
+            },
+        );
+    }
+    (messages, temperature)
+}
+
+#[tauri::command]
+pub fn list_personas(app: tauri::AppHandle) -> Vec<Persona> {
+    app.state::<PersonaStore>().personas.lock().unwrap().clone()
+}
+
+/// Create a persona, or replace the one with the same id.
+#[tauri::command]
+pub fn save_persona(app: tauri::AppHandle, mut persona: Persona) -> Result<Persona, AppError> {
+    if persona.name.trim().is_empty() {
+        return Err(AppError::InvalidInput(
+            "Persona name must not be empty".to_string(),
+        ));
+    }
+    if let Some(temperature) = persona.temperature {
+        if !(0.0..=2.0).contains(&temperature) {
+            return Err(AppError::InvalidInput(
+                "Persona temperature must be between 0 and 2".to_string(),
+            ));
+        }
+    }
+    persona.provider = persona.provider.filter(|p| !p.trim().is_empty());
+    persona.model = persona.model.filter(|m| !m.trim().is_empty());
+    if persona.id.is_empty() {
+        persona.id = uuid::Uuid::new_v4().to_string();
+    }
+
+    let store = app.state::<PersonaStore>();
+    let mut personas = store.personas.lock().unwrap();
+    match personas.iter_mut().find(|p| p.id == persona.id) {
+        Some(existing) => *existing = persona.clone(),
+        None => personas.push(persona.clone()),
+    }
+    persist::save_json(&store.path, &*personas)?;
+    let _ = app.emit("personas-changed", &*personas);
+    Ok(persona)
+}
+
+/// Swap in a whole list of personas, such as restored ones.
+pub fn replace(app: &tauri::AppHandle, replacement: Vec<Persona>) -> Result<(), String> {
+    let store = app.state::<PersonaStore>();
+    let mut personas = store.personas.lock().unwrap();
+    persist::save_json(&store.path, &replacement)?;
+    *personas = replacement;
+    let _ = app.emit("personas-changed", &*personas);
+    Ok(())
+}
+
+/// Returns whether a persona was deleted. Conversations and profiles that used it go
+/// back to the next persona in line.
+#[tauri::command]
+pub fn delete_persona(app: tauri::AppHandle, id: String) -> Result<bool, AppError> {
+    let store = app.state::<PersonaStore>();
+    let mut personas = store.personas.lock().unwrap();
+    let before = personas.len();
+    personas.retain(|p| p.id != id);
+    if personas.len() == before {
+        return Ok(false);
+    }
+    persist::save_json(&store.path, &*personas)?;
+    let _ = app.emit("personas-changed", &*personas);
+    Ok(true)
+}
+
+/// Use persona `id` wherever nothing else picks one, or none with `None`.
+#[tauri::command]
+pub fn set_default_persona(app: tauri::AppHandle, id: Option<String>) -> Result<(), AppError> {
+    if let Some(id) = &id {
+        require(&app, id)?;
+    }
+    settings::update(&app, &json!({ "assistant": { "defaultPersona": id } }))?;
+    Ok(())
+}
+
+/// Use persona `persona_id` for one conversation, ahead of app profiles and the
+/// default, or stop overriding them with `None`.
+#[tauri::command]
+pub async fn set_conversation_persona(
+    app: tauri::AppHandle,
+    db: State<'_, Database>,
+    conversation_id: String,
+    persona_id: Option<String>,
+) -> Result<(), AppError> {
+    if let Some(id) = &persona_id {
+        require(&app, id)?;
+    }
+    let updated = db.with(|conn| {
+        conn.execute(
+            "UPDATE conversations SET persona_id = ?2 WHERE id = ?1",
+            params![conversation_id, persona_id],
+        )
+    })?;
+    if updated == 0 {
+        return Err(AppError::NotFound(format!(
+            "Conversation {conversation_id} not found"
+        )));
+    }
+    Ok(())
+}
+
+/// The persona a chat in `conversation_id`, or the active conversation, would use now.
+#[tauri::command]
+pub async fn get_effective_persona(
+    app: tauri::AppHandle,
+    conversation_id: Option<String>,
+) -> Option<EffectivePersona> {
+    effective(&app, conversation_id).await
+}
@@ -0,0 +1,493 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use rhai::module_resolvers::FileModuleResolver;
+use rhai::{CallFnOptions, Dynamic, Engine, Scope, AST};
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, Manager};
+
+use crate::clipboard::{self, ClipboardContent};
+use crate::error::AppError;
+use crate::llm::ChatMessage;
+use crate::{notifications, persist};
+
+const PLUGINS_DIR: &str = "plugins";
+const MANIFEST_FILE: &str = "plugin.json";
+/// Which plugins are enabled, and with which permissions.
+const CONFIG_FILE: &str = "plugins.json";
+
+/// Script steps a single hook may take before it is stopped, so a runaway loop can't
+/// hang a completion.
+const MAX_OPERATIONS: u64 = 5_000_000;
+const MAX_CALL_LEVELS: usize = 64;
+const MAX_STRING_SIZE: usize = 10 * 1024 * 1024;
+const MAX_COLLECTION_SIZE: usize = 100_000;
+
+const ON_PROMPT: &str = "on_prompt";
+const ON_RESPONSE: &str = "on_response";
+const ON_ACTION: &str = "on_action";
+
+/// What a plugin may do beyond pure computation. Scripts get no file or network access
+/// at all; each permission adds host functions or hooks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PluginPermission {
+    /// `on_prompt` sees, and may rewrite, the user's message before it is sent.
+    Prompts,
+    /// `on_response` sees, and may rewrite, the model's answer.
+    Responses,
+    /// `clipboard_read()` and `clipboard_write(text)`.
+    Clipboard,
+    /// `notify(title, body)`.
+    Notifications,
+}
+
+/// An entry a plugin adds to the command palette, handled by its `on_action(id)`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PluginAction {
+    pub id: String,
+    pub title: String,
+    /// Extra words the palette matches on.
+    #[serde(default)]
+    pub keywords: String,
+}
+
+fn default_entry() -> String {
+    "main.rhai".to_string()
+}
+
+/// `plugin.json`, next to the script in the plugin's own folder.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PluginManifest {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub description: String,
+    /// The Rhai script, relative to the plugin's folder.
+    #[serde(default = "default_entry")]
+    pub entry: String,
+    #[serde(default)]
+    pub permissions: Vec<PluginPermission>,
+    #[serde(default)]
+    pub actions: Vec<PluginAction>,
+}
+
+/// A plugin as the settings page shows it.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginInfo {
+    /// `None` when `plugin.json` couldn't be read; `error` says why.
+    pub manifest: Option<PluginManifest>,
+    pub path: PathBuf,
+    pub enabled: bool,
+    /// Enabled once, but the manifest now asks for more than was granted then, so it
+    /// stays off until it is enabled again.
+    pub needs_approval: bool,
+    /// The hooks the script defines.
+    pub hooks: Vec<String>,
+    pub error: Option<String>,
+}
+
+/// A script compiled and ready to call.
+struct Runtime {
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+}
+
+impl Runtime {
+    fn has(&self, hook: &str, arity: usize) -> bool {
+        self.ast
+            .iter_functions()
+            .any(|function| function.name == hook && function.params.len() == arity)
+    }
+
+    fn call(&mut self, hook: &str, argument: String) -> Result<Dynamic, String> {
+        let options = CallFnOptions::new().eval_ast(false);
+        self.engine
+            .call_fn_with_options(options, &mut self.scope, &self.ast, hook, (argument,))
+            .map_err(|e| e.to_string())
+    }
+}
+
+struct Plugin {
+    dir: PathBuf,
+    manifest: Result<PluginManifest, String>,
+    runtime: Option<Runtime>,
+    enabled: bool,
+    needs_approval: bool,
+    error: Option<String>,
+}
+
+impl Plugin {
+    fn id(&self) -> Option<&str> {
+        self.manifest
+            .as_ref()
+            .ok()
+            .map(|manifest| manifest.id.as_str())
+    }
+
+    fn info(&self) -> PluginInfo {
+        let hooks = self.runtime.as_ref().map_or_else(Vec::new, |runtime| {
+            [ON_PROMPT, ON_RESPONSE, ON_ACTION]
+                .into_iter()
+                .filter(|hook| runtime.has(hook, 1))
+                .map(str::to_string)
+                .collect()
+        });
+        PluginInfo {
+            manifest: self.manifest.as_ref().ok().cloned(),
+            path: self.dir.clone(),
+            enabled: self.enabled,
+            needs_approval: self.needs_approval,
+            hooks,
+            error: self
+                .manifest
+                .as_ref()
+                .err()
+                .cloned()
+                .or_else(|| self.error.clone()),
+        }
+    }
+
+    /// The runtime, if the plugin is on and was granted `permission`.
+    fn runtime_with(&mut self, permission: PluginPermission) -> Option<&mut Runtime> {
+        let granted = self
+            .manifest
+            .as_ref()
+            .is_ok_and(|manifest| manifest.permissions.contains(&permission));
+        self.runtime.as_mut().filter(|_| granted)
+    }
+}
+
+/// The plugins found in the plugins folder, in id order.
+#[derive(Default)]
+pub struct PluginState(Mutex<Vec<Plugin>>);
+
+/// On disk as `{ "plugin-id": ["prompts", ...] }`: the permissions each enabled plugin
+/// was granted when the user turned it on.
+type PluginConfig = HashMap<String, Vec<PluginPermission>>;
+
+fn plugins_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?
+        .join(PLUGINS_DIR);
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir)
+}
+
+fn read_manifest(dir: &Path) -> Result<PluginManifest, String> {
+    let contents = fs::read_to_string(dir.join(MANIFEST_FILE))
+        .map_err(|e| format!("Could not read {MANIFEST_FILE}: {e}"))?;
+    let manifest: PluginManifest =
+        serde_json::from_str(&contents).map_err(|e| format!("Invalid {MANIFEST_FILE}: {e}"))?;
+    if manifest.id.trim().is_empty() {
+        return Err(format!("{MANIFEST_FILE} has an empty id"));
+    }
+    Ok(manifest)
+}
+
+/// An engine with only the host functions the plugin was granted, that can import other
+/// scripts from its own folder and nowhere else.
+fn engine(app: &tauri::AppHandle, dir: &Path, manifest: &PluginManifest) -> Engine {
+    let mut engine = Engine::new();
+    engine
+        .set_max_operations(MAX_OPERATIONS)
+        .set_max_call_levels(MAX_CALL_LEVELS)
+        .set_max_string_size(MAX_STRING_SIZE)
+        .set_max_array_size(MAX_COLLECTION_SIZE)
+        .set_max_map_size(MAX_COLLECTION_SIZE)
+        .set_module_resolver(FileModuleResolver::new_with_path(dir));
+    let id = manifest.id.clone();
+    engine.on_print(move |text| tracing::info!("[plugin {id}] {text}"));
+    let id = manifest.id.clone();
+    engine.on_debug(move |text, _, position| {
+        tracing::debug!("[plugin {id}] {position}: {text}");
+    });
+
+    let granted = |permission| manifest.permissions.contains(&permission);
+    if granted(PluginPermission::Clipboard) {
+        let handle = app.clone();
+        engine.register_fn("clipboard_read", move || match clipboard::read(&handle) {
+            Ok(ClipboardContent::Text { text }) => text,
+            _ => String::new(),
+        });
+        let handle = app.clone();
+        engine.register_fn("clipboard_write", move |text: &str| {
+            let content = ClipboardContent::Text {
+                text: text.to_string(),
+            };
+            if let Err(error) = clipboard::write(&handle, &content) {
+                tracing::warn!("A plugin could not write the clipboard: {error}");
+            }
+        });
+    }
+    if granted(PluginPermission::Notifications) {
+        let handle = app.clone();
+        engine.register_fn("notify", move |title: &str, body: &str| {
+            notifications::show(&handle, title, body, None);
+        });
+    }
+    engine
+}
+
+fn compile(
+    app: &tauri::AppHandle,
+    dir: &Path,
+    manifest: &PluginManifest,
+) -> Result<Runtime, String> {
+    let engine = engine(app, dir, manifest);
+    let ast = engine
+        .compile_file(dir.join(&manifest.entry))
+        .map_err(|e| e.to_string())?;
+    // Top-level statements run once, at load, rather than before every hook.
+    let mut scope = Scope::new();
+    engine
+        .run_ast_with_scope(&mut scope, &ast)
+        .map_err(|e| e.to_string())?;
+    Ok(Runtime { engine, ast, scope })
+}
+
+fn scan(app: &tauri::AppHandle) -> Result<Vec<Plugin>, String> {
+    let dir = plugins_dir(app)?;
+    let config: PluginConfig = persist::load_json(&persist::data_file(app, CONFIG_FILE)?);
+    let mut plugins: Vec<Plugin> = fs::read_dir(&dir)
+        .map_err(|e| e.to_string())?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.join(MANIFEST_FILE).is_file())
+        .map(|dir| {
+            let manifest = read_manifest(&dir);
+            let granted = manifest
+                .as_ref()
+                .ok()
+                .and_then(|manifest| config.get(&manifest.id));
+            let approved = match (&manifest, granted) {
+                (Ok(manifest), Some(granted)) => manifest
+                    .permissions
+                    .iter()
+                    .all(|permission| granted.contains(permission)),
+                _ => false,
+            };
+            let mut plugin = Plugin {
+                dir,
+                manifest,
+                runtime: None,
+                enabled: approved,
+                needs_approval: granted.is_some() && !approved,
+                error: None,
+            };
+            if let (true, Ok(manifest)) = (approved, &plugin.manifest) {
+                match compile(app, &plugin.dir, manifest) {
+                    Ok(runtime) => plugin.runtime = Some(runtime),
+                    Err(error) => {
+                        tracing::warn!("Plugin {} failed to load: {error}", manifest.id);
+                        plugin.error = Some(error);
+                    }
+                }
+            }
+            plugin
+        })
+        .collect();
+    plugins.sort_by(|a, b| a.id().cmp(&b.id()));
+    Ok(plugins)
+}
+
+/// Find and load the plugins. Called from `setup` and by `reload_plugins`.
+pub fn load(app: &tauri::AppHandle) -> Result<Vec<PluginInfo>, String> {
+    let plugins = scan(app)?;
+    let infos: Vec<PluginInfo> = plugins.iter().map(Plugin::info).collect();
+    *app.state::<PluginState>().0.lock().unwrap() = plugins;
+    let _ = app.emit("plugins-changed", &infos);
+    Ok(infos)
+}
+
+fn any_hook(app: &tauri::AppHandle, hook: &str, permission: PluginPermission) -> bool {
+    app.state::<PluginState>()
+        .0
+        .lock()
+        .unwrap()
+        .iter_mut()
+        .any(|plugin| {
+            plugin
+                .runtime_with(permission)
+                .is_some_and(|runtime| runtime.has(hook, 1))
+        })
+}
+
+/// Pass `text` through every enabled plugin's `hook`, in id order. A hook that returns
+/// something other than a string leaves the text as it was, and one that fails is
+/// logged and skipped.
+fn pipe(app: &tauri::AppHandle, hook: &str, permission: PluginPermission, text: String) -> String {
+    let state = app.state::<PluginState>();
+    let mut plugins = state.0.lock().unwrap();
+    plugins.iter_mut().fold(text, |text, plugin| {
+        let id = plugin.id().unwrap_or_default().to_string();
+        let Some(runtime) = plugin
+            .runtime_with(permission)
+            .filter(|runtime| runtime.has(hook, 1))
+        else {
+            return text;
+        };
+        match runtime.call(hook, text.clone()) {
+            Ok(value) if value.is_string() => value.into_string().unwrap_or(text),
+            Ok(_) => text,
+            Err(error) => {
+                tracing::warn!("Plugin {id} failed in {hook}: {error}");
+                text
+            }
+        }
+    })
+}
+
+/// Let plugins rewrite the last user message before it goes to the model.
+pub(crate) async fn on_prompt(
+    app: &tauri::AppHandle,
+    messages: &[ChatMessage],
+) -> Vec<ChatMessage> {
+    let mut messages = messages.to_vec();
+    if !any_hook(app, ON_PROMPT, PluginPermission::Prompts) {
+        return messages;
+    }
+    let Some(index) = messages.iter().rposition(|message| message.role == "user") else {
+        return messages;
+    };
+    let app = app.clone();
+    let prompt = std::mem::take(&mut messages[index].content);
+    let original = prompt.clone();
+    messages[index].content = tauri::async_runtime::spawn_blocking(move || {
+        pipe(&app, ON_PROMPT, PluginPermission::Prompts, prompt)
+    })
+    .await
+    .unwrap_or(original);
+    messages
+}
+
+/// Let plugins rewrite the model's finished answer.
+pub(crate) async fn on_response(app: &tauri::AppHandle, content: String) -> String {
+    if !any_hook(app, ON_RESPONSE, PluginPermission::Responses) {
+        return content;
+    }
+    let app = app.clone();
+    let original = content.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        pipe(&app, ON_RESPONSE, PluginPermission::Responses, content)
+    })
+    .await
+    .unwrap_or(original)
+}
+
+/// The palette entries of every enabled plugin, as plugin id and action.
+pub fn palette_actions(app: &tauri::AppHandle) -> Vec<(String, PluginAction)> {
+    app.state::<PluginState>()
+        .0
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|plugin| plugin.runtime.is_some())
+        .filter_map(|plugin| plugin.manifest.as_ref().ok())
+        .flat_map(|manifest| {
+            manifest
+                .actions
+                .iter()
+                .map(|action| (manifest.id.clone(), action.clone()))
+        })
+        .collect()
+}
+
+#[tauri::command]
+pub fn list_plugins(app: tauri::AppHandle) -> Vec<PluginInfo> {
+    app.state::<PluginState>()
+        .0
+        .lock()
+        .unwrap()
+        .iter()
+        .map(Plugin::info)
+        .collect()
+}
+
+/// Rescan the plugins folder and recompile every enabled script, after editing one.
+#[tauri::command]
+pub async fn reload_plugins(app: tauri::AppHandle) -> Result<Vec<PluginInfo>, AppError> {
+    tauri::async_runtime::spawn_blocking(move || load(&app))
+        .await?
+        .map_err(AppError::from)
+}
+
+/// Turn a plugin on, granting it the permissions its manifest asks for, or off.
+#[tauri::command]
+pub async fn set_plugin_enabled(
+    app: tauri::AppHandle,
+    id: String,
+    enabled: bool,
+) -> Result<Vec<PluginInfo>, AppError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let manifest = app
+            .state::<PluginState>()
+            .0
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|plugin| plugin.id() == Some(id.as_str()))
+            .and_then(|plugin| plugin.manifest.as_ref().ok().cloned())
+            .ok_or_else(|| AppError::NotFound(format!("Plugin {id} not found")))?;
+        let path = persist::data_file(&app, CONFIG_FILE)?;
+        let mut config: PluginConfig = persist::load_json(&path);
+        if enabled {
+            config.insert(id.clone(), manifest.permissions.clone());
+        } else {
+            config.remove(&id);
+        }
+        persist::save_json(&path, &config)?;
+        tracing::info!(
+            "{} plugin {id}",
+            if enabled { "Enabled" } else { "Disabled" }
+        );
+        load(&app).map_err(AppError::from)
+    })
+    .await?
+}
+
+/// Run one of a plugin's palette actions. Returns what `on_action` returned, when that
+/// was text for the overlay to show.
+#[tauri::command]
+pub async fn run_plugin_action(
+    app: tauri::AppHandle,
+    plugin_id: String,
+    action_id: String,
+) -> Result<Option<String>, AppError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<PluginState>();
+        let mut plugins = state.0.lock().unwrap();
+        let plugin = plugins
+            .iter_mut()
+            .find(|plugin| plugin.id() == Some(plugin_id.as_str()))
+            .ok_or_else(|| AppError::NotFound(format!("Plugin {plugin_id} not found")))?;
+        let declared = plugin
+            .manifest
+            .as_ref()
+            .is_ok_and(|manifest| manifest.actions.iter().any(|a| a.id == action_id));
+        let runtime = plugin
+            .runtime
+            .as_mut()
+            .filter(|runtime| declared && runtime.has(ON_ACTION, 1))
+            .ok_or_else(|| {
+                AppError::InvalidInput(format!(
+                    "Plugin {plugin_id} is off or has no action {action_id}"
+                ))
+            })?;
+        let value = runtime
+            .call(ON_ACTION, action_id)
+            .map_err(|e| AppError::from(format!("Plugin {plugin_id} failed: {e}")))?;
+        Ok(value.into_string().ok())
+    })
+    .await?
+}
@@ -0,0 +1,340 @@
+use std::ops::Range;
+use std::sync::OnceLock;
+
+use regex::Regex;
+use serde::Serialize;
+use tauri::Emitter;
+
+use crate::error::AppError;
+use crate::llm::ChatMessage;
+use crate::providers::ProviderInfo;
+use crate::settings::{self, PrivacySettings, RedactionPolicy};
+
+const EMAIL: &str = r"[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}";
+const API_KEY: &str = r"\b(?:sk-[A-Za-z0-9_-]{20,}|AKIA[0-9A-Z]{16}|gh[pousr]_[A-Za-z0-9]{36,}|github_pat_[A-Za-z0-9_]{22,}|xox[abprs]-[A-Za-z0-9-]{10,}|AIza[0-9A-Za-z_-]{35}|[rs]k_live_[0-9A-Za-z]{20,})";
+/// 13 to 19 digits, optionally grouped with spaces or dashes. Matches are then held to
+/// the Luhn check, which rules out most other long numbers.
+const CARD: &str = r"\b(?:\d[ -]?){12,18}\d\b";
+
+fn builtin(cell: &'static OnceLock<Regex>, pattern: &str) -> Regex {
+    cell.get_or_init(|| Regex::new(pattern).expect("built-in redaction patterns are valid"))
+        .clone()
+}
+
+struct Pattern {
+    /// Upper case, used in the placeholder.
+    kind: String,
+    /// What it found, for an error message: "an email address".
+    label: String,
+    regex: Regex,
+    luhn: bool,
+}
+
+/// `Customer ID` as `CUSTOMER_ID`.
+fn kind_of(name: &str) -> String {
+    let kind: String = name
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    kind.trim_matches('_').to_string()
+}
+
+/// The patterns turned on in `settings`. Custom ones were checked when they were saved.
+fn patterns(settings: &PrivacySettings) -> Vec<Pattern> {
+    static EMAIL_REGEX: OnceLock<Regex> = OnceLock::new();
+    static API_KEY_REGEX: OnceLock<Regex> = OnceLock::new();
+    static CARD_REGEX: OnceLock<Regex> = OnceLock::new();
+
+    let mut patterns = Vec::new();
+    if settings.api_keys {
+        patterns.push(Pattern {
+            kind: "API_KEY".to_string(),
+            label: "an API key".to_string(),
+            regex: builtin(&API_KEY_REGEX, API_KEY),
+            luhn: false,
+        });
+    }
+    if settings.emails {
+        patterns.push(Pattern {
+            kind: "EMAIL".to_string(),
+            label: "an email address".to_string(),
+            regex: builtin(&EMAIL_REGEX, EMAIL),
+            luhn: false,
+        });
+    }
+    if settings.credit_cards {
+        patterns.push(Pattern {
+            kind: "CARD".to_string(),
+            label: "a card number".to_string(),
+            regex: builtin(&CARD_REGEX, CARD),
+            luhn: true,
+        });
+    }
+    for custom in &settings.custom_patterns {
+        let Ok(regex) = Regex::new(&custom.regex) else {
+            continue;
+        };
+        let kind = kind_of(&custom.name);
+        patterns.push(Pattern {
+            kind: if kind.is_empty() {
+                "REDACTED".to_string()
+            } else {
+                kind
+            },
+            label: format!("a match for {}", custom.name.trim()),
+            regex,
+            luhn: false,
+        });
+    }
+    patterns
+}
+
+fn passes_luhn(number: &str) -> bool {
+    let digits: Vec<u32> = number.chars().filter_map(|c| c.to_digit(10)).collect();
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &digit)| match (i % 2 == 1, digit * 2) {
+            (true, doubled) if doubled > 9 => doubled - 9,
+            (true, doubled) => doubled,
+            (false, _) => digit,
+        })
+        .sum();
+    (13..=19).contains(&digits.len()) && sum % 10 == 0
+}
+
+/// Every match in `text`, in order and without overlaps, with the index of the pattern
+/// that found it. Where matches overlap the earlier pattern wins.
+fn find(patterns: &[Pattern], text: &str) -> Vec<(Range<usize>, usize)> {
+    let mut found: Vec<(Range<usize>, usize)> = Vec::new();
+    for (index, pattern) in patterns.iter().enumerate() {
+        for m in pattern.regex.find_iter(text) {
+            if m.is_empty() || (pattern.luhn && !passes_luhn(m.as_str())) {
+                continue;
+            }
+            let range = m.range();
+            let overlaps = found
+                .iter()
+                .any(|(other, _)| range.start < other.end && other.start < range.end);
+            if !overlaps {
+                found.push((range, index));
+            }
+        }
+    }
+    found.sort_by_key(|(range, _)| range.start);
+    found
+}
+
+struct Placeholder {
+    kind: String,
+    token: String,
+    original: String,
+}
+
+/// The placeholders handed out for one request and what each stands for. The same
+/// value gets the same placeholder every time it appears.
+#[derive(Default)]
+pub struct Masked(Vec<Placeholder>);
+
+impl Masked {
+    fn placeholder(&mut self, kind: &str, original: &str) -> String {
+        if let Some(existing) = self.0.iter().find(|p| p.original == original) {
+            return existing.token.clone();
+        }
+        let number = self.0.iter().filter(|p| p.kind == kind).count() + 1;
+        let token = format!("[{kind}_{number}]");
+        self.0.push(Placeholder {
+            kind: kind.to_string(),
+            token: token.clone(),
+            original: original.to_string(),
+        });
+        token
+    }
+
+    fn mask(&mut self, patterns: &[Pattern], text: &str) -> String {
+        let found = find(patterns, text);
+        if found.is_empty() {
+            return text.to_string();
+        }
+        let mut out = String::with_capacity(text.len());
+        let mut last = 0;
+        for (range, index) in found {
+            out.push_str(&text[last..range.start]);
+            let token = self.placeholder(&patterns[index].kind, &text[range.clone()]);
+            out.push_str(&token);
+            last = range.end;
+        }
+        out.push_str(&text[last..]);
+        out
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Put the originals back in place of their placeholders.
+    pub fn restore(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for placeholder in &self.0 {
+            if text.contains(&placeholder.token) {
+                text = text.replace(&placeholder.token, &placeholder.original);
+            }
+        }
+        text
+    }
+
+    /// For restoring a response as it streams in.
+    pub fn restorer(&self) -> Restorer<'_> {
+        Restorer {
+            masked: self,
+            held: String::new(),
+        }
+    }
+}
+
+/// Restores placeholders in streamed tokens, one of which may arrive split across several.
+pub struct Restorer<'a> {
+    masked: &'a Masked,
+    held: String,
+}
+
+impl Restorer<'_> {
+    /// Take the next token and return what can be shown so far, holding back the end
+    /// while it could still be the start of a placeholder.
+    pub fn push(&mut self, token: &str) -> String {
+        self.held.push_str(token);
+        let text = self.masked.restore(&self.held);
+        let keep = text
+            .rfind('[')
+            .filter(|&start| {
+                let tail = &text[start..];
+                self.masked.0.iter().any(|p| p.token.starts_with(tail))
+            })
+            .unwrap_or(text.len());
+        self.held = text[keep..].to_string();
+        text[..keep].to_string()
+    }
+
+    /// Whatever is still held back, at the end of a response.
+    pub fn flush(&mut self) -> String {
+        std::mem::take(&mut self.held)
+    }
+}
+
+/// The policy for `provider`: its own if it has one, otherwise the general one for
+/// providers that take a key and none for local ones.
+fn policy(settings: &PrivacySettings, provider: &ProviderInfo) -> RedactionPolicy {
+    match settings.providers.get(&provider.id) {
+        Some(policy) => *policy,
+        None if provider.requires_api_key => settings.policy,
+        None => RedactionPolicy::Off,
+    }
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RedactedPayload<'a> {
+    request_id: &'a str,
+    /// Distinct values replaced.
+    count: usize,
+}
+
+/// Apply the privacy policy for `provider` to the messages about to be sent to it. Every
+/// message is scanned, the model's own earlier answers included, since those had the
+/// originals put back. Images are sent as they are.
+pub(crate) fn filter(
+    app: &tauri::AppHandle,
+    request_id: &str,
+    provider: &ProviderInfo,
+    messages: Vec<ChatMessage>,
+) -> Result<(Vec<ChatMessage>, Masked), AppError> {
+    let settings = settings::current(app).privacy;
+    let policy = policy(&settings, provider);
+    let patterns = patterns(&settings);
+    if policy == RedactionPolicy::Off || patterns.is_empty() {
+        return Ok((messages, Masked::default()));
+    }
+
+    if policy == RedactionPolicy::Block {
+        for message in &messages {
+            if let Some((_, index)) = find(&patterns, &message.content).first() {
+                return Err(AppError::PermissionDenied(format!(
+                    "The prompt contains {}, which the privacy settings don't allow sending to {}",
+                    patterns[*index].label, provider.name
+                )));
+            }
+        }
+        return Ok((messages, Masked::default()));
+    }
+
+    let mut masked = Masked::default();
+    let messages = messages
+        .into_iter()
+        .map(|message| ChatMessage {
+            content: masked.mask(&patterns, &message.content),
+            ..message
+        })
+        .collect();
+    if !masked.is_empty() {
+        tracing::debug!(
+            "Masked {} value(s) in request {request_id} to {}",
+            masked.len(),
+            provider.id
+        );
+        let _ = app.emit(
+            "prompt-redacted",
+            RedactedPayload {
+                request_id,
+                count: masked.len(),
+            },
+        );
+    }
+    Ok((messages, masked))
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RedactionMatch {
+    pub kind: String,
+    pub value: String,
+    pub placeholder: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RedactionPreview {
+    pub text: String,
+    pub matches: Vec<RedactionMatch>,
+}
+
+/// How `text` would be masked with the patterns in settings, for trying them out.
+#[tauri::command]
+pub fn preview_redaction(app: tauri::AppHandle, text: String) -> RedactionPreview {
+    let patterns = patterns(&settings::current(&app).privacy);
+    let mut masked = Masked::default();
+    let text = masked.mask(&patterns, &text);
+    RedactionPreview {
+        text,
+        matches: masked
+            .0
+            .into_iter()
+            .map(|p| RedactionMatch {
+                kind: p.kind,
+                value: p.original,
+                placeholder: p.token,
+            })
+            .collect(),
+    }
+}
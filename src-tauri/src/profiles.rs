@@ -0,0 +1,151 @@
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, Manager};
+
+use crate::error::AppError;
+use crate::focus::{self, AppContext};
+use crate::persist;
+
+const PROFILES_FILE: &str = "profiles.json";
+
+/// How the overlay hotkey summons the overlay over a profile's app.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HotkeyBehavior {
+    /// Wherever the overlay position setting puts it.
+    #[default]
+    Default,
+    /// As a popover next to the selection or caret.
+    AtSelection,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Per-app rules applied when the overlay is summoned while a matching app is in front.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppProfile {
+    /// Assigned by `save_app_profile` when empty.
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    /// Compared case-insensitively with the app id, the executable's file name (with or
+    /// without extension) and the app name.
+    pub app: String,
+    #[serde(default)]
+    pub template_id: Option<String>,
+    #[serde(default)]
+    pub provider: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    /// The persona chats use while the app is in front, unless the conversation has one.
+    #[serde(default)]
+    pub persona_id: Option<String>,
+    #[serde(default)]
+    pub hotkey_behavior: HotkeyBehavior,
+    /// Whether the selection is captured on summon. Worth turning off in terminals, where
+    /// the simulated Ctrl+C fallback would interrupt the running program.
+    #[serde(default = "default_true")]
+    pub include_selection: bool,
+}
+
+impl AppProfile {
+    fn matches(&self, context: &AppContext) -> bool {
+        context.is_app(&self.app)
+    }
+}
+
+/// The saved profiles, in the order they are matched.
+pub struct ProfileStore {
+    path: PathBuf,
+    profiles: Mutex<Vec<AppProfile>>,
+}
+
+impl ProfileStore {
+    pub fn load(app: &tauri::AppHandle) -> Result<Self, String> {
+        let path = persist::config_file(app, PROFILES_FILE)?;
+        let profiles = persist::load_json(&path);
+        Ok(Self {
+            path,
+            profiles: Mutex::new(profiles),
+        })
+    }
+}
+
+/// The first profile matching `context`, if any.
+pub fn resolve(app: &tauri::AppHandle, context: &AppContext) -> Option<AppProfile> {
+    let store = app.state::<ProfileStore>();
+    let profiles = store.profiles.lock().unwrap();
+    profiles.iter().find(|p| p.matches(context)).cloned()
+}
+
+#[tauri::command]
+pub fn list_app_profiles(app: tauri::AppHandle) -> Vec<AppProfile> {
+    app.state::<ProfileStore>().profiles.lock().unwrap().clone()
+}
+
+/// Swap in a whole list of profiles, such as restored ones.
+pub fn replace(app: &tauri::AppHandle, replacement: Vec<AppProfile>) -> Result<(), String> {
+    let store = app.state::<ProfileStore>();
+    let mut profiles = store.profiles.lock().unwrap();
+    persist::save_json(&store.path, &replacement)?;
+    *profiles = replacement;
+    let _ = app.emit("profiles-changed", &*profiles);
+    Ok(())
+}
+
+/// Create a profile, or replace the one with the same id.
+#[tauri::command]
+pub fn save_app_profile(
+    app: tauri::AppHandle,
+    mut profile: AppProfile,
+) -> Result<AppProfile, AppError> {
+    if profile.name.trim().is_empty() {
+        return Err(AppError::InvalidInput(
+            "Profile name must not be empty".to_string(),
+        ));
+    }
+    if profile.app.trim().is_empty() {
+        return Err(AppError::InvalidInput(
+            "Profile app must not be empty".to_string(),
+        ));
+    }
+    if profile.id.is_empty() {
+        profile.id = uuid::Uuid::new_v4().to_string();
+    }
+
+    let store = app.state::<ProfileStore>();
+    let mut profiles = store.profiles.lock().unwrap();
+    match profiles.iter_mut().find(|p| p.id == profile.id) {
+        Some(existing) => *existing = profile.clone(),
+        None => profiles.push(profile.clone()),
+    }
+    persist::save_json(&store.path, &*profiles)?;
+    let _ = app.emit("profiles-changed", &*profiles);
+    Ok(profile)
+}
+
+/// Returns whether a profile was deleted.
+#[tauri::command]
+pub fn delete_app_profile(app: tauri::AppHandle, id: String) -> Result<bool, AppError> {
+    let store = app.state::<ProfileStore>();
+    let mut profiles = store.profiles.lock().unwrap();
+    let before = profiles.len();
+    profiles.retain(|p| p.id != id);
+    if profiles.len() == before {
+        return Ok(false);
+    }
+    persist::save_json(&store.path, &*profiles)?;
+    let _ = app.emit("profiles-changed", &*profiles);
+    Ok(true)
+}
+
+/// The profile for the app the user was in before the overlay was shown.
+#[tauri::command]
+pub fn get_active_app_profile(app: tauri::AppHandle) -> Option<AppProfile> {
+    focus::previous_context(&app).and_then(|context| resolve(&app, &context))
+}
@@ -0,0 +1,256 @@
+use futures_util::future::BoxFuture;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::params::{JsonMode, ParamLimits};
+use super::{error_for_status, ChatRequest, ModelInfo, Provider, ProviderInfo};
+use crate::error::AppError;
+use crate::http;
+use crate::imaging::{EncodedImage, ImageLimits};
+use crate::llm::{ChatMessage, ToolCallDelta, ToolTurn};
+use crate::usage::TokenUsage;
+
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+const DEFAULT_MAX_TOKENS: u32 = 4096;
+/// Larger images are scaled down by the API anyway, costing time without adding detail.
+const MAX_IMAGE_DIMENSION: u32 = 1568;
+const MAX_IMAGE_BYTES: usize = 5 * 1024 * 1024;
+
+pub struct Anthropic(pub ProviderInfo);
+
+#[derive(Deserialize)]
+struct ModelList {
+    data: Vec<ModelEntry>,
+}
+
+#[derive(Deserialize)]
+struct ModelEntry {
+    id: String,
+    display_name: Option<String>,
+}
+
+impl Anthropic {
+    fn request(
+        &self,
+        builder: reqwest::RequestBuilder,
+        api_key: Option<&str>,
+    ) -> reqwest::RequestBuilder {
+        let builder = builder.header("anthropic-version", ANTHROPIC_VERSION);
+        match api_key {
+            Some(key) => builder.header("x-api-key", key),
+            None => builder,
+        }
+    }
+}
+
+/// A message, with its images as base64 `image` blocks ahead of the text.
+fn message_json(message: &ChatMessage, images: &[EncodedImage]) -> Value {
+    if images.is_empty() {
+        return json!({ "role": message.role, "content": message.content });
+    }
+    let mut blocks: Vec<Value> = images
+        .iter()
+        .map(|image| {
+            json!({
+                "type": "image",
+                "source": { "type": "base64", "media_type": image.media_type, "data": image.base64 },
+            })
+        })
+        .collect();
+    if !message.content.is_empty() {
+        blocks.push(json!({ "type": "text", "text": message.content }));
+    }
+    json!({ "role": message.role, "content": blocks })
+}
+
+/// Tool rounds as an assistant message of `tool_use` blocks and a user message of results.
+fn turn_messages(turn: &ToolTurn) -> [Value; 2] {
+    let mut blocks = Vec::new();
+    if !turn.text.is_empty() {
+        blocks.push(json!({ "type": "text", "text": turn.text }));
+    }
+    blocks.extend(turn.calls.iter().map(|call| {
+        json!({ "type": "tool_use", "id": call.id, "name": call.name, "input": call.arguments })
+    }));
+    let results: Vec<Value> = turn
+        .results
+        .iter()
+        .map(|result| {
+            json!({
+                "type": "tool_result",
+                "tool_use_id": result.call_id,
+                "content": result.content,
+                "is_error": result.is_error,
+            })
+        })
+        .collect();
+    [
+        json!({ "role": "assistant", "content": blocks }),
+        json!({ "role": "user", "content": results }),
+    ]
+}
+
+impl Provider for Anthropic {
+    fn info(&self) -> &ProviderInfo {
+        &self.0
+    }
+
+    fn image_limits(&self) -> ImageLimits {
+        ImageLimits {
+            max_dimension: MAX_IMAGE_DIMENSION,
+            max_bytes: MAX_IMAGE_BYTES,
+        }
+    }
+
+    fn param_limits(&self) -> ParamLimits {
+        ParamLimits {
+            max_temperature: 1.0,
+            max_stop: None,
+            json_mode: JsonMode::Unsupported,
+        }
+    }
+
+    fn chat_request(
+        &self,
+        client: &reqwest::Client,
+        chat: &ChatRequest<'_>,
+        api_key: Option<&str>,
+    ) -> reqwest::RequestBuilder {
+        // Anthropic takes the system prompt as a top-level field rather than a message.
+        let system: Vec<&str> = chat
+            .messages
+            .iter()
+            .filter(|m| m.role == "system")
+            .map(|m| m.content.as_str())
+            .collect();
+        let mut turns: Vec<Value> = chat
+            .messages
+            .iter()
+            .enumerate()
+            .filter(|(_, m)| m.role != "system")
+            .map(|(i, m)| message_json(m, chat.images_of(i)))
+            .collect();
+        turns.extend(chat.turns.iter().flat_map(turn_messages));
+        let mut body = json!({
+            "model": chat.model,
+            "max_tokens": chat.params.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+            "messages": turns,
+            "stream": true,
+        });
+        if !system.is_empty() {
+            body["system"] = Value::String(system.join("\n\n"));
+        }
+        let params = chat.params;
+        if let Some(temperature) = params.temperature {
+            body["temperature"] = json!(temperature);
+        }
+        if let Some(top_p) = params.top_p {
+            body["top_p"] = json!(top_p);
+        }
+        if !params.stop.is_empty() {
+            body["stop_sequences"] = json!(params.stop);
+        }
+        if !chat.tools.is_empty() {
+            let tools: Vec<Value> = chat
+                .tools
+                .iter()
+                .map(|tool| {
+                    json!({
+                        "name": tool.name,
+                        "description": tool.description,
+                        "input_schema": tool.parameters,
+                    })
+                })
+                .collect();
+            body["tools"] = Value::Array(tools);
+        }
+        self.request(
+            client
+                .post(format!("{}/messages", self.0.base_url))
+                .json(&body),
+            api_key,
+        )
+    }
+
+    fn stream_token(&self, event: &Value) -> Option<String> {
+        event
+            .pointer("/delta/text")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+    }
+
+    fn stream_tool_calls(&self, event: &Value) -> Vec<ToolCallDelta> {
+        let Some(index) = event.get("index").and_then(Value::as_u64) else {
+            return Vec::new();
+        };
+        let index = index as usize;
+        // `content_block_start` opens a tool_use block, `input_json_delta`s fill in its input.
+        let delta = match event.get("type").and_then(Value::as_str) {
+            Some("content_block_start") => {
+                let block = &event["content_block"];
+                if block.get("type").and_then(Value::as_str) != Some("tool_use") {
+                    return Vec::new();
+                }
+                ToolCallDelta {
+                    index,
+                    id: block.get("id").and_then(Value::as_str).map(str::to_string),
+                    name: block
+                        .get("name")
+                        .and_then(Value::as_str)
+                        .map(str::to_string),
+                    arguments: String::new(),
+                }
+            }
+            Some("content_block_delta") => match event.pointer("/delta/partial_json") {
+                Some(Value::String(json)) => ToolCallDelta {
+                    index,
+                    arguments: json.clone(),
+                    ..Default::default()
+                },
+                _ => return Vec::new(),
+            },
+            _ => return Vec::new(),
+        };
+        vec![delta]
+    }
+
+    fn stream_usage(&self, event: &Value) -> Option<TokenUsage> {
+        // `message_start` carries the input count, `message_delta` the final output count.
+        let usage = event
+            .pointer("/message/usage")
+            .or_else(|| event.get("usage"))?;
+        Some(TokenUsage {
+            prompt_tokens: usage.get("input_tokens").and_then(Value::as_u64),
+            completion_tokens: usage.get("output_tokens").and_then(Value::as_u64),
+        })
+    }
+
+    fn list_models<'a>(
+        &'a self,
+        app: &'a tauri::AppHandle,
+        api_key: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<Vec<ModelInfo>, AppError>> {
+        Box::pin(async move {
+            let request = self.request(
+                http::client(app)?.get(format!("{}/models?limit=1000", self.0.base_url)),
+                api_key,
+            );
+            let response = http::send(app, &self.0.id, None, request)
+                .await
+                .map_err(|e| AppError::Network(format!("Could not reach {}: {e}", self.0.name)))?;
+            let list: ModelList = error_for_status(&self.0, response)
+                .await?
+                .json()
+                .await
+                .map_err(|e| e.to_string())?;
+            Ok(list
+                .data
+                .into_iter()
+                .map(|entry| ModelInfo {
+                    name: entry.display_name.unwrap_or_else(|| entry.id.clone()),
+                    id: entry.id,
+                })
+                .collect())
+        })
+    }
+}
@@ -0,0 +1,396 @@
+use std::path::{Path, PathBuf};
+
+use futures_util::future::BoxFuture;
+use serde_json::Value;
+use tokio::sync::mpsc;
+
+use super::params::{JsonMode, ParamLimits};
+use super::{ChatRequest, ModelInfo, Provider, ProviderInfo, TokenStream};
+use crate::error::AppError;
+use crate::llm::ToolCallDelta;
+use crate::models::{self, ModelKind};
+use crate::settings;
+use crate::usage::TokenUsage;
+
+/// Tokens buffered ahead of the chat reading them.
+const TOKEN_BUFFER: usize = 64;
+
+/// GGUF models run in-process through llama.cpp. The model id is a file name in the
+/// GGUF models folder, or an absolute path to one anywhere else.
+pub struct LlamaCpp(pub ProviderInfo);
+
+/// The GGUF file `model` names.
+fn model_file(app: &tauri::AppHandle, model: &str) -> Result<PathBuf, AppError> {
+    let path = Path::new(model);
+    let path = if path.is_absolute() {
+        path.to_path_buf()
+    } else if path.file_name().is_some_and(|name| name == model) {
+        models::dir(app, ModelKind::Gguf)?.join(model)
+    } else {
+        return Err(AppError::InvalidInput(format!(
+            "{model} is neither a file in the models folder nor an absolute path"
+        )));
+    };
+    if !path.is_file() {
+        return Err(AppError::NotFound(format!(
+            "No GGUF model at {}",
+            path.display()
+        )));
+    }
+    Ok(path)
+}
+
+impl Provider for LlamaCpp {
+    fn info(&self) -> &ProviderInfo {
+        &self.0
+    }
+
+    fn param_limits(&self) -> ParamLimits {
+        ParamLimits {
+            json_mode: JsonMode::Unsupported,
+            ..ParamLimits::default()
+        }
+    }
+
+    fn chat_request(
+        &self,
+        _client: &reqwest::Client,
+        _chat: &ChatRequest<'_>,
+        _api_key: Option<&str>,
+    ) -> reqwest::RequestBuilder {
+        unreachable!("llama.cpp chats are generated in-process")
+    }
+
+    fn stream_token(&self, _event: &Value) -> Option<String> {
+        None
+    }
+
+    fn stream_tool_calls(&self, _event: &Value) -> Vec<ToolCallDelta> {
+        Vec::new()
+    }
+
+    fn stream_usage(&self, _event: &Value) -> Option<TokenUsage> {
+        None
+    }
+
+    fn generate(
+        &self,
+        app: &tauri::AppHandle,
+        chat: &ChatRequest<'_>,
+    ) -> Option<Result<TokenStream, AppError>> {
+        Some(start(app, chat))
+    }
+
+    fn list_models<'a>(
+        &'a self,
+        app: &'a tauri::AppHandle,
+        _api_key: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<Vec<ModelInfo>, AppError>> {
+        Box::pin(async move {
+            let dir = models::dir(app, ModelKind::Gguf)?;
+            let mut entries = tokio::fs::read_dir(&dir)
+                .await
+                .map_err(|e| format!("Could not read {}: {e}", dir.display()))?;
+            let mut models = Vec::new();
+            while let Some(entry) = entries.next_entry().await.map_err(|e| e.to_string())? {
+                let path = entry.path();
+                let gguf = path
+                    .extension()
+                    .is_some_and(|extension| extension.eq_ignore_ascii_case("gguf"));
+                let (Some(file), Some(stem)) = (
+                    path.file_name().and_then(|name| name.to_str()),
+                    path.file_stem().and_then(|stem| stem.to_str()),
+                ) else {
+                    continue;
+                };
+                if gguf && path.is_file() {
+                    models.push(ModelInfo {
+                        id: file.to_string(),
+                        name: stem.to_string(),
+                    });
+                }
+            }
+            Ok(models)
+        })
+    }
+}
+
+/// Load the model and generate on a blocking thread, sending tokens as they come.
+/// Dropping the stream stops generation at the next token.
+fn start(app: &tauri::AppHandle, chat: &ChatRequest<'_>) -> Result<TokenStream, AppError> {
+    if chat.images.iter().any(|images| !images.is_empty()) {
+        return Err(AppError::InvalidInput(
+            "llama.cpp models here take text only; remove the images or pick another model"
+                .to_string(),
+        ));
+    }
+    let path = model_file(app, chat.model)?;
+    let llama = settings::current(app).llama;
+    let messages: Vec<(String, String)> = chat
+        .messages
+        .iter()
+        .map(|message| (message.role.clone(), message.content.clone()))
+        .collect();
+    let params = chat.params.clone();
+    let (tx, rx) = mpsc::channel(TOKEN_BUFFER);
+    tauri::async_runtime::spawn_blocking(move || {
+        if let Err(error) = engine::generate(&path, &llama, &messages, &params, &tx) {
+            let _ = tx.blocking_send(Err(AppError::Other(error)));
+        }
+    });
+    Ok(rx)
+}
+
+#[cfg(feature = "llama")]
+mod engine {
+    use std::num::NonZeroU32;
+    use std::path::{Path, PathBuf};
+    use std::sync::{Arc, Mutex, OnceLock};
+
+    use llama_cpp_2::context::params::LlamaContextParams;
+    use llama_cpp_2::llama_backend::LlamaBackend;
+    use llama_cpp_2::llama_batch::LlamaBatch;
+    use llama_cpp_2::model::params::LlamaModelParams;
+    use llama_cpp_2::model::{AddBos, LlamaChatMessage, LlamaChatTemplate, LlamaModel};
+    use llama_cpp_2::sampling::LlamaSampler;
+    use llama_cpp_2::token::LlamaToken;
+    use llama_cpp_2::TokenToStringError;
+    use tokio::sync::mpsc;
+
+    use crate::error::AppError;
+    use crate::providers::params::GenerationParams;
+    use crate::settings::LlamaSettings;
+
+    /// Prompt tokens decoded per batch.
+    const PROMPT_BATCH: usize = 512;
+    /// Asks llama.cpp for a fresh random seed.
+    const RANDOM_SEED: u32 = u32::MAX;
+    const DEFAULT_TEMPERATURE: f32 = 0.8;
+    const DEFAULT_TOP_P: f32 = 0.95;
+
+    /// llama.cpp refuses to be initialized twice, so the backend lives for the whole run.
+    static BACKEND: OnceLock<Result<LlamaBackend, String>> = OnceLock::new();
+    /// Loading a model takes seconds, so the last one used stays in memory, along with
+    /// the GPU layers it was loaded with.
+    static LOADED: Mutex<Option<(PathBuf, u32, Arc<LlamaModel>)>> = Mutex::new(None);
+
+    fn backend() -> Result<&'static LlamaBackend, String> {
+        BACKEND
+            .get_or_init(|| {
+                let mut backend = LlamaBackend::init().map_err(|e| e.to_string())?;
+                backend.void_logs();
+                Ok(backend)
+            })
+            .as_ref()
+            .map_err(Clone::clone)
+    }
+
+    fn load(
+        backend: &LlamaBackend,
+        path: &Path,
+        gpu_layers: u32,
+    ) -> Result<Arc<LlamaModel>, String> {
+        let mut loaded = LOADED.lock().unwrap();
+        if let Some((loaded_path, layers, model)) = loaded.as_ref() {
+            if loaded_path == path && *layers == gpu_layers {
+                return Ok(model.clone());
+            }
+        }
+        // Free the old model before loading the next, so both never share memory.
+        *loaded = None;
+        let params = LlamaModelParams::default().with_n_gpu_layers(gpu_layers);
+        let model = LlamaModel::load_from_file(backend, path, &params)
+            .map_err(|e| format!("Could not load {}: {e}", path.display()))?;
+        let model = Arc::new(model);
+        *loaded = Some((path.to_path_buf(), gpu_layers, model.clone()));
+        Ok(model)
+    }
+
+    /// The conversation in the model's own chat template, or ChatML when it has none.
+    fn prompt(model: &LlamaModel, messages: &[(String, String)]) -> Result<String, String> {
+        let template = match model.chat_template(None) {
+            Ok(template) => template,
+            Err(_) => LlamaChatTemplate::new("chatml").map_err(|e| e.to_string())?,
+        };
+        let messages = messages
+            .iter()
+            .map(|(role, content)| LlamaChatMessage::new(role.clone(), content.clone()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        model
+            .apply_chat_template(&template, &messages, true)
+            .map_err(|e| e.to_string())
+    }
+
+    fn piece(model: &LlamaModel, token: LlamaToken) -> Result<Vec<u8>, String> {
+        match model.token_to_piece_bytes(token, 32, false, None) {
+            Err(TokenToStringError::InsufficientBufferSpace(needed)) => model
+                .token_to_piece_bytes(token, (-needed) as usize, false, None)
+                .map_err(|e| e.to_string()),
+            piece => piece.map_err(|e| e.to_string()),
+        }
+    }
+
+    fn sampler(params: &GenerationParams) -> LlamaSampler {
+        match params.temperature {
+            Some(temperature) if temperature <= 0.0 => LlamaSampler::greedy(),
+            temperature => LlamaSampler::chain_simple([
+                LlamaSampler::top_p(params.top_p.unwrap_or(DEFAULT_TOP_P), 1),
+                LlamaSampler::temp(temperature.unwrap_or(DEFAULT_TEMPERATURE)),
+                LlamaSampler::dist(RANDOM_SEED),
+            ]),
+        }
+    }
+
+    /// Holds back text that could be the start of a stop sequence until the next
+    /// tokens show whether it is, and ends the answer at the first one written.
+    struct Stops<'a> {
+        stops: &'a [String],
+        held: String,
+    }
+
+    impl Stops<'_> {
+        /// Text that is safe to show, and whether a stop sequence was reached.
+        fn push(&mut self, text: &str) -> (String, bool) {
+            self.held.push_str(text);
+            let found = self
+                .stops
+                .iter()
+                .filter_map(|stop| self.held.find(stop.as_str()))
+                .min();
+            if let Some(at) = found {
+                self.held.truncate(at);
+                return (std::mem::take(&mut self.held), true);
+            }
+            let held = &self.held;
+            let keep = self
+                .stops
+                .iter()
+                .flat_map(|stop| {
+                    (1..stop.len()).filter(move |&len| {
+                        stop.is_char_boundary(len) && held.ends_with(&stop[..len])
+                    })
+                })
+                .max()
+                .unwrap_or(0);
+            let shown = self.held[..self.held.len() - keep].to_string();
+            self.held.drain(..shown.len());
+            (shown, false)
+        }
+    }
+
+    pub fn generate(
+        path: &Path,
+        settings: &LlamaSettings,
+        messages: &[(String, String)],
+        params: &GenerationParams,
+        tokens: &mpsc::Sender<Result<String, AppError>>,
+    ) -> Result<(), String> {
+        let backend = backend()?;
+        let model = load(backend, path, settings.gpu_layers)?;
+        let threads = settings.threads.map_or_else(
+            || {
+                std::thread::available_parallelism()
+                    .map(|n| n.get().min(8) as i32)
+                    .unwrap_or(4)
+            },
+            |threads| threads as i32,
+        );
+        // A context longer than the model was trained on only produces noise.
+        let context_size = settings.context_size.min(model.n_ctx_train().max(512));
+        let context_params = LlamaContextParams::default()
+            .with_n_ctx(NonZeroU32::new(context_size))
+            .with_n_threads(threads)
+            .with_n_threads_batch(threads);
+        let mut context = model
+            .new_context(backend, context_params)
+            .map_err(|e| e.to_string())?;
+
+        let prompt = model
+            .str_to_token(&prompt(&model, messages)?, AddBos::Always)
+            .map_err(|e| e.to_string())?;
+        let context_size = context.n_ctx() as usize;
+        if prompt.len() >= context_size {
+            return Err(format!(
+                "The prompt is {} tokens, more than the {context_size} in llama.contextSize",
+                prompt.len()
+            ));
+        }
+        let mut batch = LlamaBatch::new(PROMPT_BATCH, 1);
+        let last = prompt.len() - 1;
+        for (position, &token) in prompt.iter().enumerate() {
+            batch
+                .add(token, position as i32, &[0], position == last)
+                .map_err(|e| e.to_string())?;
+            if position == last || batch.n_tokens() as usize == PROMPT_BATCH {
+                context.decode(&mut batch).map_err(|e| e.to_string())?;
+                if position != last {
+                    batch.clear();
+                }
+            }
+        }
+
+        let mut sampler = sampler(params);
+        let mut stops = Stops {
+            stops: &params.stop,
+            held: String::new(),
+        };
+        // A character can be split across tokens, so bytes wait here until they decode.
+        let mut bytes = Vec::new();
+        let max_tokens = params.max_tokens.map_or(usize::MAX, |max| max as usize);
+        let mut position = prompt.len();
+        for _ in 0..max_tokens.min(context_size - prompt.len()) {
+            let token = sampler.sample(&context, batch.n_tokens() - 1);
+            sampler.accept(token);
+            if model.is_eog_token(token) {
+                break;
+            }
+            bytes.extend(piece(&model, token)?);
+            let valid = match std::str::from_utf8(&bytes) {
+                Ok(text) => text.len(),
+                Err(error) => error.valid_up_to(),
+            };
+            let text = String::from_utf8_lossy(&bytes[..valid]).into_owned();
+            bytes.drain(..valid);
+            let (shown, stopped) = stops.push(&text);
+            if !shown.is_empty() && tokens.blocking_send(Ok(shown)).is_err() {
+                // Nobody is reading any more: the chat was cancelled.
+                return Ok(());
+            }
+            if stopped {
+                return Ok(());
+            }
+            batch.clear();
+            batch
+                .add(token, position as i32, &[0], true)
+                .map_err(|e| e.to_string())?;
+            context.decode(&mut batch).map_err(|e| e.to_string())?;
+            position += 1;
+        }
+        if !stops.held.is_empty() {
+            let _ = tokens.blocking_send(Ok(std::mem::take(&mut stops.held)));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "llama"))]
+mod engine {
+    use std::path::Path;
+
+    use tokio::sync::mpsc;
+
+    use crate::error::AppError;
+    use crate::providers::params::GenerationParams;
+    use crate::settings::LlamaSettings;
+
+    pub fn generate(
+        _path: &Path,
+        _settings: &LlamaSettings,
+        _messages: &[(String, String)],
+        _params: &GenerationParams,
+        _tokens: &mpsc::Sender<Result<String, AppError>>,
+    ) -> Result<(), String> {
+        Err("This build doesn't include llama.cpp (the `llama` feature)".to_string())
+    }
+}
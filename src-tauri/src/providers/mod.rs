@@ -0,0 +1,337 @@
+mod anthropic;
+mod llamacpp;
+mod openai;
+pub mod params;
+pub mod status;
+
+use std::time::Instant;
+
+use futures_util::future::BoxFuture;
+use serde::Serialize;
+use serde_json::{json, Value};
+use tokio::sync::mpsc;
+
+use crate::error::AppError;
+use crate::imaging::{EncodedImage, ImageLimits};
+use crate::llm::{ChatMessage, ToolCallDelta, ToolSpec, ToolTurn};
+use crate::settings::{self, Settings};
+use crate::usage::TokenUsage;
+use crate::{requests, secrets, state};
+use params::{GenerationParams, ParamLimits};
+
+/// The wire protocol a provider speaks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderKind {
+    OpenAi,
+    Anthropic,
+    Gemini,
+    Ollama,
+    OpenAiCompatible,
+    /// GGUF models run in-process, without any server.
+    LlamaCpp,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderInfo {
+    /// Also the keychain name of the provider's API key.
+    pub id: String,
+    pub name: String,
+    pub kind: ProviderKind,
+    pub base_url: String,
+    pub requires_api_key: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelInfo {
+    pub id: String,
+    pub name: String,
+}
+
+/// Everything that goes into one streaming chat request.
+pub struct ChatRequest<'a> {
+    pub model: &'a str,
+    pub messages: &'a [ChatMessage],
+    /// The images of each of `messages`, by index, encoded for this provider.
+    pub images: &'a [Vec<EncodedImage>],
+    /// Tools the model may call. Left out of the request when empty.
+    pub tools: &'a [ToolSpec],
+    /// Earlier rounds of tool use in this completion, sent after `messages`.
+    pub turns: &'a [ToolTurn],
+    /// Already held to the provider's `param_limits`.
+    pub params: &'a GenerationParams,
+}
+
+impl ChatRequest<'_> {
+    /// The encoded images attached to `messages[index]`.
+    pub fn images_of(&self, index: usize) -> &[EncodedImage] {
+        self.images.get(index).map_or(&[], Vec::as_slice)
+    }
+}
+
+/// The text of a response generated in-process, piece by piece as it is written.
+pub type TokenStream = mpsc::Receiver<Result<String, AppError>>;
+
+/// One LLM backend. Chat requests are streamed; everything provider-specific about
+/// building them and reading the stream lives behind this trait.
+pub trait Provider: Send + Sync {
+    fn info(&self) -> &ProviderInfo;
+
+    /// How large an image the provider accepts with a prompt.
+    fn image_limits(&self) -> ImageLimits {
+        ImageLimits::default()
+    }
+
+    /// What the provider accepts in `GenerationParams`.
+    fn param_limits(&self) -> ParamLimits {
+        ParamLimits::default()
+    }
+
+    /// A streaming chat request.
+    fn chat_request(
+        &self,
+        client: &reqwest::Client,
+        chat: &ChatRequest<'_>,
+        api_key: Option<&str>,
+    ) -> reqwest::RequestBuilder;
+
+    /// The text delta carried by one parsed SSE `data:` payload, if any.
+    fn stream_token(&self, event: &Value) -> Option<String>;
+
+    /// Pieces of tool calls carried by one parsed SSE `data:` payload.
+    fn stream_tool_calls(&self, event: &Value) -> Vec<ToolCallDelta>;
+
+    /// Token counts carried by one parsed SSE `data:` payload, if any.
+    fn stream_usage(&self, event: &Value) -> Option<TokenUsage>;
+
+    /// Generate the response in-process instead of sending `chat_request`, for
+    /// providers that run the model themselves.
+    fn generate(
+        &self,
+        _app: &tauri::AppHandle,
+        _chat: &ChatRequest<'_>,
+    ) -> Option<Result<TokenStream, AppError>> {
+        None
+    }
+
+    /// Models the endpoint offers to this API key.
+    fn list_models<'a>(
+        &'a self,
+        app: &'a tauri::AppHandle,
+        api_key: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<Vec<ModelInfo>, AppError>>;
+}
+
+/// Built-in providers: id, display name, protocol, default base URL, needs a key.
+#[rustfmt::skip]
+const BUILTIN: &[(&str, &str, ProviderKind, &str, bool)] = &[
+    ("openai", "OpenAI", ProviderKind::OpenAi, "https://api.openai.com/v1", true),
+    ("anthropic", "Anthropic", ProviderKind::Anthropic, "https://api.anthropic.com/v1", true),
+    ("google", "Google Gemini", ProviderKind::Gemini, "https://generativelanguage.googleapis.com/v1beta/openai", true),
+    ("deepseek", "DeepSeek", ProviderKind::OpenAiCompatible, "https://api.deepseek.com", true),
+    ("xai", "xAI", ProviderKind::OpenAiCompatible, "https://api.x.ai/v1", true),
+    ("ollama", "Ollama", ProviderKind::Ollama, "http://localhost:11434/v1", false),
+    ("lmstudio", "LM Studio", ProviderKind::OpenAiCompatible, "http://localhost:1234/v1", false),
+    ("llamacpp", "llama.cpp", ProviderKind::LlamaCpp, "", false),
+];
+
+/// Every known provider: the built-in ones, then the user's custom endpoints.
+fn catalog(settings: &Settings) -> Vec<ProviderInfo> {
+    let builtin = BUILTIN
+        .iter()
+        .map(|&(id, name, kind, base_url, key)| ProviderInfo {
+            id: id.to_string(),
+            name: name.to_string(),
+            kind,
+            base_url: base_url.to_string(),
+            requires_api_key: key,
+        });
+    let custom = settings
+        .assistant
+        .custom_providers
+        .iter()
+        .map(|custom| ProviderInfo {
+            id: custom.id.clone(),
+            name: custom.name.clone(),
+            kind: ProviderKind::OpenAiCompatible,
+            base_url: custom.base_url.trim_end_matches('/').to_string(),
+            requires_api_key: custom.requires_api_key,
+        });
+    builtin.chain(custom).collect()
+}
+
+fn build(info: ProviderInfo) -> Box<dyn Provider> {
+    match info.kind {
+        ProviderKind::Anthropic => Box::new(anthropic::Anthropic(info)),
+        ProviderKind::LlamaCpp => Box::new(llamacpp::LlamaCpp(info)),
+        _ => Box::new(openai::OpenAi(info)),
+    }
+}
+
+/// Look up a provider by id, pointed at `base_url` instead of its default if given. An
+/// unknown id with a `base_url` is taken as an ad-hoc OpenAI-compatible endpoint.
+pub fn resolve(
+    app: &tauri::AppHandle,
+    id: &str,
+    base_url: Option<&str>,
+) -> Result<Box<dyn Provider>, String> {
+    let known = catalog(&settings::current(app))
+        .into_iter()
+        .find(|info| info.id == id);
+    let mut info = match (known, base_url) {
+        (Some(info), _) => info,
+        (None, Some(_)) => ProviderInfo {
+            id: id.to_string(),
+            name: id.to_string(),
+            kind: ProviderKind::OpenAiCompatible,
+            base_url: String::new(),
+            requires_api_key: false,
+        },
+        (None, None) => return Err(format!("Unknown provider: {id}")),
+    };
+    if let Some(base_url) = base_url {
+        info.base_url = base_url.trim_end_matches('/').to_string();
+    }
+    Ok(build(info))
+}
+
+/// The saved key for `provider`, or an error if it needs one and none is saved.
+pub fn api_key(provider: &dyn Provider) -> Result<Option<String>, AppError> {
+    let info = provider.info();
+    let key = secrets::api_key(&info.id)?;
+    if key.is_none() && info.requires_api_key {
+        return Err(AppError::ProviderAuth {
+            provider: info.id.clone(),
+            message: format!("No API key saved for {}", info.name),
+        });
+    }
+    Ok(key)
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderSummary {
+    #[serde(flatten)]
+    pub info: ProviderInfo,
+    /// Has a key saved, or doesn't need one.
+    pub configured: bool,
+    pub active: bool,
+}
+
+#[tauri::command]
+pub async fn list_providers(app: tauri::AppHandle) -> Result<Vec<ProviderSummary>, AppError> {
+    let settings = settings::current(&app);
+    catalog(&settings)
+        .into_iter()
+        .map(|info| {
+            let configured = !info.requires_api_key || secrets::api_key(&info.id)?.is_some();
+            let active = info.id == settings.assistant.provider;
+            Ok(ProviderSummary {
+                info,
+                configured,
+                active,
+            })
+        })
+        .collect()
+}
+
+#[tauri::command]
+pub async fn list_provider_models(
+    app: tauri::AppHandle,
+    provider: String,
+    base_url: Option<String>,
+    request_id: Option<String>,
+) -> Result<Vec<ModelInfo>, AppError> {
+    let provider = resolve(&app, &provider, base_url.as_deref())?;
+    let key = api_key(provider.as_ref())?;
+    let task_app = app.clone();
+    let timeout = requests::default_timeout(&app);
+    requests::run(&app, request_id, Some(timeout), async move {
+        let mut models = provider.list_models(&task_app, key.as_deref()).await?;
+        models.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok::<_, AppError>(models)
+    })
+    .await
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionTest {
+    pub latency_ms: u64,
+    pub model_count: usize,
+}
+
+/// Check that the provider is reachable and accepts the saved key, by listing its models.
+#[tauri::command]
+pub async fn test_provider(
+    app: tauri::AppHandle,
+    provider: String,
+    base_url: Option<String>,
+    request_id: Option<String>,
+) -> Result<ConnectionTest, AppError> {
+    let provider = resolve(&app, &provider, base_url.as_deref())?;
+    let key = api_key(provider.as_ref())?;
+    let task_app = app.clone();
+    let timeout = requests::default_timeout(&app);
+    requests::run(&app, request_id, Some(timeout), async move {
+        let started = Instant::now();
+        let models = provider.list_models(&task_app, key.as_deref()).await?;
+        Ok::<_, AppError>(ConnectionTest {
+            latency_ms: started.elapsed().as_millis() as u64,
+            model_count: models.len(),
+        })
+    })
+    .await
+}
+
+/// Make `model` on `provider` the one chats use when the frontend doesn't pick one,
+/// replacing any model picked for the session.
+#[tauri::command]
+pub async fn select_model(
+    app: tauri::AppHandle,
+    provider: String,
+    model: String,
+) -> Result<Settings, AppError> {
+    resolve(&app, &provider, None)?;
+    let settings = settings::update(
+        &app,
+        &json!({ "assistant": { "provider": provider, "model": model } }),
+    )?;
+    state::update(&app, |session| session.model_override = None).await;
+    Ok(settings)
+}
+
+/// Turn an error response into `ProviderAuth` when the key was refused, `Provider`
+/// otherwise.
+pub(crate) async fn error_for_status(
+    provider: &ProviderInfo,
+    response: reqwest::Response,
+) -> Result<reqwest::Response, AppError> {
+    service_error_for_status(&provider.id, &provider.name, response).await
+}
+
+/// `error_for_status` for services that aren't chat providers, by id and display name.
+pub(crate) async fn service_error_for_status(
+    id: &str,
+    name: &str,
+    response: reqwest::Response,
+) -> Result<reqwest::Response, AppError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body = response.text().await.unwrap_or_default();
+    if matches!(status.as_u16(), 401 | 403) {
+        return Err(AppError::ProviderAuth {
+            provider: id.to_string(),
+            message: format!("{name} rejected the API key ({status}): {body}"),
+        });
+    }
+    Err(AppError::Provider {
+        provider: id.to_string(),
+        status: status.as_u16(),
+        message: format!("{name} returned {status}: {body}"),
+    })
+}
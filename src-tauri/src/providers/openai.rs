@@ -0,0 +1,228 @@
+use futures_util::future::BoxFuture;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::params::{JsonMode, ParamLimits};
+use super::{error_for_status, ChatRequest, ModelInfo, Provider, ProviderInfo, ProviderKind};
+use crate::error::AppError;
+use crate::http;
+use crate::imaging::EncodedImage;
+use crate::llm::{ChatMessage, ToolCallDelta, ToolTurn};
+use crate::usage::TokenUsage;
+
+/// The OpenAI chat completions protocol, which Gemini, Ollama, LM Studio and most
+/// hosted and self-hosted servers also speak.
+pub struct OpenAi(pub ProviderInfo);
+
+#[derive(Deserialize)]
+struct ModelList {
+    data: Vec<ModelEntry>,
+}
+
+#[derive(Deserialize)]
+struct ModelEntry {
+    id: String,
+}
+
+/// A message, with its images as `image_url` parts carrying data URLs.
+fn message_json(message: &ChatMessage, images: &[EncodedImage]) -> Value {
+    if images.is_empty() {
+        return json!({ "role": message.role, "content": message.content });
+    }
+    let mut parts = Vec::with_capacity(images.len() + 1);
+    if !message.content.is_empty() {
+        parts.push(json!({ "type": "text", "text": message.content }));
+    }
+    parts.extend(
+        images
+            .iter()
+            .map(|image| json!({ "type": "image_url", "image_url": { "url": image.data_url() } })),
+    );
+    json!({ "role": message.role, "content": parts })
+}
+
+/// Tool rounds as the assistant `tool_calls` message and one `tool` message per result.
+fn turn_messages(turn: &ToolTurn) -> Vec<Value> {
+    let calls: Vec<Value> = turn
+        .calls
+        .iter()
+        .map(|call| {
+            json!({
+                "id": call.id,
+                "type": "function",
+                "function": { "name": call.name, "arguments": call.arguments.to_string() },
+            })
+        })
+        .collect();
+    let text = (!turn.text.is_empty()).then_some(turn.text.as_str());
+    let mut messages = vec![json!({ "role": "assistant", "content": text, "tool_calls": calls })];
+    messages.extend(turn.results.iter().map(|result| {
+        json!({ "role": "tool", "tool_call_id": result.call_id, "content": result.content })
+    }));
+    messages
+}
+
+impl Provider for OpenAi {
+    fn info(&self) -> &ProviderInfo {
+        &self.0
+    }
+
+    fn param_limits(&self) -> ParamLimits {
+        match self.0.kind {
+            ProviderKind::OpenAi => ParamLimits {
+                max_stop: Some(4),
+                json_mode: JsonMode::NeedsMention,
+                ..ParamLimits::default()
+            },
+            ProviderKind::Gemini => ParamLimits {
+                max_stop: Some(5),
+                ..ParamLimits::default()
+            },
+            _ => ParamLimits::default(),
+        }
+    }
+
+    fn chat_request(
+        &self,
+        client: &reqwest::Client,
+        chat: &ChatRequest<'_>,
+        api_key: Option<&str>,
+    ) -> reqwest::RequestBuilder {
+        let mut messages: Vec<Value> = chat
+            .messages
+            .iter()
+            .enumerate()
+            .map(|(i, m)| message_json(m, chat.images_of(i)))
+            .collect();
+        messages.extend(chat.turns.iter().flat_map(turn_messages));
+        let mut body = json!({
+            "model": chat.model,
+            "messages": messages,
+            "stream": true,
+        });
+        let params = chat.params;
+        if let Some(temperature) = params.temperature {
+            body["temperature"] = json!(temperature);
+        }
+        if let Some(top_p) = params.top_p {
+            body["top_p"] = json!(top_p);
+        }
+        if let Some(max_tokens) = params.max_tokens {
+            // OpenAI's reasoning models only take the newer name.
+            let key = match self.0.kind {
+                ProviderKind::OpenAi => "max_completion_tokens",
+                _ => "max_tokens",
+            };
+            body[key] = json!(max_tokens);
+        }
+        if !params.stop.is_empty() {
+            body["stop"] = json!(params.stop);
+        }
+        if params.json_mode {
+            body["response_format"] = json!({ "type": "json_object" });
+        }
+        if !chat.tools.is_empty() {
+            let tools: Vec<Value> = chat
+                .tools
+                .iter()
+                .map(|tool| json!({ "type": "function", "function": tool }))
+                .collect();
+            body["tools"] = Value::Array(tools);
+        }
+        // Only OpenAI itself is known to accept this; elsewhere usage is estimated.
+        if self.0.kind == ProviderKind::OpenAi {
+            body["stream_options"] = json!({ "include_usage": true });
+        }
+        let mut request = client
+            .post(format!("{}/chat/completions", self.0.base_url))
+            .json(&body);
+        if let Some(key) = api_key {
+            request = request.bearer_auth(key);
+        }
+        request
+    }
+
+    fn stream_token(&self, event: &Value) -> Option<String> {
+        event
+            .pointer("/choices/0/delta/content")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+    }
+
+    fn stream_tool_calls(&self, event: &Value) -> Vec<ToolCallDelta> {
+        let Some(calls) = event
+            .pointer("/choices/0/delta/tool_calls")
+            .and_then(Value::as_array)
+        else {
+            return Vec::new();
+        };
+        calls
+            .iter()
+            .enumerate()
+            .map(|(i, call)| ToolCallDelta {
+                index: call
+                    .get("index")
+                    .and_then(Value::as_u64)
+                    .map_or(i, |index| index as usize),
+                id: call.get("id").and_then(Value::as_str).map(str::to_string),
+                name: call
+                    .pointer("/function/name")
+                    .and_then(Value::as_str)
+                    .map(str::to_string),
+                arguments: call
+                    .pointer("/function/arguments")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string(),
+            })
+            .collect()
+    }
+
+    fn stream_usage(&self, event: &Value) -> Option<TokenUsage> {
+        let usage = event.get("usage").filter(|usage| usage.is_object())?;
+        Some(TokenUsage {
+            prompt_tokens: usage.get("prompt_tokens").and_then(Value::as_u64),
+            completion_tokens: usage.get("completion_tokens").and_then(Value::as_u64),
+        })
+    }
+
+    fn list_models<'a>(
+        &'a self,
+        app: &'a tauri::AppHandle,
+        api_key: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<Vec<ModelInfo>, AppError>> {
+        Box::pin(async move {
+            let mut request = http::client(app)?.get(format!("{}/models", self.0.base_url));
+            if let Some(key) = api_key {
+                request = request.bearer_auth(key);
+            }
+            let response = http::send(app, &self.0.id, None, request)
+                .await
+                .map_err(|e| AppError::Network(format!("Could not reach {}: {e}", self.0.name)))?;
+            let list: ModelList = error_for_status(&self.0, response)
+                .await?
+                .json()
+                .await
+                .map_err(|e| e.to_string())?;
+            Ok(list
+                .data
+                .into_iter()
+                .map(|entry| {
+                    // Gemini lists "models/gemini-..." but expects the bare name in requests.
+                    let id = match self.0.kind {
+                        ProviderKind::Gemini => entry
+                            .id
+                            .strip_prefix("models/")
+                            .map(str::to_string)
+                            .unwrap_or(entry.id),
+                        _ => entry.id,
+                    };
+                    ModelInfo {
+                        name: id.clone(),
+                        id,
+                    }
+                })
+                .collect())
+        })
+    }
+}
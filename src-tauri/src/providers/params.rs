@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize};
+
+use super::ProviderInfo;
+use crate::error::AppError;
+use crate::llm::ChatMessage;
+
+/// The widest temperature range any provider takes. Within it, a value above what the
+/// provider allows is lowered to its maximum rather than refused.
+const MAX_TEMPERATURE: f32 = 2.0;
+
+/// How a chat is generated. Whatever is left out is up to the provider.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct GenerationParams {
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    /// The most tokens the answer may run to.
+    pub max_tokens: Option<u32>,
+    /// Text that ends the answer when the model writes it.
+    pub stop: Vec<String>,
+    /// Have the model answer with a JSON object.
+    pub json_mode: bool,
+}
+
+/// Whether a provider can be held to answering in JSON.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonMode {
+    Unsupported,
+    Supported,
+    /// Only if the prompt itself asks for JSON, or the request is refused.
+    NeedsMention,
+}
+
+/// What a provider accepts in `GenerationParams`.
+#[derive(Debug, Clone, Copy)]
+pub struct ParamLimits {
+    pub max_temperature: f32,
+    /// `None` for no limit.
+    pub max_stop: Option<usize>,
+    pub json_mode: JsonMode,
+}
+
+impl Default for ParamLimits {
+    fn default() -> Self {
+        Self {
+            max_temperature: MAX_TEMPERATURE,
+            max_stop: None,
+            json_mode: JsonMode::Supported,
+        }
+    }
+}
+
+/// Check `params` against what `provider` accepts, so a bad value fails here with a
+/// reason instead of as a bare 400 from the provider. A temperature the provider can't
+/// go up to is lowered to its maximum.
+pub fn check(
+    provider: &ProviderInfo,
+    limits: ParamLimits,
+    messages: &[ChatMessage],
+    mut params: GenerationParams,
+) -> Result<GenerationParams, AppError> {
+    let invalid = |message: String| Err(AppError::InvalidInput(message));
+    if let Some(temperature) = params.temperature {
+        if !(0.0..=MAX_TEMPERATURE).contains(&temperature) {
+            return invalid(format!(
+                "Temperature must be between 0 and {MAX_TEMPERATURE}, not {temperature}"
+            ));
+        }
+        if temperature > limits.max_temperature {
+            tracing::debug!(
+                "Lowering temperature {temperature} to {} for {}",
+                limits.max_temperature,
+                provider.id
+            );
+            params.temperature = Some(limits.max_temperature);
+        }
+    }
+    if let Some(top_p) = params.top_p {
+        if !(top_p > 0.0 && top_p <= 1.0) {
+            return invalid(format!("top_p must be above 0 and at most 1, not {top_p}"));
+        }
+    }
+    if params.max_tokens == Some(0) {
+        return invalid("max_tokens must be at least 1".to_string());
+    }
+    if params.stop.iter().any(|stop| stop.trim().is_empty()) {
+        return invalid("Stop sequences must not be empty or only whitespace".to_string());
+    }
+    if let Some(max) = limits.max_stop {
+        if params.stop.len() > max {
+            return invalid(format!(
+                "{} takes at most {max} stop sequences, not {}",
+                provider.name,
+                params.stop.len()
+            ));
+        }
+    }
+    if params.json_mode {
+        match limits.json_mode {
+            JsonMode::Supported => {}
+            JsonMode::Unsupported => {
+                return invalid(format!(
+                    "{} has no JSON mode; ask for JSON in the prompt instead",
+                    provider.name
+                ));
+            }
+            JsonMode::NeedsMention => {
+                let mentioned = messages
+                    .iter()
+                    .any(|message| message.content.to_lowercase().contains("json"));
+                if !mentioned {
+                    return invalid(format!(
+                        "{} only takes JSON mode when the prompt asks for JSON",
+                        provider.name
+                    ));
+                }
+            }
+        }
+    }
+    Ok(params)
+}
@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use reqwest::header::HeaderMap;
+use reqwest::{Response, StatusCode};
+use serde::Serialize;
+use tauri::{Manager, State};
+
+use crate::db;
+
+/// What the last requests to each provider said about its health and rate limits.
+#[derive(Default)]
+pub struct ProviderStatuses(Mutex<HashMap<String, ProviderStatus>>);
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderStatus {
+    pub provider: String,
+    pub last_status: Option<u16>,
+    pub last_error: Option<String>,
+    /// Failed requests in a row, reset by the next success.
+    pub consecutive_failures: u32,
+    /// When the provider last asked us to back off until, in Unix milliseconds.
+    pub rate_limited_until: Option<i64>,
+    pub remaining_requests: Option<u64>,
+    pub remaining_tokens: Option<u64>,
+    pub updated_at: i64,
+}
+
+/// Remaining-quota headers, OpenAI's and Anthropic's spellings.
+const REMAINING_REQUESTS: &[&str] = &[
+    "x-ratelimit-remaining-requests",
+    "anthropic-ratelimit-requests-remaining",
+];
+const REMAINING_TOKENS: &[&str] = &[
+    "x-ratelimit-remaining-tokens",
+    "anthropic-ratelimit-tokens-remaining",
+];
+
+fn header_u64(headers: &HeaderMap, names: &[&str]) -> Option<u64> {
+    names
+        .iter()
+        .find_map(|name| headers.get(*name)?.to_str().ok()?.trim().parse().ok())
+}
+
+/// How long a response asks us to wait, from `retry-after-ms` or `Retry-After` seconds.
+/// The HTTP-date form of `Retry-After` isn't used by any provider we talk to.
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    if let Some(ms) = header_u64(headers, &["retry-after-ms"]) {
+        return Some(Duration::from_millis(ms));
+    }
+    let seconds: f64 = headers
+        .get("retry-after")?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    (seconds >= 0.0).then(|| Duration::from_secs_f64(seconds))
+}
+
+fn update(app: &tauri::AppHandle, provider: &str, f: impl FnOnce(&mut ProviderStatus)) {
+    let Some(statuses) = app.try_state::<ProviderStatuses>() else {
+        return;
+    };
+    let mut statuses = statuses.0.lock().unwrap();
+    let status = statuses
+        .entry(provider.to_string())
+        .or_insert_with(|| ProviderStatus {
+            provider: provider.to_string(),
+            ..ProviderStatus::default()
+        });
+    f(status);
+    status.updated_at = db::now_ms();
+}
+
+pub fn record_response(app: &tauri::AppHandle, provider: &str, response: &Response) {
+    let code = response.status();
+    let headers = response.headers();
+    update(app, provider, |status| {
+        status.last_status = Some(code.as_u16());
+        if let Some(remaining) = header_u64(headers, REMAINING_REQUESTS) {
+            status.remaining_requests = Some(remaining);
+        }
+        if let Some(remaining) = header_u64(headers, REMAINING_TOKENS) {
+            status.remaining_tokens = Some(remaining);
+        }
+        if code == StatusCode::TOO_MANY_REQUESTS {
+            let wait = retry_after(headers).unwrap_or(Duration::from_secs(1));
+            status.rate_limited_until = Some(db::now_ms() + wait.as_millis() as i64);
+        }
+        if code.is_success() {
+            status.last_error = None;
+            status.consecutive_failures = 0;
+        } else {
+            status.last_error = Some(code.to_string());
+            status.consecutive_failures += 1;
+        }
+    });
+}
+
+pub fn record_error(app: &tauri::AppHandle, provider: &str, error: &str) {
+    update(app, provider, |status| {
+        status.last_status = None;
+        status.last_error = Some(error.to_string());
+        status.consecutive_failures += 1;
+    });
+}
+
+/// The health and rate-limit state of every provider contacted since launch.
+#[tauri::command]
+pub fn get_provider_status(statuses: State<'_, ProviderStatuses>) -> Vec<ProviderStatus> {
+    let mut all: Vec<ProviderStatus> = statuses.0.lock().unwrap().values().cloned().collect();
+    all.sort_by(|a, b| a.provider.cmp(&b.provider));
+    all
+}
@@ -0,0 +1,330 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, Manager};
+
+use crate::capabilities::{self, Capability};
+use crate::error::AppError;
+use crate::llm::{self, ChatMessage, ProviderConfig};
+use crate::window_manager::{self, AppWindow};
+use crate::{focus, insert, notifications, persist, selection, shortcuts, templates};
+
+const QUICK_ACTIONS_FILE: &str = "quick_actions.json";
+
+/// What happens to the model's answer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QuickActionOutput {
+    /// Show it in the overlay.
+    #[default]
+    Show,
+    /// Paste it over the selection in the app it came from.
+    Replace,
+}
+
+/// A prompt run on the selection from its own hotkey, without opening the overlay first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuickAction {
+    /// Assigned by `save_quick_action` when empty.
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    /// Rendered like a template: `{{selection}}`, `{{clipboard}}` and `{{app}}`.
+    pub prompt: String,
+    #[serde(default)]
+    pub output: QuickActionOutput,
+    /// An accelerator or a chord like `Ctrl+K Ctrl+T`.
+    #[serde(default)]
+    pub shortcut: Option<String>,
+    /// The model to ask instead of the one selected in settings.
+    #[serde(default)]
+    pub config: Option<ProviderConfig>,
+}
+
+impl QuickAction {
+    fn builtin(id: &str, name: &str, prompt: &str, output: QuickActionOutput) -> Self {
+        Self {
+            id: id.to_string(),
+            name: name.to_string(),
+            prompt: prompt.to_string(),
+            output,
+            shortcut: None,
+            config: None,
+        }
+    }
+}
+
+/// What a fresh install starts with, unbound so they can't clash with anything.
+fn defaults() -> Vec<QuickAction> {
+    vec![
+        QuickAction::builtin(
+            "translate",
+            "Translate selection",
+            "Translate the following text into English, keeping its tone. If it is already \
+             English, translate it into the language it most likely came from. Reply with \
+             only the translation.\n\n{{selection}}",
+            QuickActionOutput::Show,
+        ),
+        QuickAction::builtin(
+            "explain",
+            "Explain selection",
+            "Explain the following text, selected in {{app}}, briefly and in plain \
+             language.\n\n{{selection}}",
+            QuickActionOutput::Show,
+        ),
+        QuickAction::builtin(
+            "fix-grammar",
+            "Fix grammar",
+            "Fix the spelling, grammar and punctuation of the following text without \
+             changing its meaning, tone or formatting. Reply with only the corrected \
+             text.\n\n{{selection}}",
+            QuickActionOutput::Replace,
+        ),
+    ]
+}
+
+pub struct QuickActionStore {
+    path: PathBuf,
+    actions: Mutex<Vec<QuickAction>>,
+}
+
+impl QuickActionStore {
+    pub fn load(app: &tauri::AppHandle) -> Result<Self, String> {
+        let path = persist::data_file(app, QUICK_ACTIONS_FILE)?;
+        let actions = if path.exists() {
+            persist::load_json(&path)
+        } else {
+            defaults()
+        };
+        Ok(Self {
+            path,
+            actions: Mutex::new(actions),
+        })
+    }
+}
+
+/// Emitted to the overlay with a `Show` action's answer.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct QuickActionResult<'a> {
+    action_id: &'a str,
+    name: &'a str,
+    selection: &'a str,
+    content: &'a str,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct QuickActionEvent<'a> {
+    action_id: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<&'a str>,
+}
+
+/// Set while an action runs, so key repeat on its hotkey doesn't queue up more.
+static RUNNING: AtomicBool = AtomicBool::new(false);
+
+fn find(app: &tauri::AppHandle, id: &str) -> Option<QuickAction> {
+    app.state::<QuickActionStore>()
+        .actions
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|action| action.id == id)
+        .cloned()
+}
+
+/// Capture the selection, ask the model, and show or paste the answer. Blocks, so it
+/// runs off the event loop.
+fn execute(app: &tauri::AppHandle, action: &QuickAction) -> Result<(), String> {
+    focus::remember(app);
+    let selected = selection::capture(app)
+        .filter(|text| !text.trim().is_empty())
+        .ok_or("Select some text first")?;
+    let mut values = templates::builtin_variables(app);
+    values.insert("selection".to_string(), selected.clone());
+    let prompt = templates::render(&action.prompt, &values);
+    let _ = app.emit(
+        "quick-action-started",
+        QuickActionEvent {
+            action_id: &action.id,
+            message: None,
+        },
+    );
+    let answer = tauri::async_runtime::block_on(llm::complete(
+        app,
+        action.config.clone(),
+        vec![ChatMessage {
+            role: "user".to_string(),
+            content: prompt,
+            images: Vec::new(),
+        }],
+    ))?;
+
+    match action.output {
+        QuickActionOutput::Show => {
+            window_manager::show_overlay_nonactivating(app, None)?;
+            window_manager::emit_to(
+                app,
+                AppWindow::Overlay,
+                "quick-action-result",
+                QuickActionResult {
+                    action_id: &action.id,
+                    name: &action.name,
+                    selection: &selected,
+                    content: &answer,
+                },
+            )?;
+        }
+        // The user's app never lost focus, so the paste lands on the selection.
+        QuickActionOutput::Replace => {
+            capabilities::check(app, Capability::Accessibility, &action.name)?;
+            insert::paste(app, answer.trim())?;
+        }
+    }
+    let _ = app.emit(
+        "quick-action-finished",
+        QuickActionEvent {
+            action_id: &action.id,
+            message: None,
+        },
+    );
+    Ok(())
+}
+
+fn run(app: &tauri::AppHandle, id: &str) {
+    let Some(action) = find(app, id) else {
+        return;
+    };
+    if RUNNING.swap(true, Ordering::SeqCst) {
+        tracing::debug!(
+            "Ignoring {}: a quick action is already running",
+            action.name
+        );
+        return;
+    }
+    let result = execute(app, &action);
+    RUNNING.store(false, Ordering::SeqCst);
+    if let Err(error) = result {
+        tracing::warn!("Quick action {} failed: {error}", action.name);
+        let _ = app.emit(
+            "quick-action-error",
+            QuickActionEvent {
+                action_id: &action.id,
+                message: Some(&error),
+            },
+        );
+        notifications::show(app, &action.name, &error, None);
+    }
+}
+
+fn bind(app: &tauri::AppHandle, id: &str, accelerator: &str) -> Result<String, String> {
+    let id = id.to_string();
+    shortcuts::bind_callback(app, accelerator, move |app| {
+        let app = app.clone();
+        let id = id.clone();
+        tauri::async_runtime::spawn_blocking(move || run(&app, &id));
+    })
+}
+
+/// Bind the saved actions' hotkeys. Called once from `setup`, after the shortcut actions
+/// are restored so those win any clash.
+pub fn restore(app: &tauri::AppHandle) {
+    let store = app.state::<QuickActionStore>();
+    let mut actions = store.actions.lock().unwrap();
+    for action in actions.iter_mut() {
+        let Some(accelerator) = action.shortcut.clone() else {
+            continue;
+        };
+        if let Err(error) = bind(app, &action.id, &accelerator) {
+            tracing::warn!("Leaving quick action {} unbound: {error}", action.name);
+            action.shortcut = None;
+        }
+    }
+}
+
+/// Bind the actions' hotkeys again, for when the keys they were grabbed on have moved.
+pub fn rebind_all(app: &tauri::AppHandle) {
+    let store = app.state::<QuickActionStore>();
+    let actions = store.actions.lock().unwrap();
+    for action in actions.iter() {
+        let Some(accelerator) = action.shortcut.as_deref() else {
+            continue;
+        };
+        let _ = shortcuts::unbind_callback(app, accelerator);
+        if let Err(error) = bind(app, &action.id, accelerator) {
+            tracing::warn!("Could not bind quick action {} again: {error}", action.name);
+        }
+    }
+}
+
+#[tauri::command]
+pub fn list_quick_actions(app: tauri::AppHandle) -> Vec<QuickAction> {
+    app.state::<QuickActionStore>()
+        .actions
+        .lock()
+        .unwrap()
+        .clone()
+}
+
+/// Create a quick action, or replace the one with the same id, binding its hotkey.
+#[tauri::command]
+pub fn save_quick_action(
+    app: tauri::AppHandle,
+    mut action: QuickAction,
+) -> Result<QuickAction, AppError> {
+    if action.name.trim().is_empty() || action.prompt.trim().is_empty() {
+        return Err(AppError::InvalidInput(
+            "A quick action needs a name and a prompt".to_string(),
+        ));
+    }
+    if action.id.is_empty() {
+        action.id = uuid::Uuid::new_v4().to_string();
+    }
+    action.shortcut = action
+        .shortcut
+        .filter(|accelerator| !accelerator.trim().is_empty());
+
+    let store = app.state::<QuickActionStore>();
+    let mut actions = store.actions.lock().unwrap();
+    let previous = actions
+        .iter()
+        .find(|existing| existing.id == action.id)
+        .and_then(|existing| existing.shortcut.clone());
+    if action.shortcut != previous {
+        // Bind the new hotkey before releasing the old one so a clash keeps the old one.
+        if let Some(accelerator) = &action.shortcut {
+            action.shortcut = Some(bind(&app, &action.id, accelerator)?);
+        }
+        if let Some(previous) = previous {
+            let _ = shortcuts::unbind_callback(&app, &previous);
+        }
+    }
+    match actions.iter_mut().find(|existing| existing.id == action.id) {
+        Some(existing) => *existing = action.clone(),
+        None => actions.push(action.clone()),
+    }
+    persist::save_json(&store.path, &*actions)?;
+    let _ = app.emit("quick-actions-changed", &*actions);
+    Ok(action)
+}
+
+/// Returns whether a quick action was deleted.
+#[tauri::command]
+pub fn delete_quick_action(app: tauri::AppHandle, id: String) -> Result<bool, AppError> {
+    let store = app.state::<QuickActionStore>();
+    let mut actions = store.actions.lock().unwrap();
+    let Some(index) = actions.iter().position(|action| action.id == id) else {
+        return Ok(false);
+    };
+    let removed = actions.remove(index);
+    if let Some(accelerator) = &removed.shortcut {
+        let _ = shortcuts::unbind_callback(&app, accelerator);
+    }
+    persist::save_json(&store.path, &*actions)?;
+    let _ = app.emit("quick-actions-changed", &*actions);
+    Ok(true)
+}
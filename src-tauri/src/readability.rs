@@ -0,0 +1,214 @@
+use serde::Serialize;
+
+use crate::error::AppError;
+use crate::http;
+
+/// Elements whose content is never part of the readable text.
+const SKIPPED: &[&str] = &[
+    "script", "style", "noscript", "template", "svg", "iframe", "nav", "header", "footer", "aside",
+    "form",
+];
+
+/// Elements that start a new line of text.
+const BLOCKS: &[&str] = &[
+    "p",
+    "div",
+    "br",
+    "li",
+    "tr",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "section",
+    "article",
+    "main",
+    "blockquote",
+    "pre",
+    "table",
+    "ul",
+    "ol",
+    "dt",
+    "dd",
+    "hr",
+];
+
+/// A downloaded page reduced to its readable text.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Page {
+    pub url: String,
+    pub title: Option<String>,
+    pub text: String,
+}
+
+/// Download `url` and extract its readable text. Only http(s) URLs are fetched.
+pub async fn fetch(app: &tauri::AppHandle, url: &str) -> Result<Page, String> {
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return Err("Only http and https URLs can be fetched".to_string());
+    }
+    let request = http::client(app)?.get(url);
+    let response = http::send(app, "web", None, request).await?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!("{url} returned {status}"));
+    }
+    let url = response.url().to_string();
+    let is_html = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.contains("html"));
+    let body = response.text().await.map_err(|e| e.to_string())?;
+    if !is_html {
+        return Ok(Page {
+            url,
+            title: None,
+            text: body,
+        });
+    }
+    Ok(Page {
+        url,
+        title: title(&body),
+        text: html_to_text(main_content(&body)),
+    })
+}
+
+/// The byte offset of the first `<name` tag in `lower`, a lowercased document.
+fn find_tag(lower: &str, name: &str) -> Option<usize> {
+    let open = format!("<{name}");
+    let mut at = 0;
+    while let Some(start) = lower[at..].find(&open).map(|i| at + i) {
+        let after = lower[start + open.len()..].chars().next();
+        if after.map_or(true, |c| c == '>' || c == '/' || c.is_whitespace()) {
+            return Some(start);
+        }
+        at = start + open.len();
+    }
+    None
+}
+
+fn title(html: &str) -> Option<String> {
+    let lower = html.to_ascii_lowercase();
+    let start = find_tag(&lower, "title")?;
+    let start = start + lower[start..].find('>')? + 1;
+    let end = start + lower[start..].find("</title")?;
+    let title = decode_entities(html[start..end].trim());
+    (!title.is_empty()).then_some(title)
+}
+
+/// The part of the page most likely to hold its content: the article, else the main
+/// element, else the body. Pages with none of them are used whole.
+fn main_content(html: &str) -> &str {
+    let lower = html.to_ascii_lowercase();
+    for name in ["article", "main", "body"] {
+        if let Some(start) = find_tag(&lower, name) {
+            let end = lower
+                .rfind(&format!("</{name}"))
+                .filter(|&end| end > start)
+                .unwrap_or(html.len());
+            return &html[start..end];
+        }
+    }
+    html
+}
+
+/// The lowercase name of the tag starting at `tag`, just after its `<` or `</`.
+fn tag_name(tag: &str) -> &str {
+    let end = tag
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(tag.len());
+    &tag[..end]
+}
+
+/// Visible text of HTML: markup and non-content elements dropped, entities decoded,
+/// one line per block and whitespace collapsed. Crude, but enough for a model to read.
+pub fn html_to_text(html: &str) -> String {
+    // Lowercasing ASCII keeps byte offsets, so matches in `lower` index `html` too.
+    let lower = html.to_ascii_lowercase();
+    let mut text = String::with_capacity(html.len() / 2);
+    let mut at = 0;
+    while let Some(open) = lower[at..].find('<').map(|i| at + i) {
+        text.push_str(&html[at..open]);
+        let tag = &lower[open..];
+        if tag.starts_with("<!--") {
+            at = tag.find("-->").map_or(html.len(), |i| open + i + 3);
+            continue;
+        }
+        let closing = tag[1..].starts_with('/');
+        let name = tag_name(&tag[1 + usize::from(closing)..]);
+        at = tag.find('>').map_or(html.len(), |i| open + i + 1);
+        if !closing && SKIPPED.contains(&name) {
+            // Resume at the closing tag, which is then handled like any other.
+            at = lower[at..]
+                .find(&format!("</{name}"))
+                .map_or(html.len(), |i| at + i);
+        }
+        if BLOCKS.contains(&name) {
+            text.push('\n');
+        } else if matches!(name, "td" | "th" | "img") {
+            text.push(' ');
+        }
+    }
+    text.push_str(&html[at..]);
+
+    let decoded = decode_entities(&text);
+    let mut lines = Vec::new();
+    for line in decoded.lines() {
+        let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !line.is_empty() {
+            lines.push(line);
+        }
+    }
+    lines.join("\n")
+}
+
+/// Decode the named entities pages use most and every numeric one.
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest[1..]
+            .find(';')
+            .filter(|&end| end <= 10)
+            .map(|end| &rest[1..end + 1]);
+        let character = entity.and_then(|entity| match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            _ => {
+                let number = entity.strip_prefix('#')?;
+                let code = match number.strip_prefix(['x', 'X']) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                    None => number.parse().ok()?,
+                };
+                char::from_u32(code)
+            }
+        });
+        match (entity, character) {
+            (Some(entity), Some(character)) => {
+                decoded.push(character);
+                rest = &rest[entity.len() + 2..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// Fetch a page and return its readable text, for grounding a prompt in it.
+#[tauri::command]
+pub async fn fetch_page(app: tauri::AppHandle, url: String) -> Result<Page, AppError> {
+    fetch(&app, &url).await.map_err(AppError::from)
+}
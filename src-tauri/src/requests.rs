@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::async_runtime::JoinHandle;
+use tauri::{Emitter, Manager};
+use tokio::sync::oneshot;
+
+use crate::db;
+use crate::error::AppError;
+use crate::settings;
+
+/// Backend requests that can still be cancelled, keyed by request id. Entries remove
+/// themselves when their task ends.
+#[derive(Default)]
+pub struct RequestRegistry(Mutex<HashMap<String, Running>>);
+
+struct Running {
+    handle: JoinHandle<()>,
+    started_at: i64,
+}
+
+/// A request that was still running, as recorded for session restore.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActiveRequest {
+    pub id: String,
+    pub started_at: i64,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum CancelReason {
+    Cancelled,
+    Timeout,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RequestCancelled<'a> {
+    request_id: &'a str,
+    reason: CancelReason,
+}
+
+fn emit_cancelled(app: &tauri::AppHandle, request_id: &str, reason: CancelReason) {
+    let _ = app.emit("request-cancelled", RequestCancelled { request_id, reason });
+}
+
+pub fn new_id() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+/// The request timeout from the network settings.
+pub fn default_timeout(app: &tauri::AppHandle) -> Duration {
+    Duration::from_secs(settings::current(app).network.request_timeout_secs)
+}
+
+/// Fail `future` if it hasn't finished within `limit`, emitting `request-cancelled`.
+pub async fn with_timeout<T, E: Into<AppError>>(
+    app: &tauri::AppHandle,
+    request_id: &str,
+    limit: Duration,
+    future: impl Future<Output = Result<T, E>>,
+) -> Result<T, AppError> {
+    match tokio::time::timeout(limit, future).await {
+        Ok(result) => result.map_err(Into::into),
+        Err(_) => {
+            tracing::warn!("Request {request_id} timed out after {limit:?}");
+            emit_cancelled(app, request_id, CancelReason::Timeout);
+            Err(AppError::Timeout(limit.as_secs()))
+        }
+    }
+}
+
+/// Run `future` in the background under `request_id` so `cancel` can stop it.
+pub fn spawn<F>(app: &tauri::AppHandle, request_id: &str, future: F)
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    // Hold the registry lock across the spawn so a fast-finishing task can't try to
+    // remove itself before it has been inserted.
+    let registry = app.state::<RequestRegistry>();
+    let mut active = registry.0.lock().unwrap();
+    let task_app = app.clone();
+    let task_id = request_id.to_string();
+    let handle = tauri::async_runtime::spawn(async move {
+        future.await;
+        task_app
+            .state::<RequestRegistry>()
+            .0
+            .lock()
+            .unwrap()
+            .remove(&task_id);
+    });
+    active.insert(
+        request_id.to_string(),
+        Running {
+            handle,
+            started_at: db::now_ms(),
+        },
+    );
+}
+
+/// Await `future` as a cancellable request, for commands that return its result directly.
+/// Without a `request_id` the request can't be cancelled by the caller, only time out.
+pub async fn run<T, E, F>(
+    app: &tauri::AppHandle,
+    request_id: Option<String>,
+    limit: Option<Duration>,
+    future: F,
+) -> Result<T, AppError>
+where
+    T: Send + 'static,
+    E: Into<AppError> + Send + 'static,
+    F: Future<Output = Result<T, E>> + Send + 'static,
+{
+    let request_id = request_id.unwrap_or_else(new_id);
+    let (tx, rx) = oneshot::channel();
+    let task_app = app.clone();
+    let task_id = request_id.clone();
+    spawn(app, &request_id, async move {
+        let result = match limit {
+            Some(limit) => with_timeout(&task_app, &task_id, limit, future).await,
+            None => future.await.map_err(Into::into),
+        };
+        let _ = tx.send(result);
+    });
+    rx.await.unwrap_or(Err(AppError::Cancelled))
+}
+
+/// Ids of the requests still running.
+pub fn active(app: &tauri::AppHandle) -> Vec<String> {
+    let mut ids: Vec<String> = app
+        .state::<RequestRegistry>()
+        .0
+        .lock()
+        .unwrap()
+        .keys()
+        .cloned()
+        .collect();
+    ids.sort();
+    ids
+}
+
+/// The requests still running, oldest first.
+pub fn running(app: &tauri::AppHandle) -> Vec<ActiveRequest> {
+    let mut running: Vec<ActiveRequest> = app
+        .state::<RequestRegistry>()
+        .0
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(id, running)| ActiveRequest {
+            id: id.clone(),
+            started_at: running.started_at,
+        })
+        .collect();
+    running.sort_by_key(|request| request.started_at);
+    running
+}
+
+/// Stop a running request. Returns `false` if it had already finished.
+pub fn cancel(app: &tauri::AppHandle, request_id: &str) -> bool {
+    let running = app
+        .state::<RequestRegistry>()
+        .0
+        .lock()
+        .unwrap()
+        .remove(request_id);
+    match running {
+        Some(running) => {
+            running.handle.abort();
+            emit_cancelled(app, request_id, CancelReason::Cancelled);
+            true
+        }
+        None => false,
+    }
+}
+
+#[tauri::command]
+pub fn cancel_request(app: tauri::AppHandle, id: String) -> bool {
+    cancel(&app, &id)
+}
@@ -0,0 +1,472 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use chrono::{Datelike, Local, NaiveTime, TimeZone};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, Manager, State};
+use tokio::sync::Notify;
+
+use crate::clipboard::{self, ClipboardContent};
+use crate::db::{self, Database};
+use crate::error::AppError;
+use crate::llm::{self, ChatMessage};
+use crate::notifications::{self, Completion};
+use crate::{history, templates};
+
+/// The longest the scheduler sleeps between checks, so a changed clock or a machine
+/// waking from sleep is noticed within a minute.
+const MAX_SLEEP: Duration = Duration::from_secs(60);
+
+/// Wakes the scheduler loop when jobs change.
+#[derive(Default)]
+pub struct Scheduler(Notify);
+
+/// When a job fires. Times are local to the machine.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Schedule {
+    /// Once, at a Unix time in milliseconds.
+    Once { at: i64 },
+    /// Repeatedly, `minutes` after the previous run.
+    Every { minutes: u32 },
+    /// At `hour:minute` on the given weekdays, 0 being Monday; every day when empty.
+    Daily {
+        hour: u32,
+        minute: u32,
+        #[serde(default)]
+        weekdays: Vec<u32>,
+    },
+}
+
+impl Schedule {
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            Self::Once { at } if *at <= db::now_ms() => {
+                Err("A one-off job must be scheduled in the future".to_string())
+            }
+            Self::Every { minutes: 0 } => Err("minutes must be at least 1".to_string()),
+            Self::Daily { hour, minute, .. } if *hour > 23 || *minute > 59 => {
+                Err("hour and minute must be a valid time of day".to_string())
+            }
+            Self::Daily { weekdays, .. } if weekdays.iter().any(|&day| day > 6) => {
+                Err("weekdays must be between 0 (Monday) and 6 (Sunday)".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The first time after `now` the job should fire, or `None` if it never will again.
+    fn next_after(&self, now: i64) -> Option<i64> {
+        match self {
+            Self::Once { at } => (*at > now).then_some(*at),
+            Self::Every { minutes } => Some(now + i64::from(*minutes) * 60_000),
+            Self::Daily {
+                hour,
+                minute,
+                weekdays,
+            } => {
+                let time = NaiveTime::from_hms_opt(*hour, *minute, 0)?;
+                let today = Local.timestamp_millis_opt(now).single()?.date_naive();
+                // A week and a day covers every weekday, including today's next week.
+                (0..8)
+                    .filter_map(|offset| today.checked_add_days(chrono::Days::new(offset)))
+                    .filter(|date| {
+                        weekdays.is_empty()
+                            || weekdays.contains(&date.weekday().num_days_from_monday())
+                    })
+                    // Skipped by a DST change on that day; move on to the next.
+                    .filter_map(|date| date.and_time(time).and_local_timezone(Local).earliest())
+                    .map(|at| at.timestamp_millis())
+                    .find(|&at| at > now)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduledJob {
+    pub id: String,
+    pub name: String,
+    pub schedule: Schedule,
+    /// Sent to the selected model when the job fires; its answer is saved to history.
+    pub prompt: Option<String>,
+    /// Shown as the notification when there is no prompt.
+    pub message: Option<String>,
+    pub enabled: bool,
+    pub next_run_at: Option<i64>,
+    pub last_run_at: Option<i64>,
+    pub last_error: Option<String>,
+    pub created_at: i64,
+}
+
+const JOB_COLUMNS: &str =
+    "id, name, schedule, prompt, message, enabled, next_run_at, last_run_at, last_error, created_at";
+
+fn job_from_row(row: &Row) -> rusqlite::Result<ScheduledJob> {
+    let schedule: String = row.get(2)?;
+    Ok(ScheduledJob {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        schedule: serde_json::from_str(&schedule).map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(2, rusqlite::types::Type::Text, e.into())
+        })?,
+        prompt: row.get(3)?,
+        message: row.get(4)?,
+        enabled: row.get(5)?,
+        next_run_at: row.get(6)?,
+        last_run_at: row.get(7)?,
+        last_error: row.get(8)?,
+        created_at: row.get(9)?,
+    })
+}
+
+fn find_job(conn: &Connection, id: &str) -> rusqlite::Result<Option<ScheduledJob>> {
+    conn.query_row(
+        &format!("SELECT {JOB_COLUMNS} FROM scheduled_jobs WHERE id = ?1"),
+        [id],
+        job_from_row,
+    )
+    .optional()
+}
+
+/// What a job does and when, as sent by the frontend.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobInput {
+    pub name: String,
+    pub schedule: Schedule,
+    #[serde(default)]
+    pub prompt: Option<String>,
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+impl JobInput {
+    fn validate(mut self) -> Result<Self, String> {
+        self.name = self.name.trim().to_string();
+        if self.name.is_empty() {
+            return Err("A job needs a name".to_string());
+        }
+        let present = |text: Option<String>| text.filter(|text| !text.trim().is_empty());
+        self.prompt = present(self.prompt);
+        self.message = present(self.message);
+        if self.prompt.is_none() && self.message.is_none() {
+            return Err("A job needs a prompt or a message".to_string());
+        }
+        self.schedule.validate()?;
+        Ok(self)
+    }
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JobFired<'a> {
+    job_id: &'a str,
+    /// The conversation holding the prompt and its answer.
+    conversation_id: Option<&'a str>,
+    error: Option<&'a str>,
+}
+
+/// Values for a scheduled prompt's `{{clipboard}}`, `{{clipboard_today}}` (text copied in
+/// the last day), `{{date}}` and `{{time}}`.
+fn prompt_variables(app: &tauri::AppHandle) -> Result<HashMap<String, String>, String> {
+    let now = Local::now();
+    let day_ago = db::now_ms() - 24 * 60 * 60 * 1000;
+    let copied: Vec<String> = app.state::<Database>().with(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT content FROM clipboard_entries
+             WHERE kind = 'text' AND created_at >= ?1 ORDER BY created_at",
+        )?;
+        let texts = stmt.query_map([day_ago], |row| row.get(0))?.collect();
+        texts
+    })?;
+    let clipboard = match clipboard::read(app) {
+        Ok(ClipboardContent::Text { text }) => text,
+        _ => String::new(),
+    };
+    Ok(HashMap::from([
+        ("clipboard".to_string(), clipboard),
+        ("clipboard_today".to_string(), copied.join("\n---\n")),
+        ("date".to_string(), now.format("%Y-%m-%d").to_string()),
+        ("time".to_string(), now.format("%H:%M").to_string()),
+    ]))
+}
+
+/// Send the job's prompt to the selected model and save both sides as a conversation.
+async fn run_prompt(
+    app: &tauri::AppHandle,
+    job: &ScheduledJob,
+    prompt: &str,
+) -> Result<(String, String), String> {
+    let prompt = templates::render(prompt, &prompt_variables(app)?);
+    let answer = llm::complete(
+        app,
+        None,
+        vec![ChatMessage {
+            role: "user".to_string(),
+            content: prompt.clone(),
+            images: Vec::new(),
+        }],
+    )
+    .await?;
+    let conversation_id = uuid::Uuid::new_v4().to_string();
+    app.state::<Database>().with(|conn| {
+        let tx = conn.transaction()?;
+        let now = db::now_ms();
+        tx.execute(
+            "INSERT INTO conversations (id, title, created_at, updated_at) VALUES (?1, ?2, ?3, ?4)",
+            params![conversation_id, job.name, now, now],
+        )?;
+        history::insert_message(&tx, &conversation_id, "user", &prompt)?;
+        history::insert_message(&tx, &conversation_id, "assistant", &answer)?;
+        tx.commit()
+    })?;
+    Ok((conversation_id, answer))
+}
+
+/// Run a job now: its prompt if it has one, then a notification. Emits `job-fired`.
+async fn fire(app: tauri::AppHandle, job: ScheduledJob) {
+    tracing::info!("Running scheduled job {} ({})", job.name, job.id);
+    let outcome = match &job.prompt {
+        Some(prompt) => run_prompt(&app, &job, prompt).await.map(Some),
+        None => Ok(None),
+    };
+    let (conversation_id, error) = match outcome {
+        Ok(Some((conversation_id, answer))) => {
+            let completion = Completion {
+                request_id: None,
+                conversation_id: Some(conversation_id.clone()),
+                text: answer,
+            };
+            let body = notifications::preview(&completion.text);
+            notifications::show(&app, &job.name, &body, Some(completion));
+            (Some(conversation_id), None)
+        }
+        Ok(None) => {
+            let message = job.message.as_deref().unwrap_or_default();
+            notifications::show(&app, &job.name, message, None);
+            (None, None)
+        }
+        Err(error) => {
+            tracing::warn!("Scheduled job {} failed: {error}", job.id);
+            notifications::show(&app, &job.name, &format!("Failed: {error}"), None);
+            (None, Some(error))
+        }
+    };
+    let recorded = app.state::<Database>().with(|conn| {
+        conn.execute(
+            "UPDATE scheduled_jobs SET last_error = ?2 WHERE id = ?1",
+            params![job.id, error],
+        )
+    });
+    if let Err(error) = recorded {
+        tracing::error!("Failed to record the outcome of job {}: {error}", job.id);
+    }
+    let _ = app.emit(
+        "job-fired",
+        JobFired {
+            job_id: &job.id,
+            conversation_id: conversation_id.as_deref(),
+            error: error.as_deref(),
+        },
+    );
+}
+
+/// Claim every job that is due: move it to its next run, or disable it if it has none,
+/// then return it. Jobs that came due while the app was closed fire once now.
+fn take_due(app: &tauri::AppHandle) -> Result<Vec<ScheduledJob>, String> {
+    let now = db::now_ms();
+    app.state::<Database>().with(|conn| {
+        let tx = conn.transaction()?;
+        let due: Vec<ScheduledJob> = {
+            let mut stmt = tx.prepare(&format!(
+                "SELECT {JOB_COLUMNS} FROM scheduled_jobs
+                 WHERE enabled = 1 AND next_run_at <= ?1"
+            ))?;
+            let jobs = stmt
+                .query_map([now], job_from_row)?
+                .collect::<Result<_, _>>()?;
+            jobs
+        };
+        for job in &due {
+            let next = job.schedule.next_after(now);
+            tx.execute(
+                "UPDATE scheduled_jobs
+                 SET next_run_at = ?2, enabled = ?3, last_run_at = ?4 WHERE id = ?1",
+                params![job.id, next, next.is_some(), now],
+            )?;
+        }
+        tx.commit()?;
+        Ok(due)
+    })
+}
+
+fn earliest_run(app: &tauri::AppHandle) -> Result<Option<i64>, String> {
+    app.state::<Database>().with(|conn| {
+        conn.query_row(
+            "SELECT MIN(next_run_at) FROM scheduled_jobs WHERE enabled = 1",
+            [],
+            |row| row.get(0),
+        )
+    })
+}
+
+/// Start the loop that fires jobs. Call once the database is open.
+pub fn start(app: &tauri::AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            match take_due(&app) {
+                Ok(due) => {
+                    for job in due {
+                        tauri::async_runtime::spawn(fire(app.clone(), job));
+                    }
+                }
+                Err(error) => tracing::error!("Failed to read scheduled jobs: {error}"),
+            }
+            let wait = match earliest_run(&app) {
+                Ok(Some(at)) => Duration::from_millis((at - db::now_ms()).max(0) as u64),
+                _ => MAX_SLEEP,
+            };
+            let scheduler = app.state::<Scheduler>();
+            let _ = tokio::time::timeout(wait.min(MAX_SLEEP), scheduler.0.notified()).await;
+        }
+    });
+}
+
+fn wake(app: &tauri::AppHandle) {
+    app.state::<Scheduler>().0.notify_one();
+}
+
+#[tauri::command]
+pub async fn list_scheduled_jobs(db: State<'_, Database>) -> Result<Vec<ScheduledJob>, AppError> {
+    db.with(|conn| {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {JOB_COLUMNS} FROM scheduled_jobs ORDER BY created_at"
+        ))?;
+        let jobs = stmt.query_map([], job_from_row)?.collect();
+        jobs
+    })
+    .map_err(AppError::from)
+}
+
+#[tauri::command]
+pub async fn create_scheduled_job(
+    app: tauri::AppHandle,
+    db: State<'_, Database>,
+    job: JobInput,
+) -> Result<ScheduledJob, AppError> {
+    let job = job.validate()?;
+    let now = db::now_ms();
+    let job = ScheduledJob {
+        id: uuid::Uuid::new_v4().to_string(),
+        next_run_at: job.schedule.next_after(now),
+        name: job.name,
+        schedule: job.schedule,
+        prompt: job.prompt,
+        message: job.message,
+        enabled: true,
+        last_run_at: None,
+        last_error: None,
+        created_at: now,
+    };
+    let schedule = serde_json::to_string(&job.schedule).map_err(|e| e.to_string())?;
+    db.with(|conn| {
+        conn.execute(
+            "INSERT INTO scheduled_jobs (id, name, schedule, prompt, message, next_run_at, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                job.id,
+                job.name,
+                schedule,
+                job.prompt,
+                job.message,
+                job.next_run_at,
+                job.created_at
+            ],
+        )
+    })?;
+    wake(&app);
+    Ok(job)
+}
+
+/// Replace what a job does and when. Its next run is worked out afresh.
+#[tauri::command]
+pub async fn update_scheduled_job(
+    app: tauri::AppHandle,
+    db: State<'_, Database>,
+    id: String,
+    job: JobInput,
+) -> Result<ScheduledJob, AppError> {
+    let input = job.validate()?;
+    let schedule = serde_json::to_string(&input.schedule).map_err(|e| e.to_string())?;
+    let next_run_at = input.schedule.next_after(db::now_ms());
+    let job = db.with(|conn| {
+        conn.execute(
+            "UPDATE scheduled_jobs
+             SET name = ?2, schedule = ?3, prompt = ?4, message = ?5, next_run_at = ?6
+             WHERE id = ?1",
+            params![
+                id,
+                input.name,
+                schedule,
+                input.prompt,
+                input.message,
+                next_run_at
+            ],
+        )?;
+        find_job(conn, &id)
+    })?;
+    wake(&app);
+    job.ok_or_else(|| AppError::NotFound(format!("No scheduled job {id}")))
+}
+
+/// Pause or resume a job. Resuming schedules it from now, so missed runs are skipped.
+#[tauri::command]
+pub async fn set_scheduled_job_enabled(
+    app: tauri::AppHandle,
+    db: State<'_, Database>,
+    id: String,
+    enabled: bool,
+) -> Result<ScheduledJob, AppError> {
+    let job = db.with(|conn| {
+        let Some(job) = find_job(conn, &id)? else {
+            return Ok(None);
+        };
+        let next_run_at = if enabled {
+            job.schedule.next_after(db::now_ms())
+        } else {
+            job.next_run_at
+        };
+        conn.execute(
+            "UPDATE scheduled_jobs SET enabled = ?2, next_run_at = ?3 WHERE id = ?1",
+            params![id, enabled && next_run_at.is_some(), next_run_at],
+        )?;
+        find_job(conn, &id)
+    })?;
+    wake(&app);
+    job.ok_or_else(|| AppError::NotFound(format!("No scheduled job {id}")))
+}
+
+#[tauri::command]
+pub async fn delete_scheduled_job(db: State<'_, Database>, id: String) -> Result<bool, AppError> {
+    let deleted =
+        db.with(|conn| conn.execute("DELETE FROM scheduled_jobs WHERE id = ?1", [&id]))?;
+    Ok(deleted > 0)
+}
+
+/// Fire a job right away without changing its schedule.
+#[tauri::command]
+pub async fn run_scheduled_job(
+    app: tauri::AppHandle,
+    db: State<'_, Database>,
+    id: String,
+) -> Result<(), AppError> {
+    let job = db
+        .with(|conn| find_job(conn, &id))?
+        .ok_or_else(|| format!("No scheduled job {id}"))?;
+    tauri::async_runtime::spawn(fire(app, job));
+    Ok(())
+}
@@ -0,0 +1,343 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::pin::pin;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use futures_util::future::{self, Either};
+use image::Rgba;
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, Manager, WebviewWindow};
+use tokio::sync::oneshot;
+
+use super::picker::{self, Capture};
+use super::Region;
+use crate::capabilities::{self, Capability};
+use crate::error::AppError;
+use crate::{db, persist};
+
+const LABEL_PREFIX: &str = "eyedropper-";
+const HISTORY_FILE: &str = "picked-colors.json";
+/// Colors kept in the history, newest first.
+const HISTORY_LIMIT: usize = 50;
+/// How often the cursor is sampled for the zoom preview.
+const PREVIEW_INTERVAL: Duration = Duration::from_millis(30);
+/// Pixels across the zoom preview, centred on the one under the cursor.
+const LOUPE_SIZE: i64 = 11;
+
+/// A point in CSS pixels, relative to the eyedropper window it was picked in.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rgb {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+/// Hue in degrees, saturation and lightness in percent.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Hsl {
+    pub h: f32,
+    pub s: f32,
+    pub l: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PickedColor {
+    /// `#rrggbb`.
+    pub hex: String,
+    pub rgb: Rgb,
+    pub hsl: Hsl,
+    pub picked_at: i64,
+}
+
+impl PickedColor {
+    fn new(rgb: Rgb) -> Self {
+        Self {
+            hex: hex(rgb),
+            rgb,
+            hsl: hsl(rgb),
+            picked_at: db::now_ms(),
+        }
+    }
+}
+
+fn hex(Rgb { r, g, b }: Rgb) -> String {
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+fn hsl(Rgb { r, g, b }: Rgb) -> Hsl {
+    let [r, g, b] = [r, g, b].map(|channel| f32::from(channel) / 255.0);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let lightness = (max + min) / 2.0;
+    let delta = max - min;
+    if delta == 0.0 {
+        return Hsl {
+            h: 0.0,
+            s: 0.0,
+            l: (lightness * 100.0).round(),
+        };
+    }
+    let saturation = delta / (1.0 - (2.0 * lightness - 1.0).abs());
+    let hue = if max == r {
+        ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        (b - r) / delta + 2.0
+    } else {
+        (r - g) / delta + 4.0
+    };
+    Hsl {
+        h: (hue * 60.0).round() % 360.0,
+        s: (saturation * 100.0).round(),
+        l: (lightness * 100.0).round(),
+    }
+}
+
+/// Colors picked so far, newest first, kept across restarts.
+pub struct ColorHistory {
+    path: PathBuf,
+    colors: Mutex<Vec<PickedColor>>,
+}
+
+impl ColorHistory {
+    pub fn load(app: &tauri::AppHandle) -> Result<Self, String> {
+        let path = persist::data_file(app, HISTORY_FILE)?;
+        let colors = persist::load_json(&path);
+        Ok(Self {
+            path,
+            colors: Mutex::new(colors),
+        })
+    }
+
+    /// Put `color` first, dropping an earlier pick of the same color.
+    fn add(&self, color: PickedColor) {
+        let mut colors = self.colors.lock().unwrap();
+        colors.retain(|other| other.hex != color.hex);
+        colors.insert(0, color);
+        colors.truncate(HISTORY_LIMIT);
+        if let Err(error) = persist::save_json(&self.path, &*colors) {
+            tracing::warn!("Could not save the picked colors: {error}");
+        }
+    }
+}
+
+type Picked = Option<(String, Point)>;
+
+struct Pick {
+    sender: oneshot::Sender<Picked>,
+    /// The frozen screen each eyedropper window shows, by window label.
+    backgrounds: HashMap<String, PathBuf>,
+}
+
+/// The color pick in progress, if any. There is only ever one.
+#[derive(Default)]
+pub struct EyedropperState(Mutex<Option<Pick>>);
+
+/// What the zoom preview shows around the cursor.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Preview {
+    /// Where the cursor is, in CSS pixels within the window.
+    x: f64,
+    y: f64,
+    color: String,
+    /// `LOUPE_SIZE` rows of `LOUPE_SIZE` colors, `None` past the edge of the screen.
+    pixels: Vec<Option<String>>,
+    size: i64,
+}
+
+fn finish(app: &tauri::AppHandle, picked: Picked) {
+    if let Some(pick) = app.state::<EyedropperState>().0.lock().unwrap().take() {
+        let _ = pick.sender.send(picked);
+    }
+}
+
+fn pixel(capture: &Capture, x: i64, y: i64) -> Option<Rgb> {
+    let (x, y) = (u32::try_from(x).ok()?, u32::try_from(y).ok()?);
+    let Rgba([r, g, b, _]) = *capture.image.get_pixel_checked(x, y)?;
+    Some(Rgb { r, g, b })
+}
+
+/// The zoom preview around the pixel at physical offset `x`, `y` on the capture's monitor.
+fn preview(capture: &Capture, x: i32, y: i32) -> Option<Preview> {
+    let ratio = f64::from(capture.image.width()) / f64::from(capture.size.width);
+    let image_x = (f64::from(x) * ratio) as i64;
+    let image_y = (f64::from(y) * ratio) as i64;
+    let center = pixel(capture, image_x, image_y)?;
+    let half = LOUPE_SIZE / 2;
+    let pixels = (-half..=half)
+        .flat_map(|dy| (-half..=half).map(move |dx| (dx, dy)))
+        .map(|(dx, dy)| pixel(capture, image_x + dx, image_y + dy).map(hex))
+        .collect();
+    Some(Preview {
+        x: f64::from(x) / capture.scale_factor,
+        y: f64::from(y) / capture.scale_factor,
+        color: hex(center),
+        pixels,
+        size: LOUPE_SIZE,
+    })
+}
+
+/// Follow the cursor across the frozen monitors until the pick ends, sending the window
+/// under it a zoom preview as `eyedropper-preview` whenever it moves. The pointer is
+/// sampled rather than hooked, which works wherever the cursor position can be read.
+async fn follow(app: &tauri::AppHandle, captures: &[Capture]) {
+    let mut last = None;
+    loop {
+        tokio::time::sleep(PREVIEW_INTERVAL).await;
+        let Ok(cursor) = app.cursor_position() else {
+            continue;
+        };
+        let (x, y) = (cursor.x as i32, cursor.y as i32);
+        if last.replace((x, y)) == Some((x, y)) {
+            continue;
+        }
+        let Some(capture) = captures.iter().find(|capture| {
+            let region = Region {
+                x: capture.position.x,
+                y: capture.position.y,
+                width: capture.size.width,
+                height: capture.size.height,
+            };
+            region.contains(x, y)
+        }) else {
+            continue;
+        };
+        if let Some(preview) = preview(capture, x - capture.position.x, y - capture.position.y) {
+            let _ = app.emit_to(capture.label.as_str(), "eyedropper-preview", preview);
+        }
+    }
+}
+
+async fn pick(
+    app: &tauri::AppHandle,
+    captures: &[Capture],
+    receiver: oneshot::Receiver<Picked>,
+) -> Result<Option<PickedColor>, AppError> {
+    if let Some(pick) = app.state::<EyedropperState>().0.lock().unwrap().as_mut() {
+        pick.backgrounds = captures
+            .iter()
+            .map(|capture| (capture.label.clone(), capture.background.clone()))
+            .collect();
+    }
+    let cursor = super::cursor_monitor(app).ok().map(|m| *m.position());
+    for capture in captures {
+        let window =
+            picker::open_window(app, capture, "eyedropper", "Aikeya Color Picker", |app| {
+                finish(app, None)
+            })?;
+        if Some(capture.position) == cursor {
+            let _ = window.set_focus();
+        }
+    }
+
+    let picked = match future::select(receiver, pin!(follow(app, captures))).await {
+        Either::Left((Ok(Some(picked)), _)) => picked,
+        _ => return Ok(None),
+    };
+    let (label, point) = picked;
+    let Some(rgb) = captures
+        .iter()
+        .find(|capture| capture.label == label)
+        .and_then(|capture| {
+            let ratio = capture.image_scale();
+            pixel(capture, (point.x * ratio) as i64, (point.y * ratio) as i64)
+        })
+    else {
+        return Ok(None);
+    };
+    let color = PickedColor::new(rgb);
+    app.state::<ColorHistory>().add(color.clone());
+    Ok(Some(color))
+}
+
+/// Let the user pick a color from anywhere on screen, returning it as hex, RGB and HSL
+/// and adding it to the picked-colors history. Each monitor is frozen and shown in a
+/// full-screen window at `/eyedropper`, which gets a zoom preview of the pixels under
+/// the cursor as `eyedropper-preview` and reports the click with `finish_color_pick`.
+/// Returns `None` if the pick was cancelled.
+#[tauri::command]
+pub async fn pick_color(app: tauri::AppHandle) -> Result<Option<PickedColor>, AppError> {
+    capabilities::require(&app, Capability::ScreenCapture, "Color picker").await?;
+    let (sender, receiver) = oneshot::channel();
+    {
+        let state = app.state::<EyedropperState>();
+        let mut active = state.0.lock().unwrap();
+        if active.is_some() {
+            return Err(AppError::InvalidInput(
+                "A color is already being picked".into(),
+            ));
+        }
+        *active = Some(Pick {
+            sender,
+            backgrounds: HashMap::new(),
+        });
+    }
+
+    let overlay = picker::hide_overlay(&app).await;
+    let handle = app.clone();
+    // Errors from here on still need the cleanup below.
+    let captures = tauri::async_runtime::spawn_blocking(move || {
+        picker::capture_monitors(&handle, LABEL_PREFIX, false)
+    })
+    .await
+    .map_err(AppError::from)
+    .and_then(|captures| captures.map_err(AppError::from));
+    let result = match &captures {
+        Ok(captures) => pick(&app, captures, receiver).await,
+        Err(error) => Err(error.clone()),
+    };
+
+    app.state::<EyedropperState>().0.lock().unwrap().take();
+    picker::close_windows(
+        &app,
+        LABEL_PREFIX,
+        captures.iter().flatten(),
+        overlay.as_ref(),
+    );
+    result
+}
+
+/// The frozen screen an eyedropper window should show.
+#[tauri::command]
+pub fn get_eyedropper_background(
+    app: tauri::AppHandle,
+    window: WebviewWindow,
+) -> Result<PathBuf, AppError> {
+    app.state::<EyedropperState>()
+        .0
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|pick| pick.backgrounds.get(window.label()).cloned())
+        .ok_or_else(|| AppError::NotFound("No color pick for this window".into()))
+}
+
+/// Called by an eyedropper window with the point the user clicked, or without one when
+/// they pressed Escape.
+#[tauri::command]
+pub fn finish_color_pick(app: tauri::AppHandle, window: WebviewWindow, point: Option<Point>) {
+    finish(&app, point.map(|point| (window.label().to_string(), point)));
+}
+
+/// Picked colors, newest first.
+#[tauri::command]
+pub fn list_picked_colors(app: tauri::AppHandle) -> Vec<PickedColor> {
+    app.state::<ColorHistory>().colors.lock().unwrap().clone()
+}
+
+#[tauri::command]
+pub fn clear_picked_colors(app: tauri::AppHandle) -> Result<(), AppError> {
+    let history = app.state::<ColorHistory>();
+    let mut colors = history.colors.lock().unwrap();
+    colors.clear();
+    persist::save_json(&history.path, &*colors)?;
+    Ok(())
+}
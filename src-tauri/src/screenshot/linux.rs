@@ -0,0 +1,112 @@
+use image::RgbaImage;
+use tauri::Monitor;
+use x11rb::protocol::xproto::{ConnectionExt, ImageFormat, Window};
+use x11rb::rust_connection::RustConnection;
+
+use super::Region;
+use crate::x11::{self, is_wayland};
+
+pub fn capture_monitor(app: &tauri::AppHandle, monitor: &Monitor) -> Result<RgbaImage, String> {
+    if is_wayland() {
+        return portal::capture_monitor(app, monitor);
+    }
+    let (conn, root) = x11::connect()?;
+    let position = monitor.position();
+    let size = monitor.size();
+    get_image(
+        &conn,
+        root,
+        position.x as i16,
+        position.y as i16,
+        size.width as u16,
+        size.height as u16,
+    )
+}
+
+pub fn capture_foreground_window() -> Result<RgbaImage, String> {
+    if is_wayland() {
+        return Err(
+            "Capturing a single window is not supported on Wayland; use screen or region mode"
+                .to_string(),
+        );
+    }
+    let (conn, root) = x11::connect()?;
+    let bounds = x11::foreground_window_bounds(&conn, root)?;
+    // Grab from the root window so compositing and child windows come out as seen.
+    get_image(
+        &conn,
+        root,
+        bounds.x as i16,
+        bounds.y as i16,
+        bounds.width as u16,
+        bounds.height as u16,
+    )
+}
+
+pub fn foreground_window_bounds() -> Result<Region, String> {
+    let (conn, root) = x11::connect_for_windows()?;
+    x11::foreground_window_bounds(&conn, root)
+}
+
+fn get_image(
+    conn: &RustConnection,
+    drawable: Window,
+    x: i16,
+    y: i16,
+    width: u16,
+    height: u16,
+) -> Result<RgbaImage, String> {
+    let reply = conn
+        .get_image(ImageFormat::Z_PIXMAP, drawable, x, y, width, height, !0)
+        .map_err(|e| e.to_string())?
+        .reply()
+        .map_err(|e| e.to_string())?;
+    // Every TrueColor visual a modern X server offers at depth 24/32 is BGRX.
+    if reply.depth < 24 {
+        return Err(format!("Unsupported X11 visual depth {}", reply.depth));
+    }
+    let rgba: Vec<u8> = reply
+        .data
+        .chunks_exact(4)
+        .flat_map(|px| [px[2], px[1], px[0], 255])
+        .collect();
+    RgbaImage::from_raw(width as u32, height as u32, rgba)
+        .ok_or_else(|| "X11 returned a truncated image".to_string())
+}
+
+mod portal {
+    use super::*;
+    use ashpd::desktop::screenshot::Screenshot;
+
+    async fn request() -> Result<RgbaImage, String> {
+        let response = Screenshot::request()
+            .interactive(false)
+            .modal(false)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?
+            .response()
+            .map_err(|e| e.to_string())?;
+        let path = response
+            .uri()
+            .to_file_path()
+            .map_err(|_| "Screenshot portal returned a non-file URI".to_string())?;
+        Ok(image::open(path).map_err(|e| e.to_string())?.to_rgba8())
+    }
+
+    /// The portal captures the whole desktop; cut the requested monitor out of it when the
+    /// layout maps cleanly onto the image (it won't under fractional scaling).
+    pub fn capture_monitor(app: &tauri::AppHandle, monitor: &Monitor) -> Result<RgbaImage, String> {
+        let desktop = tauri::async_runtime::block_on(request())?;
+        let monitors = app.available_monitors().map_err(|e| e.to_string())?;
+        let min_x = monitors.iter().map(|m| m.position().x).min().unwrap_or(0);
+        let min_y = monitors.iter().map(|m| m.position().y).min().unwrap_or(0);
+        let x = (monitor.position().x - min_x) as u32;
+        let y = (monitor.position().y - min_y) as u32;
+        let size = monitor.size();
+        if x + size.width > desktop.width() || y + size.height > desktop.height() {
+            return Ok(desktop);
+        }
+        Ok(image::imageops::crop_imm(&desktop, x, y, size.width, size.height).to_image())
+    }
+}
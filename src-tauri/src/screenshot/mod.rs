@@ -0,0 +1,218 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
+use tauri::{Manager, Monitor};
+
+use crate::capabilities::{self, Capability};
+use crate::error::AppError;
+use crate::focus::{self, AppContext};
+use crate::window_manager::{self, AppWindow};
+
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "linux")]
+use linux as backend;
+
+#[cfg(any(target_os = "macos", windows))]
+mod native;
+#[cfg(any(target_os = "macos", windows))]
+use native as backend;
+
+pub mod eyedropper;
+pub mod picker;
+
+/// Give the compositor time to repaint after hiding the overlay, so it isn't in the shot.
+const HIDE_SETTLE_DELAY: Duration = Duration::from_millis(150);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScreenshotMode {
+    Screen,
+    Window,
+    Region,
+}
+
+/// A rectangle in physical desktop coordinates.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Region {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Region {
+    pub fn of_monitor(monitor: &Monitor) -> Self {
+        Self {
+            x: monitor.position().x,
+            y: monitor.position().y,
+            width: monitor.size().width,
+            height: monitor.size().height,
+        }
+    }
+
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x
+            && y >= self.y
+            && x < self.x + self.width as i32
+            && y < self.y + self.height as i32
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Screenshot {
+    pub id: String,
+    pub path: PathBuf,
+    pub width: u32,
+    pub height: u32,
+    /// The app in front when it was taken.
+    pub app: Option<AppContext>,
+}
+
+/// The app in front when each screenshot of this run was taken, by id.
+static SHOWN_APPS: Mutex<BTreeMap<String, AppContext>> = Mutex::new(BTreeMap::new());
+
+fn screenshots_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_cache_dir()
+        .map_err(|e| e.to_string())?
+        .join("screenshots");
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir)
+}
+
+/// Where a screenshot returned by `capture_screenshot` lives on disk.
+pub fn screenshot_path(app: &tauri::AppHandle, id: &str) -> Result<PathBuf, String> {
+    if uuid::Uuid::parse_str(id).is_err() {
+        return Err(format!("Invalid screenshot id: {id}"));
+    }
+    Ok(screenshots_dir(app)?.join(format!("{id}.png")))
+}
+
+fn save(app: &tauri::AppHandle, image: &RgbaImage) -> Result<Screenshot, String> {
+    let id = uuid::Uuid::new_v4().to_string();
+    let path = screenshot_path(app, &id)?;
+    image.save(&path).map_err(|e| e.to_string())?;
+    let shown_app = focus::current_context().or_else(|| focus::previous_context(app));
+    if let Some(context) = &shown_app {
+        SHOWN_APPS
+            .lock()
+            .unwrap()
+            .insert(id.clone(), context.clone());
+    }
+    Ok(Screenshot {
+        id,
+        path,
+        width: image.width(),
+        height: image.height(),
+        app: shown_app,
+    })
+}
+
+/// The app that was in front when the screenshot `id` was taken, if it is from this run.
+pub fn shown_app(id: &str) -> Option<AppContext> {
+    SHOWN_APPS.lock().unwrap().get(id).cloned()
+}
+
+/// The monitor under the cursor, falling back to the primary one.
+pub fn cursor_monitor(app: &tauri::AppHandle) -> Result<Monitor, String> {
+    let monitors = app.available_monitors().map_err(|e| e.to_string())?;
+    if let Ok(cursor) = app.cursor_position() {
+        let (x, y) = (cursor.x as i32, cursor.y as i32);
+        if let Some(monitor) = monitors
+            .iter()
+            .find(|m| Region::of_monitor(m).contains(x, y))
+        {
+            return Ok(monitor.clone());
+        }
+    }
+    app.primary_monitor()
+        .map_err(|e| e.to_string())?
+        .or_else(|| monitors.into_iter().next())
+        .ok_or_else(|| "No monitor found".to_string())
+}
+
+/// Bounds of the frontmost window that isn't ours, in physical desktop coordinates.
+pub fn foreground_window_bounds() -> Result<Region, String> {
+    backend::foreground_window_bounds()
+}
+
+fn capture_region(app: &tauri::AppHandle, region: Region) -> Result<RgbaImage, String> {
+    if region.width == 0 || region.height == 0 {
+        return Err("Region must not be empty".to_string());
+    }
+    let monitors = app.available_monitors().map_err(|e| e.to_string())?;
+    let monitor = monitors
+        .iter()
+        .find(|m| Region::of_monitor(m).contains(region.x, region.y))
+        .ok_or_else(|| "Region is not on any monitor".to_string())?;
+
+    // Crop out of the monitor capture; a region spilling onto a neighbouring monitor is
+    // clamped to the one its top-left corner sits on.
+    let bounds = Region::of_monitor(monitor);
+    let image = backend::capture_monitor(app, monitor)?;
+    let x = (region.x - bounds.x) as u32;
+    let y = (region.y - bounds.y) as u32;
+    let width = region.width.min(image.width().saturating_sub(x));
+    let height = region.height.min(image.height().saturating_sub(y));
+    Ok(image::imageops::crop_imm(&image, x, y, width, height).to_image())
+}
+
+/// Capture the frontmost window that isn't ours and save it. Callers answer for the
+/// screen capture permission themselves.
+pub fn capture_window(app: &tauri::AppHandle) -> Result<Screenshot, String> {
+    save(app, &backend::capture_foreground_window()?)
+}
+
+fn capture(
+    app: &tauri::AppHandle,
+    mode: ScreenshotMode,
+    region: Option<Region>,
+) -> Result<RgbaImage, String> {
+    match mode {
+        ScreenshotMode::Screen => backend::capture_monitor(app, &cursor_monitor(app)?),
+        ScreenshotMode::Window => backend::capture_foreground_window(),
+        ScreenshotMode::Region => {
+            let region = region.ok_or_else(|| "Region mode needs a region".to_string())?;
+            capture_region(app, region)
+        }
+    }
+}
+
+/// Capture the screen under the cursor, the frontmost window that isn't ours, or a
+/// region of the desktop, saving it as a PNG in the app cache.
+#[tauri::command]
+pub async fn capture_screenshot(
+    app: tauri::AppHandle,
+    mode: ScreenshotMode,
+    region: Option<Region>,
+) -> Result<Screenshot, AppError> {
+    capabilities::require(&app, Capability::ScreenCapture, "Screenshot").await?;
+    tauri::async_runtime::spawn_blocking(move || {
+        // Window mode already skips our own windows; for the others, step out of the way.
+        let overlay = window_manager::get(&app, AppWindow::Overlay)
+            .filter(|window| window.is_visible().unwrap_or(false))
+            .filter(|_| mode != ScreenshotMode::Window);
+        if let Some(overlay) = &overlay {
+            overlay.hide().map_err(|e| e.to_string())?;
+            thread::sleep(HIDE_SETTLE_DELAY);
+        }
+
+        let result = capture(&app, mode, region);
+
+        if let Some(overlay) = &overlay {
+            let _ = overlay.show();
+            let _ = overlay.set_focus();
+        }
+        save(&app, &result?)
+    })
+    .await?
+    .map_err(AppError::from)
+}
@@ -0,0 +1,67 @@
+use image::RgbaImage;
+use tauri::Monitor;
+use xcap::{Monitor as XcapMonitor, Window as XcapWindow};
+
+use super::Region;
+
+/// xcap reports macOS monitor geometry in points, while Tauri works in physical pixels.
+fn to_physical(value: i32, scale_factor: f64) -> i32 {
+    if cfg!(target_os = "macos") {
+        (value as f64 * scale_factor).round() as i32
+    } else {
+        value
+    }
+}
+
+pub fn capture_monitor(_app: &tauri::AppHandle, monitor: &Monitor) -> Result<RgbaImage, String> {
+    let target = monitor.position();
+    let scale = monitor.scale_factor();
+    let monitors = XcapMonitor::all().map_err(|e| e.to_string())?;
+    let closest = monitors
+        .into_iter()
+        .filter_map(|m| {
+            let x = to_physical(m.x().ok()?, scale);
+            let y = to_physical(m.y().ok()?, scale);
+            let distance = (x - target.x).abs() + (y - target.y).abs();
+            Some((distance, m))
+        })
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, m)| m)
+        .ok_or_else(|| "No monitor found".to_string())?;
+    closest.capture_image().map_err(|e| e.to_string())
+}
+
+/// The topmost visible window that doesn't belong to this process.
+fn foreground_window() -> Result<XcapWindow, String> {
+    let own_pid = std::process::id();
+    let windows = XcapWindow::all().map_err(|e| e.to_string())?;
+    windows
+        .into_iter()
+        .filter(|w| w.pid().map(|pid| pid != own_pid).unwrap_or(false))
+        .filter(|w| !w.is_minimized().unwrap_or(true))
+        .filter(|w| w.width().unwrap_or(0) > 0 && w.height().unwrap_or(0) > 0)
+        .max_by_key(|w| (w.is_focused().unwrap_or(false), w.z().unwrap_or(i32::MIN)))
+        .ok_or_else(|| "No foreground window found".to_string())
+}
+
+pub fn capture_foreground_window() -> Result<RgbaImage, String> {
+    foreground_window()?
+        .capture_image()
+        .map_err(|e| e.to_string())
+}
+
+pub fn foreground_window_bounds() -> Result<Region, String> {
+    let window = foreground_window()?;
+    let scale = window
+        .current_monitor()
+        .and_then(|m| m.scale_factor())
+        .unwrap_or(1.0) as f64;
+    let width = window.width().map_err(|e| e.to_string())? as i32;
+    let height = window.height().map_err(|e| e.to_string())? as i32;
+    Ok(Region {
+        x: to_physical(window.x().map_err(|e| e.to_string())?, scale),
+        y: to_physical(window.y().map_err(|e| e.to_string())?, scale),
+        width: to_physical(width, scale) as u32,
+        height: to_physical(height, scale) as u32,
+    })
+}
@@ -0,0 +1,296 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use image::RgbaImage;
+use serde::Deserialize;
+use tauri::{
+    CursorIcon, Manager, PhysicalPosition, PhysicalSize, WebviewUrl, WebviewWindow,
+    WebviewWindowBuilder, WindowEvent,
+};
+use tokio::sync::oneshot;
+
+use super::{backend, save, screenshots_dir, Screenshot, HIDE_SETTLE_DELAY};
+use crate::capabilities::{self, Capability};
+use crate::error::AppError;
+use crate::window_manager::{self, AppWindow};
+
+const LABEL_PREFIX: &str = "region-picker-";
+/// How much of the original brightness the frozen screen keeps behind the selection.
+const DIM: f32 = 0.55;
+
+/// A rectangle in CSS pixels, relative to the picker window it was drawn in.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct Selection {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+type Picked = Option<(String, Selection)>;
+
+struct Pick {
+    sender: oneshot::Sender<Picked>,
+    /// The dimmed capture each picker window shows, by window label.
+    backgrounds: HashMap<String, PathBuf>,
+}
+
+/// The region pick in progress, if any. There is only ever one.
+#[derive(Default)]
+pub struct RegionPickerState(Mutex<Option<Pick>>);
+
+/// One monitor as it was when the pick started.
+pub(super) struct Capture {
+    pub label: String,
+    pub position: PhysicalPosition<i32>,
+    pub size: PhysicalSize<u32>,
+    pub scale_factor: f64,
+    pub image: RgbaImage,
+    pub background: PathBuf,
+}
+
+impl Capture {
+    /// Image pixels per CSS pixel of the window showing it, whatever resolution the
+    /// capture came back at.
+    pub fn image_scale(&self) -> f64 {
+        let logical_width = self.size.width as f64 / self.scale_factor;
+        self.image.width() as f64 / logical_width
+    }
+}
+
+fn dimmed(image: &RgbaImage) -> RgbaImage {
+    let mut dimmed = image.clone();
+    for pixel in dimmed.pixels_mut() {
+        for channel in &mut pixel.0[..3] {
+            *channel = (*channel as f32 * DIM) as u8;
+        }
+    }
+    dimmed
+}
+
+/// Freeze every monitor, so the picker windows can show the screen as it was and the
+/// selection is cut from that rather than from whatever is there afterwards. Windows
+/// are labelled `label_prefix` and the monitor's index; `dim` darkens what they show.
+pub(super) fn capture_monitors(
+    app: &tauri::AppHandle,
+    label_prefix: &str,
+    dim: bool,
+) -> Result<Vec<Capture>, String> {
+    let dir = screenshots_dir(app)?;
+    let monitors = app.available_monitors().map_err(|e| e.to_string())?;
+    let mut captures = Vec::with_capacity(monitors.len());
+    for (index, monitor) in monitors.iter().enumerate() {
+        let image = backend::capture_monitor(app, monitor)?;
+        let label = format!("{label_prefix}{index}");
+        let background = dir.join(format!("{label}.png"));
+        let shown = if dim { dimmed(&image) } else { image.clone() };
+        shown.save(&background).map_err(|e| e.to_string())?;
+        captures.push(Capture {
+            label,
+            position: *monitor.position(),
+            size: *monitor.size(),
+            scale_factor: monitor.scale_factor(),
+            image,
+            background,
+        });
+    }
+    Ok(captures)
+}
+
+fn finish(app: &tauri::AppHandle, picked: Picked) {
+    if let Some(pick) = app.state::<RegionPickerState>().0.lock().unwrap().take() {
+        let _ = pick.sender.send(picked);
+    }
+}
+
+/// A full-screen window over the monitor `capture` froze, showing the page at `route`.
+/// `on_closed` runs if the user closes it some other way than by picking.
+pub(super) fn open_window(
+    app: &tauri::AppHandle,
+    capture: &Capture,
+    route: &str,
+    title: &str,
+    on_closed: fn(&tauri::AppHandle),
+) -> Result<WebviewWindow, AppError> {
+    let window = WebviewWindowBuilder::new(app, &capture.label, WebviewUrl::App(route.into()))
+        .title(title)
+        .decorations(false)
+        .transparent(true)
+        .always_on_top(true)
+        .skip_taskbar(true)
+        .resizable(false)
+        .shadow(false)
+        .visible(false)
+        .build()?;
+    window.set_position(capture.position)?;
+    window.set_size(capture.size)?;
+    window.set_cursor_icon(CursorIcon::Crosshair)?;
+    let handle = app.clone();
+    window.on_window_event(move |event| {
+        if matches!(event, WindowEvent::Destroyed) {
+            on_closed(&handle);
+        }
+    });
+    window.show()?;
+    Ok(window)
+}
+
+/// Cut the selection out of the frozen capture of the monitor it was drawn on.
+fn crop(capture: &Capture, selection: Selection) -> Option<RgbaImage> {
+    let ratio = capture.image_scale();
+    let x = (selection.x.max(0.0) * ratio).round() as u32;
+    let y = (selection.y.max(0.0) * ratio).round() as u32;
+    let width =
+        ((selection.width * ratio).round() as u32).min(capture.image.width().saturating_sub(x));
+    let height =
+        ((selection.height * ratio).round() as u32).min(capture.image.height().saturating_sub(y));
+    (width > 0 && height > 0)
+        .then(|| image::imageops::crop_imm(&capture.image, x, y, width, height).to_image())
+}
+
+async fn pick(
+    app: &tauri::AppHandle,
+    captures: &[Capture],
+    receiver: oneshot::Receiver<Picked>,
+) -> Result<Option<Screenshot>, AppError> {
+    if let Some(pick) = app.state::<RegionPickerState>().0.lock().unwrap().as_mut() {
+        pick.backgrounds = captures
+            .iter()
+            .map(|capture| (capture.label.clone(), capture.background.clone()))
+            .collect();
+    }
+    let cursor = super::cursor_monitor(app).ok().map(|m| *m.position());
+    for capture in captures {
+        let window = open_window(
+            app,
+            capture,
+            "region-picker",
+            "Aikeya Region Picker",
+            |app| finish(app, None),
+        )?;
+        if Some(capture.position) == cursor {
+            let _ = window.set_focus();
+        }
+    }
+
+    let Ok(Some((label, selection))) = receiver.await else {
+        return Ok(None);
+    };
+    let Some(image) = captures
+        .iter()
+        .find(|capture| capture.label == label)
+        .and_then(|capture| crop(capture, selection))
+    else {
+        return Ok(None);
+    };
+    Ok(Some(save(app, &image)?))
+}
+
+/// Let the user drag out a rectangle on any monitor, and return a screenshot of it for
+/// OCR or a vision prompt. Each monitor is frozen and shown dimmed in a full-screen
+/// window at `/region-picker`, which reports the rectangle with `finish_region_pick`.
+/// Returns `None` if the pick was cancelled.
+#[tauri::command]
+pub async fn pick_screen_region(app: tauri::AppHandle) -> Result<Option<Screenshot>, AppError> {
+    capabilities::require(&app, Capability::ScreenCapture, "Screen region").await?;
+    let (sender, receiver) = oneshot::channel();
+    {
+        let state = app.state::<RegionPickerState>();
+        let mut active = state.0.lock().unwrap();
+        if active.is_some() {
+            return Err(AppError::InvalidInput(
+                "A screen region is already being picked".into(),
+            ));
+        }
+        *active = Some(Pick {
+            sender,
+            backgrounds: HashMap::new(),
+        });
+    }
+
+    let overlay = hide_overlay(&app).await;
+    let handle = app.clone();
+    // Errors from here on still need the cleanup below.
+    let captures =
+        tauri::async_runtime::spawn_blocking(move || capture_monitors(&handle, LABEL_PREFIX, true))
+            .await
+            .map_err(AppError::from)
+            .and_then(|captures| captures.map_err(AppError::from));
+    let result = match &captures {
+        Ok(captures) => pick(&app, captures, receiver).await,
+        Err(error) => Err(error.clone()),
+    };
+
+    app.state::<RegionPickerState>().0.lock().unwrap().take();
+    close_windows(
+        &app,
+        LABEL_PREFIX,
+        captures.iter().flatten(),
+        overlay.as_ref(),
+    );
+    result
+}
+
+/// Hide the overlay so it isn't in the capture, returning it if it was showing.
+pub(super) async fn hide_overlay(app: &tauri::AppHandle) -> Option<WebviewWindow> {
+    let overlay = window_manager::get(app, AppWindow::Overlay)
+        .filter(|window| window.is_visible().unwrap_or(false));
+    if let Some(overlay) = &overlay {
+        let _ = overlay.hide();
+        tokio::time::sleep(HIDE_SETTLE_DELAY).await;
+    }
+    overlay
+}
+
+/// Take down the windows labelled `label_prefix` and their backgrounds, and bring back
+/// the overlay if it was hidden for the pick.
+pub(super) fn close_windows<'a>(
+    app: &tauri::AppHandle,
+    label_prefix: &str,
+    captures: impl IntoIterator<Item = &'a Capture>,
+    overlay: Option<&WebviewWindow>,
+) {
+    for (label, window) in app.webview_windows() {
+        if label.starts_with(label_prefix) {
+            let _ = window.destroy();
+        }
+    }
+    for capture in captures {
+        let _ = fs::remove_file(&capture.background);
+    }
+    if let Some(overlay) = overlay {
+        let _ = overlay.show();
+        let _ = overlay.set_focus();
+    }
+}
+
+/// The dimmed screen a picker window should show behind the selection.
+#[tauri::command]
+pub fn get_region_picker_background(
+    app: tauri::AppHandle,
+    window: WebviewWindow,
+) -> Result<PathBuf, AppError> {
+    app.state::<RegionPickerState>()
+        .0
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|pick| pick.backgrounds.get(window.label()).cloned())
+        .ok_or_else(|| AppError::NotFound("No region pick for this window".into()))
+}
+
+/// Called by a picker window with the rectangle the user drew, or without one when they
+/// pressed Escape.
+#[tauri::command]
+pub fn finish_region_pick(
+    app: tauri::AppHandle,
+    window: WebviewWindow,
+    selection: Option<Selection>,
+) {
+    finish(
+        &app,
+        selection.map(|selection| (window.label().to_string(), selection)),
+    );
+}
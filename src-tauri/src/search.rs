@@ -0,0 +1,144 @@
+use futures_util::future::join_all;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::error::AppError;
+use crate::settings::{self, SearchProvider};
+use crate::{http, readability, secrets};
+
+/// Page text kept per fetched result, so a handful of them fit in one prompt.
+const MAX_PAGE_CHARS: usize = 8_000;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResult {
+    pub title: String,
+    pub url: String,
+    pub snippet: String,
+    /// The page's readable text, when it was fetched as well.
+    pub content: Option<String>,
+}
+
+fn key(provider: &str) -> Result<String, String> {
+    secrets::api_key(provider)?.ok_or_else(|| format!("No API key saved for {provider}"))
+}
+
+/// Pick `title`, `url` and `snippet` out of each entry of a provider's result list.
+fn results(list: Option<&Value>, fields: [&str; 3]) -> Vec<SearchResult> {
+    let Some(list) = list.and_then(Value::as_array) else {
+        return Vec::new();
+    };
+    let text = |entry: &Value, field: &str| {
+        entry
+            .get(field)
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string()
+    };
+    list.iter()
+        .map(|entry| SearchResult {
+            title: readability::html_to_text(&text(entry, fields[0])),
+            url: text(entry, fields[1]),
+            // Brave and SearxNG highlight matches with markup.
+            snippet: readability::html_to_text(&text(entry, fields[2])),
+            content: None,
+        })
+        .filter(|result| !result.url.is_empty())
+        .collect()
+}
+
+/// Search the web with `provider`, or the one in settings.
+pub async fn search(
+    app: &tauri::AppHandle,
+    query: &str,
+    provider: Option<SearchProvider>,
+) -> Result<Vec<SearchResult>, String> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Err("The search query is empty".to_string());
+    }
+    let settings = settings::current(app).search;
+    let count = settings.max_results.to_string();
+    let client = http::client(app)?;
+    let provider = provider.unwrap_or(settings.provider);
+    let (name, request) = match provider {
+        SearchProvider::Searxng => {
+            let base = settings
+                .searxng_url
+                .ok_or("Set search.searxngUrl to search with SearxNG")?;
+            let url = format!("{}/search", base.trim_end_matches('/'));
+            let request = client.get(url).query(&[("q", query), ("format", "json")]);
+            ("searxng", request)
+        }
+        SearchProvider::Brave => {
+            let request = client
+                .get("https://api.search.brave.com/res/v1/web/search")
+                .query(&[("q", query), ("count", &count)])
+                .header("X-Subscription-Token", key("brave")?);
+            ("brave", request)
+        }
+        SearchProvider::Bing => {
+            let request = client
+                .get("https://api.bing.microsoft.com/v7.0/search")
+                .query(&[("q", query), ("count", &count)])
+                .header("Ocp-Apim-Subscription-Key", key("bing")?);
+            ("bing", request)
+        }
+    };
+    let response = http::send(app, name, None, request).await?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!("{name} search returned {status}"));
+    }
+    let body: Value = response.json().await.map_err(|e| e.to_string())?;
+    let mut found = match provider {
+        SearchProvider::Searxng => results(body.get("results"), ["title", "url", "content"]),
+        SearchProvider::Brave => results(
+            body.pointer("/web/results"),
+            ["title", "url", "description"],
+        ),
+        SearchProvider::Bing => {
+            results(body.pointer("/webPages/value"), ["name", "url", "snippet"])
+        }
+    };
+    found.truncate(settings.max_results as usize);
+    Ok(found)
+}
+
+/// Fill in `content` for the first `pages` results, fetching them side by side. Pages
+/// that fail to load keep just their snippet.
+pub async fn fetch_contents(app: &tauri::AppHandle, results: &mut [SearchResult], pages: usize) {
+    let pages = pages.min(results.len());
+    let fetched = join_all(
+        results[..pages]
+            .iter()
+            .map(|result| readability::fetch(app, &result.url)),
+    )
+    .await;
+    for (result, page) in results.iter_mut().zip(fetched) {
+        match page {
+            Ok(page) => {
+                let mut text = page.text;
+                if let Some((cut, _)) = text.char_indices().nth(MAX_PAGE_CHARS) {
+                    text.truncate(cut);
+                }
+                result.content = Some(text);
+            }
+            Err(error) => tracing::debug!("Could not fetch {}: {error}", result.url),
+        }
+    }
+}
+
+/// Search the web from the backend, so the frontend needs no CORS access. With `pages`,
+/// the readable text of that many top results is included as well.
+#[tauri::command]
+pub async fn web_search(
+    app: tauri::AppHandle,
+    query: String,
+    provider: Option<SearchProvider>,
+    pages: Option<usize>,
+) -> Result<Vec<SearchResult>, AppError> {
+    let mut results = search(&app, &query, provider).await?;
+    fetch_contents(&app, &mut results, pages.unwrap_or(0)).await;
+    Ok(results)
+}
@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use keyring::Entry;
+
+use crate::error::AppError;
+use crate::workspaces;
+
+/// Service name under which every provider key is filed in the OS keychain.
+const SERVICE: &str = "com.aikeya.app";
+
+/// Provider keys already read from the keychain, which can be slow or prompt the user.
+static CACHE: Mutex<Option<HashMap<String, String>>> = Mutex::new(None);
+
+fn cache_key(provider: &str, key: Option<&str>) {
+    let mut cache = CACHE.lock().unwrap();
+    let cache = cache.get_or_insert_with(HashMap::new);
+    match key {
+        Some(key) => cache.insert(provider.trim().to_string(), key.to_string()),
+        None => cache.remove(provider.trim()),
+    };
+}
+
+/// Forget every cached provider key, so the next use reads it from the keychain again.
+pub fn lock_cache() {
+    *CACHE.lock().unwrap() = None;
+}
+
+fn entry(provider: &str) -> Result<Entry, String> {
+    let provider = provider.trim();
+    if provider.is_empty() {
+        return Err("Provider name must not be empty".to_string());
+    }
+    // Keys of the default workspace keep the name they had before there were others.
+    let account = match workspaces::active_id() {
+        workspaces::DEFAULT_WORKSPACE => format!("api-key:{provider}"),
+        workspace => format!("workspace:{workspace}:api-key:{provider}"),
+    };
+    Entry::new(SERVICE, &account).map_err(|e| e.to_string())
+}
+
+fn read(entry: Entry) -> Result<Option<String>, String> {
+    match entry.get_password() {
+        Ok(key) => Ok(Some(key)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Look up a provider's key for backend use. Returns `None` when nothing is stored.
+pub fn api_key(provider: &str) -> Result<Option<String>, String> {
+    let cached = CACHE
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|cache| cache.get(provider.trim()).cloned());
+    if cached.is_some() {
+        return Ok(cached);
+    }
+    let key = read(entry(provider)?)?;
+    if let Some(key) = &key {
+        cache_key(provider, Some(key));
+    }
+    Ok(key)
+}
+
+/// The app's own secrets, such as the local API token, kept apart from provider keys.
+fn app_entry(name: &str) -> Result<Entry, String> {
+    Entry::new(SERVICE, &format!("app:{name}")).map_err(|e| e.to_string())
+}
+
+pub fn app_secret(name: &str) -> Result<Option<String>, String> {
+    read(app_entry(name)?)
+}
+
+pub fn set_app_secret(name: &str, value: &str) -> Result<(), String> {
+    app_entry(name)?
+        .set_password(value)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn save_api_key(provider: String, key: String) -> Result<(), AppError> {
+    let key = key.trim();
+    if key.is_empty() {
+        return Err(AppError::InvalidInput(
+            "API key must not be empty".to_string(),
+        ));
+    }
+    entry(&provider)?
+        .set_password(key)
+        .map_err(|e| AppError::from(e.to_string()))?;
+    cache_key(&provider, Some(key));
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_api_key(provider: String) -> Result<Option<String>, AppError> {
+    api_key(&provider).map_err(AppError::from)
+}
+
+#[tauri::command]
+pub async fn delete_api_key(provider: String) -> Result<(), AppError> {
+    cache_key(&provider, None);
+    match entry(&provider)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(e.to_string().into()),
+    }
+}
@@ -0,0 +1,493 @@
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use tauri::Manager;
+
+use crate::capabilities::{self, Capability};
+use crate::clipboard::{self, ClipboardContent};
+use crate::error::AppError;
+use crate::keyboard;
+use crate::screenshot::Region;
+use crate::window_manager::{self, AppWindow};
+
+/// How long to wait for the foreground app to answer a simulated copy.
+const COPY_POLL_INTERVAL: Duration = Duration::from_millis(25);
+const COPY_POLL_ATTEMPTS: usize = 12;
+
+/// The selection captured the last time the overlay was summoned. Once the overlay has
+/// focus the foreground app's selection can no longer be read, so this is served instead.
+#[derive(Default)]
+pub struct SelectionState(Mutex<Option<String>>);
+
+#[cfg(target_os = "macos")]
+fn accessibility_selection(_app: &tauri::AppHandle) -> Option<String> {
+    use accessibility_sys::{
+        kAXErrorSuccess, kAXFocusedUIElementAttribute, kAXSelectedTextAttribute,
+        AXUIElementCopyAttributeValue, AXUIElementCreateSystemWide, AXUIElementRef,
+    };
+    use core_foundation::base::{CFType, CFTypeRef, TCFType};
+    use core_foundation::string::CFString;
+
+    unsafe fn copy_attribute(element: AXUIElementRef, name: &str) -> Option<CFType> {
+        let attribute = CFString::new(name);
+        let mut value: CFTypeRef = std::ptr::null();
+        let status =
+            AXUIElementCopyAttributeValue(element, attribute.as_concrete_TypeRef(), &mut value);
+        if status != kAXErrorSuccess || value.is_null() {
+            return None;
+        }
+        Some(CFType::wrap_under_create_rule(value))
+    }
+
+    // SAFETY: every returned reference is owned by a CFType wrapper that releases it.
+    unsafe {
+        let system = CFType::wrap_under_create_rule(AXUIElementCreateSystemWide() as CFTypeRef);
+        let focused = copy_attribute(
+            system.as_CFTypeRef() as AXUIElementRef,
+            kAXFocusedUIElementAttribute,
+        )?;
+        let selected = copy_attribute(
+            focused.as_CFTypeRef() as AXUIElementRef,
+            kAXSelectedTextAttribute,
+        )?;
+        selected.downcast::<CFString>().map(|s| s.to_string())
+    }
+}
+
+#[cfg(windows)]
+fn accessibility_selection(_app: &tauri::AppHandle) -> Option<String> {
+    use uiautomation::patterns::UITextPattern;
+    use uiautomation::UIAutomation;
+
+    let automation = UIAutomation::new().ok()?;
+    let element = automation.get_focused_element().ok()?;
+    let pattern = element.get_pattern::<UITextPattern>().ok()?;
+    let ranges = pattern.get_selection().ok()?;
+    let parts: Vec<String> = ranges
+        .iter()
+        .filter_map(|range| range.get_text(-1).ok())
+        .collect();
+    Some(parts.join("\n"))
+}
+
+/// A client for just enough of AT-SPI, over its own D-Bus bus, to read the focused
+/// control's selection.
+#[cfg(target_os = "linux")]
+mod atspi {
+    use std::collections::{HashMap, VecDeque};
+    use std::time::Duration;
+
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+    use zbus::zvariant::{DynamicType, OwnedObjectPath, Type};
+    use zbus::Connection;
+
+    const REGISTRY: &str = "org.a11y.atspi.Registry";
+    const ROOT: &str = "/org/a11y/atspi/accessible/root";
+    const ACCESSIBLE: &str = "org.a11y.atspi.Accessible";
+    const COLLECTION: &str = "org.a11y.atspi.Collection";
+    const TEXT: &str = "org.a11y.atspi.Text";
+
+    /// Bits of the first word of the state set `GetState` returns.
+    const STATE_ACTIVE: u32 = 1 << 1;
+    const STATE_FOCUSED: u32 = 1 << 12;
+    const STATE_SHOWING: u32 = 1 << 25;
+    const MATCH_ALL: i32 = 1;
+    const SORT_CANONICAL: u32 = 1;
+
+    /// An app that is slow to answer shouldn't hold up the clipboard fallback for long.
+    const CALL_TIMEOUT: Duration = Duration::from_millis(150);
+    const LOOKUP_TIMEOUT: Duration = Duration::from_millis(400);
+    /// How many accessibles to look through for the focused one when the toolkit can't
+    /// search for it itself.
+    const MAX_WALK: usize = 2000;
+
+    /// An accessible, as the bus name of its app and its object path.
+    type Object = (String, OwnedObjectPath);
+
+    async fn call<B, R>(
+        bus: &Connection,
+        object: (&str, &str),
+        interface: &str,
+        method: &str,
+        body: &B,
+    ) -> zbus::Result<R>
+    where
+        B: Serialize + DynamicType,
+        R: DeserializeOwned + Type,
+    {
+        bus.call_method(Some(object.0), object.1, Some(interface), method, body)
+            .await?
+            .body()
+            .deserialize()
+    }
+
+    fn at(object: &Object) -> (&str, &str) {
+        (object.0.as_str(), object.1.as_str())
+    }
+
+    async fn connect() -> zbus::Result<Connection> {
+        let address = match std::env::var("AT_SPI_BUS_ADDRESS") {
+            Ok(address) => address,
+            Err(_) => {
+                let session = Connection::session().await?;
+                call(
+                    &session,
+                    ("org.a11y.Bus", "/org/a11y/bus"),
+                    "org.a11y.Bus",
+                    "GetAddress",
+                    &(),
+                )
+                .await?
+            }
+        };
+        zbus::connection::Builder::address(address.as_str())?
+            .method_timeout(CALL_TIMEOUT)
+            .build()
+            .await
+    }
+
+    async fn state(bus: &Connection, object: &Object) -> u32 {
+        call::<_, Vec<u32>>(bus, at(object), ACCESSIBLE, "GetState", &())
+            .await
+            .ok()
+            .and_then(|words| words.first().copied())
+            .unwrap_or(0)
+    }
+
+    async fn children(bus: &Connection, object: &Object) -> Vec<Object> {
+        call(bus, at(object), ACCESSIBLE, "GetChildren", &())
+            .await
+            .unwrap_or_default()
+    }
+
+    /// The focused accessible inside `window`.
+    async fn focused_in(bus: &Connection, window: &Object) -> Option<Object> {
+        let rule = (
+            vec![STATE_FOCUSED as i32, 0],
+            MATCH_ALL,
+            HashMap::<String, String>::new(),
+            MATCH_ALL,
+            Vec::<i32>::new(),
+            MATCH_ALL,
+            Vec::<String>::new(),
+            MATCH_ALL,
+            false,
+        );
+        let matches = call::<_, Vec<Object>>(
+            bus,
+            at(window),
+            COLLECTION,
+            "GetMatches",
+            &(rule, SORT_CANONICAL, 1i32, true),
+        )
+        .await;
+        if let Ok(matches) = matches {
+            return matches.into_iter().next();
+        }
+        // Toolkits without Collection are walked instead, through what is on screen.
+        let mut queue = VecDeque::from([window.clone()]);
+        let mut walked = 0;
+        while let Some(object) = queue.pop_front() {
+            walked += 1;
+            if walked > MAX_WALK {
+                break;
+            }
+            let state = state(bus, &object).await;
+            if state & STATE_FOCUSED != 0 {
+                return Some(object);
+            }
+            if state & STATE_SHOWING != 0 {
+                queue.extend(children(bus, &object).await);
+            }
+        }
+        None
+    }
+
+    /// The focused accessible in the active window of whichever app has it.
+    async fn focused(bus: &Connection) -> Option<Object> {
+        let root = (REGISTRY.to_string(), OwnedObjectPath::try_from(ROOT).ok()?);
+        for app in children(bus, &root).await {
+            for window in children(bus, &app).await {
+                if state(bus, &window).await & STATE_ACTIVE == 0 {
+                    continue;
+                }
+                if let Some(focused) = focused_in(bus, &window).await {
+                    return Some(focused);
+                }
+            }
+        }
+        None
+    }
+
+    async fn selected_text(bus: &Connection, object: &Object) -> Option<String> {
+        let count: i32 = call(bus, at(object), TEXT, "GetNSelections", &())
+            .await
+            .ok()?;
+        let mut parts = Vec::new();
+        for index in 0..count {
+            let (start, end): (i32, i32) = call(bus, at(object), TEXT, "GetSelection", &index)
+                .await
+                .ok()?;
+            parts.push(
+                call::<_, String>(bus, at(object), TEXT, "GetText", &(start, end))
+                    .await
+                    .ok()?,
+            );
+        }
+        Some(parts.join("\n"))
+    }
+
+    /// The focused control's selected text, or `None` if nothing focused exposes any. An
+    /// error means the accessibility bus isn't there to ask.
+    pub async fn selection() -> zbus::Result<Option<String>> {
+        let bus = connect().await?;
+        let lookup = async {
+            let focused = focused(&bus).await?;
+            selected_text(&bus, &focused).await
+        };
+        Ok(tokio::time::timeout(LOOKUP_TIMEOUT, lookup)
+            .await
+            .unwrap_or(None))
+    }
+}
+
+/// Ask AT-SPI, like the other platforms' accessibility APIs. Only when its bus isn't
+/// running is the PRIMARY selection used instead, since that can hold text highlighted
+/// long ago, and GNOME on Wayland doesn't hand it to us at all.
+#[cfg(target_os = "linux")]
+fn accessibility_selection(app: &tauri::AppHandle) -> Option<String> {
+    match tauri::async_runtime::block_on(atspi::selection()) {
+        Ok(selection) => selection,
+        Err(error) => {
+            tracing::debug!("AT-SPI is unavailable, reading PRIMARY instead: {error}");
+            clipboard::read_primary_selection(app)
+        }
+    }
+}
+
+/// AX reports geometry in points from the top-left of the primary display; convert using
+/// the scale of whichever monitor the point falls on.
+#[cfg(target_os = "macos")]
+fn points_to_physical(app: &tauri::AppHandle, x: f64, y: f64) -> (i32, i32) {
+    let scale = app
+        .available_monitors()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|m| {
+            (
+                m.position().to_logical::<f64>(m.scale_factor()),
+                m.size().to_logical::<f64>(m.scale_factor()),
+                m.scale_factor(),
+            )
+        })
+        .find(|(pos, size, _)| {
+            x >= pos.x && y >= pos.y && x < pos.x + size.width && y < pos.y + size.height
+        })
+        .map(|(_, _, scale)| scale)
+        .unwrap_or(1.0);
+    ((x * scale).round() as i32, (y * scale).round() as i32)
+}
+
+/// Screen rectangle of the focused element's selection (or caret), in physical pixels.
+#[cfg(target_os = "macos")]
+pub fn selection_bounds(app: &tauri::AppHandle) -> Option<Region> {
+    use accessibility_sys::{
+        kAXBoundsForRangeParameterizedAttribute, kAXErrorSuccess, kAXFocusedUIElementAttribute,
+        kAXSelectedTextRangeAttribute, kAXValueTypeCGRect, AXUIElementCopyAttributeValue,
+        AXUIElementCopyParameterizedAttributeValue, AXUIElementCreateSystemWide, AXUIElementRef,
+        AXValueGetValue, AXValueRef,
+    };
+    use core_foundation::base::{CFType, CFTypeRef, TCFType};
+    use core_foundation::string::CFString;
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct CGRect {
+        x: f64,
+        y: f64,
+        width: f64,
+        height: f64,
+    }
+
+    unsafe fn copy_attribute(element: &CFType, name: &str) -> Option<CFType> {
+        let attribute = CFString::new(name);
+        let mut value: CFTypeRef = std::ptr::null();
+        let status = AXUIElementCopyAttributeValue(
+            element.as_CFTypeRef() as AXUIElementRef,
+            attribute.as_concrete_TypeRef(),
+            &mut value,
+        );
+        if status != kAXErrorSuccess || value.is_null() {
+            return None;
+        }
+        Some(CFType::wrap_under_create_rule(value))
+    }
+
+    // SAFETY: every returned reference is owned by a CFType wrapper that releases it, and
+    // the AXValue is checked to hold a CGRect before it is read into one.
+    let rect = unsafe {
+        let system = CFType::wrap_under_create_rule(AXUIElementCreateSystemWide() as CFTypeRef);
+        let focused = copy_attribute(&system, kAXFocusedUIElementAttribute)?;
+        let range = copy_attribute(&focused, kAXSelectedTextRangeAttribute)?;
+        let attribute = CFString::new(kAXBoundsForRangeParameterizedAttribute);
+        let mut value: CFTypeRef = std::ptr::null();
+        let status = AXUIElementCopyParameterizedAttributeValue(
+            focused.as_CFTypeRef() as AXUIElementRef,
+            attribute.as_concrete_TypeRef(),
+            range.as_CFTypeRef(),
+            &mut value,
+        );
+        if status != kAXErrorSuccess || value.is_null() {
+            return None;
+        }
+        let value = CFType::wrap_under_create_rule(value);
+        let mut rect = CGRect::default();
+        if !AXValueGetValue(
+            value.as_CFTypeRef() as AXValueRef,
+            kAXValueTypeCGRect,
+            &mut rect as *mut CGRect as *mut std::ffi::c_void,
+        ) {
+            return None;
+        }
+        rect
+    };
+    let (x, y) = points_to_physical(app, rect.x, rect.y);
+    let (right, bottom) = points_to_physical(app, rect.x + rect.width, rect.y + rect.height);
+    Some(Region {
+        x,
+        y,
+        width: (right - x).max(0) as u32,
+        height: (bottom - y).max(0) as u32,
+    })
+}
+
+/// Screen rectangle of the focused element's selection (or caret), in physical pixels.
+#[cfg(windows)]
+pub fn selection_bounds(_app: &tauri::AppHandle) -> Option<Region> {
+    use uiautomation::patterns::{UITextPattern, UITextRange};
+    use uiautomation::types::TextUnit;
+    use uiautomation::variants::SafeArray;
+    use uiautomation::UIAutomation;
+
+    /// Union of a range's line rectangles, which UIA flattens into `[x, y, w, h, ...]`.
+    fn range_bounds(range: &UITextRange) -> Option<Region> {
+        // SAFETY: the returned SAFEARRAY is owned by the wrapper, which destroys it.
+        let rects: Vec<f64> =
+            SafeArray::from(unsafe { range.as_ref().GetBoundingRectangles() }.ok()?)
+                .try_into()
+                .ok()?;
+        let (mut left, mut top, mut right, mut bottom) = (f64::MAX, f64::MAX, f64::MIN, f64::MIN);
+        for rect in rects.chunks_exact(4) {
+            left = left.min(rect[0]);
+            top = top.min(rect[1]);
+            right = right.max(rect[0] + rect[2]);
+            bottom = bottom.max(rect[1] + rect[3]);
+        }
+        (left <= right).then_some(Region {
+            x: left as i32,
+            y: top as i32,
+            width: (right - left) as u32,
+            height: (bottom - top) as u32,
+        })
+    }
+
+    let automation = UIAutomation::new().ok()?;
+    let element = automation.get_focused_element().ok()?;
+    if let Ok(pattern) = element.get_pattern::<UITextPattern>() {
+        if let Some(range) = pattern
+            .get_selection()
+            .ok()
+            .and_then(|r| r.into_iter().next())
+        {
+            if let Some(bounds) = range_bounds(&range) {
+                return Some(bounds);
+            }
+            // A bare caret is an empty range, which has no rectangles of its own; measure
+            // the character next to it instead.
+            if range.expand_to_enclosing_unit(TextUnit::Character).is_ok() {
+                if let Some(bounds) = range_bounds(&range) {
+                    return Some(Region { width: 0, ..bounds });
+                }
+            }
+        }
+    }
+    // No text pattern: anchor to the focused control itself.
+    let rect = element.get_bounding_rectangle().ok()?;
+    Some(Region {
+        x: rect.get_left(),
+        y: rect.get_top(),
+        width: rect.get_width().max(0) as u32,
+        height: rect.get_height().max(0) as u32,
+    })
+}
+
+/// Neither X11 nor the Wayland portals expose caret geometry without a full AT-SPI client.
+#[cfg(target_os = "linux")]
+pub fn selection_bounds(_app: &tauri::AppHandle) -> Option<Region> {
+    None
+}
+
+/// Fallback: copy the selection with a synthetic Ctrl/Cmd+C, then put the user's
+/// clipboard back the way it was.
+fn copy_selection(app: &tauri::AppHandle) -> Option<String> {
+    let _borrow = clipboard::borrow(app);
+    // Never overwrite a clipboard that couldn't be saved to put back afterwards.
+    let saved = clipboard::read(app).ok()?;
+    let _restore = Restore { app, saved };
+    // Clear first so an app without a selection can't hand us stale clipboard text.
+    clipboard::write(app, &ClipboardContent::Empty).ok()?;
+    keyboard::send_copy().ok()?;
+
+    for _ in 0..COPY_POLL_ATTEMPTS {
+        thread::sleep(COPY_POLL_INTERVAL);
+        if let Ok(ClipboardContent::Text { text }) = clipboard::read(app) {
+            return Some(text);
+        }
+    }
+    None
+}
+
+/// Puts the user's clipboard back as `copy_selection` returns, however it returns.
+struct Restore<'a> {
+    app: &'a tauri::AppHandle,
+    saved: ClipboardContent,
+}
+
+impl Drop for Restore<'_> {
+    fn drop(&mut self) {
+        if let Err(error) = clipboard::write(self.app, &self.saved) {
+            tracing::warn!("Could not restore the clipboard: {error}");
+        }
+    }
+}
+
+/// Read the foreground app's selection right now. Blocks for up to a few hundred
+/// milliseconds when the clipboard fallback is needed, so call it off the main thread.
+pub fn capture(app: &tauri::AppHandle) -> Option<String> {
+    if let Err(error) = capabilities::check(app, Capability::Accessibility, "Read the selection") {
+        tracing::debug!("Not reading the selection: {error}");
+        return None;
+    }
+    let text = accessibility_selection(app)
+        .filter(|text| !text.trim().is_empty())
+        .or_else(|| copy_selection(app));
+    *app.state::<SelectionState>().0.lock().unwrap() = text.clone();
+    text
+}
+
+/// The selection captured the last time the overlay was summoned.
+pub fn cached(app: &tauri::AppHandle) -> Option<String> {
+    app.state::<SelectionState>().0.lock().unwrap().clone()
+}
+
+#[tauri::command]
+pub async fn get_selected_text(app: tauri::AppHandle) -> Result<Option<String>, AppError> {
+    if window_manager::is_focused(&app, AppWindow::Overlay) {
+        return Ok(cached(&app));
+    }
+    tauri::async_runtime::spawn_blocking(move || capture(&app))
+        .await
+        .map_err(AppError::from)
+}
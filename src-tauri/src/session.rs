@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, Manager};
+
+use crate::db::{self, Database};
+use crate::dock::{self, DockedOverlay};
+use crate::error::AppError;
+use crate::requests::{self, ActiveRequest};
+use crate::state::{self, Session};
+use crate::window_manager::{self, AppWindow};
+use crate::{history, overlay, persist, settings};
+
+const SESSION_FILE: &str = "session.json";
+/// How often the session is written out, and so how much a crash can lose.
+const SAVE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How the app stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SessionEnd {
+    Quit,
+    /// Relaunching into an update or another workspace.
+    Restart,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct OverlaySession {
+    pub visible: bool,
+    pub pinned: bool,
+    pub dock: Option<DockedOverlay>,
+}
+
+/// Where the user was, as saved every few seconds.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SavedSession {
+    #[serde(flatten)]
+    pub session: Session,
+    /// Scroll offsets reported by the frontend, by conversation id.
+    pub scroll: HashMap<String, f64>,
+    pub overlay: OverlaySession,
+    /// Requests that were still running; after a crash, ones that never finished.
+    pub requests: Vec<ActiveRequest>,
+    pub saved_at: i64,
+    /// `None` while the app runs, so a session left with none ended in a crash.
+    pub ended: Option<SessionEnd>,
+}
+
+pub struct SessionStore {
+    path: PathBuf,
+    /// What the last run left behind when it didn't end in a quit, until restored.
+    previous: Mutex<Option<SavedSession>>,
+    scroll: Mutex<HashMap<String, f64>>,
+    /// The session as last written, to skip writes when nothing changed.
+    last: Mutex<Option<SavedSession>>,
+}
+
+impl SessionStore {
+    pub fn load(app: &tauri::AppHandle) -> Result<Self, String> {
+        let path = persist::data_file(app, SESSION_FILE)?;
+        let saved: Option<SavedSession> = persist::load_json(&path);
+        let scroll = saved
+            .as_ref()
+            .map(|saved| saved.scroll.clone())
+            .unwrap_or_default();
+        let previous = saved.filter(|saved| saved.ended != Some(SessionEnd::Quit));
+        Ok(Self {
+            path,
+            previous: Mutex::new(previous),
+            scroll: Mutex::new(scroll),
+            last: Mutex::new(None),
+        })
+    }
+
+    fn save(&self, mut session: SavedSession) -> Result<(), String> {
+        let mut last = self.last.lock().unwrap();
+        let unchanged = last.as_ref().is_some_and(|last| {
+            session.saved_at = last.saved_at;
+            serde_json::to_value(last).ok() == serde_json::to_value(&session).ok()
+        });
+        if unchanged {
+            return Ok(());
+        }
+        session.saved_at = db::now_ms();
+        persist::save_json(&self.path, &session)?;
+        *last = Some(session);
+        Ok(())
+    }
+}
+
+async fn capture(app: &tauri::AppHandle) -> SavedSession {
+    SavedSession {
+        session: state::session(app).await,
+        scroll: app.state::<SessionStore>().scroll.lock().unwrap().clone(),
+        overlay: OverlaySession {
+            visible: window_manager::is_visible(app, AppWindow::Overlay),
+            pinned: overlay::pinned(app),
+            dock: dock::current(app),
+        },
+        requests: requests::running(app),
+        saved_at: 0,
+        ended: None,
+    }
+}
+
+/// Save the session every `SAVE_INTERVAL` for as long as the app runs.
+pub fn start(app: &tauri::AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(SAVE_INTERVAL).await;
+            let session = capture(&app).await;
+            if let Err(error) = app.state::<SessionStore>().save(session) {
+                tracing::warn!("Could not save the session: {error}");
+            }
+        }
+    });
+}
+
+/// Record how the app is stopping, on top of the session as last saved, so the next
+/// launch knows whether to restore it.
+pub fn end(app: &tauri::AppHandle, how: SessionEnd) {
+    let store = app.state::<SessionStore>();
+    let mut session = store.last.lock().unwrap().clone().unwrap_or_default();
+    session.scroll = store.scroll.lock().unwrap().clone();
+    // Relaunching from another thread goes through a normal exit too.
+    if session.ended != Some(SessionEnd::Restart) {
+        session.ended = Some(how);
+    }
+    if let Err(error) = store.save(session) {
+        tracing::warn!("Could not save the session: {error}");
+    }
+}
+
+/// Put the overlay and the active conversation back the way the last run left them, if
+/// it crashed or restarted to update and `general.restoreSession` is on. The frontend
+/// gets the rest from `restore_session`.
+pub fn restore(app: &tauri::AppHandle) {
+    let previous = app.state::<SessionStore>().previous.lock().unwrap().clone();
+    let Some(previous) = previous else {
+        return;
+    };
+    if !settings::current(app).general.restore_session {
+        app.state::<SessionStore>().previous.lock().unwrap().take();
+        return;
+    }
+    tracing::info!(
+        "Restoring the session after {}",
+        match previous.ended {
+            Some(SessionEnd::Restart) => "a restart",
+            _ => "a crash",
+        }
+    );
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut session = previous.session;
+        if let Some(id) = &session.active_conversation {
+            let exists = app
+                .state::<Database>()
+                .with(|conn| history::find_conversation(conn, id))
+                .is_ok_and(|found| found.is_some());
+            if !exists {
+                session.active_conversation = None;
+            }
+        }
+        state::update(&app, |current| *current = session).await;
+
+        let overlay = previous.overlay;
+        let restored = async {
+            if let Some(docked) = overlay.dock {
+                dock::dock_overlay(app.clone(), docked.edge, docked.monitor_id, docked.reserved)
+                    .await?;
+            }
+            if overlay.pinned {
+                overlay::set_pinned(&app, true)?;
+            }
+            if overlay.visible {
+                window_manager::show_overlay(&app)?;
+            }
+            Ok::<_, AppError>(())
+        };
+        if let Err(error) = restored.await {
+            tracing::warn!("Could not restore the overlay: {error}");
+        }
+        let _ = app.emit("session-restored", ());
+    });
+}
+
+/// Forget the scroll position of a conversation that was deleted.
+pub fn discard(app: &tauri::AppHandle, conversation_id: &str) {
+    app.state::<SessionStore>()
+        .scroll
+        .lock()
+        .unwrap()
+        .remove(conversation_id);
+}
+
+/// Remember how far `conversation_id` is scrolled, to scroll back there after a restore.
+#[tauri::command]
+pub fn set_scroll_position(app: tauri::AppHandle, conversation_id: String, scroll_top: f64) {
+    app.state::<SessionStore>()
+        .scroll
+        .lock()
+        .unwrap()
+        .insert(conversation_id, scroll_top);
+}
+
+/// The session the last run left behind when it crashed or restarted to update, once:
+/// the conversation to reopen and how far it was scrolled, and the requests that were
+/// cut off. The overlay and active conversation are already back by then.
+#[tauri::command]
+pub fn restore_session(app: tauri::AppHandle) -> Option<SavedSession> {
+    app.state::<SessionStore>().previous.lock().unwrap().take()
+}
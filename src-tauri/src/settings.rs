@@ -0,0 +1,1220 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{Emitter, Manager};
+
+use crate::error::AppError;
+use crate::{locale, persist};
+
+pub const SETTINGS_FILE: &str = "settings.json";
+
+/// Bump when a change needs more than new defaulted fields, and teach `migrate` the step.
+pub const SETTINGS_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    System,
+    Light,
+    Dark,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct GeneralSettings {
+    pub theme: Theme,
+    /// BCP 47 tag for the UI; `None` follows the system.
+    pub language: Option<String>,
+    /// Show a notification when a response finishes while the overlay is hidden.
+    pub notify_background_completions: bool,
+    /// Hold back notifications and the mouse edge trigger while do not disturb is on or a
+    /// full-screen app is in front.
+    pub quiet_when_busy: bool,
+    /// After a crash or a restart to update, reopen the conversation and overlay the
+    /// app was closed with.
+    pub restore_session: bool,
+}
+
+impl Default for GeneralSettings {
+    fn default() -> Self {
+        Self {
+            theme: Theme::System,
+            language: None,
+            notify_background_completions: true,
+            quiet_when_busy: false,
+            restore_session: true,
+        }
+    }
+}
+
+/// A native material drawn behind the overlay's transparent webview. Each platform uses
+/// the closest one it has: vibrancy on macOS, and a Windows material on Windows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackdropEffect {
+    None,
+    Blur,
+    Acrylic,
+    /// Windows 11 only; shows no backdrop on Windows 10.
+    Mica,
+    Vibrancy,
+}
+
+/// The overlay backdrop for each appearance the theme can resolve to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct OverlayBackdrop {
+    pub light: BackdropEffect,
+    pub dark: BackdropEffect,
+}
+
+impl Default for OverlayBackdrop {
+    fn default() -> Self {
+        Self {
+            light: BackdropEffect::None,
+            dark: BackdropEffect::None,
+        }
+    }
+}
+
+/// The overlay's size as a share of its monitor's work area, kept within bounds given in
+/// logical pixels so it comes out the same physical size at any scale factor.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct OverlaySize {
+    pub width_percent: f64,
+    pub height_percent: f64,
+    pub min_width: u32,
+    pub min_height: u32,
+    pub max_width: u32,
+    pub max_height: u32,
+}
+
+impl Default for OverlaySize {
+    fn default() -> Self {
+        Self {
+            width_percent: 40.0,
+            height_percent: 60.0,
+            min_width: 360,
+            min_height: 320,
+            max_width: 960,
+            max_height: 1200,
+        }
+    }
+}
+
+/// Where the overlay appears when it is shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverlayPosition {
+    /// Centered on the monitor under the mouse cursor.
+    CursorMonitorCenter,
+    /// Centered on the monitor showing the frontmost window.
+    ActiveWindowMonitor,
+    /// Centered on the primary monitor.
+    Primary,
+    /// Wherever the user last left it.
+    Remember,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct OverlaySettings {
+    pub position: OverlayPosition,
+    pub opacity: f64,
+    pub always_on_top: bool,
+    /// Hide the overlay when it loses focus, unless it is pinned.
+    pub auto_hide: bool,
+    /// How long the overlay may stay unfocused before it is hidden.
+    pub auto_hide_delay_ms: u64,
+    pub backdrop: OverlayBackdrop,
+    /// How long after launch the overlay is built hidden, so the first summon doesn't
+    /// wait on its webview. `None` builds it on the first summon.
+    pub prewarm_delay_ms: Option<u64>,
+    /// Unload the overlay's webview once it has been hidden this long, to give back the
+    /// memory it holds. `None` keeps it loaded.
+    pub unload_after_minutes: Option<u32>,
+    /// Fit the overlay to each monitor it lands on. `None` leaves it the size it was
+    /// made or last resized to.
+    pub size: Option<OverlaySize>,
+}
+
+impl Default for OverlaySettings {
+    fn default() -> Self {
+        Self {
+            position: OverlayPosition::CursorMonitorCenter,
+            opacity: 1.0,
+            always_on_top: true,
+            auto_hide: true,
+            auto_hide_delay_ms: 250,
+            backdrop: OverlayBackdrop::default(),
+            prewarm_delay_ms: Some(3_000),
+            unload_after_minutes: None,
+            size: Some(OverlaySize::default()),
+        }
+    }
+}
+
+/// Using the overlay with a screen reader, or with less on screen moving.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct AccessibilitySettings {
+    /// Have screen readers say when the overlay opens, closes or is pinned.
+    pub announcements: bool,
+    /// No slide-in, and no show or hide animations where the platform allows it.
+    pub reduced_motion: bool,
+}
+
+impl Default for AccessibilitySettings {
+    fn default() -> Self {
+        Self {
+            announcements: true,
+            reduced_motion: false,
+        }
+    }
+}
+
+/// A screen edge or corner that summons the overlay when the cursor rests against it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ScreenEdge {
+    Top,
+    Bottom,
+    Left,
+    Right,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// The side buttons aren't reported on Linux, so chords there use the first three.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    Back,
+    Forward,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Modifier {
+    Shift,
+    Ctrl,
+    Alt,
+    /// Command on macOS, the Windows or Super key elsewhere.
+    Meta,
+}
+
+/// A mouse button pressed while holding exactly these modifiers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct MouseChord {
+    pub button: MouseButton,
+    #[serde(default)]
+    pub modifiers: Vec<Modifier>,
+}
+
+/// Summoning the overlay with the mouse instead of a keyboard shortcut.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct MouseSettings {
+    pub enabled: bool,
+    pub edge: Option<ScreenEdge>,
+    /// How long the cursor must rest against `edge`, so merely crossing it does nothing.
+    pub edge_dwell_ms: u64,
+    pub chord: Option<MouseChord>,
+}
+
+impl Default for MouseSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            edge: None,
+            edge_dwell_ms: 300,
+            chord: None,
+        }
+    }
+}
+
+/// A user-added OpenAI-compatible endpoint, e.g. a self-hosted vLLM or LiteLLM server.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct CustomProvider {
+    /// Also the keychain name of its API key.
+    pub id: String,
+    pub name: String,
+    pub base_url: String,
+    #[serde(default)]
+    pub requires_api_key: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct AssistantSettings {
+    pub provider: String,
+    pub model: Option<String>,
+    pub base_url: Option<String>,
+    pub temperature: Option<f32>,
+    pub system_prompt: Option<String>,
+    /// The persona chats use when neither their conversation nor an app profile picks one.
+    pub default_persona: Option<String>,
+    pub custom_providers: Vec<CustomProvider>,
+    /// Overrides the context window assumed for the selected model, in tokens.
+    pub context_window: Option<usize>,
+}
+
+impl Default for AssistantSettings {
+    fn default() -> Self {
+        Self {
+            provider: "openai".to_string(),
+            model: None,
+            base_url: None,
+            temperature: None,
+            system_prompt: None,
+            default_persona: None,
+            custom_providers: Vec::new(),
+            context_window: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct SpeechSettings {
+    /// Which downloaded ggml model `transcribe_audio` uses.
+    pub whisper_model: String,
+    /// ISO 639-1 code of the language spoken; `None` lets Whisper detect it.
+    pub language: Option<String>,
+}
+
+impl Default for SpeechSettings {
+    fn default() -> Self {
+        Self {
+            whisper_model: "base".to_string(),
+            language: None,
+        }
+    }
+}
+
+/// How GGUF models run in-process with the `llamacpp` provider.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct LlamaSettings {
+    /// Tokens of prompt and answer the model sees at once.
+    pub context_size: u32,
+    /// Layers offloaded to the GPU; 0 runs on the CPU only.
+    pub gpu_layers: u32,
+    /// `None` uses one thread per core, up to 8.
+    pub threads: Option<u32>,
+}
+
+impl Default for LlamaSettings {
+    fn default() -> Self {
+        Self {
+            context_size: 4096,
+            gpu_layers: 0,
+            threads: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct HistorySettings {
+    /// Whether conversations are written to the history database at all.
+    pub enabled: bool,
+    /// Condense the older messages of a conversation into a summary once it takes up
+    /// half the model's context window.
+    pub auto_summarize: bool,
+    /// The newest messages always sent as written, never summarized.
+    pub keep_recent_messages: usize,
+}
+
+impl Default for HistorySettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            auto_summarize: true,
+            keep_recent_messages: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct ClipboardSettings {
+    /// Whether the clipboard is watched and its entries kept in the history database.
+    pub history_enabled: bool,
+    /// How many unpinned entries to keep.
+    pub history_limit: u32,
+}
+
+impl Default for ClipboardSettings {
+    fn default() -> Self {
+        Self {
+            history_enabled: false,
+            history_limit: 200,
+        }
+    }
+}
+
+/// Embedding model used to index the user's files for retrieval.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct IndexSettings {
+    /// Any provider with an OpenAI-compatible `/embeddings` endpoint.
+    pub provider: String,
+    pub model: String,
+    pub base_url: Option<String>,
+}
+
+impl Default for IndexSettings {
+    fn default() -> Self {
+        Self {
+            provider: "ollama".to_string(),
+            model: "nomic-embed-text".to_string(),
+            base_url: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct ApiSettings {
+    /// Serve the automation API on localhost.
+    pub enabled: bool,
+    pub port: u16,
+}
+
+impl Default for ApiSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 47823,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct ExtensionSettings {
+    /// Accept WebSocket connections from the browser extension on localhost.
+    pub enabled: bool,
+    pub port: u16,
+}
+
+impl Default for ExtensionSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 47824,
+        }
+    }
+}
+
+/// How to reach an MCP server.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum McpTransport {
+    /// Launch `command` and speak JSON-RPC over its stdin and stdout.
+    Stdio {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+        #[serde(default)]
+        env: BTreeMap<String, String>,
+    },
+    /// Connect to the server's SSE endpoint and post messages to the URL it hands back.
+    Sse { url: String },
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct McpServerConfig {
+    /// Prefixes the server's tool names, so it may only use letters, digits, `-` and `_`.
+    pub id: String,
+    pub name: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    pub transport: McpTransport,
+    /// Tools the assistant may call without asking first.
+    #[serde(default)]
+    pub trusted_tools: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct McpSettings {
+    pub servers: Vec<McpServerConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct ToolSettings {
+    /// Built-in tools offered to the model, by name.
+    pub enabled: Vec<String>,
+    /// Folders the `read_file` tool may read from. It is left out while this is empty.
+    pub allowed_dirs: Vec<PathBuf>,
+    /// How long a shell command may run before it is killed.
+    pub command_timeout_secs: u64,
+}
+
+impl Default for ToolSettings {
+    fn default() -> Self {
+        Self {
+            enabled: ["calculator", "web_fetch", "web_search", "read_file"]
+                .map(str::to_string)
+                .to_vec(),
+            allowed_dirs: Vec::new(),
+            command_timeout_secs: 60,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchProvider {
+    /// A SearxNG instance at `search.searxngUrl`; needs no key.
+    Searxng,
+    /// The Brave Search API, with the key saved as provider `brave`.
+    Brave,
+    /// The Bing Web Search API, with the key saved as provider `bing`.
+    Bing,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct SearchSettings {
+    pub provider: SearchProvider,
+    pub searxng_url: Option<String>,
+    pub max_results: u32,
+}
+
+impl Default for SearchSettings {
+    fn default() -> Self {
+        Self {
+            provider: SearchProvider::Searxng,
+            searxng_url: None,
+            max_results: 5,
+        }
+    }
+}
+
+/// What happens once nobody has touched the keyboard or mouse for a while.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct IdleSettings {
+    pub enabled: bool,
+    pub timeout_minutes: u64,
+    /// Delete the unpinned clipboard history.
+    pub clear_clipboard_history: bool,
+    /// Forget the API keys read from the keychain, so they are read again on next use.
+    pub lock_api_keys: bool,
+    pub hide_overlay: bool,
+}
+
+impl Default for IdleSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            timeout_minutes: 15,
+            clear_clipboard_history: true,
+            lock_api_keys: true,
+            hide_overlay: true,
+        }
+    }
+}
+
+/// What `capture_context_bundle` gathers. Each item can be left out.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct ContextBundleSettings {
+    /// The app in front: its name, id, executable and pid.
+    pub app: bool,
+    pub window_title: bool,
+    pub selection: bool,
+    pub clipboard: bool,
+    /// A screenshot of the window in front.
+    pub screenshot: bool,
+}
+
+impl Default for ContextBundleSettings {
+    fn default() -> Self {
+        Self {
+            app: true,
+            window_title: true,
+            selection: true,
+            clipboard: true,
+            screenshot: true,
+        }
+    }
+}
+
+/// Snippets expanded as their triggers are typed in any app.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct ExpanderSettings {
+    /// Watch the keyboard for triggers. Off by default, since it sees everything typed.
+    pub enabled: bool,
+    /// Apps where triggers are left alone, matched like app profiles.
+    pub excluded_apps: Vec<String>,
+}
+
+/// A folder whose new files are processed as they arrive: audio transcribed, documents
+/// summarized and images read with OCR.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct DropFolderSettings {
+    /// Off when unset.
+    pub path: Option<PathBuf>,
+}
+
+/// What `search_launchables` finds besides installed applications.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct LauncherSettings {
+    /// Offer the files the system lists as recently used.
+    pub recent_files: bool,
+    /// More folders to look for applications in, alongside the system's own.
+    pub folders: Vec<PathBuf>,
+}
+
+impl Default for LauncherSettings {
+    fn default() -> Self {
+        Self {
+            recent_files: true,
+            folders: Vec::new(),
+        }
+    }
+}
+
+/// Long-running work like indexing, downloads and exports, run as background jobs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct JobSettings {
+    /// Jobs that may run at once; the rest wait their turn.
+    pub max_concurrent: usize,
+}
+
+impl Default for JobSettings {
+    fn default() -> Self {
+        Self { max_concurrent: 2 }
+    }
+}
+
+/// Working without the internet, by choice or because the network is down.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct OfflineSettings {
+    /// Stay offline even while the network is up.
+    pub enabled: bool,
+    /// The local provider chats go to while offline, instead of one that takes a key.
+    pub fallback_provider: String,
+    /// Without one, chats with cloud providers wait for the network instead.
+    pub fallback_model: Option<String>,
+    pub fallback_base_url: Option<String>,
+    /// Fetched now and then to tell whether the internet can be reached.
+    pub check_url: String,
+}
+
+impl Default for OfflineSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            fallback_provider: "ollama".to_string(),
+            fallback_model: None,
+            fallback_base_url: None,
+            check_url: "https://www.gstatic.com/generate_204".to_string(),
+        }
+    }
+}
+
+/// What to do with sensitive text found in a prompt before it is sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RedactionPolicy {
+    /// Send the prompt as it is.
+    Off,
+    /// Swap each match for a placeholder like `[EMAIL_1]`, and put the original back in
+    /// the response.
+    Mask,
+    /// Refuse to send the prompt.
+    Block,
+}
+
+/// A pattern of the user's own, such as an internal project or customer id.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct RedactionPattern {
+    /// Shown to the user, and upper-cased into the placeholder.
+    pub name: String,
+    pub regex: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct PrivacySettings {
+    /// Used for every provider that takes an API key. Local providers are left alone
+    /// unless listed in `providers`.
+    pub policy: RedactionPolicy,
+    /// Overrides `policy` for a provider id.
+    pub providers: BTreeMap<String, RedactionPolicy>,
+    pub emails: bool,
+    /// Keys and tokens in the well-known formats of OpenAI, Anthropic, AWS, GitHub,
+    /// Slack, Google and Stripe.
+    pub api_keys: bool,
+    /// Card numbers that pass the Luhn check.
+    pub credit_cards: bool,
+    pub custom_patterns: Vec<RedactionPattern>,
+}
+
+impl Default for PrivacySettings {
+    fn default() -> Self {
+        Self {
+            policy: RedactionPolicy::Off,
+            providers: BTreeMap::new(),
+            emails: true,
+            api_keys: true,
+            credit_cards: true,
+            custom_patterns: Vec::new(),
+        }
+    }
+}
+
+/// Prompts held back until `confirm_send` before they go to a provider that takes an
+/// API key. Local providers are left alone.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct GuardrailSettings {
+    pub enabled: bool,
+    /// Prompts bigger than this, text and images together, in KiB.
+    pub max_payload_kb: u32,
+    /// Prompts with files attached from anywhere else, besides the app's own folders.
+    /// Empty allows every folder.
+    pub allowed_dirs: Vec<PathBuf>,
+    /// Prompts with a screenshot taken while one of these apps was in front, matched
+    /// like app profiles.
+    pub excluded_apps: Vec<String>,
+}
+
+impl Default for GuardrailSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_payload_kb: 512,
+            allowed_dirs: Vec::new(),
+            excluded_apps: [
+                "1Password",
+                "Bitwarden",
+                "Dashlane",
+                "Enpass",
+                "KeePassXC",
+                "Keychain Access",
+                "LastPass",
+                "Proton Pass",
+            ]
+            .map(String::from)
+            .to_vec(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateChannel {
+    Stable,
+    /// Pre-releases as well as stable releases.
+    Beta,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct UpdateSettings {
+    pub channel: UpdateChannel,
+    /// Look for an update shortly after launch and emit `update-available`.
+    pub check_on_startup: bool,
+}
+
+impl Default for UpdateSettings {
+    fn default() -> Self {
+        Self {
+            channel: UpdateChannel::Stable,
+            check_on_startup: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProxyMode {
+    /// Use the OS proxy configuration and the `HTTP(S)_PROXY` environment variables.
+    System,
+    /// Connect directly.
+    None,
+    /// Use `proxyUrl`.
+    Manual,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct NetworkSettings {
+    /// How long an LLM or API request may run before it is given up on.
+    pub request_timeout_secs: u64,
+    /// Retries after a rate limit, transient server error or dropped connection.
+    pub max_retries: u32,
+    pub proxy_mode: ProxyMode,
+    /// An `http://`, `https://` or `socks5://` URL, credentials included if needed.
+    pub proxy_url: Option<String>,
+    /// Comma-separated hosts and domains to reach without the manual proxy.
+    pub no_proxy: Option<String>,
+    /// A PEM file of extra root certificates, e.g. a corporate TLS-inspection CA.
+    pub ca_bundle: Option<PathBuf>,
+    /// Skip TLS certificate verification entirely. Only for debugging broken setups.
+    pub accept_invalid_certs: bool,
+}
+
+impl Default for NetworkSettings {
+    fn default() -> Self {
+        Self {
+            request_timeout_secs: 300,
+            max_retries: 3,
+            proxy_mode: ProxyMode::System,
+            proxy_url: None,
+            no_proxy: None,
+            ca_bundle: None,
+            accept_invalid_certs: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct Settings {
+    pub version: u32,
+    pub general: GeneralSettings,
+    pub overlay: OverlaySettings,
+    pub accessibility: AccessibilitySettings,
+    pub mouse: MouseSettings,
+    pub assistant: AssistantSettings,
+    pub speech: SpeechSettings,
+    pub llama: LlamaSettings,
+    pub history: HistorySettings,
+    pub clipboard: ClipboardSettings,
+    pub index: IndexSettings,
+    pub network: NetworkSettings,
+    pub updates: UpdateSettings,
+    pub api: ApiSettings,
+    pub extension: ExtensionSettings,
+    pub mcp: McpSettings,
+    pub tools: ToolSettings,
+    pub search: SearchSettings,
+    pub idle: IdleSettings,
+    pub privacy: PrivacySettings,
+    pub guardrails: GuardrailSettings,
+    pub offline: OfflineSettings,
+    pub context_bundle: ContextBundleSettings,
+    pub expander: ExpanderSettings,
+    pub drop_folder: DropFolderSettings,
+    pub launcher: LauncherSettings,
+    pub jobs: JobSettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            general: GeneralSettings::default(),
+            overlay: OverlaySettings::default(),
+            accessibility: AccessibilitySettings::default(),
+            mouse: MouseSettings::default(),
+            assistant: AssistantSettings::default(),
+            speech: SpeechSettings::default(),
+            llama: LlamaSettings::default(),
+            history: HistorySettings::default(),
+            clipboard: ClipboardSettings::default(),
+            index: IndexSettings::default(),
+            network: NetworkSettings::default(),
+            updates: UpdateSettings::default(),
+            api: ApiSettings::default(),
+            extension: ExtensionSettings::default(),
+            mcp: McpSettings::default(),
+            tools: ToolSettings::default(),
+            search: SearchSettings::default(),
+            idle: IdleSettings::default(),
+            privacy: PrivacySettings::default(),
+            guardrails: GuardrailSettings::default(),
+            offline: OfflineSettings::default(),
+            context_bundle: ContextBundleSettings::default(),
+            expander: ExpanderSettings::default(),
+            drop_folder: DropFolderSettings::default(),
+            launcher: LauncherSettings::default(),
+            jobs: JobSettings::default(),
+        }
+    }
+}
+
+impl Settings {
+    fn validate(&self) -> Result<(), String> {
+        if let Some(language) = &self.general.language {
+            if locale::normalize(language).is_none() {
+                return Err(format!(
+                    "general.language \"{language}\" is not a language tag"
+                ));
+            }
+        }
+        if !(0.2..=1.0).contains(&self.overlay.opacity) {
+            return Err("overlay.opacity must be between 0.2 and 1".to_string());
+        }
+        if self.overlay.auto_hide_delay_ms > 10_000 {
+            return Err("overlay.autoHideDelayMs must be at most 10000".to_string());
+        }
+        if self
+            .overlay
+            .prewarm_delay_ms
+            .is_some_and(|delay| delay > 600_000)
+        {
+            return Err("overlay.prewarmDelayMs must be at most 600000".to_string());
+        }
+        if let Some(minutes) = self.overlay.unload_after_minutes {
+            if !(1..=1440).contains(&minutes) {
+                return Err("overlay.unloadAfterMinutes must be between 1 and 1440".to_string());
+            }
+        }
+        if let Some(size) = &self.overlay.size {
+            for (name, percent) in [
+                ("widthPercent", size.width_percent),
+                ("heightPercent", size.height_percent),
+            ] {
+                if !(10.0..=100.0).contains(&percent) {
+                    return Err(format!("overlay.size.{name} must be between 10 and 100"));
+                }
+            }
+            if size.min_width > size.max_width || size.min_height > size.max_height {
+                return Err("overlay.size minimums must not exceed the maximums".to_string());
+            }
+        }
+        if self.mouse.edge_dwell_ms > 5_000 {
+            return Err("mouse.edgeDwellMs must be at most 5000".to_string());
+        }
+        if let Some(chord) = &self.mouse.chord {
+            // A bare left or right click can't be told apart from ordinary use.
+            let primary = matches!(chord.button, MouseButton::Left | MouseButton::Right);
+            if primary && chord.modifiers.is_empty() {
+                return Err("mouse.chord needs a modifier for the left or right button".to_string());
+            }
+        }
+        if self.assistant.provider.trim().is_empty() {
+            return Err("assistant.provider must not be empty".to_string());
+        }
+        if let Some(temperature) = self.assistant.temperature {
+            if !(0.0..=2.0).contains(&temperature) {
+                return Err("assistant.temperature must be between 0 and 2".to_string());
+            }
+        }
+        if let Some(window) = self.assistant.context_window {
+            if !(1024..=10_000_000).contains(&window) {
+                return Err("assistant.contextWindow must be between 1024 and 10000000".to_string());
+            }
+        }
+        if let Some(url) = &self.assistant.base_url {
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                return Err("assistant.baseUrl must be an http(s) URL".to_string());
+            }
+        }
+        for (i, custom) in self.assistant.custom_providers.iter().enumerate() {
+            if custom.id.trim().is_empty() {
+                return Err("assistant.customProviders ids must not be empty".to_string());
+            }
+            if self.assistant.custom_providers[..i]
+                .iter()
+                .any(|other| other.id == custom.id)
+            {
+                return Err(format!("Duplicate custom provider id {}", custom.id));
+            }
+            if !custom.base_url.starts_with("http://") && !custom.base_url.starts_with("https://") {
+                return Err(format!(
+                    "Custom provider {} needs an http(s) URL",
+                    custom.id
+                ));
+            }
+        }
+        if !(512..=131_072).contains(&self.llama.context_size) {
+            return Err("llama.contextSize must be between 512 and 131072".to_string());
+        }
+        if self
+            .llama
+            .threads
+            .is_some_and(|threads| !(1..=256).contains(&threads))
+        {
+            return Err("llama.threads must be between 1 and 256".to_string());
+        }
+        if !(1..=3600).contains(&self.network.request_timeout_secs) {
+            return Err("network.requestTimeoutSecs must be between 1 and 3600".to_string());
+        }
+        if self.api.port < 1024 {
+            return Err("api.port must be at least 1024".to_string());
+        }
+        if self.extension.port < 1024 {
+            return Err("extension.port must be at least 1024".to_string());
+        }
+        if self.api.enabled && self.extension.enabled && self.api.port == self.extension.port {
+            return Err("extension.port must differ from api.port".to_string());
+        }
+        if self.network.max_retries > 10 {
+            return Err("network.maxRetries must be at most 10".to_string());
+        }
+        match (&self.network.proxy_mode, &self.network.proxy_url) {
+            (ProxyMode::Manual, None) => {
+                return Err("network.proxyUrl is required for a manual proxy".to_string());
+            }
+            (_, Some(url))
+                if !["http://", "https://", "socks5://", "socks5h://"]
+                    .iter()
+                    .any(|scheme| url.starts_with(scheme)) =>
+            {
+                return Err("network.proxyUrl must be an http(s) or socks5 URL".to_string());
+            }
+            _ => {}
+        }
+        if let Some(path) = &self.network.ca_bundle {
+            if !path.is_absolute() {
+                return Err("network.caBundle must be an absolute path".to_string());
+            }
+        }
+        if let Some(path) = &self.drop_folder.path {
+            if !path.is_absolute() {
+                return Err("dropFolder.path must be an absolute path".to_string());
+            }
+        }
+        if self.guardrails.max_payload_kb == 0 {
+            return Err("guardrails.maxPayloadKb must be at least 1".to_string());
+        }
+        if self
+            .guardrails
+            .allowed_dirs
+            .iter()
+            .any(|dir| !dir.is_absolute())
+        {
+            return Err("guardrails.allowedDirs must be absolute paths".to_string());
+        }
+        if self.launcher.folders.iter().any(|dir| !dir.is_absolute()) {
+            return Err("launcher.folders must be absolute paths".to_string());
+        }
+        if !(1..=8).contains(&self.jobs.max_concurrent) {
+            return Err("jobs.maxConcurrent must be between 1 and 8".to_string());
+        }
+        if !(2..=200).contains(&self.history.keep_recent_messages) {
+            return Err("history.keepRecentMessages must be between 2 and 200".to_string());
+        }
+        if !(1..=10_000).contains(&self.clipboard.history_limit) {
+            return Err("clipboard.historyLimit must be between 1 and 10000".to_string());
+        }
+        if self.index.provider.trim().is_empty() || self.index.model.trim().is_empty() {
+            return Err("index.provider and index.model must not be empty".to_string());
+        }
+        if let Some(url) = &self.index.base_url {
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                return Err("index.baseUrl must be an http(s) URL".to_string());
+            }
+        }
+        if self.tools.allowed_dirs.iter().any(|dir| !dir.is_absolute()) {
+            return Err("tools.allowedDirs must be absolute paths".to_string());
+        }
+        if !(1..=3600).contains(&self.tools.command_timeout_secs) {
+            return Err("tools.commandTimeoutSecs must be between 1 and 3600".to_string());
+        }
+        if let Some(url) = &self.search.searxng_url {
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                return Err("search.searxngUrl must be an http(s) URL".to_string());
+            }
+        }
+        if !(1..=20).contains(&self.search.max_results) {
+            return Err("search.maxResults must be between 1 and 20".to_string());
+        }
+        if !(1..=1440).contains(&self.idle.timeout_minutes) {
+            return Err("idle.timeoutMinutes must be between 1 and 1440".to_string());
+        }
+        if self.offline.fallback_provider.trim().is_empty() {
+            return Err("offline.fallbackProvider must not be empty".to_string());
+        }
+        if let Some(url) = &self.offline.fallback_base_url {
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                return Err("offline.fallbackBaseUrl must be an http(s) URL".to_string());
+            }
+        }
+        if !self.offline.check_url.starts_with("http://")
+            && !self.offline.check_url.starts_with("https://")
+        {
+            return Err("offline.checkUrl must be an http(s) URL".to_string());
+        }
+        for pattern in &self.privacy.custom_patterns {
+            if pattern.name.trim().is_empty() {
+                return Err("privacy.customPatterns names must not be empty".to_string());
+            }
+            if let Err(error) = regex::Regex::new(&pattern.regex) {
+                return Err(format!(
+                    "Redaction pattern {} is invalid: {error}",
+                    pattern.name
+                ));
+            }
+        }
+        for (i, server) in self.mcp.servers.iter().enumerate() {
+            let valid_id = !server.id.is_empty()
+                && server
+                    .id
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+            if !valid_id {
+                return Err(format!(
+                    "MCP server id {:?} may only use letters, digits, - and _",
+                    server.id
+                ));
+            }
+            if self.mcp.servers[..i]
+                .iter()
+                .any(|other| other.id == server.id)
+            {
+                return Err(format!("Duplicate MCP server id {}", server.id));
+            }
+            match &server.transport {
+                McpTransport::Stdio { command, .. } if command.trim().is_empty() => {
+                    return Err(format!("MCP server {} needs a command", server.id));
+                }
+                McpTransport::Sse { url }
+                    if !url.starts_with("http://") && !url.starts_with("https://") =>
+                {
+                    return Err(format!("MCP server {} needs an http(s) URL", server.id));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Upgrade a settings document written by an older version in place.
+fn migrate(value: &mut Value) {
+    let Some(object) = value.as_object_mut() else {
+        return;
+    };
+    // Files written before versioning carry no version field and need no changes.
+    object.insert("version".to_string(), SETTINGS_VERSION.into());
+}
+
+/// RFC 7386 JSON merge patch: objects merge recursively and `null` resets a key to its default.
+fn merge_patch(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Default::default());
+    }
+    let target = target.as_object_mut().unwrap();
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(key);
+        } else {
+            merge_patch(target.entry(key.clone()).or_insert(Value::Null), value);
+        }
+    }
+}
+
+/// Read a settings document, upgrading it from an older version, and validate it.
+pub fn parse(contents: &str) -> Result<Settings, String> {
+    let mut value = serde_json::from_str::<Value>(contents).map_err(|e| e.to_string())?;
+    migrate(&mut value);
+    let settings = serde_json::from_value::<Settings>(value).map_err(|e| e.to_string())?;
+    settings.validate()?;
+    Ok(settings)
+}
+
+pub struct SettingsStore {
+    path: PathBuf,
+    settings: Mutex<Settings>,
+}
+
+impl SettingsStore {
+    /// Load settings from disk. A file that no longer parses is set aside as
+    /// `settings.json.bak` rather than silently overwritten.
+    pub fn load(app: &tauri::AppHandle) -> Result<Self, String> {
+        let path = persist::config_file(app, SETTINGS_FILE)?;
+        let settings = match fs::read_to_string(&path) {
+            Ok(contents) => parse(&contents).unwrap_or_else(|error| {
+                tracing::warn!("Ignoring invalid settings file: {error}");
+                let _ = fs::rename(&path, path.with_extension("json.bak"));
+                Settings::default()
+            }),
+            Err(_) => Settings::default(),
+        };
+        Ok(Self {
+            path,
+            settings: Mutex::new(settings),
+        })
+    }
+
+    pub fn current(&self) -> Settings {
+        self.settings.lock().unwrap().clone()
+    }
+
+    fn update(&self, patch: &Value) -> Result<Settings, String> {
+        let mut settings = self.settings.lock().unwrap();
+        let mut value = serde_json::to_value(&*settings).map_err(|e| e.to_string())?;
+        merge_patch(&mut value, patch);
+        let mut updated: Settings =
+            serde_json::from_value(value).map_err(|e| format!("Invalid settings: {e}"))?;
+        updated.version = SETTINGS_VERSION;
+        updated.validate()?;
+        persist::save_json(&self.path, &updated)?;
+        *settings = updated.clone();
+        Ok(updated)
+    }
+
+    fn replace(&self, replacement: Settings) -> Result<(), String> {
+        let mut settings = self.settings.lock().unwrap();
+        persist::save_json(&self.path, &replacement)?;
+        *settings = replacement;
+        Ok(())
+    }
+}
+
+/// The current settings, for use from other modules.
+pub fn current(app: &tauri::AppHandle) -> Settings {
+    app.state::<SettingsStore>().current()
+}
+
+#[tauri::command]
+pub fn get_settings(app: tauri::AppHandle) -> Settings {
+    current(&app)
+}
+
+/// Apply a JSON merge patch, validate the result and broadcast `settings-changed`.
+pub fn update(app: &tauri::AppHandle, patch: &Value) -> Result<Settings, String> {
+    let settings = app.state::<SettingsStore>().update(patch)?;
+    let _ = app.emit("settings-changed", &settings);
+    Ok(settings)
+}
+
+/// Swap in a whole set of settings, such as restored ones, and broadcast `settings-changed`.
+pub fn replace(app: &tauri::AppHandle, settings: Settings) -> Result<(), String> {
+    app.state::<SettingsStore>().replace(settings.clone())?;
+    let _ = app.emit("settings-changed", &settings);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn update_settings(app: tauri::AppHandle, patch: Value) -> Result<Settings, AppError> {
+    update(&app, &patch).map_err(AppError::from)
+}
@@ -0,0 +1,591 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, Manager};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Modifiers, Shortcut, ShortcutState};
+
+use crate::capabilities::{self, Capability};
+use crate::clipboard::{self, ClipboardContent};
+use crate::error::AppError;
+use crate::focus::{self, AppContext};
+use crate::latency::{self, LatencyStage};
+use crate::persist;
+use crate::profiles::{self, AppProfile, HotkeyBehavior};
+use crate::settings::Modifier;
+use crate::window_layout::{self, WindowLayout};
+use crate::window_manager::{self, AppWindow};
+use crate::{audio, keyboard_layout, overlay, selection};
+
+const CONFIG_FILE: &str = "shortcuts.json";
+/// How long after the first step of a chord the second is waited for.
+const CHORD_TIMEOUT: Duration = Duration::from_millis(1500);
+
+/// Actions a global shortcut can be bound to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ShortcutAction {
+    /// Summon the overlay with the current selection, or dismiss it.
+    Overlay,
+    /// Toggle whether the overlay lets clicks through to the app underneath.
+    ClickThrough,
+    /// Record a voice prompt for as long as the shortcut is held.
+    PushToTalk,
+    /// Move the window in front to the left half of its monitor.
+    WindowLeftHalf,
+    WindowRightHalf,
+    WindowMaximize,
+    /// Move the window in front to the next monitor to the right.
+    WindowNextMonitor,
+}
+
+/// On disk as `{ "overlay": "CmdOrCtrl+Shift+Space", ... }`; unbound actions may be `null`.
+type ShortcutConfig = HashMap<ShortcutAction, Option<String>>;
+
+/// What an action is bound to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Binding {
+    Accelerator(Shortcut),
+    /// Two accelerators one after the other, written `Ctrl+K Ctrl+A`. Only the first is
+    /// grabbed from other apps until it is pressed.
+    Chord(Shortcut, Shortcut),
+    /// A modifier pressed and released twice on its own, written `Ctrl Ctrl`.
+    DoubleTap(Modifier),
+}
+
+impl Binding {
+    fn into_string(self) -> String {
+        match self {
+            Binding::Accelerator(shortcut) => shortcut.into_string(),
+            Binding::Chord(first, second) => {
+                format!("{} {}", first.into_string(), second.into_string())
+            }
+            Binding::DoubleTap(modifier) => {
+                let name = modifier_name(modifier);
+                format!("{name} {name}")
+            }
+        }
+    }
+
+    /// The accelerators the binding uses, in the order they are pressed.
+    fn shortcuts(self) -> Vec<Shortcut> {
+        match self {
+            Binding::Accelerator(shortcut) => vec![shortcut],
+            Binding::Chord(first, second) => vec![first, second],
+            Binding::DoubleTap(_) => Vec::new(),
+        }
+    }
+
+    /// How the binding reads on the current keyboard layout.
+    fn describe(self, app: &tauri::AppHandle) -> String {
+        match self {
+            Binding::Accelerator(shortcut) => keyboard_layout::describe(app, shortcut),
+            Binding::Chord(first, second) => format!(
+                "{} {}",
+                keyboard_layout::describe(app, first),
+                keyboard_layout::describe(app, second)
+            ),
+            Binding::DoubleTap(modifier) => {
+                let mods = match modifier {
+                    Modifier::Shift => Modifiers::SHIFT,
+                    Modifier::Ctrl => Modifiers::CONTROL,
+                    Modifier::Alt => Modifiers::ALT,
+                    Modifier::Meta => Modifiers::SUPER,
+                };
+                let name = keyboard_layout::modifiers_label(mods).concat();
+                format!("{name} {name}")
+            }
+        }
+    }
+
+    /// Whether both would fire on the same keys.
+    fn overlaps(self, other: Binding) -> bool {
+        self == other
+            || self
+                .shortcuts()
+                .iter()
+                .any(|shortcut| other.shortcuts().contains(shortcut))
+    }
+}
+
+/// The actions currently bound by the backend, and what to.
+pub struct ShortcutRegistry {
+    path: PathBuf,
+    bound: Mutex<HashMap<ShortcutAction, Binding>>,
+}
+
+impl ShortcutRegistry {
+    pub fn load(app: &tauri::AppHandle) -> Result<Self, String> {
+        Ok(Self {
+            path: persist::data_file(app, CONFIG_FILE)?,
+            bound: Mutex::new(HashMap::new()),
+        })
+    }
+
+    fn persist(&self, bound: &HashMap<ShortcutAction, Binding>) -> Result<(), String> {
+        let config: ShortcutConfig = bound
+            .iter()
+            .map(|(action, binding)| (*action, Some(binding.into_string())))
+            .collect();
+        persist::save_json(&self.path, &config)
+    }
+}
+
+fn parse_accelerator(accelerator: &str) -> Result<Shortcut, String> {
+    Shortcut::from_str(accelerator.trim())
+        .map_err(|e| format!("Invalid accelerator \"{accelerator}\": {e}"))
+}
+
+/// The name a bare modifier is written with, matching how accelerators are printed.
+fn modifier_name(modifier: Modifier) -> &'static str {
+    match modifier {
+        Modifier::Shift => "shift",
+        Modifier::Ctrl => "control",
+        Modifier::Alt => "alt",
+        Modifier::Meta => "super",
+    }
+}
+
+fn parse_modifier(name: &str) -> Option<Modifier> {
+    match name.to_ascii_lowercase().as_str() {
+        "shift" => Some(Modifier::Shift),
+        "ctrl" | "control" => Some(Modifier::Ctrl),
+        "alt" | "option" => Some(Modifier::Alt),
+        "super" | "meta" | "cmd" | "command" | "win" => Some(Modifier::Meta),
+        "cmdorctrl" | "commandorcontrol" | "cmdorcontrol" | "commandorctrl" => {
+            Some(if cfg!(target_os = "macos") {
+                Modifier::Meta
+            } else {
+                Modifier::Ctrl
+            })
+        }
+        _ => None,
+    }
+}
+
+/// An accelerator, a two-step chord like `Ctrl+K Ctrl+A`, or a double tap like
+/// `Ctrl Ctrl`.
+fn parse_binding(text: &str) -> Result<Binding, String> {
+    // "Ctrl + K" is one step, so only whitespace away from a `+` separates steps.
+    let text = text.split('+').map(str::trim).collect::<Vec<_>>().join("+");
+    match text.split_whitespace().collect::<Vec<_>>().as_slice() {
+        [accelerator] => parse_accelerator(accelerator).map(Binding::Accelerator),
+        [first, second] => match (parse_modifier(first), parse_modifier(second)) {
+            (Some(first), Some(second)) if first == second => Ok(Binding::DoubleTap(first)),
+            (Some(_), Some(_)) => Err(format!(
+                "Invalid shortcut \"{text}\": a double tap is the same modifier twice"
+            )),
+            _ => Ok(Binding::Chord(
+                parse_accelerator(first)?,
+                parse_accelerator(second)?,
+            )),
+        },
+        _ => Err(format!(
+            "Invalid shortcut \"{text}\": expected an accelerator, two accelerators for a \
+             chord, or a modifier twice for a double tap"
+        )),
+    }
+}
+
+fn on_press(
+    app: &tauri::AppHandle,
+    shortcut: Shortcut,
+    handler: impl Fn(&tauri::AppHandle, ShortcutState) + Send + Sync + 'static,
+) -> Result<(), String> {
+    app.global_shortcut()
+        .on_shortcut(shortcut, move |app, _, event| handler(app, event.state))
+        .map_err(|e| format!("Could not register {}: {e}", shortcut.into_string()))
+}
+
+fn bind(app: &tauri::AppHandle, action: ShortcutAction, binding: Binding) -> Result<(), String> {
+    bind_with(app, binding, move |app, state| {
+        let app = app.clone();
+        if action == ShortcutAction::PushToTalk {
+            PUSH_TO_TALK_HELD.store(state == ShortcutState::Pressed, Ordering::SeqCst);
+        }
+        // Actions may simulate keystrokes or wait on the clipboard, so keep them off
+        // the event loop.
+        tauri::async_runtime::spawn_blocking(move || run_action(&app, action, state));
+    })
+}
+
+fn bind_with<F>(app: &tauri::AppHandle, binding: Binding, fire: F) -> Result<(), String>
+where
+    F: Fn(&tauri::AppHandle, ShortcutState) + Clone + Send + Sync + 'static,
+{
+    match binding {
+        Binding::Accelerator(shortcut) => on_press(app, shortcut, fire),
+        Binding::Chord(first, second) => on_press(app, first, move |app, state| {
+            if state == ShortcutState::Pressed {
+                // Registering from inside a shortcut handler would deadlock the plugin.
+                let app = app.clone();
+                let fire = fire.clone();
+                tauri::async_runtime::spawn_blocking(move || arm_chord(&app, second, fire));
+            }
+        }),
+        // Watched for by `modifier_taps`.
+        Binding::DoubleTap(_) => Ok(()),
+    }
+}
+
+fn release(app: &tauri::AppHandle, binding: Binding) -> Result<(), String> {
+    match binding {
+        Binding::Accelerator(shortcut) | Binding::Chord(shortcut, _) => app
+            .global_shortcut()
+            .unregister(shortcut)
+            .map_err(|e| e.to_string()),
+        Binding::DoubleTap(_) => Ok(()),
+    }
+}
+
+/// Bumped each time a chord is started, so a timeout only ends the chord it was for.
+static CHORD_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// The first step of a chord was pressed: grab the second for a moment, and run the
+/// action if it comes in time.
+fn arm_chord<F>(app: &tauri::AppHandle, second: Shortcut, fire: F)
+where
+    F: Fn(&tauri::AppHandle, ShortcutState) + Send + Sync + 'static,
+{
+    let generation = CHORD_GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    // Pressing the first step again while waiting just restarts the wait.
+    if !app.global_shortcut().is_registered(second) {
+        let armed = on_press(app, second, move |app, state| {
+            if state == ShortcutState::Pressed {
+                let handle = app.clone();
+                tauri::async_runtime::spawn_blocking(move || {
+                    let _ = handle.global_shortcut().unregister(second);
+                });
+                fire(app, state);
+            }
+        });
+        if let Err(error) = armed {
+            tracing::warn!("Could not wait for the second step of a chord: {error}");
+            return;
+        }
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(CHORD_TIMEOUT).await;
+        if CHORD_GENERATION.load(Ordering::SeqCst) == generation {
+            let _ = app.global_shortcut().unregister(second);
+        }
+    });
+}
+
+fn run_action(app: &tauri::AppHandle, action: ShortcutAction, state: ShortcutState) {
+    match (action, state) {
+        (ShortcutAction::Overlay, ShortcutState::Pressed) => summon_or_dismiss(app),
+        (ShortcutAction::ClickThrough, ShortcutState::Pressed) => {
+            let enabled = !overlay::click_through(app);
+            if let Err(error) = overlay::set_click_through(app, enabled) {
+                tracing::error!("Failed to toggle click-through: {error}");
+            }
+        }
+        (ShortcutAction::PushToTalk, _) => sync_push_to_talk(app),
+        (ShortcutAction::WindowLeftHalf, ShortcutState::Pressed) => {
+            arrange_window(app, WindowLayout::LeftHalf)
+        }
+        (ShortcutAction::WindowRightHalf, ShortcutState::Pressed) => {
+            arrange_window(app, WindowLayout::RightHalf)
+        }
+        (ShortcutAction::WindowMaximize, ShortcutState::Pressed) => {
+            arrange_window(app, WindowLayout::Maximize)
+        }
+        (ShortcutAction::WindowNextMonitor, ShortcutState::Pressed) => {
+            arrange_window(app, WindowLayout::NextMonitor)
+        }
+        _ => {}
+    }
+}
+
+fn arrange_window(app: &tauri::AppHandle, layout: WindowLayout) {
+    if let Err(error) = window_layout::arrange_foreground(app, layout) {
+        tracing::warn!("Could not arrange the window ({layout:?}): {error}");
+    }
+}
+
+/// Whether the push-to-talk shortcut is down, recorded on the event loop so it is in
+/// event order even when the worker tasks below run out of order.
+static PUSH_TO_TALK_HELD: AtomicBool = AtomicBool::new(false);
+static PUSH_TO_TALK_LOCK: Mutex<()> = Mutex::new(());
+
+/// Bring the recorder in line with the key: a quick tap may release before the recorder
+/// has started, and key repeat sends extra presses while held.
+fn sync_push_to_talk(app: &tauri::AppHandle) {
+    let _guard = PUSH_TO_TALK_LOCK.lock().unwrap();
+    let held = PUSH_TO_TALK_HELD.load(Ordering::SeqCst);
+    let result = match (held, audio::is_recording(app)) {
+        (true, false) => capabilities::check(app, Capability::Microphone, "Push-to-talk")
+            .and_then(|()| audio::start(app)),
+        (false, true) => audio::stop(app).map(|_| ()),
+        _ => Ok(()),
+    };
+    if let Err(error) = result {
+        let _ = app.emit("recording-error", error);
+    }
+}
+
+/// Context gathered when the overlay is summoned by hotkey, for the prompt to draw on.
+#[derive(Clone, Serialize)]
+struct SummonContext {
+    selection: Option<String>,
+    clipboard: Option<ClipboardContent>,
+    app: Option<AppContext>,
+    profile: Option<AppProfile>,
+}
+
+fn summon_or_dismiss(app: &tauri::AppHandle) {
+    if window_manager::is_visible(app, AppWindow::Overlay) {
+        // A pinned overlay is meant to stay up while the user works elsewhere, so the
+        // hotkey brings it back into focus first and only dismisses it from there. The
+        // same goes for one that just lost focus and is about to auto-hide: the user is
+        // reaching for it, not trying to close it.
+        let focused = window_manager::is_focused(app, AppWindow::Overlay);
+        let hide_was_pending = overlay::cancel_auto_hide(app);
+        if !focused && (overlay::pinned(app) || hide_was_pending) {
+            let _ = window_manager::show_overlay_nonactivating(app, None);
+        } else {
+            let _ = window_manager::hide_overlay(app);
+        }
+        return;
+    }
+
+    // Read the context while the user's app still has focus.
+    let pressed = Instant::now();
+    focus::remember(app);
+    let app_context = focus::previous_context(app);
+    let profile = app_context
+        .as_ref()
+        .and_then(|context| profiles::resolve(app, context));
+    let (include_selection, behavior) = profile
+        .as_ref()
+        .map_or((true, HotkeyBehavior::Default), |p| {
+            (p.include_selection, p.hotkey_behavior)
+        });
+    let anchor = match behavior {
+        HotkeyBehavior::Default => None,
+        HotkeyBehavior::AtSelection => overlay::selection_anchor(app),
+    };
+    let context = SummonContext {
+        selection: include_selection.then(|| selection::capture(app)).flatten(),
+        clipboard: clipboard::read(app).ok(),
+        app: app_context,
+        profile,
+    };
+    latency::record(
+        app,
+        None,
+        LatencyStage::ContextCapture,
+        None,
+        pressed.elapsed(),
+    );
+    if window_manager::show_overlay_nonactivating(app, anchor).is_ok() {
+        latency::record(app, None, LatencyStage::Summon, None, pressed.elapsed());
+        let _ = window_manager::emit_to(app, AppWindow::Overlay, "overlay-summoned", context);
+    }
+}
+
+/// The actions bound to a double tap, and of which modifier.
+pub fn double_taps(app: &tauri::AppHandle) -> Vec<(ShortcutAction, Modifier)> {
+    let registry = app.state::<ShortcutRegistry>();
+    let bound = registry.bound.lock().unwrap();
+    bound
+        .iter()
+        .filter_map(|(action, binding)| match binding {
+            Binding::DoubleTap(modifier) => Some((*action, *modifier)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Run `action` as if its shortcut had just been pressed.
+pub fn trigger(app: &tauri::AppHandle, action: ShortcutAction) {
+    let app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || run_action(&app, action, ShortcutState::Pressed));
+}
+
+/// Re-register the persisted shortcuts. Called once from `setup`.
+pub fn restore(app: &tauri::AppHandle) {
+    let registry = app.state::<ShortcutRegistry>();
+    let config: ShortcutConfig = persist::load_json(&registry.path);
+    let mut bound = registry.bound.lock().unwrap();
+    for (action, accelerator) in config {
+        let Some(binding) = accelerator.as_deref().and_then(|a| parse_binding(a).ok()) else {
+            continue;
+        };
+        if bind(app, action, binding).is_ok() {
+            bound.insert(action, binding);
+        }
+    }
+}
+
+/// Bind `accelerator` to `action`, replacing whatever the action was bound to before.
+/// Besides a plain accelerator this takes a chord of two, like `Ctrl+K Ctrl+A`, or a
+/// modifier twice for a double tap, like `Ctrl Ctrl`, which hardly ever clash with
+/// other apps' shortcuts.
+pub fn register(
+    app: &tauri::AppHandle,
+    action: ShortcutAction,
+    accelerator: &str,
+) -> Result<String, String> {
+    let binding = parse_binding(accelerator)?;
+    if action == ShortcutAction::PushToTalk && !matches!(binding, Binding::Accelerator(_)) {
+        return Err("Push-to-talk needs a single accelerator that can be held down".into());
+    }
+    let registry = app.state::<ShortcutRegistry>();
+    let mut bound = registry.bound.lock().unwrap();
+
+    if bound.get(&action) == Some(&binding) {
+        return Ok(binding.into_string());
+    }
+    let taken_by_action = bound
+        .iter()
+        .any(|(other, existing)| *other != action && existing.overlaps(binding));
+    let grabbed = binding.shortcuts().first().copied();
+    if taken_by_action || grabbed.is_some_and(|s| app.global_shortcut().is_registered(s)) {
+        return Err(format!(
+            "{} is already in use by another action",
+            binding.into_string()
+        ));
+    }
+
+    // Bind the new shortcut before releasing the old one so a failure keeps the user's
+    // existing binding working.
+    bind(app, action, binding)?;
+    if let Some(previous) = bound.insert(action, binding) {
+        let _ = release(app, previous);
+    }
+    registry.persist(&bound)?;
+    Ok(binding.into_string())
+}
+
+/// Bind an accelerator or chord to something other than a `ShortcutAction`, such as a
+/// quick action, calling `on_press` each time it fires. Returns the shortcut as it is
+/// written back. Double taps are left to the built-in actions.
+pub fn bind_callback<F>(
+    app: &tauri::AppHandle,
+    accelerator: &str,
+    on_press: F,
+) -> Result<String, String>
+where
+    F: Fn(&tauri::AppHandle) + Clone + Send + Sync + 'static,
+{
+    let binding = parse_binding(accelerator)?;
+    if matches!(binding, Binding::DoubleTap(_)) {
+        return Err("A double tap can only be bound to a built-in shortcut action".into());
+    }
+    let registry = app.state::<ShortcutRegistry>();
+    let bound = registry.bound.lock().unwrap();
+    let taken_by_action = bound.values().any(|existing| existing.overlaps(binding));
+    let grabbed = binding.shortcuts()[0];
+    if taken_by_action || app.global_shortcut().is_registered(grabbed) {
+        return Err(format!(
+            "{} is already in use by another action",
+            binding.into_string()
+        ));
+    }
+    bind_with(app, binding, move |app, state| {
+        if state == ShortcutState::Pressed {
+            on_press(app);
+        }
+    })?;
+    Ok(binding.into_string())
+}
+
+/// Register every bound shortcut again, for when the keys they were grabbed on have
+/// moved. On X11 the grab on the old key outlives this until the app restarts, since
+/// the plugin ungrabs by the key the symbol is on now.
+pub fn rebind_all(app: &tauri::AppHandle) {
+    let registry = app.state::<ShortcutRegistry>();
+    let bound = registry.bound.lock().unwrap();
+    for (action, binding) in bound.iter() {
+        let _ = release(app, *binding);
+        if let Err(e) = bind(app, *action, *binding) {
+            tracing::warn!("Could not bind {action:?} again: {e}");
+        }
+    }
+}
+
+/// How each bound shortcut reads on the current keyboard layout.
+pub fn describe_bound(app: &tauri::AppHandle) -> HashMap<ShortcutAction, String> {
+    // Copied out first: describing may wait on the main thread, which may want the lock.
+    let bound = app
+        .state::<ShortcutRegistry>()
+        .bound
+        .lock()
+        .unwrap()
+        .clone();
+    bound
+        .into_iter()
+        .map(|(action, binding)| (action, binding.describe(app)))
+        .collect()
+}
+
+/// Release a shortcut bound with `bind_callback`.
+pub fn unbind_callback(app: &tauri::AppHandle, accelerator: &str) -> Result<(), String> {
+    release(app, parse_binding(accelerator)?)
+}
+
+pub fn unregister(app: &tauri::AppHandle, action: ShortcutAction) -> Result<(), String> {
+    let registry = app.state::<ShortcutRegistry>();
+    let mut bound = registry.bound.lock().unwrap();
+    if let Some(binding) = bound.remove(&action) {
+        release(app, binding)?;
+    }
+    registry.persist(&bound)
+}
+
+#[tauri::command]
+pub fn list_shortcuts(app: tauri::AppHandle) -> HashMap<ShortcutAction, String> {
+    let registry = app.state::<ShortcutRegistry>();
+    let bound = registry.bound.lock().unwrap();
+    bound
+        .iter()
+        .map(|(action, binding)| (*action, binding.into_string()))
+        .collect()
+}
+
+/// How `accelerator` reads on the current keyboard layout, such as `⌘⇧K` on macOS or
+/// `Ctrl+Shift+K` elsewhere, with a key named for what the layout prints on it.
+#[tauri::command]
+pub async fn describe_shortcut(
+    app: tauri::AppHandle,
+    accelerator: String,
+) -> Result<String, AppError> {
+    let binding = parse_binding(&accelerator)?;
+    tauri::async_runtime::spawn_blocking(move || binding.describe(&app))
+        .await
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+pub fn register_shortcut(
+    app: tauri::AppHandle,
+    action: ShortcutAction,
+    accelerator: String,
+) -> Result<String, AppError> {
+    register(&app, action, &accelerator).map_err(AppError::from)
+}
+
+#[tauri::command]
+pub fn unregister_shortcut(app: tauri::AppHandle, action: ShortcutAction) -> Result<(), AppError> {
+    unregister(&app, action).map_err(AppError::from)
+}
+
+#[tauri::command]
+pub fn register_overlay_shortcut(
+    app: tauri::AppHandle,
+    accelerator: String,
+) -> Result<String, AppError> {
+    register(&app, ShortcutAction::Overlay, &accelerator).map_err(AppError::from)
+}
+
+#[tauri::command]
+pub fn unregister_overlay_shortcut(app: tauri::AppHandle) -> Result<(), AppError> {
+    unregister(&app, ShortcutAction::Overlay).map_err(AppError::from)
+}
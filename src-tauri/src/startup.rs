@@ -0,0 +1,124 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::Manager;
+
+use crate::settings;
+use crate::window_manager::{self, AppWindow};
+
+/// One step of starting up, timed from launch.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StartupPhase {
+    pub name: &'static str,
+    /// When the phase began, in milliseconds since launch.
+    pub started_ms: u64,
+    pub duration_ms: u64,
+    /// Whether it ran alongside setup rather than holding it up.
+    pub background: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StartupReport {
+    /// How long until setup returned and the app could take commands; `None` before.
+    pub setup_ms: Option<u64>,
+    pub phases: Vec<StartupPhase>,
+}
+
+/// Timings of the phases of startup. Created as the builder is set up, which stands in
+/// for launch.
+pub struct StartupMetrics {
+    launched: Instant,
+    last_mark: Mutex<Instant>,
+    setup_done: Mutex<Option<Instant>>,
+    phases: Mutex<Vec<StartupPhase>>,
+}
+
+impl Default for StartupMetrics {
+    fn default() -> Self {
+        let now = Instant::now();
+        Self {
+            launched: now,
+            last_mark: Mutex::new(now),
+            setup_done: Mutex::new(None),
+            phases: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+fn millis(duration: Duration) -> u64 {
+    duration.as_millis().try_into().unwrap_or(u64::MAX)
+}
+
+impl StartupMetrics {
+    fn record(&self, name: &'static str, started: Instant, background: bool) {
+        let phase = StartupPhase {
+            name,
+            started_ms: millis(started.saturating_duration_since(self.launched)),
+            duration_ms: millis(started.elapsed()),
+            background,
+        };
+        tracing::debug!("Startup phase {name} took {} ms", phase.duration_ms);
+        self.phases.lock().unwrap().push(phase);
+    }
+}
+
+/// End a phase of setup named `name`, which began where the last one ended.
+pub fn mark(app: &tauri::AppHandle, name: &'static str) {
+    let metrics = app.state::<StartupMetrics>();
+    let mut last = metrics.last_mark.lock().unwrap();
+    metrics.record(name, *last, false);
+    *last = Instant::now();
+}
+
+/// Setup has returned. Everything still running from here on is in the background.
+pub fn finish_setup(app: &tauri::AppHandle) {
+    mark(app, "setup");
+    let metrics = app.state::<StartupMetrics>();
+    *metrics.setup_done.lock().unwrap() = Some(Instant::now());
+    tracing::info!("Started in {} ms", millis(metrics.launched.elapsed()));
+}
+
+/// Time `f` as a phase of startup that doesn't hold up setup.
+pub fn background<T>(app: &tauri::AppHandle, name: &'static str, f: impl FnOnce() -> T) -> T {
+    let started = Instant::now();
+    let result = f();
+    app.state::<StartupMetrics>().record(name, started, true);
+    result
+}
+
+/// Build the overlay hidden once `overlay.prewarmDelayMs` has passed, so its webview is
+/// loaded by the first summon. Without a delay it is built on that summon instead.
+pub fn prewarm_overlay(app: &tauri::AppHandle) {
+    let Some(delay) = settings::current(app).overlay.prewarm_delay_ms else {
+        return;
+    };
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(delay)).await;
+        if window_manager::get(&app, AppWindow::Overlay).is_some() {
+            return;
+        }
+        let handle = app.clone();
+        let built = tauri::async_runtime::spawn_blocking(move || {
+            background(&handle, "overlayPrewarm", || {
+                window_manager::get_or_create(&handle, AppWindow::Overlay)
+            })
+        })
+        .await;
+        if let Ok(Err(error)) = built {
+            tracing::warn!("Could not prewarm the overlay: {error}");
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_startup_metrics(metrics: tauri::State<'_, StartupMetrics>) -> StartupReport {
+    let setup_done = *metrics.setup_done.lock().unwrap();
+    StartupReport {
+        setup_ms: setup_done.map(|done| millis(done.saturating_duration_since(metrics.launched))),
+        phases: metrics.phases.lock().unwrap().clone(),
+    }
+}
@@ -0,0 +1,132 @@
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, Manager, State};
+use tokio::sync::Mutex;
+
+use crate::db::Database;
+use crate::error::AppError;
+use crate::providers::status::{self, ProviderStatus};
+use crate::settings::{self, Settings};
+use crate::window_manager::{self, AppWindow, WindowInfo};
+use crate::{history, overlay, providers, requests};
+
+/// What the user is working with right now. Settings, overlay modes, windows and
+/// requests stay with the modules that own them; `get_app_state` puts all of it
+/// together so the frontend has one place to read it from.
+#[derive(Default)]
+pub struct AppState(Mutex<Session>);
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Session {
+    /// The conversation new messages go to.
+    pub active_conversation: Option<String>,
+    /// The model picked for this session, ahead of the one in settings until cleared.
+    pub model_override: Option<ActiveModel>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActiveModel {
+    pub provider: String,
+    pub model: String,
+    pub base_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OverlayModes {
+    pub visible: bool,
+    pub pinned: bool,
+    pub click_through: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppStateSnapshot {
+    pub settings: Settings,
+    #[serde(flatten)]
+    pub session: Session,
+    /// The model chats use when they don't name one, `None` if none is selected.
+    pub active_model: Option<ActiveModel>,
+    pub overlay: OverlayModes,
+    pub windows: Vec<WindowInfo>,
+    /// Ids of the requests still running.
+    pub requests: Vec<String>,
+    pub providers: Vec<ProviderStatus>,
+}
+
+pub async fn session(app: &tauri::AppHandle) -> Session {
+    app.state::<AppState>().0.lock().await.clone()
+}
+
+/// The session's model if one was picked, else the one selected in settings.
+pub async fn active_model(app: &tauri::AppHandle) -> Option<ActiveModel> {
+    if let Some(model) = session(app).await.model_override {
+        return Some(model);
+    }
+    let assistant = settings::current(app).assistant;
+    Some(ActiveModel {
+        provider: assistant.provider,
+        model: assistant.model?,
+        base_url: assistant.base_url,
+    })
+}
+
+pub async fn snapshot(app: &tauri::AppHandle) -> AppStateSnapshot {
+    AppStateSnapshot {
+        settings: settings::current(app),
+        session: session(app).await,
+        active_model: active_model(app).await,
+        overlay: OverlayModes {
+            visible: window_manager::is_visible(app, AppWindow::Overlay),
+            pinned: overlay::pinned(app),
+            click_through: overlay::click_through(app),
+        },
+        windows: window_manager::list_windows(app.clone()),
+        requests: requests::active(app),
+        providers: status::get_provider_status(app.state()),
+    }
+}
+
+/// Change the session and broadcast the new state as `app-state-changed`.
+pub async fn update(app: &tauri::AppHandle, change: impl FnOnce(&mut Session)) {
+    change(&mut *app.state::<AppState>().0.lock().await);
+    let _ = app.emit("app-state-changed", snapshot(app).await);
+}
+
+#[tauri::command]
+pub async fn get_app_state(app: tauri::AppHandle) -> AppStateSnapshot {
+    snapshot(&app).await
+}
+
+#[tauri::command]
+pub async fn set_active_conversation(
+    app: tauri::AppHandle,
+    db: State<'_, Database>,
+    id: Option<String>,
+) -> Result<(), AppError> {
+    if let Some(id) = &id {
+        if db
+            .with(|conn| history::find_conversation(conn, id))?
+            .is_none()
+        {
+            return Err(AppError::NotFound(format!("Conversation {id} not found")));
+        }
+    }
+    update(&app, |session| session.active_conversation = id).await;
+    Ok(())
+}
+
+/// Use `model` for this session without touching settings, or go back to the model in
+/// settings with `None`.
+#[tauri::command]
+pub async fn set_session_model(
+    app: tauri::AppHandle,
+    model: Option<ActiveModel>,
+) -> Result<(), AppError> {
+    if let Some(model) = &model {
+        providers::resolve(&app, &model.provider, model.base_url.as_deref())?;
+    }
+    update(&app, |session| session.model_override = model).await;
+    Ok(())
+}
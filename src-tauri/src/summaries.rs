@@ -0,0 +1,289 @@
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+use rusqlite::{params, Connection, Row};
+use serde::Serialize;
+use tauri::{Emitter, Manager};
+
+use crate::db::{self, Database};
+use crate::error::AppError;
+use crate::history::{self, Message};
+use crate::llm::{self, ChatMessage};
+use crate::{settings, state, tokens};
+
+const SUMMARY_PROMPT: &str = "You condense conversations between a user and an AI \
+assistant. Given the summary so far, if any, and the messages that follow it, write one \
+updated summary of the whole conversation for the assistant to carry on from. Keep the \
+user's goals, decisions, facts they gave, names, numbers, code and file names, open \
+questions and anything the assistant promised. Drop greetings and repetition. Write it \
+as compact notes in the conversation's language, and reply with the summary only.";
+/// Leads the summary where it stands in for the messages it covers.
+const SUMMARY_HEADING: &str = "Summary of the earlier conversation:";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConversationSummary {
+    pub id: String,
+    pub conversation_id: String,
+    /// The last message covered, along with everything before it.
+    pub through_message_id: String,
+    pub content: String,
+    /// Messages covered, counting from the first.
+    pub message_count: usize,
+    pub created_at: i64,
+}
+
+/// Conversations being summarized right now, so each is only summarized once at a time.
+#[derive(Default)]
+pub struct Summarizer(Mutex<HashSet<String>>);
+
+struct Running<'a>(&'a Summarizer, String);
+
+impl Drop for Running<'_> {
+    fn drop(&mut self) {
+        self.0 .0.lock().unwrap().remove(&self.1);
+    }
+}
+
+fn summary_from_row(row: &Row) -> rusqlite::Result<ConversationSummary> {
+    Ok(ConversationSummary {
+        id: row.get(0)?,
+        conversation_id: row.get(1)?,
+        through_message_id: row.get(2)?,
+        content: row.get(3)?,
+        message_count: row.get::<_, i64>(4)? as usize,
+        created_at: row.get(5)?,
+    })
+}
+
+/// The newest summary of a branch that goes through `path`, with the position in it of
+/// the last message covered.
+fn latest_on_path(
+    conn: &Connection,
+    conversation_id: &str,
+    path: &[Message],
+) -> rusqlite::Result<Option<(ConversationSummary, usize)>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, conversation_id, through_message_id, content, message_count, created_at
+         FROM conversation_summaries WHERE conversation_id = ?1
+         ORDER BY created_at DESC, rowid DESC",
+    )?;
+    let summaries = stmt.query_map([conversation_id], summary_from_row)?;
+    for summary in summaries {
+        let summary = summary?;
+        if let Some(index) = path
+            .iter()
+            .position(|message| message.id == summary.through_message_id)
+        {
+            return Ok(Some((summary, index)));
+        }
+    }
+    Ok(None)
+}
+
+fn chat_message(role: &str, content: String) -> ChatMessage {
+    ChatMessage {
+        role: role.to_string(),
+        content,
+        images: Vec::new(),
+    }
+}
+
+/// `messages` for a chat in `conversation_id`, with the ones its latest summary covers
+/// swapped for the summary. Left as they are unless they begin with the conversation's
+/// shown branch, as a chat sent from the overlay does.
+pub fn condense(
+    app: &tauri::AppHandle,
+    conversation_id: &str,
+    messages: Vec<ChatMessage>,
+) -> Vec<ChatMessage> {
+    let found = app.state::<Database>().with(|conn| {
+        let path = history::conversation_messages(conn, conversation_id)?;
+        let summary = latest_on_path(conn, conversation_id, &path)?;
+        Ok(summary.map(|(summary, index)| (summary, path[..=index].to_vec())))
+    });
+    let (summary, covered) = match found {
+        Ok(Some(found)) => found,
+        Ok(None) => return messages,
+        Err(error) => {
+            tracing::warn!("Could not read the summary of {conversation_id}: {error}");
+            return messages;
+        }
+    };
+    let covered: Vec<&Message> = covered.iter().filter(|m| m.role != "system").collect();
+    let conversation: Vec<usize> = (0..messages.len())
+        .filter(|&i| messages[i].role != "system")
+        .collect();
+    // Only when what was sent is the branch the summary was made from, and goes on past it.
+    let matches = conversation.len() > covered.len()
+        && covered.iter().zip(&conversation).all(|(stored, &i)| {
+            stored.role == messages[i].role && stored.content == messages[i].content
+        });
+    if !matches {
+        return messages;
+    }
+    let dropped: HashSet<usize> = conversation[..covered.len()].iter().copied().collect();
+    let first = conversation[0];
+    let mut condensed = Vec::with_capacity(messages.len() - dropped.len() + 1);
+    for (i, message) in messages.into_iter().enumerate() {
+        if i == first {
+            condensed.push(chat_message(
+                "system",
+                format!("{SUMMARY_HEADING}\n{}", summary.content),
+            ));
+        }
+        if !dropped.contains(&i) {
+            condensed.push(message);
+        }
+    }
+    condensed
+}
+
+fn transcript(messages: &[Message]) -> String {
+    messages
+        .iter()
+        .map(|message| format!("{}: {}", message.role, message.content.trim()))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Fold the older messages of a conversation's shown branch into its rolling summary,
+/// leaving `history.keepRecentMessages` of the newest out. Unless `force` is set this
+/// only happens once the branch takes up half the model's context window. Returns the
+/// new summary, or `None` when nothing needed summarizing.
+pub async fn summarize(
+    app: &tauri::AppHandle,
+    conversation_id: &str,
+    force: bool,
+) -> Result<Option<ConversationSummary>, AppError> {
+    let summarizer = app.state::<Summarizer>();
+    if !summarizer
+        .0
+        .lock()
+        .unwrap()
+        .insert(conversation_id.to_string())
+    {
+        return Ok(None);
+    }
+    let _running = Running(&summarizer, conversation_id.to_string());
+
+    let (path, previous) = app
+        .state::<Database>()
+        .with(|conn| {
+            if history::find_conversation(conn, conversation_id)?.is_none() {
+                return Err(rusqlite::Error::QueryReturnedNoRows);
+            }
+            let path = history::conversation_messages(conn, conversation_id)?;
+            let previous = latest_on_path(conn, conversation_id, &path)?;
+            Ok((path, previous))
+        })
+        .map_err(|_| AppError::NotFound(format!("Conversation {conversation_id} not found")))?;
+
+    let start = previous.as_ref().map_or(0, |(_, index)| index + 1);
+    let keep = settings::current(app).history.keep_recent_messages;
+    let end = path.len().saturating_sub(keep);
+    if end <= start {
+        return Ok(None);
+    }
+    let model = state::active_model(app)
+        .await
+        .map(|model| model.model)
+        .unwrap_or_default();
+    let window = tokens::context_window(app, &model);
+    let previous_text = previous
+        .as_ref()
+        .map(|(summary, _)| summary.content.as_str());
+    let mut used = tokens::count(&model, previous_text.unwrap_or_default());
+    if !force {
+        let unsummarized: usize = path[start..]
+            .iter()
+            .map(|message| tokens::count(&model, &message.content))
+            .sum();
+        if used + unsummarized < window / 2 {
+            return Ok(None);
+        }
+    }
+
+    // As much as fits in half the window at once; the rest is folded in next time.
+    let mut through = start;
+    for (index, message) in path.iter().enumerate().take(end).skip(start) {
+        used += tokens::count(&model, &message.content);
+        if index > start && used > window / 2 {
+            break;
+        }
+        through = index;
+    }
+    let mut prompt = String::new();
+    if let Some(previous) = previous_text {
+        prompt.push_str(&format!("Summary so far:\n{previous}\n\n"));
+    }
+    prompt.push_str(&format!(
+        "Messages that follow:\n\n{}",
+        transcript(&path[start..=through])
+    ));
+    let messages = vec![
+        chat_message("system", SUMMARY_PROMPT.to_string()),
+        chat_message("user", prompt),
+    ];
+    let content = llm::complete(app, None, messages).await?.trim().to_string();
+    if content.is_empty() {
+        return Err(AppError::Other(
+            "The model returned an empty summary".into(),
+        ));
+    }
+
+    let summary = ConversationSummary {
+        id: uuid::Uuid::new_v4().to_string(),
+        conversation_id: conversation_id.to_string(),
+        through_message_id: path[through].id.clone(),
+        content,
+        message_count: through + 1,
+        created_at: db::now_ms(),
+    };
+    app.state::<Database>().with(|conn| {
+        conn.execute(
+            "INSERT INTO conversation_summaries
+                 (id, conversation_id, through_message_id, content, message_count, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                summary.id,
+                summary.conversation_id,
+                summary.through_message_id,
+                summary.content,
+                summary.message_count as i64,
+                summary.created_at
+            ],
+        )
+    })?;
+    tracing::info!(
+        "Summarized {} messages of conversation {conversation_id}",
+        summary.message_count
+    );
+    let _ = app.emit("conversation-summarized", &summary);
+    Ok(Some(summary))
+}
+
+/// Summarize in the background if the conversation has grown long enough, per
+/// `history.autoSummarize`. Called as messages are added.
+pub fn schedule(app: &tauri::AppHandle, conversation_id: &str) {
+    if !settings::current(app).history.auto_summarize {
+        return;
+    }
+    let app = app.clone();
+    let conversation_id = conversation_id.to_string();
+    tauri::async_runtime::spawn(async move {
+        if let Err(error) = summarize(&app, &conversation_id, false).await {
+            tracing::warn!("Could not summarize conversation {conversation_id}: {error}");
+        }
+    });
+}
+
+/// Summarize the older messages of a conversation now, however long it is. Returns
+/// `None` when there is nothing older than the messages always kept as written.
+#[tauri::command]
+pub async fn summarize_conversation(
+    app: tauri::AppHandle,
+    id: String,
+) -> Result<Option<ConversationSummary>, AppError> {
+    summarize(&app, &id, true).await
+}
@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, Manager};
+
+use crate::clipboard::{self, ClipboardContent};
+use crate::error::AppError;
+use crate::{focus, persist, selection};
+
+const TEMPLATES_FILE: &str = "templates.json";
+
+/// Placeholders every template can use without the caller supplying them.
+const BUILTIN_VARIABLES: [&str; 3] = ["selection", "clipboard", "app"];
+
+/// A named prompt with `{{variable}}` placeholders.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptTemplate {
+    /// Assigned by `save_template` when empty.
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    pub body: String,
+}
+
+pub struct TemplateStore {
+    path: PathBuf,
+    templates: Mutex<Vec<PromptTemplate>>,
+}
+
+impl TemplateStore {
+    pub fn load(app: &tauri::AppHandle) -> Result<Self, String> {
+        let path = persist::data_file(app, TEMPLATES_FILE)?;
+        let templates = persist::load_json(&path);
+        Ok(Self {
+            path,
+            templates: Mutex::new(templates),
+        })
+    }
+}
+
+/// Replace each `{{ name }}` with its value. Placeholders without a value are left as
+/// written so a typo shows up in the prompt instead of silently vanishing.
+pub fn render(body: &str, vars: &HashMap<String, String>) -> String {
+    let mut out = String::with_capacity(body.len());
+    let mut rest = body;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let placeholder = &rest[start..start + 2 + len + 2];
+        out.push_str(&rest[..start]);
+        match vars.get(rest[start + 2..start + 2 + len].trim()) {
+            Some(value) => out.push_str(value),
+            None => out.push_str(placeholder),
+        }
+        rest = &rest[start + placeholder.len()..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Values for the built-in placeholders, taken from what was captured when the overlay
+/// was last summoned. Missing ones render as empty rather than as a raw placeholder.
+pub fn builtin_variables(app: &tauri::AppHandle) -> HashMap<String, String> {
+    let clipboard = match clipboard::read(app) {
+        Ok(ClipboardContent::Text { text }) => Some(text),
+        _ => None,
+    };
+    let app_name = focus::previous_context(app).and_then(|context| context.app_name);
+    BUILTIN_VARIABLES
+        .into_iter()
+        .zip([selection::cached(app), clipboard, app_name])
+        .map(|(name, value)| (name.to_string(), value.unwrap_or_default()))
+        .collect()
+}
+
+#[tauri::command]
+pub fn list_templates(app: tauri::AppHandle) -> Vec<PromptTemplate> {
+    app.state::<TemplateStore>()
+        .templates
+        .lock()
+        .unwrap()
+        .clone()
+}
+
+/// Create a template, or replace the one with the same id.
+#[tauri::command]
+pub fn save_template(
+    app: tauri::AppHandle,
+    mut template: PromptTemplate,
+) -> Result<PromptTemplate, AppError> {
+    if template.name.trim().is_empty() {
+        return Err(AppError::InvalidInput(
+            "Template name must not be empty".to_string(),
+        ));
+    }
+    if template.id.is_empty() {
+        template.id = uuid::Uuid::new_v4().to_string();
+    }
+
+    let store = app.state::<TemplateStore>();
+    let mut templates = store.templates.lock().unwrap();
+    match templates.iter_mut().find(|t| t.id == template.id) {
+        Some(existing) => *existing = template.clone(),
+        None => templates.push(template.clone()),
+    }
+    persist::save_json(&store.path, &*templates)?;
+    let _ = app.emit("templates-changed", &*templates);
+    Ok(template)
+}
+
+/// Swap in a whole list of templates, such as restored ones.
+pub fn replace(app: &tauri::AppHandle, replacement: Vec<PromptTemplate>) -> Result<(), String> {
+    let store = app.state::<TemplateStore>();
+    let mut templates = store.templates.lock().unwrap();
+    persist::save_json(&store.path, &replacement)?;
+    *templates = replacement;
+    let _ = app.emit("templates-changed", &*templates);
+    Ok(())
+}
+
+/// Returns whether a template was deleted.
+#[tauri::command]
+pub fn delete_template(app: tauri::AppHandle, id: String) -> Result<bool, AppError> {
+    let store = app.state::<TemplateStore>();
+    let mut templates = store.templates.lock().unwrap();
+    let before = templates.len();
+    templates.retain(|t| t.id != id);
+    if templates.len() == before {
+        return Ok(false);
+    }
+    persist::save_json(&store.path, &*templates)?;
+    let _ = app.emit("templates-changed", &*templates);
+    Ok(true)
+}
+
+/// Render the saved template `id`, with `vars` on top of the built-in values.
+pub fn render_saved(
+    app: &tauri::AppHandle,
+    id: &str,
+    vars: HashMap<String, String>,
+) -> Result<String, String> {
+    let body = app
+        .state::<TemplateStore>()
+        .templates
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|t| t.id == id)
+        .map(|t| t.body.clone())
+        .ok_or_else(|| format!("Template {id} not found"))?;
+    let mut values = builtin_variables(app);
+    values.extend(vars);
+    Ok(render(&body, &values))
+}
+
+/// Render a saved template. `vars` override the built-in `selection`, `clipboard` and
+/// `app` values and may define any other placeholder.
+#[tauri::command]
+pub fn render_template(
+    app: tauri::AppHandle,
+    id: String,
+    vars: Option<HashMap<String, String>>,
+) -> Result<String, AppError> {
+    Ok(render_saved(&app, &id, vars.unwrap_or_default())?)
+}
@@ -0,0 +1,319 @@
+use std::sync::Mutex;
+
+use serde::Serialize;
+use tauri::{Emitter, Listener, Manager};
+
+use crate::backdrop::{self, Appearance};
+use crate::settings::{self, Theme};
+
+/// What the OS is set to, whatever the theme setting says.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SystemTheme {
+    pub appearance: Appearance,
+    /// As `#rrggbb`, where the OS has one and says what it is.
+    pub accent_color: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ThemeChangedPayload {
+    system: SystemTheme,
+    /// The appearance the app uses, once the theme setting is applied.
+    appearance: Appearance,
+}
+
+/// The system theme as last seen, to tell a real change from a repeat notification.
+#[derive(Default)]
+pub struct ThemeState(Mutex<Option<SystemTheme>>);
+
+fn hex(red: u8, green: u8, blue: u8) -> String {
+    format!("#{red:02x}{green:02x}{blue:02x}")
+}
+
+#[cfg(windows)]
+mod platform {
+    use windows::Foundation::TypedEventHandler;
+    use windows::UI::ViewManagement::{UIColorType, UISettings};
+
+    use super::{hex, SystemTheme};
+    use crate::backdrop::Appearance;
+
+    pub async fn detect(_app: &tauri::AppHandle) -> Option<SystemTheme> {
+        let settings = UISettings::new().ok()?;
+        let foreground = settings.GetColorValue(UIColorType::Foreground).ok()?;
+        // Light text means a dark theme; the weights are the ones Microsoft suggests.
+        let (r, g, b) = (
+            u32::from(foreground.R),
+            u32::from(foreground.G),
+            u32::from(foreground.B),
+        );
+        let appearance = if 5 * g + 2 * r + b > 8 * 128 {
+            Appearance::Dark
+        } else {
+            Appearance::Light
+        };
+        let accent_color = settings
+            .GetColorValue(UIColorType::Accent)
+            .ok()
+            .map(|accent| hex(accent.R, accent.G, accent.B));
+        Some(SystemTheme {
+            appearance,
+            accent_color,
+        })
+    }
+
+    /// `UISettings` reports changes for as long as it lives, so a thread keeps it.
+    pub fn watch(app: &tauri::AppHandle) {
+        let app = app.clone();
+        let spawned = std::thread::Builder::new()
+            .name("theme-watch".to_string())
+            .spawn(move || {
+                let settings = match UISettings::new() {
+                    Ok(settings) => settings,
+                    Err(error) => {
+                        tracing::warn!("Not watching the system theme: {error}");
+                        return;
+                    }
+                };
+                let handler = TypedEventHandler::new(move |_, _| {
+                    let app = app.clone();
+                    tauri::async_runtime::spawn(async move { super::changed(&app).await });
+                    Ok(())
+                });
+                if let Err(error) = settings.ColorValuesChanged(&handler) {
+                    tracing::warn!("Not watching the system theme: {error}");
+                    return;
+                }
+                loop {
+                    std::thread::park();
+                }
+            });
+        if let Err(error) = spawned {
+            tracing::warn!("Not watching the system theme: {error}");
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::time::Duration;
+
+    use objc2_app_kit::{NSColor, NSColorSpace};
+    use objc2_foundation::{NSString, NSUserDefaults};
+
+    use super::{hex, SystemTheme};
+    use crate::backdrop::Appearance;
+
+    /// Neither setting has a notification that reaches us off the main run loop.
+    const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+    fn component(value: f64) -> u8 {
+        (value.clamp(0.0, 1.0) * 255.0).round() as u8
+    }
+
+    /// Read from the global defaults rather than the app, whose appearance follows the
+    /// theme setting.
+    pub async fn detect(_app: &tauri::AppHandle) -> Option<SystemTheme> {
+        let style = NSUserDefaults::standardUserDefaults()
+            .stringForKey(&NSString::from_str("AppleInterfaceStyle"));
+        let appearance = match style {
+            Some(style) if style.to_string() == "Dark" => Appearance::Dark,
+            _ => Appearance::Light,
+        };
+        let accent_color = NSColor::controlAccentColor()
+            .colorUsingColorSpace(&NSColorSpace::sRGBColorSpace())
+            .map(|color| {
+                hex(
+                    component(color.redComponent()),
+                    component(color.greenComponent()),
+                    component(color.blueComponent()),
+                )
+            });
+        Some(SystemTheme {
+            appearance,
+            accent_color,
+        })
+    }
+
+    pub fn watch(app: &tauri::AppHandle) {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            loop {
+                tokio::time::sleep(POLL_INTERVAL).await;
+                super::changed(&app).await;
+            }
+        });
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use ashpd::desktop::settings::{ColorScheme, Settings, APPEARANCE_NAMESPACE};
+    use futures_util::StreamExt;
+
+    use super::{hex, SystemTheme};
+    use crate::backdrop::Appearance;
+
+    fn component(value: f64) -> u8 {
+        (value.clamp(0.0, 1.0) * 255.0).round() as u8
+    }
+
+    /// From the desktop portal, which GNOME, KDE and most others implement.
+    pub async fn detect(_app: &tauri::AppHandle) -> Option<SystemTheme> {
+        let settings = Settings::new().await.ok()?;
+        let appearance = match settings.color_scheme().await.ok()? {
+            ColorScheme::PreferDark => Appearance::Dark,
+            ColorScheme::PreferLight | ColorScheme::NoPreference => Appearance::Light,
+        };
+        // Out of range when the desktop has no accent color.
+        let accent_color = settings
+            .accent_color()
+            .await
+            .ok()
+            .filter(|color| {
+                [color.red(), color.green(), color.blue()]
+                    .iter()
+                    .all(|c| (0.0..=1.0).contains(c))
+            })
+            .map(|color| {
+                hex(
+                    component(color.red()),
+                    component(color.green()),
+                    component(color.blue()),
+                )
+            });
+        Some(SystemTheme {
+            appearance,
+            accent_color,
+        })
+    }
+
+    pub fn watch(app: &tauri::AppHandle) {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            let changes = match Settings::new().await {
+                Ok(settings) => settings
+                    .receive_setting_changed()
+                    .await
+                    .map(|s| (settings, s)),
+                Err(error) => Err(error),
+            };
+            let (_settings, mut changes) = match changes {
+                Ok(changes) => changes,
+                Err(error) => {
+                    tracing::warn!("Not watching the system theme: {error}");
+                    return;
+                }
+            };
+            while let Some(setting) = changes.next().await {
+                if setting.namespace() == APPEARANCE_NAMESPACE {
+                    super::changed(&app).await;
+                }
+            }
+        });
+    }
+}
+
+/// The system theme, falling back on what the main window was given when the OS won't
+/// say.
+pub async fn detect(app: &tauri::AppHandle) -> SystemTheme {
+    if let Some(theme) = platform::detect(app).await {
+        return theme;
+    }
+    let appearance = app
+        .get_webview_window("main")
+        .and_then(|window| window.theme().ok())
+        .map_or(Appearance::Light, |theme| match theme {
+            tauri::Theme::Dark => Appearance::Dark,
+            _ => Appearance::Light,
+        });
+    SystemTheme {
+        appearance,
+        accent_color: None,
+    }
+}
+
+/// The system appearance as last seen.
+pub fn system_appearance(app: &tauri::AppHandle) -> Option<Appearance> {
+    let seen = app.state::<ThemeState>().0.lock().unwrap().clone();
+    seen.map(|theme| theme.appearance)
+}
+
+/// The appearance the app uses: the theme setting, or the system's.
+pub fn appearance(app: &tauri::AppHandle) -> Appearance {
+    match settings::current(app).general.theme {
+        Theme::Light => Appearance::Light,
+        Theme::Dark => Appearance::Dark,
+        Theme::System => system_appearance(app).unwrap_or(Appearance::Light),
+    }
+}
+
+/// Give every native window frame, title bars included, the theme setting.
+fn apply_native(app: &tauri::AppHandle) {
+    app.set_theme(match settings::current(app).general.theme {
+        Theme::System => None,
+        Theme::Light => Some(tauri::Theme::Light),
+        Theme::Dark => Some(tauri::Theme::Dark),
+    });
+}
+
+fn emit(app: &tauri::AppHandle, system: SystemTheme) {
+    let _ = app.emit(
+        "theme-changed",
+        ThemeChangedPayload {
+            system,
+            appearance: appearance(app),
+        },
+    );
+}
+
+/// Look at the system theme again, and if it is new, redraw the backdrop and emit
+/// `theme-changed`.
+async fn changed(app: &tauri::AppHandle) {
+    let system = detect(app).await;
+    {
+        let state = app.state::<ThemeState>();
+        let mut seen = state.0.lock().unwrap();
+        if seen.as_ref() == Some(&system) {
+            return;
+        }
+        *seen = Some(system.clone());
+    }
+    tracing::debug!("System theme is now {system:?}");
+    backdrop::refresh(app);
+    emit(app, system);
+}
+
+/// Follow the system theme and the theme setting for as long as the app runs.
+pub fn init(app: &tauri::AppHandle) {
+    apply_native(app);
+    refresh(app);
+    platform::watch(app);
+
+    let applied = Mutex::new(settings::current(app).general.theme);
+    let handle = app.clone();
+    app.listen("settings-changed", move |_| {
+        let theme = settings::current(&handle).general.theme;
+        let mut applied = applied.lock().unwrap();
+        if *applied == theme {
+            return;
+        }
+        *applied = theme;
+        apply_native(&handle);
+        if let Some(system) = handle.state::<ThemeState>().0.lock().unwrap().clone() {
+            emit(&handle, system);
+        }
+    });
+}
+
+/// Check the system theme soon, for when a window saw it change.
+pub fn refresh(app: &tauri::AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move { changed(&app).await });
+}
+
+#[tauri::command]
+pub async fn get_system_theme(app: tauri::AppHandle) -> SystemTheme {
+    detect(&app).await
+}
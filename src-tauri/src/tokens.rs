@@ -0,0 +1,160 @@
+use serde::Serialize;
+use tauri::Emitter;
+use tiktoken_rs::tokenizer::{get_tokenizer, Tokenizer};
+use tiktoken_rs::{cl100k_base_singleton, o200k_base_singleton, CoreBPE};
+
+use crate::error::AppError;
+use crate::llm::ChatMessage;
+use crate::settings;
+
+/// Tokens the chat format adds around every message.
+const MESSAGE_OVERHEAD: usize = 4;
+/// Context assumed for models we know nothing about, mostly small local ones.
+const DEFAULT_CONTEXT_WINDOW: usize = 8192;
+/// Most of the window the reply is allowed to need.
+const MAX_REPLY_RESERVE: usize = 4096;
+
+/// Context window sizes by model-name prefix, checked in order against the lowercased
+/// name with any `vendor/` prefix removed.
+#[rustfmt::skip]
+const CONTEXT_WINDOWS: &[(&str, usize)] = &[
+    ("gpt-5", 400_000), ("gpt-4.1", 1_047_576), ("gpt-4o", 128_000), ("gpt-4-turbo", 128_000),
+    ("gpt-4-32k", 32_768), ("gpt-4", 8192), ("gpt-3.5", 16_385), ("o1", 200_000), ("o3", 200_000),
+    ("o4", 200_000), ("claude", 200_000), ("gemini", 1_048_576), ("deepseek", 128_000),
+    ("grok", 131_072), ("llama3.1", 128_000), ("llama3.2", 128_000), ("llama3.3", 128_000),
+    ("llama3", 8192), ("qwen", 32_768), ("mistral", 32_768), ("mixtral", 32_768),
+    ("gemma", 8192), ("phi", 4096),
+];
+
+/// The tokenizer for `model`. Models outside OpenAI's get cl100k, which is within a
+/// few percent for most modern BPE vocabularies.
+fn encoding(model: &str) -> &'static CoreBPE {
+    match get_tokenizer(model) {
+        Some(Tokenizer::O200kBase) => o200k_base_singleton(),
+        _ => cl100k_base_singleton(),
+    }
+}
+
+pub fn count(model: &str, text: &str) -> usize {
+    encoding(model).encode_ordinary(text).len()
+}
+
+fn count_messages(model: &str, messages: &[ChatMessage]) -> usize {
+    messages
+        .iter()
+        .map(|m| count(model, &m.content) + MESSAGE_OVERHEAD)
+        .sum()
+}
+
+/// The context window of `model`, or the override in settings.
+pub fn context_window(app: &tauri::AppHandle, model: &str) -> usize {
+    if let Some(window) = settings::current(app).assistant.context_window {
+        return window;
+    }
+    let name = model.to_lowercase();
+    let name = name.rsplit('/').next().unwrap_or(&name);
+    CONTEXT_WINDOWS
+        .iter()
+        .find(|(prefix, _)| name.starts_with(prefix))
+        .map_or(DEFAULT_CONTEXT_WINDOW, |&(_, window)| window)
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ContextTruncated<'a> {
+    request_id: &'a str,
+    model: &'a str,
+    dropped_messages: usize,
+    /// The newest message alone was too long and was cut short.
+    truncated_message: bool,
+    tokens: usize,
+    limit: usize,
+}
+
+/// Trim `messages` to fit the model's window, leaving room for the reply. System
+/// messages and the newest message are always kept; older turns are dropped oldest
+/// first, and the newest message is cut short if it still doesn't fit. Emits
+/// `context-truncated` when anything was removed.
+pub fn fit(
+    app: &tauri::AppHandle,
+    request_id: &str,
+    model: &str,
+    messages: &[ChatMessage],
+) -> Vec<ChatMessage> {
+    let window = context_window(app, model);
+    let limit = window - (window / 4).min(MAX_REPLY_RESERVE);
+    if count_messages(model, messages) <= limit {
+        return messages.to_vec();
+    }
+
+    let newest = messages.iter().rposition(|m| m.role != "system");
+    let cost = |m: &ChatMessage| count(model, &m.content) + MESSAGE_OVERHEAD;
+    let mut keep: Vec<bool> = (0..messages.len())
+        .map(|i| messages[i].role == "system" || Some(i) == newest)
+        .collect();
+    let mut used: usize = messages
+        .iter()
+        .zip(&keep)
+        .filter(|(_, &keep)| keep)
+        .map(|(m, _)| cost(m))
+        .sum();
+    let mut dropped = 0;
+    for i in (0..messages.len()).rev() {
+        if keep[i] {
+            continue;
+        }
+        let cost = cost(&messages[i]);
+        // Once one turn doesn't fit, keep nothing older so the history stays contiguous.
+        if dropped == 0 && used + cost <= limit {
+            keep[i] = true;
+            used += cost;
+        } else {
+            dropped += 1;
+        }
+    }
+
+    let mut fitted: Vec<ChatMessage> = messages
+        .iter()
+        .zip(&keep)
+        .filter(|(_, &keep)| keep)
+        .map(|(m, _)| m.clone())
+        .collect();
+    let mut truncated_message = false;
+    if used > limit {
+        if let Some(last) = fitted.iter_mut().rev().find(|m| m.role != "system") {
+            let tokens = encoding(model).encode_ordinary(&last.content);
+            let excess = used - limit;
+            let allowed = tokens.len().saturating_sub(excess);
+            if let Ok(text) = encoding(model).decode(tokens[..allowed].to_vec()) {
+                last.content = text;
+                used -= tokens.len() - allowed;
+                truncated_message = true;
+            }
+        }
+    }
+
+    if dropped == 0 && !truncated_message {
+        return fitted;
+    }
+    tracing::info!("Trimmed {dropped} messages to fit {model}'s context of {limit} tokens");
+    let _ = app.emit(
+        "context-truncated",
+        ContextTruncated {
+            request_id,
+            model,
+            dropped_messages: dropped,
+            truncated_message,
+            tokens: used,
+            limit,
+        },
+    );
+    fitted
+}
+
+/// How many tokens `text` takes up for `model`.
+#[tauri::command]
+pub async fn count_tokens(model: String, text: String) -> Result<usize, AppError> {
+    tauri::async_runtime::spawn_blocking(move || count(&model, &text))
+        .await
+        .map_err(AppError::from)
+}
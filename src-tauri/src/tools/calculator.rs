@@ -0,0 +1,174 @@
+use futures_util::future::BoxFuture;
+use serde_json::{json, Value};
+
+use super::{string_argument, Tool};
+
+/// Evaluates arithmetic so the model doesn't have to do it in its head.
+pub struct Calculator;
+
+impl Tool for Calculator {
+    fn name(&self) -> &'static str {
+        "calculator"
+    }
+
+    fn description(&self) -> &'static str {
+        "Evaluate an arithmetic expression. Supports + - * / % ^, parentheses, the constants \
+         pi and e, and sqrt, abs, ln, log, sin, cos, tan, floor, ceil and round."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "expression": { "type": "string", "description": "For example (2 + 3) * sqrt(16)" },
+            },
+            "required": ["expression"],
+        })
+    }
+
+    fn call<'a>(
+        &'a self,
+        _app: &'a tauri::AppHandle,
+        _request_id: &'a str,
+        arguments: Value,
+    ) -> BoxFuture<'a, Result<String, String>> {
+        Box::pin(async move {
+            let value = evaluate(string_argument(&arguments, "expression")?)?;
+            Ok(value.to_string())
+        })
+    }
+}
+
+fn evaluate(expression: &str) -> Result<f64, String> {
+    let mut parser = Parser {
+        chars: expression.chars().filter(|c| !c.is_whitespace()).collect(),
+        at: 0,
+    };
+    let value = parser.sum()?;
+    if parser.at < parser.chars.len() {
+        return Err(format!("Unexpected {:?}", parser.chars[parser.at]));
+    }
+    if !value.is_finite() {
+        return Err("The result is not a finite number".to_string());
+    }
+    Ok(value)
+}
+
+/// Recursive descent over the usual precedence levels: sums, products, powers, atoms.
+struct Parser {
+    chars: Vec<char>,
+    at: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.at).copied()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let found = self.peek() == Some(c);
+        if found {
+            self.at += 1;
+        }
+        found
+    }
+
+    fn sum(&mut self) -> Result<f64, String> {
+        let mut value = self.product()?;
+        loop {
+            if self.eat('+') {
+                value += self.product()?;
+            } else if self.eat('-') {
+                value -= self.product()?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn product(&mut self) -> Result<f64, String> {
+        let mut value = self.power()?;
+        loop {
+            if self.eat('*') {
+                value *= self.power()?;
+            } else if self.eat('/') {
+                value /= self.power()?;
+            } else if self.eat('%') {
+                value %= self.power()?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    /// `^` is right-associative and binds tighter than unary minus on its left.
+    fn power(&mut self) -> Result<f64, String> {
+        if self.eat('-') {
+            return Ok(-self.power()?);
+        }
+        if self.eat('+') {
+            return self.power();
+        }
+        let base = self.atom()?;
+        if self.eat('^') {
+            return Ok(base.powf(self.power()?));
+        }
+        Ok(base)
+    }
+
+    fn atom(&mut self) -> Result<f64, String> {
+        if self.eat('(') {
+            let value = self.sum()?;
+            if !self.eat(')') {
+                return Err("Missing )".to_string());
+            }
+            return Ok(value);
+        }
+        let start = self.at;
+        match self.peek() {
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                while self.peek().is_some_and(|c| c.is_ascii_digit() || c == '.') {
+                    self.at += 1;
+                }
+                let number: String = self.chars[start..self.at].iter().collect();
+                number
+                    .parse()
+                    .map_err(|_| format!("Invalid number {number}"))
+            }
+            Some(c) if c.is_ascii_alphabetic() => {
+                while self.peek().is_some_and(|c| c.is_ascii_alphanumeric()) {
+                    self.at += 1;
+                }
+                let name: String = self.chars[start..self.at].iter().collect();
+                match name.as_str() {
+                    "pi" => return Ok(std::f64::consts::PI),
+                    "e" => return Ok(std::f64::consts::E),
+                    _ => {}
+                }
+                if !self.eat('(') {
+                    return Err(format!("Unknown constant {name}"));
+                }
+                let argument = self.sum()?;
+                if !self.eat(')') {
+                    return Err("Missing )".to_string());
+                }
+                let function: fn(f64) -> f64 = match name.as_str() {
+                    "sqrt" => f64::sqrt,
+                    "abs" => f64::abs,
+                    "ln" => f64::ln,
+                    "log" => f64::log10,
+                    "sin" => f64::sin,
+                    "cos" => f64::cos,
+                    "tan" => f64::tan,
+                    "floor" => f64::floor,
+                    "ceil" => f64::ceil,
+                    "round" => f64::round,
+                    _ => return Err(format!("Unknown function {name}")),
+                };
+                Ok(function(argument))
+            }
+            Some(c) => Err(format!("Unexpected {c:?}")),
+            None => Err("The expression ended early".to_string()),
+        }
+    }
+}
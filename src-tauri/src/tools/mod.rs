@@ -0,0 +1,274 @@
+mod calculator;
+mod read_file;
+mod shell;
+mod web_fetch;
+mod web_search;
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use futures_util::future::BoxFuture;
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{Emitter, Manager, State};
+use tokio::sync::oneshot;
+
+use crate::error::AppError;
+use crate::llm::{ToolCall, ToolResult, ToolSpec};
+use crate::mcp;
+use crate::settings::{self, Settings};
+
+/// A tool built into the app. MCP servers supply the rest.
+pub trait Tool: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Tells the model what the tool does and when to use it.
+    fn description(&self) -> &'static str;
+
+    /// JSON Schema for the arguments.
+    fn parameters(&self) -> Value;
+
+    /// Ask the user before every call.
+    fn needs_confirmation(&self) -> bool {
+        false
+    }
+
+    /// Whether the current settings let the tool do anything useful.
+    fn available(&self, _settings: &Settings) -> bool {
+        true
+    }
+
+    /// Run the tool for the chat request `request_id`, returning text for the model.
+    fn call<'a>(
+        &'a self,
+        app: &'a tauri::AppHandle,
+        request_id: &'a str,
+        arguments: Value,
+    ) -> BoxFuture<'a, Result<String, String>>;
+}
+
+const BUILTIN: &[&dyn Tool] = &[
+    &calculator::Calculator,
+    &web_fetch::WebFetch,
+    &web_search::WebSearch,
+    &read_file::ReadFile,
+    &shell::Shell,
+];
+
+/// Tool calls waiting for the user to allow or deny them, by call id.
+#[derive(Default)]
+pub struct ToolPermissions(Mutex<HashMap<String, PendingPermission>>);
+
+struct PendingPermission {
+    /// The MCP server the tool belongs to; `None` for built-in tools.
+    server: Option<String>,
+    tool: String,
+    decision: oneshot::Sender<bool>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CallStatus {
+    Started,
+    Finished,
+    Failed,
+    Denied,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ToolCallEvent<'a> {
+    request_id: &'a str,
+    call_id: &'a str,
+    server: Option<&'a str>,
+    tool: &'a str,
+    status: CallStatus,
+    /// The tool's result once it has finished or failed.
+    output: Option<&'a str>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PermissionRequest<'a> {
+    request_id: &'a str,
+    call_id: &'a str,
+    server: Option<&'a str>,
+    tool: &'a str,
+    arguments: &'a Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuiltinTool {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub enabled: bool,
+    pub available: bool,
+    pub needs_confirmation: bool,
+}
+
+/// Emit a `tool-call` event for one step of a call.
+pub(crate) fn emit_call(
+    app: &tauri::AppHandle,
+    request_id: &str,
+    call: &ToolCall,
+    (server, tool): (Option<&str>, &str),
+    status: CallStatus,
+    output: Option<&str>,
+) {
+    let _ = app.emit(
+        "tool-call",
+        ToolCallEvent {
+            request_id,
+            call_id: &call.id,
+            server,
+            tool,
+            status,
+            output,
+        },
+    );
+}
+
+/// Emit `tool-permission-request` and wait for `respond_tool_permission`.
+pub(crate) async fn ask_permission(
+    app: &tauri::AppHandle,
+    request_id: &str,
+    call: &ToolCall,
+    (server, tool): (Option<&str>, &str),
+) -> bool {
+    let (decision, decided) = oneshot::channel();
+    app.state::<ToolPermissions>().0.lock().unwrap().insert(
+        call.id.clone(),
+        PendingPermission {
+            server: server.map(str::to_string),
+            tool: tool.to_string(),
+            decision,
+        },
+    );
+    let _ = app.emit(
+        "tool-permission-request",
+        PermissionRequest {
+            request_id,
+            call_id: &call.id,
+            server,
+            tool,
+            arguments: &call.arguments,
+        },
+    );
+    decided.await.unwrap_or(false)
+}
+
+pub(crate) fn declined(call: &ToolCall) -> ToolResult {
+    ToolResult {
+        call_id: call.id.clone(),
+        content: "The user declined to run this tool.".to_string(),
+        is_error: true,
+    }
+}
+
+fn enabled(settings: &Settings) -> impl Iterator<Item = &'static dyn Tool> + '_ {
+    BUILTIN.iter().copied().filter(|tool| {
+        settings
+            .tools
+            .enabled
+            .iter()
+            .any(|name| name == tool.name())
+            && tool.available(settings)
+    })
+}
+
+/// Every tool the model may call in a chat: enabled built-ins, then MCP tools.
+pub fn chat_tools(app: &tauri::AppHandle) -> Vec<ToolSpec> {
+    let settings = settings::current(app);
+    let builtin = enabled(&settings).map(|tool| ToolSpec {
+        name: tool.name().to_string(),
+        description: tool.description().to_string(),
+        parameters: tool.parameters(),
+    });
+    builtin.chain(mcp::chat_tools(app)).collect()
+}
+
+/// Run a tool call from the model and turn the outcome into a result for the next round.
+pub async fn run(app: &tauri::AppHandle, request_id: &str, call: &ToolCall) -> ToolResult {
+    let settings = settings::current(app);
+    let Some(tool) = enabled(&settings).find(|tool| tool.name() == call.name) else {
+        return mcp::call_tool(app, request_id, call).await;
+    };
+    let source = (None, tool.name());
+    if tool.needs_confirmation() && !ask_permission(app, request_id, call, source).await {
+        emit_call(app, request_id, call, source, CallStatus::Denied, None);
+        return declined(call);
+    }
+    emit_call(app, request_id, call, source, CallStatus::Started, None);
+    let (content, is_error) = match tool.call(app, request_id, call.arguments.clone()).await {
+        Ok(content) => (content, false),
+        Err(error) => (error, true),
+    };
+    let status = if is_error {
+        CallStatus::Failed
+    } else {
+        CallStatus::Finished
+    };
+    emit_call(app, request_id, call, source, status, Some(&content));
+    ToolResult {
+        call_id: call.id.clone(),
+        content,
+        is_error,
+    }
+}
+
+/// The string argument `key`, or an error the model can act on.
+fn string_argument<'a>(arguments: &'a Value, key: &str) -> Result<&'a str, String> {
+    arguments
+        .get(key)
+        .and_then(Value::as_str)
+        .ok_or_else(|| format!("Missing string argument {key:?}"))
+}
+
+/// Cut `text` down to `max_chars`, saying so, so one tool result can't fill the context.
+fn truncate(mut text: String, max_chars: usize) -> String {
+    if let Some((cut, _)) = text.char_indices().nth(max_chars) {
+        text.truncate(cut);
+        text.push_str("\n[truncated]");
+    }
+    text
+}
+
+#[tauri::command]
+pub fn list_builtin_tools(app: tauri::AppHandle) -> Vec<BuiltinTool> {
+    let settings = settings::current(&app);
+    BUILTIN
+        .iter()
+        .map(|tool| BuiltinTool {
+            name: tool.name(),
+            description: tool.description(),
+            enabled: settings
+                .tools
+                .enabled
+                .iter()
+                .any(|name| name == tool.name()),
+            available: tool.available(&settings),
+            needs_confirmation: tool.needs_confirmation(),
+        })
+        .collect()
+}
+
+/// Answer a `tool-permission-request`. With `always`, an allowed MCP tool is added to its
+/// server's trusted tools; built-in tools that ask always ask. Returns `false` if the
+/// call is no longer waiting.
+#[tauri::command]
+pub fn respond_tool_permission(
+    app: tauri::AppHandle,
+    state: State<'_, ToolPermissions>,
+    call_id: String,
+    allow: bool,
+    always: Option<bool>,
+) -> Result<bool, AppError> {
+    let Some(pending) = state.0.lock().unwrap().remove(&call_id) else {
+        return Ok(false);
+    };
+    if let (true, Some(true), Some(server)) = (allow, always, &pending.server) {
+        mcp::trust(&app, server, &pending.tool)?;
+    }
+    Ok(pending.decision.send(allow).is_ok())
+}
@@ -0,0 +1,109 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use futures_util::future::BoxFuture;
+use serde_json::{json, Value};
+
+use super::{string_argument, truncate, Tool};
+use crate::capabilities;
+use crate::documents::{self, DocumentFormat};
+use crate::settings::{self, Settings};
+
+const MAX_CHARS: usize = 50_000;
+/// Plain files bigger than this are refused; documents have their own limit.
+const MAX_TEXT_BYTES: u64 = 5 * 1024 * 1024;
+
+/// Reads files, but only inside the folders the user allowed in settings.
+pub struct ReadFile;
+
+/// `path` resolved through symlinks and `..`, if it lies inside one of `allowed`.
+fn resolve(path: &Path, allowed: &[PathBuf]) -> Result<PathBuf, String> {
+    if !path.is_absolute() {
+        return Err("The path must be absolute".to_string());
+    }
+    let resolved = fs::canonicalize(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    let inside = allowed
+        .iter()
+        .any(|dir| fs::canonicalize(dir).is_ok_and(|dir| resolved.starts_with(dir)));
+    if !inside {
+        return Err(format!(
+            "{} is outside the folders the assistant may read",
+            path.display()
+        ));
+    }
+    Ok(resolved)
+}
+
+fn read(path: &Path) -> Result<String, String> {
+    if DocumentFormat::from_path(path).is_some() {
+        let document = documents::extract(path, &|_, _| {})?;
+        let parts: Vec<String> = document.chunks.into_iter().map(|c| c.text).collect();
+        return Ok(parts.join("\n\n"));
+    }
+    let metadata = fs::metadata(path).map_err(|e| e.to_string())?;
+    if metadata.is_dir() {
+        let mut names: Vec<String> = fs::read_dir(path)
+            .map_err(|e| e.to_string())?
+            .flatten()
+            .map(|entry| {
+                let mut name = entry.file_name().to_string_lossy().into_owned();
+                if entry.path().is_dir() {
+                    name.push('/');
+                }
+                name
+            })
+            .collect();
+        names.sort();
+        return Ok(names.join("\n"));
+    }
+    if metadata.len() > MAX_TEXT_BYTES {
+        return Err("The file is too large to read".to_string());
+    }
+    let bytes = fs::read(path).map_err(|e| e.to_string())?;
+    String::from_utf8(bytes).map_err(|_| "The file is not text".to_string())
+}
+
+impl Tool for ReadFile {
+    fn name(&self) -> &'static str {
+        "read_file"
+    }
+
+    fn description(&self) -> &'static str {
+        "Read a text file, PDF or Word document, or list a folder, by absolute path. Only \
+         paths inside the folders the user has shared are readable."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "path": { "type": "string", "description": "Absolute path of a file or folder" },
+            },
+            "required": ["path"],
+        })
+    }
+
+    fn available(&self, settings: &Settings) -> bool {
+        !settings.tools.allowed_dirs.is_empty()
+    }
+
+    fn call<'a>(
+        &'a self,
+        app: &'a tauri::AppHandle,
+        _request_id: &'a str,
+        arguments: Value,
+    ) -> BoxFuture<'a, Result<String, String>> {
+        Box::pin(async move {
+            let path = PathBuf::from(string_argument(&arguments, "path")?);
+            capabilities::require_path(app, &path, "Read a file for the assistant")
+                .await
+                .map_err(|e| e.to_string())?;
+            let allowed = settings::current(app).tools.allowed_dirs;
+            let text =
+                tauri::async_runtime::spawn_blocking(move || read(&resolve(&path, &allowed)?))
+                    .await
+                    .map_err(|e| e.to_string())??;
+            Ok(truncate(text, MAX_CHARS))
+        })
+    }
+}
@@ -0,0 +1,57 @@
+use std::path::Path;
+
+use futures_util::future::BoxFuture;
+use serde_json::{json, Value};
+
+use super::{string_argument, truncate, Tool};
+use crate::exec;
+
+const MAX_CHARS: usize = 20_000;
+
+/// Runs a shell command. `exec` asks the user to approve every single call.
+pub struct Shell;
+
+impl Tool for Shell {
+    fn name(&self) -> &'static str {
+        "shell"
+    }
+
+    fn description(&self) -> &'static str {
+        "Run a shell command on the user's computer and return its output and exit code. \
+         The user is asked to approve every command before it runs."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "command": { "type": "string" },
+                "cwd": { "type": "string", "description": "Working directory, absolute" },
+            },
+            "required": ["command"],
+        })
+    }
+
+    fn call<'a>(
+        &'a self,
+        app: &'a tauri::AppHandle,
+        request_id: &'a str,
+        arguments: Value,
+    ) -> BoxFuture<'a, Result<String, String>> {
+        Box::pin(async move {
+            let command = string_argument(&arguments, "command")?;
+            let cwd = arguments.get("cwd").and_then(Value::as_str).map(Path::new);
+            let output = exec::run_command(app, Some(request_id), command, cwd).await?;
+            let mut text = output.stdout;
+            if !output.stderr.trim().is_empty() {
+                text.push_str("\n[stderr]\n");
+                text.push_str(&output.stderr);
+            }
+            let code = output
+                .exit_code
+                .map_or("none".to_string(), |code| code.to_string());
+            text.push_str(&format!("\n[exit code {code}]"));
+            Ok(truncate(text, MAX_CHARS))
+        })
+    }
+}
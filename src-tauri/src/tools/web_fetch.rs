@@ -0,0 +1,47 @@
+use futures_util::future::BoxFuture;
+use serde_json::{json, Value};
+
+use super::{string_argument, truncate, Tool};
+use crate::readability;
+
+/// Roughly what fits comfortably in a prompt next to the conversation.
+const MAX_CHARS: usize = 20_000;
+
+/// Downloads a page and hands the model its text.
+pub struct WebFetch;
+
+impl Tool for WebFetch {
+    fn name(&self) -> &'static str {
+        "web_fetch"
+    }
+
+    fn description(&self) -> &'static str {
+        "Download a web page or text file over http(s) and return its text content."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "url": { "type": "string", "description": "An http or https URL" },
+            },
+            "required": ["url"],
+        })
+    }
+
+    fn call<'a>(
+        &'a self,
+        app: &'a tauri::AppHandle,
+        _request_id: &'a str,
+        arguments: Value,
+    ) -> BoxFuture<'a, Result<String, String>> {
+        Box::pin(async move {
+            let page = readability::fetch(app, string_argument(&arguments, "url")?).await?;
+            let text = match page.title {
+                Some(title) => format!("# {title}\n\n{}", page.text),
+                None => page.text,
+            };
+            Ok(truncate(text, MAX_CHARS))
+        })
+    }
+}
@@ -0,0 +1,70 @@
+use futures_util::future::BoxFuture;
+use serde_json::{json, Value};
+
+use super::{string_argument, truncate, Tool};
+use crate::search;
+use crate::settings::{SearchProvider, Settings};
+
+/// Top results whose pages are read as well as their snippets.
+const FETCHED_PAGES: usize = 3;
+const MAX_CHARS: usize = 30_000;
+
+/// Searches the web and reads the best results, so answers can cite live pages.
+pub struct WebSearch;
+
+impl Tool for WebSearch {
+    fn name(&self) -> &'static str {
+        "web_search"
+    }
+
+    fn description(&self) -> &'static str {
+        "Search the web. Returns the top results with their URLs and snippets, plus the \
+         text of the first few pages. Cite the URLs you use."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "query": { "type": "string" },
+            },
+            "required": ["query"],
+        })
+    }
+
+    /// Brave and Bing keys live in the keychain, so only SearxNG can be checked here.
+    fn available(&self, settings: &Settings) -> bool {
+        settings.search.provider != SearchProvider::Searxng || settings.search.searxng_url.is_some()
+    }
+
+    fn call<'a>(
+        &'a self,
+        app: &'a tauri::AppHandle,
+        _request_id: &'a str,
+        arguments: Value,
+    ) -> BoxFuture<'a, Result<String, String>> {
+        Box::pin(async move {
+            let query = string_argument(&arguments, "query")?;
+            let mut results = search::search(app, query, None).await?;
+            if results.is_empty() {
+                return Ok(format!("No results for {query:?}"));
+            }
+            search::fetch_contents(app, &mut results, FETCHED_PAGES).await;
+            let mut text = String::new();
+            for (i, result) in results.iter().enumerate() {
+                text.push_str(&format!(
+                    "[{}] {}\n{}\n{}\n",
+                    i + 1,
+                    result.title,
+                    result.url,
+                    result.snippet
+                ));
+                if let Some(content) = &result.content {
+                    text.push_str(&format!("\n{content}\n"));
+                }
+                text.push('\n');
+            }
+            Ok(truncate(text, MAX_CHARS))
+        })
+    }
+}
@@ -0,0 +1,160 @@
+use serde::Serialize;
+use tauri::menu::{CheckMenuItem, Menu, MenuEvent, MenuItem, PredefinedMenuItem};
+use tauri::tray::{MouseButton, MouseButtonState, TrayIcon, TrayIconBuilder, TrayIconEvent};
+use tauri::{Emitter, Manager, Wry};
+
+use crate::overlay;
+use crate::window_manager::{self, AppWindow};
+
+const TRAY_ID: &str = "main";
+
+/// Menu items whose state follows the overlay.
+pub struct TrayMenu {
+    show: CheckMenuItem<Wry>,
+    hide: MenuItem<Wry>,
+    click_through: CheckMenuItem<Wry>,
+    pin: CheckMenuItem<Wry>,
+}
+
+#[derive(Clone, Serialize)]
+struct TrayActionPayload {
+    action: &'static str,
+}
+
+pub fn init(app: &tauri::AppHandle) -> tauri::Result<()> {
+    let show = CheckMenuItem::with_id(app, "show", "Show Overlay", true, false, None::<&str>)?;
+    let hide = MenuItem::with_id(app, "hide", "Hide Overlay", false, None::<&str>)?;
+    let click_through = CheckMenuItem::with_id(
+        app,
+        "click-through",
+        "Click-Through",
+        true,
+        false,
+        None::<&str>,
+    )?;
+    let pin = CheckMenuItem::with_id(app, "pin", "Pin Overlay", true, false, None::<&str>)?;
+    let settings = MenuItem::with_id(app, "settings", "Settings…", true, None::<&str>)?;
+    let quit = MenuItem::with_id(app, "quit", "Quit Aikeya", true, None::<&str>)?;
+    let menu = Menu::with_items(
+        app,
+        &[
+            &show,
+            &hide,
+            &pin,
+            &click_through,
+            &PredefinedMenuItem::separator(app)?,
+            &settings,
+            &PredefinedMenuItem::separator(app)?,
+            &quit,
+        ],
+    )?;
+
+    let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+        .menu(&menu)
+        .tooltip("Aikeya")
+        .show_menu_on_left_click(false)
+        .on_menu_event(on_menu_event)
+        .on_tray_icon_event(on_tray_icon_event);
+    if let Some(icon) = app.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+    builder.build(app)?;
+
+    app.manage(TrayMenu {
+        show,
+        hide,
+        click_through,
+        pin,
+    });
+    Ok(())
+}
+
+/// Update the menu to match the overlay's current visibility.
+pub fn sync_overlay_state(app: &tauri::AppHandle, visible: bool) {
+    let Some(menu) = app.try_state::<TrayMenu>() else {
+        return;
+    };
+    let _ = menu.show.set_checked(visible);
+    let _ = menu.show.set_enabled(!visible);
+    let _ = menu.hide.set_enabled(visible);
+    if let Some(tray) = app.tray_by_id(TRAY_ID) {
+        let tooltip = if visible {
+            "Aikeya — overlay shown"
+        } else {
+            "Aikeya"
+        };
+        let _ = tray.set_tooltip(Some(tooltip));
+    }
+}
+
+pub fn sync_click_through(app: &tauri::AppHandle, enabled: bool) {
+    if let Some(menu) = app.try_state::<TrayMenu>() {
+        let _ = menu.click_through.set_checked(enabled);
+    }
+}
+
+pub fn sync_pinned(app: &tauri::AppHandle, pinned: bool) {
+    if let Some(menu) = app.try_state::<TrayMenu>() {
+        let _ = menu.pin.set_checked(pinned);
+    }
+}
+
+fn on_menu_event(app: &tauri::AppHandle, event: MenuEvent) {
+    let action = match event.id().as_ref() {
+        "show" => {
+            window_manager::spawn(app, |app| {
+                let _ = window_manager::show_overlay(app);
+            });
+            "show"
+        }
+        "hide" => {
+            let _ = window_manager::hide_overlay(app);
+            "hide"
+        }
+        "click-through" => {
+            window_manager::spawn(app, |app| {
+                let enabled = !overlay::click_through(app);
+                if overlay::set_click_through(app, enabled).is_err() {
+                    // Undo the check mark the menu toggled on its own.
+                    sync_click_through(app, !enabled);
+                }
+            });
+            "click-through"
+        }
+        "pin" => {
+            window_manager::spawn(app, |app| {
+                let pinned = !overlay::pinned(app);
+                if overlay::set_pinned(app, pinned).is_err() {
+                    sync_pinned(app, !pinned);
+                }
+            });
+            "pin"
+        }
+        "settings" => {
+            window_manager::spawn(app, |app| {
+                let _ = window_manager::show(app, AppWindow::Settings);
+            });
+            "settings"
+        }
+        "quit" => "quit",
+        _ => return,
+    };
+
+    let _ = app.emit("tray-action", TrayActionPayload { action });
+    if action == "quit" {
+        app.exit(0);
+    }
+}
+
+fn on_tray_icon_event(tray: &TrayIcon, event: TrayIconEvent) {
+    if let TrayIconEvent::Click {
+        button: MouseButton::Left,
+        button_state: MouseButtonState::Up,
+        ..
+    } = event
+    {
+        window_manager::spawn(tray.app_handle(), |app| {
+            let _ = window_manager::toggle_overlay(app);
+        });
+    }
+}
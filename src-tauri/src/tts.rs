@@ -0,0 +1,171 @@
+use std::io::Write;
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::{Emitter, Manager};
+
+use crate::error::AppError;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+struct Utterance {
+    id: String,
+    child: Arc<Mutex<Child>>,
+}
+
+/// The utterance currently being spoken. Speech goes through each platform's own
+/// synthesizer CLI, which keeps voices and settings identical to the rest of the system.
+#[derive(Default)]
+pub struct SpeechState(Mutex<Option<Utterance>>);
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SpeechFinishedPayload {
+    id: String,
+    interrupted: bool,
+}
+
+/// `rate` is a multiplier of the normal speaking rate.
+#[cfg(target_os = "macos")]
+fn synthesizer(voice: Option<&str>, rate: f32) -> Command {
+    let mut command = Command::new("say");
+    command.args(["-f", "-", "-r"]);
+    command.arg(((175.0 * rate).round() as i32).to_string());
+    if let Some(voice) = voice {
+        command.args(["-v", voice]);
+    }
+    command
+}
+
+#[cfg(windows)]
+fn synthesizer(voice: Option<&str>, rate: f32) -> Command {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+    // SAPI rates run from -10 to 10 around a default of 0.
+    let sapi_rate = (((rate - 1.0) * 10.0).round() as i32).clamp(-10, 10);
+    let select_voice = voice
+        .map(|v| format!("$s.SelectVoice('{}');", v.replace('\'', "''")))
+        .unwrap_or_default();
+    let script = format!(
+        "Add-Type -AssemblyName System.Speech; \
+         $s = New-Object System.Speech.Synthesis.SpeechSynthesizer; \
+         $s.Rate = {sapi_rate}; {select_voice} \
+         $s.Speak([Console]::In.ReadToEnd())"
+    );
+    let mut command = Command::new("powershell");
+    command
+        .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+        .creation_flags(CREATE_NO_WINDOW);
+    command
+}
+
+#[cfg(target_os = "linux")]
+fn synthesizer(voice: Option<&str>, rate: f32) -> Command {
+    // speech-dispatcher picks whatever engine the desktop is configured for.
+    let mut command = Command::new("spd-say");
+    command.args(["-w", "-e", "-r"]);
+    command.arg(
+        (((rate - 1.0) * 100.0).round() as i32)
+            .clamp(-100, 100)
+            .to_string(),
+    );
+    if let Some(voice) = voice {
+        command.args(["-y", voice]);
+    }
+    command
+}
+
+fn spawn(text: &str, voice: Option<&str>, rate: f32) -> Result<Child, String> {
+    let mut child = synthesizer(voice, rate)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound && cfg!(target_os = "linux") {
+                "Text-to-speech needs speech-dispatcher (spd-say) to be installed".to_string()
+            } else {
+                format!("Could not start text-to-speech: {e}")
+            }
+        })?;
+    // Text goes over stdin so nothing in it can be mistaken for an option.
+    let mut stdin = child
+        .stdin
+        .take()
+        .ok_or_else(|| "Could not write to text-to-speech".to_string())?;
+    stdin
+        .write_all(text.as_bytes())
+        .map_err(|e| e.to_string())?;
+    Ok(child)
+}
+
+fn finish(app: &tauri::AppHandle, id: &str, interrupted: bool) {
+    let _ = app.emit(
+        "speech-finished",
+        SpeechFinishedPayload {
+            id: id.to_string(),
+            interrupted,
+        },
+    );
+}
+
+/// Stop the current utterance, returning whether anything was playing.
+pub fn stop(app: &tauri::AppHandle) -> bool {
+    let Some(utterance) = app.state::<SpeechState>().0.lock().unwrap().take() else {
+        return false;
+    };
+    let _ = utterance.child.lock().unwrap().kill();
+    // spd-say only hands text to the speech-dispatcher daemon, which keeps talking.
+    #[cfg(target_os = "linux")]
+    let _ = Command::new("spd-say").arg("-C").status();
+    finish(app, &utterance.id, true);
+    true
+}
+
+/// Read `text` aloud, interrupting anything already playing. Returns an id that the
+/// `speech-finished` event will carry once playback ends or is stopped.
+#[tauri::command]
+pub fn speak(
+    app: tauri::AppHandle,
+    text: String,
+    voice: Option<String>,
+    rate: Option<f32>,
+) -> Result<String, AppError> {
+    stop(&app);
+    if text.trim().is_empty() {
+        return Err(AppError::InvalidInput("Nothing to speak".to_string()));
+    }
+    let rate = rate.unwrap_or(1.0).clamp(0.25, 4.0);
+    let child = Arc::new(Mutex::new(spawn(&text, voice.as_deref(), rate)?));
+    let id = uuid::Uuid::new_v4().to_string();
+    *app.state::<SpeechState>().0.lock().unwrap() = Some(Utterance {
+        id: id.clone(),
+        child: child.clone(),
+    });
+
+    let watcher = app.clone();
+    let watched_id = id.clone();
+    thread::spawn(move || {
+        while matches!(child.lock().unwrap().try_wait(), Ok(None)) {
+            thread::sleep(POLL_INTERVAL);
+        }
+        // `stop` already reported an interrupted utterance and cleared it.
+        let state = watcher.state::<SpeechState>();
+        let mut current = state.0.lock().unwrap();
+        if current.as_ref().is_some_and(|u| u.id == watched_id) {
+            *current = None;
+            drop(current);
+            finish(&watcher, &watched_id, false);
+        }
+    });
+    Ok(id)
+}
+
+#[tauri::command]
+pub fn stop_speaking(app: tauri::AppHandle) -> bool {
+    stop(&app)
+}
@@ -0,0 +1,289 @@
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, Manager, State, Url};
+use tauri_plugin_updater::{Update, UpdaterExt};
+
+use crate::db;
+use crate::error::AppError;
+use crate::persist;
+use crate::session::{self, SessionEnd};
+use crate::settings::{self, ProxyMode, UpdateChannel};
+
+const STATE_FILE: &str = "updates.json";
+const DEFAULT_REMIND_HOURS: u32 = 24;
+
+/// Release manifests per channel. Beta is published to a rolling `beta` release because
+/// GitHub's `latest` never points at a pre-release.
+const STABLE_ENDPOINT: &str =
+    "https://github.com/aikeyaorg/aikeya/releases/latest/download/latest.json";
+const BETA_ENDPOINT: &str =
+    "https://github.com/aikeyaorg/aikeya/releases/download/beta/latest.json";
+
+/// "Remind me later" and "skip this version", kept across launches.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct Deferral {
+    remind_after: Option<i64>,
+    /// The version `remind_after` holds back; a newer one is reported regardless. `None`
+    /// holds back every version, as when nothing was pending at the time.
+    snoozed_version: Option<String>,
+    skipped_version: Option<String>,
+}
+
+#[derive(Default)]
+enum Pending {
+    #[default]
+    None,
+    Available(Update),
+    Downloaded {
+        update: Update,
+        bytes: Vec<u8>,
+        /// Install when the app quits instead of right away.
+        on_exit: bool,
+    },
+}
+
+/// The update found by the last check, and its package once downloaded.
+#[derive(Default)]
+pub struct UpdaterState(Mutex<Pending>);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateInfo {
+    pub version: String,
+    pub current_version: String,
+    pub notes: Option<String>,
+    /// Release date, Unix milliseconds.
+    pub date: Option<i64>,
+}
+
+impl From<&Update> for UpdateInfo {
+    fn from(update: &Update) -> Self {
+        Self {
+            version: update.version.clone(),
+            current_version: update.current_version.clone(),
+            notes: update.body.clone(),
+            date: update.date.map(|date| date.unix_timestamp() * 1000),
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DownloadProgress {
+    downloaded: u64,
+    total: Option<u64>,
+}
+
+fn deferral_path(app: &tauri::AppHandle) -> Result<std::path::PathBuf, String> {
+    persist::data_file(app, STATE_FILE)
+}
+
+/// Builds without a signing key configured can't verify, so can't install, updates.
+fn ensure_configured(app: &tauri::AppHandle) -> Result<(), String> {
+    let pubkey = app
+        .config()
+        .plugins
+        .0
+        .get("updater")
+        .and_then(|updater| updater.get("pubkey"))
+        .and_then(|key| key.as_str())
+        .unwrap_or_default();
+    if pubkey.trim().is_empty() {
+        return Err("Updates aren't available for this build".to_string());
+    }
+    Ok(())
+}
+
+async fn find_update(app: &tauri::AppHandle) -> Result<Option<Update>, String> {
+    ensure_configured(app)?;
+    let settings = settings::current(app);
+    let endpoint = match settings.updates.channel {
+        UpdateChannel::Stable => STABLE_ENDPOINT,
+        UpdateChannel::Beta => BETA_ENDPOINT,
+    };
+    let mut builder = app
+        .updater_builder()
+        .endpoints(vec![Url::parse(endpoint).map_err(|e| e.to_string())?])
+        .map_err(|e| e.to_string())?;
+    builder = match settings.network.proxy_mode {
+        ProxyMode::System => builder,
+        ProxyMode::None => builder.no_proxy(),
+        ProxyMode::Manual => {
+            let url = settings.network.proxy_url.as_deref().unwrap_or_default();
+            builder.proxy(Url::parse(url).map_err(|e| format!("Invalid proxy URL: {e}"))?)
+        }
+    };
+    builder
+        .build()
+        .map_err(|e| e.to_string())?
+        .check()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Look for a newer release on the configured channel. Unless `force` is set, a version
+/// the user skipped or is waiting to be reminded about again is not reported.
+#[tauri::command]
+pub async fn check_for_updates(
+    app: tauri::AppHandle,
+    state: State<'_, UpdaterState>,
+    force: Option<bool>,
+) -> Result<Option<UpdateInfo>, AppError> {
+    let Some(update) = find_update(&app).await? else {
+        *state.0.lock().unwrap() = Pending::None;
+        return Ok(None);
+    };
+    let info = UpdateInfo::from(&update);
+    if !force.unwrap_or(false) {
+        let deferral: Deferral = persist::load_json(&deferral_path(&app)?);
+        let skipped = deferral.skipped_version.as_deref() == Some(info.version.as_str());
+        let snoozed = deferral.remind_after.is_some_and(|at| at > db::now_ms())
+            && deferral
+                .snoozed_version
+                .as_deref()
+                .map_or(true, |version| version == info.version);
+        if skipped || snoozed {
+            return Ok(None);
+        }
+    }
+    let mut pending = state.0.lock().unwrap();
+    // Keep a finished download of the same version rather than throwing it away.
+    let already_downloaded = matches!(&*pending,
+        Pending::Downloaded { update: downloaded, .. } if downloaded.version == update.version);
+    if !already_downloaded {
+        *pending = Pending::Available(update);
+    }
+    Ok(Some(info))
+}
+
+/// Download and verify the update found by `check_for_updates`, emitting
+/// `update-download-progress` as it arrives.
+#[tauri::command]
+pub async fn download_update(
+    app: tauri::AppHandle,
+    state: State<'_, UpdaterState>,
+) -> Result<UpdateInfo, AppError> {
+    let update = match &*state.0.lock().unwrap() {
+        Pending::None => {
+            return Err(AppError::NotFound(
+                "No update to download; check for updates first".to_string(),
+            ))
+        }
+        Pending::Available(update) => update.clone(),
+        Pending::Downloaded { update, .. } => return Ok(UpdateInfo::from(update)),
+    };
+    let mut downloaded = 0u64;
+    let bytes = update
+        .download(
+            |chunk, total| {
+                downloaded += chunk as u64;
+                let _ = app.emit(
+                    "update-download-progress",
+                    DownloadProgress { downloaded, total },
+                );
+            },
+            || {},
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+    let info = UpdateInfo::from(&update);
+    *state.0.lock().unwrap() = Pending::Downloaded {
+        update,
+        bytes,
+        on_exit: false,
+    };
+    Ok(info)
+}
+
+/// Install the downloaded update and relaunch into it.
+#[tauri::command]
+pub fn install_and_restart(
+    app: tauri::AppHandle,
+    state: State<'_, UpdaterState>,
+) -> Result<(), AppError> {
+    let Pending::Downloaded { update, bytes, .. } = std::mem::take(&mut *state.0.lock().unwrap())
+    else {
+        return Err(AppError::NotFound(
+            "No downloaded update to install".to_string(),
+        ));
+    };
+    update.install(bytes).map_err(|e| e.to_string())?;
+    session::end(&app, SessionEnd::Restart);
+    app.restart()
+}
+
+/// Install the downloaded update when the app next quits instead of now.
+#[tauri::command]
+pub fn defer_update(state: State<'_, UpdaterState>) -> Result<(), AppError> {
+    match &mut *state.0.lock().unwrap() {
+        Pending::Downloaded { on_exit, .. } => {
+            *on_exit = true;
+            Ok(())
+        }
+        _ => Err(AppError::NotFound(
+            "No downloaded update to install".to_string(),
+        )),
+    }
+}
+
+/// Stop reporting the available update for `hours`, or for good with `skip_version`.
+/// Either way a newer release is still reported.
+#[tauri::command]
+pub fn remind_me_later(
+    app: tauri::AppHandle,
+    state: State<'_, UpdaterState>,
+    hours: Option<u32>,
+    skip_version: Option<String>,
+) -> Result<(), AppError> {
+    let path = deferral_path(&app)?;
+    let mut deferral: Deferral = persist::load_json(&path);
+    if skip_version.is_some() {
+        deferral.skipped_version = skip_version;
+    } else {
+        let hours = i64::from(hours.unwrap_or(DEFAULT_REMIND_HOURS));
+        deferral.remind_after = Some(db::now_ms() + hours * 60 * 60 * 1000);
+        deferral.snoozed_version = match &*state.0.lock().unwrap() {
+            Pending::Available(update) | Pending::Downloaded { update, .. } => {
+                Some(update.version.clone())
+            }
+            Pending::None => None,
+        };
+    }
+    persist::save_json(&path, &deferral).map_err(AppError::from)
+}
+
+/// Install an update deferred with `defer_update`. Called as the app exits.
+pub fn install_deferred(app: &tauri::AppHandle) {
+    let state = app.state::<UpdaterState>();
+    let pending = std::mem::take(&mut *state.0.lock().unwrap());
+    if let Pending::Downloaded {
+        update,
+        bytes,
+        on_exit: true,
+    } = pending
+    {
+        if let Err(error) = update.install(bytes) {
+            tracing::error!("Failed to install the deferred update: {error}");
+        }
+    }
+}
+
+/// Check once in the background after launch if the user wants that.
+pub fn check_on_startup(app: &tauri::AppHandle) {
+    if !settings::current(app).updates.check_on_startup || ensure_configured(app).is_err() {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let state = app.state::<UpdaterState>();
+        match check_for_updates(app.clone(), state, None).await {
+            Ok(Some(info)) => {
+                let _ = app.emit("update-available", info);
+            }
+            Ok(None) => {}
+            Err(error) => tracing::warn!("Update check failed: {error}"),
+        }
+    });
+}
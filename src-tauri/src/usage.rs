@@ -0,0 +1,268 @@
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::{Manager, State};
+
+use crate::db::{self, Database};
+use crate::error::AppError;
+use crate::llm::ChatMessage;
+use crate::tokens;
+
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
+/// List prices in USD per million input and output tokens, by model-name prefix. More
+/// specific prefixes come first.
+#[rustfmt::skip]
+const PRICES: &[(&str, f64, f64)] = &[
+    ("gpt-5-nano", 0.05, 0.4), ("gpt-5-mini", 0.25, 2.0), ("gpt-5", 1.25, 10.0),
+    ("gpt-4.1-nano", 0.1, 0.4), ("gpt-4.1-mini", 0.4, 1.6), ("gpt-4.1", 2.0, 8.0),
+    ("gpt-4o-mini", 0.15, 0.6), ("gpt-4o", 2.5, 10.0), ("gpt-4-turbo", 10.0, 30.0),
+    ("gpt-3.5-turbo", 0.5, 1.5), ("o4-mini", 1.1, 4.4), ("o3-mini", 1.1, 4.4), ("o3", 2.0, 8.0),
+    ("o1-mini", 1.1, 4.4), ("o1", 15.0, 60.0),
+    ("claude-opus-4", 15.0, 75.0), ("claude-sonnet-4", 3.0, 15.0), ("claude-haiku-4", 1.0, 5.0),
+    ("claude-3-7-sonnet", 3.0, 15.0), ("claude-3-5-sonnet", 3.0, 15.0),
+    ("claude-3-5-haiku", 0.8, 4.0), ("claude-3-opus", 15.0, 75.0), ("claude-3-haiku", 0.25, 1.25),
+    ("gemini-2.5-pro", 1.25, 10.0), ("gemini-2.5-flash-lite", 0.1, 0.4),
+    ("gemini-2.5-flash", 0.3, 2.5), ("gemini-2.0-flash", 0.1, 0.4),
+    ("gemini-1.5-pro", 1.25, 5.0), ("gemini-1.5-flash", 0.075, 0.3),
+    ("deepseek-chat", 0.27, 1.1), ("deepseek-reasoner", 0.55, 2.19),
+    ("grok-4", 3.0, 15.0), ("grok-3-mini", 0.3, 0.5), ("grok-3", 3.0, 15.0),
+    ("text-embedding-3-small", 0.02, 0.0), ("text-embedding-3-large", 0.13, 0.0),
+    ("text-embedding-ada-002", 0.1, 0.0),
+];
+
+/// Token counts a provider reported for a call. Either may be missing, in which case
+/// it is estimated.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokenUsage {
+    pub prompt_tokens: Option<u64>,
+    pub completion_tokens: Option<u64>,
+}
+
+impl TokenUsage {
+    /// Providers report usage across several stream events; later numbers win.
+    pub fn merge(&mut self, other: TokenUsage) {
+        self.prompt_tokens = other.prompt_tokens.or(self.prompt_tokens);
+        self.completion_tokens = other.completion_tokens.or(self.completion_tokens);
+    }
+}
+
+/// The cost of a call in USD, or `None` for models we have no price for. Providers that
+/// run without an API key are taken to be local and free.
+fn cost(model: &str, local: bool, prompt_tokens: u64, completion_tokens: u64) -> Option<f64> {
+    if local {
+        return Some(0.0);
+    }
+    let name = model.to_lowercase();
+    let name = name.rsplit('/').next().unwrap_or(&name);
+    let &(_, input, output) = PRICES
+        .iter()
+        .find(|(prefix, ..)| name.starts_with(prefix))?;
+    Some((prompt_tokens as f64 * input + completion_tokens as f64 * output) / 1_000_000.0)
+}
+
+struct UsageRecord<'a> {
+    request_id: Option<&'a str>,
+    kind: &'a str,
+    provider: &'a str,
+    model: &'a str,
+    local: bool,
+    prompt: (u64, bool),
+    completion: (u64, bool),
+}
+
+fn insert(app: &tauri::AppHandle, record: UsageRecord) {
+    let Some(db) = app.try_state::<Database>() else {
+        return;
+    };
+    let (prompt_tokens, prompt_estimated) = record.prompt;
+    let (completion_tokens, completion_estimated) = record.completion;
+    let cost = cost(record.model, record.local, prompt_tokens, completion_tokens);
+    let inserted = db.with(|conn| {
+        conn.execute(
+            "INSERT INTO usage_events (request_id, kind, provider, model, prompt_tokens,
+                completion_tokens, cost, estimated, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                record.request_id,
+                record.kind,
+                record.provider,
+                record.model,
+                prompt_tokens as i64,
+                completion_tokens as i64,
+                cost,
+                prompt_estimated || completion_estimated,
+                db::now_ms()
+            ],
+        )
+    });
+    if let Err(error) = inserted {
+        tracing::error!("Failed to record usage: {error}");
+    }
+}
+
+/// Record a finished chat completion, estimating whatever the provider didn't report.
+pub fn record_chat(
+    app: &tauri::AppHandle,
+    request_id: &str,
+    (provider, local): (&str, bool),
+    model: &str,
+    messages: &[ChatMessage],
+    reply: &str,
+    reported: TokenUsage,
+) {
+    let prompt = match reported.prompt_tokens {
+        Some(tokens) => (tokens, false),
+        None => {
+            let text: Vec<&str> = messages.iter().map(|m| m.content.as_str()).collect();
+            (tokens::count(model, &text.join("\n")) as u64, true)
+        }
+    };
+    let completion = match reported.completion_tokens {
+        Some(tokens) => (tokens, false),
+        None => (tokens::count(model, reply) as u64, true),
+    };
+    insert(
+        app,
+        UsageRecord {
+            request_id: Some(request_id),
+            kind: "chat",
+            provider,
+            model,
+            local,
+            prompt,
+            completion,
+        },
+    );
+}
+
+/// Record one embeddings request.
+pub fn record_embedding(
+    app: &tauri::AppHandle,
+    (provider, local): (&str, bool),
+    model: &str,
+    inputs: &[String],
+    reported_tokens: Option<u64>,
+) {
+    let prompt = match reported_tokens {
+        Some(tokens) => (tokens, false),
+        None => (tokens::count(model, &inputs.join("\n")) as u64, true),
+    };
+    insert(
+        app,
+        UsageRecord {
+            request_id: None,
+            kind: "embedding",
+            provider,
+            model,
+            local,
+            prompt,
+            completion: (0, false),
+        },
+    );
+}
+
+/// How far back `get_usage_stats` looks.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UsageRange {
+    Day,
+    Week,
+    Month,
+    Year,
+    All,
+}
+
+impl UsageRange {
+    pub(crate) fn since(self) -> i64 {
+        let days = match self {
+            Self::Day => 1,
+            Self::Week => 7,
+            Self::Month => 30,
+            Self::Year => 365,
+            Self::All => return 0,
+        };
+        db::now_ms() - days * DAY_MS
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UsageGroup {
+    Day,
+    Model,
+    Provider,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageBucket {
+    /// A `YYYY-MM-DD` local date, model name or provider id, depending on the grouping.
+    pub key: String,
+    pub requests: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// USD, over the requests we have a price for.
+    pub cost: f64,
+    /// Requests to models with no known price.
+    pub unpriced_requests: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageStats {
+    pub total: UsageBucket,
+    pub buckets: Vec<UsageBucket>,
+}
+
+fn stats(conn: &Connection, since: i64, group_by: UsageGroup) -> rusqlite::Result<UsageStats> {
+    let key = match group_by {
+        UsageGroup::Day => "date(created_at / 1000, 'unixepoch', 'localtime')",
+        UsageGroup::Model => "model",
+        UsageGroup::Provider => "provider",
+    };
+    let order = match group_by {
+        UsageGroup::Day => "key",
+        _ => "SUM(prompt_tokens + completion_tokens) DESC",
+    };
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {key} AS key, COUNT(*), SUM(prompt_tokens), SUM(completion_tokens),
+            TOTAL(cost), SUM(cost IS NULL)
+         FROM usage_events WHERE created_at >= ?1
+         GROUP BY key ORDER BY {order}"
+    ))?;
+    let buckets = stmt
+        .query_map([since], |row| {
+            Ok(UsageBucket {
+                key: row.get(0)?,
+                requests: row.get::<_, i64>(1)? as u64,
+                prompt_tokens: row.get::<_, i64>(2)? as u64,
+                completion_tokens: row.get::<_, i64>(3)? as u64,
+                cost: row.get(4)?,
+                unpriced_requests: row.get::<_, i64>(5)? as u64,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let mut total = UsageBucket {
+        key: "total".to_string(),
+        ..UsageBucket::default()
+    };
+    for bucket in &buckets {
+        total.requests += bucket.requests;
+        total.prompt_tokens += bucket.prompt_tokens;
+        total.completion_tokens += bucket.completion_tokens;
+        total.cost += bucket.cost;
+        total.unpriced_requests += bucket.unpriced_requests;
+    }
+    Ok(UsageStats { total, buckets })
+}
+
+/// Token and cost totals for LLM calls within `range`, grouped by day, model or provider.
+#[tauri::command]
+pub async fn get_usage_stats(
+    db: State<'_, Database>,
+    range: UsageRange,
+    group_by: UsageGroup,
+) -> Result<UsageStats, AppError> {
+    db.with(|conn| stats(conn, range.since(), group_by))
+        .map_err(AppError::from)
+}
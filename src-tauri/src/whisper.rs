@@ -0,0 +1,288 @@
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use tauri::Emitter;
+
+use crate::error::AppError;
+use crate::jobs::{self, JobSpec, Progress};
+use crate::models::{self, ModelKind};
+use crate::{capabilities, settings};
+
+const MODEL_BASE_URL: &str = "https://huggingface.co/ggerganov/whisper.cpp/resolve/main";
+/// whisper.cpp expects 16 kHz mono.
+const SAMPLE_RATE: u32 = 16_000;
+
+/// The ggml models offered for download, with their approximate size.
+const MODELS: &[(&str, u64)] = &[
+    ("tiny", 75),
+    ("tiny.en", 75),
+    ("base", 142),
+    ("base.en", 142),
+    ("small", 466),
+    ("small.en", 466),
+    ("medium", 1_500),
+    ("large-v3-turbo", 1_600),
+];
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WhisperModel {
+    pub name: &'static str,
+    pub size_mb: u64,
+    pub downloaded: bool,
+}
+
+#[derive(Clone, Serialize)]
+struct DownloadProgressPayload<'a> {
+    model: &'a str,
+    downloaded: u64,
+    total: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TranscriptSegment {
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub text: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Transcript {
+    pub text: String,
+    pub segments: Vec<TranscriptSegment>,
+}
+
+fn model_path(app: &tauri::AppHandle, name: &str) -> Result<PathBuf, String> {
+    if !MODELS.iter().any(|(model, _)| *model == name) {
+        return Err(format!("Unknown Whisper model: {name}"));
+    }
+    Ok(models::dir(app, ModelKind::Whisper)?.join(format!("ggml-{name}.bin")))
+}
+
+/// Read a WAV file as 16 kHz mono, whatever its original format.
+fn load_audio(path: &Path) -> Result<Vec<f32>, String> {
+    let mut reader = hound::WavReader::open(path).map_err(|e| e.to_string())?;
+    let spec = reader.spec();
+    let samples: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => reader
+            .samples::<f32>()
+            .collect::<Result<_, _>>()
+            .map_err(|e| e.to_string())?,
+        hound::SampleFormat::Int => {
+            let scale = (1_i64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|s| s.map(|s| s as f32 / scale))
+                .collect::<Result<_, _>>()
+                .map_err(|e| e.to_string())?
+        }
+    };
+    let channels = spec.channels.max(1) as usize;
+    let mono: Vec<f32> = samples
+        .chunks(channels)
+        .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+        .collect();
+    Ok(resample(&mono, spec.sample_rate, SAMPLE_RATE))
+}
+
+/// Linear-interpolation resampling; plenty for speech going into Whisper.
+fn resample(samples: &[f32], from: u32, to: u32) -> Vec<f32> {
+    if from == to || samples.is_empty() {
+        return samples.to_vec();
+    }
+    let ratio = from as f64 / to as f64;
+    let len = (samples.len() as f64 / ratio) as usize;
+    (0..len)
+        .map(|i| {
+            let position = i as f64 * ratio;
+            let index = position as usize;
+            let next = samples.get(index + 1).copied().unwrap_or(samples[index]);
+            let fraction = (position - index as f64) as f32;
+            samples[index] + (next - samples[index]) * fraction
+        })
+        .collect()
+}
+
+#[cfg(feature = "whisper")]
+mod engine {
+    use std::path::{Path, PathBuf};
+    use std::sync::Mutex;
+
+    use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
+
+    use super::{Transcript, TranscriptSegment};
+
+    /// Loading a model takes seconds, so the last one used stays in memory.
+    static LOADED: Mutex<Option<(PathBuf, WhisperContext)>> = Mutex::new(None);
+
+    pub fn transcribe(
+        model: &Path,
+        samples: &[f32],
+        language: Option<&str>,
+    ) -> Result<Transcript, String> {
+        let mut loaded = LOADED.lock().unwrap();
+        if loaded.as_ref().map(|(path, _)| path.as_path()) != Some(model) {
+            let path = model.to_str().ok_or("Model path is not valid UTF-8")?;
+            let context =
+                WhisperContext::new_with_params(path, WhisperContextParameters::default())
+                    .map_err(|e| e.to_string())?;
+            *loaded = Some((model.to_path_buf(), context));
+        }
+        let (_, context) = loaded.as_ref().unwrap();
+        let mut state = context.create_state().map_err(|e| e.to_string())?;
+
+        let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+        params.set_language(Some(language.unwrap_or("auto")));
+        params.set_n_threads(
+            std::thread::available_parallelism()
+                .map(|n| n.get().min(8) as i32)
+                .unwrap_or(4),
+        );
+        params.set_print_progress(false);
+        params.set_print_realtime(false);
+        params.set_print_special(false);
+        params.set_print_timestamps(false);
+        state.full(params, samples).map_err(|e| e.to_string())?;
+
+        let mut segments = Vec::new();
+        for i in 0..state.full_n_segments().map_err(|e| e.to_string())? {
+            let text = state
+                .full_get_segment_text_lossy(i)
+                .map_err(|e| e.to_string())?;
+            // Timestamps come back in centiseconds.
+            segments.push(TranscriptSegment {
+                start_ms: state.full_get_segment_t0(i).map_err(|e| e.to_string())? * 10,
+                end_ms: state.full_get_segment_t1(i).map_err(|e| e.to_string())? * 10,
+                text: text.trim().to_string(),
+            });
+        }
+        let text = segments
+            .iter()
+            .map(|s| s.text.as_str())
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        Ok(Transcript { text, segments })
+    }
+}
+
+#[cfg(not(feature = "whisper"))]
+mod engine {
+    use std::path::Path;
+
+    use super::Transcript;
+
+    pub fn transcribe(
+        _model: &Path,
+        _samples: &[f32],
+        _language: Option<&str>,
+    ) -> Result<Transcript, String> {
+        Err(
+            "This build doesn't include local speech recognition (the `whisper` feature)"
+                .to_string(),
+        )
+    }
+}
+
+#[tauri::command]
+pub fn list_whisper_models(app: tauri::AppHandle) -> Result<Vec<WhisperModel>, AppError> {
+    MODELS
+        .iter()
+        .map(|&(name, size_mb)| {
+            Ok(WhisperModel {
+                name,
+                size_mb,
+                downloaded: model_path(&app, name)?.exists(),
+            })
+        })
+        .collect()
+}
+
+/// Download a ggml model as a job, emitting `whisper-download-progress` as it arrives.
+/// The file is written under a temporary name so an interrupted download is never
+/// mistaken for a model, and is picked up from there next time. Downloads have no
+/// timeout but can be stopped with `cancel_job(requestId)`.
+#[tauri::command]
+pub async fn download_whisper_model(
+    app: tauri::AppHandle,
+    name: String,
+    request_id: Option<String>,
+) -> Result<(), AppError> {
+    let task_app = app.clone();
+    let spec = JobSpec::DownloadWhisperModel { name: name.clone() };
+    jobs::run(&app, request_id, spec, move |progress| {
+        download(task_app, name, progress)
+    })
+    .await
+}
+
+pub(crate) async fn download(
+    app: tauri::AppHandle,
+    name: String,
+    progress: Progress,
+) -> Result<(), String> {
+    let path = model_path(&app, &name)?;
+    let file = format!("ggml-{name}.bin");
+    let url = format!("{MODEL_BASE_URL}/{file}");
+    let sha256 = models::fetch(&app, &url, &path, None, &progress, |downloaded, total| {
+        let _ = app.emit(
+            "whisper-download-progress",
+            DownloadProgressPayload {
+                model: &name,
+                downloaded,
+                total,
+            },
+        );
+    })
+    .await?;
+    models::remember(&app, ModelKind::Whisper, &file, &url, sha256)
+}
+
+#[tauri::command]
+pub fn delete_whisper_model(app: tauri::AppHandle, name: String) -> Result<(), AppError> {
+    model_path(&app, &name)?;
+    models::delete_model(app, ModelKind::Whisper, format!("ggml-{name}.bin")).map(drop)
+}
+
+pub(crate) async fn transcribe(
+    app: tauri::AppHandle,
+    path: PathBuf,
+    language: Option<String>,
+) -> Result<Transcript, AppError> {
+    let speech = settings::current(&app).speech;
+    let model = model_path(&app, &speech.whisper_model)?;
+    if !model.exists() {
+        return Err(AppError::NotFound(format!(
+            "Whisper model \"{}\" has not been downloaded",
+            speech.whisper_model
+        )));
+    }
+    let language = language.or(speech.language);
+    tauri::async_runtime::spawn_blocking(move || {
+        let samples = load_audio(&path)?;
+        engine::transcribe(&model, &samples, language.as_deref())
+    })
+    .await?
+    .map_err(AppError::from)
+}
+
+/// Transcribe a WAV file offline, as a job. `language` is an ISO 639-1 code; when omitted
+/// the speech settings decide, and Whisper detects the language if those don't either.
+#[tauri::command]
+pub async fn transcribe_audio(
+    app: tauri::AppHandle,
+    path: PathBuf,
+    language: Option<String>,
+) -> Result<Transcript, AppError> {
+    capabilities::require_path(&app, &path, "Transcribe audio").await?;
+    let task_app = app.clone();
+    let spec = JobSpec::Transcribe {
+        path: path.clone(),
+        language: language.clone(),
+    };
+    jobs::run(&app, None, spec, move |_| {
+        transcribe(task_app, path, language)
+    })
+    .await
+}
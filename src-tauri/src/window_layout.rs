@@ -0,0 +1,356 @@
+use serde::{Deserialize, Serialize};
+
+use crate::capabilities::{self, Capability};
+use crate::error::AppError;
+use crate::focus::{self, ForegroundWindow};
+use crate::screenshot::Region;
+
+/// Where `arrange_foreground_window` puts the window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WindowLayout {
+    LeftHalf,
+    RightHalf,
+    Maximize,
+    /// The next monitor to the right, wrapping around, at the same relative place and size.
+    NextMonitor,
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::ffi::c_void;
+
+    use windows::Win32::Foundation::{HWND, RECT};
+    use windows::Win32::Graphics::Dwm::{DwmGetWindowAttribute, DWMWA_EXTENDED_FRAME_BOUNDS};
+    use windows::Win32::UI::WindowsAndMessaging::{
+        GetWindowRect, IsZoomed, SetWindowPos, ShowWindow, SWP_NOACTIVATE, SWP_NOZORDER,
+        SW_MAXIMIZE, SW_RESTORE,
+    };
+
+    use super::ForegroundWindow;
+    use crate::screenshot::Region;
+
+    fn hwnd(window: ForegroundWindow) -> HWND {
+        HWND(window.raw() as *mut _)
+    }
+
+    fn region(rect: RECT) -> Region {
+        Region {
+            x: rect.left,
+            y: rect.top,
+            width: (rect.right - rect.left).max(0) as u32,
+            height: (rect.bottom - rect.top).max(0) as u32,
+        }
+    }
+
+    /// The window's rect and, inside it, the part that is actually drawn: Windows 10 and
+    /// later pad windows with invisible resize borders.
+    fn bounds(hwnd: HWND) -> Result<(RECT, RECT), String> {
+        let mut outer = RECT::default();
+        // SAFETY: both rects outlive the calls, and the size passed is that of a RECT.
+        unsafe {
+            GetWindowRect(hwnd, &mut outer).map_err(|e| e.to_string())?;
+            let mut visible = outer;
+            let _ = DwmGetWindowAttribute(
+                hwnd,
+                DWMWA_EXTENDED_FRAME_BOUNDS,
+                std::ptr::addr_of_mut!(visible).cast::<c_void>(),
+                std::mem::size_of::<RECT>() as u32,
+            );
+            Ok((outer, visible))
+        }
+    }
+
+    pub fn frame(window: ForegroundWindow) -> Result<(Region, bool), String> {
+        let hwnd = hwnd(window);
+        let (_, visible) = bounds(hwnd)?;
+        // SAFETY: a stale handle just makes this false.
+        let maximized = unsafe { IsZoomed(hwnd) }.as_bool();
+        Ok((region(visible), maximized))
+    }
+
+    pub fn set_frame(window: ForegroundWindow, frame: Region) -> Result<(), String> {
+        let hwnd = hwnd(window);
+        // SAFETY: a stale handle just makes these calls fail.
+        unsafe {
+            if IsZoomed(hwnd).as_bool() {
+                let _ = ShowWindow(hwnd, SW_RESTORE);
+            }
+            let (outer, visible) = bounds(hwnd)?;
+            let (left, top) = (visible.left - outer.left, visible.top - outer.top);
+            let (right, bottom) = (outer.right - visible.right, outer.bottom - visible.bottom);
+            SetWindowPos(
+                hwnd,
+                None,
+                frame.x - left,
+                frame.y - top,
+                frame.width as i32 + left + right,
+                frame.height as i32 + top + bottom,
+                SWP_NOZORDER | SWP_NOACTIVATE,
+            )
+            .map_err(|e| e.to_string())
+        }
+    }
+
+    pub fn maximize(window: ForegroundWindow, _area: Region) -> Result<(), String> {
+        // SAFETY: as above.
+        let _ = unsafe { ShowWindow(hwnd(window), SW_MAXIMIZE) };
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::ffi::c_void;
+
+    use accessibility_sys::{
+        kAXErrorSuccess, kAXFocusedWindowAttribute, kAXPositionAttribute, kAXSizeAttribute,
+        kAXValueTypeCGPoint, kAXValueTypeCGSize, AXUIElementCopyAttributeValue,
+        AXUIElementCreateApplication, AXUIElementRef, AXUIElementSetAttributeValue, AXValueCreate,
+        AXValueGetValue, AXValueRef,
+    };
+    use core_foundation::base::{CFType, CFTypeRef, TCFType};
+    use core_foundation::string::CFString;
+
+    use super::ForegroundWindow;
+    use crate::screenshot::Region;
+
+    /// `CGPoint` and `CGSize`, which share a layout.
+    #[repr(C)]
+    #[derive(Default)]
+    struct Pair(f64, f64);
+
+    unsafe fn copy_attribute(element: &CFType, name: &str) -> Option<CFType> {
+        let attribute = CFString::new(name);
+        let mut value: CFTypeRef = std::ptr::null();
+        let status = AXUIElementCopyAttributeValue(
+            element.as_CFTypeRef() as AXUIElementRef,
+            attribute.as_concrete_TypeRef(),
+            &mut value,
+        );
+        if status != kAXErrorSuccess || value.is_null() {
+            return None;
+        }
+        Some(CFType::wrap_under_create_rule(value))
+    }
+
+    /// The app's focused window. Needs the accessibility permission.
+    fn focused_window(window: ForegroundWindow) -> Result<CFType, String> {
+        // SAFETY: every returned reference is owned by a CFType wrapper that releases it.
+        unsafe {
+            let app = CFType::wrap_under_create_rule(AXUIElementCreateApplication(
+                window.raw() as i32
+            ) as CFTypeRef);
+            copy_attribute(&app, kAXFocusedWindowAttribute)
+                .ok_or_else(|| "Could not find the window in front".to_string())
+        }
+    }
+
+    fn get(window: &CFType, name: &str, kind: u32) -> Option<Pair> {
+        let mut pair = Pair::default();
+        // SAFETY: `pair` has the layout of the value type asked for.
+        unsafe {
+            let value = copy_attribute(window, name)?;
+            AXValueGetValue(
+                value.as_CFTypeRef() as AXValueRef,
+                kind,
+                std::ptr::addr_of_mut!(pair).cast::<c_void>(),
+            )
+            .then_some(pair)
+        }
+    }
+
+    fn set(window: &CFType, name: &str, kind: u32, pair: Pair) -> Result<(), String> {
+        // SAFETY: as above; the created value is released by its wrapper.
+        unsafe {
+            let value = CFType::wrap_under_create_rule(AXValueCreate(
+                kind,
+                std::ptr::addr_of!(pair).cast::<c_void>(),
+            ) as CFTypeRef);
+            let status = AXUIElementSetAttributeValue(
+                window.as_CFTypeRef() as AXUIElementRef,
+                CFString::new(name).as_concrete_TypeRef(),
+                value.as_CFTypeRef(),
+            );
+            if status == kAXErrorSuccess {
+                Ok(())
+            } else {
+                Err(format!("The window can't be moved or resized ({status})"))
+            }
+        }
+    }
+
+    /// In points, like the work areas it is compared with.
+    pub fn frame(window: ForegroundWindow) -> Result<(Region, bool), String> {
+        let window = focused_window(window)?;
+        let missing = || "Could not read where the window is".to_string();
+        let Pair(x, y) =
+            get(&window, kAXPositionAttribute, kAXValueTypeCGPoint).ok_or_else(missing)?;
+        let Pair(width, height) =
+            get(&window, kAXSizeAttribute, kAXValueTypeCGSize).ok_or_else(missing)?;
+        let frame = Region {
+            x: x as i32,
+            y: y as i32,
+            width: width as u32,
+            height: height as u32,
+        };
+        Ok((frame, false))
+    }
+
+    pub fn set_frame(window: ForegroundWindow, frame: Region) -> Result<(), String> {
+        let window = focused_window(window)?;
+        let size = || Pair(f64::from(frame.width), f64::from(frame.height));
+        // Sized before and after the move, since a window may not fit where it is going
+        // until it is smaller, nor grow past the edge of the screen it is leaving.
+        let _ = set(&window, kAXSizeAttribute, kAXValueTypeCGSize, size());
+        let position = Pair(f64::from(frame.x), f64::from(frame.y));
+        set(&window, kAXPositionAttribute, kAXValueTypeCGPoint, position)?;
+        set(&window, kAXSizeAttribute, kAXValueTypeCGSize, size())
+    }
+
+    /// Fill the screen without going full screen, as the green button does with Option held.
+    pub fn maximize(window: ForegroundWindow, area: Region) -> Result<(), String> {
+        set_frame(window, area)
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::ForegroundWindow;
+    use crate::screenshot::Region;
+    use crate::x11;
+
+    pub fn frame(window: ForegroundWindow) -> Result<(Region, bool), String> {
+        let (conn, root) = x11::connect_for_windows()?;
+        x11::window_frame(&conn, root, window.raw() as u32)
+    }
+
+    pub fn set_frame(window: ForegroundWindow, frame: Region) -> Result<(), String> {
+        let (conn, root) = x11::connect_for_windows()?;
+        let window = window.raw() as u32;
+        // Window managers ignore moves of a maximized window.
+        x11::set_maximized(&conn, root, window, false)?;
+        x11::move_resize(&conn, root, window, frame)
+    }
+
+    pub fn maximize(window: ForegroundWindow, _area: Region) -> Result<(), String> {
+        let (conn, root) = x11::connect_for_windows()?;
+        x11::set_maximized(&conn, root, window.raw() as u32, true)
+    }
+}
+
+/// Each monitor's work area, left to right, in the units the platform places windows in:
+/// points on macOS and pixels elsewhere.
+fn work_areas(app: &tauri::AppHandle) -> Result<Vec<Region>, String> {
+    let monitors = app.available_monitors().map_err(|e| e.to_string())?;
+    let mut areas: Vec<Region> = monitors
+        .iter()
+        .map(|monitor| {
+            let area = monitor.work_area();
+            let scale = if cfg!(target_os = "macos") {
+                monitor.scale_factor()
+            } else {
+                1.0
+            };
+            Region {
+                x: (f64::from(area.position.x) / scale).round() as i32,
+                y: (f64::from(area.position.y) / scale).round() as i32,
+                width: (f64::from(area.size.width) / scale).round() as u32,
+                height: (f64::from(area.size.height) / scale).round() as u32,
+            }
+        })
+        .collect();
+    areas.sort_by_key(|area| (area.x, area.y));
+    Ok(areas)
+}
+
+/// How much of `frame` is on `area`, in square units.
+fn overlap(frame: Region, area: Region) -> i64 {
+    let width =
+        (frame.x + frame.width as i32).min(area.x + area.width as i32) - frame.x.max(area.x);
+    let height =
+        (frame.y + frame.height as i32).min(area.y + area.height as i32) - frame.y.max(area.y);
+    i64::from(width.max(0)) * i64::from(height.max(0))
+}
+
+/// `frame` moved from `from` to the same relative place on `to`, shrunk to fit.
+fn carry(frame: Region, from: Region, to: Region) -> Region {
+    let width = frame.width.min(to.width);
+    let height = frame.height.min(to.height);
+    let relative = |offset: i32, size: u32| f64::from(offset) / f64::from(size.max(1));
+    let x = to.x + (relative(frame.x - from.x, from.width) * f64::from(to.width)) as i32;
+    let y = to.y + (relative(frame.y - from.y, from.height) * f64::from(to.height)) as i32;
+    Region {
+        x: x.clamp(to.x, to.x + (to.width - width) as i32),
+        y: y.clamp(to.y, to.y + (to.height - height) as i32),
+        width,
+        height,
+    }
+}
+
+fn arrange(
+    app: &tauri::AppHandle,
+    window: ForegroundWindow,
+    layout: WindowLayout,
+) -> Result<(), String> {
+    let (frame, maximized) = platform::frame(window)?;
+    let areas = work_areas(app)?;
+    let (index, area) = areas
+        .iter()
+        .copied()
+        .enumerate()
+        .max_by_key(|(_, area)| overlap(frame, *area))
+        .ok_or_else(|| "No monitors found".to_string())?;
+    let half = area.width / 2;
+    match layout {
+        WindowLayout::LeftHalf => platform::set_frame(
+            window,
+            Region {
+                width: half,
+                ..area
+            },
+        ),
+        WindowLayout::RightHalf => platform::set_frame(
+            window,
+            Region {
+                x: area.x + half as i32,
+                width: area.width - half,
+                ..area
+            },
+        ),
+        WindowLayout::Maximize => platform::maximize(window, area),
+        WindowLayout::NextMonitor => {
+            let next_index = (index + 1) % areas.len();
+            if next_index == index {
+                return Ok(());
+            }
+            let next = areas[next_index];
+            platform::set_frame(window, carry(frame, area, next))?;
+            if maximized {
+                platform::maximize(window, next)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Arrange the window in front, or the one the overlay was summoned over, for shortcuts
+/// and palette actions. Wayland doesn't let apps move other apps' windows.
+pub fn arrange_foreground(app: &tauri::AppHandle, layout: WindowLayout) -> Result<(), String> {
+    capabilities::check(app, Capability::Accessibility, "Window arrangement")?;
+    let window = focus::target_window(app).ok_or_else(|| "No window to arrange".to_string())?;
+    arrange(app, window, layout)
+}
+
+#[tauri::command]
+pub async fn arrange_foreground_window(
+    app: tauri::AppHandle,
+    layout: WindowLayout,
+) -> Result<(), AppError> {
+    capabilities::require(&app, Capability::Accessibility, "Window arrangement").await?;
+    let window = focus::target_window(&app)
+        .ok_or_else(|| AppError::NotFound("No window to arrange".into()))?;
+    tauri::async_runtime::spawn_blocking(move || arrange(&app, window, layout))
+        .await?
+        .map_err(AppError::from)
+}
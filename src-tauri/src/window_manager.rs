@@ -0,0 +1,416 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{Emitter, Manager, State, WebviewWindow, WebviewWindowBuilder, Window, WindowEvent};
+
+use crate::error::AppError;
+use crate::screenshot::Region;
+use crate::window_state::{self, WindowStateStore};
+use crate::{accessibility, backdrop, dock, focus, headless, layer_shell, overlay, panel, tray};
+
+/// The app's windows. Each is described in `tauri.conf.json`; the ones marked
+/// `"create": false` there are only built the first time they are needed, and any of
+/// them is built again if it was closed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AppWindow {
+    /// The main window, hosting the avatar.
+    Main,
+    /// The companion overlay summoned by the hotkey.
+    Overlay,
+    /// A one-line prompt bar.
+    QuickAsk,
+    /// The full chat panel.
+    Chat,
+    /// Settings on their own.
+    Settings,
+}
+
+impl AppWindow {
+    pub const ALL: [AppWindow; 5] = [
+        AppWindow::Main,
+        AppWindow::Overlay,
+        AppWindow::QuickAsk,
+        AppWindow::Chat,
+        AppWindow::Settings,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            AppWindow::Main => "main",
+            AppWindow::Overlay => "overlay",
+            AppWindow::QuickAsk => "quick-ask",
+            AppWindow::Chat => "chat",
+            AppWindow::Settings => "settings",
+        }
+    }
+
+    pub fn from_label(label: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|window| window.label() == label)
+    }
+}
+
+/// Events sent to windows that had only just been created, by label, until the window
+/// has loaded and asks for them with `take_window_messages`.
+#[derive(Default)]
+pub struct WindowMessages(Mutex<HashMap<String, Vec<WindowMessage>>>);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WindowMessage {
+    pub event: String,
+    pub payload: Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowInfo {
+    pub window: AppWindow,
+    pub label: &'static str,
+    /// Whether the window exists yet; the rest is `false` when it doesn't.
+    pub created: bool,
+    pub visible: bool,
+    pub focused: bool,
+}
+
+/// The window if it exists, without creating it.
+pub fn get(app: &tauri::AppHandle, window: AppWindow) -> Option<WebviewWindow> {
+    app.get_webview_window(window.label())
+}
+
+/// The window, built from its configuration first if it doesn't exist. New windows get
+/// their remembered geometry back and stay hidden until shown.
+pub fn get_or_create(app: &tauri::AppHandle, window: AppWindow) -> Result<WebviewWindow, AppError> {
+    if let Some(existing) = get(app, window) {
+        return Ok(existing);
+    }
+    headless::forbid_windows(window.label())?;
+    let config = app
+        .config()
+        .app
+        .windows
+        .iter()
+        .find(|config| config.label == window.label())
+        .ok_or_else(|| AppError::WindowMissing {
+            window: window.label().to_string(),
+            message: format!("No configuration for the {} window", window.label()),
+        })?;
+    let created = WebviewWindowBuilder::from_config(app, config)
+        .and_then(|builder| builder.visible(false).build())
+        .map_err(|e| AppError::WindowMissing {
+            window: window.label().to_string(),
+            message: format!("Could not create the {} window: {e}", window.label()),
+        })?;
+    tracing::info!("Created the {} window", window.label());
+    if window == AppWindow::Overlay {
+        prepare_overlay(app, &created)?;
+    }
+    if let Err(error) = window_state::restore(&created) {
+        tracing::warn!("Could not restore the {} window: {error}", window.label());
+    }
+    Ok(created)
+}
+
+/// Make sure a window exists, rebuilding it if it was closed, and with `reload` load
+/// its page again if it does, for a webview that stopped responding. Returns whether
+/// the window had to be rebuilt.
+pub fn ensure(app: &tauri::AppHandle, window: AppWindow, reload: bool) -> Result<bool, AppError> {
+    if let Some(existing) = get(app, window) {
+        if reload {
+            tracing::info!("Reloading the {} window", window.label());
+            existing.reload()?;
+        }
+        return Ok(false);
+    }
+    tracing::warn!("The {} window was missing; rebuilding it", window.label());
+    get_or_create(app, window)?;
+    Ok(true)
+}
+
+/// Run `task` on a worker thread. Tray, deep link and single-instance handlers are called
+/// on the main thread, where building a window deadlocks on Windows, so any of them that
+/// may create one hands the work over here.
+pub fn spawn(app: &tauri::AppHandle, task: impl FnOnce(&tauri::AppHandle) + Send + 'static) {
+    let app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || task(&app));
+}
+
+pub fn is_visible(app: &tauri::AppHandle, window: AppWindow) -> bool {
+    get(app, window)
+        .and_then(|window| window.is_visible().ok())
+        .unwrap_or(false)
+}
+
+pub fn is_focused(app: &tauri::AppHandle, window: AppWindow) -> bool {
+    get(app, window)
+        .and_then(|window| window.is_focused().ok())
+        .unwrap_or(false)
+}
+
+/// Show and focus a window, creating it if needed. The overlay goes through
+/// `show_overlay`, which also places it and remembers what had focus.
+pub fn show(app: &tauri::AppHandle, window: AppWindow) -> Result<(), AppError> {
+    if window == AppWindow::Overlay {
+        return show_overlay(app);
+    }
+    let webview = get_or_create(app, window)?;
+    webview.show()?;
+    webview.set_focus()?;
+    emit_visibility(app, window, true);
+    Ok(())
+}
+
+/// Hide a window. One that was never created has nothing to hide. An overlay that had
+/// the keyboard gives it back to the app it was summoned over, rather than leave it
+/// wherever the platform puts it.
+pub fn hide(app: &tauri::AppHandle, window: AppWindow) -> Result<(), AppError> {
+    let Some(webview) = get(app, window) else {
+        return Ok(());
+    };
+    let overlay = window == AppWindow::Overlay;
+    let return_focus = overlay && webview.is_focused().unwrap_or(false);
+    let was_visible = webview.is_visible().unwrap_or(false);
+    webview.hide()?;
+    app.state::<WindowStateStore>().flush()?;
+    emit_visibility(app, window, false);
+    if overlay && was_visible {
+        accessibility::announce(app, "Aikeya closed");
+    }
+    if return_focus {
+        if let Err(error) = focus::restore(app) {
+            tracing::debug!("Could not give focus back after hiding the overlay: {error}");
+        }
+    }
+    Ok(())
+}
+
+/// Flip a window's visibility, returning whether it is now shown.
+pub fn toggle(app: &tauri::AppHandle, window: AppWindow) -> Result<bool, AppError> {
+    if is_visible(app, window) {
+        hide(app, window)?;
+        Ok(false)
+    } else {
+        show(app, window)?;
+        Ok(true)
+    }
+}
+
+/// Emit `event` to one window. A window that doesn't exist yet is created, and since it
+/// can't be listening before it has loaded, the event waits in `WindowMessages` for it.
+pub fn emit_to<S: Serialize + Clone>(
+    app: &tauri::AppHandle,
+    window: AppWindow,
+    event: &str,
+    payload: S,
+) -> Result<(), AppError> {
+    if get(app, window).is_some() {
+        return app
+            .emit_to(window.label(), event, payload)
+            .map_err(AppError::from);
+    }
+    let payload = serde_json::to_value(payload).map_err(|e| e.to_string())?;
+    app.state::<WindowMessages>()
+        .0
+        .lock()
+        .unwrap()
+        .entry(window.label().to_string())
+        .or_default()
+        .push(WindowMessage {
+            event: event.to_string(),
+            payload,
+        });
+    get_or_create(app, window).map(drop)
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct VisibilityChanged {
+    window: AppWindow,
+    visible: bool,
+}
+
+/// Tell every window that one was shown or hidden. The overlay keeps its own event and
+/// tray state as well.
+fn emit_visibility(app: &tauri::AppHandle, window: AppWindow, visible: bool) {
+    if window == AppWindow::Overlay {
+        overlay::on_visibility(app, visible);
+        tray::sync_overlay_state(app, visible);
+        let _ = app.emit("overlay-visibility-changed", visible);
+    }
+    let _ = app.emit(
+        "window-visibility-changed",
+        VisibilityChanged { window, visible },
+    );
+}
+
+/// A closed window is gone, not just hidden, so say so; it is rebuilt when next needed.
+pub fn on_window_event(window: &Window, event: &WindowEvent) {
+    if let (WindowEvent::Destroyed, Some(app_window)) =
+        (event, AppWindow::from_label(window.label()))
+    {
+        emit_visibility(window.app_handle(), app_window, false);
+    }
+}
+
+pub fn show_overlay(app: &tauri::AppHandle) -> Result<(), AppError> {
+    show_overlay_at(app, None)
+}
+
+/// Show the overlay next to `anchor` if given, otherwise where the positioning setting
+/// puts it (only when it wasn't already visible).
+pub fn show_overlay_at(app: &tauri::AppHandle, anchor: Option<Region>) -> Result<(), AppError> {
+    present_overlay(app, Placement::from(anchor), true)
+}
+
+/// Like `show_overlay_at`, but leaves the app the user is in frontmost where the
+/// platform allows it, for summoning the overlay on top of their work.
+pub fn show_overlay_nonactivating(
+    app: &tauri::AppHandle,
+    anchor: Option<Region>,
+) -> Result<(), AppError> {
+    present_overlay(app, Placement::from(anchor), false)
+}
+
+/// Show the overlay wherever it was moved to, without activating the app.
+pub fn show_overlay_in_place(app: &tauri::AppHandle) -> Result<(), AppError> {
+    present_overlay(app, Placement::Keep, false)
+}
+
+/// Turn a freshly built overlay into the native kind of floating window each platform
+/// has, with its backdrop and the click-through and pinned modes the last one had.
+pub fn prepare_overlay(app: &tauri::AppHandle, overlay: &WebviewWindow) -> Result<(), AppError> {
+    panel::make_panel(overlay)?;
+    layer_shell::init(overlay)?;
+    overlay::apply_modes(app, overlay)?;
+    accessibility::apply_reduced_motion(app, overlay);
+    if let Err(error) = backdrop::apply(app, overlay) {
+        tracing::warn!("Could not set the overlay backdrop: {error}");
+    }
+    Ok(())
+}
+
+/// Where `present_overlay` moves the overlay before showing it.
+enum Placement {
+    /// Where the positioning setting says, unless it is already up.
+    Setting,
+    Near(Region),
+    Keep,
+}
+
+impl From<Option<Region>> for Placement {
+    fn from(anchor: Option<Region>) -> Self {
+        anchor.map_or(Placement::Setting, Placement::Near)
+    }
+}
+
+fn present_overlay(
+    app: &tauri::AppHandle,
+    placement: Placement,
+    activate: bool,
+) -> Result<(), AppError> {
+    let window = get_or_create(app, AppWindow::Overlay)?;
+    overlay::cancel_auto_hide(app);
+    let visible = window.is_visible()?;
+    if !visible {
+        focus::remember(app);
+    }
+    // A docked overlay stays on its edge.
+    let placement = if dock::is_docked(app) {
+        Placement::Keep
+    } else {
+        placement
+    };
+    match placement {
+        Placement::Near(anchor) => overlay::place_near(&window, anchor)?,
+        Placement::Setting if !visible => overlay::place(&window)?,
+        Placement::Setting | Placement::Keep => {}
+    }
+    if activate {
+        window.show()?;
+        window.set_focus()?;
+    } else {
+        panel::show_without_activating(&window)?;
+    }
+    emit_visibility(app, AppWindow::Overlay, true);
+    if !visible {
+        accessibility::announce(app, "Aikeya open");
+    }
+    Ok(())
+}
+
+pub fn hide_overlay(app: &tauri::AppHandle) -> Result<(), AppError> {
+    hide(app, AppWindow::Overlay)
+}
+
+pub fn toggle_overlay(app: &tauri::AppHandle) -> Result<bool, AppError> {
+    toggle(app, AppWindow::Overlay)
+}
+
+// Async so that creating a window never runs on the main thread, which deadlocks on
+// Windows.
+#[tauri::command]
+pub async fn show_window(app: tauri::AppHandle, window: AppWindow) -> Result<(), AppError> {
+    show(&app, window)
+}
+
+#[tauri::command]
+pub async fn hide_window(app: tauri::AppHandle, window: AppWindow) -> Result<(), AppError> {
+    hide(&app, window)
+}
+
+#[tauri::command]
+pub async fn toggle_window(app: tauri::AppHandle, window: AppWindow) -> Result<bool, AppError> {
+    toggle(&app, window)
+}
+
+#[tauri::command]
+pub fn list_windows(app: tauri::AppHandle) -> Vec<WindowInfo> {
+    AppWindow::ALL
+        .into_iter()
+        .map(|window| {
+            let webview = get(&app, window);
+            let state = |read: fn(&WebviewWindow) -> tauri::Result<bool>| {
+                webview.as_ref().and_then(|w| read(w).ok()).unwrap_or(false)
+            };
+            WindowInfo {
+                window,
+                label: window.label(),
+                created: webview.is_some(),
+                visible: state(WebviewWindow::is_visible),
+                focused: state(WebviewWindow::is_focused),
+            }
+        })
+        .collect()
+}
+
+/// Events sent to the calling window before it was ready, oldest first.
+#[tauri::command]
+pub fn take_window_messages(
+    window: WebviewWindow,
+    messages: State<'_, WindowMessages>,
+) -> Vec<WindowMessage> {
+    messages
+        .0
+        .lock()
+        .unwrap()
+        .remove(window.label())
+        .unwrap_or_default()
+}
+
+/// Hand a message from one window to another, e.g. to continue a quick question in the
+/// chat panel. The target is created if needed; with `show` it is brought up as well.
+#[tauri::command]
+pub async fn send_to_window(
+    app: tauri::AppHandle,
+    window: AppWindow,
+    event: String,
+    payload: Value,
+    show: Option<bool>,
+) -> Result<(), AppError> {
+    emit_to(&app, window, &event, payload)?;
+    if show.unwrap_or(false) {
+        self::show(&app, window)?;
+    }
+    Ok(())
+}
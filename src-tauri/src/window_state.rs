@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tauri::{Manager, PhysicalPosition, PhysicalSize, WebviewWindow, Window, WindowEvent};
+
+use crate::persist;
+
+const STATE_FILE: &str = "window-state.json";
+
+/// Windows whose geometry is remembered between launches.
+const TRACKED_WINDOWS: &[&str] = &["overlay", "quick-ask", "chat", "settings"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WindowGeometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub monitor: Option<String>,
+}
+
+pub struct WindowStateStore {
+    path: PathBuf,
+    windows: Mutex<HashMap<String, WindowGeometry>>,
+}
+
+impl WindowStateStore {
+    pub fn load(app: &tauri::AppHandle) -> Result<Self, String> {
+        let path = persist::data_file(app, STATE_FILE)?;
+        let windows = persist::load_json(&path);
+        Ok(Self {
+            path,
+            windows: Mutex::new(windows),
+        })
+    }
+
+    fn get(&self, label: &str) -> Option<WindowGeometry> {
+        self.windows.lock().unwrap().get(label).cloned()
+    }
+
+    fn set(&self, label: &str, geometry: WindowGeometry) {
+        self.windows
+            .lock()
+            .unwrap()
+            .insert(label.to_string(), geometry);
+    }
+
+    pub fn flush(&self) -> Result<(), String> {
+        let windows = self.windows.lock().unwrap();
+        persist::save_json(&self.path, &*windows)
+    }
+}
+
+/// Apply the saved geometry to a window, skipping it if the monitor it was on is gone.
+pub fn restore(window: &WebviewWindow) -> Result<(), String> {
+    let store = window.state::<WindowStateStore>();
+    let Some(geometry) = store.get(window.label()) else {
+        return Ok(());
+    };
+
+    let monitors = window.available_monitors().map_err(|e| e.to_string())?;
+    let on_screen = monitors.iter().any(|monitor| {
+        let pos = monitor.position();
+        let size = monitor.size();
+        geometry.x >= pos.x
+            && geometry.y >= pos.y
+            && geometry.x < pos.x + size.width as i32
+            && geometry.y < pos.y + size.height as i32
+    });
+    if !on_screen {
+        return Ok(());
+    }
+
+    window
+        .set_size(PhysicalSize::new(geometry.width, geometry.height))
+        .map_err(|e| e.to_string())?;
+    window
+        .set_position(PhysicalPosition::new(geometry.x, geometry.y))
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Restore every tracked window that exists at startup.
+pub fn restore_all(app: &tauri::AppHandle) {
+    for label in TRACKED_WINDOWS {
+        if let Some(window) = app.get_webview_window(label) {
+            let _ = restore(&window);
+        }
+    }
+}
+
+fn capture(window: &Window) -> Option<WindowGeometry> {
+    let position = window.outer_position().ok()?;
+    let size = window.inner_size().ok()?;
+    let monitor = window
+        .current_monitor()
+        .ok()
+        .flatten()
+        .and_then(|m| m.name().cloned());
+    Some(WindowGeometry {
+        x: position.x,
+        y: position.y,
+        width: size.width,
+        height: size.height,
+        monitor,
+    })
+}
+
+/// Track moves and resizes in memory, writing to disk once the interaction is over.
+pub fn on_window_event(window: &Window, event: &WindowEvent) {
+    if !TRACKED_WINDOWS.contains(&window.label()) {
+        return;
+    }
+    let store = window.state::<WindowStateStore>();
+    match event {
+        WindowEvent::Moved(_) | WindowEvent::Resized(_) => {
+            // Minimized windows report bogus coordinates on Windows; don't remember those.
+            if window.is_minimized().unwrap_or(false) {
+                return;
+            }
+            if let Some(geometry) = capture(window) {
+                store.set(window.label(), geometry);
+            }
+        }
+        WindowEvent::Focused(false) | WindowEvent::CloseRequested { .. } => {
+            let _ = store.flush();
+        }
+        _ => {}
+    }
+}
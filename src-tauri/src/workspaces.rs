@@ -0,0 +1,219 @@
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, Manager, State};
+
+use crate::error::AppError;
+use crate::imaging::ImageSource;
+use crate::llm::ChatMessage;
+use crate::session::{self, SessionEnd};
+use crate::{db, persist};
+
+const WORKSPACES_FILE: &str = "workspaces.json";
+/// Where every workspace but the default one keeps its data, a folder per id.
+const WORKSPACES_DIR: &str = "workspaces";
+/// The workspace whose data is where it was before there were workspaces.
+pub const DEFAULT_WORKSPACE: &str = "default";
+/// Files and folders every workspace shares, because they belong to the app or the
+/// machine rather than to what the user is working on.
+const SHARED: [&str; 8] = [
+    WORKSPACES_FILE,
+    "updates.json",
+    "capabilities.json",
+    "crashes",
+    "models.json",
+    "models",
+    "plugins.json",
+    "plugins",
+];
+
+/// Fixed at startup: switching workspaces relaunches the app, so nothing loaded from
+/// one is ever still in memory in another.
+static ACTIVE: OnceLock<String> = OnceLock::new();
+
+/// A separate set of settings, API keys, history and templates, such as "Work" and
+/// "Personal".
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Workspace {
+    pub id: String,
+    pub name: String,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Workspaces {
+    /// The id of the workspace the app is running in.
+    pub active: String,
+    /// The default workspace first, then the rest in the order they were created.
+    pub workspaces: Vec<Workspace>,
+}
+
+pub struct WorkspaceStore {
+    path: PathBuf,
+    workspaces: Mutex<Workspaces>,
+}
+
+impl WorkspaceStore {
+    /// Load the workspaces and settle which one this run uses. Must come before any
+    /// other store is loaded, since they read from its folders.
+    pub fn load(app: &tauri::AppHandle) -> Result<Self, String> {
+        let path = persist::data_file(app, WORKSPACES_FILE)?;
+        let mut workspaces: Workspaces = persist::load_json(&path);
+        if !workspaces
+            .workspaces
+            .iter()
+            .any(|w| w.id == DEFAULT_WORKSPACE)
+        {
+            workspaces.workspaces.insert(
+                0,
+                Workspace {
+                    id: DEFAULT_WORKSPACE.to_string(),
+                    name: "Default".to_string(),
+                    created_at: 0,
+                },
+            );
+        }
+        if !workspaces
+            .workspaces
+            .iter()
+            .any(|w| w.id == workspaces.active)
+        {
+            workspaces.active = DEFAULT_WORKSPACE.to_string();
+        }
+        let _ = ACTIVE.set(workspaces.active.clone());
+        Ok(Self {
+            path,
+            workspaces: Mutex::new(workspaces),
+        })
+    }
+}
+
+/// The id of the workspace this run of the app uses.
+pub fn active_id() -> &'static str {
+    ACTIVE.get().map_or(DEFAULT_WORKSPACE, String::as_str)
+}
+
+/// The folder inside `base`, the app's data or config folder, that `name` belongs in
+/// for the active workspace.
+pub fn dir(base: PathBuf, name: &str) -> PathBuf {
+    let id = active_id();
+    if id == DEFAULT_WORKSPACE || SHARED.contains(&name) {
+        base
+    } else {
+        base.join(WORKSPACES_DIR).join(id)
+    }
+}
+
+/// The workspace whose data `path` is, or `None` if it isn't any workspace's data.
+fn owner(app: &tauri::AppHandle, path: &Path) -> Option<String> {
+    let paths = app.path();
+    let roots = [paths.app_data_dir(), paths.app_config_dir()];
+    let rest = roots
+        .into_iter()
+        .flatten()
+        .map(|root| fs::canonicalize(&root).unwrap_or(root))
+        .find_map(|root| path.strip_prefix(root).ok().map(Path::to_path_buf))?;
+    let mut components = rest.components().filter_map(|c| match c {
+        Component::Normal(name) => name.to_str(),
+        _ => None,
+    });
+    match components.next()? {
+        WORKSPACES_DIR => components.next().map(str::to_string),
+        name if SHARED.contains(&name) => None,
+        _ => Some(DEFAULT_WORKSPACE.to_string()),
+    }
+}
+
+/// Whether `path`, resolved, lies among the data of a workspace other than the active one.
+pub fn is_foreign(app: &tauri::AppHandle, path: &Path) -> bool {
+    owner(app, path).is_some_and(|id| id != active_id())
+}
+
+/// Refuse a prompt that would send along a file from another workspace's data.
+pub fn guard_prompt(app: &tauri::AppHandle, messages: &[ChatMessage]) -> Result<(), AppError> {
+    for message in messages {
+        for image in &message.images {
+            let ImageSource::Path { path } = image else {
+                continue;
+            };
+            let resolved = fs::canonicalize(path).unwrap_or_else(|_| path.clone());
+            if is_foreign(app, &resolved) {
+                return Err(AppError::InvalidInput(format!(
+                    "{} belongs to another workspace",
+                    path.display()
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn save(store: &WorkspaceStore, workspaces: &Workspaces) -> Result<(), String> {
+    persist::save_json(&store.path, workspaces)
+}
+
+#[tauri::command]
+pub fn list_workspaces(store: State<'_, WorkspaceStore>) -> Workspaces {
+    store.workspaces.lock().unwrap().clone()
+}
+
+/// Add an empty workspace, which starts with default settings and no API keys.
+#[tauri::command]
+pub fn create_workspace(
+    app: tauri::AppHandle,
+    store: State<'_, WorkspaceStore>,
+    name: String,
+) -> Result<Workspace, AppError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(AppError::InvalidInput(
+            "Workspace name must not be empty".to_string(),
+        ));
+    }
+    let mut workspaces = store.workspaces.lock().unwrap();
+    if workspaces
+        .workspaces
+        .iter()
+        .any(|w| w.name.to_lowercase() == name.to_lowercase())
+    {
+        return Err(AppError::InvalidInput(format!(
+            "There is already a workspace named {name}"
+        )));
+    }
+    let workspace = Workspace {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: name.to_string(),
+        created_at: db::now_ms(),
+    };
+    workspaces.workspaces.push(workspace.clone());
+    save(&store, &workspaces)?;
+    let _ = app.emit("workspaces-changed", &*workspaces);
+    Ok(workspace)
+}
+
+/// Make `id` the active workspace and relaunch into it. Does nothing if it already is.
+#[tauri::command]
+pub fn switch_workspace(
+    app: tauri::AppHandle,
+    store: State<'_, WorkspaceStore>,
+    id: String,
+) -> Result<(), AppError> {
+    {
+        let mut workspaces = store.workspaces.lock().unwrap();
+        if !workspaces.workspaces.iter().any(|w| w.id == id) {
+            return Err(AppError::NotFound(format!("Workspace {id} not found")));
+        }
+        if id == active_id() {
+            return Ok(());
+        }
+        workspaces.active = id.clone();
+        save(&store, &workspaces)?;
+    }
+    tracing::info!("Switching to workspace {id}");
+    session::end(&app, SessionEnd::Restart);
+    app.restart()
+}
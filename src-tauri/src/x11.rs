@@ -0,0 +1,256 @@
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::{
+    Atom, AtomEnum, ClientMessageEvent, ConnectionExt, EventMask, MapState, Window,
+};
+use x11rb::rust_connection::RustConnection;
+
+use crate::screenshot::Region;
+
+pub fn is_wayland() -> bool {
+    std::env::var("XDG_SESSION_TYPE").is_ok_and(|session| session == "wayland")
+        || (std::env::var_os("WAYLAND_DISPLAY").is_some() && std::env::var_os("DISPLAY").is_none())
+}
+
+pub fn connect() -> Result<(RustConnection, Window), String> {
+    let (conn, screen) = x11rb::connect(None).map_err(|e| e.to_string())?;
+    let root = conn.setup().roots[screen].root;
+    Ok((conn, root))
+}
+
+/// Wayland doesn't let clients see other windows, and XWayland only knows about
+/// X11 clients, so window queries are X11-only.
+pub fn connect_for_windows() -> Result<(RustConnection, Window), String> {
+    if is_wayland() {
+        return Err("Inspecting other windows is not supported on Wayland".to_string());
+    }
+    connect()
+}
+
+pub fn foreground_window_bounds(conn: &RustConnection, root: Window) -> Result<Region, String> {
+    let window = foreground_window(conn, root)?;
+    let geometry = conn
+        .get_geometry(window)
+        .map_err(|e| e.to_string())?
+        .reply()
+        .map_err(|e| e.to_string())?;
+    let origin = conn
+        .translate_coordinates(window, root, 0, 0)
+        .map_err(|e| e.to_string())?
+        .reply()
+        .map_err(|e| e.to_string())?;
+    Ok(Region {
+        x: origin.dst_x.into(),
+        y: origin.dst_y.into(),
+        width: geometry.width.into(),
+        height: geometry.height.into(),
+    })
+}
+
+pub fn atom(conn: &RustConnection, name: &str) -> Result<Atom, String> {
+    Ok(conn
+        .intern_atom(true, name.as_bytes())
+        .map_err(|e| e.to_string())?
+        .reply()
+        .map_err(|e| e.to_string())?
+        .atom)
+}
+
+pub fn property_u32s(conn: &RustConnection, window: Window, property: Atom) -> Vec<u32> {
+    conn.get_property(false, window, property, AtomEnum::ANY, 0, u32::MAX)
+        .ok()
+        .and_then(|cookie| cookie.reply().ok())
+        .and_then(|reply| reply.value32().map(|values| values.collect()))
+        .unwrap_or_default()
+}
+
+pub fn property_string(conn: &RustConnection, window: Window, property: Atom) -> Option<Vec<u8>> {
+    conn.get_property(false, window, property, AtomEnum::ANY, 0, u32::MAX)
+        .ok()?
+        .reply()
+        .ok()
+        .map(|reply| reply.value)
+        .filter(|value| !value.is_empty())
+}
+
+pub fn window_pid(conn: &RustConnection, window: Window) -> Option<u32> {
+    let pid_atom = atom(conn, "_NET_WM_PID").ok()?;
+    property_u32s(conn, window, pid_atom).first().copied()
+}
+
+/// `_NET_WM_NAME`, falling back to the legacy `WM_NAME`.
+pub fn window_title(conn: &RustConnection, window: Window) -> Option<String> {
+    atom(conn, "_NET_WM_NAME")
+        .ok()
+        .and_then(|name| property_string(conn, window, name))
+        .or_else(|| property_string(conn, window, AtomEnum::WM_NAME.into()))
+        .map(|title| String::from_utf8_lossy(&title).into_owned())
+}
+
+/// The class half of `WM_CLASS` ("instance\0Class\0"), which names the application.
+pub fn window_class(conn: &RustConnection, window: Window) -> Option<String> {
+    let value = property_string(conn, window, AtomEnum::WM_CLASS.into())?;
+    let mut parts = value.split(|&b| b == 0).filter(|part| !part.is_empty());
+    let instance = parts.next()?;
+    let class = parts.next().unwrap_or(instance);
+    Some(String::from_utf8_lossy(class).into_owned())
+}
+
+/// The active window, or the topmost viewable one when the active window is ours.
+pub fn foreground_window(conn: &RustConnection, root: Window) -> Result<Window, String> {
+    let own_pid = std::process::id();
+    let pid_atom = atom(conn, "_NET_WM_PID")?;
+    let active = property_u32s(conn, root, atom(conn, "_NET_ACTIVE_WINDOW")?);
+    let stacking = property_u32s(conn, root, atom(conn, "_NET_CLIENT_LIST_STACKING")?);
+
+    let candidates = active.into_iter().chain(stacking.into_iter().rev());
+    for window in candidates.filter(|&w| w != 0) {
+        if property_u32s(conn, window, pid_atom).first() == Some(&own_pid) {
+            continue;
+        }
+        let viewable = conn
+            .get_window_attributes(window)
+            .ok()
+            .and_then(|cookie| cookie.reply().ok())
+            .is_some_and(|attrs| attrs.map_state == MapState::VIEWABLE);
+        if viewable {
+            return Ok(window);
+        }
+    }
+    Err("No foreground window found".to_string())
+}
+
+/// Whether the window manager shows `window` full screen.
+pub fn is_fullscreen(conn: &RustConnection, window: Window) -> bool {
+    let (Ok(state), Ok(fullscreen)) = (
+        atom(conn, "_NET_WM_STATE"),
+        atom(conn, "_NET_WM_STATE_FULLSCREEN"),
+    ) else {
+        return false;
+    };
+    fullscreen != x11rb::NONE && property_u32s(conn, window, state).contains(&fullscreen)
+}
+
+/// Ask the window manager to raise and focus `window`, as a pager would.
+pub fn activate(conn: &RustConnection, root: Window, window: Window) -> Result<(), String> {
+    // Source indication 2 means "from a pager", which window managers honour over the
+    // focus-stealing prevention they apply to ordinary applications.
+    send_root_message(
+        conn,
+        root,
+        window,
+        "_NET_ACTIVE_WINDOW",
+        [2, x11rb::CURRENT_TIME, 0, 0, 0],
+    )
+}
+
+/// Send the root window a client message about `window`, as a pager would.
+fn send_root_message(
+    conn: &RustConnection,
+    root: Window,
+    window: Window,
+    message: &str,
+    data: [u32; 5],
+) -> Result<(), String> {
+    let event = ClientMessageEvent::new(32, window, atom(conn, message)?, data);
+    conn.send_event(
+        false,
+        root,
+        EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY,
+        event,
+    )
+    .map_err(|e| e.to_string())?;
+    conn.flush().map_err(|e| e.to_string())
+}
+
+/// The space the window manager's decorations take around `window`, as left, right, top
+/// and bottom.
+fn frame_extents(conn: &RustConnection, window: Window) -> [u32; 4] {
+    let extents = atom(conn, "_NET_FRAME_EXTENTS")
+        .map(|extents| property_u32s(conn, window, extents))
+        .unwrap_or_default();
+    match extents.as_slice() {
+        [left, right, top, bottom, ..] => [*left, *right, *top, *bottom],
+        _ => [0; 4],
+    }
+}
+
+/// Where `window` is on screen with its decorations, and whether it is maximized.
+pub fn window_frame(
+    conn: &RustConnection,
+    root: Window,
+    window: Window,
+) -> Result<(Region, bool), String> {
+    let geometry = conn
+        .get_geometry(window)
+        .map_err(|e| e.to_string())?
+        .reply()
+        .map_err(|e| e.to_string())?;
+    let origin = conn
+        .translate_coordinates(window, root, 0, 0)
+        .map_err(|e| e.to_string())?
+        .reply()
+        .map_err(|e| e.to_string())?;
+    let [left, right, top, bottom] = frame_extents(conn, window);
+    let frame = Region {
+        x: i32::from(origin.dst_x) - left as i32,
+        y: i32::from(origin.dst_y) - top as i32,
+        width: u32::from(geometry.width) + left + right,
+        height: u32::from(geometry.height) + top + bottom,
+    };
+    let maximized = match (
+        atom(conn, "_NET_WM_STATE"),
+        atom(conn, "_NET_WM_STATE_MAXIMIZED_HORZ"),
+    ) {
+        (Ok(state), Ok(horizontal)) => {
+            horizontal != x11rb::NONE && property_u32s(conn, window, state).contains(&horizontal)
+        }
+        _ => false,
+    };
+    Ok((frame, maximized))
+}
+
+/// Maximize `window`, or put it back to its normal size.
+pub fn set_maximized(
+    conn: &RustConnection,
+    root: Window,
+    window: Window,
+    maximized: bool,
+) -> Result<(), String> {
+    let horizontal = atom(conn, "_NET_WM_STATE_MAXIMIZED_HORZ")?;
+    let vertical = atom(conn, "_NET_WM_STATE_MAXIMIZED_VERT")?;
+    // 1 adds the states and 0 removes them; the last field marks a pager again.
+    let action = u32::from(maximized);
+    send_root_message(
+        conn,
+        root,
+        window,
+        "_NET_WM_STATE",
+        [action, horizontal, vertical, 2, 0],
+    )
+}
+
+/// Ask the window manager to move `window` so its decorated frame fills `frame`.
+pub fn move_resize(
+    conn: &RustConnection,
+    root: Window,
+    window: Window,
+    frame: Region,
+) -> Result<(), String> {
+    let [left, right, top, bottom] = frame_extents(conn, window);
+    // North-west gravity places the frame rather than the client at x and y, the next
+    // four bits say all of x, y, width and height are given, and source 2 is a pager.
+    let flags = 1 | 0xF << 8 | 2 << 12;
+    send_root_message(
+        conn,
+        root,
+        window,
+        "_NET_MOVERESIZE_WINDOW",
+        [
+            flags,
+            frame.x as u32,
+            frame.y as u32,
+            frame.width.saturating_sub(left + right).max(1),
+            frame.height.saturating_sub(top + bottom).max(1),
+        ],
+    )
+}